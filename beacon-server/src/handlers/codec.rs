@@ -0,0 +1,66 @@
+//! Wire encodings for signaling frames.
+//!
+//! JSON text frames are the default. A client can ask for MessagePack binary frames by
+//! offering the `cordia.msgpack` WebSocket subprotocol; the beacon then answers in binary.
+//! Compression is the standard permessage-deflate extension (see `deflate`), below the
+//! encoding, so it applies to either.
+//!
+//! Any encoding is accepted inbound on any connection. Handlers always produce JSON, and
+//! the connection's send task transcodes it, so nothing else needs to know the encoding.
+
+use serde_json::Value;
+
+use crate::SignalingMessage;
+
+pub const MSGPACK_SUBPROTOCOL: &str = "cordia.msgpack";
+pub const JSON_SUBPROTOCOL: &str = "cordia.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    MessagePack,
+}
+
+impl Encoding {
+    /// The encoding for the subprotocol picked during the upgrade (none = JSON).
+    pub fn from_subprotocol(protocol: Option<&str>) -> Self {
+        match protocol {
+            Some(MSGPACK_SUBPROTOCOL) => Encoding::MessagePack,
+            _ => Encoding::Json,
+        }
+    }
+}
+
+/// Decode a MessagePack frame. Goes through a JSON value so every encoding parses
+/// identically.
+pub fn decode_binary(bytes: &[u8]) -> Result<SignalingMessage, String> {
+    let value: Value = rmp_serde::from_slice(bytes).map_err(|e| e.to_string())?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+/// Transcode an outgoing JSON message to a MessagePack frame (maps keep their field names).
+pub fn encode_binary(json: &str) -> Result<Vec<u8>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    rmp_serde::to_vec_named(&value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msgpack_round_trips_tagged_messages() {
+        let json = r#"{"type":"IceCandidate","from_peer":"a","to_peer":"b","candidate":"candidate:1 1 udp 2122260223 10.0.0.2 54321 typ host"}"#;
+        let bytes = encode_binary(json).unwrap();
+        assert!(bytes.len() < json.len());
+        match decode_binary(&bytes).unwrap() {
+            SignalingMessage::IceCandidate { from_peer, to_peer, .. } => {
+                assert_eq!((from_peer.as_str(), to_peer.as_str()), ("a", "b"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert_eq!(Encoding::from_subprotocol(Some(MSGPACK_SUBPROTOCOL)), Encoding::MessagePack);
+        assert_eq!(Encoding::from_subprotocol(None), Encoding::Json);
+    }
+}
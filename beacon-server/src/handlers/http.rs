@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
@@ -12,8 +12,10 @@ use sysinfo::{get_current_pid, Networks, System};
 
 use crate::{
     decode_path_segment,
+    security::{ClientIp, MessageClass},
     state::AppState,
-    AckRequest, EncryptedServerHint, InviteTokenCreateRequest, ServerEvent,
+    state::events::{is_stale_server_hint, verify_member_leave, verify_server_hint_signature},
+    AckRequest, EncryptedServerHint, InviteTokenCreateRequest, MemberLeaveRequest, ServerEvent,
 };
 
 type SharedState = Arc<AppState>;
@@ -61,6 +63,7 @@ pub async fn get_status(State(state): State<SharedState>) -> impl IntoResponse {
         *prev_guard = Some((cur_rx, cur_tx, now));
         (rx_bps, tx_bps)
     };
+    let route_locks = state.routes.contention();
     let json = serde_json::json!({
         "connections": connections,
         "uptime_secs": uptime_secs,
@@ -69,11 +72,26 @@ pub async fn get_status(State(state): State<SharedState>) -> impl IntoResponse {
         "memory_bytes": memory_bytes,
         "cpu_percent": cpu_percent,
         "rx_bps": rx_bps,
-        "tx_bps": tx_bps
+        "tx_bps": tx_bps,
+        "route_lock_acquisitions": route_locks.acquisitions,
+        "route_lock_contended": route_locks.contended
     });
     Json(json)
 }
 
+/// Version, protocol range, enabled features and limits, so clients can gate features per
+/// beacon. The same capabilities go out in `Welcome` on the WebSocket.
+pub async fn get_info(State(state): State<SharedState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol_version": crate::PROTOCOL_VERSION,
+        "min_protocol_version": state.security.min_protocol_version,
+        "features": state.features().await,
+        "limits": state.limits(),
+    }))
+}
+
 // ---------- Invites ----------
 
 pub async fn get_invite(
@@ -177,10 +195,24 @@ pub async fn revoke_invite(
 
 pub async fn register_server_hint(
     State(state): State<SharedState>,
+    Extension(ClientIp(client_ip)): Extension<ClientIp>,
     Path(signing_pubkey): Path<String>,
     Json(hint): Json<EncryptedServerHint>,
 ) -> impl IntoResponse {
+    if let Err(msg) = state.type_rate_limiters.check(MessageClass::ServerHint, &client_ip) {
+        return (StatusCode::TOO_MANY_REQUESTS, msg).into_response();
+    }
     let signing_pubkey = decode_path_segment(&signing_pubkey);
+    if hint.signing_pubkey != signing_pubkey {
+        return (StatusCode::BAD_REQUEST, "signing_pubkey does not match path").into_response();
+    }
+    if let Err(msg) = verify_server_hint_signature(&hint) {
+        return (StatusCode::UNAUTHORIZED, msg).into_response();
+    }
+    let previous = stored_server_hint(&state, &signing_pubkey).await;
+    if is_stale_server_hint(&hint, previous.as_ref()) {
+        return (StatusCode::CONFLICT, "Server hint is not newer than the stored one").into_response();
+    }
 
     #[cfg(feature = "postgres")]
     {
@@ -202,20 +234,13 @@ pub async fn register_server_hint(
         let mut events = state.events.write().await;
         events.register_server_hint(signing_pubkey.to_string(), hint.clone());
     }
-    {
-        let signaling = state.signaling.read().await;
-        signaling.broadcast_server_hint_updated(&signing_pubkey, &hint);
-    }
+    state.routes.broadcast_server_hint_updated(&signing_pubkey, &hint);
     info!("Registered server hint");
-    (StatusCode::OK, Json(serde_json::json!({"status": "ok"})))
+    (StatusCode::OK, Json(serde_json::json!({"status": "ok"}))).into_response()
 }
 
-pub async fn get_server_hint(
-    State(state): State<SharedState>,
-    Path(signing_pubkey): Path<String>,
-) -> impl IntoResponse {
-    let signing_pubkey = decode_path_segment(&signing_pubkey);
-
+/// The current hint for a server, from the DB if configured, else from memory.
+async fn stored_server_hint(state: &SharedState, signing_pubkey: &str) -> Option<EncryptedServerHint> {
     #[cfg(feature = "postgres")]
     {
         let db = {
@@ -223,16 +248,22 @@ pub async fn get_server_hint(
             backends.db.clone()
         };
         if let Some(pool) = db {
-            if let Ok(Some(hint)) = get_server_hint_db(&pool, &signing_pubkey).await {
-                return (StatusCode::OK, Json(serde_json::to_value(&hint).unwrap())).into_response();
-            }
-            return (StatusCode::NOT_FOUND, "Server hint not found").into_response();
+            return get_server_hint_db(&pool, signing_pubkey).await.ok().flatten();
         }
     }
 
     let events = state.events.read().await;
-    match events.get_server_hint(&signing_pubkey) {
-        Some(hint) => (StatusCode::OK, Json(serde_json::to_value(hint).unwrap())).into_response(),
+    events.get_server_hint(signing_pubkey).cloned()
+}
+
+pub async fn get_server_hint(
+    State(state): State<SharedState>,
+    Path(signing_pubkey): Path<String>,
+) -> impl IntoResponse {
+    let signing_pubkey = decode_path_segment(&signing_pubkey);
+
+    match stored_server_hint(&state, &signing_pubkey).await {
+        Some(hint) => (StatusCode::OK, Json(serde_json::to_value(&hint).unwrap())).into_response(),
         None => (StatusCode::NOT_FOUND, "Server hint not found").into_response(),
     }
 }
@@ -332,6 +363,54 @@ pub async fn post_event(
     (StatusCode::CREATED, Json(serde_json::json!({"status": "created"})))
 }
 
+/// A member leaving on their own. Members can't sign hints, so the verified leave is kept as a
+/// MemberLeave event (payload: the member's public key, timestamp: left_at) for the owner to
+/// check again and apply, and subscribers are told right away.
+pub async fn post_member_leave(
+    State(state): State<SharedState>,
+    Extension(ClientIp(client_ip)): Extension<ClientIp>,
+    Path(signing_pubkey): Path<String>,
+    Json(leave): Json<MemberLeaveRequest>,
+) -> impl IntoResponse {
+    if let Err(msg) = state.type_rate_limiters.check(MessageClass::ServerHint, &client_ip) {
+        return (StatusCode::TOO_MANY_REQUESTS, msg).into_response();
+    }
+    let signing_pubkey = decode_path_segment(&signing_pubkey);
+    let user_id = match verify_member_leave(&signing_pubkey, &leave) {
+        Ok(user_id) => user_id,
+        Err(msg) => return (StatusCode::UNAUTHORIZED, msg).into_response(),
+    };
+    let event = ServerEvent {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        signing_pubkey: signing_pubkey.clone(),
+        event_type: "MemberLeave".to_string(),
+        encrypted_payload: leave.public_key.to_lowercase(),
+        signature: leave.signature,
+        timestamp: leave.left_at,
+    };
+
+    #[cfg(feature = "postgres")]
+    {
+        let db = {
+            let backends = state.backends.read().await;
+            backends.db.clone()
+        };
+        if let Some(pool) = db {
+            if let Err(e) = insert_event_db(&pool, &event).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+            }
+            state.routes.broadcast_server_member_left(&signing_pubkey, &user_id);
+            info!("Member {} left server (db)", user_id);
+            return (StatusCode::CREATED, Json(serde_json::json!({"status": "created"}))).into_response();
+        }
+    }
+
+    state.events.write().await.post_event(signing_pubkey.clone(), event);
+    state.routes.broadcast_server_member_left(&signing_pubkey, &user_id);
+    info!("Member {} left server", user_id);
+    (StatusCode::CREATED, Json(serde_json::json!({"status": "created"}))).into_response()
+}
+
 pub async fn ack_events(
     State(state): State<SharedState>,
     Path(signing_pubkey): Path<String>,
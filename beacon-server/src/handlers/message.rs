@@ -1,12 +1,13 @@
 use std::sync::Arc;
 use log::{info, warn};
 use crate::{
-    SignalingMessage, ConnId, ServerId, SigningPubkey, WebSocketSender,
+    security::MessageClass,
+    SignalingMessage, ConnId, PeerId, ServerId, SigningPubkey, WebSocketSender,
     ProfileRecord, ProfileSnapshotRecord,
     FriendRequestIncomingItem, CodeRedemptionItem,
     state::AppState,
     state::presence::PresenceUserStatus,
-    state::signaling::{FRIENDS_PEER_PREFIX, FRIENDS_SIGNING_PUBKEY},
+    state::signaling::FRIENDS_SIGNING_PUBKEY,
 };
 
 type SharedState = Arc<AppState>;
@@ -14,22 +15,192 @@ type SharedState = Arc<AppState>;
 #[cfg(feature = "postgres")]
 use crate::handlers::db::{upsert_profile_db, load_profiles_db};
 #[cfg(feature = "redis-backend")]
-use crate::handlers::redis::{redis_presence_hello, redis_presence_active, redis_presence_snapshot};
+use crate::handlers::redis::{redis_presence_hello, redis_presence_active, redis_presence_snapshot, redis_relay_claim, redis_relay_publish, RelayVoiceRoom};
+
+/// Client message types handled below, advertised in `Welcome`.
+const CLIENT_MESSAGE_TYPES: &[&str] = &[
+    "Hello", "Authenticate", "Register", "PresenceHello", "PresenceActive", "ProfileAnnounce",
+    "ProfileHello", "ProfilePush", "EphemeralChatSend", "EphemeralReceiptSend", "Offer", "Answer",
+    "IceCandidate", "VoiceRegister", "VoiceUnregister", "VoiceOffer", "VoiceAnswer",
+    "VoiceIceCandidate", "Ping", "FriendMutualCheck", "FriendMutualCheckReply",
+    "AttachmentTransferRequest", "AttachmentTransferResponse", "AttachmentTransferSignal",
+    "SwarmAnnounce", "SwarmUnannounce", "SwarmPeerListRequest", "SwarmHealthUpdate",
+];
+
+/// Which per-type quota a client message counts against; None for keepalive and auth.
+fn message_class(msg: &SignalingMessage) -> Option<MessageClass> {
+    use SignalingMessage::*;
+    match msg {
+        Offer { .. } | Answer { .. } | IceCandidate { .. }
+        | VoiceOffer { .. } | VoiceAnswer { .. } | VoiceIceCandidate { .. } => Some(MessageClass::Signaling),
+        Register { .. } | VoiceRegister { .. } | VoiceUnregister { .. }
+        | PresenceHello { .. } | PresenceActive { .. } => Some(MessageClass::Presence),
+        EphemeralChatSend { .. } | EphemeralReceiptSend { .. } => Some(MessageClass::Chat),
+        AttachmentTransferRequest { .. } | AttachmentTransferResponse { .. } | AttachmentTransferSignal { .. }
+        | SwarmAnnounce { .. } | SwarmUnannounce { .. } | SwarmPeerListRequest { .. }
+        | SwarmHealthUpdate { .. } => Some(MessageClass::Transfer),
+        ProfileAnnounce { .. } | ProfileHello { .. } | ProfilePush { .. } => Some(MessageClass::Profile),
+        FriendMutualCheck { .. } | FriendMutualCheckReply { .. } => Some(MessageClass::Friends),
+        _ => None,
+    }
+}
+
+/// Refuses a `user_id` claim the connection hasn't proved by answering its AuthChallenge.
+async fn require_authenticated_user(state: &SharedState, conn_id: &ConnId, user_id: &str) -> Result<(), String> {
+    if state.signaling.read().await.is_authenticated_user(conn_id, user_id) {
+        Ok(())
+    } else {
+        Err(format!("Not authenticated as user {}", user_id))
+    }
+}
+
+/// Record on the Redis relay that this instance holds `peer_id` (no-op without Redis).
+async fn claim_relay_peer(state: &SharedState, peer_id: &PeerId) {
+    #[cfg(feature = "redis-backend")]
+    {
+        let (conn, ttl, instance_id) = {
+            let backends = state.backends.read().await;
+            (backends.redis_conn.clone(), backends.redis_presence_ttl_secs, backends.relay_instance_id.clone())
+        };
+        if let Some(mut conn) = conn {
+            if let Err(e) = redis_relay_claim(&mut conn, ttl, &instance_id, std::slice::from_ref(peer_id)).await {
+                warn!("Redis relay claim failed: {}", e);
+            }
+        }
+    }
+    #[cfg(not(feature = "redis-backend"))]
+    let _ = (state, peer_id);
+}
+
+/// Forward `msg` through the Redis relay to a peer connected to another instance.
+/// Returns false when there is no relay or no instance holds the peer.
+async fn relay_to_remote_peer(state: &SharedState, to_peer: &str, msg: &SignalingMessage) -> bool {
+    relay_message(state, to_peer, msg, None).await
+}
+
+/// Relay a voice signaling message, only when `from_peer` is in the `chat_id` voice room
+/// here. The receiving instance delivers only if `to_peer` is in the same room.
+async fn relay_voice_to_remote_peer(
+    state: &SharedState,
+    from_peer: &str,
+    to_peer: &str,
+    chat_id: &str,
+    msg: &SignalingMessage,
+) -> bool {
+    let Some(signing_pubkey) = state.voice.read().await.room_key_of(from_peer, chat_id) else {
+        return false;
+    };
+    relay_message(state, to_peer, msg, Some((signing_pubkey, chat_id.to_string()))).await
+}
+
+/// Publish `msg` for `to_peer` on the Redis relay, tagged with its voice room if any.
+async fn relay_message(
+    state: &SharedState,
+    to_peer: &str,
+    msg: &SignalingMessage,
+    voice_room: Option<(SigningPubkey, String)>,
+) -> bool {
+    #[cfg(feature = "redis-backend")]
+    {
+        let (conn, instance_id) = {
+            let backends = state.backends.read().await;
+            (backends.redis_conn.clone(), backends.relay_instance_id.clone())
+        };
+        let Some(mut conn) = conn else {
+            return false;
+        };
+        let Ok(json) = serde_json::to_string(msg) else {
+            return false;
+        };
+        let voice_room = voice_room.map(|(signing_pubkey, chat_id)| RelayVoiceRoom { signing_pubkey, chat_id });
+        match redis_relay_publish(&mut conn, &instance_id, to_peer, json, voice_room).await {
+            Ok(relayed) => relayed,
+            Err(e) => {
+                warn!("Redis relay publish failed: {}", e);
+                false
+            }
+        }
+    }
+    #[cfg(not(feature = "redis-backend"))]
+    {
+        let _ = (state, to_peer, msg, voice_room);
+        false
+    }
+}
 
 pub async fn handle_message(
     msg: SignalingMessage,
     conn_id: &ConnId,
+    client_ip: &str,
     state: &SharedState,
     sender: &WebSocketSender,
 ) -> Result<(), String> {
+    if let Some(class) = message_class(&msg) {
+        state.type_rate_limiters.check(class, client_ip)?;
+    }
+
+    // Clients that never say Hello speak protocol 1; turn them away once that is too old.
+    let min_protocol = state.security.min_protocol_version;
+    if min_protocol > 1
+        && !matches!(msg, SignalingMessage::Hello { .. } | SignalingMessage::Ping)
+        && state.signaling.read().await.protocol_for(conn_id).is_none()
+    {
+        return Err(format!(
+            "This beacon requires signaling protocol {} or newer; update Cordia",
+            min_protocol
+        ));
+    }
+
     match msg {
+        SignalingMessage::Hello { protocol_versions, client_version } => {
+            let negotiated = state.signaling.write().await.negotiate_protocol(
+                conn_id,
+                &protocol_versions,
+                min_protocol,
+                crate::PROTOCOL_VERSION,
+            );
+            info!(
+                "Connection {} hello: client {} offers {:?}, using {:?}",
+                conn_id,
+                client_version.as_deref().unwrap_or("unknown"),
+                protocol_versions,
+                negotiated
+            );
+
+            // Without overlap, answer with our own version; the client reports the mismatch.
+            let response = SignalingMessage::Welcome {
+                protocol_version: negotiated.unwrap_or(crate::PROTOCOL_VERSION),
+                min_protocol_version: min_protocol,
+                server_version: env!("CARGO_PKG_VERSION").to_string(),
+                accepts: CLIENT_MESSAGE_TYPES.iter().map(|t| t.to_string()).collect(),
+                features: state.features().await.into_iter().map(str::to_string).collect(),
+                limits: state.limits(),
+            };
+            let json = serde_json::to_string(&response)
+                .map_err(|e| format!("Failed to serialize response: {}", e))?;
+            sender
+                .send(tokio_tungstenite::tungstenite::Message::Text(json))
+                .map_err(|e| format!("Failed to send response: {}", e))?;
+            Ok(())
+        }
+        SignalingMessage::Authenticate { public_key, signature } => {
+            let user_id = state.signaling.write().await.authenticate(conn_id, &public_key, &signature)?;
+            info!("Connection {} authenticated as user {}", conn_id, user_id);
+
+            let response = SignalingMessage::Authenticated { public_key, user_id };
+            let json = serde_json::to_string(&response)
+                .map_err(|e| format!("Failed to serialize response: {}", e))?;
+            sender
+                .send(tokio_tungstenite::tungstenite::Message::Text(json))
+                .map_err(|e| format!("Failed to send response: {}", e))?;
+            Ok(())
+        }
         SignalingMessage::Register { server_id, peer_id, signing_pubkey } => {
             let mut signaling = state.signaling.write().await;
-            let peers = signaling.register_peer(peer_id.clone(), server_id.clone(), signing_pubkey, conn_id.clone());
-
-            // Store the sender for this peer
-            signaling.peer_senders.insert(peer_id.clone(), sender.clone());
+            signaling.check_register(conn_id, &peer_id)?;
+            let peers = signaling.register_peer(peer_id.clone(), server_id.clone(), signing_pubkey, conn_id.clone(), sender.clone());
             drop(signaling);
+            claim_relay_peer(state, &peer_id).await;
 
             info!("Registered peer {} in server {}", peer_id, server_id);
 
@@ -48,6 +219,8 @@ pub async fn handle_message(
             Ok(())
         }
         SignalingMessage::PresenceHello { user_id, signing_pubkeys, active_signing_pubkey, friend_user_ids } => {
+            require_authenticated_user(state, conn_id, &user_id).await?;
+
             let (affected_spks, redis_client, redis_ttl, local_snaps) = {
                 let mut presence = state.presence.write().await;
                 // Upsert presence
@@ -147,26 +320,8 @@ pub async fn handle_message(
                         .collect()
                 };
                 let friend_ids_set: std::collections::HashSet<String> = friend_user_ids.iter().cloned().collect();
-                let mut signaling = state.signaling.write().await;
-                let friends_peer_id = format!("{}{}", FRIENDS_PEER_PREFIX, conn_id);
-                // If this conn already had a friend subscription, remove it before re-adding (e.g. friends list changed)
-                if let Some(old_ids) = signaling.conn_friend_ids.remove(conn_id) {
-                    for uid in &old_ids {
-                        if let Some(subs) = signaling.friend_presence_subscribers.get_mut(uid) {
-                            subs.remove(&friends_peer_id);
-                            if subs.is_empty() {
-                                signaling.friend_presence_subscribers.remove(uid);
-                            }
-                        }
-                    }
-                }
-                signaling.peer_senders.insert(friends_peer_id.clone(), sender.clone());
-                signaling.conn_peers.entry(conn_id.clone()).or_default().insert(friends_peer_id.clone());
-                signaling.conn_friend_ids.insert(conn_id.clone(), friend_ids_set.clone());
-                for uid in &friend_ids_set {
-                    signaling.friend_presence_subscribers.entry(uid.clone()).or_default().insert(friends_peer_id.clone());
-                }
-                drop(signaling);
+                // Replaces this connection's earlier subscription (e.g. friends list changed)
+                state.signaling.write().await.subscribe_friends(conn_id, friend_ids_set, sender.clone());
                 let snap = SignalingMessage::PresenceSnapshot {
                     signing_pubkey: FRIENDS_SIGNING_PUBKEY.to_string(),
                     users: friend_snap,
@@ -235,6 +390,8 @@ pub async fn handle_message(
             Ok(())
         }
         SignalingMessage::PresenceActive { user_id, active_signing_pubkey } => {
+            require_authenticated_user(state, conn_id, &user_id).await?;
+
             let (spks, redis_client, redis_ttl) = {
                 let mut presence = state.presence.write().await;
                 let spks = presence.update_presence_active(&user_id, active_signing_pubkey.clone());
@@ -273,6 +430,8 @@ pub async fn handle_message(
             Ok(())
         }
         SignalingMessage::ProfileAnnounce { user_id, display_name, real_name, show_real_name, rev, signing_pubkeys } => {
+            require_authenticated_user(state, conn_id, &user_id).await?;
+
             let (rec_opt, db_opt) = {
                 let mut profiles = state.profiles.write().await;
                 let update = match profiles.profiles.get(&user_id) {
@@ -396,8 +555,7 @@ pub async fn handle_message(
                 sent_at: chrono::Utc::now().to_rfc3339(),
             };
 
-            let signaling = state.signaling.read().await;
-            signaling.broadcast_ephemeral_chat_message(&signing_pubkey, &outgoing, Some(conn_id));
+            state.routes.broadcast_ephemeral_chat_message(&signing_pubkey, &outgoing, Some(conn_id));
             Ok(())
         }
         SignalingMessage::EphemeralReceiptSend { signing_pubkey, chat_id, message_id, receipt_type } => {
@@ -424,21 +582,25 @@ pub async fn handle_message(
                 sent_at: chrono::Utc::now().to_rfc3339(),
             };
 
-            let signaling = state.signaling.read().await;
-            signaling.broadcast_ephemeral_chat_message(&signing_pubkey, &outgoing, Some(conn_id));
+            state.routes.broadcast_ephemeral_chat_message(&signing_pubkey, &outgoing, Some(conn_id));
             Ok(())
         }
         SignalingMessage::Offer { from_peer, to_peer, sdp } => {
             info!("Forwarding offer from {} to {}", from_peer, to_peer);
 
-            let target_sender = state.signaling.read().await.validate_and_get_target_sender(&from_peer, conn_id, &to_peer);
+            let target_sender = state.routes.validate_and_get_target_sender(&from_peer, conn_id, &to_peer);
             match target_sender {
                 Ok(Some(sender)) => {
                     let forward_msg = SignalingMessage::Offer { from_peer, to_peer, sdp };
                     let json = serde_json::to_string(&forward_msg).map_err(|e| format!("Failed to serialize offer: {}", e))?;
                     sender.send(tokio_tungstenite::tungstenite::Message::Text(json)).map_err(|e| format!("Failed to forward offer: {}", e))?;
                 }
-                Ok(None) => warn!("Target peer {} not found for offer", to_peer),
+                Ok(None) => {
+                    let forward_msg = SignalingMessage::Offer { from_peer, to_peer: to_peer.clone(), sdp };
+                    if !relay_to_remote_peer(state, &to_peer, &forward_msg).await {
+                        warn!("Target peer {} not found for offer", to_peer);
+                    }
+                }
                 Err(()) => return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id)),
             }
             Ok(())
@@ -446,14 +608,19 @@ pub async fn handle_message(
         SignalingMessage::Answer { from_peer, to_peer, sdp } => {
             info!("Forwarding answer from {} to {}", from_peer, to_peer);
 
-            let target_sender = state.signaling.read().await.validate_and_get_target_sender(&from_peer, conn_id, &to_peer);
+            let target_sender = state.routes.validate_and_get_target_sender(&from_peer, conn_id, &to_peer);
             match target_sender {
                 Ok(Some(sender)) => {
                     let forward_msg = SignalingMessage::Answer { from_peer, to_peer, sdp };
                     let json = serde_json::to_string(&forward_msg).map_err(|e| format!("Failed to serialize answer: {}", e))?;
                     sender.send(tokio_tungstenite::tungstenite::Message::Text(json)).map_err(|e| format!("Failed to forward answer: {}", e))?;
                 }
-                Ok(None) => warn!("Target peer {} not found for answer", to_peer),
+                Ok(None) => {
+                    let forward_msg = SignalingMessage::Answer { from_peer, to_peer: to_peer.clone(), sdp };
+                    if !relay_to_remote_peer(state, &to_peer, &forward_msg).await {
+                        warn!("Target peer {} not found for answer", to_peer);
+                    }
+                }
                 Err(()) => return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id)),
             }
             Ok(())
@@ -461,14 +628,19 @@ pub async fn handle_message(
         SignalingMessage::IceCandidate { from_peer, to_peer, candidate } => {
             info!("Forwarding ICE candidate from {} to {}", from_peer, to_peer);
 
-            let target_sender = state.signaling.read().await.validate_and_get_target_sender(&from_peer, conn_id, &to_peer);
+            let target_sender = state.routes.validate_and_get_target_sender(&from_peer, conn_id, &to_peer);
             match target_sender {
                 Ok(Some(sender)) => {
                     let forward_msg = SignalingMessage::IceCandidate { from_peer, to_peer, candidate };
                     let json = serde_json::to_string(&forward_msg).map_err(|e| format!("Failed to serialize ICE candidate: {}", e))?;
                     sender.send(tokio_tungstenite::tungstenite::Message::Text(json)).map_err(|e| format!("Failed to forward ICE candidate: {}", e))?;
                 }
-                Ok(None) => warn!("Target peer {} not found for ICE candidate", to_peer),
+                Ok(None) => {
+                    let forward_msg = SignalingMessage::IceCandidate { from_peer, to_peer: to_peer.clone(), candidate };
+                    if !relay_to_remote_peer(state, &to_peer, &forward_msg).await {
+                        warn!("Target peer {} not found for ICE candidate", to_peer);
+                    }
+                }
                 Err(()) => return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id)),
             }
             Ok(())
@@ -479,20 +651,18 @@ pub async fn handle_message(
         SignalingMessage::VoiceRegister { server_id, chat_id, peer_id, user_id, signing_pubkey } => {
             info!("Voice register: peer={} user={} server={} chat={}", peer_id, user_id, server_id, chat_id);
 
-            let peers = {
-                let mut signaling = state.signaling.write().await;
+            require_authenticated_user(state, conn_id, &user_id).await?;
 
+            {
+                let mut signaling = state.signaling.write().await;
                 // Register peer if not already registered (allows voice-first registration)
-                if !signaling.peers.contains_key(&peer_id) {
-                    signaling.register_peer(peer_id.clone(), server_id.clone(), Some(signing_pubkey.clone()), conn_id.clone());
-                } else {
-                    if !signaling.validate_peer_connection(&peer_id, conn_id) {
-                        return Err(format!("Invalid peer_id {} for connection {}", peer_id, conn_id));
-                    }
+                if !signaling.routes.peers.contains_key(&peer_id) {
+                    signaling.register_peer(peer_id.clone(), server_id.clone(), Some(signing_pubkey.clone()), conn_id.clone(), sender.clone());
+                } else if !signaling.routes.validate_peer_connection(&peer_id, conn_id) {
+                    return Err(format!("Invalid peer_id {} for connection {}", peer_id, conn_id));
                 }
-
-                signaling.peer_senders.insert(peer_id.clone(), sender.clone());
-            };
+            }
+            claim_relay_peer(state, &peer_id).await;
 
             {
                 let mut voice = state.voice.write().await;
@@ -538,7 +708,7 @@ pub async fn handle_message(
 
             {
                 let signaling = state.signaling.read().await;
-                if !signaling.validate_peer_connection(&peer_id, conn_id) {
+                if !signaling.routes.validate_peer_connection(&peer_id, conn_id) {
                     return Err(format!("Invalid peer_id {} for connection {}", peer_id, conn_id));
                 }
             }
@@ -585,12 +755,10 @@ pub async fn handle_message(
         SignalingMessage::VoiceOffer { from_peer, from_user, to_peer, chat_id, sdp } => {
             info!("Voice offer from {} to {} in chat {}", from_peer, to_peer, chat_id);
 
-            {
-                let signaling = state.signaling.read().await;
-                if !signaling.validate_peer_connection(&from_peer, conn_id) {
-                    return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id));
-                }
+            if !state.routes.validate_peer_connection(&from_peer, conn_id) {
+                return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id));
             }
+            require_authenticated_user(state, conn_id, &from_user).await?;
 
             let target_sender = {
                 let voice = state.voice.read().await;
@@ -609,20 +777,20 @@ pub async fn handle_message(
                 }
             };
 
+            let forward_msg = SignalingMessage::VoiceOffer {
+                from_peer: from_peer.clone(),
+                from_user,
+                to_peer: to_peer.clone(),
+                chat_id: chat_id.clone(),
+                sdp,
+            };
             if let Some(target) = target_sender {
-                let forward_msg = SignalingMessage::VoiceOffer {
-                    from_peer,
-                    from_user,
-                    to_peer,
-                    chat_id,
-                    sdp,
-                };
                 let json = serde_json::to_string(&forward_msg)
                     .map_err(|e| format!("Failed to serialize VoiceOffer: {}", e))?;
                 target
                     .send(tokio_tungstenite::tungstenite::Message::Text(json))
                     .map_err(|e| format!("Failed to forward VoiceOffer: {}", e))?;
-            } else {
+            } else if !relay_voice_to_remote_peer(state, &from_peer, &to_peer, &chat_id, &forward_msg).await {
                 warn!("Target peer {} not found in chat {} for VoiceOffer", to_peer, chat_id);
             }
 
@@ -632,12 +800,10 @@ pub async fn handle_message(
         SignalingMessage::VoiceAnswer { from_peer, from_user, to_peer, chat_id, sdp } => {
             info!("Voice answer from {} to {} in chat {}", from_peer, to_peer, chat_id);
 
-            {
-                let signaling = state.signaling.read().await;
-                if !signaling.validate_peer_connection(&from_peer, conn_id) {
-                    return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id));
-                }
+            if !state.routes.validate_peer_connection(&from_peer, conn_id) {
+                return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id));
             }
+            require_authenticated_user(state, conn_id, &from_user).await?;
 
             let target_sender = {
                 let voice = state.voice.read().await;
@@ -656,20 +822,20 @@ pub async fn handle_message(
                 }
             };
 
+            let forward_msg = SignalingMessage::VoiceAnswer {
+                from_peer: from_peer.clone(),
+                from_user,
+                to_peer: to_peer.clone(),
+                chat_id: chat_id.clone(),
+                sdp,
+            };
             if let Some(target) = target_sender {
-                let forward_msg = SignalingMessage::VoiceAnswer {
-                    from_peer,
-                    from_user,
-                    to_peer,
-                    chat_id,
-                    sdp,
-                };
                 let json = serde_json::to_string(&forward_msg)
                     .map_err(|e| format!("Failed to serialize VoiceAnswer: {}", e))?;
                 target
                     .send(tokio_tungstenite::tungstenite::Message::Text(json))
                     .map_err(|e| format!("Failed to forward VoiceAnswer: {}", e))?;
-            } else {
+            } else if !relay_voice_to_remote_peer(state, &from_peer, &to_peer, &chat_id, &forward_msg).await {
                 warn!("Target peer {} not found in chat {} for VoiceAnswer", to_peer, chat_id);
             }
 
@@ -677,11 +843,8 @@ pub async fn handle_message(
         }
 
         SignalingMessage::VoiceIceCandidate { from_peer, to_peer, chat_id, candidate } => {
-            {
-                let signaling = state.signaling.read().await;
-                if !signaling.validate_peer_connection(&from_peer, conn_id) {
-                    return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id));
-                }
+            if !state.routes.validate_peer_connection(&from_peer, conn_id) {
+                return Err(format!("Invalid peer_id {} for connection {}", from_peer, conn_id));
             }
 
             let target_sender = {
@@ -701,18 +864,20 @@ pub async fn handle_message(
                 }
             };
 
+            let forward_msg = SignalingMessage::VoiceIceCandidate {
+                from_peer: from_peer.clone(),
+                to_peer: to_peer.clone(),
+                chat_id: chat_id.clone(),
+                candidate,
+            };
             if let Some(target) = target_sender {
-                let forward_msg = SignalingMessage::VoiceIceCandidate {
-                    from_peer,
-                    to_peer,
-                    chat_id,
-                    candidate,
-                };
                 let json = serde_json::to_string(&forward_msg)
                     .map_err(|e| format!("Failed to serialize VoiceIceCandidate: {}", e))?;
                 target
                     .send(tokio_tungstenite::tungstenite::Message::Text(json))
                     .map_err(|e| format!("Failed to forward VoiceIceCandidate: {}", e))?;
+            } else {
+                relay_voice_to_remote_peer(state, &from_peer, &to_peer, &chat_id, &forward_msg).await;
             }
             // Don't warn on missing peer for ICE candidates - they may have left
 
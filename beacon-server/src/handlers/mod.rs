@@ -1,4 +1,6 @@
 pub mod message;
+pub mod codec;
+pub mod deflate;
 pub mod http;
 pub mod ws;
 pub mod friends;
@@ -1,7 +1,17 @@
 #[cfg(feature = "redis-backend")]
-use crate::{SigningPubkey, state::presence::PresenceUserStatus};
+use crate::{PeerId, SigningPubkey, state::{AppState, presence::PresenceUserStatus}};
 #[cfg(feature = "redis-backend")]
-use redis::AsyncCommands;
+use futures_util::StreamExt;
+#[cfg(feature = "redis-backend")]
+use log::{info, warn};
+#[cfg(feature = "redis-backend")]
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+#[cfg(feature = "redis-backend")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "redis-backend")]
+use std::sync::Arc;
+#[cfg(feature = "redis-backend")]
+use tokio_tungstenite::tungstenite::Message;
 
 #[cfg(feature = "redis-backend")]
 pub fn redis_user_key(user_id: &str) -> String {
@@ -174,3 +184,155 @@ pub async fn redis_presence_refresh(
         .map_err(|e| format!("redis_presence_refresh query: {}", e))?;
     Ok(())
 }
+
+// ============================================
+// Cross-instance relay
+// ============================================
+
+/// Redis key naming the beacon instance that holds a peer's WebSocket.
+#[cfg(feature = "redis-backend")]
+pub fn redis_relay_peer_key(peer_id: &str) -> String {
+    format!("relay:peer:{}", peer_id)
+}
+
+/// Pub/sub channel each instance subscribes to for messages addressed to its peers.
+#[cfg(feature = "redis-backend")]
+pub fn redis_relay_channel(instance_id: &str) -> String {
+    format!("relay:instance:{}", instance_id)
+}
+
+/// Voice room a relayed message belongs to. Servers are named by signing key, since
+/// clients on different instances may use different local server ids.
+#[cfg(feature = "redis-backend")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayVoiceRoom {
+    pub signing_pubkey: SigningPubkey,
+    pub chat_id: String,
+}
+
+/// A signaling message published to the instance that holds `to_peer`.
+#[cfg(feature = "redis-backend")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelayEnvelope {
+    pub to_peer: PeerId,
+    pub message: String,
+    /// Set for voice signaling; the receiving instance only delivers if `to_peer` is in this room.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_room: Option<RelayVoiceRoom>,
+}
+
+/// Record this instance as the owner of `peer_ids` (refreshed with the presence TTL).
+#[cfg(feature = "redis-backend")]
+pub async fn redis_relay_claim(
+    conn: &mut MultiplexedConnection,
+    ttl_secs: u64,
+    instance_id: &str,
+    peer_ids: &[PeerId],
+) -> Result<(), String> {
+    if peer_ids.is_empty() {
+        return Ok(());
+    }
+    let mut pipe = redis::pipe();
+    for peer_id in peer_ids {
+        pipe.set_ex(redis_relay_peer_key(peer_id), instance_id, ttl_secs);
+    }
+    pipe.query_async::<_, ()>(conn)
+        .await
+        .map_err(|e| format!("redis_relay_claim query: {}", e))?;
+    Ok(())
+}
+
+/// Drop ownership of `peer_ids`, unless another instance has claimed them since.
+#[cfg(feature = "redis-backend")]
+pub async fn redis_relay_release(
+    conn: &mut MultiplexedConnection,
+    instance_id: &str,
+    peer_ids: &[PeerId],
+) -> Result<(), String> {
+    if peer_ids.is_empty() {
+        return Ok(());
+    }
+    let script = redis::Script::new(
+        "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('DEL', KEYS[1]) else return 0 end",
+    );
+    for peer_id in peer_ids {
+        script
+            .key(redis_relay_peer_key(peer_id))
+            .arg(instance_id)
+            .invoke_async::<_, i64>(conn)
+            .await
+            .map_err(|e| format!("redis_relay_release query: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Publish `message` to the instance holding `to_peer`.
+/// Returns false when no other instance owns the peer or nobody is subscribed.
+#[cfg(feature = "redis-backend")]
+pub async fn redis_relay_publish(
+    conn: &mut MultiplexedConnection,
+    instance_id: &str,
+    to_peer: &str,
+    message: String,
+    voice_room: Option<RelayVoiceRoom>,
+) -> Result<bool, String> {
+    let owner: Option<String> = conn
+        .get(redis_relay_peer_key(to_peer))
+        .await
+        .map_err(|e| format!("redis_relay_publish lookup: {}", e))?;
+    let Some(owner) = owner.filter(|owner| owner != instance_id) else {
+        return Ok(false);
+    };
+    let envelope = serde_json::to_string(&RelayEnvelope { to_peer: to_peer.to_string(), message, voice_room })
+        .map_err(|e| format!("redis_relay_publish serialize: {}", e))?;
+    let receivers: i64 = conn
+        .publish(redis_relay_channel(&owner), envelope)
+        .await
+        .map_err(|e| format!("redis_relay_publish query: {}", e))?;
+    Ok(receivers > 0)
+}
+
+/// Subscribe to this instance's relay channel and deliver messages to local peers.
+/// Reconnects after a short delay if the subscription drops.
+#[cfg(feature = "redis-backend")]
+pub async fn run_redis_relay(state: Arc<AppState>, client: redis::Client, instance_id: String) {
+    let channel = redis_relay_channel(&instance_id);
+    loop {
+        match client.get_async_pubsub().await {
+            Ok(mut pubsub) => match pubsub.subscribe(&channel).await {
+                Ok(()) => {
+                    info!("Redis relay subscribed to {}", channel);
+                    let mut messages = pubsub.into_on_message();
+                    while let Some(msg) = messages.next().await {
+                        let envelope = msg
+                            .get_payload::<String>()
+                            .map_err(|e| e.to_string())
+                            .and_then(|payload| serde_json::from_str::<RelayEnvelope>(&payload).map_err(|e| e.to_string()));
+                        match envelope {
+                            Ok(envelope) => {
+                                if let Some(room) = &envelope.voice_room {
+                                    let in_room = state.voice.read().await.room_key_of(&envelope.to_peer, &room.chat_id);
+                                    if in_room.as_ref() != Some(&room.signing_pubkey) {
+                                        warn!("Dropped relayed voice message for {} outside chat {}", envelope.to_peer, room.chat_id);
+                                        continue;
+                                    }
+                                }
+                                match state.routes.sender(&envelope.to_peer) {
+                                    Some(sender) => {
+                                        let _ = sender.send(Message::Text(envelope.message));
+                                    }
+                                    None => warn!("Relayed message for unknown peer {}", envelope.to_peer),
+                                }
+                            }
+                            Err(e) => warn!("Invalid relay message: {}", e),
+                        }
+                    }
+                    warn!("Redis relay subscription closed; reconnecting");
+                }
+                Err(e) => warn!("Redis relay subscribe failed: {}", e),
+            },
+            Err(e) => warn!("Redis relay connection failed: {}", e),
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    }
+}
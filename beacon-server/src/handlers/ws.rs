@@ -1,12 +1,20 @@
-use axum::extract::ws::{Message as AxumMessage, WebSocket, WebSocketUpgrade};
-use axum::extract::{State, Extension};
-use axum::http::StatusCode;
+use axum::extract::{Extension, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, Response, StatusCode};
 use axum::response::IntoResponse;
 use futures_util::{SinkExt, StreamExt};
+use hyper_util::rt::TokioIo;
 use log::{error, info, warn};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
 
+use crate::handlers::codec::{self, Encoding, JSON_SUBPROTOCOL, MSGPACK_SUBPROTOCOL};
+use crate::handlers::deflate::{Deflate, DeflateStream};
 use crate::handlers::message::handle_message;
 use crate::security::ClientIp;
 use crate::state::AppState;
@@ -15,24 +23,38 @@ use crate::{ConnId, SignalingMessage};
 type SharedState = Arc<AppState>;
 
 #[cfg(feature = "redis-backend")]
-use crate::handlers::redis::redis_presence_disconnect;
+use crate::handlers::redis::{redis_presence_disconnect, redis_relay_release};
 
-fn tungstenite_to_axum(msg: tokio_tungstenite::tungstenite::Message) -> AxumMessage {
-    use tokio_tungstenite::tungstenite::Message as WsMsg;
+/// Handlers queue JSON text; on a MessagePack connection it goes out as a binary frame.
+fn encode_outgoing(msg: Message, encoding: Encoding) -> Message {
     match msg {
-        WsMsg::Text(s) => AxumMessage::Text(s),
-        WsMsg::Binary(v) => AxumMessage::Binary(v),
-        WsMsg::Ping(v) => AxumMessage::Ping(v),
-        WsMsg::Pong(v) => AxumMessage::Pong(v),
-        WsMsg::Close(_) => AxumMessage::Close(None),
-        WsMsg::Frame(_) => unreachable!(),
+        Message::Text(s) if encoding == Encoding::MessagePack => match codec::encode_binary(&s) {
+            Ok(bytes) => Message::Binary(bytes),
+            Err(e) => {
+                warn!("Failed to encode MessagePack frame, sending JSON: {}", e);
+                Message::Text(s)
+            }
+        },
+        other => other,
     }
 }
 
+/// Whether a comma-separated header lists `token` (case-insensitive).
+fn header_has_token(headers: &HeaderMap, name: HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| v.trim().eq_ignore_ascii_case(token))
+}
+
+/// The WebSocket upgrade is done by hand rather than with axum's extractor, which can't
+/// negotiate extensions: the permessage-deflate layer has to sit under the socket.
 pub async fn ws_handler(
-    ws: WebSocketUpgrade,
     State(state): State<SharedState>,
     Extension(ClientIp(client_ip)): Extension<ClientIp>,
+    mut request: Request,
 ) -> axum::response::Response {
     {
         let tracker = state.connection_tracker.read().await;
@@ -40,10 +62,81 @@ pub async fn ws_handler(
             return (StatusCode::SERVICE_UNAVAILABLE, "Connection limit reached").into_response();
         }
     }
-    ws.on_upgrade(move |socket| handle_connection_axum(socket, state, client_ip))
+
+    let headers = request.headers();
+    let key = match headers.get(header::SEC_WEBSOCKET_KEY) {
+        Some(key)
+            if header_has_token(headers, header::CONNECTION, "upgrade")
+                && header_has_token(headers, header::UPGRADE, "websocket")
+                && headers.get(header::SEC_WEBSOCKET_VERSION).map(|v| v.as_bytes()) == Some(b"13") =>
+        {
+            key.as_bytes().to_vec()
+        }
+        _ => return (StatusCode::BAD_REQUEST, "Expected a WebSocket upgrade").into_response(),
+    };
+    let protocol = [MSGPACK_SUBPROTOCOL, JSON_SUBPROTOCOL]
+        .into_iter()
+        .find(|p| header_has_token(headers, header::SEC_WEBSOCKET_PROTOCOL, p));
+    let deflate = if state.security.ws_compression_threshold_bytes > 0 {
+        let limits = Deflate {
+            threshold: state.security.ws_compression_threshold_bytes,
+            level: state.security.ws_compression_level,
+            send_window_bits: state.security.ws_deflate_window_bits,
+            receive_window_bits: state.security.ws_deflate_window_bits,
+            max_message: state.security.max_body_bytes,
+        };
+        headers
+            .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .find_map(|offers| limits.negotiate(offers))
+    } else {
+        None
+    };
+    let on_upgrade = match request.extensions_mut().remove::<hyper::upgrade::OnUpgrade>() {
+        Some(on_upgrade) => on_upgrade,
+        None => return (StatusCode::UPGRADE_REQUIRED, "Connection can't be upgraded").into_response(),
+    };
+
+    let mut response = Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::CONNECTION, "upgrade")
+        .header(header::UPGRADE, "websocket")
+        .header(header::SEC_WEBSOCKET_ACCEPT, derive_accept_key(&key));
+    if let Some(protocol) = protocol {
+        response = response.header(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+    }
+    let deflate = match deflate {
+        Some((agreed, extension)) => {
+            response = response.header(header::SEC_WEBSOCKET_EXTENSIONS, extension);
+            Some(agreed)
+        }
+        None => None,
+    };
+
+    tokio::spawn(async move {
+        let upgraded = match on_upgrade.await {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                warn!("WebSocket upgrade failed: {}", e);
+                return;
+            }
+        };
+        let io = DeflateStream::new(TokioIo::new(upgraded), deflate);
+        let socket = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+        handle_connection(socket, Encoding::from_subprotocol(protocol), state, client_ip).await;
+    });
+    response
+        .body(axum::body::Body::empty())
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
 }
 
-async fn handle_connection_axum(socket: WebSocket, state: SharedState, client_ip: String) {
+async fn handle_connection(
+    socket: WebSocketStream<DeflateStream<TokioIo<hyper::upgrade::Upgraded>>>,
+    encoding: Encoding,
+    state: SharedState,
+    client_ip: String,
+) {
     if state.connection_tracker.write().await.try_register(&client_ip).is_err() {
         return;
     }
@@ -51,44 +144,73 @@ async fn handle_connection_axum(socket: WebSocket, state: SharedState, client_ip
     info!("WebSocket connection established");
 
     let conn_id: ConnId = uuid::Uuid::new_v4().to_string();
-    let (tx, mut rx) = mpsc::unbounded_channel::<tokio_tungstenite::tungstenite::Message>();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
 
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
     let mut send_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
-            let axum_msg = tungstenite_to_axum(msg);
-            if ws_sender.send(axum_msg).await.is_err() {
+            if ws_sender.send(encode_outgoing(msg, encoding)).await.is_err() {
                 break;
             }
         }
     });
 
+    // Challenge first: Register is refused until the client signs this nonce.
+    let nonce = state.signaling.write().await.issue_challenge(&conn_id);
+    if let Ok(json) = serde_json::to_string(&SignalingMessage::AuthChallenge { nonce }) {
+        let _ = tx.send(Message::Text(json));
+    }
+
+    // Heartbeat: ping the client and reap the connection once it goes quiet (e.g. a NAT
+    // mapping timed out), so its peers don't stay registered forever.
+    let ping_every = state.security.ws_ping_interval_secs;
+    let idle_timeout = Duration::from_secs(state.security.ws_idle_timeout_secs);
+    let heartbeat_enabled = ping_every > 0 || !idle_timeout.is_zero();
+    let tick = if ping_every > 0 { Duration::from_secs(ping_every) } else { idle_timeout.max(Duration::from_secs(1)) };
+    let mut heartbeat = tokio::time::interval_at(Instant::now() + tick, tick);
+    let mut last_seen = Instant::now();
+
     loop {
         tokio::select! {
+            _ = heartbeat.tick(), if heartbeat_enabled => {
+                if !idle_timeout.is_zero() && last_seen.elapsed() > idle_timeout {
+                    info!("Closing idle connection {} (silent for {}s)", conn_id, last_seen.elapsed().as_secs());
+                    let _ = tx.send(Message::Close(None));
+                    break;
+                }
+                if ping_every > 0 {
+                    let _ = tx.send(Message::Ping(Vec::new()));
+                }
+            }
             msg_opt = ws_receiver.next() => {
+                last_seen = Instant::now();
                 match msg_opt {
-                    Some(Ok(AxumMessage::Text(text))) => {
+                    Some(Ok(frame @ (Message::Text(_) | Message::Binary(_)))) => {
                         if let Some(ref limiter) = state.ws_rate_limiter {
                             if !limiter.check_key(&client_ip) {
                                 let error_msg = SignalingMessage::Error {
                                     message: "Rate limit exceeded".to_string(),
                                 };
                                 if let Ok(json) = serde_json::to_string(&error_msg) {
-                                    let _ = tx.send(tokio_tungstenite::tungstenite::Message::Text(json));
+                                    let _ = tx.send(Message::Text(json));
                                 }
                                 continue;
                             }
                         }
-                        match serde_json::from_str::<SignalingMessage>(&text) {
+                        let parsed = match frame {
+                            Message::Text(text) => serde_json::from_str::<SignalingMessage>(&text).map_err(|e| e.to_string()),
+                            other => codec::decode_binary(&other.into_data()),
+                        };
+                        match parsed {
                             Ok(msg) => {
-                                if let Err(e) = handle_message(msg, &conn_id, &state, &tx).await {
+                                if let Err(e) = handle_message(msg, &conn_id, &client_ip, &state, &tx).await {
                                     warn!("Error handling message: {}", e);
                                     let error_msg = SignalingMessage::Error {
                                         message: e.to_string(),
                                     };
                                     if let Ok(json) = serde_json::to_string(&error_msg) {
-                                        let _ = tx.send(tokio_tungstenite::tungstenite::Message::Text(json));
+                                        let _ = tx.send(Message::Text(json));
                                     }
                                 }
                             }
@@ -98,17 +220,17 @@ async fn handle_connection_axum(socket: WebSocket, state: SharedState, client_ip
                                     message: format!("Invalid message format: {}", e),
                                 };
                                 if let Ok(json) = serde_json::to_string(&error_msg) {
-                                    let _ = tx.send(tokio_tungstenite::tungstenite::Message::Text(json));
+                                    let _ = tx.send(Message::Text(json));
                                 }
                             }
                         }
                     }
-                    Some(Ok(AxumMessage::Close(_))) => {
+                    Some(Ok(Message::Close(_))) => {
                         info!("Client closed connection");
                         break;
                     }
-                    Some(Ok(AxumMessage::Ping(data))) => {
-                        let _ = tx.send(tokio_tungstenite::tungstenite::Message::Pong(data));
+                    Some(Ok(Message::Ping(data))) => {
+                        let _ = tx.send(Message::Pong(data));
                     }
                     Some(Ok(_)) => {}
                     Some(Err(e)) => {
@@ -127,18 +249,11 @@ async fn handle_connection_axum(socket: WebSocket, state: SharedState, client_ip
         voice.server_signing_pubkeys.clone()
     };
 
-    let (presence_removed, voice_removed, redis_client) = {
+    let (peer_ids, presence_removed, voice_removed, redis_client) = {
         let mut signaling = state.signaling.write().await;
 
-        let peer_ids = if let Some(peer_ids) = signaling.conn_peers.remove(&conn_id) {
-            let ids: Vec<_> = peer_ids.iter().cloned().collect();
-            for peer_id in &ids {
-                signaling.unregister_peer(peer_id);
-            }
-            ids
-        } else {
-            Vec::new()
-        };
+        let peer_ids = signaling.unregister_conn(&conn_id);
+        signaling.remove_conn_state(&conn_id);
 
         drop(signaling);
 
@@ -162,9 +277,24 @@ async fn handle_connection_axum(socket: WebSocket, state: SharedState, client_ip
         #[cfg(not(feature = "redis-backend"))]
         let redis_client: Option<()> = None;
 
-        (presence_removed, voice_removed, redis_client)
+        (peer_ids, presence_removed, voice_removed, redis_client)
     };
 
+    #[cfg(feature = "redis-backend")]
+    {
+        let (relay_conn, instance_id) = {
+            let backends = state.backends.read().await;
+            (backends.redis_conn.clone(), backends.relay_instance_id.clone())
+        };
+        if let Some(mut conn) = relay_conn {
+            if let Err(e) = redis_relay_release(&mut conn, &instance_id, &peer_ids).await {
+                warn!("Redis relay release failed: {}", e);
+            }
+        }
+    }
+    #[cfg(not(feature = "redis-backend"))]
+    let _ = peer_ids;
+
     if !voice_removed.is_empty() {
         for (server_id, chat_id, peer_id, user_id) in voice_removed.clone() {
             info!(
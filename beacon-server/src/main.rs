@@ -132,6 +132,12 @@ pub enum SignalingMessage {
         last_updated: DateTime<Utc>,
     },
 
+    /// Broadcast when a member posts a signed leave via REST API (owner drops them from the hint)
+    ServerMemberLeft {
+        signing_pubkey: SigningPubkey,
+        user_id: String,
+    },
+
     /// Client sends a live-only encrypted chat message for a server chat.
     /// Beacon relays the envelope only; payload remains opaque.
     EphemeralChatSend {
@@ -416,6 +422,49 @@ pub enum SignalingMessage {
     /// Server pong response
     Pong,
 
+    // ============================
+    // Protocol negotiation
+    // ============================
+
+    /// Client -> server, first message: signaling protocol versions it speaks
+    Hello {
+        protocol_versions: Vec<u32>,
+        #[serde(default)]
+        client_version: Option<String>,
+    },
+
+    /// Server reply to Hello: the version picked (outside the client's list when there is no
+    /// overlap), client message types it handles, enabled features and limits
+    Welcome {
+        protocol_version: u32,
+        min_protocol_version: u32,
+        server_version: String,
+        accepts: Vec<String>,
+        features: Vec<String>,
+        limits: serde_json::Value,
+    },
+
+    // ============================
+    // Connection authentication (challenge-response)
+    // ============================
+
+    /// Server -> client on connect: sign `AUTH_CONTEXT` + nonce to prove key ownership
+    AuthChallenge {
+        nonce: String,
+    },
+
+    /// Client answer to AuthChallenge (hex Ed25519 public key, base64 signature)
+    Authenticate {
+        public_key: String,
+        signature: String,
+    },
+
+    /// Server confirms the key is now bound to this connection
+    Authenticated {
+        public_key: String,
+        user_id: String,
+    },
+
     // ============================
     // Friends (requests + codes)
     // ============================
@@ -651,6 +700,15 @@ pub struct ServerEvent {
     pub timestamp: DateTime<Utc>,
 }
 
+/// A member's own notice that they left a server. Only the owner can publish hints, so this is
+/// stored as a MemberLeave event for the owner to apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberLeaveRequest {
+    pub public_key: String,  // Member's identity key (hex)
+    pub signature: String,   // Over MEMBER_LEAVE_CONTEXT, signing_pubkey and left_at (ms)
+    pub left_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AckRequest {
     pub user_id: String,
@@ -700,6 +758,14 @@ pub struct ProfileRecord {
 // Moved to handlers/db.rs and handlers/redis.rs
 
 const EVENT_RETENTION_DAYS: i64 = 30;
+
+/// Signaling protocol spoken by this beacon, reported on `/api/info` and in `Welcome`. Bump
+/// when a message changes incompatibly; raise the minimum when support for an old client is
+/// dropped. 1: the original message set. 2: `Hello`/`Welcome` (protocol 1 clients skip it).
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Default for `BEACON_MIN_PROTOCOL_VERSION`.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
 #[cfg(feature = "redis-backend")]
 pub const DEFAULT_REDIS_PRESENCE_TTL_SECS: u64 = 120;
 
@@ -716,7 +782,7 @@ use handlers::db::init_db;
 #[cfg(feature = "postgres")]
 use handlers::db::gc_old_events_db;
 #[cfg(feature = "redis-backend")]
-use handlers::redis::redis_presence_refresh;
+use handlers::redis::{redis_presence_refresh, redis_relay_claim, run_redis_relay};
 
 type SharedState = Arc<AppState>;
 
@@ -936,7 +1002,7 @@ async fn main() {
 
     let downtime_secs = read_downtime_secs();
     let addr: SocketAddr = "0.0.0.0:9001".parse().expect("Invalid address");
-    let state = Arc::new(AppState::new(downtime_secs, connection_tracker, ws_rate_limiter));
+    let state = Arc::new(AppState::new(downtime_secs, connection_tracker, ws_rate_limiter, security_config.clone()));
 
     // Optional Postgres durability (profiles first; others later)
     #[cfg(feature = "postgres")]
@@ -976,9 +1042,14 @@ async fn main() {
                             match pong {
                                 Ok(_) => {
                                     let mut backends = state.backends.write().await;
-                                    backends.redis = Some(client);
+                                    backends.redis = Some(client.clone());
+                                    backends.redis_conn = Some(conn);
                                     backends.redis_presence_ttl_secs = ttl_secs;
                                     info!("Redis presence enabled (SIGNALING_REDIS_URL set).");
+
+                                    let instance_id = backends.relay_instance_id.clone();
+                                    info!("Redis relay enabled as instance {}", instance_id);
+                                    tokio::spawn(run_redis_relay(state.clone(), client, instance_id));
                                 }
                                 Err(e) => log::warn!("Redis PING failed; continuing without Redis: {}", e),
                             }
@@ -1062,11 +1133,14 @@ async fn main() {
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                let (client, ttl, users) = {
+                let (client, relay_conn, ttl, instance_id, local_peers, users) = {
                     let backends = refresh_state.backends.read().await;
                     let presence = refresh_state.presence.read().await;
                     let client = backends.redis.clone();
+                    let relay_conn = backends.redis_conn.clone();
                     let ttl = backends.redis_presence_ttl_secs;
+                    let instance_id = backends.relay_instance_id.clone();
+                    let local_peers = refresh_state.routes.peers.keys();
                     let users = presence
                         .presence_users
                         .iter()
@@ -1078,7 +1152,7 @@ async fn main() {
                             )
                         })
                         .collect::<Vec<_>>();
-                    (client, ttl, users)
+                    (client, relay_conn, ttl, instance_id, local_peers, users)
                 };
 
                 if let Some(client) = client {
@@ -1086,6 +1160,11 @@ async fn main() {
                         log::warn!("Redis presence refresh failed: {}", e);
                     }
                 }
+                if let Some(mut conn) = relay_conn {
+                    if let Err(e) = redis_relay_claim(&mut conn, ttl, &instance_id, &local_peers).await {
+                        log::warn!("Redis relay refresh failed: {}", e);
+                    }
+                }
             }
         });
     }
@@ -1096,6 +1175,7 @@ async fn main() {
         .route("/invites", axum::routing::post(handlers::http::create_server_invite))
         .route("/events", get(handlers::http::get_events).post(handlers::http::post_event))
         .route("/events/ack", axum::routing::post(handlers::http::ack_events))
+        .route("/leave", axum::routing::post(handlers::http::post_member_leave))
         .route("/ack", axum::routing::post(handlers::http::ack_events));
 
     // Friend routes with full paths and auth middleware. Merge (don't nest) so the same request
@@ -1115,6 +1195,7 @@ async fn main() {
 
     let app = Router::new()
         .route("/api/status", get(handlers::http::get_status))
+        .route("/api/info", get(handlers::http::get_info))
         .route("/api/invites/:code", get(handlers::http::get_invite))
         .route("/api/invites/:code/redeem", axum::routing::post(handlers::http::redeem_invite))
         .route("/api/invites/:code/revoke", axum::routing::post(handlers::http::revoke_invite))
@@ -1124,7 +1205,7 @@ async fn main() {
         .route("/", get(status_page_handler))
         .route("/status", get(status_page_handler))
         .route("/ws", get(handlers::ws::ws_handler))
-        .fallback(|| async { (StatusCode::NOT_FOUND, "Not found. Use / or /status, /health, /api/*, or /ws for WebSocket.") })
+        .fallback(|| async { (StatusCode::NOT_FOUND, "Not found. Use / or /status, /health, /api/info, /api/*, or /ws for WebSocket.") })
         .layer(middleware::from_fn(security::client_ip_middleware))
         .layer(middleware::from_fn(move |req: axum::extract::Request, next: axum::middleware::Next| {
             let limiter = Arc::clone(&rest_rate_limiter_for_layer);
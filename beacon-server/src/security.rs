@@ -40,6 +40,21 @@ pub struct SecurityConfig {
     pub rate_limit_rest_per_min: u32,
     /// WebSocket messages per minute per IP; 0 = no limit.
     pub rate_limit_ws_per_min: u32,
+    /// Seconds between server-initiated WebSocket pings; 0 = don't ping.
+    pub ws_ping_interval_secs: u64,
+    /// Close WebSocket connections silent for this many seconds; 0 = never.
+    pub ws_idle_timeout_secs: u64,
+    /// Messages at least this big are deflated on connections that negotiate
+    /// permessage-deflate; 0 = don't accept the extension.
+    pub ws_compression_threshold_bytes: usize,
+    /// Deflate level for those messages, 0-9.
+    pub ws_compression_level: u32,
+    /// Largest deflate window (`server_max_window_bits` / `client_max_window_bits`), 9-15.
+    pub ws_deflate_window_bits: u8,
+    /// Oldest signaling protocol accepted; clients that never send Hello count as protocol 1.
+    pub min_protocol_version: u32,
+    /// Per-type quotas (messages per minute per IP) on top of rate_limit_ws_per_min; 0 = no limit.
+    pub type_rate_limits: Vec<(MessageClass, u32)>,
 }
 
 impl SecurityConfig {
@@ -71,6 +86,50 @@ impl SecurityConfig {
             .and_then(|v| v.parse().ok())
             .unwrap_or(250);
 
+        let ws_ping_interval_secs = env::var("BEACON_WS_PING_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
+
+        let ws_idle_timeout_secs = env::var("BEACON_WS_IDLE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(90);
+
+        let ws_compression_threshold_bytes = env::var("BEACON_WS_COMPRESSION_THRESHOLD_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1024);
+
+        let ws_compression_level = env::var("BEACON_WS_COMPRESSION_LEVEL")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(6)
+            .min(9);
+
+        let ws_deflate_window_bits = env::var("BEACON_WS_DEFLATE_WINDOW_BITS")
+            .ok()
+            .and_then(|v| v.parse::<u8>().ok())
+            .unwrap_or(12)
+            .clamp(9, 15);
+
+        let min_protocol_version = env::var("BEACON_MIN_PROTOCOL_VERSION")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(crate::MIN_PROTOCOL_VERSION)
+            .clamp(1, crate::PROTOCOL_VERSION);
+
+        let type_rate_limits = MessageClass::ALL
+            .iter()
+            .map(|class| {
+                let per_min = env::var(class.env_var())
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(class.default_per_min());
+                (*class, per_min)
+            })
+            .collect();
+
         Self {
             cors_origins,
             max_body_bytes,
@@ -78,6 +137,81 @@ impl SecurityConfig {
             max_ws_per_ip,
             rate_limit_rest_per_min,
             rate_limit_ws_per_min,
+            ws_ping_interval_secs,
+            ws_idle_timeout_secs,
+            ws_compression_threshold_bytes,
+            ws_compression_level,
+            ws_deflate_window_bits,
+            min_protocol_version,
+            type_rate_limits,
+        }
+    }
+}
+
+/// Message categories with their own rate limit. Offers/answers/ICE need room for
+/// renegotiation bursts; server-hint writes are rare, so their quota is strict.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    /// Offer/Answer/IceCandidate and their voice counterparts
+    Signaling,
+    /// Register/VoiceRegister/PresenceHello and the like
+    Presence,
+    /// Ephemeral chat messages and receipts relayed to server members
+    Chat,
+    /// Attachment transfer and swarm coordination
+    Transfer,
+    /// Profile announce/hello/push
+    Profile,
+    /// Friend mutual checks
+    Friends,
+    /// REST server-hint registration
+    ServerHint,
+}
+
+impl MessageClass {
+    pub const ALL: [MessageClass; 7] = [
+        MessageClass::Signaling,
+        MessageClass::Presence,
+        MessageClass::Chat,
+        MessageClass::Transfer,
+        MessageClass::Profile,
+        MessageClass::Friends,
+        MessageClass::ServerHint,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageClass::Signaling => "signaling",
+            MessageClass::Presence => "presence",
+            MessageClass::Chat => "chat",
+            MessageClass::Transfer => "transfer",
+            MessageClass::Profile => "profile",
+            MessageClass::Friends => "friends",
+            MessageClass::ServerHint => "server_hint",
+        }
+    }
+
+    fn env_var(self) -> &'static str {
+        match self {
+            MessageClass::Signaling => "BEACON_RATE_LIMIT_SIGNALING_PER_MIN",
+            MessageClass::Presence => "BEACON_RATE_LIMIT_PRESENCE_PER_MIN",
+            MessageClass::Chat => "BEACON_RATE_LIMIT_CHAT_PER_MIN",
+            MessageClass::Transfer => "BEACON_RATE_LIMIT_TRANSFER_PER_MIN",
+            MessageClass::Profile => "BEACON_RATE_LIMIT_PROFILE_PER_MIN",
+            MessageClass::Friends => "BEACON_RATE_LIMIT_FRIENDS_PER_MIN",
+            MessageClass::ServerHint => "BEACON_RATE_LIMIT_SERVER_HINT_PER_MIN",
+        }
+    }
+
+    fn default_per_min(self) -> u32 {
+        match self {
+            MessageClass::Signaling => 240,
+            MessageClass::Presence => 120,
+            MessageClass::Chat => 90,
+            MessageClass::Transfer => 120,
+            MessageClass::Profile => 30,
+            MessageClass::Friends => 30,
+            MessageClass::ServerHint => 10,
         }
     }
 }
@@ -158,6 +292,36 @@ pub fn build_ws_rate_limiter(messages_per_minute: u32) -> Option<Arc<KeyedRateLi
     KeyedRateLimiter::per_minute(messages_per_minute)
 }
 
+/// One per-IP limiter per MessageClass; classes configured with 0 are unlimited.
+pub struct TypeRateLimiters {
+    limiters: HashMap<MessageClass, (u32, Arc<KeyedRateLimiter>)>,
+}
+
+impl TypeRateLimiters {
+    pub fn new(limits: &[(MessageClass, u32)]) -> Self {
+        let limiters = limits
+            .iter()
+            .filter_map(|(class, per_min)| {
+                KeyedRateLimiter::per_minute(*per_min).map(|l| (*class, (*per_min, l)))
+            })
+            .collect();
+        Self { limiters }
+    }
+
+    /// Ok if the key is under the class quota (one unit consumed); otherwise the 429-style
+    /// error to send back.
+    pub fn check(&self, class: MessageClass, key: &str) -> Result<(), String> {
+        match self.limiters.get(&class) {
+            Some((per_min, limiter)) if !limiter.check_key(key) => Err(format!(
+                "Rate limit exceeded (429): at most {} {} messages per minute",
+                per_min,
+                class.name()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Middleware: reject REST request with 429 if client IP is over rate limit.
 /// Run after client_ip_middleware so ClientIp is in extensions.
 pub async fn rest_rate_limit_middleware(
@@ -248,3 +412,26 @@ impl ConnectionTracker {
 
 /// Shared connection tracker for use in AppState and ws_handler.
 pub type SharedConnectionTracker = Arc<RwLock<ConnectionTracker>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_quotas_are_independent_per_class_and_key() {
+        let limiters = TypeRateLimiters::new(&[
+            (MessageClass::ServerHint, 2),
+            (MessageClass::Signaling, 0),
+        ]);
+        assert!(limiters.check(MessageClass::ServerHint, "1.2.3.4").is_ok());
+        assert!(limiters.check(MessageClass::ServerHint, "1.2.3.4").is_ok());
+        let err = limiters.check(MessageClass::ServerHint, "1.2.3.4").unwrap_err();
+        assert!(err.contains("429") && err.contains("server_hint"));
+
+        assert!(limiters.check(MessageClass::ServerHint, "5.6.7.8").is_ok());
+        for _ in 0..100 {
+            assert!(limiters.check(MessageClass::Signaling, "1.2.3.4").is_ok());
+        }
+        assert!(limiters.check(MessageClass::Chat, "1.2.3.4").is_ok());
+    }
+}
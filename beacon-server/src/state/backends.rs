@@ -1,7 +1,7 @@
 #[cfg(feature = "postgres")]
 use sqlx::PgPool;
 #[cfg(feature = "redis-backend")]
-use redis::Client;
+use redis::{aio::MultiplexedConnection, Client};
 
 /// Backend state (db, redis)
 pub struct BackendState {
@@ -9,8 +9,14 @@ pub struct BackendState {
     pub db: Option<PgPool>,
     #[cfg(feature = "redis-backend")]
     pub redis: Option<Client>,
+    /// Multiplexed connection opened once at startup and cloned for each relay call.
+    #[cfg(feature = "redis-backend")]
+    pub redis_conn: Option<MultiplexedConnection>,
     #[cfg(feature = "redis-backend")]
     pub redis_presence_ttl_secs: u64,
+    /// Identifies this beacon on the Redis relay, so other instances can route to its peers.
+    #[cfg(feature = "redis-backend")]
+    pub relay_instance_id: String,
 }
 
 impl BackendState {
@@ -21,7 +27,35 @@ impl BackendState {
             #[cfg(feature = "redis-backend")]
             redis: None,
             #[cfg(feature = "redis-backend")]
+            redis_conn: None,
+            #[cfg(feature = "redis-backend")]
             redis_presence_ttl_secs: 120, // Matches DEFAULT_REDIS_PRESENCE_TTL_SECS in main.rs
+            #[cfg(feature = "redis-backend")]
+            relay_instance_id: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Whether Postgres is configured and connected (always false without the feature).
+    pub fn has_db(&self) -> bool {
+        #[cfg(feature = "postgres")]
+        {
+            self.db.is_some()
+        }
+        #[cfg(not(feature = "postgres"))]
+        {
+            false
+        }
+    }
+
+    /// Whether the Redis presence store is configured and connected.
+    pub fn has_redis(&self) -> bool {
+        #[cfg(feature = "redis-backend")]
+        {
+            self.redis.is_some()
+        }
+        #[cfg(not(feature = "redis-backend"))]
+        {
+            false
         }
     }
 }
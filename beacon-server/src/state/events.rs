@@ -1,8 +1,13 @@
 use std::collections::HashMap;
-use chrono::{Duration, Utc};
-use crate::{SigningPubkey, EncryptedServerHint, InviteTokenRecord, ServerEvent, InviteTokenCreateRequest};
+use chrono::{DateTime, Duration, Utc};
+use crate::{SigningPubkey, EncryptedServerHint, InviteTokenRecord, ServerEvent, InviteTokenCreateRequest, MemberLeaveRequest};
+use crate::state::signaling::user_id_for_key;
 
 const EVENT_RETENTION_DAYS: i64 = 30;
+/// How far ahead of the beacon's clock a hint's last_updated may be.
+const HINT_MAX_CLOCK_SKEW_SECS: i64 = 300;
+/// Prefix of the message a member signs to leave a server.
+pub const MEMBER_LEAVE_CONTEXT: &str = "cordia-member-left:";
 
 /// Event queue state (REST API)
 /// Hints only - clients treat local state as authoritative
@@ -28,7 +33,8 @@ impl EventState {
         }
     }
 
-    /// Register/update server hint (any member can call this at any time)
+    /// Register/update server hint (callers check it with verify_server_hint_signature and
+    /// is_stale_server_hint first)
     pub fn register_server_hint(&mut self, signing_pubkey: String, hint: EncryptedServerHint) {
         self.server_hints.insert(signing_pubkey, hint);
     }
@@ -87,8 +93,8 @@ impl EventState {
     }
 
     /// Post event to queue
+    /// Queue an event; the caller sets its timestamp.
     pub fn post_event(&mut self, signing_pubkey: String, mut event: ServerEvent) {
-        event.timestamp = Utc::now();
         if event.event_id.is_empty() {
             event.event_id = uuid::Uuid::new_v4().to_string();
         }
@@ -133,3 +139,144 @@ impl EventState {
         self.event_queues.retain(|_, events| !events.is_empty());
     }
 }
+
+/// What the server signing key signs for a hint: pubkey, ciphertext and last_updated (ms).
+pub fn server_hint_signed_message(hint: &EncryptedServerHint) -> String {
+    format!(
+        "{}\n{}\n{}",
+        hint.signing_pubkey,
+        hint.encrypted_state,
+        hint.last_updated.timestamp_millis()
+    )
+}
+
+/// Checks the hint is signed by the server it claims to describe (signing_pubkey is the
+/// base64 Ed25519 verifying key), so members can't push state for a server they don't own.
+pub fn verify_server_hint_signature(hint: &EncryptedServerHint) -> Result<(), &'static str> {
+    use base64::Engine;
+    let engine = base64::engine::general_purpose::STANDARD;
+    let key_bytes: [u8; 32] = engine
+        .decode(&hint.signing_pubkey)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Invalid signing_pubkey")?;
+    let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|_| "Invalid signing_pubkey")?;
+    let sig_bytes: [u8; 64] = engine
+        .decode(&hint.signature)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Missing or malformed hint signature")?;
+    verifying_key
+        .verify_strict(
+            server_hint_signed_message(hint).as_bytes(),
+            &ed25519_dalek::Signature::from_bytes(&sig_bytes),
+        )
+        .map_err(|_| "Invalid hint signature")
+}
+
+/// What a leaving member signs with their identity key.
+pub fn member_leave_signed_message(signing_pubkey: &str, left_at: &DateTime<Utc>) -> String {
+    format!("{}{}\n{}", MEMBER_LEAVE_CONTEXT, signing_pubkey, left_at.timestamp_millis())
+}
+
+/// Checks a leave is signed by the member's own key and recent (so an old one can't be
+/// replayed after they rejoin). Returns the member's user_id.
+pub fn verify_member_leave(signing_pubkey: &str, leave: &MemberLeaveRequest) -> Result<String, &'static str> {
+    if (Utc::now() - leave.left_at).num_seconds().abs() > HINT_MAX_CLOCK_SKEW_SECS {
+        return Err("left_at is too far from the beacon's clock");
+    }
+    let key_bytes: [u8; 32] = hex::decode(&leave.public_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Invalid public_key")?;
+    let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|_| "Invalid public_key")?;
+    let sig_bytes: [u8; 64] = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &leave.signature)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or("Missing or malformed leave signature")?;
+    verifying_key
+        .verify_strict(
+            member_leave_signed_message(signing_pubkey, &leave.left_at).as_bytes(),
+            &ed25519_dalek::Signature::from_bytes(&sig_bytes),
+        )
+        .map_err(|_| "Invalid leave signature")?;
+    Ok(user_id_for_key(&leave.public_key.to_lowercase()))
+}
+
+/// A hint is stale if it isn't newer than the stored one, or claims to be from the future
+/// (which would lock out every later update).
+pub fn is_stale_server_hint(hint: &EncryptedServerHint, previous: Option<&EncryptedServerHint>) -> bool {
+    if hint.last_updated > Utc::now() + Duration::seconds(HINT_MAX_CLOCK_SKEW_SECS) {
+        return true;
+    }
+    previous.is_some_and(|prev| hint.last_updated <= prev.last_updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use ed25519_dalek::Signer;
+
+    fn signed_hint(key: &ed25519_dalek::SigningKey, state: &str, age_secs: i64) -> EncryptedServerHint {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut hint = EncryptedServerHint {
+            signing_pubkey: engine.encode(key.verifying_key().as_bytes()),
+            encrypted_state: state.to_string(),
+            signature: String::new(),
+            last_updated: Utc::now() - Duration::seconds(age_secs),
+        };
+        hint.signature = engine.encode(key.sign(server_hint_signed_message(&hint).as_bytes()).to_bytes());
+        hint
+    }
+
+    #[test]
+    fn server_hints_must_be_signed_by_the_server_and_fresh() {
+        let server_key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let member_key = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+
+        let older = signed_hint(&server_key, "state-1", 60);
+        let newer = signed_hint(&server_key, "state-2", 10);
+        assert!(verify_server_hint_signature(&newer).is_ok());
+
+        let mut tampered = newer.clone();
+        tampered.encrypted_state = "poisoned".to_string();
+        assert!(verify_server_hint_signature(&tampered).is_err());
+
+        let mut forged = signed_hint(&member_key, "poisoned", 0);
+        forged.signing_pubkey = newer.signing_pubkey.clone();
+        assert!(verify_server_hint_signature(&forged).is_err());
+
+        let mut unsigned = newer.clone();
+        unsigned.signature = String::new();
+        assert!(verify_server_hint_signature(&unsigned).is_err());
+
+        assert!(!is_stale_server_hint(&newer, Some(&older)));
+        assert!(is_stale_server_hint(&older, Some(&newer)));
+        assert!(is_stale_server_hint(&newer, Some(&newer)));
+        assert!(is_stale_server_hint(&signed_hint(&server_key, "s", -3600), None));
+    }
+
+    #[test]
+    fn member_leaves_are_signed_by_the_member_and_recent() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let member_key = ed25519_dalek::SigningKey::from_bytes(&[3u8; 32]);
+        let public_key = hex::encode(member_key.verifying_key().as_bytes());
+        let leave = |server: &str, age_secs: i64| {
+            let left_at = Utc::now() - Duration::seconds(age_secs);
+            let signature = member_key.sign(member_leave_signed_message(server, &left_at).as_bytes());
+            MemberLeaveRequest { public_key: public_key.clone(), signature: engine.encode(signature.to_bytes()), left_at }
+        };
+
+        assert_eq!(verify_member_leave("server-a", &leave("server-a", 5)), Ok(user_id_for_key(&public_key)));
+        // Signed for another server, or an old leave replayed later.
+        assert!(verify_member_leave("server-b", &leave("server-a", 5)).is_err());
+        assert!(verify_member_leave("server-a", &leave("server-a", 3600)).is_err());
+
+        let mut impostor = leave("server-a", 5);
+        impostor.public_key = hex::encode(ed25519_dalek::SigningKey::from_bytes(&[4u8; 32]).verifying_key().as_bytes());
+        assert!(verify_member_leave("server-a", &impostor).is_err());
+    }
+}
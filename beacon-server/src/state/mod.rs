@@ -6,8 +6,9 @@ pub mod events;
 pub mod backends;
 pub mod friends;
 pub mod swarm;
+pub mod shards;
 
-pub use signaling::SignalingState;
+pub use signaling::{PeerRoutes, SignalingState};
 pub use voice::VoiceState;
 pub use presence::PresenceState;
 pub use profiles::ProfileState;
@@ -28,6 +29,9 @@ use crate::state::signaling::FRIENDS_SIGNING_PUBKEY;
 /// Read-heavy state uses RwLock so multiple readers don't block each other; caches use Mutex.
 pub struct AppState {
     pub signaling: Arc<RwLock<SignalingState>>,
+    /// Same sharded routing tables as `signaling.routes`, for forwarding and broadcasts without
+    /// the signaling lock.
+    pub routes: PeerRoutes,
     pub voice: Arc<RwLock<VoiceState>>,
     pub presence: Arc<RwLock<PresenceState>>,
     pub profiles: Arc<RwLock<ProfileState>>,
@@ -49,6 +53,10 @@ pub struct AppState {
     pub connection_tracker: crate::security::SharedConnectionTracker,
     /// Per-IP WebSocket message rate limiter; None = no limit.
     pub ws_rate_limiter: Option<Arc<crate::security::KeyedRateLimiter>>,
+    /// Per-IP quotas per message type (signaling, chat, server hints, ...).
+    pub type_rate_limiters: crate::security::TypeRateLimiters,
+    /// Limits in effect, reported to clients on `/api/info`.
+    pub security: crate::security::SecurityConfig,
 }
 
 impl AppState {
@@ -56,10 +64,13 @@ impl AppState {
         downtime_secs: Option<u64>,
         connection_tracker: crate::security::SharedConnectionTracker,
         ws_rate_limiter: Option<Arc<crate::security::KeyedRateLimiter>>,
+        security: crate::security::SecurityConfig,
     ) -> Self {
         let now_utc = chrono::Utc::now();
+        let signaling = SignalingState::new();
         Self {
-            signaling: Arc::new(RwLock::new(SignalingState::new())),
+            routes: signaling.routes.clone(),
+            signaling: Arc::new(RwLock::new(signaling)),
             voice: Arc::new(RwLock::new(VoiceState::new())),
             presence: Arc::new(RwLock::new(PresenceState::new())),
             profiles: Arc::new(RwLock::new(ProfileState::new())),
@@ -74,14 +85,49 @@ impl AppState {
             cpu_percent_cache: Arc::new(Mutex::new(None)),
             connection_tracker,
             ws_rate_limiter,
+            type_rate_limiters: crate::security::TypeRateLimiters::new(&security.type_rate_limits),
+            security,
         }
     }
 
+    /// Enabled features. Features backed by an optional store are only listed when the store
+    /// is connected.
+    pub async fn features(&self) -> Vec<&'static str> {
+        let stores = {
+            let backends = self.backends.read().await;
+            [("postgres", backends.has_db()), ("redis", backends.has_redis())]
+        };
+        let compression = [("deflate", self.security.ws_compression_threshold_bytes > 0)];
+        [
+            "voice", "presence", "profiles", "friends", "ephemeral_chat", "attachments", "swarm",
+            "server_hints", "invites", "events", "connection_auth", "msgpack",
+        ]
+        .into_iter()
+        .chain(stores.iter().chain(compression.iter()).filter(|(_, on)| *on).map(|(name, _)| *name))
+        .collect()
+    }
+
+    /// Limits in effect; 0 means unlimited.
+    pub fn limits(&self) -> serde_json::Value {
+        let security = &self.security;
+        serde_json::json!({
+            "max_body_bytes": security.max_body_bytes,
+            "max_ws_connections": security.max_ws_connections,
+            "max_ws_per_ip": security.max_ws_per_ip,
+            "rate_limit_rest_per_min": security.rate_limit_rest_per_min,
+            "rate_limit_ws_per_min": security.rate_limit_ws_per_min,
+            "rate_limits_per_type": security
+                .type_rate_limits
+                .iter()
+                .map(|(class, per_min)| (class.name(), *per_min))
+                .collect::<std::collections::BTreeMap<_, _>>()
+        })
+    }
+
     /// Broadcast a presence update to all peers subscribed to a server.
     /// This coordinates between PresenceState and SignalingState.
     pub async fn broadcast_presence_update(&self, signing_pubkey: &SigningPubkey, user_id: &str, online: bool, active: Option<SigningPubkey>) {
-        let signaling = self.signaling.read().await;
-        let Some(peers) = signaling.signing_servers.get(signing_pubkey) else {
+        let Some(peers) = self.routes.signing_servers.get(signing_pubkey) else {
             return;
         };
 
@@ -96,8 +142,8 @@ impl AppState {
             return;
         };
 
-        for peer_id in peers {
-            if let Some(sender) = signaling.peer_senders.get(peer_id) {
+        for peer_id in &peers {
+            if let Some(sender) = self.routes.sender(peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -106,8 +152,7 @@ impl AppState {
     /// Broadcast a profile update to all peers subscribed to a server.
     /// This coordinates between ProfileState and SignalingState.
     pub async fn broadcast_profile_update(&self, signing_pubkey: &SigningPubkey, user_id: &str, rec: &ProfileRecord) {
-        let signaling = self.signaling.read().await;
-        let Some(peers) = signaling.signing_servers.get(signing_pubkey) else {
+        let Some(peers) = self.routes.signing_servers.get(signing_pubkey) else {
             return;
         };
 
@@ -124,8 +169,8 @@ impl AppState {
             return;
         };
 
-        for peer_id in peers {
-            if let Some(sender) = signaling.peer_senders.get(peer_id) {
+        for peer_id in &peers {
+            if let Some(sender) = self.routes.sender(peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -144,8 +189,6 @@ impl AppState {
             return;
         };
 
-        // Need to get peer_senders from signaling
-        let signaling = self.signaling.read().await;
         for peer in peers {
             // Skip excluded peer
             if let Some(excluded) = exclude_peer {
@@ -154,7 +197,7 @@ impl AppState {
                 }
             }
 
-            if let Some(sender) = signaling.peer_senders.get(&peer.peer_id) {
+            if let Some(sender) = self.routes.sender(&peer.peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -172,15 +215,13 @@ impl AppState {
             return None;
         }
 
-        let signaling = self.signaling.read().await;
-        signaling.peer_senders.get(peer_id).cloned()
+        self.routes.sender(peer_id)
     }
 
     /// Broadcast voice presence update to all presence connections for a server.
     /// This coordinates between VoiceState and SignalingState.
     pub async fn broadcast_voice_presence(&self, signing_pubkey: &SigningPubkey, user_id: &str, chat_id: &str, in_voice: bool) {
-        let signaling = self.signaling.read().await;
-        let Some(peers) = signaling.signing_servers.get(signing_pubkey) else {
+        let Some(peers) = self.routes.signing_servers.get(signing_pubkey) else {
             return;
         };
 
@@ -196,8 +237,8 @@ impl AppState {
         };
 
         // Send to all peer connections subscribed to this server (same mechanism as presence updates)
-        for peer_id in peers {
-            if let Some(sender) = signaling.peer_senders.get(peer_id) {
+        for peer_id in &peers {
+            if let Some(sender) = self.routes.sender(peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -205,8 +246,7 @@ impl AppState {
 
     /// Broadcast a presence update to all peers that have this user_id in their friend list.
     pub async fn broadcast_friend_presence_update(&self, user_id: &str, online: bool, active: Option<SigningPubkey>) {
-        let signaling = self.signaling.read().await;
-        let Some(peers) = signaling.friend_presence_subscribers.get(user_id) else {
+        let Some(peers) = self.routes.friend_presence_subscribers.get(&user_id.to_string()) else {
             return;
         };
 
@@ -221,8 +261,8 @@ impl AppState {
             return;
         };
 
-        for peer_id in peers {
-            if let Some(sender) = signaling.peer_senders.get(peer_id) {
+        for peer_id in &peers {
+            if let Some(sender) = self.routes.sender(peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -230,8 +270,7 @@ impl AppState {
 
     /// Broadcast a profile update to all peers that have this user_id in their friend list.
     pub async fn broadcast_profile_update_to_friends(&self, user_id: &str, rec: &ProfileRecord) {
-        let signaling = self.signaling.read().await;
-        let Some(peers) = signaling.friend_presence_subscribers.get(user_id) else {
+        let Some(peers) = self.routes.friend_presence_subscribers.get(&user_id.to_string()) else {
             return;
         };
 
@@ -248,8 +287,8 @@ impl AppState {
             return;
         };
 
-        for peer_id in peers {
-            if let Some(sender) = signaling.peer_senders.get(peer_id) {
+        for peer_id in &peers {
+            if let Some(sender) = self.routes.sender(peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -0,0 +1,151 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Number of shards per map; keys are spread across them by hash.
+pub const SHARD_COUNT: usize = 16;
+
+/// Lock acquisitions on a sharded map and how many of them had to wait for another holder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Contention {
+    pub acquisitions: u64,
+    pub contended: u64,
+}
+
+impl std::ops::Add for Contention {
+    type Output = Contention;
+
+    fn add(self, other: Contention) -> Contention {
+        Contention {
+            acquisitions: self.acquisitions + other.acquisitions,
+            contended: self.contended + other.contended,
+        }
+    }
+}
+
+/// HashMap split into `SHARD_COUNT` independently locked shards, so lookups for different keys
+/// don't queue behind each other. Guards are never held across an await.
+pub struct ShardedMap<K, V> {
+    shards: Vec<RwLock<HashMap<K, V>>>,
+    hasher: RandomState,
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> ShardedMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARD_COUNT).map(|_| RwLock::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, key: &K) -> &RwLock<HashMap<K, V>> {
+        &self.shards[self.hasher.hash_one(key) as usize % SHARD_COUNT]
+    }
+
+    fn read<'a>(&self, shard: &'a RwLock<HashMap<K, V>>) -> RwLockReadGuard<'a, HashMap<K, V>> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match shard.try_read() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                shard.read().unwrap_or_else(|e| e.into_inner())
+            }
+        }
+    }
+
+    fn write<'a>(&self, shard: &'a RwLock<HashMap<K, V>>) -> RwLockWriteGuard<'a, HashMap<K, V>> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match shard.try_write() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                shard.write().unwrap_or_else(|e| e.into_inner())
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.read(self.shard(key)).get(key).cloned()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.read(self.shard(key)).contains_key(key)
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let shard = self.shard(&key);
+        self.write(shard).insert(key, value)
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.write(self.shard(key)).remove(key)
+    }
+
+    /// Runs `f` on the shard holding `key` under its write lock, for read-modify-write
+    /// updates that must not interleave with another writer on that key.
+    pub fn update<R>(&self, key: &K, f: impl FnOnce(&mut HashMap<K, V>) -> R) -> R {
+        f(&mut self.write(self.shard(key)))
+    }
+
+    /// Snapshot of all keys; shards are locked one at a time.
+    pub fn keys(&self) -> Vec<K> {
+        self.shards.iter().flat_map(|shard| self.read(shard).keys().cloned().collect::<Vec<_>>()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| self.read(shard).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contention(&self) -> Contention {
+        Contention {
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_lookups_that_wait_on_a_held_shard() {
+        let map: ShardedMap<String, u32> = ShardedMap::new();
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+        assert_eq!(map.get(&"a".to_string()), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.contention().contended, 0);
+
+        let key = "a".to_string();
+        std::thread::scope(|s| {
+            let guard = map.shard(&key).write().unwrap();
+            let reader = s.spawn(|| map.get(&key));
+            while map.contention().contended == 0 {
+                std::thread::yield_now();
+            }
+            drop(guard);
+            assert_eq!(reader.join().unwrap(), Some(1));
+        });
+        assert_eq!(map.contention().contended, 1);
+
+        assert_eq!(map.remove(&"a".to_string()), Some(1));
+        assert!(!map.contains_key(&"a".to_string()));
+    }
+}
@@ -1,55 +1,91 @@
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::Arc;
+use crate::state::shards::{Contention, ShardedMap};
 use crate::{PeerId, ServerId, SigningPubkey, WebSocketSender, ConnId, PeerConnection, EncryptedServerHint, SignalingMessage};
 use tokio_tungstenite::tungstenite::Message;
+use sha2::{Digest, Sha256};
 
 /// Synthetic peer_id prefix for friend-scoped presence subscriptions (one per connection).
 pub const FRIENDS_PEER_PREFIX: &str = "friends:";
 /// Signing pubkey value used for friend-scoped presence/profile messages (client merges by user_id).
 pub const FRIENDS_SIGNING_PUBKEY: &str = "_friends";
+/// Prefix of the message a client signs to answer an AuthChallenge (followed by the nonce).
+pub const AUTH_CONTEXT: &str = "cordia-signaling-auth:";
 
-/// WebSocket signaling state (peer ↔ peer)
-pub struct SignalingState {
-    /// Map of peer_id -> PeerConnection
-    pub peers: HashMap<PeerId, PeerConnection>,
+/// A registered peer: the connection that owns it (none for a connection's `friends:`
+/// subscription) and where to send its messages. One entry holds both, so a peer is never
+/// seen with an owner but no sender or the other way round.
+#[derive(Clone)]
+pub struct Route {
+    pub peer: Option<PeerConnection>,
+    pub sender: WebSocketSender,
+}
+
+/// Routing tables, sharded and shared with AppState so forwarding and broadcasts don't take
+/// the SignalingState lock. Only SignalingState writes them, under its write lock, so checking
+/// and applying a registration is one step. A peer's route goes in before it joins any set
+/// and comes out before it leaves them; a set can briefly name a peer with no route, which
+/// readers skip.
+#[derive(Clone, Default)]
+pub struct PeerRoutes {
+    /// Map of peer_id -> owner and sender
+    pub peers: Arc<ShardedMap<PeerId, Route>>,
     /// Map of server_id -> set of peer_ids in that server
-    pub servers: HashMap<ServerId, HashSet<PeerId>>,
+    pub servers: Arc<ShardedMap<ServerId, HashSet<PeerId>>>,
     /// Map of signing_pubkey -> set of peer_ids subscribed to that server
-    pub signing_servers: HashMap<SigningPubkey, HashSet<PeerId>>,
-    /// Map of peer_id -> WebSocket sender (for message forwarding)
-    pub peer_senders: HashMap<PeerId, WebSocketSender>,
+    pub signing_servers: Arc<ShardedMap<SigningPubkey, HashSet<PeerId>>>,
     /// Map of conn_id -> peer_ids registered on that websocket connection (allows correct cleanup)
-    pub conn_peers: HashMap<ConnId, HashSet<PeerId>>,
+    pub conn_peers: Arc<ShardedMap<ConnId, HashSet<PeerId>>>,
     /// Friend presence: conn_id -> set of user_ids this connection cares about (for cleanup on disconnect)
-    pub conn_friend_ids: HashMap<ConnId, HashSet<String>>,
+    pub conn_friend_ids: Arc<ShardedMap<ConnId, HashSet<String>>>,
     /// Friend presence: target user_id -> set of peer_ids (friends:conn_id) that want this user's presence
-    pub friend_presence_subscribers: HashMap<String, HashSet<PeerId>>,
+    pub friend_presence_subscribers: Arc<ShardedMap<String, HashSet<PeerId>>>,
 }
 
-impl SignalingState {
-    pub fn new() -> Self {
-        Self {
-            peers: HashMap::new(),
-            servers: HashMap::new(),
-            signing_servers: HashMap::new(),
-            peer_senders: HashMap::new(),
-            conn_peers: HashMap::new(),
-            conn_friend_ids: HashMap::new(),
-            friend_presence_subscribers: HashMap::new(),
+fn add_member<K: Hash + Eq + Clone>(map: &ShardedMap<K, HashSet<String>>, key: &K, member: &str) {
+    map.update(key, |shard| {
+        shard.entry(key.clone()).or_default().insert(member.to_string());
+    });
+}
+
+/// Removes `member` from the set at `key`, dropping the set once it's empty.
+fn remove_member<K: Hash + Eq + Clone>(map: &ShardedMap<K, HashSet<String>>, key: &K, member: &str) {
+    map.update(key, |shard| {
+        if let Some(members) = shard.get_mut(key) {
+            members.remove(member);
+            if members.is_empty() {
+                shard.remove(key);
+            }
         }
+    });
+}
+
+impl PeerRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The connection that registered `peer_id`.
+    pub fn peer(&self, peer_id: &PeerId) -> Option<PeerConnection> {
+        self.peers.get(peer_id).and_then(|route| route.peer)
+    }
+
+    pub fn sender(&self, peer_id: &PeerId) -> Option<WebSocketSender> {
+        self.peers.get(peer_id).map(|route| route.sender)
     }
 
     /// Validates that a peer_id belongs to the connection sending the message.
     /// This enforces connection identity consistency, not authorization.
     /// Returns true if the peer_id is registered and belongs to the given conn_id.
     pub fn validate_peer_connection(&self, peer_id: &PeerId, conn_id: &ConnId) -> bool {
-        match self.peers.get(peer_id) {
+        match self.peer(peer_id) {
             Some(peer) => peer.conn_id == *conn_id,
             None => false,
         }
     }
 
     /// Validates from_peer belongs to conn_id and returns the sender for to_peer.
-    /// Single lock instead of validate-then-unlock-then-lock-again for forwarding.
     /// Returns Err(()) if from_peer is invalid; Ok(None) if valid but to_peer not found; Ok(Some(sender)) if found.
     pub fn validate_and_get_target_sender(
         &self,
@@ -60,109 +96,58 @@ impl SignalingState {
         if !self.validate_peer_connection(from_peer, conn_id) {
             return Err(());
         }
-        Ok(self.peer_senders.get(to_peer).cloned())
+        Ok(self.sender(to_peer))
     }
 
-    pub fn register_peer(
-        &mut self,
-        peer_id: PeerId,
-        server_id: ServerId,
-        signing_pubkey: Option<SigningPubkey>,
-        conn_id: ConnId,
-    ) -> Vec<PeerId> {
-        // Add peer connection
-        self.peers.insert(
-            peer_id.clone(),
-            PeerConnection {
-                peer_id: peer_id.clone(),
-                server_id: server_id.clone(),
-                signing_pubkey: signing_pubkey.clone(),
-                conn_id: conn_id.clone(),
-            },
-        );
-
-        // Track peer_id for this connection (fixes memory leaks when socket dies)
-        self.conn_peers
-            .entry(conn_id)
-            .or_insert_with(HashSet::new)
-            .insert(peer_id.clone());
-
-        // Add peer to server
-        let peers_in_server = self.servers.entry(server_id.clone()).or_insert_with(HashSet::new);
-        peers_in_server.insert(peer_id.clone());
+    /// Shard lock waits across all the tables, reported on `/api/status`.
+    pub fn contention(&self) -> Contention {
+        self.peers.contention()
+            + self.servers.contention()
+            + self.signing_servers.contention()
+            + self.conn_peers.contention()
+            + self.conn_friend_ids.contention()
+            + self.friend_presence_subscribers.contention()
+    }
 
-        // If a signing_pubkey was provided, treat this peer as subscribed for server-hint broadcasts
-        if let Some(spk) = signing_pubkey {
-            self.signing_servers.entry(spk).or_insert_with(HashSet::new).insert(peer_id.clone());
-        }
+    pub fn broadcast_server_hint_updated(&self, signing_pubkey: &SigningPubkey, hint: &EncryptedServerHint) {
+        let Some(peers) = self.signing_servers.get(signing_pubkey) else {
+            return;
+        };
 
-        // Return other peers in the same server
-        peers_in_server
-            .iter()
-            .filter(|p| **p != peer_id)
-            .cloned()
-            .collect()
-    }
+        let msg = SignalingMessage::ServerHintUpdated {
+            signing_pubkey: signing_pubkey.clone(),
+            encrypted_state: hint.encrypted_state.clone(),
+            signature: hint.signature.clone(),
+            last_updated: hint.last_updated,
+        };
 
-    pub fn unregister_peer(&mut self, peer_id: &PeerId) {
-        // Friend-scoped synthetic peer: clean up conn_friend_ids and friend_presence_subscribers
-        if peer_id.starts_with(FRIENDS_PEER_PREFIX) {
-            let conn_id = peer_id.strip_prefix(FRIENDS_PEER_PREFIX).unwrap_or(peer_id);
-            if let Some(user_ids) = self.conn_friend_ids.remove(conn_id) {
-                for uid in &user_ids {
-                    if let Some(subs) = self.friend_presence_subscribers.get_mut(uid) {
-                        subs.remove(peer_id);
-                        if subs.is_empty() {
-                            self.friend_presence_subscribers.remove(uid);
-                        }
-                    }
-                }
-            }
-        } else if let Some(conn) = self.peers.remove(peer_id) {
-            // Remove from server list
-            if let Some(peers_in_server) = self.servers.get_mut(&conn.server_id) {
-                peers_in_server.remove(peer_id);
-                if peers_in_server.is_empty() {
-                    self.servers.remove(&conn.server_id);
-                }
-            }
+        let Ok(json) = serde_json::to_string(&msg) else {
+            return;
+        };
 
-            // Remove from signing server list (if subscribed)
-            if let Some(spk) = conn.signing_pubkey {
-                if let Some(peers_for_signing) = self.signing_servers.get_mut(&spk) {
-                    peers_for_signing.remove(peer_id);
-                    if peers_for_signing.is_empty() {
-                        self.signing_servers.remove(&spk);
-                    }
-                }
+        for peer_id in &peers {
+            if let Some(sender) = self.sender(peer_id) {
+                let _ = sender.send(Message::Text(json.clone()));
             }
         }
-        // Remove WebSocket sender
-        self.peer_senders.remove(peer_id);
-    }
-
-    pub fn get_server(&self, peer_id: &PeerId) -> Option<ServerId> {
-        self.peers.get(peer_id).map(|c| c.server_id.clone())
     }
 
-    pub fn broadcast_server_hint_updated(&self, signing_pubkey: &SigningPubkey, hint: &EncryptedServerHint) {
+    pub fn broadcast_server_member_left(&self, signing_pubkey: &SigningPubkey, user_id: &str) {
         let Some(peers) = self.signing_servers.get(signing_pubkey) else {
             return;
         };
 
-        let msg = SignalingMessage::ServerHintUpdated {
+        let msg = SignalingMessage::ServerMemberLeft {
             signing_pubkey: signing_pubkey.clone(),
-            encrypted_state: hint.encrypted_state.clone(),
-            signature: hint.signature.clone(),
-            last_updated: hint.last_updated,
+            user_id: user_id.to_string(),
         };
 
         let Ok(json) = serde_json::to_string(&msg) else {
             return;
         };
 
-        for peer_id in peers {
-            if let Some(sender) = self.peer_senders.get(peer_id) {
+        for peer_id in &peers {
+            if let Some(sender) = self.sender(peer_id) {
                 let _ = sender.send(Message::Text(json.clone()));
             }
         }
@@ -183,21 +168,384 @@ impl SignalingState {
         };
 
         // A single websocket connection can have multiple peer_ids; fan-out once per conn_id.
-        let mut sent_conn_ids: HashSet<&str> = HashSet::new();
+        let mut sent_conn_ids: HashSet<ConnId> = HashSet::new();
 
-        for peer_id in peers {
-            let Some(peer) = self.peers.get(peer_id) else {
+        for peer_id in &peers {
+            let Some(Route { peer: Some(peer), sender }) = self.peers.get(peer_id) else {
                 continue;
             };
             if exclude_conn_id.is_some_and(|cid| peer.conn_id == *cid) {
                 continue;
             }
-            if !sent_conn_ids.insert(peer.conn_id.as_str()) {
+            if !sent_conn_ids.insert(peer.conn_id) {
                 continue;
             }
-            if let Some(sender) = self.peer_senders.get(peer_id) {
-                let _ = sender.send(Message::Text(json.clone()));
+            let _ = sender.send(Message::Text(json.clone()));
+        }
+    }
+}
+
+/// WebSocket signaling state (peer ↔ peer)
+pub struct SignalingState {
+    /// Routing tables (sharded, also reachable without this lock via AppState)
+    pub routes: PeerRoutes,
+    /// Map of conn_id -> nonce sent in that connection's AuthChallenge
+    pub conn_nonces: HashMap<ConnId, String>,
+    /// Map of conn_id -> public keys (hex) that proved ownership on that connection
+    pub conn_identities: HashMap<ConnId, HashSet<String>>,
+    /// Map of conn_id -> protocol agreed in Hello/Welcome (absent = protocol 1 client)
+    pub conn_protocols: HashMap<ConnId, u32>,
+}
+
+impl SignalingState {
+    pub fn new() -> Self {
+        Self {
+            routes: PeerRoutes::new(),
+            conn_nonces: HashMap::new(),
+            conn_identities: HashMap::new(),
+            conn_protocols: HashMap::new(),
+        }
+    }
+
+    /// Picks the newest of the client's versions within min..=max and records it for the
+    /// connection. None if they don't overlap.
+    pub fn negotiate_protocol(&mut self, conn_id: &ConnId, offered: &[u32], min: u32, max: u32) -> Option<u32> {
+        let version = offered.iter().copied().filter(|v| (min..=max).contains(v)).max()?;
+        self.conn_protocols.insert(conn_id.clone(), version);
+        Some(version)
+    }
+
+    pub fn protocol_for(&self, conn_id: &ConnId) -> Option<u32> {
+        self.conn_protocols.get(conn_id).copied()
+    }
+
+    /// Creates the nonce a new connection must sign before it may register.
+    pub fn issue_challenge(&mut self, conn_id: &ConnId) -> String {
+        let nonce = hex::encode(rand::random::<[u8; 32]>());
+        self.conn_nonces.insert(conn_id.clone(), nonce.clone());
+        nonce
+    }
+
+    /// Verifies a signature over this connection's nonce and binds the key to the connection.
+    /// A connection may authenticate several keys (e.g. a user identity and a server signing key).
+    /// Returns the user_id derived from the key.
+    pub fn authenticate(&mut self, conn_id: &ConnId, public_key: &str, signature: &str) -> Result<String, String> {
+        let nonce = self
+            .conn_nonces
+            .get(conn_id)
+            .ok_or_else(|| "No authentication challenge for this connection".to_string())?;
+        let key_bytes: [u8; 32] = hex::decode(public_key)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| "Invalid public key".to_string())?;
+        let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|_| "Invalid public key".to_string())?;
+        let sig_bytes: [u8; 64] = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, signature)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| "Invalid signature".to_string())?;
+        let message = format!("{}{}", AUTH_CONTEXT, nonce);
+        verifying_key
+            .verify_strict(message.as_bytes(), &ed25519_dalek::Signature::from_bytes(&sig_bytes))
+            .map_err(|_| "Authentication failed: bad signature".to_string())?;
+
+        let public_key = public_key.to_lowercase();
+        self.conn_identities
+            .entry(conn_id.clone())
+            .or_default()
+            .insert(public_key.clone());
+        Ok(user_id_for_key(&public_key))
+    }
+
+    /// True once the connection has proven ownership of at least one key.
+    pub fn is_authenticated(&self, conn_id: &ConnId) -> bool {
+        self.conn_identities.get(conn_id).is_some_and(|keys| !keys.is_empty())
+    }
+
+    /// True if `user_id` belongs to a key authenticated on this connection.
+    pub fn is_authenticated_user(&self, conn_id: &ConnId, user_id: &str) -> bool {
+        self.conn_identities
+            .get(conn_id)
+            .is_some_and(|keys| keys.iter().any(|k| user_id_for_key(k) == user_id))
+    }
+
+    /// Checks that an authenticated connection may register `peer_id`: a peer already
+    /// registered elsewhere can only be taken over by a connection that proved one of the same
+    /// keys (a reconnect), so one user can't claim another's peer and receive their offers.
+    pub fn check_register(&self, conn_id: &ConnId, peer_id: &PeerId) -> Result<(), String> {
+        if !self.is_authenticated(conn_id) {
+            return Err("Not authenticated: answer the AuthChallenge before Register".to_string());
+        }
+        if peer_id.starts_with(FRIENDS_PEER_PREFIX) {
+            return Err(format!("peer_id {} is reserved", peer_id));
+        }
+        let Some(peer) = self.routes.peer(peer_id) else {
+            return Ok(());
+        };
+        let same_identity = peer.conn_id == *conn_id
+            || match (self.conn_identities.get(conn_id), self.conn_identities.get(&peer.conn_id)) {
+                (Some(ours), Some(theirs)) => !ours.is_disjoint(theirs),
+                _ => false,
+            };
+        if !same_identity {
+            return Err(format!("peer_id {} is registered to another identity", peer_id));
+        }
+        Ok(())
+    }
+
+    /// Drops the challenge, authenticated keys and protocol of a closed connection.
+    pub fn remove_conn_state(&mut self, conn_id: &ConnId) {
+        self.conn_nonces.remove(conn_id);
+        self.conn_identities.remove(conn_id);
+        self.conn_protocols.remove(conn_id);
+    }
+
+    /// Registers `peer_id` on `conn_id`, replacing any earlier registration of it (a
+    /// reconnect taking it over), and returns the other peers in the server.
+    pub fn register_peer(
+        &mut self,
+        peer_id: PeerId,
+        server_id: ServerId,
+        signing_pubkey: Option<SigningPubkey>,
+        conn_id: ConnId,
+        sender: WebSocketSender,
+    ) -> Vec<PeerId> {
+        let peer = PeerConnection {
+            peer_id: peer_id.clone(),
+            server_id: server_id.clone(),
+            signing_pubkey: signing_pubkey.clone(),
+            conn_id: conn_id.clone(),
+        };
+        let previous = self.routes.peers.insert(peer_id.clone(), Route { peer: Some(peer), sender });
+        if let Some(previous) = previous.and_then(|route| route.peer) {
+            self.leave_sets(&peer_id, &previous);
+        }
+
+        // Track peer_id for this connection (fixes memory leaks when socket dies)
+        add_member(&self.routes.conn_peers, &conn_id, &peer_id);
+        add_member(&self.routes.servers, &server_id, &peer_id);
+        // If a signing_pubkey was provided, treat this peer as subscribed for server-hint broadcasts
+        if let Some(spk) = &signing_pubkey {
+            add_member(&self.routes.signing_servers, spk, &peer_id);
+        }
+
+        // Return other peers in the same server
+        self.routes
+            .servers
+            .get(&server_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| *p != peer_id)
+            .collect()
+    }
+
+    /// Takes a peer out of its server's sets and its connection's; the route is left alone.
+    fn leave_sets(&self, peer_id: &PeerId, peer: &PeerConnection) {
+        remove_member(&self.routes.servers, &peer.server_id, peer_id);
+        if let Some(spk) = &peer.signing_pubkey {
+            remove_member(&self.routes.signing_servers, spk, peer_id);
+        }
+        remove_member(&self.routes.conn_peers, &peer.conn_id, peer_id);
+    }
+
+    /// Points a connection's `friends:` peer at `friend_ids`, replacing its previous list.
+    pub fn subscribe_friends(&mut self, conn_id: &ConnId, friend_ids: HashSet<String>, sender: WebSocketSender) {
+        let friends_peer_id = format!("{}{}", FRIENDS_PEER_PREFIX, conn_id);
+        self.unsubscribe_friends(conn_id, &friends_peer_id);
+        self.routes.peers.insert(friends_peer_id.clone(), Route { peer: None, sender });
+        add_member(&self.routes.conn_peers, conn_id, &friends_peer_id);
+        for uid in &friend_ids {
+            add_member(&self.routes.friend_presence_subscribers, uid, &friends_peer_id);
+        }
+        self.routes.conn_friend_ids.insert(conn_id.clone(), friend_ids);
+    }
+
+    fn unsubscribe_friends(&self, conn_id: &ConnId, friends_peer_id: &PeerId) {
+        for uid in self.routes.conn_friend_ids.remove(conn_id).unwrap_or_default() {
+            remove_member(&self.routes.friend_presence_subscribers, &uid, friends_peer_id);
+        }
+    }
+
+    /// Unregisters the peers of a closed connection and returns their ids. Peers another
+    /// connection has taken over since stay with that connection.
+    pub fn unregister_conn(&mut self, conn_id: &ConnId) -> Vec<PeerId> {
+        let peer_ids = self.routes.conn_peers.remove(conn_id).unwrap_or_default();
+        let mut removed = Vec::new();
+        for peer_id in peer_ids {
+            if peer_id.strip_prefix(FRIENDS_PEER_PREFIX) == Some(conn_id.as_str()) {
+                self.routes.peers.remove(&peer_id);
+                self.unsubscribe_friends(conn_id, &peer_id);
+            } else if self.routes.validate_peer_connection(&peer_id, conn_id) {
+                if let Some(peer) = self.routes.peers.remove(&peer_id).and_then(|route| route.peer) {
+                    self.leave_sets(&peer_id, &peer);
+                }
+            } else {
+                continue;
             }
+            removed.push(peer_id);
         }
+        removed
+    }
+
+    pub fn get_server(&self, peer_id: &PeerId) -> Option<ServerId> {
+        self.routes.peer(peer_id).map(|c| c.server_id)
+    }
+}
+
+/// Client user_id for an identity key: first 16 bytes of SHA-256 of the public key, hex.
+pub fn user_id_for_key(public_key_hex: &str) -> String {
+    let Ok(bytes) = hex::decode(public_key_hex) else {
+        return String::new();
+    };
+    hex::encode(&Sha256::digest(&bytes)[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer;
+
+    #[test]
+    fn authenticate_requires_signature_over_connection_nonce() {
+        let mut state = SignalingState::new();
+        let conn_a = "conn-a".to_string();
+        let conn_b = "conn-b".to_string();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let public_key = hex::encode(key.verifying_key().to_bytes());
+        let sign = |nonce: &str| {
+            let sig = key.sign(format!("{}{}", AUTH_CONTEXT, nonce).as_bytes());
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, sig.to_bytes())
+        };
+
+        assert!(state.authenticate(&conn_a, &public_key, &sign("x")).is_err());
+
+        let nonce_a = state.issue_challenge(&conn_a);
+        let nonce_b = state.issue_challenge(&conn_b);
+        assert_ne!(nonce_a, nonce_b);
+
+        // A signature for another connection's nonce doesn't carry over.
+        assert!(state.authenticate(&conn_a, &public_key, &sign(&nonce_b)).is_err());
+        assert!(!state.is_authenticated(&conn_a));
+
+        let user_id = state.authenticate(&conn_a, &public_key, &sign(&nonce_a)).unwrap();
+        assert_eq!(user_id.len(), 32);
+        assert!(state.is_authenticated(&conn_a));
+        assert!(state.is_authenticated_user(&conn_a, &user_id));
+        assert!(!state.is_authenticated_user(&conn_b, &user_id));
+
+        state.remove_conn_state(&conn_a);
+        assert!(!state.is_authenticated(&conn_a));
+    }
+
+    #[test]
+    fn register_keeps_peers_with_the_identity_that_registered_them() {
+        let mut state = SignalingState::new();
+        let key_for = |seed: u8| ed25519_dalek::SigningKey::from_bytes(&[seed; 32]);
+        let mut login = |conn: &str, key: &ed25519_dalek::SigningKey| {
+            let conn = conn.to_string();
+            let nonce = state.issue_challenge(&conn);
+            let sig = key.sign(format!("{}{}", AUTH_CONTEXT, nonce).as_bytes());
+            let sig = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, sig.to_bytes());
+            state.authenticate(&conn, &hex::encode(key.verifying_key().to_bytes()), &sig).unwrap();
+        };
+        let (alice, mallory) = (key_for(1), key_for(2));
+        login("alice-1", &alice);
+        login("alice-2", &alice);
+        login("mallory", &mallory);
+
+        let peer = "server-sync:alice:s1".to_string();
+        assert!(state.check_register(&"nobody".to_string(), &peer).is_err());
+        assert!(state.check_register(&"alice-1".to_string(), &peer).is_ok());
+        state.register_peer(peer.clone(), "s1".to_string(), None, "alice-1".to_string(), tokio::sync::mpsc::unbounded_channel().0);
+
+        assert!(state.check_register(&"alice-1".to_string(), &peer).is_ok());
+        // Same key on a new connection (a reconnect) may take it over; another key may not.
+        assert!(state.check_register(&"alice-2".to_string(), &peer).is_ok());
+        assert!(state.check_register(&"mallory".to_string(), &peer).is_err());
+        assert!(state.check_register(&"mallory".to_string(), &"friends:alice-1".to_string()).is_err());
+    }
+
+    #[test]
+    fn old_connection_cleanup_leaves_a_taken_over_peer_alone() {
+        let mut state = SignalingState::new();
+        let (old_conn, new_conn) = ("alice-1".to_string(), "alice-2".to_string());
+        let peer = "server-sync:alice:s1".to_string();
+        let spk = "spk".to_string();
+        let sender = || tokio::sync::mpsc::unbounded_channel().0;
+
+        state.register_peer(peer.clone(), "s1".to_string(), Some(spk.clone()), old_conn.clone(), sender());
+        state.subscribe_friends(&old_conn, HashSet::from(["bob".to_string()]), sender());
+        state.register_peer(peer.clone(), "s1".to_string(), Some(spk.clone()), new_conn.clone(), sender());
+
+        assert_eq!(state.unregister_conn(&old_conn), vec!["friends:alice-1".to_string()]);
+        assert!(state.routes.validate_peer_connection(&peer, &new_conn));
+        assert_eq!(state.routes.servers.get(&"s1".to_string()), Some(HashSet::from([peer.clone()])));
+        assert_eq!(state.routes.signing_servers.get(&spk), Some(HashSet::from([peer.clone()])));
+        assert!(state.routes.friend_presence_subscribers.is_empty());
+
+        assert_eq!(state.unregister_conn(&new_conn), vec![peer]);
+        assert!(state.routes.peers.is_empty());
+        assert!(state.routes.servers.is_empty());
+        assert!(state.routes.signing_servers.is_empty());
+        assert!(state.routes.conn_peers.is_empty());
+    }
+
+    #[test]
+    fn readers_never_see_a_half_registered_peer() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        let mut state = SignalingState::new();
+        let routes = state.routes.clone();
+        let done = AtomicBool::new(false);
+        let seen = AtomicUsize::new(0);
+        let spk = "spk".to_string();
+        let peer = "server-sync:alice:s1".to_string();
+
+        std::thread::scope(|s| {
+            let reader = s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    for peer_id in routes.signing_servers.get(&spk).unwrap_or_default() {
+                        // A set member may already be unrouted, but a route is always whole.
+                        if let Some(route) = routes.peers.get(&peer_id) {
+                            let owner = route.peer.expect("server peer routed without an owner");
+                            assert_eq!(owner.signing_pubkey.as_ref(), Some(&spk));
+                            assert!(owner.conn_id.starts_with("conn-"));
+                            seen.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+
+            let mut i = 0;
+            while seen.load(Ordering::Relaxed) < 1_000 {
+                assert!(i < 10_000_000, "reader never saw a route");
+                let conn = format!("conn-{}", i % 3);
+                state.register_peer(peer.clone(), "s1".to_string(), Some(spk.clone()), conn.clone(), tokio::sync::mpsc::unbounded_channel().0);
+                if i % 2 == 1 {
+                    state.unregister_conn(&conn);
+                }
+                i += 1;
+            }
+            for i in 0..3 {
+                state.unregister_conn(&format!("conn-{}", i));
+            }
+            done.store(true, Ordering::Relaxed);
+            reader.join().unwrap();
+        });
+
+        assert!(state.routes.peers.is_empty());
+        assert!(state.routes.servers.is_empty());
+        assert!(state.routes.signing_servers.is_empty());
+        assert!(state.routes.conn_peers.is_empty());
+    }
+
+    #[test]
+    fn negotiates_newest_common_protocol() {
+        let mut state = SignalingState::new();
+        let conn = "conn-a".to_string();
+        assert_eq!(state.negotiate_protocol(&conn, &[3, 2, 1], 1, 2), Some(2));
+        assert_eq!(state.protocol_for(&conn), Some(2));
+        assert_eq!(state.negotiate_protocol(&"conn-b".to_string(), &[1], 2, 2), None);
+        assert_eq!(state.protocol_for(&"conn-b".to_string()), None);
     }
 }
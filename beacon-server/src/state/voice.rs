@@ -25,6 +25,14 @@ impl VoiceState {
         }
     }
 
+    /// Signing key of the server whose `chat_id` voice room holds `peer_id`.
+    pub fn room_key_of(&self, peer_id: &str, chat_id: &str) -> Option<SigningPubkey> {
+        self.voice_chats
+            .iter()
+            .find(|((_, c), peers)| c == chat_id && peers.iter().any(|p| p.peer_id == peer_id))
+            .and_then(|((server_id, _), _)| self.server_signing_pubkeys.get(server_id).cloned())
+    }
+
     /// Register a peer for voice in a specific chat.
     /// Returns list of other peers in the chat.
     pub fn register_voice_peer(
@@ -100,3 +108,22 @@ impl VoiceState {
         removed
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_key_matches_servers_by_signing_key_across_local_ids() {
+        let mut state = VoiceState::new();
+        state.register_voice_peer("p1".into(), "u1".into(), "local-a".into(), "chat".into(), "c1".into());
+        state.register_voice_peer("p2".into(), "u2".into(), "local-b".into(), "chat".into(), "c2".into());
+        state.server_signing_pubkeys.insert("local-a".into(), "spk".into());
+        state.server_signing_pubkeys.insert("local-b".into(), "spk".into());
+
+        assert_eq!(state.room_key_of("p1", "chat"), Some("spk".to_string()));
+        assert_eq!(state.room_key_of("p2", "chat"), Some("spk".to_string()));
+        assert_eq!(state.room_key_of("p1", "other"), None);
+        assert_eq!(state.room_key_of("p3", "chat"), None);
+    }
+}
@@ -39,6 +39,9 @@ pub struct AccountInfo {
     pub created_at: String,
     #[serde(default)]
     pub signaling_server_url: Option<String>,
+    /// Beacons to fail over to, in order, when the signaling server is unreachable
+    #[serde(default)]
+    pub fallback_beacon_urls: Vec<String>,
 }
 
 /// Cached profile data for a remote user (display name, avatar, etc.) persisted per account.
@@ -283,6 +286,7 @@ impl AccountManager {
             display_name: display_name.to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
             signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
         };
         self.save_account_info(&info)?;
 
@@ -311,6 +315,9 @@ impl AccountManager {
             self.clear_session()?;
         }
 
+        // Keychain entries first: the list of app secret names lives in the directory
+        crate::secrets::delete_account_secrets(account_id);
+
         // Remove the entire account directory
         if account_dir.exists() {
             fs::remove_dir_all(&account_dir)?;
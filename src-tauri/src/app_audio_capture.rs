@@ -0,0 +1,361 @@
+//! Per-application audio capture (Windows ApplicationLoopback API).
+//!
+//! Captures only the audio rendered by one process tree (a game, a browser) instead of
+//! the whole desktop mix. Windows 10 2004+ only; other platforms report unsupported.
+//! The OS converts to our format (48 kHz mono f32) so no resampling is needed; frames are
+//! re-sliced to 10 ms and sent through a bounded channel (drop if full), like loopback.
+
+use crate::audio_capture::{monotonic_micros, try_send_frame, ProcessedFrame};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// Process-capture frames dropped because the emitter was behind.
+static DROPPED_APP_CAPTURE: AtomicU64 = AtomicU64::new(0);
+
+/// A process currently producing audio (has an audio session on the default render device).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioApplication {
+    pub pid: u32,
+    /// Executable name, e.g. "firefox.exe".
+    pub name: String,
+}
+
+struct AppCaptureState {
+    stop: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<()>>,
+}
+
+static APP_CAPTURE_STATE: Mutex<Option<AppCaptureState>> = Mutex::new(None);
+
+/// List processes with an active audio session.
+pub fn enumerate_audio_applications() -> Result<Vec<AudioApplication>, String> {
+    imp::enumerate_audio_applications()
+}
+
+/// Start capturing audio from `pid` (and its child processes). Replaces any running app capture.
+pub fn start_app_capture(pid: u32, frame_sender: mpsc::SyncSender<ProcessedFrame>) -> Result<(), String> {
+    stop_app_capture();
+    DROPPED_APP_CAPTURE.store(0, Ordering::Relaxed);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+    let stop_flag = stop.clone();
+    let capture_thread = thread::spawn(move || {
+        let mut seq: u64 = 0;
+        imp::run_process_capture(pid, &stop_flag, ready_tx, |pcm| {
+            let frame = ProcessedFrame::pooled(pcm, monotonic_micros(Instant::now()), seq);
+            seq += 1;
+            if !try_send_frame(&frame_sender, frame) {
+                DROPPED_APP_CAPTURE.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|_| "App capture thread exited unexpectedly".to_string())??;
+
+    let mut state = APP_CAPTURE_STATE
+        .lock()
+        .map_err(|_| "Failed to lock app capture state".to_string())?;
+    *state = Some(AppCaptureState {
+        stop,
+        capture_thread: Some(capture_thread),
+    });
+    Ok(())
+}
+
+/// Stop per-application capture.
+pub fn stop_app_capture() {
+    let state = match APP_CAPTURE_STATE.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return,
+    };
+    if let Some(mut state) = state {
+        state.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = state.capture_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Frames dropped since app capture started.
+pub fn dropped_app_capture_frames() -> u64 {
+    DROPPED_APP_CAPTURE.load(Ordering::Relaxed)
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::AudioApplication;
+    use crate::audio_resample::{FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc, Condvar, Mutex};
+    use windows::core::{implement, IUnknown, Interface, HRESULT, PROPVARIANT, PWSTR};
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Media::Audio::{
+        eConsole, eRender, ActivateAudioInterfaceAsync, IActivateAudioInterfaceAsyncOperation,
+        IActivateAudioInterfaceCompletionHandler, IActivateAudioInterfaceCompletionHandler_Impl,
+        IAudioCaptureClient, IAudioClient, IAudioSessionControl2, IAudioSessionManager2,
+        IMMDeviceEnumerator, MMDeviceEnumerator, AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED,
+        AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+        AUDCLNT_STREAMFLAGS_LOOPBACK, AUDIOCLIENT_ACTIVATION_PARAMS, AUDIOCLIENT_ACTIVATION_PARAMS_0,
+        AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK, AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS,
+        PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE, VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+        WAVEFORMATEX,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+    use windows::Win32::System::Threading::{
+        CreateEventW, OpenProcess, QueryFullProcessImageNameW, WaitForSingleObject, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    /// WAVE_FORMAT_IEEE_FLOAT
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+    /// VT_BLOB
+    const VT_BLOB: u16 = 65;
+    /// 100 ms shared-mode buffer, in 100 ns units.
+    const BUFFER_DURATION_HNS: i64 = 1_000_000;
+
+    /// PROPVARIANT holding a VT_BLOB. windows-core doesn't expose a blob constructor, so
+    /// this mirrors the C layout (vt + 3 reserved words, then BLOB { cbSize, pBlobData }).
+    #[repr(C)]
+    struct BlobPropVariant {
+        vt: u16,
+        reserved: [u16; 3],
+        cb_size: u32,
+        blob_data: *const u8,
+    }
+
+    #[implement(IActivateAudioInterfaceCompletionHandler)]
+    struct ActivationHandler {
+        done: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl IActivateAudioInterfaceCompletionHandler_Impl for ActivationHandler {
+        fn ActivateCompleted(
+            &self,
+            _operation: Option<&IActivateAudioInterfaceAsyncOperation>,
+        ) -> windows::core::Result<()> {
+            let (done, cvar) = &*self.done;
+            if let Ok(mut done) = done.lock() {
+                *done = true;
+            }
+            cvar.notify_all();
+            Ok(())
+        }
+    }
+
+    fn process_name(pid: u32) -> Option<String> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buf = [0u16; 260];
+            let mut len = buf.len() as u32;
+            let result = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut len);
+            let _ = CloseHandle(handle);
+            result.ok()?;
+            let path = String::from_utf16_lossy(&buf[..len as usize]);
+            path.rsplit('\\').next().map(|s| s.to_string())
+        }
+    }
+
+    pub fn enumerate_audio_applications() -> Result<Vec<AudioApplication>, String> {
+        unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+                .map_err(|e| format!("Failed to create device enumerator: {}", e))?;
+            let device = enumerator
+                .GetDefaultAudioEndpoint(eRender, eConsole)
+                .map_err(|e| format!("Failed to get default output device: {}", e))?;
+            let manager: IAudioSessionManager2 = device
+                .Activate(CLSCTX_ALL, None)
+                .map_err(|e| format!("Failed to activate session manager: {}", e))?;
+            let sessions = manager
+                .GetSessionEnumerator()
+                .map_err(|e| format!("Failed to enumerate audio sessions: {}", e))?;
+            let count = sessions.GetCount().map_err(|e| e.to_string())?;
+
+            let mut apps: Vec<AudioApplication> = Vec::new();
+            for i in 0..count {
+                let Ok(session) = sessions.GetSession(i) else { continue };
+                let Ok(session2) = session.cast::<IAudioSessionControl2>() else { continue };
+                let Ok(pid) = session2.GetProcessId() else { continue };
+                // pid 0 is the system sounds session.
+                if pid == 0 || apps.iter().any(|a| a.pid == pid) {
+                    continue;
+                }
+                if let Some(name) = process_name(pid) {
+                    apps.push(AudioApplication { pid, name });
+                }
+            }
+            Ok(apps)
+        }
+    }
+
+    unsafe fn activate_process_loopback(pid: u32) -> Result<IAudioClient, String> {
+        let params = AUDIOCLIENT_ACTIVATION_PARAMS {
+            ActivationType: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+            Anonymous: AUDIOCLIENT_ACTIVATION_PARAMS_0 {
+                ProcessLoopbackParams: AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS {
+                    TargetProcessId: pid,
+                    ProcessLoopbackMode: PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE,
+                },
+            },
+        };
+        let prop = BlobPropVariant {
+            vt: VT_BLOB,
+            reserved: [0; 3],
+            cb_size: std::mem::size_of::<AUDIOCLIENT_ACTIVATION_PARAMS>() as u32,
+            blob_data: &params as *const _ as *const u8,
+        };
+
+        let signal = Arc::new((Mutex::new(false), Condvar::new()));
+        let handler: IActivateAudioInterfaceCompletionHandler = ActivationHandler {
+            done: signal.clone(),
+        }
+        .into();
+        let operation = ActivateAudioInterfaceAsync(
+            VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+            &IAudioClient::IID,
+            Some(&prop as *const BlobPropVariant as *const PROPVARIANT),
+            &handler,
+        )
+        .map_err(|e| format!("Failed to activate process loopback: {}", e))?;
+
+        let (done, cvar) = &*signal;
+        let mut done = done.lock().map_err(|_| "Activation lock poisoned".to_string())?;
+        while !*done {
+            done = cvar.wait(done).map_err(|_| "Activation lock poisoned".to_string())?;
+        }
+
+        let mut activate_hr = HRESULT(0);
+        let mut activated: Option<IUnknown> = None;
+        operation
+            .GetActivateResult(&mut activate_hr, &mut activated)
+            .map_err(|e| format!("Process loopback activation failed: {}", e))?;
+        activate_hr
+            .ok()
+            .map_err(|e| format!("Process loopback activation failed: {}", e))?;
+        activated
+            .ok_or_else(|| "Process loopback returned no interface".to_string())?
+            .cast::<IAudioClient>()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn run_process_capture<F>(
+        pid: u32,
+        stop: &Arc<AtomicBool>,
+        ready_tx: mpsc::Sender<Result<(), String>>,
+        mut on_frame: F,
+    ) where
+        F: FnMut(&[f32; FRAME_SAMPLES]),
+    {
+        let setup = unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            (|| -> Result<(IAudioClient, IAudioCaptureClient, HANDLE), String> {
+                let client = activate_process_loopback(pid)?;
+                // Process loopback has no mix format; ask the OS to convert to ours.
+                let format = WAVEFORMATEX {
+                    wFormatTag: WAVE_FORMAT_IEEE_FLOAT,
+                    nChannels: 1,
+                    nSamplesPerSec: PIPELINE_SAMPLE_RATE,
+                    nAvgBytesPerSec: PIPELINE_SAMPLE_RATE * 4,
+                    nBlockAlign: 4,
+                    wBitsPerSample: 32,
+                    cbSize: 0,
+                };
+                client
+                    .Initialize(
+                        AUDCLNT_SHAREMODE_SHARED,
+                        AUDCLNT_STREAMFLAGS_LOOPBACK
+                            | AUDCLNT_STREAMFLAGS_EVENTCALLBACK
+                            | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+                        BUFFER_DURATION_HNS,
+                        0,
+                        &format,
+                        None,
+                    )
+                    .map_err(|e| format!("Failed to initialize process capture: {}", e))?;
+                let event = CreateEventW(None, false, false, None).map_err(|e| e.to_string())?;
+                client.SetEventHandle(event).map_err(|e| e.to_string())?;
+                let capture: IAudioCaptureClient = client.GetService().map_err(|e| e.to_string())?;
+                client.Start().map_err(|e| format!("Failed to start process capture: {}", e))?;
+                Ok((client, capture, event))
+            })()
+        };
+
+        let (client, capture, event) = match setup {
+            Ok(parts) => {
+                let _ = ready_tx.send(Ok(()));
+                parts
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+
+        let mut frame = [0.0f32; FRAME_SAMPLES];
+        let mut pos = 0usize;
+        while !stop.load(Ordering::SeqCst) {
+            unsafe {
+                // Wake at least every 100 ms to check the stop flag.
+                WaitForSingleObject(event, 100);
+                while let Ok(packet) = capture.GetNextPacketSize() {
+                    if packet == 0 {
+                        break;
+                    }
+                    let mut data: *mut u8 = std::ptr::null_mut();
+                    let mut frames: u32 = 0;
+                    let mut flags: u32 = 0;
+                    if capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None).is_err() {
+                        break;
+                    }
+                    let silent = flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0;
+                    let samples = std::slice::from_raw_parts(data as *const f32, frames as usize);
+                    for &s in samples {
+                        frame[pos] = if silent { 0.0 } else { s };
+                        pos += 1;
+                        if pos == FRAME_SAMPLES {
+                            pos = 0;
+                            on_frame(&frame);
+                        }
+                    }
+                    let _ = capture.ReleaseBuffer(frames);
+                }
+            }
+        }
+
+        unsafe {
+            let _ = client.Stop();
+            let _ = CloseHandle(event);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use super::AudioApplication;
+    use crate::audio_resample::FRAME_SAMPLES;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{mpsc, Arc};
+
+    const UNSUPPORTED: &str = "Per-application audio capture is only supported on Windows";
+
+    pub fn enumerate_audio_applications() -> Result<Vec<AudioApplication>, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn run_process_capture<F>(
+        _pid: u32,
+        _stop: &Arc<AtomicBool>,
+        ready_tx: mpsc::Sender<Result<(), String>>,
+        _on_frame: F,
+    ) where
+        F: FnMut(&[f32; FRAME_SAMPLES]),
+    {
+        let _ = ready_tx.send(Err(UNSUPPORTED.to_string()));
+    }
+}
@@ -0,0 +1,311 @@
+//! Acoustic echo cancellation for people on speakers.
+//!
+//! The output mixer offers what it plays (peers after master volume, without sidetone) via
+//! [`feed_reference`], stamped with when each frame reaches the speaker. The DSP runs the
+//! mic through [`EchoCanceller`] first: it lines the reference up with the mic frame's
+//! capture time, predicts the echo with a partitioned frequency-domain adaptive filter
+//! (overlap-save, 10 ms blocks) and subtracts it.
+//!
+//! Adaptation pauses while the near end talks over the far end (Geigel detector) and while
+//! nothing is playing. Only native playback feeds the reference; audio played elsewhere
+//! (e.g. by the webview) can't be cancelled.
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// One filter block: 10 ms at 48 kHz, same as the capture and mixer frames.
+pub const BLOCK: usize = 480;
+const FFT_SIZE: usize = 2 * BLOCK;
+const BINS: usize = BLOCK + 1;
+const BLOCK_DURATION: Duration = Duration::from_millis(10);
+
+pub const DEFAULT_TAIL_MS: u32 = 200;
+const TAIL_RANGE_MS: (u32, u32) = (50, 500);
+/// Normalized step size.
+const STEP: f32 = 0.5;
+/// Smoothing of the far-end power estimate per bin.
+const POWER_SMOOTHING: f32 = 0.9;
+/// Near end louder than this share of the recent far-end peak = double talk.
+const GEIGEL_RATIO: f32 = 0.5;
+/// Far end quieter than this (≈ -60 dBFS) isn't worth adapting on.
+const MIN_FAR_PEAK: f32 = 0.001;
+/// ~500 ms of reference between the output callback and the capture processing thread.
+const REFERENCE_RING_FRAMES: usize = 50;
+/// Pick reference a little older than the mic block, so timing jitter never makes the
+/// filter need audio from the future; the tail covers the extra delay.
+const ALIGN_MARGIN: Duration = Duration::from_millis(10);
+
+/// Far-end audio as played, for the echo canceller.
+pub struct ReferenceFrame {
+    pub samples: [f32; BLOCK],
+    /// When the first sample reaches the speaker.
+    pub played_at: Instant,
+}
+
+static REFERENCE_TAP: Mutex<Option<Producer<ReferenceFrame>>> = Mutex::new(None);
+static AEC_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Identifies the canceller that owns the tap, so dropping a replaced one leaves it alone.
+static AEC_SESSION: AtomicU64 = AtomicU64::new(0);
+
+/// Fast check for the output callback: skip building reference frames when nobody listens.
+pub fn reference_wanted() -> bool {
+    AEC_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Offer a played frame to the canceller. Never blocks; dropped if the tap is busy or full.
+pub fn feed_reference(frame: ReferenceFrame) {
+    if let Ok(mut tap) = REFERENCE_TAP.try_lock() {
+        if let Some(producer) = tap.as_mut() {
+            let _ = producer.push(frame);
+        }
+    }
+}
+
+/// Partitioned-block frequency-domain adaptive filter (constrained, overlap-save).
+/// Allocates only in `new`.
+struct AdaptiveFilter {
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    /// Filter partitions, each covering one block of echo path.
+    weights: Vec<Vec<Complex<f32>>>,
+    /// Spectra of the last `partitions` far-end windows; `newest` indexes the latest.
+    far_spectra: Vec<Vec<Complex<f32>>>,
+    newest: usize,
+    far_power: Vec<f32>,
+    /// Previous + current far-end block.
+    far_window: Vec<f32>,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    error_spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl AdaptiveFilter {
+    fn new(partitions: usize) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(FFT_SIZE);
+        let ifft = planner.plan_fft_inverse(FFT_SIZE);
+        let zeros = vec![Complex::new(0.0, 0.0); BINS];
+        Self {
+            weights: vec![zeros.clone(); partitions],
+            far_spectra: vec![zeros.clone(); partitions],
+            newest: 0,
+            far_power: vec![0.0; BINS],
+            far_window: vec![0.0; FFT_SIZE],
+            time: vec![0.0; FFT_SIZE],
+            spectrum: zeros.clone(),
+            error_spectrum: zeros.clone(),
+            scratch: zeros,
+            fft,
+            ifft,
+        }
+    }
+
+    /// Inverse FFT of `spectrum` into `time`, normalized.
+    fn inverse(&mut self) {
+        self.spectrum[0].im = 0.0;
+        self.spectrum[BINS - 1].im = 0.0;
+        let _ = self.ifft.process(&mut self.spectrum, &mut self.time);
+        for s in self.time.iter_mut() {
+            *s /= FFT_SIZE as f32;
+        }
+    }
+
+    /// Cancel the echo of `far` from `near`, writing the residual to `out`.
+    fn process(&mut self, far: &[f32; BLOCK], near: &[f32], out: &mut [f32], adapt: bool) {
+        let partitions = self.weights.len();
+        self.far_window.copy_within(BLOCK.., 0);
+        self.far_window[BLOCK..].copy_from_slice(far);
+        self.newest = (self.newest + 1) % partitions;
+        self.time.copy_from_slice(&self.far_window);
+        let _ = self.fft.process(&mut self.time, &mut self.far_spectra[self.newest]);
+
+        // Echo estimate: sum of every partition's response to its (older) far-end window.
+        self.spectrum.fill(Complex::new(0.0, 0.0));
+        for p in 0..partitions {
+            let far = &self.far_spectra[(self.newest + partitions - p) % partitions];
+            for ((acc, w), x) in self.spectrum.iter_mut().zip(&self.weights[p]).zip(far) {
+                *acc += w * x;
+            }
+        }
+        self.inverse();
+        for ((o, &d), &y) in out.iter_mut().zip(near).zip(&self.time[BLOCK..]) {
+            *o = d - y;
+        }
+        if !adapt {
+            return;
+        }
+
+        // Normalized gradient per partition, constrained to a causal block (overlap-save).
+        self.time[..BLOCK].fill(0.0);
+        self.time[BLOCK..].copy_from_slice(out);
+        let _ = self.fft.process(&mut self.time, &mut self.error_spectrum);
+        let newest = &self.far_spectra[self.newest];
+        for (power, x) in self.far_power.iter_mut().zip(newest) {
+            *power = POWER_SMOOTHING * *power + (1.0 - POWER_SMOOTHING) * x.norm_sqr();
+        }
+        let floor = self.far_power.iter().sum::<f32>() / BINS as f32 * 0.01 + 1e-6;
+        for p in 0..partitions {
+            let far = &self.far_spectra[(self.newest + partitions - p) % partitions];
+            for (((g, x), e), power) in self.spectrum.iter_mut().zip(far).zip(&self.error_spectrum).zip(&self.far_power) {
+                *g = x.conj() * e * (STEP / (partitions as f32 * power + floor));
+            }
+            self.inverse();
+            self.time[BLOCK..].fill(0.0);
+            let _ = self.fft.process(&mut self.time, &mut self.scratch);
+            for (w, g) in self.weights[p].iter_mut().zip(&self.scratch) {
+                *w += g;
+            }
+        }
+    }
+}
+
+/// Mic-side echo canceller; owns the reference tap while it exists.
+pub struct EchoCanceller {
+    filter: AdaptiveFilter,
+    reference: Consumer<ReferenceFrame>,
+    session: u64,
+    tail_ms: u32,
+    far: [f32; BLOCK],
+    /// Far-end block peaks over the tail, for double-talk detection.
+    far_peaks: Vec<f32>,
+    far_peak_pos: usize,
+    out: [f32; BLOCK],
+}
+
+impl EchoCanceller {
+    /// Cancel echoes up to `tail_ms` long (speaker → room → mic, plus device latency slack).
+    pub fn new(tail_ms: u32) -> Result<Self, String> {
+        if !(TAIL_RANGE_MS.0..=TAIL_RANGE_MS.1).contains(&tail_ms) {
+            return Err(format!(
+                "Echo tail out of range: {} ms ({}-{})",
+                tail_ms, TAIL_RANGE_MS.0, TAIL_RANGE_MS.1
+            ));
+        }
+        let partitions = (tail_ms as usize).div_ceil(10);
+        let (producer, reference) = RingBuffer::<ReferenceFrame>::new(REFERENCE_RING_FRAMES);
+        let session = AEC_SESSION.fetch_add(1, Ordering::SeqCst) + 1;
+        {
+            let mut tap = REFERENCE_TAP
+                .lock()
+                .map_err(|_| "Failed to lock echo reference tap".to_string())?;
+            *tap = Some(producer);
+        }
+        AEC_ACTIVE.store(true, Ordering::Relaxed);
+        Ok(Self {
+            filter: AdaptiveFilter::new(partitions),
+            reference,
+            session,
+            tail_ms,
+            far: [0.0; BLOCK],
+            far_peaks: vec![0.0; partitions],
+            far_peak_pos: 0,
+            out: [0.0; BLOCK],
+        })
+    }
+
+    pub fn tail_ms(&self) -> u32 {
+        self.tail_ms
+    }
+
+    /// Next far-end block for a mic block that started at `block_start`: the reference
+    /// played around then, or silence if playback isn't running or is behind.
+    fn next_reference(&mut self, block_start: Instant) {
+        let target = block_start - ALIGN_MARGIN;
+        // Skip reference played well before this block (startup backlog or clock drift).
+        while self.reference.slots() > 1
+            && self.reference.peek().is_ok_and(|f| f.played_at + BLOCK_DURATION <= target)
+        {
+            let _ = self.reference.pop();
+        }
+        match self.reference.peek() {
+            Ok(frame) if frame.played_at <= target + BLOCK_DURATION => {
+                if let Ok(frame) = self.reference.pop() {
+                    self.far = frame.samples;
+                }
+            }
+            _ => self.far = [0.0; BLOCK],
+        }
+    }
+
+    /// Remove echo from a capture frame in place. `samples` is a whole number of 10 ms
+    /// blocks; `captured_at` is when the device delivered the first one.
+    pub fn process(&mut self, samples: &mut [f32], captured_at: Instant) {
+        // Delivery marks the end of a block, so it started one block earlier.
+        let frame_start = captured_at.checked_sub(BLOCK_DURATION).unwrap_or(captured_at);
+        for (i, chunk) in samples.chunks_mut(BLOCK).enumerate() {
+            self.next_reference(frame_start + BLOCK_DURATION * i as u32);
+
+            let far_peak = self.far.iter().fold(0.0f32, |a, s| a.max(s.abs()));
+            self.far_peaks[self.far_peak_pos] = far_peak;
+            self.far_peak_pos = (self.far_peak_pos + 1) % self.far_peaks.len();
+            let recent_far = self.far_peaks.iter().fold(0.0f32, |a, &p| a.max(p));
+            let near_peak = chunk.iter().fold(0.0f32, |a, s| a.max(s.abs()));
+            let double_talk = near_peak > GEIGEL_RATIO * recent_far;
+            let adapt = recent_far > MIN_FAR_PEAK && !double_talk;
+
+            let out = &mut self.out[..chunk.len()];
+            self.filter.process(&self.far, chunk, out, adapt);
+            // A filter that makes things worse (echo path just changed) is bypassed until
+            // it re-converges.
+            let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+            if energy(out) <= energy(chunk) {
+                chunk.copy_from_slice(out);
+            }
+        }
+    }
+}
+
+impl Drop for EchoCanceller {
+    fn drop(&mut self) {
+        if AEC_SESSION.load(Ordering::SeqCst) != self.session {
+            return;
+        }
+        AEC_ACTIVE.store(false, Ordering::Relaxed);
+        if let Ok(mut tap) = REFERENCE_TAP.lock() {
+            tap.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_learns_a_delayed_echo_path() {
+        // Deterministic white-ish noise as the far end.
+        let mut seed = 1u32;
+        let far: Vec<f32> = (0..BLOCK * 400)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+            })
+            .collect();
+        // Room: 25 ms delay, attenuated, plus a weaker reflection.
+        let near: Vec<f32> = (0..far.len())
+            .map(|i| {
+                let tap = |d: usize, g: f32| if i >= d { far[i - d] * g } else { 0.0 };
+                tap(1200, 0.4) + tap(2000, -0.15)
+            })
+            .collect();
+
+        let mut filter = AdaptiveFilter::new(10);
+        let mut out = [0.0f32; BLOCK];
+        let (mut echo, mut residual) = (0.0f32, 0.0f32);
+        for (i, (f, n)) in far.chunks(BLOCK).zip(near.chunks(BLOCK)).enumerate() {
+            let f: &[f32; BLOCK] = f.try_into().unwrap();
+            filter.process(f, n, &mut out, true);
+            if i >= 300 {
+                echo += n.iter().map(|x| x * x).sum::<f32>();
+                residual += out.iter().map(|x| x * x).sum::<f32>();
+            }
+        }
+        let erle_db = 10.0 * (echo / residual).log10();
+        assert!(erle_db > 20.0, "ERLE {} dB", erle_db);
+    }
+}
@@ -0,0 +1,249 @@
+//! Automatic input gain / voice activity threshold calibration.
+//!
+//! The user talks normally for a few seconds while we tap the mic chain before DSP
+//! ([`tee_calibration`], same tap pattern as recording). Frames are split into noise and
+//! speech by their RMS relative to the quietest frames; the gain is chosen so typical
+//! speech peaks land near the top of the level meter without clipping, and the threshold
+//! sits between the (gained) noise floor and the speech level.
+
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::audio_capture::{self, CaptureOptions};
+use crate::audio_dsp::get_dsp;
+use crate::audio_resample::FRAME_SAMPLES;
+use crate::audio_tap::tee_slots;
+
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(5);
+/// ~1 s of slack between the processing thread and the analysis loop.
+const RING_FRAMES: usize = 100;
+/// Frames this much louder (RMS) than the noise floor count as speech (≈ 12 dB).
+const SPEECH_OVER_NOISE: f32 = 4.0;
+/// Need at least this share of speech frames to trust the result.
+const MIN_SPEECH_FRACTION: f32 = 0.1;
+/// Target DSP meter level for typical (95th percentile) speech peaks.
+const TARGET_SPEECH_LEVEL: f32 = 0.9;
+/// Never let the loudest peak seen go above this after gain.
+const MAX_PEAK_AFTER_GAIN: f32 = 0.9;
+const GAIN_RANGE: (f32, f32) = (0.1, 10.0);
+const THRESHOLD_RANGE: (f32, f32) = (0.05, 0.8);
+/// Threshold position between noise level (0.0) and median speech level (1.0).
+const THRESHOLD_POSITION: f32 = 0.35;
+
+static CALIBRATION_TAP: Mutex<Option<Producer<[f32; FRAME_SAMPLES]>>> = Mutex::new(None);
+static CALIBRATION_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Offer a pre-DSP frame to a running calibration. Cheap no-op otherwise; never blocks.
+pub fn tee_calibration(pcm: &[f32]) {
+    tee_slots(&CALIBRATION_ACTIVE, &CALIBRATION_TAP, pcm, |_, _, frame| frame);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationResult {
+    /// Proposed input gain (DSP `set_gain`).
+    pub gain: f32,
+    /// Proposed voice activity threshold (DSP `set_threshold`, 0–1 meter scale).
+    pub threshold: f32,
+    pub noise_floor_dbfs: f32,
+    /// 95th percentile speech peak, before gain.
+    pub speech_peak_dbfs: f32,
+    /// Median speech RMS, before gain.
+    pub speech_rms_dbfs: f32,
+    /// Share of the sample that was classified as speech.
+    pub speech_pct: f32,
+    /// Values were written to the DSP.
+    pub applied: bool,
+}
+
+/// Per-frame measurements of the raw input.
+#[derive(Debug, Clone, Copy)]
+struct FrameStats {
+    peak: f32,
+    rms: f32,
+}
+
+fn frame_stats(frame: &[f32]) -> FrameStats {
+    let peak = frame.iter().fold(0.0f32, |a, s| a.max(s.abs()));
+    let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len().max(1) as f32).sqrt();
+    FrameStats { peak, rms }
+}
+
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let idx = ((sorted.len() - 1) as f32 * p).round() as usize;
+    sorted[idx]
+}
+
+fn dbfs(x: f32) -> f32 {
+    20.0 * x.max(1e-6).log10()
+}
+
+/// Turn raw frame stats into gain/threshold. `level_for_peak` maps a post-gain frame peak
+/// to the DSP meter scale the threshold is compared against.
+fn analyze(frames: &[FrameStats], level_for_peak: impl Fn(f32) -> f32) -> Result<CalibrationResult, String> {
+    if frames.is_empty() {
+        return Err("No audio arrived from the input device".to_string());
+    }
+    let mut rms: Vec<f32> = frames.iter().map(|f| f.rms).collect();
+    rms.sort_by(f32::total_cmp);
+    let noise_rms = percentile(&rms, 0.1);
+    let speech_gate = (noise_rms * SPEECH_OVER_NOISE).max(1e-4);
+
+    let (speech, noise): (Vec<FrameStats>, Vec<FrameStats>) = frames.iter().partition(|f| f.rms > speech_gate);
+    let speech_fraction = speech.len() as f32 / frames.len() as f32;
+    if speech_fraction < MIN_SPEECH_FRACTION {
+        return Err("Not enough speech detected: talk normally for the whole calibration".to_string());
+    }
+
+    let mut speech_peaks: Vec<f32> = speech.iter().map(|f| f.peak).collect();
+    speech_peaks.sort_by(f32::total_cmp);
+    let mut speech_rms: Vec<f32> = speech.iter().map(|f| f.rms).collect();
+    speech_rms.sort_by(f32::total_cmp);
+    let mut noise_peaks: Vec<f32> = noise.iter().map(|f| f.peak).collect();
+    noise_peaks.sort_by(f32::total_cmp);
+
+    let speech_peak = percentile(&speech_peaks, 0.95);
+    let max_peak = *speech_peaks.last().unwrap_or(&speech_peak);
+
+    // Invert the meter curve by bisection so the curve lives in one place (the DSP).
+    let (mut lo, mut hi) = (GAIN_RANGE.0, GAIN_RANGE.1);
+    for _ in 0..40 {
+        let mid = (lo + hi) / 2.0;
+        if level_for_peak(speech_peak * mid) < TARGET_SPEECH_LEVEL {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let gain = lo
+        .min(MAX_PEAK_AFTER_GAIN / max_peak.max(1e-6))
+        .clamp(GAIN_RANGE.0, GAIN_RANGE.1);
+
+    let noise_level = level_for_peak(percentile(&noise_peaks, 0.9) * gain);
+    let speech_level = level_for_peak(percentile(&speech_peaks, 0.5) * gain);
+    let threshold = (noise_level + THRESHOLD_POSITION * (speech_level - noise_level))
+        .clamp(THRESHOLD_RANGE.0, THRESHOLD_RANGE.1);
+
+    Ok(CalibrationResult {
+        gain,
+        threshold,
+        noise_floor_dbfs: dbfs(noise_rms),
+        speech_peak_dbfs: dbfs(speech_peak),
+        speech_rms_dbfs: dbfs(percentile(&speech_rms, 0.5)),
+        speech_pct: speech_fraction * 100.0,
+        applied: false,
+    })
+}
+
+fn collect(consumer: &mut Consumer<[f32; FRAME_SAMPLES]>, duration: Duration) -> Vec<FrameStats> {
+    let deadline = Instant::now() + duration;
+    let mut frames = Vec::with_capacity((duration.as_millis() / 10) as usize + 1);
+    while Instant::now() < deadline {
+        while let Ok(frame) = consumer.pop() {
+            frames.push(frame_stats(&frame));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    frames
+}
+
+/// Sample `duration` of the mic and propose gain/threshold; with `apply`, also set them on
+/// the DSP. Uses the running capture if there is one, otherwise opens `device_id` for the
+/// duration. Blocks for `duration`.
+pub fn calibrate_input(device_id: Option<String>, duration: Duration, apply: bool) -> Result<CalibrationResult, String> {
+    let (producer, mut consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(RING_FRAMES);
+    {
+        let mut tap = CALIBRATION_TAP
+            .lock()
+            .map_err(|_| "Failed to lock calibration tap".to_string())?;
+        if tap.is_some() {
+            return Err("Calibration already in progress".to_string());
+        }
+        *tap = Some(producer);
+    }
+
+    let owns_capture = !audio_capture::is_capturing();
+    let started = if owns_capture {
+        // Frames and levels go nowhere; only the tap is read.
+        let (frame_tx, _frame_rx) = mpsc::sync_channel(1);
+        let (level_tx, _level_rx) = mpsc::channel();
+        audio_capture::start_capture(
+            vec![device_id],
+            CaptureOptions::default(),
+            frame_tx,
+            level_tx,
+            Arc::new(|_| {}),
+            Arc::new(|_| {}),
+        )
+    } else {
+        Ok(())
+    };
+
+    let frames = started.map(|()| {
+        CALIBRATION_ACTIVE.store(true, Ordering::Relaxed);
+        let frames = collect(&mut consumer, duration);
+        CALIBRATION_ACTIVE.store(false, Ordering::Relaxed);
+        frames
+    });
+    if owns_capture {
+        audio_capture::stop_capture();
+    }
+    if let Ok(mut tap) = CALIBRATION_TAP.lock() {
+        tap.take();
+    }
+
+    let dsp = get_dsp();
+    let mut result = {
+        let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+        analyze(&frames?, |peak| dsp_guard.level_for_peak(peak))?
+    };
+    if apply {
+        let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+        dsp_guard.set_gain(result.gain);
+        dsp_guard.set_threshold(result.threshold);
+        result.applied = true;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Same curve as the DSP meter with its default constants.
+    fn meter(peak: f32) -> f32 {
+        if peak < 0.0002 {
+            0.0
+        } else {
+            ((peak - 0.0002) / (0.07 - 0.0002)).clamp(0.0, 1.0).sqrt()
+        }
+    }
+
+    #[test]
+    fn quiet_speaker_gets_boosted_and_threshold_sits_above_noise() {
+        let mut frames = Vec::new();
+        for i in 0..500 {
+            if i % 2 == 0 {
+                frames.push(FrameStats { peak: 0.0008, rms: 0.0003 });
+            } else {
+                frames.push(FrameStats { peak: 0.02, rms: 0.006 });
+            }
+        }
+        let result = analyze(&frames, meter).unwrap();
+        assert!(result.gain > 2.0, "gain {}", result.gain);
+        assert!((meter(0.02 * result.gain) - TARGET_SPEECH_LEVEL).abs() < 0.02);
+        assert!(result.threshold > meter(0.0008 * result.gain));
+        assert!(result.threshold < meter(0.02 * result.gain));
+    }
+
+    #[test]
+    fn silence_is_rejected() {
+        let frames = vec![FrameStats { peak: 0.001, rms: 0.0004 }; 500];
+        assert!(analyze(&frames, meter).is_err());
+    }
+}
@@ -3,23 +3,158 @@
 //! Philosophy: **Audio loss > audio latency.** Never block the audio callback.
 //! If JS falls behind, frames are dropped (never queued).
 
+use crate::audio_resample::{DriftEstimator, FrameResampler, FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+use crate::wasapi_exclusive::{start_exclusive_capture, ExclusiveCapture};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, SampleFormat, Stream, StreamConfig};
 use rtrb::{Producer, RingBuffer};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicU64, Ordering};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
 /// Drop counters for debugging. Expose via get_audio_drop_stats() / dev overlay.
 static DROPPED_RAW: AtomicU64 = AtomicU64::new(0);
 static DROPPED_PROCESSED: AtomicU64 = AtomicU64::new(0);
+/// Estimated clock drift of the (first) capture device in ppm, stored as f64 bits.
+static CAPTURE_DRIFT_PPM: AtomicU64 = AtomicU64::new(0);
+/// Device callbacks (or exclusive-mode packets) since capture started.
+static CALLBACK_COUNT: AtomicU64 = AtomicU64::new(0);
+/// Mixed frames that made it through the DSP stage since capture started.
+static PROCESSED_FRAMES: AtomicU64 = AtomicU64::new(0);
+/// Highest raw ring fill (frames) seen by the processing thread since the last stats sample.
+static RAW_RING_PEAK: AtomicUsize = AtomicUsize::new(0);
+/// Raw ring capacity (frames) of the running session; scales with the capture frame duration.
+static RAW_RING_CAPACITY: AtomicUsize = AtomicUsize::new(RAW_RING_CAP);
+/// Capture→emit latency of the last frame handed to the frontend, in µs.
+static EMIT_LATENCY_US: AtomicU64 = AtomicU64::new(0);
+/// Sequence gaps seen by the emitter (frames lost anywhere after processing).
+static SEQ_GAPS: AtomicU64 = AtomicU64::new(0);
+/// Next sequence number the emitter expects.
+static NEXT_EMIT_SEQ: AtomicU64 = AtomicU64::new(0);
+/// Bumped on every start/stop so stats reporters can tell their session ended.
+static CAPTURE_SESSION: AtomicU64 = AtomicU64::new(0);
+/// Streams are paused on purpose (deafen); the stall watchdog stands down.
+static CAPTURE_PAUSED: AtomicBool = AtomicBool::new(false);
+/// Watchdog restarts since audio last flowed; caps restart loops on a dead driver.
+static STALL_RESTARTS: AtomicU32 = AtomicU32::new(0);
+/// DSP time of the most recent capture frame, in µs.
+static DSP_FRAME_US: AtomicU64 = AtomicU64::new(0);
+/// Slowest DSP frame in the last complete `DSP_TIMING_WINDOW`, in µs.
+static DSP_FRAME_MAX_US: AtomicU64 = AtomicU64::new(0);
+/// Frames whose DSP took longer than `DSP_OVERRUN_BUDGET` since capture started.
+static DSP_OVERRUNS: AtomicU64 = AtomicU64::new(0);
+/// The processing thread, parked while the raw rings are empty; producers unpark it.
+static PROCESSING_THREAD: Mutex<Option<thread::Thread>> = Mutex::new(None);
 
-/// Fixed frame size: 10 ms at 48 kHz. No heap allocation in callback.
-const FRAME_SAMPLES: usize = 480;
-/// Raw ring capacity: ~80 ms. If consumer falls behind, drop (never block).
+/// Raw ring capacity per 10 ms of frame duration: ~80 ms at 10 ms frames. If consumer falls behind, drop (never block).
 const RAW_RING_CAP: usize = 8;
+/// Multi-device mixing: frames a device may run ahead before a lagging device is mixed as silence
+/// (per 10 ms of frame duration, since devices deliver a whole capture frame at a time).
+const MIX_ALIGN_SLACK: usize = 3;
+/// Multi-device mixing: per-device backlog cap (~80 ms per 10 ms of frame duration); oldest dropped beyond it.
+const MAX_LANE_BACKLOG: usize = 8;
+/// Capture frame durations the pipeline supports.
+const SUPPORTED_FRAME_MS: [u32; 3] = [10, 20, 40];
+/// How often the stream owner thread checks whether a device has disappeared.
+const DEVICE_LOST_POLL: Duration = Duration::from_millis(250);
+/// No device audio for this long while running = stalled stream (e.g. driver after resume).
+const STALL_TIMEOUT: Duration = Duration::from_secs(3);
+/// Give up rebuilding after this many restarts that didn't bring audio back.
+const MAX_STALL_RESTARTS: u32 = 3;
+/// Longest the processing thread parks without a wakeup; bounds the cost of a missed
+/// unpark and how long it takes to notice the rings closing.
+const PROCESSING_PARK: Duration = Duration::from_millis(5);
+/// DSP slower than this on one frame risks falling behind real time: counted as an overrun.
+const DSP_OVERRUN_BUDGET: Duration = Duration::from_millis(10);
+/// Window the reported maximum DSP frame time covers.
+const DSP_TIMING_WINDOW: Duration = Duration::from_secs(1);
+
+/// One device buffer as pushed by the audio callback, stamped on arrival.
+#[derive(Clone, Copy)]
+pub struct RawFrame {
+    pub samples: [f32; FRAME_SAMPLES],
+    /// Valid samples. Below `FRAME_SAMPLES` only for the tail of an odd-sized device
+    /// buffer (default buffer size); the processing thread reframes those.
+    pub len: usize,
+    pub captured_at: Instant,
+}
+
+/// A processed frame on its way to the emitter (mic, loopback and app capture).
+#[derive(Debug, Clone)]
+pub struct ProcessedFrame {
+    /// Mono 48 kHz samples: 10 ms, or the capture frame duration for the mic (20/40 ms).
+    pub pcm: Vec<f32>,
+    /// When the audio was captured, in µs on the pipeline clock (see `monotonic_micros`).
+    pub capture_ts: u64,
+    /// Per-session sequence number. Gaps mean frames were dropped before the channel.
+    pub seq: u64,
+}
+
+impl ProcessedFrame {
+    /// A frame holding a copy of `samples` in a pooled buffer.
+    pub fn pooled(samples: &[f32], capture_ts: u64, seq: u64) -> Self {
+        let mut pcm = take_frame_buffer();
+        pcm.extend_from_slice(samples);
+        Self { pcm, capture_ts, seq }
+    }
+
+    /// Hand the sample buffer back to the pool once the frame has been consumed.
+    pub fn recycle(self) {
+        recycle_frame_buffer(self.pcm);
+    }
+}
+
+/// Spare `ProcessedFrame` buffers. Producers take from here and consumers hand buffers back,
+/// so steady-state capture does no per-frame heap allocation. Only touched with `try_lock`:
+/// on contention we allocate (or drop the buffer) rather than wait.
+static FRAME_POOL: Mutex<Vec<Vec<f32>>> = Mutex::new(Vec::new());
+/// Covers the emitter backlog plus frames in flight on every pipeline.
+const FRAME_POOL_CAP: usize = 32;
+
+/// An empty buffer with room for the longest capture frame (40 ms).
+pub fn take_frame_buffer() -> Vec<f32> {
+    FRAME_POOL
+        .try_lock()
+        .ok()
+        .and_then(|mut pool| pool.pop())
+        .unwrap_or_else(|| Vec::with_capacity(FRAME_SAMPLES * 4))
+}
+
+/// Non-blocking send; a frame that doesn't fit goes straight back to the pool.
+/// Returns false if it was dropped.
+pub fn try_send_frame(sender: &mpsc::SyncSender<ProcessedFrame>, frame: ProcessedFrame) -> bool {
+    match sender.try_send(frame) {
+        Ok(()) => true,
+        Err(mpsc::TrySendError::Full(frame) | mpsc::TrySendError::Disconnected(frame)) => {
+            frame.recycle();
+            false
+        }
+    }
+}
+
+pub fn recycle_frame_buffer(mut buf: Vec<f32>) {
+    buf.clear();
+    if let Ok(mut pool) = FRAME_POOL.try_lock() {
+        if pool.len() < FRAME_POOL_CAP {
+            pool.push(buf);
+        }
+    }
+}
+
+/// Zero point of the pipeline clock; set on first use.
+static CLOCK_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// Microseconds since the pipeline clock's epoch. Monotonic and shared by every capture
+/// path, so timestamps from different sources can be compared directly.
+pub fn monotonic_micros(t: Instant) -> u64 {
+    let epoch = *CLOCK_EPOCH.get_or_init(Instant::now);
+    t.saturating_duration_since(epoch).as_micros() as u64
+}
 
 /// Audio device information (matches frontend AudioDevice)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,67 +162,494 @@ pub struct AudioDevice {
     pub device_id: String,
     pub label: String,
     pub kind: AudioDeviceKind,
+    /// Audio host (backend) the device belongs to, e.g. "WASAPI" or "ASIO".
+    pub host: String,
+    /// The OS default device for this direction.
+    #[serde(default)]
+    pub is_default: bool,
+    /// Windows "default communications device" (what voice apps are expected to use).
+    /// Elsewhere this is the same as `is_default`.
+    #[serde(default)]
+    pub is_default_communications: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Device IDs that follow the OS default instead of naming a device.
+pub const DEFAULT_DEVICE_ID: &str = "default";
+pub const COMMUNICATIONS_DEVICE_ID: &str = "communications";
+
+/// Whether `device_id` tracks the OS default (None, "default" or "communications").
+pub fn follows_system_default(device_id: Option<&str>) -> bool {
+    matches!(device_id, None | Some(DEFAULT_DEVICE_ID) | Some(COMMUNICATIONS_DEVICE_ID))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AudioDeviceKind {
     #[serde(rename = "audioinput")]
     Input,
     #[serde(rename = "audiooutput")]
     Output,
+    /// Monitor of an output ("what I hear"): PulseAudio/PipeWire monitor sources, or a
+    /// loopback/aggregate device on macOS. These are inputs to cpal and share input IDs,
+    /// so `start_capture` opens them like any mic.
+    #[serde(rename = "audiomonitor")]
+    Monitor,
+}
+
+/// Whether an input device is really a monitor of an output (Linux/macOS only; Windows
+/// captures outputs directly through WASAPI loopback).
+#[cfg(target_os = "linux")]
+fn is_monitor_source(name: &str) -> bool {
+    name.to_lowercase().contains("monitor")
+}
+
+#[cfg(target_os = "macos")]
+fn is_monitor_source(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["blackhole", "soundflower", "loopback", "aggregate"].iter().any(|n| name.contains(n))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn is_monitor_source(_name: &str) -> bool {
+    false
+}
+
+/// Requests to the stream owner thread. Each carries a reply channel for the result.
+enum StreamControl {
+    Pause(mpsc::Sender<Result<(), String>>),
+    Resume(mpsc::Sender<Result<(), String>>),
+}
+
+/// A running capture stream: cpal (shared mode) or our own WASAPI exclusive-mode client.
+enum ActiveStream {
+    Cpal(Stream),
+    Exclusive(ExclusiveCapture),
 }
 
+impl ActiveStream {
+    fn pause(&self) -> Result<(), String> {
+        match self {
+            ActiveStream::Cpal(stream) => stream.pause().map_err(|e| format!("Failed to pause stream: {}", e)),
+            ActiveStream::Exclusive(capture) => {
+                capture.pause();
+                Ok(())
+            }
+        }
+    }
+
+    fn play(&self) -> Result<(), String> {
+        match self {
+            ActiveStream::Cpal(stream) => stream.play().map_err(|e| format!("Failed to resume stream: {}", e)),
+            ActiveStream::Exclusive(capture) => {
+                capture.resume();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Optional capture settings passed from the frontend. Missing fields use defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureOptions {
+    /// Windows: open the device in WASAPI exclusive mode (lower latency, bypasses the OS
+    /// mixer). Falls back to shared mode if the device rejects the format or is busy.
+    #[serde(default)]
+    pub exclusive: bool,
+    /// Frame duration in ms: 10 (default), 20 or 40. Longer frames mean fewer device
+    /// callbacks and DSP/encoder passes per second, at the cost of latency.
+    #[serde(default)]
+    pub frame_ms: Option<u32>,
+    /// Zero-based device channels to capture, averaged into the mono signal (e.g. `[0]` for
+    /// the mic on input 1 of a 4-channel interface). None = the driver's mono downmix.
+    /// Applies to every device being captured; exclusive mode is skipped when set.
+    #[serde(default)]
+    pub channels: Option<Vec<u16>>,
+}
+
+impl CaptureOptions {
+    /// Number of 10 ms granules per capture frame.
+    fn frame_granules(&self) -> Result<usize, String> {
+        let ms = self.frame_ms.unwrap_or(10);
+        if !SUPPORTED_FRAME_MS.contains(&ms) {
+            return Err(format!("Unsupported frame duration: {} ms (use 10, 20 or 40)", ms));
+        }
+        Ok((ms / 10) as usize)
+    }
+}
+
+/// Reported when a capture device disappears mid-stream (e.g. unplugged).
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceLostEvent {
+    /// Devices that were being captured (None = default device).
+    pub device_ids: Vec<Option<String>>,
+    /// Capture was restarted on the default input device.
+    pub failed_over: bool,
+    /// Why failover didn't happen (capture is stopped in that case).
+    pub error: Option<String>,
+}
+
+/// Called from a background thread after a lost device has been handled.
+pub type DeviceLostHandler = Arc<dyn Fn(&DeviceLostEvent) + Send + Sync>;
+
+/// Reported when the watchdog finds a running stream that stopped delivering audio.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureStallEvent {
+    pub device_ids: Vec<Option<String>>,
+    /// How long no audio arrived before the watchdog fired.
+    pub stalled_ms: u64,
+    /// The streams were rebuilt on the same devices.
+    pub restarted: bool,
+    /// Why the rebuild failed or wasn't attempted.
+    pub error: Option<String>,
+}
+
+/// Called from a background thread after a stall has been handled.
+pub type CaptureStallHandler = Arc<dyn Fn(&CaptureStallEvent) + Send + Sync>;
+
 /// Global audio capture state.
-/// Processed frames go via bounded channel (drop if full); stream is leaked to stay alive.
+/// Processed frames go via bounded channel (drop if full). The cpal Stream is !Send, so it
+/// lives on its own owner thread; dropping `control_tx` drops the stream and frees the device.
 struct AudioCaptureState {
-    processed_frame_sender: Option<mpsc::SyncSender<Vec<f32>>>,
+    processed_frame_sender: Option<mpsc::SyncSender<ProcessedFrame>>,
     level_update_sender: Option<mpsc::Sender<f32>>,
     sample_rate: u32,
+    control_tx: Option<mpsc::Sender<StreamControl>>,
+    stream_thread: Option<thread::JoinHandle<()>>,
     processing_thread: Option<thread::JoinHandle<()>>,
-    _stream_handle: Option<Arc<Mutex<()>>>,
+    /// What was started, so a lost device can be replaced with the same settings.
+    device_ids: Vec<Option<String>>,
+    options: CaptureOptions,
+    /// Set by a stream error callback when its device goes away. Unique per session.
+    device_lost: Arc<AtomicBool>,
+    on_device_lost: DeviceLostHandler,
+    /// Set by the processing thread's watchdog when audio stops arriving. Unique per session.
+    stalled: Arc<AtomicBool>,
+    on_stall: CaptureStallHandler,
 }
 
 static AUDIO_CAPTURE_STATE: Mutex<Option<AudioCaptureState>> = Mutex::new(None);
 
-/// Enumerate all available audio devices
+/// Audio host picked by the user (None = platform default). Read on every enumerate/start.
+static SELECTED_HOST: Mutex<Option<cpal::HostId>> = Mutex::new(None);
+
+/// Names of the audio hosts compiled in and available on this machine.
+/// ASIO and JACK only show up in builds with the `asio` / `jack` features. PipeWire is
+/// reached through JACK (pipewire-jack) or its ALSA/Pulse emulation via the default host.
+pub fn available_hosts() -> Vec<String> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| id.name().to_string())
+        .collect()
+}
+
+/// Select the audio host by name (None = platform default). Applies to the next
+/// enumerate/start; running streams keep their host until restarted.
+pub fn set_audio_host(name: Option<&str>) -> Result<(), String> {
+    let host_id = match name {
+        None => None,
+        Some(name) => Some(host_id_by_name(name)?),
+    };
+    let mut selected = SELECTED_HOST.lock()
+        .map_err(|_| "Failed to lock audio host selection".to_string())?;
+    *selected = host_id;
+    Ok(())
+}
+
+/// Look up an available host by its cpal name (case-insensitive).
+fn host_id_by_name(name: &str) -> Result<cpal::HostId, String> {
+    cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Audio host not available: {}", name))
+}
+
+/// The selected audio host, falling back to the default if it can't be opened
+/// (e.g. the ASIO driver was uninstalled).
+pub fn audio_host() -> cpal::Host {
+    let selected = SELECTED_HOST.lock().ok().and_then(|s| *s);
+    match selected {
+        Some(id) => cpal::host_from_id(id).unwrap_or_else(|e| {
+            eprintln!("Failed to open audio host {}: {}; using default", id.name(), e);
+            cpal::default_host()
+        }),
+        None => cpal::default_host(),
+    }
+}
+
+/// Enumerate all available audio devices on the selected host
 pub fn enumerate_devices() -> Result<Vec<AudioDevice>, String> {
-    let host = cpal::default_host();
+    list_host_devices(&audio_host())
+}
+
+/// Enumerate devices on a specific host without changing the selection
+/// (lets the settings UI preview e.g. JACK ports before switching).
+pub fn enumerate_host_devices(host_name: &str) -> Result<Vec<AudioDevice>, String> {
+    let host = cpal::host_from_id(host_id_by_name(host_name)?)
+        .map_err(|e| format!("Failed to open audio host {}: {}", host_name, e))?;
+    list_host_devices(&host)
+}
+
+fn list_host_devices(host: &cpal::Host) -> Result<Vec<AudioDevice>, String> {
+    let host_name = host.id().name().to_string();
     
     let mut devices = Vec::new();
+    for kind in [AudioDeviceKind::Input, AudioDeviceKind::Output, AudioDeviceKind::Monitor] {
+        let default_name = default_device_name(host, &kind);
+        let communications_name = communications_device_name(host, &kind);
+        // Names aren't unique; flag only the first device with the default's name.
+        let (mut default_seen, mut communications_seen) = (false, false);
+        for (device_id, name, _) in list_devices_with_ids(host, &kind)? {
+            // Monitors are listed under their own kind only.
+            if kind == AudioDeviceKind::Input && is_monitor_source(&name) {
+                continue;
+            }
+            let is_default = !default_seen && default_name.as_deref() == Some(name.as_str());
+            let is_default_communications =
+                !communications_seen && communications_name.as_deref() == Some(name.as_str());
+            default_seen |= is_default;
+            communications_seen |= is_default_communications;
+            devices.push(AudioDevice {
+                device_id,
+                label: clean_device_label(&name),
+                kind: kind.clone(),
+                host: host_name.clone(),
+                is_default,
+                is_default_communications,
+            });
+        }
+    }
     
-    // Enumerate input devices
-    let input_devices = host.input_devices()
-        .map_err(|e| format!("Failed to enumerate input devices: {}", e))?;
+    Ok(devices)
+}
+
+fn default_device_name(host: &cpal::Host, kind: &AudioDeviceKind) -> Option<String> {
+    let device = match kind {
+        AudioDeviceKind::Input => host.default_input_device(),
+        AudioDeviceKind::Output => host.default_output_device(),
+        AudioDeviceKind::Monitor => None,
+    };
+    device.and_then(|d| d.name().ok())
+}
+
+/// Friendly name of the default communications endpoint (WASAPI only; cpal names WASAPI
+/// devices by friendly name, so this matches `Device::name`).
+#[cfg(windows)]
+fn communications_device_name(host: &cpal::Host, kind: &AudioDeviceKind) -> Option<String> {
+    use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+    use windows::Win32::Media::Audio::{eCapture, eCommunications, eRender, IMMDeviceEnumerator, MMDeviceEnumerator};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ};
+
+    if host.id() != cpal::HostId::Wasapi {
+        return default_device_name(host, kind);
+    }
+    let flow = match kind {
+        AudioDeviceKind::Input => eCapture,
+        AudioDeviceKind::Output => eRender,
+        AudioDeviceKind::Monitor => return None,
+    };
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+        let device = enumerator.GetDefaultAudioEndpoint(flow, eCommunications).ok()?;
+        let store = device.OpenPropertyStore(STGM_READ).ok()?;
+        let name = store.GetValue(&PKEY_Device_FriendlyName).ok()?;
+        Some(name.to_string())
+    }
+}
+
+/// Only Windows has a separate communications role; elsewhere it's the default device.
+#[cfg(not(windows))]
+fn communications_device_name(host: &cpal::Host, kind: &AudioDeviceKind) -> Option<String> {
+    default_device_name(host, kind)
+}
+
+/// Stable device ID: hash of host API + direction + raw device name + ordinal among
+/// devices with the same name (stands in for the port, which cpal doesn't expose).
+/// Unlike enumeration indices this survives reboots and hotplug reordering.
+fn stable_device_id(host_id: cpal::HostId, kind: &AudioDeviceKind, name: &str, ordinal: usize) -> String {
+    let prefix = match kind {
+        AudioDeviceKind::Input | AudioDeviceKind::Monitor => "input",
+        AudioDeviceKind::Output => "output",
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(host_id.name().as_bytes());
+    hasher.update([0]);
+    hasher.update(prefix.as_bytes());
+    hasher.update([0]);
+    hasher.update(name.as_bytes());
+    hasher.update([0]);
+    hasher.update((ordinal as u32).to_le_bytes());
+    format!("{}:{}", prefix, &hex::encode(hasher.finalize())[..16])
+}
+
+/// List devices of one direction as (stable_id, raw_name, device).
+fn list_devices_with_ids(
+    host: &cpal::Host,
+    kind: &AudioDeviceKind,
+) -> Result<Vec<(String, String, Device)>, String> {
+    let iter: Box<dyn Iterator<Item = Device>> = match kind {
+        AudioDeviceKind::Input => Box::new(host.input_devices()
+            .map_err(|e| format!("Failed to enumerate input devices: {}", e))?),
+        AudioDeviceKind::Output => Box::new(host.output_devices()
+            .map_err(|e| format!("Failed to enumerate output devices: {}", e))?),
+        // Same IDs as in the input list: filter it rather than numbering monitors separately.
+        AudioDeviceKind::Monitor => {
+            return Ok(list_devices_with_ids(host, &AudioDeviceKind::Input)?
+                .into_iter()
+                .filter(|(_, name, _)| is_monitor_source(name))
+                .collect());
+        }
+    };
     
-    for (idx, device) in input_devices.enumerate() {
+    let mut seen: Vec<String> = Vec::new();
+    let mut out = Vec::new();
+    for device in iter {
         let name = device.name()
             .map_err(|e| format!("Failed to get device name: {}", e))?;
-        let device_id = format!("input_{}", idx);
-        
-        devices.push(AudioDevice {
-            device_id,
-            label: clean_device_label(&name),
-            kind: AudioDeviceKind::Input,
-        });
+        let ordinal = seen.iter().filter(|n| **n == name).count();
+        seen.push(name.clone());
+        out.push((stable_device_id(host.id(), kind, &name, ordinal), name, device));
+    }
+    Ok(out)
+}
+
+/// Resolve a device by stable ID (or the default device when `device_id` is None or
+/// "default"; "communications" picks the default communications device).
+/// Legacy index IDs ("input_0", "output_1") are still accepted for saved settings.
+pub fn resolve_device(kind: AudioDeviceKind, device_id: Option<&str>) -> Result<Device, String> {
+    let host = audio_host();
+    if device_id == Some(COMMUNICATIONS_DEVICE_ID) {
+        if let Some(name) = communications_device_name(&host, &kind) {
+            if let Some((_, _, device)) = list_devices_with_ids(&host, &kind)?.into_iter().find(|(_, n, _)| *n == name) {
+                return Ok(device);
+            }
+        }
     }
+    let Some(id) = device_id.filter(|id| !follows_system_default(Some(id))) else {
+        return match kind {
+            AudioDeviceKind::Input => host.default_input_device()
+                .ok_or_else(|| "No default input device available".to_string()),
+            AudioDeviceKind::Output => host.default_output_device()
+                .ok_or_else(|| "No default output device available".to_string()),
+            AudioDeviceKind::Monitor => list_devices_with_ids(&host, &kind)?
+                .into_iter()
+                .next()
+                .map(|(_, _, device)| device)
+                .ok_or_else(|| "No loopback/monitor source available on this system".to_string()),
+        };
+    };
     
-    // Enumerate output devices
-    let output_devices = host.output_devices()
-        .map_err(|e| format!("Failed to enumerate output devices: {}", e))?;
+    let devices = list_devices_with_ids(&host, &kind)?;
+    if let Some((_, _, device)) = devices.iter().find(|(stable_id, _, _)| stable_id == id) {
+        return Ok(device.clone());
+    }
     
-    for (idx, device) in output_devices.enumerate() {
-        let name = device.name()
-            .map_err(|e| format!("Failed to get device name: {}", e))?;
-        let device_id = format!("output_{}", idx);
-        
-        devices.push(AudioDevice {
-            device_id,
-            label: clean_device_label(&name),
-            kind: AudioDeviceKind::Output,
-        });
+    let legacy_prefix = match kind {
+        AudioDeviceKind::Input | AudioDeviceKind::Monitor => "input_",
+        AudioDeviceKind::Output => "output_",
+    };
+    if let Some(idx) = id.strip_prefix(legacy_prefix).and_then(|s| s.parse::<usize>().ok()) {
+        return devices.into_iter()
+            .nth(idx)
+            .map(|(_, _, device)| device)
+            .ok_or_else(|| format!("Device index {} not found", idx));
     }
     
-    Ok(devices)
+    Err(format!("Audio device not found: {}", id))
+}
+
+/// Sample rates checked against each supported range when probing a device.
+const PROBE_SAMPLE_RATES: [u32; 9] = [8000, 16000, 22050, 24000, 32000, 44100, 48000, 96000, 192000];
+
+/// One supported stream configuration range, as reported by the driver.
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportedConfigRange {
+    pub channels: u16,
+    pub sample_format: String,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    /// None when the driver doesn't report buffer limits.
+    pub min_buffer_size: Option<u32>,
+    pub max_buffer_size: Option<u32>,
+}
+
+/// What a device can do, for validating settings before `start_capture`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceCapabilities {
+    pub label: String,
+    /// Common rates (8 kHz–192 kHz) supported by at least one config.
+    pub sample_rates: Vec<u32>,
+    pub channels: Vec<u16>,
+    pub sample_formats: Vec<String>,
+    /// Buffer size limits across all configs, in frames. None if the driver doesn't say.
+    pub min_buffer_size: Option<u32>,
+    pub max_buffer_size: Option<u32>,
+    pub default_sample_rate: Option<u32>,
+    pub default_channels: Option<u16>,
+    pub default_sample_format: Option<String>,
+    pub configs: Vec<SupportedConfigRange>,
+}
+
+/// Query a device's supported configs (None = default device of that kind).
+pub fn probe_device(kind: AudioDeviceKind, device_id: Option<&str>) -> Result<DeviceCapabilities, String> {
+    let device = resolve_device(kind.clone(), device_id)?;
+    let (ranges, default) = match kind {
+        AudioDeviceKind::Input | AudioDeviceKind::Monitor => (
+            device.supported_input_configs()
+                .map_err(|e| format!("Failed to query device configs: {}", e))?
+                .collect::<Vec<_>>(),
+            device.default_input_config().ok(),
+        ),
+        AudioDeviceKind::Output => (
+            device.supported_output_configs()
+                .map_err(|e| format!("Failed to query device configs: {}", e))?
+                .collect::<Vec<_>>(),
+            device.default_output_config().ok(),
+        ),
+    };
+
+    let configs: Vec<SupportedConfigRange> = ranges.iter()
+        .map(|r| {
+            let (min_buffer_size, max_buffer_size) = match r.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => (Some(*min), Some(*max)),
+                cpal::SupportedBufferSize::Unknown => (None, None),
+            };
+            SupportedConfigRange {
+                channels: r.channels(),
+                sample_format: r.sample_format().to_string(),
+                min_sample_rate: r.min_sample_rate().0,
+                max_sample_rate: r.max_sample_rate().0,
+                min_buffer_size,
+                max_buffer_size,
+            }
+        })
+        .collect();
+
+    let sample_rates = PROBE_SAMPLE_RATES.iter()
+        .copied()
+        .filter(|rate| configs.iter().any(|c| c.min_sample_rate <= *rate && c.max_sample_rate >= *rate))
+        .collect();
+    let mut channels: Vec<u16> = configs.iter().map(|c| c.channels).collect();
+    channels.sort_unstable();
+    channels.dedup();
+    let mut sample_formats: Vec<String> = Vec::new();
+    for c in &configs {
+        if !sample_formats.contains(&c.sample_format) {
+            sample_formats.push(c.sample_format.clone());
+        }
+    }
+
+    Ok(DeviceCapabilities {
+        label: clean_device_label(&device.name().unwrap_or_default()),
+        sample_rates,
+        channels,
+        sample_formats,
+        min_buffer_size: configs.iter().filter_map(|c| c.min_buffer_size).min(),
+        max_buffer_size: configs.iter().filter_map(|c| c.max_buffer_size).max(),
+        default_sample_rate: default.as_ref().map(|d| d.sample_rate().0),
+        default_channels: default.as_ref().map(|d| d.channels()),
+        default_sample_format: default.as_ref().map(|d| d.sample_format().to_string()),
+        configs,
+    })
 }
 
 /// Clean device label (remove Windows prefixes, etc.)
@@ -109,145 +671,578 @@ fn clean_device_label(label: &str) -> String {
     clean.trim().to_string()
 }
 
-/// Start audio capture from the specified device (or default)
-/// Returns receiver for processed PCM frames and receiver for level updates
-pub fn start_capture(
-    device_id: Option<String>,
-    processed_frame_sender: mpsc::SyncSender<Vec<f32>>,
-    level_update_sender: mpsc::Sender<f32>,
-) -> Result<(), String> {
-    // Stop any existing capture
-    stop_capture();
-    
-    let host = cpal::default_host();
+/// One input device opened for capture: resolved device, stream config and ring ends.
+struct CaptureSource {
+    device: Device,
+    sample_format: SampleFormat,
+    config: StreamConfig,
+    /// Interleaved channels to average per sample (`[0]` for a mono stream).
+    channel_select: Vec<usize>,
+    producer: Producer<RawFrame>,
+}
+
+/// Pick the capture config for a device: 48kHz mono when the device supports it in its
+/// native format; otherwise the default rate, resampled in the processing thread.
+/// With `channels`, the stream opens with all native channels and returns the ones to keep.
+fn input_stream_config(
+    device: &Device,
+    frame_samples: usize,
+    channels: Option<&[u16]>,
+) -> Result<(StreamConfig, SampleFormat, Vec<usize>), String> {
+    let config = device.default_input_config()
+        .map_err(|e| format!("Failed to get device config: {}", e))?;
     
-    // Find the device
-    let device: Device = if let Some(id) = device_id {
-        // Parse device index from ID (format: "input_0", "input_1", etc.)
-        if let Some(idx_str) = id.strip_prefix("input_") {
-            let idx: usize = idx_str.parse()
-                .map_err(|_| format!("Invalid device ID: {}", id))?;
-            
-            let input_devices: Vec<_> = host.input_devices()
-                .map_err(|e| format!("Failed to enumerate devices: {}", e))?
-                .collect();
-            
-            input_devices.get(idx)
-                .ok_or_else(|| format!("Device index {} not found", idx))?
-                .clone()
-        } else {
-            return Err(format!("Invalid device ID format: {}", id));
+    let (stream_channels, channel_select) = match channels {
+        None => (1, vec![0]),
+        Some([]) => return Err("Channel selection is empty".to_string()),
+        Some(selected) => {
+            let native = config.channels();
+            if let Some(bad) = selected.iter().find(|&&c| c >= native) {
+                return Err(format!("Input channel {} out of range (device has {})", bad, native));
+            }
+            (native, selected.iter().map(|&c| c as usize).collect())
         }
-    } else {
-        // Use default input device
-        host.default_input_device()
-            .ok_or_else(|| "No default input device available".to_string())?
     };
     
-    // Get default config
-    let config = device.default_input_config()
-        .map_err(|e| format!("Failed to get device config: {}", e))?;
-    
-    // We want 48kHz, mono, f32
-    // Try to use f32 format, fall back to device's native format
     let sample_format = config.sample_format();
-    let sample_rate = config.sample_rate();
-    
-    // Request 48kHz if supported, otherwise use device default
-    let target_sample_rate = if sample_rate.0 == 48000 {
-        sample_rate
+    let target_sample_rate = if supports_sample_rate(device, sample_format, PIPELINE_SAMPLE_RATE) {
+        cpal::SampleRate(PIPELINE_SAMPLE_RATE)
     } else {
-        // Try to find 48kHz in supported configs, or use default
-        sample_rate
+        config.sample_rate()
     };
     
-    // Explicit buffer size: 10 ms at 48 kHz. Do not trust driver defaults.
-    let stream_config = StreamConfig {
-        channels: 1,
-        sample_rate: target_sample_rate,
-        buffer_size: cpal::BufferSize::Fixed(FRAME_SAMPLES as u32),
+    // Explicit buffer size: one capture frame (10/20/40 ms at 48 kHz). Do not trust driver
+    // defaults, unless the device says it can't do it (open_source also retries on failure).
+    let buffer_size = match config.buffer_size() {
+        cpal::SupportedBufferSize::Range { min, max } if !(*min..=*max).contains(&(frame_samples as u32)) => {
+            eprintln!("Device buffer range {}-{} excludes {} samples, using its default", min, max, frame_samples);
+            cpal::BufferSize::Default
+        }
+        _ => cpal::BufferSize::Fixed(frame_samples as u32),
     };
+    Ok((
+        StreamConfig {
+            channels: stream_channels,
+            sample_rate: target_sample_rate,
+            buffer_size,
+        },
+        sample_format,
+        channel_select,
+    ))
+}
+
+/// Start audio capture from one or more input devices (None = default device).
+/// With several devices, each gets its own stream and ring; the processing thread
+/// aligns them and sums them into a single signal before the DSP stage.
+/// If a device disappears, capture restarts on the default input and `on_device_lost` is told.
+pub fn start_capture(
+    device_ids: Vec<Option<String>>,
+    options: CaptureOptions,
+    processed_frame_sender: mpsc::SyncSender<ProcessedFrame>,
+    level_update_sender: mpsc::Sender<f32>,
+    on_device_lost: DeviceLostHandler,
+    on_stall: CaptureStallHandler,
+) -> Result<(), String> {
+    // Stop any existing capture
+    stop_capture();
+    
+    let device_ids = if device_ids.is_empty() { vec![None] } else { device_ids };
+    let granules = options.frame_granules()?;
+    
+    // Lock-free ring per device: audio callback pushes, processing thread drains. Drop if full.
+    let mut sources = Vec::with_capacity(device_ids.len());
+    let mut lanes = Vec::with_capacity(device_ids.len());
+    for device_id in &device_ids {
+        let device = resolve_device(AudioDeviceKind::Input, device_id.as_deref())?;
+        let (config, sample_format, channel_select) =
+            input_stream_config(&device, FRAME_SAMPLES * granules, options.channels.as_deref())?;
+        let (producer, consumer) = RingBuffer::<RawFrame>::new(RAW_RING_CAP * granules);
+        lanes.push(SourceLane {
+            consumer,
+            // Always resample so the device clock can be corrected, even at 48 kHz.
+            resampler: FrameResampler::with_drift_compensation(config.sample_rate.0)?,
+            drift: DriftEstimator::new(config.sample_rate.0),
+            pending: VecDeque::with_capacity(MAX_LANE_BACKLOG * granules + 1),
+            max_backlog: MAX_LANE_BACKLOG * granules,
+        });
+        sources.push(CaptureSource { device, sample_format, config, channel_select, producer });
+    }
+    let sample_rate = sources[0].config.sample_rate.0;
 
     // Reset drop counters for this session (for dev overlay / debug log).
     DROPPED_RAW.store(0, Ordering::Relaxed);
     DROPPED_PROCESSED.store(0, Ordering::Relaxed);
+    // Pin the clock epoch before the first callback can stamp a frame.
+    monotonic_micros(Instant::now());
+    CAPTURE_DRIFT_PPM.store(0f64.to_bits(), Ordering::Relaxed);
+    CALLBACK_COUNT.store(0, Ordering::Relaxed);
+    PROCESSED_FRAMES.store(0, Ordering::Relaxed);
+    RAW_RING_PEAK.store(0, Ordering::Relaxed);
+    RAW_RING_CAPACITY.store(RAW_RING_CAP * granules, Ordering::Relaxed);
+    EMIT_LATENCY_US.store(0, Ordering::Relaxed);
+    SEQ_GAPS.store(0, Ordering::Relaxed);
+    NEXT_EMIT_SEQ.store(0, Ordering::Relaxed);
+    DSP_FRAME_US.store(0, Ordering::Relaxed);
+    DSP_FRAME_MAX_US.store(0, Ordering::Relaxed);
+    DSP_OVERRUNS.store(0, Ordering::Relaxed);
+    if let Ok(mut dsp) = crate::audio_dsp::get_dsp().lock() {
+        dsp.set_frame_duration_ms(granules as u32 * 10);
+    }
 
-    // Lock-free ring: audio callback pushes, processing thread drains. Drop if full.
-    let (raw_producer, raw_consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(RAW_RING_CAP);
+    let (control_tx, control_rx) = mpsc::channel::<StreamControl>();
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+    let exclusive = options.exclusive;
+    let device_lost = Arc::new(AtomicBool::new(false));
+    let lost_flag = device_lost.clone();
+    let stalled = Arc::new(AtomicBool::new(false));
+    let stall_flag = stalled.clone();
+    CAPTURE_PAUSED.store(false, Ordering::SeqCst);
 
-    // Build stream: callback must NOT allocate and NOT block; push to ring only.
-    let stream = match sample_format {
-        SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, raw_producer)?,
-        SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, raw_producer)?,
-        SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, raw_producer)?,
-        _ => return Err(format!("Unsupported sample format: {:?}", sample_format)),
-    };
+    // Stream owner thread: build + play, then serve pause/resume until the control
+    // channel closes. Build stream: callback must NOT allocate and NOT block; push to ring only.
+    let stream_thread = thread::spawn(move || {
+        let mut streams = Vec::with_capacity(sources.len());
+        for source in sources {
+            match open_source(source, exclusive, &lost_flag) {
+                Ok(s) => streams.push(s),
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            }
+        }
+        let _ = ready_tx.send(Ok(()));
+        loop {
+            match control_rx.recv_timeout(DEVICE_LOST_POLL) {
+                Ok(StreamControl::Pause(reply)) => {
+                    CAPTURE_PAUSED.store(true, Ordering::SeqCst);
+                    let _ = reply.send(streams.iter().try_for_each(|s| s.pause()));
+                }
+                Ok(StreamControl::Resume(reply)) => {
+                    let _ = reply.send(streams.iter().try_for_each(|s| s.play()));
+                    CAPTURE_PAUSED.store(false, Ordering::SeqCst);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    // Failover/rebuild restart capture, which joins this thread: run them elsewhere.
+                    if lost_flag.swap(false, Ordering::SeqCst) {
+                        let lost = lost_flag.clone();
+                        thread::spawn(move || fail_over_to_default(&lost));
+                    } else if stall_flag.swap(false, Ordering::SeqCst) {
+                        let stalled = stall_flag.clone();
+                        thread::spawn(move || restart_stalled(&stalled));
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        // Dropping the streams releases the devices and abandons the raw rings.
+        drop(streams);
+    });
 
-    stream.play().map_err(|e| format!("Failed to start stream: {}", e))?;
+    ready_rx.recv()
+        .map_err(|_| "Capture thread exited unexpectedly".to_string())??;
 
-    // Single producer: one thread drains raw ring → DSP → bounded channel (drop if full).
+    // Single producer: one thread drains raw rings → mix → DSP → bounded channel (drop if full).
     let processed_tx = processed_frame_sender.clone();
     let level_tx = level_update_sender.clone();
+    let stall_flag = stalled.clone();
     let processing_thread = thread::spawn(move || {
-        process_audio_frames(raw_consumer, processed_tx, level_tx);
+        process_audio_frames(lanes, granules, processed_tx, level_tx, stall_flag);
     });
     
-    // Store state (stream is kept alive by callback, we leak it to prevent drop)
     let mut state = AUDIO_CAPTURE_STATE.lock()
         .map_err(|_| "Failed to lock audio capture state".to_string())?;
     
-    // Keep stream alive by boxing and leaking it
-    // The stream will continue running until the callback stops receiving data
-    let _stream_box = Box::new(stream);
-    std::mem::forget(_stream_box);
-    
     *state = Some(AudioCaptureState {
         processed_frame_sender: Some(processed_frame_sender),
         level_update_sender: Some(level_update_sender),
-        sample_rate: target_sample_rate.0,
+        sample_rate,
+        control_tx: Some(control_tx),
+        stream_thread: Some(stream_thread),
         processing_thread: Some(processing_thread),
-        _stream_handle: Some(Arc::new(Mutex::new(()))),
+        device_ids,
+        options,
+        device_lost,
+        on_device_lost,
+        stalled,
+        on_stall,
     });
+    CAPTURE_SESSION.fetch_add(1, Ordering::SeqCst);
     
     Ok(())
 }
 
-/// Process audio frames: drain lock-free raw ring → DSP → push to bounded channel (drop if full).
+/// Restart capture on the default input after a device vanished, reusing the running
+/// session's channels so the emitters keep going. `lost` identifies the session that
+/// reported it; if capture was restarted or stopped meanwhile, there's nothing to do.
+fn fail_over_to_default(lost: &Arc<AtomicBool>) {
+    let Some(session) = restart_params(|state| Arc::ptr_eq(&state.device_lost, lost)) else { return };
+
+    eprintln!("Capture device lost, switching to the default input");
+    let on_device_lost = session.on_device_lost.clone();
+    // The default input may have a different channel layout; let the driver downmix.
+    let options = CaptureOptions { channels: None, ..session.options };
+    let result = start_capture(
+        vec![None],
+        options,
+        session.processed_tx,
+        session.level_tx,
+        session.on_device_lost,
+        session.on_stall,
+    );
+    on_device_lost(&DeviceLostEvent {
+        device_ids: session.device_ids,
+        failed_over: result.is_ok(),
+        error: result.err(),
+    });
+}
+
+/// Rebuild the streams of a session whose watchdog fired, on the same devices and channels.
+/// Gives up (and says so) after `MAX_STALL_RESTARTS` rebuilds that didn't bring audio back.
+fn restart_stalled(stalled: &Arc<AtomicBool>) {
+    let Some(session) = restart_params(|state| Arc::ptr_eq(&state.stalled, stalled)) else { return };
+
+    let on_stall = session.on_stall.clone();
+    let device_ids = session.device_ids.clone();
+    let result = if STALL_RESTARTS.fetch_add(1, Ordering::SeqCst) >= MAX_STALL_RESTARTS {
+        Err(format!("No audio after {} restarts; leaving capture as is", MAX_STALL_RESTARTS))
+    } else {
+        eprintln!("Capture stalled, rebuilding the input stream");
+        start_capture(
+            session.device_ids,
+            session.options,
+            session.processed_tx,
+            session.level_tx,
+            session.on_device_lost,
+            session.on_stall,
+        )
+    };
+    on_stall(&CaptureStallEvent {
+        device_ids,
+        stalled_ms: STALL_TIMEOUT.as_millis() as u64,
+        restarted: result.is_ok(),
+        error: result.err(),
+    });
+}
+
+/// What a restart needs from the running session.
+struct RestartParams {
+    device_ids: Vec<Option<String>>,
+    options: CaptureOptions,
+    processed_tx: mpsc::SyncSender<ProcessedFrame>,
+    level_tx: mpsc::Sender<f32>,
+    on_device_lost: DeviceLostHandler,
+    on_stall: CaptureStallHandler,
+}
+
+/// Snapshot the running session if `is_session` matches it (it may have been restarted or
+/// stopped since the flag was raised, in which case there's nothing to do).
+fn restart_params(is_session: impl Fn(&AudioCaptureState) -> bool) -> Option<RestartParams> {
+    let guard = AUDIO_CAPTURE_STATE.lock().ok()?;
+    let state = guard.as_ref().filter(|s| is_session(s))?;
+    Some(RestartParams {
+        device_ids: state.device_ids.clone(),
+        options: state.options.clone(),
+        processed_tx: state.processed_frame_sender.clone()?,
+        level_tx: state.level_update_sender.clone()?,
+        on_device_lost: state.on_device_lost.clone(),
+        on_stall: state.on_stall.clone(),
+    })
+}
+
+/// Ring producer handed to a cpal callback. It stays in the slot until the first callback
+/// takes it, so a stream that fails to build gives it back for a retry.
+type ProducerSlot = Arc<Mutex<Option<Producer<RawFrame>>>>;
+
+/// Open and start one capture source. With `exclusive`, try WASAPI exclusive mode first
+/// and fall back to a shared-mode cpal stream on the same ring if the device refuses.
+/// Drivers that reject the fixed buffer size get a second try with their default one.
+fn open_source(source: CaptureSource, exclusive: bool, device_lost: &Arc<AtomicBool>) -> Result<ActiveStream, String> {
+    let CaptureSource { device, sample_format, config, channel_select, mut producer } = source;
+    // Our exclusive client only opens mono/stereo, so channel selection needs shared mode.
+    if exclusive && config.channels > 1 {
+        eprintln!("Exclusive mode skipped: channel selection needs the shared-mode stream");
+    } else if exclusive {
+        let name = device.name().unwrap_or_default();
+        match start_exclusive_capture(&name, config.sample_rate.0, producer, &DROPPED_RAW, &CALLBACK_COUNT) {
+            Ok(capture) => return Ok(ActiveStream::Exclusive(capture)),
+            Err((e, returned)) => {
+                eprintln!("Exclusive mode unavailable for {}, using shared mode: {}", name, e);
+                producer = returned;
+            }
+        }
+    }
+    
+    let slot: ProducerSlot = Arc::new(Mutex::new(Some(producer)));
+    let build = |config: &StreamConfig| {
+        let (slot, lost) = (slot.clone(), device_lost.clone());
+        match sample_format {
+            // I32 also covers 24-bit interfaces, which cpal reports as I24-in-I32.
+            SampleFormat::F32 => build_stream::<f32>(&device, config, &channel_select, slot, lost),
+            SampleFormat::F64 => build_stream::<f64>(&device, config, &channel_select, slot, lost),
+            SampleFormat::I8 => build_stream::<i8>(&device, config, &channel_select, slot, lost),
+            SampleFormat::I16 => build_stream::<i16>(&device, config, &channel_select, slot, lost),
+            SampleFormat::I32 => build_stream::<i32>(&device, config, &channel_select, slot, lost),
+            SampleFormat::I64 => build_stream::<i64>(&device, config, &channel_select, slot, lost),
+            SampleFormat::U8 => build_stream::<u8>(&device, config, &channel_select, slot, lost),
+            SampleFormat::U16 => build_stream::<u16>(&device, config, &channel_select, slot, lost),
+            SampleFormat::U32 => build_stream::<u32>(&device, config, &channel_select, slot, lost),
+            SampleFormat::U64 => build_stream::<u64>(&device, config, &channel_select, slot, lost),
+            _ => Err(format!("Unsupported sample format: {:?}", sample_format)),
+        }
+    };
+    let stream = match build(&config) {
+        Ok(stream) => stream,
+        Err(e) if matches!(config.buffer_size, cpal::BufferSize::Fixed(_)) => {
+            eprintln!("Fixed buffer size rejected ({}), retrying with the device default", e);
+            build(&StreamConfig { buffer_size: cpal::BufferSize::Default, ..config.clone() })?
+        }
+        Err(e) => return Err(e),
+    };
+    stream.play().map_err(|e| format!("Failed to start stream: {}", e))?;
+    Ok(ActiveStream::Cpal(stream))
+}
+
+/// Whether the device can capture at `rate` in the given sample format.
+fn supports_sample_rate(device: &Device, format: SampleFormat, rate: u32) -> bool {
+    let rate = cpal::SampleRate(rate);
+    device
+        .supported_input_configs()
+        .map(|mut configs| {
+            configs.any(|c| {
+                c.sample_format() == format && c.min_sample_rate() <= rate && c.max_sample_rate() >= rate
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// One capture device as seen by the processing thread: its raw ring, resampler and
+/// 48 kHz frames waiting to be mixed with the other devices.
+struct SourceLane {
+    consumer: rtrb::Consumer<RawFrame>,
+    resampler: FrameResampler,
+    /// Measures this device's real clock so the resampler can absorb the difference.
+    drift: DriftEstimator,
+    /// Resampled frames, stamped with the capture time of the raw frame that completed them.
+    pending: VecDeque<([f32; FRAME_SAMPLES], Instant)>,
+    /// Backlog cap in 10 ms granules (scaled with the capture frame duration).
+    max_backlog: usize,
+}
+
+impl SourceLane {
+    /// Drain the raw ring into `pending`. Returns whether anything was read, plus the
+    /// updated drift estimate when a measurement window completes.
+    fn fill(&mut self) -> (bool, Option<f64>) {
+        let mut got_any = false;
+        let mut drift_update = None;
+        RAW_RING_PEAK.fetch_max(self.consumer.slots(), Ordering::Relaxed);
+        while let Ok(raw) = self.consumer.pop() {
+            got_any = true;
+            if let Some(ppm) = self.drift.record(raw.len, Instant::now()) {
+                self.resampler.set_drift_ppm(ppm);
+                drift_update = Some(ppm);
+            }
+            let pending = &mut self.pending;
+            let max_backlog = self.max_backlog;
+            // The resampler buffers its input, so short frames (default buffer size) are
+            // reframed into full 480-sample frames here.
+            self.resampler.process(&raw.samples[..raw.len], |frame_48k| {
+                pending.push_back((*frame_48k, raw.captured_at));
+                // A lane this far ahead means another device stalled; drop its oldest audio.
+                if pending.len() > max_backlog {
+                    pending.pop_front();
+                    DROPPED_RAW.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+        (got_any, drift_update)
+    }
+
+    /// Still expecting audio but nothing buffered yet.
+    fn is_waiting(&self) -> bool {
+        self.pending.is_empty() && !self.consumer.is_abandoned()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.pending.is_empty() && self.consumer.is_abandoned() && self.consumer.is_empty()
+    }
+}
+
+/// Next mixed frame: one frame from every lane, summed. Waits (returns None) while any
+/// live lane is empty, unless another lane has built up `slack` frames, in which
+/// case the lagging device contributes silence rather than stalling the others.
+/// The mixed frame carries the earliest capture time of its parts.
+fn next_mixed_frame(lanes: &mut [SourceLane], slack: usize) -> Option<([f32; FRAME_SAMPLES], Instant)> {
+    let any_pending = lanes.iter().any(|l| !l.pending.is_empty());
+    let all_ready = !lanes.iter().any(|l| l.is_waiting());
+    let overdue = lanes.iter().any(|l| l.pending.len() >= slack);
+    if !any_pending || !(all_ready || overdue) {
+        return None;
+    }
+    if lanes.len() == 1 {
+        return lanes[0].pending.pop_front();
+    }
+    let mut mixed = [0.0f32; FRAME_SAMPLES];
+    let mut captured_at: Option<Instant> = None;
+    for lane in lanes.iter_mut() {
+        if let Some((frame, at)) = lane.pending.pop_front() {
+            for (out, s) in mixed.iter_mut().zip(frame.iter()) {
+                *out += s;
+            }
+            captured_at = Some(captured_at.map_or(at, |c| c.min(at)));
+        }
+    }
+    for s in mixed.iter_mut() {
+        *s = s.clamp(-1.0, 1.0);
+    }
+    captured_at.map(|at| (mixed, at))
+}
+
+/// Per-frame DSP timing for the processing thread; publishes to the `DSP_FRAME_*` counters.
+struct DspTiming {
+    window_start: Instant,
+    window_max_us: u64,
+}
+
+impl DspTiming {
+    fn new() -> Self {
+        Self { window_start: Instant::now(), window_max_us: 0 }
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        DSP_FRAME_US.store(us, Ordering::Relaxed);
+        if elapsed > DSP_OVERRUN_BUDGET {
+            DSP_OVERRUNS.fetch_add(1, Ordering::Relaxed);
+        }
+        self.window_max_us = self.window_max_us.max(us);
+        if self.window_start.elapsed() >= DSP_TIMING_WINDOW {
+            DSP_FRAME_MAX_US.store(self.window_max_us, Ordering::Relaxed);
+            self.window_max_us = 0;
+            self.window_start = Instant::now();
+        }
+    }
+}
+
+/// Wake the processing thread after pushing raw frames. Never blocks: if the handle is
+/// busy, the thread's park timeout picks the frames up instead.
+pub(crate) fn wake_processing() {
+    if let Ok(thread) = PROCESSING_THREAD.try_lock() {
+        if let Some(thread) = thread.as_ref() {
+            thread.unpark();
+        }
+    }
+}
+
+/// Process audio frames: drain lock-free raw rings → resample to 48 kHz → mix devices →
+/// group `granules` 10 ms frames into one capture frame → DSP → push to bounded channel.
 /// Never block; if processed channel is full, drop frame (audio loss > latency).
+/// Also the stall watchdog: raises `stalled` once if no device audio arrives for
+/// `STALL_TIMEOUT` while capture isn't paused. Runs at realtime priority where allowed and
+/// parks between frames.
 fn process_audio_frames(
-    mut raw_consumer: rtrb::Consumer<[f32; FRAME_SAMPLES]>,
-    processed_sender: mpsc::SyncSender<Vec<f32>>,
+    mut lanes: Vec<SourceLane>,
+    granules: usize,
+    processed_sender: mpsc::SyncSender<ProcessedFrame>,
     level_sender: mpsc::Sender<f32>,
+    stalled: Arc<AtomicBool>,
 ) {
     use crate::audio_dsp::get_dsp;
+    use crate::audio_recording::{tee_frame, RecordingSource};
     let dsp = get_dsp();
+    let mut seq: u64 = 0;
+    let slack = MIX_ALIGN_SLACK * granules;
+    // Mixed audio for the capture frame being assembled, stamped with its first granule.
+    let mut frame: Vec<f32> = Vec::with_capacity(FRAME_SAMPLES * granules);
+    let mut frame_captured_at: Option<Instant> = None;
+    let mut last_input = Instant::now();
+    let mut stall_reported = false;
+    let mut dsp_timing = DspTiming::new();
+    let _priority = crate::audio_priority::promote_current_thread()
+        .map_err(|e| eprintln!("Capture processing stays at normal priority: {}", e))
+        .ok();
+    if let Ok(mut slot) = PROCESSING_THREAD.lock() {
+        *slot = Some(thread::current());
+    }
 
     loop {
-        let frame = match raw_consumer.pop() {
-            Ok(f) => f,
-            Err(rtrb::PopError::Empty) => {
-                if raw_consumer.is_abandoned() {
-                    break;
-                }
-                std::thread::yield_now();
+        let mut got_any = false;
+        for (i, lane) in lanes.iter_mut().enumerate() {
+            let (got, drift_update) = lane.fill();
+            got_any |= got;
+            if let (0, Some(ppm)) = (i, drift_update) {
+                CAPTURE_DRIFT_PPM.store(ppm.to_bits(), Ordering::Relaxed);
+            }
+        }
+
+        if got_any {
+            // Audio is flowing (again): a later stall gets a fresh set of restarts.
+            if STALL_RESTARTS.load(Ordering::Relaxed) != 0 {
+                STALL_RESTARTS.store(0, Ordering::SeqCst);
+            }
+            stall_reported = false;
+            last_input = Instant::now();
+        } else if CAPTURE_PAUSED.load(Ordering::Relaxed) {
+            last_input = Instant::now();
+        } else if !stall_reported && last_input.elapsed() >= STALL_TIMEOUT {
+            stall_reported = true;
+            stalled.store(true, Ordering::SeqCst);
+        }
+
+        while let Some((granule, captured_at)) = next_mixed_frame(&mut lanes, slack) {
+            frame.extend_from_slice(&granule);
+            let captured_at = *frame_captured_at.get_or_insert(captured_at);
+            if frame.len() < FRAME_SAMPLES * granules {
                 continue;
             }
-        };
-        let raw_slice = &frame[..];
+            frame_captured_at = None;
 
-        let (processed, level) = {
-            let mut dsp_guard = match dsp.lock() {
-                Ok(g) => g,
-                Err(_) => break,
+            tee_frame(RecordingSource::Raw, &frame);
+            crate::audio_calibration::tee_calibration(&frame);
+            let mut processed = take_frame_buffer();
+            let level = {
+                let mut dsp_guard = match dsp.lock() {
+                    Ok(g) => g,
+                    Err(_) => return,
+                };
+                let started = Instant::now();
+                let level = dsp_guard.process_frame(&frame, captured_at, &mut processed);
+                dsp_timing.record(started.elapsed());
+                level
             };
-            dsp_guard.process_frame(raw_slice)
-        };
+            frame.clear();
+
+            PROCESSED_FRAMES.fetch_add(1, Ordering::Relaxed);
+            tee_frame(RecordingSource::Processed, &processed);
+            crate::audio_multitrack::tee_local(&processed);
+            crate::audio_output::push_sidetone(&processed);
+            crate::audio_spectrum::tee_spectrum(&processed);
+            let frame = ProcessedFrame {
+                pcm: processed,
+                capture_ts: monotonic_micros(captured_at),
+                seq,
+            };
+            seq += 1;
+
+            // Non-blocking: if emitter is behind (>30ms backlog), drop this frame.
+            if !try_send_frame(&processed_sender, frame) {
+                DROPPED_PROCESSED.fetch_add(1, Ordering::Relaxed);
+            }
+            let _ = level_sender.send(level);
+        }
 
-        // Non-blocking: if emitter is behind (>30ms backlog), drop this frame.
-        if processed_sender.try_send(processed).is_err() {
-            DROPPED_PROCESSED.fetch_add(1, Ordering::Relaxed);
+        if lanes.iter().all(|l| l.is_finished()) {
+            break;
+        }
+        if !got_any {
+            thread::park_timeout(PROCESSING_PARK);
+        }
+    }
+
+    if let Ok(mut slot) = PROCESSING_THREAD.lock() {
+        if slot.as_ref().map(|t| t.id()) == Some(thread::current().id()) {
+            *slot = None;
         }
-        let _ = level_sender.send(level);
     }
 }
 
@@ -256,27 +1251,55 @@ fn process_audio_frames(
 fn build_stream<T>(
     device: &Device,
     config: &StreamConfig,
-    mut raw_producer: Producer<[f32; FRAME_SAMPLES]>,
+    channel_select: &[usize],
+    producer_slot: ProducerSlot,
+    device_lost: Arc<AtomicBool>,
 ) -> Result<Stream, String>
 where
     T: cpal::SizedSample,
     f32: cpal::FromSample<T>,
 {
-    let err_fn = |err| eprintln!("Audio stream error: {}", err);
+    let err_fn = move |err: cpal::StreamError| {
+        eprintln!("Audio stream error: {}", err);
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            device_lost.store(true, Ordering::SeqCst);
+        }
+    };
+
+    let channels = config.channels as usize;
+    // Allocated once here; the callback only reads it.
+    let channel_select = channel_select.to_vec();
+    let scale = 1.0 / channel_select.len() as f32;
+    let mut raw_producer: Option<Producer<RawFrame>> = None;
 
     let stream = device.build_input_stream(
         config,
         move |data: &[T], _: &cpal::InputCallbackInfo| {
-            // Stack-only: no heap allocation. Copy into fixed buffer.
-            let mut frame = [0.0f32; FRAME_SAMPLES];
-            let len = data.len().min(FRAME_SAMPLES);
-            for (i, s) in data.iter().take(len).enumerate() {
-                frame[i] = <f32 as cpal::FromSample<T>>::from_sample_(*s);
+            CALLBACK_COUNT.fetch_add(1, Ordering::Relaxed);
+            if raw_producer.is_none() {
+                raw_producer = producer_slot.try_lock().ok().and_then(|mut slot| slot.take());
             }
-            // Push to lock-free ring; if full (JS/emitter behind), drop. Never block.
-            if raw_producer.push(frame).is_err() {
-                DROPPED_RAW.fetch_add(1, Ordering::Relaxed);
+            let Some(raw_producer) = raw_producer.as_mut() else { return };
+            let captured_at = Instant::now();
+            // 20/40 ms buffers arrive as one callback; split into 10 ms ring frames.
+            // Default-size buffers leave a short tail frame, reframed downstream.
+            for chunk in data.chunks(FRAME_SAMPLES * channels) {
+                // Stack-only: no heap allocation. Copy (selected channels averaged) into fixed buffer.
+                let mut frame = [0.0f32; FRAME_SAMPLES];
+                for (out, s) in frame.iter_mut().zip(chunk.chunks_exact(channels)) {
+                    let sum: f32 = channel_select
+                        .iter()
+                        .map(|&c| <f32 as cpal::FromSample<T>>::from_sample_(s[c]))
+                        .sum();
+                    *out = sum * scale;
+                }
+                let len = chunk.len() / channels;
+                // Push to lock-free ring; if full (JS/emitter behind), drop. Never block.
+                if raw_producer.push(RawFrame { samples: frame, len, captured_at }).is_err() {
+                    DROPPED_RAW.fetch_add(1, Ordering::Relaxed);
+                }
             }
+            wake_processing();
         },
         err_fn,
         None,
@@ -293,23 +1316,50 @@ pub fn stop_capture() {
         Err(_) => return,
     };
     
+    CAPTURE_SESSION.fetch_add(1, Ordering::SeqCst);
     if let Some(mut state) = state_guard.take() {
-        // Close channels (this will cause processing thread to exit)
-        // Dropping the senders will close the channels
+        // Close the control channel: the owner thread drops the stream and exits.
+        state.control_tx.take();
+        if let Some(thread) = state.stream_thread.take() {
+            let _ = thread.join();
+        }
+        
+        // Stream dropped → raw ring abandoned → processing thread drains and exits,
+        // closing the processed/level channels behind it.
         state.processed_frame_sender.take();
         state.level_update_sender.take();
-        
-        // Wait for processing thread to finish
         if let Some(thread) = state.processing_thread.take() {
             let _ = thread.join();
         }
-        
-        // Stream is managed by cpal and will be cleaned up when dropped
-        // Since we leaked it, we can't directly stop it, but closing channels
-        // will stop the processing loop
     }
 }
 
+/// Send a control request to the stream owner thread and wait for its result.
+fn send_stream_control(make: fn(mpsc::Sender<Result<(), String>>) -> StreamControl) -> Result<(), String> {
+    let (reply_tx, reply_rx) = mpsc::channel();
+    {
+        let state = AUDIO_CAPTURE_STATE.lock()
+            .map_err(|_| "Failed to lock audio capture state".to_string())?;
+        let control_tx = state.as_ref()
+            .and_then(|s| s.control_tx.as_ref())
+            .ok_or_else(|| "Audio capture not started".to_string())?;
+        control_tx.send(make(reply_tx))
+            .map_err(|_| "Capture thread is not running".to_string())?;
+    }
+    reply_rx.recv()
+        .map_err(|_| "Capture thread is not running".to_string())?
+}
+
+/// Pause the capture stream without releasing the device (e.g. while deafened).
+pub fn pause_capture() -> Result<(), String> {
+    send_stream_control(StreamControl::Pause)
+}
+
+/// Resume a paused capture stream.
+pub fn resume_capture() -> Result<(), String> {
+    send_stream_control(StreamControl::Resume)
+}
+
 // Note: Frame receivers are created in start_capture and passed to Tauri command
 // They're managed via Tauri events, not returned directly
 
@@ -318,15 +1368,116 @@ pub fn stop_capture() {
 pub struct AudioDropStats {
     pub dropped_raw: u64,
     pub dropped_processed: u64,
+    /// Remote frames dropped by the native playback mixer (see audio_output).
+    pub dropped_playback: u64,
+    /// Desktop-audio frames dropped by loopback capture (see audio_loopback).
+    pub dropped_loopback: u64,
+    /// Per-application capture frames dropped (see app_audio_capture).
+    pub dropped_app_capture: u64,
+    /// Estimated capture device clock drift in ppm (positive = device runs fast).
+    pub capture_drift_ppm: f64,
+    /// DSP processing time of the most recent capture frame, in ms.
+    pub dsp_frame_ms: f64,
+    /// Slowest DSP frame over the last second, in ms.
+    pub dsp_frame_max_ms: f64,
+    /// Frames whose DSP took longer than 10 ms since capture started.
+    pub dsp_overruns: u64,
 }
 
 pub fn get_audio_drop_stats() -> AudioDropStats {
     AudioDropStats {
         dropped_raw: DROPPED_RAW.load(Ordering::Relaxed),
         dropped_processed: DROPPED_PROCESSED.load(Ordering::Relaxed),
+        dropped_playback: crate::audio_output::dropped_playback_frames(),
+        dropped_loopback: crate::audio_loopback::dropped_loopback_frames(),
+        dropped_app_capture: crate::app_audio_capture::dropped_app_capture_frames(),
+        capture_drift_ppm: f64::from_bits(CAPTURE_DRIFT_PPM.load(Ordering::Relaxed)),
+        dsp_frame_ms: DSP_FRAME_US.load(Ordering::Relaxed) as f64 / 1000.0,
+        dsp_frame_max_ms: DSP_FRAME_MAX_US.load(Ordering::Relaxed) as f64 / 1000.0,
+        dsp_overruns: DSP_OVERRUNS.load(Ordering::Relaxed),
+    }
+}
+
+/// Live pipeline health, pushed once a second while capture runs (see `CaptureStatsMeter`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureStats {
+    pub dropped_raw: u64,
+    pub dropped_processed: u64,
+    /// Peak raw ring fill (frames) during the last interval, out of `ring_capacity`.
+    pub ring_fill: usize,
+    pub ring_capacity: usize,
+    pub callback_count: u64,
+    /// Processed frames per second over the last interval (real time = 1000 / frame ms).
+    pub frame_rate: f64,
+    /// Capture→emit latency of the most recent frame, in ms.
+    pub latency_ms: f64,
+    /// Frames missing from the emitted sequence since capture started.
+    pub seq_gaps: u64,
+}
+
+/// Samples capture counters for one capture session; turns totals into per-interval rates.
+pub struct CaptureStatsMeter {
+    session: u64,
+    last_frames: u64,
+    last_at: Instant,
+}
+
+impl CaptureStatsMeter {
+    /// Interval the stats event is emitted at.
+    pub const INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Meter for the capture session that is currently running.
+    pub fn new() -> Self {
+        Self {
+            session: CAPTURE_SESSION.load(Ordering::SeqCst),
+            last_frames: PROCESSED_FRAMES.load(Ordering::Relaxed),
+            last_at: Instant::now(),
+        }
+    }
+
+    /// Take a sample. None once the session this meter was created for has stopped.
+    pub fn sample(&mut self) -> Option<CaptureStats> {
+        if CAPTURE_SESSION.load(Ordering::SeqCst) != self.session {
+            return None;
+        }
+        let now = Instant::now();
+        let frames = PROCESSED_FRAMES.load(Ordering::Relaxed);
+        let elapsed = now.duration_since(self.last_at).as_secs_f64();
+        let frame_rate = if elapsed > 0.0 {
+            frames.saturating_sub(self.last_frames) as f64 / elapsed
+        } else {
+            0.0
+        };
+        self.last_frames = frames;
+        self.last_at = now;
+        Some(CaptureStats {
+            dropped_raw: DROPPED_RAW.load(Ordering::Relaxed),
+            dropped_processed: DROPPED_PROCESSED.load(Ordering::Relaxed),
+            ring_fill: RAW_RING_PEAK.swap(0, Ordering::Relaxed),
+            ring_capacity: RAW_RING_CAPACITY.load(Ordering::Relaxed),
+            callback_count: CALLBACK_COUNT.load(Ordering::Relaxed),
+            frame_rate,
+            latency_ms: EMIT_LATENCY_US.load(Ordering::Relaxed) as f64 / 1000.0,
+            seq_gaps: SEQ_GAPS.load(Ordering::Relaxed),
+        })
     }
 }
 
+/// Called by the mic emitter for each frame it sends to the frontend: records end-to-end
+/// latency and sequence gaps for the stats event.
+pub fn note_emitted_frame(frame: &ProcessedFrame) {
+    let now = monotonic_micros(Instant::now());
+    EMIT_LATENCY_US.store(now.saturating_sub(frame.capture_ts), Ordering::Relaxed);
+    let expected = NEXT_EMIT_SEQ.swap(frame.seq + 1, Ordering::Relaxed);
+    if frame.seq > expected {
+        SEQ_GAPS.fetch_add(frame.seq - expected, Ordering::Relaxed);
+    }
+}
+
+pub fn is_capturing() -> bool {
+    AUDIO_CAPTURE_STATE.lock().map(|s| s.is_some()).unwrap_or(false)
+}
+
 /// Get current sample rate
 pub fn get_sample_rate() -> Result<u32, String> {
     let state = AUDIO_CAPTURE_STATE.lock()
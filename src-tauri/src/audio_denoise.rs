@@ -0,0 +1,110 @@
+//! Pluggable noise suppression backends for the DSP's noise suppression stage.
+//!
+//! A backend implements [`DenoiserPlugin`] and is registered by name with
+//! [`register_denoiser`]; AudioDSP only ever talks to the trait. Backends are registered
+//! at startup in [`register_builtin_denoisers`]; optional ones (DeepFilterNet, vendor SDKs)
+//! go there behind their own cargo features.
+
+use nnnoiseless::DenoiseState;
+use std::sync::Mutex;
+
+pub const DEFAULT_DENOISER: &str = "rnnoise";
+
+/// A noise suppressor running on the capture processing thread.
+pub trait DenoiserPlugin: Send {
+    /// Denoise 48 kHz mono audio in place. `samples` is a whole number of 10 ms frames.
+    /// Runs once per capture frame, so it must not block; allocate in the factory instead.
+    fn process_frame(&mut self, samples: &mut [f32]);
+
+    /// Blend of denoised (1.0) and original (0.0) audio. Backends that can't blend may
+    /// ignore it.
+    fn set_strength(&mut self, _strength: f32) {}
+}
+
+/// Builds a backend at the given strength.
+pub type DenoiserFactory = fn(strength: f32) -> Box<dyn DenoiserPlugin>;
+
+static DENOISERS: Mutex<Vec<(&'static str, DenoiserFactory)>> = Mutex::new(Vec::new());
+
+/// Make a backend available under `name`, replacing any earlier one with that name.
+pub fn register_denoiser(name: &'static str, factory: DenoiserFactory) {
+    if let Ok(mut denoisers) = DENOISERS.lock() {
+        denoisers.retain(|(n, _)| *n != name);
+        denoisers.push((name, factory));
+    }
+}
+
+/// Register the backends compiled into this build. Called once at startup.
+pub fn register_builtin_denoisers() {
+    register_denoiser(DEFAULT_DENOISER, |strength| Box::new(Rnnoise::new(strength)));
+}
+
+/// Names of every registered backend, in registration order.
+pub fn denoiser_names() -> Vec<&'static str> {
+    DENOISERS
+        .lock()
+        .map(|denoisers| denoisers.iter().map(|(n, _)| *n).collect())
+        .unwrap_or_default()
+}
+
+/// Instantiate the backend called `name`.
+pub fn create_denoiser(name: &str, strength: f32) -> Result<Box<dyn DenoiserPlugin>, String> {
+    let registered = DENOISERS
+        .lock()
+        .map_err(|_| "Failed to lock denoiser registry".to_string())?
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, factory)| *factory);
+    match registered {
+        Some(factory) => Ok(factory(strength)),
+        None => Err(format!("Unknown denoiser: {}", name)),
+    }
+}
+
+/// RNNoise works on 10 ms frames at 48 kHz; capture frames are whole multiples of it.
+pub(crate) const RNNOISE_FRAME: usize = DenoiseState::FRAME_SIZE;
+/// RNNoise expects 16-bit sample magnitudes in f32.
+pub(crate) const RNNOISE_SCALE: f32 = 32767.0;
+
+/// Noise suppression (nnnoiseless). The denoised signal lags its input by one frame, so the
+/// dry signal mixed back in below full strength is delayed by the same amount.
+struct Rnnoise {
+    state: Box<DenoiseState<'static>>,
+    strength: f32,
+    dry: [f32; RNNOISE_FRAME],
+    input: [f32; RNNOISE_FRAME],
+    output: [f32; RNNOISE_FRAME],
+}
+
+impl Rnnoise {
+    fn new(strength: f32) -> Self {
+        Self {
+            state: DenoiseState::new(),
+            strength,
+            dry: [0.0; RNNOISE_FRAME],
+            input: [0.0; RNNOISE_FRAME],
+            output: [0.0; RNNOISE_FRAME],
+        }
+    }
+}
+
+impl DenoiserPlugin for Rnnoise {
+    fn process_frame(&mut self, samples: &mut [f32]) {
+        for chunk in samples.chunks_mut(RNNOISE_FRAME) {
+            self.input.fill(0.0);
+            for (dst, &s) in self.input.iter_mut().zip(chunk.iter()) {
+                *dst = s * RNNOISE_SCALE;
+            }
+            self.state.process_frame(&mut self.output, &self.input);
+            for ((s, &wet), dry) in chunk.iter_mut().zip(&self.output).zip(self.dry.iter_mut()) {
+                let input = *s;
+                *s = wet / RNNOISE_SCALE * self.strength + *dry * (1.0 - self.strength);
+                *dry = input;
+            }
+        }
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+    }
+}
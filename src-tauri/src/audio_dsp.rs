@@ -1,10 +1,25 @@
+use crate::audio_aec::EchoCanceller;
+#[cfg(feature = "fun-effects")]
+use crate::audio_voice_fx::VoiceChanger;
+use crate::audio_resample::PIPELINE_SAMPLE_RATE;
+use crate::audio_denoise::{create_denoiser, DenoiserPlugin, DEFAULT_DENOISER, RNNOISE_FRAME, RNNOISE_SCALE};
+use nnnoiseless::DenoiseState;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// Input mode for audio processing
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMode {
+    /// Speech detector (RNNoise VAD) plus the level threshold.
     VoiceActivity,
+    /// Level threshold only; the old behaviour, for when the VAD misjudges a voice.
+    LevelGate,
     PushToTalk,
+    /// Voice activity as usual, but holding PTT forces the gate open (priority talk).
+    Hybrid,
+    /// Always transmit (no gate); the meter still runs.
+    OpenMic,
 }
 
 /// DSP pipeline for audio processing
@@ -15,20 +30,968 @@ pub struct AudioDSP {
     threshold: f32,
     input_mode: InputMode,
     ptt_pressed: bool,
+    /// Keep transmitting this long after PTT is released.
+    ptt_release_ms: u32,
+    /// Remaining release tail; reloaded while PTT is held.
+    ptt_tail_ms: u32,
     transmission_muted: bool,
+    /// Debounced open/closed state of the gate, as peers hear it.
+    transmission: TransmissionState,
     
     // Envelope tracking (for level meter)
     displayed_level: f32,
+    /// dBFS meter for the last frame; `clipped` latches until read.
+    meter: MeterReading,
+    level_smoother: LevelSmoother,
     current_gain: f32,  // Smoothed gain for gating
     
-    // Constants (matching JS implementation)
+    // Meter range; start at the JS implementation's constants, then follow the learned
+    // noise floor of the current mic
     noise_floor: f32,
     max_level: f32,
+    noise_tracker: NoiseFloorTracker,
+    /// Meter constants as set (per 10 ms frame); the coefficients below are scaled from it.
+    meter_tuning: MeterTuning,
+    frame_ms: u32,
     decay_factor: f32,
     attack_coeff: f32,
     release_coeff: f32,
+
+    /// Raw mode for troubleshooting: skip every stage except gain.
+    bypass: bool,
+    /// Stage order and bypass flags; every stage appears exactly once.
+    chain: [DspStageConfig; DSP_STAGE_COUNT],
+    /// Rumble/plosive filter.
+    high_pass: Option<Biquad>,
+    high_pass_hz: f32,
+    /// Optional echo canceller.
+    echo_canceller: Option<EchoCanceller>,
+    /// Speech detector for `InputMode::VoiceActivity`/`Hybrid`; only exists in those modes.
+    voice_detector: Option<VoiceDetector>,
+    /// Optional noise suppression, from whichever backend is selected.
+    noise_suppressor: Option<Box<dyn DenoiserPlugin>>,
+    denoiser: String,
+    noise_suppression_strength: f32,
+    /// Parametric EQ bands, in order; empty = off.
+    eq: Vec<Biquad>,
+    /// The (clamped) band settings behind `eq`, for presets.
+    eq_bands: Vec<EqBand>,
+    /// Optional presence boost + de-esser for muffled mics.
+    clarity: Option<Clarity>,
+    /// Optional automatic gain on top of the manual gain.
+    auto_gain: Option<AutoGain>,
+    /// Optional compressor + limiter on what actually gets transmitted.
+    compressor: Option<Compressor>,
+    /// Optional pitch/formant shifter (`fun-effects` builds only).
+    #[cfg(feature = "fun-effects")]
+    voice_changer: Option<VoiceChanger>,
+    /// Always last, outside the configurable chain: nothing leaves the DSP clipped.
+    output_limiter: LookaheadLimiter,
+}
+
+/// Floor for dBFS readings (digital silence would be -inf, which JSON can't carry).
+const METER_FLOOR_DB: f32 = -120.0;
+/// A sample this close to full scale counts as clipping.
+const CLIP_LEVEL: f32 = 0.999;
+
+/// Per-frame dBFS levels of the mic as captured and as transmitted, for proper meters.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MeterReading {
+    pub input_peak_dbfs: f32,
+    pub input_rms_dbfs: f32,
+    pub output_peak_dbfs: f32,
+    pub output_rms_dbfs: f32,
+    /// A sample reached full scale (input or output) since the last read.
+    pub clipped: bool,
+    /// Learned ambient floor the meter and gate treat as silence.
+    pub noise_floor_dbfs: f32,
+    /// UI meter level (0-1) under the selected smoothing profile.
+    pub level: f32,
+    /// Highest recent `level`; held ~1.5 s, then falls.
+    pub peak_hold: f32,
+}
+
+impl Default for MeterReading {
+    fn default() -> Self {
+        Self {
+            input_peak_dbfs: METER_FLOOR_DB,
+            input_rms_dbfs: METER_FLOOR_DB,
+            output_peak_dbfs: METER_FLOOR_DB,
+            output_rms_dbfs: METER_FLOOR_DB,
+            clipped: false,
+            noise_floor_dbfs: linear_to_db(DEFAULT_NOISE_FLOOR),
+            level: 0.0,
+            peak_hold: 0.0,
+        }
+    }
+}
+
+/// Gate gain at or above this counts as transmitting.
+const TRANSMIT_GAIN: f32 = 0.1;
+/// A new gate state must hold this long before it's reported, so VAD flutter and PTT
+/// bounce don't flicker the speaking indicator.
+const TRANSMIT_OPEN_MS: u32 = 20;
+const TRANSMIT_CLOSE_MS: u32 = 100;
+
+/// Debounced transmission state, with a flag for the change not yet reported.
+struct TransmissionState {
+    open: bool,
+    pending_ms: u32,
+    changed: bool,
+}
+
+impl TransmissionState {
+    fn new() -> Self {
+        Self { open: false, pending_ms: 0, changed: false }
+    }
+
+    fn update(&mut self, gain: f32, frame_ms: u32) {
+        let open = gain >= TRANSMIT_GAIN;
+        if open == self.open {
+            self.pending_ms = 0;
+            return;
+        }
+        self.pending_ms += frame_ms;
+        if self.pending_ms >= if open { TRANSMIT_OPEN_MS } else { TRANSMIT_CLOSE_MS } {
+            self.open = open;
+            self.pending_ms = 0;
+            self.changed = true;
+        }
+    }
+}
+
+/// How the UI meter's `level` moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeterSmoothing {
+    /// Instant rise, quick fall: the same level as `cordia:audio-level`.
+    #[default]
+    Peak,
+    /// Instant rise, slow steady fall (PPM-style); easier to read on speech.
+    Ppm,
+    /// ~300 ms average both ways (VU-style).
+    Vu,
+}
+
+const PEAK_HOLD_MS: u32 = 1500;
+/// Meter scale per second the hold bar falls once the hold time is up.
+const PEAK_HOLD_FALL_PER_SEC: f32 = 1.0;
+const PPM_FALL_PER_SEC: f32 = 0.6;
+const VU_TIME_MS: f32 = 300.0;
+
+/// Smoothed UI level plus a peak-hold bar, on the 0-1 meter scale.
+struct LevelSmoother {
+    profile: MeterSmoothing,
+    level: f32,
+    hold: f32,
+    hold_ms: u32,
+}
+
+impl LevelSmoother {
+    fn new() -> Self {
+        Self { profile: MeterSmoothing::default(), level: 0.0, hold: 0.0, hold_ms: 0 }
+    }
+
+    fn update(&mut self, level: f32, frame_ms: u32) {
+        let seconds = frame_ms as f32 / 1000.0;
+        self.level = match self.profile {
+            MeterSmoothing::Peak => level,
+            MeterSmoothing::Ppm => level.max(self.level - PPM_FALL_PER_SEC * seconds),
+            MeterSmoothing::Vu => {
+                self.level + (level - self.level) * (1.0 - (-(frame_ms as f32) / VU_TIME_MS).exp())
+            }
+        };
+        if self.level >= self.hold {
+            self.hold = self.level;
+            self.hold_ms = PEAK_HOLD_MS;
+        } else if self.hold_ms > 0 {
+            self.hold_ms = self.hold_ms.saturating_sub(frame_ms);
+        } else {
+            self.hold = (self.hold - PEAK_HOLD_FALL_PER_SEC * seconds).max(self.level);
+        }
+    }
+}
+
+// Hot-path helpers. Written as fixed-width lanes with independent accumulators so the
+// compiler vectorizes them (SSE/AVX/NEON) on stable Rust.
+const LANES: usize = 8;
+
+/// Largest absolute sample.
+fn peak_abs(samples: &[f32]) -> f32 {
+    let mut acc = [0.0f32; LANES];
+    let chunks = samples.chunks_exact(LANES);
+    let rest = chunks.remainder();
+    for chunk in chunks {
+        for (a, &s) in acc.iter_mut().zip(chunk) {
+            let v = s.abs();
+            *a = if v > *a { v } else { *a };
+        }
+    }
+    rest.iter().chain(acc.iter()).fold(0.0f32, |a, s| a.max(s.abs()))
+}
+
+/// Root mean square.
+fn rms(samples: &[f32]) -> f32 {
+    let mut acc = [0.0f32; LANES];
+    let chunks = samples.chunks_exact(LANES);
+    let rest = chunks.remainder();
+    for chunk in chunks {
+        for (a, &s) in acc.iter_mut().zip(chunk) {
+            *a += s * s;
+        }
+    }
+    let sum = acc.iter().sum::<f32>() + rest.iter().map(|s| s * s).sum::<f32>();
+    (sum / samples.len().max(1) as f32).sqrt()
+}
+
+/// Multiply every sample by `gain`; unity is skipped.
+fn scale(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 {
+        return;
+    }
+    let mut chunks = samples.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        for s in chunk.iter_mut() {
+            *s *= gain;
+        }
+    }
+    for s in chunks.into_remainder() {
+        *s *= gain;
+    }
+}
+
+/// Sample peak and RMS of `samples`, in dBFS.
+fn peak_rms_dbfs(samples: &[f32]) -> (f32, f32) {
+    let peak = peak_abs(samples);
+    let rms = rms(samples);
+    (
+        linear_to_db(peak).max(METER_FLOOR_DB),
+        linear_to_db(rms).max(METER_FLOOR_DB),
+    )
+}
+
+/// One step of the mic chain. The meter reads the signal where the gate sits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DspStage {
+    HighPass,
+    EchoCancellation,
+    NoiseSuppression,
+    Eq,
+    Clarity,
+    Gain,
+    AutoGain,
+    Gate,
+    VoiceEffect,
+    Compressor,
+}
+
+const DSP_STAGE_COUNT: usize = 10;
+
+/// A stage's place in the chain. A bypassed gate still meters but always transmits
+/// (mute still applies).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DspStageConfig {
+    pub stage: DspStage,
+    #[serde(default)]
+    pub bypass: bool,
+}
+
+/// High-pass first (rumble would count as speech), echo cancellation before anything
+/// nonlinear, cleanup and tone before gain, gain/AGC before the meter and gate, voice
+/// effects after the gate (so it judges the real voice), and the compressor on what
+/// actually gets transmitted.
+pub fn default_dsp_chain() -> Vec<DspStageConfig> {
+    [
+        DspStage::HighPass,
+        DspStage::EchoCancellation,
+        DspStage::NoiseSuppression,
+        DspStage::Eq,
+        DspStage::Clarity,
+        DspStage::Gain,
+        DspStage::AutoGain,
+        DspStage::Gate,
+        DspStage::VoiceEffect,
+        DspStage::Compressor,
+    ]
+    .into_iter()
+    .map(|stage| DspStageConfig { stage, bypass: false })
+    .collect()
+}
+
+/// Everything about how the mic is processed, as saved in a preset. Input mode and PTT
+/// stay out: they're about how someone talks, not about the mic. `None` = stage off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DspConfig {
+    pub gain: f32,
+    pub threshold: f32,
+    #[serde(default)]
+    pub high_pass_hz: Option<f32>,
+    #[serde(default)]
+    pub echo_cancellation_tail_ms: Option<u32>,
+    #[serde(default = "default_denoiser")]
+    pub denoiser: String,
+    #[serde(default)]
+    pub noise_suppression_strength: Option<f32>,
+    #[serde(default)]
+    pub eq_bands: Vec<EqBand>,
+    #[serde(default)]
+    pub clarity_strength: Option<f32>,
+    #[serde(default)]
+    pub auto_gain_target_db: Option<f32>,
+    #[serde(default)]
+    pub compressor: Option<CompressorSettings>,
+    #[serde(default)]
+    pub voice_effect: Option<VoiceEffect>,
+    #[serde(default = "default_dsp_chain")]
+    pub chain: Vec<DspStageConfig>,
+}
+
+fn default_denoiser() -> String {
+    DEFAULT_DENOISER.to_string()
+}
+
+/// Stages missing from `chain` (e.g. added after it was saved) go back in right after the
+/// stage that precedes them by default.
+fn validate_chain(chain: &[DspStageConfig]) -> Result<[DspStageConfig; DSP_STAGE_COUNT], String> {
+    for (i, slot) in chain.iter().enumerate() {
+        if chain[..i].iter().any(|other| other.stage == slot.stage) {
+            return Err(format!("DSP stage listed twice: {:?}", slot.stage));
+        }
+    }
+    let mut full = chain.to_vec();
+    let defaults = default_dsp_chain();
+    for (i, default) in defaults.iter().enumerate() {
+        if full.iter().any(|slot| slot.stage == default.stage) {
+            continue;
+        }
+        let at = match i.checked_sub(1) {
+            Some(prev) => full
+                .iter()
+                .position(|slot| slot.stage == defaults[prev].stage)
+                .map_or(full.len(), |p| p + 1),
+            None => 0,
+        };
+        full.insert(at, *default);
+    }
+    full.try_into()
+        .map_err(|_| format!("DSP chain must list all {} stages", DSP_STAGE_COUNT))
+}
+
+const SAMPLE_RATE: f32 = PIPELINE_SAMPLE_RATE as f32;
+pub const DEFAULT_AGC_TARGET_DB: f32 = -20.0;
+/// Frames quieter than this (RMS, before AGC) don't move the gain, so pauses and room noise
+/// aren't pumped up.
+const AGC_SPEECH_FLOOR_DB: f32 = -50.0;
+const AGC_MAX_GAIN_DB: f32 = 30.0;
+const AGC_MIN_GAIN_DB: f32 = -20.0;
+/// Slow rise, quicker fall, so a loud burst is tamed fast but quiet talkers ramp up gently.
+const AGC_RISE_DB_PER_SEC: f32 = 3.0;
+const AGC_FALL_DB_PER_SEC: f32 = 10.0;
+/// Peak ceiling after AGC; exceeding it cuts the gain immediately.
+const AGC_PEAK_LIMIT: f32 = 0.9;
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+fn linear_to_db(linear: f32) -> f32 {
+    20.0 * linear.max(1e-9).log10()
+}
+
+/// Duration of a 48 kHz frame of `samples` samples.
+fn frame_ms(samples: usize) -> u32 {
+    (samples as f32 / SAMPLE_RATE * 1000.0) as u32
+}
+
+pub const DEFAULT_HIGH_PASS_HZ: f32 = 90.0;
+pub const DEFAULT_CLARITY_STRENGTH: f32 = 0.5;
+
+/// Most bands a parametric EQ may have.
+pub const MAX_EQ_BANDS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EqBandKind {
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+/// One parametric EQ band from the frontend.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EqBand {
+    pub kind: EqBandKind,
+    pub freq_hz: f32,
+    pub gain_db: f32,
+    /// Bandwidth (peaking) or shelf slope; 0.707 is a gentle default.
+    #[serde(default = "default_eq_q")]
+    pub q: f32,
+}
+
+fn default_eq_q() -> f32 {
+    std::f32::consts::FRAC_1_SQRT_2
+}
+
+/// Biquad filter (RBJ cookbook, transposed direct form II).
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn new() -> Self {
+        Self { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0, z1: 0.0, z2: 0.0 }
+    }
+
+    fn high_pass(cutoff_hz: f32) -> Self {
+        let mut filter = Self::new();
+        filter.set_high_pass(cutoff_hz);
+        filter
+    }
+
+    /// Retune without clearing state, so moving a slider doesn't click.
+    fn set_coefficients(&mut self, b: [f32; 3], a: [f32; 3]) {
+        self.b0 = b[0] / a[0];
+        self.b1 = b[1] / a[0];
+        self.b2 = b[2] / a[0];
+        self.a1 = a[1] / a[0];
+        self.a2 = a[2] / a[0];
+    }
+
+    /// Band-pass with 0 dB peak gain; `x - band_pass(x)` is the matching notch.
+    fn set_band_pass(&mut self, center_hz: f32, q: f32) {
+        let w0 = 2.0 * std::f32::consts::PI * center_hz / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos = w0.cos();
+        self.set_coefficients([alpha, 0.0, -alpha], [1.0 + alpha, -2.0 * cos, 1.0 - alpha]);
+    }
+
+    /// Second-order Butterworth high-pass.
+    fn set_high_pass(&mut self, cutoff_hz: f32) {
+        let w0 = 2.0 * std::f32::consts::PI * cutoff_hz / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        self.set_coefficients(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        );
+    }
+
+    fn set_band(&mut self, band: &EqBand) {
+        let w0 = 2.0 * std::f32::consts::PI * band.freq_hz / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * band.q);
+        let cos = w0.cos();
+        let a = 10f32.powf(band.gain_db / 40.0);
+        let shelf = 2.0 * a.sqrt() * alpha;
+        match band.kind {
+            EqBandKind::Peaking => self.set_coefficients(
+                [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+            ),
+            EqBandKind::LowShelf => self.set_coefficients(
+                [
+                    a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                ],
+                [
+                    (a + 1.0) + (a - 1.0) * cos + shelf,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - shelf,
+                ],
+            ),
+            EqBandKind::HighShelf => self.set_coefficients(
+                [
+                    a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                ],
+                [
+                    (a + 1.0) - (a - 1.0) * cos + shelf,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - shelf,
+                ],
+            ),
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.tick(*sample);
+        }
+    }
+
+    fn tick(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// Presence shelf for the clarity stage; full strength is `CLARITY_MAX_BOOST_DB` above it.
+const CLARITY_SHELF_HZ: f32 = 2500.0;
+const CLARITY_MAX_BOOST_DB: f32 = 6.0;
+/// De-esser band: where "s"/"sh" energy sits.
+const DEESS_HZ: f32 = 6500.0;
+const DEESS_Q: f32 = 1.0;
+/// Sibilance = the de-ess band carries more than this share of the broadband envelope.
+const DEESS_THRESHOLD: f32 = 0.5;
+/// Deepest cut of the de-ess band at full strength.
+const DEESS_MAX_CUT_DB: f32 = 10.0;
+const DEESS_ATTACK_MS: f32 = 1.0;
+const DEESS_RELEASE_MS: f32 = 60.0;
+
+/// Clarity for muffled (laptop) mics: a gentle high shelf from ~2.5 kHz, then a split-band
+/// de-esser so the lift doesn't turn sibilants harsh. One `strength` (0-1) scales both.
+struct Clarity {
+    strength: f32,
+    shelf: Biquad,
+    sibilance: Biquad,
+    band_env: f32,
+    full_env: f32,
+    min_gain: f32,
+    attack: f32,
+    release: f32,
+}
+
+impl Clarity {
+    fn new(strength: f32) -> Self {
+        let mut sibilance = Biquad::new();
+        sibilance.set_band_pass(DEESS_HZ, DEESS_Q);
+        let mut clarity = Self {
+            strength: 0.0,
+            shelf: Biquad::new(),
+            sibilance,
+            band_env: 0.0,
+            full_env: 0.0,
+            min_gain: 1.0,
+            attack: 1.0 - (-1.0 / (DEESS_ATTACK_MS * 0.001 * SAMPLE_RATE)).exp(),
+            release: 1.0 - (-1.0 / (DEESS_RELEASE_MS * 0.001 * SAMPLE_RATE)).exp(),
+        };
+        clarity.set_strength(strength);
+        clarity
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+        self.shelf.set_band(&EqBand {
+            kind: EqBandKind::HighShelf,
+            freq_hz: CLARITY_SHELF_HZ,
+            gain_db: CLARITY_MAX_BOOST_DB * strength,
+            q: default_eq_q(),
+        });
+        self.min_gain = db_to_linear(-DEESS_MAX_CUT_DB * strength);
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        self.shelf.process(samples);
+        for sample in samples.iter_mut() {
+            let x = *sample;
+            let band = self.sibilance.tick(x);
+            self.band_env = follow(self.band_env, band.abs(), self.attack, self.release);
+            self.full_env = follow(self.full_env, x.abs(), self.attack, self.release);
+            let share = self.band_env / self.full_env.max(1e-6);
+            let gain = if share > DEESS_THRESHOLD {
+                (DEESS_THRESHOLD / share).max(self.min_gain)
+            } else {
+                1.0
+            };
+            *sample = (x - band) + band * gain;
+        }
+    }
+}
+
+/// One step of a peak envelope follower.
+fn follow(env: f32, level: f32, attack: f32, release: f32) -> f32 {
+    let coeff = if level > env { attack } else { release };
+    env + (level - env) * coeff
+}
+
+/// Automatic gain control: steers the speech RMS toward `target_db` (dBFS).
+struct AutoGain {
+    target_db: f32,
+    gain_db: f32,
+}
+
+impl AutoGain {
+    fn new(target_db: f32) -> Self {
+        Self { target_db, gain_db: 0.0 }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let rms = rms(samples);
+        let peak = peak_abs(samples);
+        let start_gain = db_to_linear(self.gain_db);
+
+        let rms_db = linear_to_db(rms);
+        if rms_db > AGC_SPEECH_FLOOR_DB {
+            let seconds = samples.len() as f32 / SAMPLE_RATE;
+            let error = self.target_db - rms_db - self.gain_db;
+            let step = if error > 0.0 {
+                error.min(AGC_RISE_DB_PER_SEC * seconds)
+            } else {
+                error.max(-AGC_FALL_DB_PER_SEC * seconds)
+            };
+            self.gain_db = (self.gain_db + step).clamp(AGC_MIN_GAIN_DB, AGC_MAX_GAIN_DB);
+        }
+        // Peak protection: never push this frame past the ceiling.
+        if peak * db_to_linear(self.gain_db) > AGC_PEAK_LIMIT {
+            self.gain_db = linear_to_db(AGC_PEAK_LIMIT / peak);
+        }
+
+        // Ramp across the frame to avoid zipper noise; a peak cut applies at once.
+        let end_gain = db_to_linear(self.gain_db);
+        let start_gain = if end_gain < start_gain && peak * start_gain > AGC_PEAK_LIMIT {
+            end_gain
+        } else {
+            start_gain
+        };
+        let step = (end_gain - start_gain) / samples.len() as f32;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample *= start_gain + step * i as f32;
+        }
+    }
+}
+
+/// Voice changer settings, in semitones (0 = unchanged, ±12 max). Pitch moves the voice's
+/// notes, formant its timbre (bigger/smaller).
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceEffect {
+    pub pitch_semitones: f32,
+    pub formant_semitones: f32,
+}
+
+/// Compressor settings from the frontend. Missing fields use defaults.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressorSettings {
+    /// Level (dBFS) where compression starts.
+    pub threshold_db: f32,
+    /// Input:output ratio above the threshold (1 = off).
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Gain added after compression, to bring quiet speech back up.
+    pub makeup_db: f32,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: -18.0,
+            ratio: 3.0,
+            attack_ms: 5.0,
+            release_ms: 100.0,
+            makeup_db: 6.0,
+        }
+    }
+}
+
+/// Soft knee width around the threshold.
+const COMPRESSOR_KNEE_DB: f32 = 6.0;
+/// Brickwall ceiling (-1 dBFS) so nothing reaches the encoder clipped.
+const LIMITER_CEILING: f32 = 0.891;
+const LIMITER_RELEASE_MS: f32 = 50.0;
+
+fn smoothing_coeff(ms: f32) -> f32 {
+    (-1.0 / (ms.max(0.1) / 1000.0 * SAMPLE_RATE)).exp()
+}
+
+/// Feed-forward soft-knee compressor followed by a brickwall limiter (instant attack).
+struct Compressor {
+    settings: CompressorSettings,
+    attack_coeff: f32,
+    release_coeff: f32,
+    makeup: f32,
+    /// Smoothed gain reduction in dB (<= 0).
+    reduction_db: f32,
+    limiter_gain: f32,
+    limiter_release: f32,
+}
+
+impl Compressor {
+    fn new(settings: CompressorSettings) -> Self {
+        let mut compressor = Self {
+            settings,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            makeup: 1.0,
+            reduction_db: 0.0,
+            limiter_gain: 1.0,
+            limiter_release: smoothing_coeff(LIMITER_RELEASE_MS),
+        };
+        compressor.configure(settings);
+        compressor
+    }
+
+    fn configure(&mut self, settings: CompressorSettings) {
+        self.settings = CompressorSettings {
+            threshold_db: settings.threshold_db.clamp(-60.0, 0.0),
+            ratio: settings.ratio.clamp(1.0, 20.0),
+            attack_ms: settings.attack_ms.clamp(0.1, 200.0),
+            release_ms: settings.release_ms.clamp(10.0, 2000.0),
+            makeup_db: settings.makeup_db.clamp(0.0, 24.0),
+        };
+        self.attack_coeff = smoothing_coeff(self.settings.attack_ms);
+        self.release_coeff = smoothing_coeff(self.settings.release_ms);
+        self.makeup = db_to_linear(self.settings.makeup_db);
+    }
+
+    /// Static curve: gain change in dB for an input level in dB.
+    fn gain_computer(&self, level_db: f32) -> f32 {
+        let CompressorSettings { threshold_db, ratio, .. } = self.settings;
+        let over = level_db - threshold_db;
+        let slope = 1.0 / ratio - 1.0;
+        if 2.0 * over < -COMPRESSOR_KNEE_DB {
+            0.0
+        } else if 2.0 * over.abs() <= COMPRESSOR_KNEE_DB {
+            slope * (over + COMPRESSOR_KNEE_DB / 2.0).powi(2) / (2.0 * COMPRESSOR_KNEE_DB)
+        } else {
+            slope * over
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            let target = self.gain_computer(linear_to_db(sample.abs()));
+            let coeff = if target < self.reduction_db { self.attack_coeff } else { self.release_coeff };
+            self.reduction_db = coeff * self.reduction_db + (1.0 - coeff) * target;
+            let y = *sample * db_to_linear(self.reduction_db) * self.makeup;
+
+            let limit = if y.abs() > LIMITER_CEILING { LIMITER_CEILING / y.abs() } else { 1.0 };
+            self.limiter_gain = if limit < self.limiter_gain {
+                limit
+            } else {
+                self.limiter_release * self.limiter_gain + (1.0 - self.limiter_release) * limit
+            };
+            *sample = y * self.limiter_gain;
+        }
+    }
+}
+
+/// RNNoise speech probability at or above which a 10 ms chunk counts as voice.
+const VAD_SPEECH_PROBABILITY: f32 = 0.6;
+/// Keep the gate open this long after the last voiced chunk so word endings aren't cut.
+const VAD_HANGOVER_MS: u32 = 300;
+
+/// Voice activity detection using RNNoise's speech probability (the denoised output is
+/// discarded). Typing, breath and bumps score low even when they are loud.
+struct VoiceDetector {
+    state: Box<DenoiseState<'static>>,
+    input: [f32; RNNOISE_FRAME],
+    output: [f32; RNNOISE_FRAME],
+    /// Chunks left before the hangover runs out.
+    hangover: u32,
+}
+
+impl VoiceDetector {
+    fn new() -> Self {
+        Self {
+            state: DenoiseState::new(),
+            input: [0.0; RNNOISE_FRAME],
+            output: [0.0; RNNOISE_FRAME],
+            hangover: 0,
+        }
+    }
+
+    /// Whether the frame contains speech (or is within the hangover after it).
+    fn detect(&mut self, samples: &[f32]) -> bool {
+        for chunk in samples.chunks(RNNOISE_FRAME) {
+            self.input.fill(0.0);
+            for (dst, &s) in self.input.iter_mut().zip(chunk.iter()) {
+                *dst = s * RNNOISE_SCALE;
+            }
+            let probability = self.state.process_frame(&mut self.output, &self.input);
+            if probability >= VAD_SPEECH_PROBABILITY {
+                self.hangover = VAD_HANGOVER_MS / BASE_FRAME_MS;
+            } else {
+                self.hangover = self.hangover.saturating_sub(1);
+            }
+        }
+        self.hangover > 0
+    }
+}
+
+pub const DEFAULT_PTT_RELEASE_MS: u32 = 150;
+const MAX_PTT_RELEASE_MS: u32 = 500;
+
+/// Lookahead of the output limiter (~1.3 ms), which is also the latency it adds.
+const LOOKAHEAD: usize = 64;
+/// Output ceiling, a hair under full scale.
+const OUTPUT_CEILING: f32 = 0.98;
+const OUTPUT_LIMITER_RELEASE_MS: f32 = 80.0;
+
+/// Lookahead peak limiter: the gain needed by each sample is min-filtered over the
+/// lookahead, released exponentially, then box-smoothed over the lookahead, so it has
+/// fully ramped down by the time a peak leaves the delay line.
+struct LookaheadLimiter {
+    delay: [f32; LOOKAHEAD],
+    /// Needed gain for the last LOOKAHEAD + 1 inputs.
+    required: [f32; LOOKAHEAD + 1],
+    /// How many of `required` are below 1, so quiet audio skips the min search.
+    limited: usize,
+    /// Released gain for the last LOOKAHEAD inputs (the box filter's window).
+    released: [f32; LOOKAHEAD],
+    pos: usize,
+    gain: f32,
+    release_coeff: f32,
+}
+
+impl LookaheadLimiter {
+    fn new() -> Self {
+        Self {
+            delay: [0.0; LOOKAHEAD],
+            required: [1.0; LOOKAHEAD + 1],
+            limited: 0,
+            released: [1.0; LOOKAHEAD],
+            pos: 0,
+            gain: 1.0,
+            release_coeff: smoothing_coeff(OUTPUT_LIMITER_RELEASE_MS),
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        // Re-summed per call rather than kept running, so f32 error can't accumulate.
+        let mut box_sum: f32 = self.released.iter().sum();
+        for sample in samples.iter_mut() {
+            let x = *sample;
+            let required = if x.abs() > OUTPUT_CEILING { OUTPUT_CEILING / x.abs() } else { 1.0 };
+            let old = std::mem::replace(&mut self.required[self.pos % (LOOKAHEAD + 1)], required);
+            self.limited = self.limited + (required < 1.0) as usize - (old < 1.0) as usize;
+            let target = if self.limited == 0 {
+                1.0
+            } else {
+                self.required.iter().fold(1.0f32, |a, &g| a.min(g))
+            };
+            self.gain = target.min(self.release_coeff * self.gain + (1.0 - self.release_coeff));
+
+            let slot = self.pos % LOOKAHEAD;
+            box_sum += self.gain - self.released[slot];
+            self.released[slot] = self.gain;
+            let delayed = std::mem::replace(&mut self.delay[slot], x);
+            *sample = (delayed * box_sum / LOOKAHEAD as f32).clamp(-1.0, 1.0);
+
+            self.pos = (self.pos + 1) % (LOOKAHEAD * (LOOKAHEAD + 1));
+        }
+    }
+}
+
+const DEFAULT_NOISE_FLOOR: f32 = 0.0002;
+const DEFAULT_MAX_LEVEL: f32 = 0.07;
+/// Minimum-statistics windows: the floor drops at once and rises within
+/// NOISE_WINDOWS × NOISE_WINDOW_MS once the room gets louder.
+const NOISE_WINDOW_MS: u32 = 1000;
+const NOISE_WINDOWS: usize = 5;
+/// Frame peaks below this (-100 dBFS) are digital silence, not room noise.
+const NOISE_MIN_PEAK: f32 = 0.00001;
+/// The quietest frame peak sits below the typical one; pad it so ambient reads as silence.
+const NOISE_FLOOR_MARGIN: f32 = 2.0;
+const NOISE_FLOOR_RANGE: (f32, f32) = (0.00005, 0.01);
+const MAX_LEVEL_RANGE: (f32, f32) = (0.02, 0.5);
+
+/// Meter/gate constants, for mic hardware the defaults (from the JS meter) don't suit.
+/// Coefficients are per 10 ms frame. With `adaptive_noise_floor`, `noise_floor` and
+/// `max_level` are only the starting range before the learned floor takes over.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeterTuning {
+    /// Peak (linear) treated as silence.
+    pub noise_floor: f32,
+    /// Peak (linear) that reads as a full meter.
+    pub max_level: f32,
+    /// Meter fall per frame (fraction of the level kept).
+    pub decay_factor: f32,
+    /// Gate opening speed (fraction of the way per frame).
+    pub attack_coeff: f32,
+    /// Gate closing speed (fraction of the way per frame).
+    pub release_coeff: f32,
+    pub adaptive_noise_floor: bool,
 }
 
+impl Default for MeterTuning {
+    fn default() -> Self {
+        Self {
+            noise_floor: DEFAULT_NOISE_FLOOR,
+            max_level: DEFAULT_MAX_LEVEL,
+            decay_factor: BASE_DECAY_FACTOR,
+            attack_coeff: BASE_ATTACK_COEFF,
+            release_coeff: BASE_RELEASE_COEFF,
+            adaptive_noise_floor: true,
+        }
+    }
+}
+
+impl MeterTuning {
+    fn clamped(self) -> Self {
+        Self {
+            noise_floor: self.noise_floor.clamp(NOISE_FLOOR_RANGE.0, NOISE_FLOOR_RANGE.1),
+            max_level: self.max_level.clamp(MAX_LEVEL_RANGE.0, MAX_LEVEL_RANGE.1),
+            decay_factor: self.decay_factor.clamp(0.5, 0.999),
+            attack_coeff: self.attack_coeff.clamp(0.01, 1.0),
+            release_coeff: self.release_coeff.clamp(0.001, 1.0),
+            adaptive_noise_floor: self.adaptive_noise_floor,
+        }
+    }
+}
+
+/// Learns the mic's ambient level as the minimum frame peak over the last few seconds.
+struct NoiseFloorTracker {
+    window_min: f32,
+    window_ms: u32,
+    minima: [f32; NOISE_WINDOWS],
+    filled: usize,
+    next: usize,
+}
+
+impl NoiseFloorTracker {
+    fn new() -> Self {
+        Self {
+            window_min: f32::MAX,
+            window_ms: 0,
+            minima: [f32::MAX; NOISE_WINDOWS],
+            filled: 0,
+            next: 0,
+        }
+    }
+
+    /// Feed one frame's peak. Returns a new ambient estimate when a window completes.
+    fn update(&mut self, peak: f32, frame_ms: u32) -> Option<f32> {
+        if peak >= NOISE_MIN_PEAK {
+            self.window_min = self.window_min.min(peak);
+        }
+        self.window_ms += frame_ms;
+        if self.window_ms < NOISE_WINDOW_MS {
+            return None;
+        }
+        let window_min = std::mem::replace(&mut self.window_min, f32::MAX);
+        self.window_ms = 0;
+        if window_min == f32::MAX {
+            return None;
+        }
+        self.minima[self.next] = window_min;
+        self.next = (self.next + 1) % NOISE_WINDOWS;
+        self.filled = (self.filled + 1).min(NOISE_WINDOWS);
+        Some(self.minima[..self.filled].iter().fold(f32::MAX, |a, &m| a.min(m)))
+    }
+}
+
+/// Per-frame envelope coefficients are tuned for 10 ms frames.
+const BASE_FRAME_MS: u32 = 10;
+const BASE_DECAY_FACTOR: f32 = 0.88;
+const BASE_ATTACK_COEFF: f32 = 0.3;
+const BASE_RELEASE_COEFF: f32 = 0.05;
+
 impl AudioDSP {
     pub fn new() -> Self {
         Self {
@@ -36,43 +999,207 @@ impl AudioDSP {
             threshold: 0.2,
             input_mode: InputMode::VoiceActivity,
             ptt_pressed: false,
+            ptt_release_ms: DEFAULT_PTT_RELEASE_MS,
+            ptt_tail_ms: 0,
             transmission_muted: false,
+            transmission: TransmissionState::new(),
             displayed_level: 0.0,
+            meter: MeterReading::default(),
+            level_smoother: LevelSmoother::new(),
             current_gain: 0.0,
-            noise_floor: 0.0002,
-            max_level: 0.07,
-            decay_factor: 0.88,
-            attack_coeff: 0.3,
-            release_coeff: 0.05,
+            noise_floor: DEFAULT_NOISE_FLOOR,
+            max_level: DEFAULT_MAX_LEVEL,
+            noise_tracker: NoiseFloorTracker::new(),
+            meter_tuning: MeterTuning::default(),
+            frame_ms: BASE_FRAME_MS,
+            decay_factor: BASE_DECAY_FACTOR,
+            attack_coeff: BASE_ATTACK_COEFF,
+            release_coeff: BASE_RELEASE_COEFF,
+            bypass: false,
+            chain: default_dsp_chain()
+                .try_into()
+                .expect("default chain lists every stage"),
+            high_pass: Some(Biquad::high_pass(DEFAULT_HIGH_PASS_HZ)),
+            high_pass_hz: DEFAULT_HIGH_PASS_HZ,
+            echo_canceller: None,
+            voice_detector: Some(VoiceDetector::new()),
+            noise_suppressor: None,
+            denoiser: DEFAULT_DENOISER.to_string(),
+            noise_suppression_strength: 1.0,
+            eq: Vec::with_capacity(MAX_EQ_BANDS),
+            eq_bands: Vec::new(),
+            clarity: None,
+            auto_gain: None,
+            compressor: None,
+            #[cfg(feature = "fun-effects")]
+            voice_changer: None,
+            output_limiter: LookaheadLimiter::new(),
         }
     }
     
-    /// Process a frame of audio samples
-    /// Returns (processed_samples, level_for_ui)
-    pub fn process_frame(&mut self, input: &[f32]) -> (Vec<f32>, f32) {
+    /// Rescale the envelope/gate coefficients so meter decay and gate attack/release keep
+    /// the same timing whatever the frame duration.
+    pub fn set_frame_duration_ms(&mut self, frame_ms: u32) {
+        self.frame_ms = frame_ms.max(1);
+        let steps = self.frame_ms as f32 / BASE_FRAME_MS as f32;
+        let tuning = self.meter_tuning;
+        self.decay_factor = tuning.decay_factor.powf(steps);
+        self.attack_coeff = 1.0 - (1.0 - tuning.attack_coeff).powf(steps);
+        self.release_coeff = 1.0 - (1.0 - tuning.release_coeff).powf(steps);
+    }
+
+    /// Replace the meter/gate constants (clamped to sane ranges) and restart the noise
+    /// floor from the new range. Returns what was applied.
+    pub fn set_meter_tuning(&mut self, tuning: MeterTuning) -> MeterTuning {
+        self.meter_tuning = tuning.clamped();
+        self.set_frame_duration_ms(self.frame_ms);
+        self.reset_noise_floor();
+        self.meter_tuning
+    }
+
+    
+    /// Process a frame of audio samples into `samples` (cleared first; pass a pooled buffer
+    /// so no allocation happens per frame). `captured_at` lines the echo reference up.
+    /// Returns level_for_ui
+    pub fn process_frame(&mut self, input: &[f32], captured_at: Instant, samples: &mut Vec<f32>) -> f32 {
+        samples.clear();
         if input.is_empty() {
-            return (vec![], 0.0);
+            return 0.0;
         }
         
-        // 1. Apply gain
-        let mut samples: Vec<f32> = input.iter()
-            .map(|&s| s * self.gain)
-            .collect();
-        
-        // 2. Calculate peak (for level meter)
-        let peak = samples.iter()
-            .map(|&s| s.abs())
-            .fold(0.0f32, |a, b| a.max(b));
+        samples.extend_from_slice(input);
+        let (input_peak_dbfs, input_rms_dbfs) = peak_rms_dbfs(input);
+
+        if self.bypass {
+            // Raw mode: gain only, so device problems can be told apart from DSP ones.
+            // Mute still applies.
+            let gain = if self.transmission_muted { 0.0 } else { self.gain };
+            let level = self.measure_level(samples);
+            scale(samples, gain);
+            self.transmission.update(if self.transmission_muted { 0.0 } else { 1.0 }, frame_ms(samples.len()));
+            self.update_meter(input_peak_dbfs, input_rms_dbfs, samples);
+            return level;
+        }
+
+        let mut level = 0.0;
+        let chain = self.chain;
+        for slot in chain.iter() {
+            if slot.stage == DspStage::Gate {
+                // The meter reads whatever reaches the gate, bypassed or not.
+                level = self.measure_level(samples);
+                let transmission_gain = if slot.bypass {
+                    if self.transmission_muted { 0.0 } else { 1.0 }
+                } else {
+                    self.transmission_gain(samples, level)
+                };
+                scale(samples, transmission_gain);
+                self.transmission.update(transmission_gain, frame_ms(samples.len()));
+                continue;
+            }
+            if slot.bypass {
+                continue;
+            }
+            match slot.stage {
+                // Rumble, desk thumps and plosives would otherwise count as speech for the
+                // meter and gate
+                DspStage::HighPass => {
+                    if let Some(high_pass) = self.high_pass.as_mut() {
+                        high_pass.process(samples);
+                    }
+                }
+                // Needs the mic before anything nonlinear
+                DspStage::EchoCancellation => {
+                    if let Some(canceller) = self.echo_canceller.as_mut() {
+                        canceller.process(samples, captured_at);
+                    }
+                }
+                DspStage::NoiseSuppression => {
+                    if let Some(suppressor) = self.noise_suppressor.as_mut() {
+                        suppressor.process_frame(samples);
+                    }
+                }
+                DspStage::Eq => {
+                    for band in self.eq.iter_mut() {
+                        band.process(samples);
+                    }
+                }
+                DspStage::Clarity => {
+                    if let Some(clarity) = self.clarity.as_mut() {
+                        clarity.process(samples);
+                    }
+                }
+                DspStage::Gain => scale(samples, self.gain),
+                DspStage::AutoGain => {
+                    if let Some(auto_gain) = self.auto_gain.as_mut() {
+                        auto_gain.process(samples);
+                    }
+                }
+                // After the gate by default, so makeup gain doesn't lift the noise the gate
+                // decided on
+                DspStage::VoiceEffect => {
+                    #[cfg(feature = "fun-effects")]
+                    if let Some(changer) = self.voice_changer.as_mut() {
+                        changer.process(samples);
+                    }
+                }
+                DspStage::Compressor => {
+                    if let Some(compressor) = self.compressor.as_mut() {
+                        compressor.process(samples);
+                    }
+                }
+                DspStage::Gate => {}
+            }
+        }
+
+        self.output_limiter.process(samples);
+        self.update_meter(input_peak_dbfs, input_rms_dbfs, samples);
+
+        level
+    }
+
+    fn update_meter(&mut self, input_peak_dbfs: f32, input_rms_dbfs: f32, output: &[f32]) {
+        let (output_peak_dbfs, output_rms_dbfs) = peak_rms_dbfs(output);
+        let clip_db = linear_to_db(CLIP_LEVEL);
+        self.meter = MeterReading {
+            input_peak_dbfs,
+            input_rms_dbfs,
+            output_peak_dbfs,
+            output_rms_dbfs,
+            clipped: self.meter.clipped || input_peak_dbfs >= clip_db || output_peak_dbfs >= clip_db,
+            noise_floor_dbfs: self.noise_floor_dbfs(),
+            level: self.level_smoother.level,
+            peak_hold: self.level_smoother.hold,
+        };
+    }
+
+    /// Meter level (0-1) for the frame, updating the envelope.
+    fn measure_level(&mut self, samples: &[f32]) -> f32 {
+        // 1. Calculate peak (for level meter)
+        let peak = peak_abs(samples);
+
+        // Follow the mic's ambient level; the meter keeps its usual dynamic range above it
+        let frame_ms = frame_ms(samples.len());
+        let tuning = self.meter_tuning;
+        let ambient = if tuning.adaptive_noise_floor {
+            self.noise_tracker.update(peak, frame_ms)
+        } else {
+            None
+        };
+        if let Some(ambient) = ambient {
+            self.noise_floor = (ambient * NOISE_FLOOR_MARGIN).clamp(NOISE_FLOOR_RANGE.0, NOISE_FLOOR_RANGE.1);
+            self.max_level = (self.noise_floor * tuning.max_level / tuning.noise_floor)
+                .clamp(MAX_LEVEL_RANGE.0, MAX_LEVEL_RANGE.1);
+        }
         
-        // 3. Envelope — fast attack (instant rise), slow decay
+        // 2. Envelope — fast attack (instant rise), slow decay
         self.displayed_level = peak.max(self.displayed_level * self.decay_factor);
         
-        // 4. Mute-floor fix — clamp true silence to 0
+        // 3. Mute-floor fix — clamp true silence to 0
         if self.displayed_level < self.noise_floor {
             self.displayed_level = 0.0;
         }
         
-        // 5. Normalize level for UI (0-1 range)
+        // 4. Normalize level for UI (0-1 range)
         let normalized = if self.displayed_level < self.noise_floor {
             0.0
         } else {
@@ -84,39 +1211,71 @@ impl AudioDSP {
         
         // Perceptual boost for quiet sounds (sqrt for gentle curve)
         let level = normalized.sqrt();
-        
-        // 6. Apply threshold gating for transmission
-        let transmission_gain = if self.transmission_muted {
-            0.0
-        } else if self.input_mode == InputMode::VoiceActivity {
-            // Voice Activity mode: gate based on threshold
-            let target_gain = if level >= self.threshold { 1.0 } else { 0.0 };
-            
-            // Smooth envelope with exponential attack/release
-            if target_gain > self.current_gain {
-                // Attack (opening gate) - faster
-                self.current_gain = self.current_gain * (1.0 - self.attack_coeff) 
-                    + target_gain * self.attack_coeff;
-            } else {
-                // Release (closing gate) - slower
-                self.current_gain = self.current_gain * (1.0 - self.release_coeff) 
-                    + target_gain * self.release_coeff;
+        self.level_smoother.update(level, frame_ms);
+        level
+    }
+
+    /// Gate gain for transmission under the current input mode.
+    fn transmission_gain(&mut self, samples: &[f32], level: f32) -> f32 {
+        if self.transmission_muted {
+            return 0.0;
+        }
+        match self.input_mode {
+            InputMode::VoiceActivity | InputMode::LevelGate => self.voice_gate(samples, level),
+            InputMode::PushToTalk => {
+                if self.ptt_gate(samples.len()) { 1.0 } else { 0.0 }
             }
-            
-            // Clamp to avoid 0 (exponential ramp issue)
-            self.current_gain.max(0.001)
-        } else {
-            // Push-to-Talk mode: transmit only when key is pressed
-            if self.ptt_pressed { 1.0 } else { 0.0 }
+            InputMode::OpenMic => 1.0,
+            InputMode::Hybrid => {
+                // Keep the detector and envelope running under PTT, and release from
+                // fully open when the key (and its tail) lets go.
+                let voice = self.voice_gate(samples, level);
+                if self.ptt_gate(samples.len()) {
+                    self.current_gain = 1.0;
+                    1.0
+                } else {
+                    voice
+                }
+            }
+        }
+    }
+    
+    /// Voice Activity mode: speech above the threshold; Level Gate: threshold only.
+    /// Returns the smoothed gate gain.
+    fn voice_gate(&mut self, samples: &[f32], level: f32) -> f32 {
+        let voiced = match self.voice_detector.as_mut() {
+            Some(detector) => detector.detect(samples),
+            None => true,
         };
+        let target_gain = if voiced && level >= self.threshold { 1.0 } else { 0.0 };
         
-        // 7. Apply transmission gating to samples
-        for sample in &mut samples {
-            *sample *= transmission_gain;
+        // Smooth envelope with exponential attack/release
+        if target_gain > self.current_gain {
+            // Attack (opening gate) - faster
+            self.current_gain = self.current_gain * (1.0 - self.attack_coeff) 
+                + target_gain * self.attack_coeff;
+        } else {
+            // Release (closing gate) - slower
+            self.current_gain = self.current_gain * (1.0 - self.release_coeff) 
+                + target_gain * self.release_coeff;
         }
         
-        // 8. Return processed samples and UI level
-        (samples, level)
+        // Clamp to avoid 0 (exponential ramp issue)
+        self.current_gain.max(0.001)
+    }
+
+    /// Whether PTT holds the gate open: the key is pressed, or its release tail is running.
+    fn ptt_gate(&mut self, frame_samples: usize) -> bool {
+        let frame_ms = frame_ms(frame_samples);
+        if self.ptt_pressed {
+            self.ptt_tail_ms = self.ptt_release_ms;
+            true
+        } else if self.ptt_tail_ms > 0 {
+            self.ptt_tail_ms = self.ptt_tail_ms.saturating_sub(frame_ms);
+            true
+        } else {
+            false
+        }
     }
     
     pub fn set_gain(&mut self, gain: f32) {
@@ -126,13 +1285,230 @@ impl AudioDSP {
     pub fn set_threshold(&mut self, threshold: f32) {
         self.threshold = threshold.clamp(0.0, 1.0);
     }
+
+    /// Turn noise suppression on or off. `strength` (0-1) blends denoised with original
+    /// audio; 1.0 is full suppression. RNNoise adds 10 ms of latency while enabled.
+    pub fn set_noise_suppression(&mut self, enabled: bool, strength: f32) {
+        let strength = strength.clamp(0.0, 1.0);
+        self.noise_suppression_strength = strength;
+        match (enabled, self.noise_suppressor.as_mut()) {
+            (false, _) => self.noise_suppressor = None,
+            (true, Some(suppressor)) => suppressor.set_strength(strength),
+            (true, None) => match create_denoiser(&self.denoiser, strength) {
+                Ok(suppressor) => self.noise_suppressor = Some(suppressor),
+                Err(e) => eprintln!("[DSP] {}", e),
+            },
+        }
+    }
+
+    /// Pick the noise suppression backend (see `audio_denoise::denoiser_names`). A running
+    /// stage switches over immediately.
+    pub fn set_denoiser(&mut self, name: &str) -> Result<(), String> {
+        if self.noise_suppressor.is_some() {
+            self.noise_suppressor = Some(create_denoiser(name, self.noise_suppression_strength)?);
+        } else {
+            // Fail now rather than when the stage is switched on.
+            create_denoiser(name, self.noise_suppression_strength)?;
+        }
+        self.denoiser = name.to_string();
+        Ok(())
+    }
+    
+    /// Raw mode: frames leave with only the gain applied (no filters, gate or limiter).
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+    }
+
+    /// Reorder/bypass stages. No stage may appear twice; missing ones keep their default place.
+    pub fn set_chain(&mut self, chain: &[DspStageConfig]) -> Result<(), String> {
+        self.chain = validate_chain(chain)?;
+        Ok(())
+    }
+
+    pub fn chain(&self) -> Vec<DspStageConfig> {
+        self.chain.to_vec()
+    }
+
+    /// Snapshot of the current processing settings.
+    pub fn config(&self) -> DspConfig {
+        DspConfig {
+            gain: self.gain,
+            threshold: self.threshold,
+            high_pass_hz: self.high_pass.as_ref().map(|_| self.high_pass_hz),
+            echo_cancellation_tail_ms: self.echo_canceller.as_ref().map(|c| c.tail_ms()),
+            denoiser: self.denoiser.clone(),
+            noise_suppression_strength: self.noise_suppressor.as_ref().map(|_| self.noise_suppression_strength),
+            eq_bands: self.eq_bands.clone(),
+            clarity_strength: self.clarity.as_ref().map(|c| c.strength),
+            auto_gain_target_db: self.auto_gain.as_ref().map(|a| a.target_db),
+            compressor: self.compressor.as_ref().map(|c| c.settings),
+            voice_effect: self.voice_effect(),
+            chain: self.chain(),
+        }
+    }
+
+    /// Apply a snapshot from `config`. Validated before anything changes, so a bad preset
+    /// leaves the current settings alone.
+    pub fn apply_config(&mut self, config: &DspConfig) -> Result<(), String> {
+        if config.eq_bands.len() > MAX_EQ_BANDS {
+            return Err(format!("Too many EQ bands: {} (max {})", config.eq_bands.len(), MAX_EQ_BANDS));
+        }
+        let chain = validate_chain(&config.chain)?;
+        // Keep a running canceller (and what it has learned) if the tail is unchanged.
+        if config.echo_cancellation_tail_ms != self.echo_canceller.as_ref().map(|c| c.tail_ms()) {
+            self.echo_canceller = config.echo_cancellation_tail_ms.map(EchoCanceller::new).transpose()?;
+        }
+        self.chain = chain;
+        self.set_gain(config.gain);
+        self.set_threshold(config.threshold);
+        self.set_high_pass(config.high_pass_hz.is_some(), config.high_pass_hz.unwrap_or(DEFAULT_HIGH_PASS_HZ));
+        if config.denoiser != self.denoiser {
+            // An unavailable backend (e.g. a feature not built in) falls back to the current one.
+            if let Err(e) = self.set_denoiser(&config.denoiser) {
+                eprintln!("[DSP] Preset denoiser: {}", e);
+            }
+        }
+        self.set_noise_suppression(
+            config.noise_suppression_strength.is_some(),
+            config.noise_suppression_strength.unwrap_or(1.0),
+        );
+        self.set_eq(&config.eq_bands)?;
+        self.set_clarity(config.clarity_strength.is_some(), config.clarity_strength.unwrap_or(DEFAULT_CLARITY_STRENGTH));
+        self.set_auto_gain(
+            config.auto_gain_target_db.is_some(),
+            config.auto_gain_target_db.unwrap_or(DEFAULT_AGC_TARGET_DB),
+        );
+        self.set_compressor(config.compressor.is_some(), config.compressor.unwrap_or_default());
+        // A preset from a build with voice effects still loads without them.
+        if let Err(e) = self.set_voice_effect(config.voice_effect.is_some(), config.voice_effect.unwrap_or_default()) {
+            eprintln!("[DSP] Preset voice effect: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Turn the high-pass filter on or off. `cutoff_hz` is clamped to 20-300 Hz.
+    pub fn set_high_pass(&mut self, enabled: bool, cutoff_hz: f32) {
+        let cutoff_hz = cutoff_hz.clamp(20.0, 300.0);
+        self.high_pass_hz = cutoff_hz;
+        match (enabled, self.high_pass.as_mut()) {
+            (false, _) => self.high_pass = None,
+            (true, Some(high_pass)) => high_pass.set_high_pass(cutoff_hz),
+            (true, None) => self.high_pass = Some(Biquad::high_pass(cutoff_hz)),
+        }
+    }
+
+    /// Replace the EQ bands (up to `MAX_EQ_BANDS`; empty turns the EQ off). Out-of-range
+    /// values are clamped. Bands that already exist keep their state, so edits don't click.
+    pub fn set_eq(&mut self, bands: &[EqBand]) -> Result<(), String> {
+        if bands.len() > MAX_EQ_BANDS {
+            return Err(format!("Too many EQ bands: {} (max {})", bands.len(), MAX_EQ_BANDS));
+        }
+        self.eq.truncate(bands.len());
+        while self.eq.len() < bands.len() {
+            self.eq.push(Biquad::new());
+        }
+        self.eq_bands.clear();
+        for (filter, band) in self.eq.iter_mut().zip(bands) {
+            let band = EqBand {
+                kind: band.kind,
+                freq_hz: band.freq_hz.clamp(20.0, 20000.0),
+                gain_db: band.gain_db.clamp(-18.0, 18.0),
+                q: band.q.clamp(0.1, 10.0),
+            };
+            filter.set_band(&band);
+            self.eq_bands.push(band);
+        }
+        Ok(())
+    }
+
+    /// Turn the voice changer on or off; shifts are clamped to ±12 semitones. Only
+    /// available in builds with the `fun-effects` feature.
+    pub fn set_voice_effect(&mut self, enabled: bool, effect: VoiceEffect) -> Result<(), String> {
+        #[cfg(feature = "fun-effects")]
+        {
+            let effect = VoiceEffect {
+                pitch_semitones: effect.pitch_semitones.clamp(-12.0, 12.0),
+                formant_semitones: effect.formant_semitones.clamp(-12.0, 12.0),
+            };
+            match (enabled, self.voice_changer.as_mut()) {
+                (false, _) => self.voice_changer = None,
+                (true, Some(changer)) => changer.set_effect(effect),
+                (true, None) => self.voice_changer = Some(VoiceChanger::new(effect)),
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "fun-effects"))]
+        {
+            let _ = effect;
+            if enabled {
+                Err("Voice effects are not included in this build".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn voice_effect(&self) -> Option<VoiceEffect> {
+        #[cfg(feature = "fun-effects")]
+        {
+            self.voice_changer.as_ref().map(|c| c.effect())
+        }
+        #[cfg(not(feature = "fun-effects"))]
+        {
+            None
+        }
+    }
+
+    /// Turn the clarity stage (presence boost + de-esser) on or off. `strength` is 0-1.
+    pub fn set_clarity(&mut self, enabled: bool, strength: f32) {
+        let strength = strength.clamp(0.0, 1.0);
+        match (enabled, self.clarity.as_mut()) {
+            (false, _) => self.clarity = None,
+            (true, Some(clarity)) => clarity.set_strength(strength),
+            (true, None) => self.clarity = Some(Clarity::new(strength)),
+        }
+    }
+
+    /// Turn automatic gain control on or off. `target_db` is the speech level to aim for in
+    /// dBFS RMS (clamped to -40..-6).
+    pub fn set_auto_gain(&mut self, enabled: bool, target_db: f32) {
+        let target_db = target_db.clamp(-40.0, -6.0);
+        match (enabled, self.auto_gain.as_mut()) {
+            (false, _) => self.auto_gain = None,
+            (true, Some(auto_gain)) => auto_gain.target_db = target_db,
+            (true, None) => self.auto_gain = Some(AutoGain::new(target_db)),
+        }
+    }
+
+    /// Turn the compressor/limiter on or off; out-of-range settings are clamped.
+    pub fn set_compressor(&mut self, enabled: bool, settings: CompressorSettings) {
+        match (enabled, self.compressor.as_mut()) {
+            (false, _) => self.compressor = None,
+            (true, Some(compressor)) => compressor.configure(settings),
+            (true, None) => self.compressor = Some(Compressor::new(settings)),
+        }
+    }
+
+    /// Turn echo cancellation on or off. `tail_ms` is the longest echo to cancel (50-500);
+    /// changing it restarts adaptation.
+    pub fn set_echo_cancellation(&mut self, enabled: bool, tail_ms: u32) -> Result<(), String> {
+        self.echo_canceller = if enabled {
+            Some(EchoCanceller::new(tail_ms)?)
+        } else {
+            None
+        };
+        Ok(())
+    }
     
     pub fn set_input_mode(&mut self, mode: InputMode) {
         self.input_mode = mode;
+        self.voice_detector = matches!(mode, InputMode::VoiceActivity | InputMode::Hybrid)
+            .then(VoiceDetector::new);
         // Reset gain when switching modes
         self.current_gain = 0.0;
-        if mode == InputMode::PushToTalk {
+        if matches!(mode, InputMode::PushToTalk | InputMode::Hybrid) {
             self.ptt_pressed = false;
+            self.ptt_tail_ms = 0;
         }
     }
     
@@ -140,22 +1516,72 @@ impl AudioDSP {
         self.ptt_pressed = pressed;
     }
     
+    /// How long (0-500 ms) transmission continues after PTT is released, so the last
+    /// syllable isn't clipped.
+    pub fn set_ptt_release_ms(&mut self, ms: u32) {
+        self.ptt_release_ms = ms.min(MAX_PTT_RELEASE_MS);
+    }
+    
     pub fn set_transmission_muted(&mut self, muted: bool) {
         self.transmission_muted = muted;
         if muted {
             self.current_gain = 0.0;
         }
     }
+
+    pub fn transmission_muted(&self) -> bool {
+        self.transmission_muted
+    }
     
+    /// Whether the gate is (debounced) open, i.e. peers hear this mic.
+    pub fn is_transmitting(&self) -> bool {
+        self.transmission.open
+    }
+
+    /// New transmission state if it changed since the last call.
+    pub fn take_transmission_change(&mut self) -> Option<bool> {
+        std::mem::take(&mut self.transmission.changed).then_some(self.transmission.open)
+    }
+
     pub fn get_level(&self) -> f32 {
         // Return the normalized level for UI
-        if self.displayed_level < self.noise_floor {
+        self.level_for_peak(self.displayed_level)
+    }
+
+    /// Pick how the meter reading's `level` is smoothed.
+    pub fn set_meter_smoothing(&mut self, profile: MeterSmoothing) {
+        self.level_smoother.profile = profile;
+    }
+
+    /// Forget the learned noise floor (new mic, moved room) and start over from the tuned
+    /// range.
+    pub fn reset_noise_floor(&mut self) {
+        self.noise_tracker = NoiseFloorTracker::new();
+        self.noise_floor = self.meter_tuning.noise_floor;
+        self.max_level = self.meter_tuning.max_level;
+    }
+
+    /// Learned meter/gate floor in dBFS.
+    pub fn noise_floor_dbfs(&self) -> f32 {
+        linear_to_db(self.noise_floor)
+    }
+
+    /// Latest dBFS meter reading; clears the clip flag.
+    pub fn take_meter(&mut self) -> MeterReading {
+        let reading = self.meter;
+        self.meter.clipped = false;
+        reading
+    }
+
+    /// Meter/threshold scale (0-1) for a post-gain peak; used by input calibration.
+    pub fn level_for_peak(&self, peak: f32) -> f32 {
+        if peak < self.noise_floor {
             return 0.0;
         }
         
         let min_level = self.noise_floor;
         let max_level = self.max_level;
-        let normalized = (self.displayed_level - min_level) / (max_level - min_level);
+        let normalized = (peak - min_level) / (max_level - min_level);
         let clamped = normalized.min(1.0).max(0.0);
         clamped.sqrt()
     }
@@ -172,3 +1598,131 @@ pub fn get_dsp() -> Arc<Mutex<AudioDSP>> {
     }
     state.as_ref().unwrap().clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lane_helpers_match_scalar() {
+        // Odd length so the remainder path runs too.
+        let samples: Vec<f32> = (0..483).map(|i| ((i * 37) % 101) as f32 / 50.0 - 1.0).collect();
+        let peak = samples.iter().fold(0.0f32, |a, s| a.max(s.abs()));
+        let rms_scalar = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        assert_eq!(peak_abs(&samples), peak);
+        assert!((rms(&samples) - rms_scalar).abs() < 1e-5);
+        let mut scaled = samples.clone();
+        scale(&mut scaled, 0.5);
+        assert!(scaled.iter().zip(&samples).all(|(a, b)| *a == b * 0.5));
+    }
+
+    #[test]
+    fn noise_floor_tracker_follows_the_room() {
+        let mut tracker = NoiseFloorTracker::new();
+        let mut feed = |peak: f32, seconds: u32| {
+            let mut estimate = None;
+            for _ in 0..seconds * 100 {
+                estimate = tracker.update(peak, 10).or(estimate);
+            }
+            estimate
+        };
+        assert_eq!(feed(0.002, 3), Some(0.002));
+        // Quieter room: follows within a window. Digital silence is ignored.
+        assert_eq!(feed(0.0, 2), None);
+        assert_eq!(feed(0.0005, 1), Some(0.0005));
+        // Louder room: only once the quiet minima have aged out.
+        assert_eq!(feed(0.004, 4), Some(0.0005));
+        assert_eq!(feed(0.004, 1), Some(0.004));
+    }
+
+    #[test]
+    fn lookahead_limiter_never_exceeds_the_ceiling() {
+        let mut limiter = LookaheadLimiter::new();
+        let mut peak = 0.0f32;
+        for frame in 0..50 {
+            // Hot sine with sudden spikes up to +12 dB.
+            let mut samples: Vec<f32> = (0..480)
+                .map(|i| {
+                    let t = (frame * 480 + i) as f32;
+                    let spike = if i % 97 == 0 { 4.0 } else { 1.0 };
+                    1.5 * (t * 0.03).sin() * spike
+                })
+                .collect();
+            limiter.process(&mut samples);
+            peak = samples.iter().fold(peak, |a, s| a.max(s.abs()));
+        }
+        assert!(peak <= OUTPUT_CEILING + 1e-4, "peak {}", peak);
+
+        // Quiet audio passes untouched, just delayed by the lookahead.
+        let mut limiter = LookaheadLimiter::new();
+        let quiet: Vec<f32> = (0..480).map(|i| 0.3 * (i as f32 * 0.05).sin()).collect();
+        let mut samples = quiet.clone();
+        limiter.process(&mut samples);
+        for (out, inp) in samples[LOOKAHEAD..].iter().zip(&quiet) {
+            assert!((out - inp).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn clarity_lifts_presence_more_than_sibilance() {
+        let gain_at = |hz: f32| {
+            let mut clarity = Clarity::new(1.0);
+            let tone: Vec<f32> = (0..4800)
+                .map(|i| 0.1 * (i as f32 * hz * std::f32::consts::TAU / SAMPLE_RATE).sin())
+                .collect();
+            let mut samples = tone.clone();
+            clarity.process(&mut samples);
+            // Skip the filters' settling time.
+            rms(&samples[2400..]) / rms(&tone[2400..])
+        };
+        let presence = gain_at(3500.0);
+        let sibilance = gain_at(6500.0);
+        assert!(presence > 1.5, "presence gain {}", presence);
+        assert!(sibilance < presence * 0.7, "sibilance gain {}", sibilance);
+    }
+
+    #[test]
+    fn saved_chain_gains_new_stages_in_default_place() {
+        let old: Vec<DspStageConfig> = default_dsp_chain()
+            .into_iter()
+            .filter(|slot| slot.stage != DspStage::Clarity)
+            .collect();
+        assert_eq!(validate_chain(&old).unwrap().to_vec(), default_dsp_chain());
+        let mut twice = default_dsp_chain();
+        twice[0].stage = DspStage::Gate;
+        assert!(validate_chain(&twice).is_err());
+    }
+
+    #[test]
+    fn peak_hold_holds_then_falls() {
+        let mut smoother = LevelSmoother::new();
+        smoother.update(0.8, 10);
+        for _ in 0..140 {
+            smoother.update(0.1, 10);
+        }
+        assert_eq!(smoother.hold, 0.8);
+        for _ in 0..20 {
+            smoother.update(0.1, 10);
+        }
+        assert!(smoother.hold < 0.8 && smoother.hold > 0.1, "hold {}", smoother.hold);
+    }
+
+    #[test]
+    fn transmission_state_is_debounced() {
+        let mut state = TransmissionState::new();
+        state.update(1.0, 10);
+        assert!(!state.open);
+        state.update(1.0, 10);
+        assert!(state.open && std::mem::take(&mut state.changed));
+        // A short dip doesn't close it.
+        for _ in 0..5 {
+            state.update(0.001, 10);
+        }
+        state.update(1.0, 10);
+        assert!(state.open && !state.changed);
+        for _ in 0..10 {
+            state.update(0.001, 10);
+        }
+        assert!(!state.open && state.changed);
+    }
+}
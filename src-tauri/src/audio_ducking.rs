@@ -0,0 +1,358 @@
+//! System audio ducking: turn other applications down while someone in the call is talking.
+//!
+//! The output path reports remote audio via [`note_remote_audio`] (a cheap peak check); a
+//! controller thread ticks every 50 ms, ducks immediately when speech is seen and ramps
+//! back up over the release time once it stops. Original volumes are snapshotted when a
+//! duck begins and restored exactly when it ends (or when ducking is stopped).
+//!
+//! - Windows: per-session volume (`ISimpleAudioVolume`) on the default render device.
+//! - Linux: PulseAudio/PipeWire sink-input volumes via `pactl`.
+//! - macOS has no per-app volume API; start reports unsupported.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::audio_capture::monotonic_micros;
+
+/// Peak above which a remote frame counts as speech (about -40 dBFS).
+const SPEECH_PEAK: f32 = 0.01;
+/// Keep ducking this long after the last speech frame so pauses between words don't pump.
+const HOLD_MICROS: u64 = 300_000;
+const TICK: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DuckingSettings {
+    /// How far other apps are turned down while ducked (0.0 = not at all, 1.0 = silent).
+    #[serde(default = "default_duck_amount")]
+    pub duck_amount: f32,
+    /// Time to ramp other apps back to full volume after speech stops.
+    #[serde(default = "default_release_ms")]
+    pub release_ms: u32,
+}
+
+fn default_duck_amount() -> f32 {
+    0.6
+}
+
+fn default_release_ms() -> u32 {
+    800
+}
+
+impl Default for DuckingSettings {
+    fn default() -> Self {
+        Self {
+            duck_amount: default_duck_amount(),
+            release_ms: default_release_ms(),
+        }
+    }
+}
+
+struct DuckingState {
+    stop: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+static DUCKING_STATE: Mutex<Option<DuckingState>> = Mutex::new(None);
+static DUCKING_SETTINGS: Mutex<Option<DuckingSettings>> = Mutex::new(None);
+static DUCKING_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// `monotonic_micros` of the last remote frame that looked like speech.
+static LAST_REMOTE_SPEECH: AtomicU64 = AtomicU64::new(0);
+
+/// Offer remote audio to the speech detector. No-op unless ducking is running.
+pub fn note_remote_audio(samples: &[f32]) {
+    if !DUCKING_ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    if samples.iter().any(|s| s.abs() > SPEECH_PEAK) {
+        LAST_REMOTE_SPEECH.store(monotonic_micros(Instant::now()), Ordering::Relaxed);
+    }
+}
+
+pub fn ducking_settings() -> DuckingSettings {
+    DUCKING_SETTINGS
+        .lock()
+        .ok()
+        .and_then(|s| *s)
+        .unwrap_or_default()
+}
+
+/// Validate and store settings; a running controller picks them up on its next tick.
+pub fn set_ducking_settings(settings: DuckingSettings) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&settings.duck_amount) {
+        return Err(format!("Duck amount out of range: {} (0.0-1.0)", settings.duck_amount));
+    }
+    if settings.release_ms > 10_000 {
+        return Err(format!("Release time out of range: {} ms (max 10000)", settings.release_ms));
+    }
+    let mut current = DUCKING_SETTINGS
+        .lock()
+        .map_err(|_| "Failed to lock ducking settings".to_string())?;
+    *current = Some(settings);
+    Ok(())
+}
+
+/// Start ducking other applications while remote speech is playing.
+pub fn start_ducking() -> Result<(), String> {
+    stop_ducking();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<(), String>>();
+    let stop_flag = stop.clone();
+    let thread = thread::spawn(move || {
+        let mut ducker = match imp::Ducker::new() {
+            Ok(d) => {
+                let _ = ready_tx.send(Ok(()));
+                d
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        run_controller(&mut ducker, &stop_flag);
+        ducker.restore();
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|_| "Ducking thread exited unexpectedly".to_string())??;
+
+    LAST_REMOTE_SPEECH.store(0, Ordering::Relaxed);
+    DUCKING_ACTIVE.store(true, Ordering::Relaxed);
+    let mut state = DUCKING_STATE
+        .lock()
+        .map_err(|_| "Failed to lock ducking state".to_string())?;
+    *state = Some(DuckingState { stop, thread });
+    Ok(())
+}
+
+/// Stop ducking and put other applications back at their original volume.
+pub fn stop_ducking() {
+    DUCKING_ACTIVE.store(false, Ordering::Relaxed);
+    let state = match DUCKING_STATE.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return,
+    };
+    if let Some(state) = state {
+        state.stop.store(true, Ordering::SeqCst);
+        let _ = state.thread.join();
+    }
+}
+
+pub fn is_ducking() -> bool {
+    DUCKING_ACTIVE.load(Ordering::Relaxed)
+}
+
+fn run_controller(ducker: &mut imp::Ducker, stop: &AtomicBool) {
+    // 1.0 = other apps untouched, 1.0 - duck_amount = fully ducked.
+    let mut level = 1.0f32;
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(TICK);
+        let settings = ducking_settings();
+        let floor = 1.0 - settings.duck_amount;
+        let now = monotonic_micros(Instant::now());
+        let last = LAST_REMOTE_SPEECH.load(Ordering::Relaxed);
+        let speaking = last != 0 && now.saturating_sub(last) < HOLD_MICROS;
+
+        let target = if speaking {
+            floor
+        } else {
+            let step = TICK.as_millis() as f32 / settings.release_ms.max(1) as f32;
+            (level + step * (1.0 - floor)).min(1.0)
+        };
+        if target == level {
+            continue;
+        }
+        level = target;
+        if level >= 1.0 {
+            ducker.restore();
+        } else if let Err(e) = ducker.apply(level) {
+            eprintln!("[Ducking] {}", e);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use windows::core::Interface;
+    use windows::Win32::Media::Audio::{
+        eConsole, eRender, IAudioSessionControl2, IAudioSessionManager2, IMMDeviceEnumerator,
+        ISimpleAudioVolume, MMDeviceEnumerator,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+
+    /// Session volumes of other processes, snapshotted when a duck begins.
+    pub struct Ducker {
+        ducked: Vec<(ISimpleAudioVolume, f32)>,
+    }
+
+    impl Ducker {
+        pub fn new() -> Result<Self, String> {
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            }
+            Ok(Self { ducked: Vec::new() })
+        }
+
+        /// Set every other app to `level` times its original volume.
+        pub fn apply(&mut self, level: f32) -> Result<(), String> {
+            if self.ducked.is_empty() {
+                self.ducked = unsafe { snapshot_sessions()? };
+            }
+            for (volume, original) in &self.ducked {
+                unsafe {
+                    let _ = volume.SetMasterVolume(original * level, std::ptr::null());
+                }
+            }
+            Ok(())
+        }
+
+        pub fn restore(&mut self) {
+            for (volume, original) in self.ducked.drain(..) {
+                unsafe {
+                    let _ = volume.SetMasterVolume(original, std::ptr::null());
+                }
+            }
+        }
+    }
+
+    unsafe fn snapshot_sessions() -> Result<Vec<(ISimpleAudioVolume, f32)>, String> {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+            .map_err(|e| format!("Failed to create device enumerator: {}", e))?;
+        let device = enumerator
+            .GetDefaultAudioEndpoint(eRender, eConsole)
+            .map_err(|e| format!("Failed to get default output device: {}", e))?;
+        let manager: IAudioSessionManager2 = device
+            .Activate(CLSCTX_ALL, None)
+            .map_err(|e| format!("Failed to activate session manager: {}", e))?;
+        let sessions = manager
+            .GetSessionEnumerator()
+            .map_err(|e| format!("Failed to enumerate audio sessions: {}", e))?;
+        let count = sessions.GetCount().map_err(|e| e.to_string())?;
+
+        let own_pid = std::process::id();
+        let mut out = Vec::new();
+        for i in 0..count {
+            let Ok(session) = sessions.GetSession(i) else { continue };
+            let Ok(session2) = session.cast::<IAudioSessionControl2>() else { continue };
+            let Ok(pid) = session2.GetProcessId() else { continue };
+            // Leave our own output and system sounds (pid 0) alone.
+            if pid == 0 || pid == own_pid {
+                continue;
+            }
+            let Ok(volume) = session.cast::<ISimpleAudioVolume>() else { continue };
+            let Ok(original) = volume.GetMasterVolume() else { continue };
+            out.push((volume, original));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::process::Command;
+
+    struct SinkInput {
+        index: u32,
+        /// Raw PulseAudio volumes (65536 = 100%) in channel-map order.
+        volumes: Vec<u64>,
+    }
+
+    /// Sink-input volumes of other processes, snapshotted when a duck begins.
+    pub struct Ducker {
+        ducked: Vec<SinkInput>,
+    }
+
+    impl Ducker {
+        pub fn new() -> Result<Self, String> {
+            Command::new("pactl")
+                .arg("info")
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .ok_or_else(|| "Ducking needs PulseAudio or PipeWire (pactl not available)".to_string())?;
+            Ok(Self { ducked: Vec::new() })
+        }
+
+        /// Set every other app to `level` times its original volume.
+        pub fn apply(&mut self, level: f32) -> Result<(), String> {
+            if self.ducked.is_empty() {
+                self.ducked = snapshot_sink_inputs()?;
+            }
+            for input in &self.ducked {
+                let scaled: Vec<u64> = input
+                    .volumes
+                    .iter()
+                    .map(|v| (*v as f32 * level).round() as u64)
+                    .collect();
+                set_volume(input.index, &scaled);
+            }
+            Ok(())
+        }
+
+        pub fn restore(&mut self) {
+            for input in self.ducked.drain(..) {
+                set_volume(input.index, &input.volumes);
+            }
+        }
+    }
+
+    fn set_volume(index: u32, volumes: &[u64]) {
+        let _ = Command::new("pactl")
+            .arg("set-sink-input-volume")
+            .arg(index.to_string())
+            .args(volumes.iter().map(|v| v.to_string()))
+            .output();
+    }
+
+    fn snapshot_sink_inputs() -> Result<Vec<SinkInput>, String> {
+        let output = Command::new("pactl")
+            .args(["-f", "json", "list", "sink-inputs"])
+            .output()
+            .map_err(|e| format!("Failed to run pactl: {}", e))?;
+        if !output.status.success() {
+            return Err("Failed to list sink inputs (pactl 16+ required)".to_string());
+        }
+        let inputs: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse pactl output: {}", e))?;
+
+        let own_pid = std::process::id().to_string();
+        let mut out = Vec::new();
+        for input in &inputs {
+            let Some(index) = input["index"].as_u64() else { continue };
+            if input["properties"]["application.process.id"].as_str() == Some(own_pid.as_str()) {
+                continue;
+            }
+            // The volume map is keyed by channel name; the channel map gives the order.
+            let Some(channel_map) = input["channel_map"].as_str() else { continue };
+            let volumes: Option<Vec<u64>> = channel_map
+                .split(',')
+                .map(|ch| input["volume"][ch]["value"].as_u64())
+                .collect();
+            if let Some(volumes) = volumes {
+                out.push(SinkInput { index: index as u32, volumes });
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod imp {
+    pub struct Ducker;
+
+    impl Ducker {
+        pub fn new() -> Result<Self, String> {
+            Err("System audio ducking is not supported on this platform".to_string())
+        }
+
+        pub fn apply(&mut self, _level: f32) -> Result<(), String> {
+            Ok(())
+        }
+
+        pub fn restore(&mut self) {}
+    }
+}
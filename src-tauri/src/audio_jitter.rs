@@ -0,0 +1,486 @@
+//! Adaptive jitter buffer for remote audio.
+//!
+//! Remote packets (Opus or raw PCM) arrive with the sender's sequence number and capture
+//! timestamp, in whatever order and rhythm the network delivers them. Each peer gets a
+//! buffer that reorders by sequence, waits until it holds the target delay, then hands
+//! the output mixer exactly 10 ms per tick from a single playout thread.
+//!
+//! Losses are concealed rather than skipped: Opus uses the next packet's in-band FEC when
+//! it's already here, otherwise libopus PLC; PCM repeats the last frame with a fade-out.
+//! The target delay follows the measured interarrival jitter (RFC 3550 estimator) within
+//! configurable bounds, or can be pinned to a fixed value.
+
+use crate::audio_output::push_peer_pcm;
+use crate::audio_resample::FRAME_SAMPLES;
+use audiopus::coder::Decoder;
+use audiopus::packet::Packet;
+use audiopus::{Channels, MutSignals, SampleRate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SAMPLES_PER_MS: usize = 48;
+const TICK: Duration = Duration::from_millis(10);
+/// If the playout thread oversleeps by more than this, resync instead of bursting.
+const MAX_TICK_LAG: Duration = Duration::from_millis(100);
+/// Largest Opus frame (120 ms at 48 kHz).
+const MAX_OPUS_FRAME_SAMPLES: usize = 5760;
+/// Audio held beyond the target before the oldest is discarded to catch up.
+const CATCHUP_SLACK_MS: usize = 60;
+/// A sequence jump this large (either way) means the sender restarted; start over.
+const SEQ_RESET_GAP: u64 = 500;
+/// Consecutive concealed frames before PCM concealment has faded to silence.
+const PCM_FADE_FRAMES: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct JitterConfig {
+    /// Lower bound for the adaptive target delay.
+    #[serde(default = "default_min_delay")]
+    pub min_delay_ms: u32,
+    /// Upper bound for the adaptive target delay.
+    #[serde(default = "default_max_delay")]
+    pub max_delay_ms: u32,
+    /// Fixed target delay; None = adapt to measured jitter.
+    #[serde(default)]
+    pub target_delay_ms: Option<u32>,
+}
+
+fn default_min_delay() -> u32 {
+    20
+}
+
+fn default_max_delay() -> u32 {
+    200
+}
+
+impl Default for JitterConfig {
+    fn default() -> Self {
+        Self {
+            min_delay_ms: default_min_delay(),
+            max_delay_ms: default_max_delay(),
+            target_delay_ms: None,
+        }
+    }
+}
+
+/// Encoded or raw audio for one remote packet.
+pub enum PacketPayload {
+    Opus(Vec<u8>),
+    /// Mono 48 kHz samples.
+    Pcm(Vec<f32>),
+}
+
+impl PacketPayload {
+    /// Samples the packet decodes to (validates Opus packets).
+    fn samples(&self) -> Result<usize, String> {
+        match self {
+            PacketPayload::Opus(bytes) => {
+                let packet = Packet::try_from(bytes.as_slice())
+                    .map_err(|e| format!("Invalid Opus packet: {}", e))?;
+                audiopus::packet::nb_samples(packet, SampleRate::Hz48000)
+                    .map_err(|e| format!("Invalid Opus packet: {}", e))
+            }
+            PacketPayload::Pcm(pcm) => Ok(pcm.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JitterStats {
+    pub buffered_ms: f64,
+    pub target_ms: f64,
+    /// Smoothed interarrival jitter.
+    pub jitter_ms: f64,
+    pub received: u64,
+    /// Frames concealed (PLC / fade) because their packet never arrived in time.
+    pub concealed: u64,
+    /// Lost packets rebuilt from the next packet's FEC data.
+    pub fec_recovered: u64,
+    /// Packets that arrived after their slot was played.
+    pub late: u64,
+    /// Times the buffer ran dry and had to rebuffer.
+    pub underruns: u64,
+    /// Packets dropped to bring an overfull buffer back to target.
+    pub discarded: u64,
+}
+
+struct QueuedPacket {
+    payload: PacketPayload,
+    samples: usize,
+}
+
+/// Jitter buffer for one remote peer.
+struct PeerJitter {
+    packets: BTreeMap<u64, QueuedPacket>,
+    /// Next sequence number to play; None until the first packet.
+    next_seq: Option<u64>,
+    /// Decoded audio waiting to be played.
+    out: VecDeque<f32>,
+    playing: bool,
+    decoder: Option<Decoder>,
+    decode_buf: Vec<f32>,
+    /// Duration of the last played packet, used to size concealment.
+    last_packet_samples: usize,
+    /// Last decoded PCM packet, for PCM concealment.
+    last_pcm: Vec<f32>,
+    concealed_run: u32,
+    /// (arrival, sender capture_ts µs, seq) of the newest packet, for jitter estimation.
+    last_arrival: Option<(Instant, u64, u64)>,
+    jitter_us: f64,
+    stats: JitterStats,
+}
+
+impl PeerJitter {
+    fn new() -> Self {
+        Self {
+            packets: BTreeMap::new(),
+            next_seq: None,
+            out: VecDeque::with_capacity(FRAME_SAMPLES * 8),
+            playing: false,
+            decoder: None,
+            decode_buf: vec![0.0; MAX_OPUS_FRAME_SAMPLES],
+            last_packet_samples: FRAME_SAMPLES,
+            last_pcm: Vec::new(),
+            concealed_run: 0,
+            last_arrival: None,
+            jitter_us: 0.0,
+            stats: JitterStats::default(),
+        }
+    }
+
+    fn insert(&mut self, seq: u64, capture_ts: u64, payload: PacketPayload, now: Instant) -> Result<(), String> {
+        let samples = payload.samples()?;
+        if let Some(next) = self.next_seq {
+            if seq + SEQ_RESET_GAP < next || seq > next + SEQ_RESET_GAP {
+                let stats = std::mem::take(&mut self.stats);
+                *self = Self::new();
+                self.stats = stats;
+            } else if seq < next {
+                self.stats.late += 1;
+                return Ok(());
+            }
+        }
+        if self.packets.contains_key(&seq) {
+            return Ok(());
+        }
+
+        // RFC 3550: D = (arrival delta) - (send delta), J += (|D| - J) / 16.
+        if let Some((prev_at, prev_ts, prev_seq)) = self.last_arrival {
+            if seq > prev_seq {
+                let arrival = now.saturating_duration_since(prev_at).as_micros() as f64;
+                let sent = capture_ts.saturating_sub(prev_ts) as f64;
+                self.jitter_us += ((arrival - sent).abs() - self.jitter_us) / 16.0;
+            }
+        }
+        if !matches!(self.last_arrival, Some((_, _, prev_seq)) if seq <= prev_seq) {
+            self.last_arrival = Some((now, capture_ts, seq));
+        }
+
+        self.stats.received += 1;
+        self.packets.insert(seq, QueuedPacket { payload, samples });
+        Ok(())
+    }
+
+    fn buffered_samples(&self) -> usize {
+        self.out.len() + self.packets.values().map(|p| p.samples).sum::<usize>()
+    }
+
+    fn target_samples(&self, config: &JitterConfig) -> usize {
+        let ms = match config.target_delay_ms {
+            Some(fixed) => fixed as f64,
+            None => {
+                let frame_ms = (self.last_packet_samples / SAMPLES_PER_MS) as f64;
+                (frame_ms + 3.0 * self.jitter_us / 1000.0)
+                    .clamp(config.min_delay_ms as f64, config.max_delay_ms.max(config.min_delay_ms) as f64)
+            }
+        };
+        ms as usize * SAMPLES_PER_MS
+    }
+
+    /// Produce the next 10 ms of audio, or None while (re)buffering.
+    fn tick(&mut self, config: &JitterConfig) -> Option<[f32; FRAME_SAMPLES]> {
+        let target = self.target_samples(config);
+        if !self.playing {
+            if self.packets.is_empty() || self.buffered_samples() < target {
+                return None;
+            }
+            self.playing = true;
+        }
+
+        while self.out.len() < FRAME_SAMPLES && self.pull_next() {}
+
+        let mut frame = [0.0f32; FRAME_SAMPLES];
+        let len = self.out.len().min(FRAME_SAMPLES);
+        for (dst, src) in frame.iter_mut().zip(self.out.drain(..len)) {
+            *dst = src;
+        }
+        if len < FRAME_SAMPLES {
+            // Ran dry with nothing left to conceal from: rebuffer up to the target again.
+            self.playing = false;
+            self.stats.underruns += 1;
+        }
+
+        // Too much queued (network burst after a stall): drop the oldest to get back to target.
+        while self.buffered_samples() > target + CATCHUP_SLACK_MS * SAMPLES_PER_MS {
+            let Some((&seq, _)) = self.packets.iter().next() else { break };
+            self.packets.remove(&seq);
+            self.next_seq = Some(seq + 1);
+            self.stats.discarded += 1;
+        }
+        Some(frame)
+    }
+
+    /// Decode (or conceal) the next packet in sequence into `out`. False when there is
+    /// nothing to play: the next packet hasn't arrived and none after it has either.
+    fn pull_next(&mut self) -> bool {
+        let next = match self.next_seq {
+            Some(next) => next,
+            None => match self.packets.keys().next() {
+                Some(&first) => first,
+                None => return false,
+            },
+        };
+
+        if let Some(packet) = self.packets.remove(&next) {
+            self.next_seq = Some(next + 1);
+            self.last_packet_samples = packet.samples;
+            self.concealed_run = 0;
+            self.decode(Some(&packet.payload), false);
+            return true;
+        }
+        if self.packets.is_empty() {
+            return false;
+        }
+
+        // Later packets are here but `next` isn't: treat it as lost.
+        self.next_seq = Some(next + 1);
+        self.concealed_run += 1;
+        match self.packets.get(&(next + 1)).map(|p| &p.payload) {
+            Some(PacketPayload::Opus(bytes)) => {
+                // The following packet carries a low-bitrate copy of this one.
+                let bytes = bytes.clone();
+                if self.decode(Some(&PacketPayload::Opus(bytes)), true) {
+                    self.stats.fec_recovered += 1;
+                    return true;
+                }
+                self.stats.concealed += 1;
+                self.decode(None, false);
+            }
+            _ => {
+                self.stats.concealed += 1;
+                self.decode(None, false);
+            }
+        }
+        true
+    }
+
+    /// Decode a payload into `out`; `None` conceals one packet's worth of audio.
+    /// With `fec`, the payload is the packet *after* the lost one. Returns false on failure
+    /// (after appending silence so timing is preserved).
+    fn decode(&mut self, payload: Option<&PacketPayload>, fec: bool) -> bool {
+        let samples = self.last_packet_samples.min(MAX_OPUS_FRAME_SAMPLES);
+        match payload {
+            Some(PacketPayload::Pcm(pcm)) => {
+                self.out.extend(pcm.iter().copied());
+                self.last_pcm.clear();
+                self.last_pcm.extend_from_slice(pcm);
+                true
+            }
+            Some(PacketPayload::Opus(bytes)) => {
+                let decoded = self.opus_decode(Some(bytes), samples, fec);
+                decoded.is_some()
+            }
+            None if self.decoder.is_some() => self.opus_decode(None, samples, false).is_some(),
+            None => {
+                // PCM concealment: repeat the last frame, fading out over a few frames.
+                let fade = (1.0 - self.concealed_run as f32 / PCM_FADE_FRAMES).max(0.0);
+                if self.last_pcm.is_empty() || fade == 0.0 {
+                    self.out.extend(std::iter::repeat_n(0.0, samples));
+                } else {
+                    self.out.extend(self.last_pcm.iter().map(|s| s * fade));
+                }
+                true
+            }
+        }
+    }
+
+    fn opus_decode(&mut self, bytes: Option<&Vec<u8>>, samples: usize, fec: bool) -> Option<usize> {
+        if self.decoder.is_none() {
+            self.decoder = Decoder::new(SampleRate::Hz48000, Channels::Mono).ok();
+        }
+        // FEC/PLC decode exactly one lost frame's worth; normal decode takes what's in the packet.
+        let len = if fec || bytes.is_none() { samples } else { MAX_OPUS_FRAME_SAMPLES };
+        let decoded = (|| {
+            let decoder = self.decoder.as_mut()?;
+            let packet = match bytes {
+                Some(b) => Some(Packet::try_from(b.as_slice()).ok()?),
+                None => None,
+            };
+            let output = MutSignals::try_from(&mut self.decode_buf[..len]).ok()?;
+            decoder.decode_float(packet, output, fec).ok()
+        })();
+        match decoded {
+            Some(n) => self.out.extend(self.decode_buf[..n].iter().copied()),
+            None => self.out.extend(std::iter::repeat_n(0.0, samples)),
+        }
+        decoded
+    }
+
+    fn stats(&self, config: &JitterConfig) -> JitterStats {
+        JitterStats {
+            buffered_ms: self.buffered_samples() as f64 / SAMPLES_PER_MS as f64,
+            target_ms: self.target_samples(config) as f64 / SAMPLES_PER_MS as f64,
+            jitter_ms: self.jitter_us / 1000.0,
+            ..self.stats.clone()
+        }
+    }
+}
+
+struct JitterState {
+    peers: HashMap<String, PeerJitter>,
+    config: JitterConfig,
+}
+
+static JITTER_STATE: Mutex<Option<JitterState>> = Mutex::new(None);
+/// Bumped on every start/stop so a stopped playout thread can't be revived by a quick restart.
+static PLAYOUT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn with_state<T>(f: impl FnOnce(&mut JitterState) -> T) -> Result<T, String> {
+    let mut guard = JITTER_STATE
+        .lock()
+        .map_err(|_| "Failed to lock jitter buffer state".to_string())?;
+    let state = guard.get_or_insert_with(|| JitterState {
+        peers: HashMap::new(),
+        config: JitterConfig::default(),
+    });
+    Ok(f(state))
+}
+
+/// Queue a remote packet for `peer_id`. Packets may arrive out of order or twice.
+pub fn push_packet(peer_id: &str, seq: u64, capture_ts: u64, payload: PacketPayload) -> Result<(), String> {
+    let now = Instant::now();
+    with_state(|state| {
+        state
+            .peers
+            .entry(peer_id.to_string())
+            .or_insert_with(PeerJitter::new)
+            .insert(seq, capture_ts, payload, now)
+    })?
+}
+
+/// Start the playout thread that feeds the output mixer every 10 ms. Restarts if running.
+pub fn start_playout() {
+    let generation = PLAYOUT_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    thread::spawn(move || {
+        let mut deadline = Instant::now();
+        let mut frames: Vec<(String, [f32; FRAME_SAMPLES])> = Vec::new();
+        while PLAYOUT_GENERATION.load(Ordering::SeqCst) == generation {
+            deadline += TICK;
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            } else if now - deadline > MAX_TICK_LAG {
+                deadline = now;
+            }
+
+            frames.clear();
+            let _ = with_state(|state| {
+                let config = state.config;
+                for (peer_id, peer) in state.peers.iter_mut() {
+                    if let Some(frame) = peer.tick(&config) {
+                        frames.push((peer_id.clone(), frame));
+                    }
+                }
+            });
+            // Outside the jitter lock: the mixer has its own.
+            for (peer_id, frame) in &frames {
+                crate::audio_multitrack::tee_remote(peer_id, frame);
+                let _ = push_peer_pcm(peer_id, frame);
+            }
+        }
+    });
+}
+
+/// Stop the playout thread and drop all buffered audio.
+pub fn stop_playout() {
+    PLAYOUT_GENERATION.fetch_add(1, Ordering::SeqCst);
+    let _ = with_state(|state| state.peers.clear());
+}
+
+pub fn remove_peer(peer_id: &str) {
+    let _ = with_state(|state| state.peers.remove(peer_id));
+}
+
+pub fn set_config(config: JitterConfig) -> Result<(), String> {
+    if config.min_delay_ms > config.max_delay_ms {
+        return Err("Jitter buffer min delay exceeds max delay".to_string());
+    }
+    with_state(|state| state.config = config)
+}
+
+pub fn config() -> JitterConfig {
+    with_state(|state| state.config).unwrap_or_default()
+}
+
+/// Per-peer buffer health for the dev overlay.
+pub fn stats() -> HashMap<String, JitterStats> {
+    with_state(|state| {
+        let config = state.config;
+        state
+            .peers
+            .iter()
+            .map(|(id, peer)| (id.clone(), peer.stats(&config)))
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(value: f32) -> PacketPayload {
+        PacketPayload::Pcm(vec![value; FRAME_SAMPLES])
+    }
+
+    fn fixed(ms: u32) -> JitterConfig {
+        JitterConfig {
+            target_delay_ms: Some(ms),
+            ..JitterConfig::default()
+        }
+    }
+
+    #[test]
+    fn reorders_and_waits_for_target() {
+        let config = fixed(30);
+        let mut peer = PeerJitter::new();
+        let now = Instant::now();
+        peer.insert(1, 10_000, pcm(0.2), now).unwrap();
+        peer.insert(0, 0, pcm(0.1), now).unwrap();
+        assert!(peer.tick(&config).is_none(), "20 ms buffered, target 30 ms");
+        peer.insert(2, 20_000, pcm(0.3), now).unwrap();
+        assert_eq!(peer.tick(&config).unwrap()[0], 0.1);
+        assert_eq!(peer.tick(&config).unwrap()[0], 0.2);
+        assert_eq!(peer.tick(&config).unwrap()[0], 0.3);
+    }
+
+    #[test]
+    fn conceals_a_lost_packet_and_drops_it_when_late() {
+        let config = fixed(10);
+        let mut peer = PeerJitter::new();
+        let now = Instant::now();
+        peer.insert(0, 0, pcm(0.5), now).unwrap();
+        peer.insert(2, 20_000, pcm(0.7), now).unwrap();
+        assert_eq!(peer.tick(&config).unwrap()[0], 0.5);
+        let concealed = peer.tick(&config).unwrap()[0];
+        assert!(concealed > 0.0 && concealed < 0.5, "faded repeat, got {}", concealed);
+        peer.insert(1, 10_000, pcm(0.6), now).unwrap();
+        assert_eq!(peer.tick(&config).unwrap()[0], 0.7);
+        assert_eq!(peer.stats.concealed, 1);
+        assert_eq!(peer.stats.late, 1);
+    }
+}
@@ -0,0 +1,218 @@
+//! System loopback ("what you hear") capture for screen-share audio.
+//!
+//! Runs alongside mic capture with its own stream and processing thread. Frames are
+//! downmixed to mono, resampled to 48 kHz and re-sliced to 10 ms, but skip the mic DSP
+//! (no gain or gating on shared desktop audio).
+//!
+//! - Windows: WASAPI loopback. cpal enables it transparently when an input stream is
+//!   built on an output (render) device.
+//! - Linux: PulseAudio/PipeWire monitor sources, which show up as input devices whose
+//!   name contains "monitor".
+//! - macOS has no native loopback; an installed virtual device (e.g. BlackHole) is used,
+//!   or any input can be picked by ID.
+
+use crate::audio_capture::{
+    monotonic_micros, resolve_device, try_send_frame, AudioDeviceKind, ProcessedFrame, RawFrame,
+};
+use crate::audio_resample::{FrameResampler, FRAME_SAMPLES};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{Device, SampleFormat, Stream, StreamConfig, SupportedStreamConfig};
+use rtrb::{Producer, RingBuffer};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Loopback frames dropped (ring full or emitter behind).
+static DROPPED_LOOPBACK: AtomicU64 = AtomicU64::new(0);
+
+/// Raw ring capacity: ~80 ms, same as mic capture.
+const RAW_RING_CAP: usize = 8;
+
+/// The cpal Stream is !Send, so it lives on its own thread; dropping `stop_tx` ends it.
+struct LoopbackCaptureState {
+    stop_tx: mpsc::Sender<()>,
+    stream_thread: Option<thread::JoinHandle<()>>,
+    processing_thread: Option<thread::JoinHandle<()>>,
+}
+
+static LOOPBACK_CAPTURE_STATE: Mutex<Option<LoopbackCaptureState>> = Mutex::new(None);
+
+/// Pick the device + native config to capture desktop audio from.
+#[cfg(windows)]
+fn find_loopback_source(device_id: Option<&str>) -> Result<(Device, SupportedStreamConfig), String> {
+    // Loopback must use the render device's mix format (shared mode).
+    let device = resolve_device(AudioDeviceKind::Output, device_id)?;
+    let config = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get device config: {}", e))?;
+    Ok((device, config))
+}
+
+/// Pick the device + native config to capture desktop audio from.
+#[cfg(not(windows))]
+fn find_loopback_source(device_id: Option<&str>) -> Result<(Device, SupportedStreamConfig), String> {
+    // Any input can be picked by ID (e.g. a virtual cable); otherwise the first monitor.
+    let device = match device_id {
+        Some(id) => resolve_device(AudioDeviceKind::Input, Some(id))?,
+        None => resolve_device(AudioDeviceKind::Monitor, None)?,
+    };
+    let config = device
+        .default_input_config()
+        .map_err(|e| format!("Failed to get device config: {}", e))?;
+    Ok((device, config))
+}
+
+/// Start desktop-audio capture. Processed 48 kHz mono frames go to `frame_sender`
+/// (bounded; frames are dropped when it's full). Replaces any running loopback capture.
+pub fn start_loopback_capture(
+    device_id: Option<String>,
+    frame_sender: mpsc::SyncSender<ProcessedFrame>,
+) -> Result<(), String> {
+    stop_loopback_capture();
+
+    let (device, supported) = find_loopback_source(device_id.as_deref())?;
+    let sample_format = supported.sample_format();
+    // Native channel count and rate; loopback rejects anything but the mix format.
+    let config = StreamConfig {
+        channels: supported.channels(),
+        sample_rate: supported.sample_rate(),
+        buffer_size: cpal::BufferSize::Default,
+    };
+    let resampler = FrameResampler::new(config.sample_rate.0)?;
+
+    DROPPED_LOOPBACK.store(0, Ordering::Relaxed);
+
+    let (raw_producer, mut raw_consumer) = RingBuffer::<RawFrame>::new(RAW_RING_CAP);
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+
+    let stream_thread = thread::spawn(move || {
+        let stream = match sample_format {
+            SampleFormat::F32 => build_loopback_stream::<f32>(&device, &config, raw_producer),
+            SampleFormat::I16 => build_loopback_stream::<i16>(&device, &config, raw_producer),
+            SampleFormat::U16 => build_loopback_stream::<u16>(&device, &config, raw_producer),
+            _ => Err(format!("Unsupported sample format: {:?}", sample_format)),
+        };
+        let stream = match stream.and_then(|s| {
+            s.play().map_err(|e| format!("Failed to start stream: {}", e))?;
+            Ok(s)
+        }) {
+            Ok(s) => s,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+        let _ = stop_rx.recv();
+        drop(stream);
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|_| "Loopback thread exited unexpectedly".to_string())??;
+
+    // Drain ring → resample → bounded channel. Exits once the stream (producer) is dropped.
+    let processing_thread = thread::spawn(move || {
+        let mut resampler = resampler;
+        let mut seq: u64 = 0;
+        loop {
+            let raw = match raw_consumer.pop() {
+                Ok(f) => f,
+                Err(rtrb::PopError::Empty) => {
+                    if raw_consumer.is_abandoned() {
+                        break;
+                    }
+                    thread::sleep(std::time::Duration::from_millis(2));
+                    continue;
+                }
+            };
+            resampler.process(&raw.samples[..raw.len], |frame_48k| {
+                let frame = ProcessedFrame::pooled(frame_48k, monotonic_micros(raw.captured_at), seq);
+                seq += 1;
+                if !try_send_frame(&frame_sender, frame) {
+                    DROPPED_LOOPBACK.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let mut state = LOOPBACK_CAPTURE_STATE
+        .lock()
+        .map_err(|_| "Failed to lock loopback capture state".to_string())?;
+    *state = Some(LoopbackCaptureState {
+        stop_tx,
+        stream_thread: Some(stream_thread),
+        processing_thread: Some(processing_thread),
+    });
+    Ok(())
+}
+
+/// Build a loopback stream. The callback downmixes interleaved device audio to mono and
+/// packs it into fixed 480-sample frames (device buffer sizes are arbitrary here).
+/// No allocation, no blocking: drop the frame if the ring is full.
+fn build_loopback_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    mut raw_producer: Producer<RawFrame>,
+) -> Result<Stream, String>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    let scale = 1.0 / channels as f32;
+    let mut frame = [0.0f32; FRAME_SAMPLES];
+    let mut pos = 0usize;
+    let err_fn = |err| eprintln!("Loopback stream error: {}", err);
+
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                for sample_frame in data.chunks_exact(channels) {
+                    let sum: f32 = sample_frame
+                        .iter()
+                        .map(|s| <f32 as cpal::FromSample<T>>::from_sample_(*s))
+                        .sum();
+                    frame[pos] = sum * scale;
+                    pos += 1;
+                    if pos == FRAME_SAMPLES {
+                        pos = 0;
+                        let raw = RawFrame { samples: frame, len: FRAME_SAMPLES, captured_at: Instant::now() };
+                        if raw_producer.push(raw).is_err() {
+                            DROPPED_LOOPBACK.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+            },
+            err_fn,
+            None,
+        )
+        .map_err(|e| format!("Failed to build loopback stream: {}", e))
+}
+
+/// Stop desktop-audio capture and release the device.
+pub fn stop_loopback_capture() {
+    let state = match LOOPBACK_CAPTURE_STATE.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return,
+    };
+    if let Some(mut state) = state {
+        let _ = state.stop_tx.send(());
+        if let Some(thread) = state.stream_thread.take() {
+            let _ = thread.join();
+        }
+        // Stream dropped → ring abandoned → processing thread exits.
+        if let Some(thread) = state.processing_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Loopback frames dropped since loopback capture started.
+pub fn dropped_loopback_frames() -> u64 {
+    DROPPED_LOOPBACK.load(Ordering::Relaxed)
+}
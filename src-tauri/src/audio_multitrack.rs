@@ -0,0 +1,530 @@
+//! Multitrack call recording: one track per participant, for podcast-style editing.
+//!
+//! The local mic is teed after DSP ([`tee_local`], from the capture processing thread) and
+//! each remote peer as it leaves its jitter buffer ([`tee_remote`], from the playout
+//! thread), before local volume or pan. A writer thread places every frame on a shared
+//! timeline by arrival time: a track that falls behind (late joiner, gap in the stream) is
+//! padded with silence and one that runs ahead is trimmed, so the tracks line up in an editor.
+//!
+//! Tracks are written as separate mono files; with [`MultitrackLayout::Combined`] they are
+//! merged into one WAV (a channel per participant) when the recording stops.
+//!
+//! Consent: every new remote track is announced through the event hook so the app can tell
+//! that peer they're being recorded. With `require_consent`, a peer's track stays silent
+//! until [`set_peer_consent`] grants it.
+
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::audio_recording::{RecordingFormat, RecordingWriter, WAV_MAX_BYTES};
+use crate::audio_resample::{FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+use crate::audio_tap::tee_slots;
+
+/// ~2 s of mic frames between the processing thread and the writer.
+const LOCAL_RING_FRAMES: usize = 200;
+/// ~2 s of audio for a handful of talking peers.
+const REMOTE_RING_FRAMES: usize = 1000;
+/// How far a track may stray from the timeline before it's padded or trimmed (50 ms:
+/// a 40 ms capture frame arrives all at once).
+const ALIGN_SLACK_SAMPLES: u64 = 5 * FRAME_SAMPLES as u64;
+const MAX_TRACKS: usize = 64;
+const SILENCE: [f32; FRAME_SAMPLES] = [0.0; FRAME_SAMPLES];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MultitrackLayout {
+    /// One mono file per participant.
+    #[default]
+    Separate,
+    /// One WAV with a channel per participant, assembled when the recording stops.
+    Combined,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultitrackOptions {
+    #[serde(default = "default_format")]
+    pub format: RecordingFormat,
+    #[serde(default)]
+    pub layout: MultitrackLayout,
+    /// Record the local mic as its own track.
+    #[serde(default = "default_include_local")]
+    pub include_local: bool,
+    /// Keep remote tracks silent until the peer consents (see [`set_peer_consent`]).
+    #[serde(default)]
+    pub require_consent: bool,
+}
+
+fn default_format() -> RecordingFormat {
+    RecordingFormat::Wav
+}
+
+fn default_include_local() -> bool {
+    true
+}
+
+impl Default for MultitrackOptions {
+    fn default() -> Self {
+        Self {
+            format: default_format(),
+            layout: MultitrackLayout::default(),
+            include_local: default_include_local(),
+            require_consent: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackSummary {
+    /// None for the local mic.
+    pub peer_id: Option<String>,
+    /// The track's own file; None once merged into the combined file.
+    pub path: Option<String>,
+    /// Channel in the combined file.
+    pub channel: Option<u16>,
+    pub duration_secs: f64,
+    /// Silence inserted and audio trimmed to keep the track on the timeline.
+    pub padded_secs: f64,
+    pub trimmed_secs: f64,
+    /// Whether the peer had consented by the end (always true for the local track).
+    pub consented: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MultitrackSummary {
+    pub directory: String,
+    pub combined_path: Option<String>,
+    pub duration_secs: f64,
+    pub tracks: Vec<TrackSummary>,
+    pub dropped_frames: u64,
+    /// "stopped" or an error message.
+    pub stop_reason: String,
+}
+
+/// Consent signaling hooks, emitted as `cordia:multitrack-recording`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MultitrackEvent {
+    /// Recording began: let everyone in the call know.
+    Started { directory: String, require_consent: bool },
+    /// First audio from a peer opened a track for them; ask them for consent here.
+    TrackAdded { peer_id: String, consented: bool },
+    Stopped(MultitrackSummary),
+}
+
+struct TapFrame {
+    samples: [f32; FRAME_SAMPLES],
+    at: Instant,
+}
+
+struct RemoteFrame {
+    peer_id: String,
+    samples: [f32; FRAME_SAMPLES],
+    at: Instant,
+}
+
+struct MultitrackState {
+    stop: Arc<AtomicBool>,
+    writer_thread: JoinHandle<MultitrackSummary>,
+}
+
+static MULTITRACK_STATE: Mutex<Option<MultitrackState>> = Mutex::new(None);
+static LOCAL_TAP: Mutex<Option<Producer<TapFrame>>> = Mutex::new(None);
+static REMOTE_TAP: Mutex<Option<Producer<RemoteFrame>>> = Mutex::new(None);
+static MULTITRACK_ACTIVE: AtomicBool = AtomicBool::new(false);
+static DROPPED_MULTITRACK_FRAMES: AtomicU64 = AtomicU64::new(0);
+/// Peers that agreed to be recorded in the current recording.
+static CONSENTED_PEERS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Offer a processed mic frame to the recording. Cheap no-op when not recording; never blocks.
+pub fn tee_local(pcm: &[f32]) {
+    if !MULTITRACK_ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    // A 20/40 ms capture frame ends now; spread its 10 ms slots back over that span.
+    let now = Instant::now();
+    let dropped = tee_slots(&MULTITRACK_ACTIVE, &LOCAL_TAP, pcm, |i, count, samples| TapFrame {
+        samples,
+        at: now - Duration::from_millis(10 * (count - 1 - i) as u64),
+    });
+    if dropped > 0 {
+        DROPPED_MULTITRACK_FRAMES.fetch_add(dropped, Ordering::Relaxed);
+    }
+}
+
+/// Offer a peer's 10 ms playout frame to the recording. Cheap no-op when not recording.
+pub fn tee_remote(peer_id: &str, samples: &[f32; FRAME_SAMPLES]) {
+    if !MULTITRACK_ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let Ok(mut tap) = REMOTE_TAP.try_lock() else {
+        return;
+    };
+    if let Some(producer) = tap.as_mut() {
+        let frame = RemoteFrame {
+            peer_id: peer_id.to_string(),
+            samples: *samples,
+            at: Instant::now(),
+        };
+        if producer.push(frame).is_err() {
+            DROPPED_MULTITRACK_FRAMES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Record (or stop recording) a peer's consent. Only matters with `require_consent`;
+/// their track carries silence until consent is granted.
+pub fn set_peer_consent(peer_id: &str, granted: bool) -> Result<(), String> {
+    let mut consent = CONSENTED_PEERS
+        .lock()
+        .map_err(|_| "Failed to lock recording consent".to_string())?;
+    let peers = consent.get_or_insert_with(HashSet::new);
+    if granted {
+        peers.insert(peer_id.to_string());
+    } else {
+        peers.remove(peer_id);
+    }
+    Ok(())
+}
+
+fn has_consent(peer_id: &str) -> bool {
+    CONSENTED_PEERS
+        .lock()
+        .map(|c| c.as_ref().is_some_and(|peers| peers.contains(peer_id)))
+        .unwrap_or(false)
+}
+
+/// Where a frame goes relative to a track's end.
+#[derive(Debug, PartialEq)]
+enum Placement {
+    Write,
+    /// Fill this many samples of silence first.
+    Pad(u64),
+    /// Track is ahead of the timeline: drop the frame.
+    Trim,
+}
+
+fn place(written: u64, position: u64) -> Placement {
+    if position > written + ALIGN_SLACK_SAMPLES {
+        Placement::Pad(position - written)
+    } else if written > position + ALIGN_SLACK_SAMPLES {
+        Placement::Trim
+    } else {
+        Placement::Write
+    }
+}
+
+struct Track {
+    peer_id: Option<String>,
+    path: PathBuf,
+    writer: RecordingWriter,
+    written: u64,
+    padded: u64,
+    trimmed: u64,
+    consented: bool,
+}
+
+impl Track {
+    fn open(dir: &Path, index: usize, peer_id: Option<String>, format: RecordingFormat, consented: bool) -> Result<Self, String> {
+        let name = match &peer_id {
+            Some(id) => {
+                let clean: String = id
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                    .take(32)
+                    .collect();
+                format!("{:02}-peer-{}", index, clean)
+            }
+            None => format!("{:02}-local", index),
+        };
+        let ext = match format {
+            RecordingFormat::Wav => "wav",
+            RecordingFormat::Flac => "flac",
+        };
+        let path = dir.join(format!("{}.{}", name, ext));
+        let writer = RecordingWriter::create(&path, format)?;
+        Ok(Self { peer_id, path, writer, written: 0, padded: 0, trimmed: 0, consented })
+    }
+
+    fn pad(&mut self, mut samples: u64) -> Result<(), String> {
+        while samples > 0 {
+            let n = samples.min(FRAME_SAMPLES as u64) as usize;
+            self.writer.write(&SILENCE[..n])?;
+            self.written += n as u64;
+            self.padded += n as u64;
+            samples -= n as u64;
+        }
+        Ok(())
+    }
+
+    /// Write a frame that arrived `position` samples into the recording.
+    fn push(&mut self, samples: &[f32; FRAME_SAMPLES], position: u64) -> Result<(), String> {
+        match place(self.written, position) {
+            Placement::Trim => {
+                self.trimmed += FRAME_SAMPLES as u64;
+                return Ok(());
+            }
+            Placement::Pad(n) => self.pad(n)?,
+            Placement::Write => {}
+        }
+        self.writer.write(if self.consented { samples } else { &SILENCE })?;
+        self.written += FRAME_SAMPLES as u64;
+        Ok(())
+    }
+}
+
+/// Start a multitrack recording into `directory` (created if needed). `on_event` runs on the
+/// calling thread for `Started` and on the writer thread afterwards.
+pub fn start_multitrack_recording<F>(directory: PathBuf, options: MultitrackOptions, on_event: F) -> Result<(), String>
+where
+    F: Fn(MultitrackEvent) + Send + 'static,
+{
+    if options.layout == MultitrackLayout::Combined && options.format != RecordingFormat::Wav {
+        return Err("Combined multitrack recordings are WAV only".to_string());
+    }
+    let mut state = MULTITRACK_STATE
+        .lock()
+        .map_err(|_| "Failed to lock multitrack recording state".to_string())?;
+    if let Some(existing) = state.as_ref() {
+        if !existing.writer_thread.is_finished() {
+            return Err("A multitrack recording is already in progress".to_string());
+        }
+    }
+    if let Some(old) = state.take() {
+        let _ = old.writer_thread.join();
+    }
+
+    std::fs::create_dir_all(&directory).map_err(|e| format!("Failed to create recording directory: {}", e))?;
+    let mut tracks = Vec::new();
+    if options.include_local {
+        tracks.push(Track::open(&directory, 0, None, options.format, true)?);
+    }
+
+    let (local_producer, local_consumer) = RingBuffer::<TapFrame>::new(LOCAL_RING_FRAMES);
+    let (remote_producer, remote_consumer) = RingBuffer::<RemoteFrame>::new(REMOTE_RING_FRAMES);
+    {
+        let mut local = LOCAL_TAP.lock().map_err(|_| "Failed to lock multitrack tap".to_string())?;
+        let mut remote = REMOTE_TAP.lock().map_err(|_| "Failed to lock multitrack tap".to_string())?;
+        *local = Some(local_producer);
+        *remote = Some(remote_producer);
+    }
+    if let Ok(mut consent) = CONSENTED_PEERS.lock() {
+        consent.take();
+    }
+    DROPPED_MULTITRACK_FRAMES.store(0, Ordering::Relaxed);
+
+    on_event(MultitrackEvent::Started {
+        directory: directory.to_string_lossy().to_string(),
+        require_consent: options.require_consent,
+    });
+    let started = Instant::now();
+    MULTITRACK_ACTIVE.store(true, Ordering::Relaxed);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    let writer_thread = std::thread::spawn(move || {
+        let mut writer = MultitrackWriter { dir: directory, options, started, tracks };
+        let mut stop_reason = writer
+            .run(local_consumer, remote_consumer, &stop_flag, &on_event)
+            .err()
+            .unwrap_or_else(|| "stopped".to_string());
+        MULTITRACK_ACTIVE.store(false, Ordering::Relaxed);
+        if let Ok(mut tap) = LOCAL_TAP.lock() {
+            tap.take();
+        }
+        if let Ok(mut tap) = REMOTE_TAP.lock() {
+            tap.take();
+        }
+        let summary = writer.finish(&mut stop_reason);
+        on_event(MultitrackEvent::Stopped(summary.clone()));
+        summary
+    });
+
+    *state = Some(MultitrackState { stop, writer_thread });
+    Ok(())
+}
+
+struct MultitrackWriter {
+    dir: PathBuf,
+    options: MultitrackOptions,
+    started: Instant,
+    tracks: Vec<Track>,
+}
+
+impl MultitrackWriter {
+    fn position(&self, at: Instant) -> u64 {
+        (at.saturating_duration_since(self.started).as_secs_f64() * PIPELINE_SAMPLE_RATE as f64) as u64
+    }
+
+    fn run(
+        &mut self,
+        mut local: Consumer<TapFrame>,
+        mut remote: Consumer<RemoteFrame>,
+        stop: &AtomicBool,
+        on_event: &dyn Fn(MultitrackEvent),
+    ) -> Result<(), String> {
+        loop {
+            let stopping = stop.load(Ordering::Relaxed);
+            if self.options.require_consent {
+                for track in self.tracks.iter_mut() {
+                    if let Some(peer_id) = &track.peer_id {
+                        track.consented = has_consent(peer_id);
+                    }
+                }
+            }
+            while let Ok(frame) = local.pop() {
+                let position = self.position(frame.at);
+                if let Some(track) = self.tracks.iter_mut().find(|t| t.peer_id.is_none()) {
+                    track.push(&frame.samples, position)?;
+                }
+            }
+            while let Ok(frame) = remote.pop() {
+                let position = self.position(frame.at);
+                let index = match self.tracks.iter().position(|t| t.peer_id.as_deref() == Some(&frame.peer_id)) {
+                    Some(index) => index,
+                    None if self.tracks.len() >= MAX_TRACKS => {
+                        DROPPED_MULTITRACK_FRAMES.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    None => {
+                        let consented = !self.options.require_consent || has_consent(&frame.peer_id);
+                        let track = Track::open(
+                            &self.dir,
+                            self.tracks.len(),
+                            Some(frame.peer_id.clone()),
+                            self.options.format,
+                            consented,
+                        )?;
+                        self.tracks.push(track);
+                        on_event(MultitrackEvent::TrackAdded { peer_id: frame.peer_id.clone(), consented });
+                        self.tracks.len() - 1
+                    }
+                };
+                self.tracks[index].push(&frame.samples, position)?;
+            }
+            if stopping {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Pad every track to the same length, close the files and merge them if asked.
+    fn finish(self, stop_reason: &mut String) -> MultitrackSummary {
+        let longest = self.tracks.iter().map(|t| t.written).max().unwrap_or(0);
+        let mut tracks = Vec::with_capacity(self.tracks.len());
+        let mut paths = Vec::with_capacity(self.tracks.len());
+        for mut track in self.tracks {
+            let result = track.pad(longest - track.written).and_then(|()| track.writer.finish());
+            if let Err(e) = result {
+                *stop_reason = e;
+            }
+            tracks.push(TrackSummary {
+                peer_id: track.peer_id,
+                path: Some(track.path.to_string_lossy().to_string()),
+                channel: None,
+                duration_secs: (track.written - track.padded) as f64 / PIPELINE_SAMPLE_RATE as f64,
+                padded_secs: track.padded as f64 / PIPELINE_SAMPLE_RATE as f64,
+                trimmed_secs: track.trimmed as f64 / PIPELINE_SAMPLE_RATE as f64,
+                consented: track.consented,
+            });
+            paths.push(track.path);
+        }
+
+        let mut combined_path = None;
+        if self.options.layout == MultitrackLayout::Combined && !paths.is_empty() {
+            let path = self.dir.join("multitrack.wav");
+            match merge_tracks(&paths, &path, longest) {
+                Ok(()) => {
+                    for (channel, (track, file)) in tracks.iter_mut().zip(&paths).enumerate() {
+                        let _ = std::fs::remove_file(file);
+                        track.path = None;
+                        track.channel = Some(channel as u16);
+                    }
+                    combined_path = Some(path.to_string_lossy().to_string());
+                }
+                Err(e) => *stop_reason = format!("{} (per-track files kept)", e),
+            }
+        }
+
+        MultitrackSummary {
+            directory: self.dir.to_string_lossy().to_string(),
+            combined_path,
+            duration_secs: longest as f64 / PIPELINE_SAMPLE_RATE as f64,
+            tracks,
+            dropped_frames: DROPPED_MULTITRACK_FRAMES.load(Ordering::Relaxed),
+            stop_reason: stop_reason.clone(),
+        }
+    }
+}
+
+/// Interleave equal-length mono 16-bit WAVs into one WAV with a channel per file.
+fn merge_tracks(tracks: &[PathBuf], path: &Path, samples: u64) -> Result<(), String> {
+    let channels = tracks.len() as u64;
+    if 44 + samples * channels * 2 > WAV_MAX_BYTES {
+        return Err("Combined file would exceed the WAV size limit".to_string());
+    }
+    let mut inputs = Vec::with_capacity(tracks.len());
+    for track in tracks {
+        let reader = hound::WavReader::open(track).map_err(|e| format!("Failed to reopen track: {}", e))?;
+        inputs.push(reader.into_samples::<i16>());
+    }
+    let spec = hound::WavSpec {
+        channels: tracks.len() as u16,
+        sample_rate: PIPELINE_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer =
+        hound::WavWriter::create(path, spec).map_err(|e| format!("Failed to create WAV file: {}", e))?;
+    for _ in 0..samples {
+        for input in inputs.iter_mut() {
+            let sample = input
+                .next()
+                .transpose()
+                .map_err(|e| format!("Failed to read track: {}", e))?
+                .unwrap_or(0);
+            writer
+                .write_sample(sample)
+                .map_err(|e| format!("Failed to write WAV samples: {}", e))?;
+        }
+    }
+    writer.finalize().map_err(|e| format!("Failed to finalize WAV file: {}", e))
+}
+
+/// Stop the multitrack recording, finish (and merge) the files and return the summary.
+pub fn stop_multitrack_recording() -> Result<MultitrackSummary, String> {
+    let state = MULTITRACK_STATE
+        .lock()
+        .map_err(|_| "Failed to lock multitrack recording state".to_string())?
+        .take()
+        .ok_or_else(|| "No multitrack recording in progress".to_string())?;
+    state.stop.store(true, Ordering::Relaxed);
+    state
+        .writer_thread
+        .join()
+        .map_err(|_| "Multitrack writer thread panicked".to_string())
+}
+
+pub fn is_multitrack_recording() -> bool {
+    MULTITRACK_ACTIVE.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_are_padded_or_trimmed_only_beyond_the_slack() {
+        assert_eq!(place(0, 0), Placement::Write);
+        assert_eq!(place(48_000, 48_000 + ALIGN_SLACK_SAMPLES), Placement::Write);
+        // Peer joining 2 s in gets 2 s of leading silence.
+        assert_eq!(place(0, 96_000), Placement::Pad(96_000));
+        assert_eq!(place(48_000 + ALIGN_SLACK_SAMPLES + 1, 48_000), Placement::Trim);
+    }
+}
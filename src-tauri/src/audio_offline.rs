@@ -0,0 +1,132 @@
+//! Offline processing: run a WAV file through the DSP chain and write the result.
+//!
+//! Lets users preview what their denoise/gate settings do to a recording, and gives DSP
+//! changes something deterministic to be checked against. The file is read whole, mixed to
+//! mono, resampled to 48 kHz and fed through a fresh `AudioDSP` in 10 ms frames, so the live
+//! pipeline's DSP state is never touched.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::audio_dsp::{AudioDSP, DspConfig};
+use crate::audio_recording::{RecordingFormat, RecordingWriter};
+use crate::audio_resample::{FrameResampler, FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessedFile {
+    pub path: String,
+    pub duration_secs: f64,
+    /// Sample rate of the input; the output is always 48 kHz mono.
+    pub input_sample_rate: u32,
+}
+
+/// Process `input` with `config` into a 48 kHz mono 16-bit WAV at `output`. The output is
+/// as long as the input; the chain's own latency (RNNoise, limiter lookahead) is not
+/// compensated. Echo cancellation is skipped: there is no far end offline.
+pub fn process_audio_file(input: &Path, output: &Path, config: &DspConfig) -> Result<ProcessedFile, String> {
+    let (mono, input_sample_rate) = read_wav_mono(input)?;
+    let samples = resample_to_pipeline(&mono, input_sample_rate)?;
+
+    let mut dsp = AudioDSP::new();
+    dsp.apply_config(&DspConfig { echo_cancellation_tail_ms: None, ..config.clone() })?;
+    dsp.set_frame_duration_ms(10);
+
+    let mut writer = RecordingWriter::create(&PathBuf::from(output), RecordingFormat::Wav)?;
+    let mut frame = [0.0f32; FRAME_SAMPLES];
+    let mut processed = Vec::with_capacity(FRAME_SAMPLES);
+    for chunk in samples.chunks(FRAME_SAMPLES) {
+        frame.fill(0.0);
+        frame[..chunk.len()].copy_from_slice(chunk);
+        dsp.process_frame(&frame, Instant::now(), &mut processed);
+        writer.write(&processed[..chunk.len()])?;
+    }
+    writer.finish()?;
+
+    Ok(ProcessedFile {
+        path: output.to_string_lossy().to_string(),
+        duration_secs: samples.len() as f64 / PIPELINE_SAMPLE_RATE as f64,
+        input_sample_rate,
+    })
+}
+
+/// Decode any PCM/float WAV, averaging channels. Returns samples in -1..1 and the rate.
+fn read_wav_mono(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to open WAV file: {}", e))?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read WAV samples: {}", e))?,
+        hound::SampleFormat::Int => {
+            let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / full_scale))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Failed to read WAV samples: {}", e))?
+        }
+    };
+    let channels = spec.channels.max(1) as usize;
+    let mono = interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((mono, spec.sample_rate))
+}
+
+fn resample_to_pipeline(samples: &[f32], sample_rate: u32) -> Result<Vec<f32>, String> {
+    if sample_rate == PIPELINE_SAMPLE_RATE {
+        return Ok(samples.to_vec());
+    }
+    let expected = (samples.len() as u64 * PIPELINE_SAMPLE_RATE as u64 / sample_rate as u64) as usize;
+    let mut resampler = FrameResampler::new(sample_rate)?;
+    let mut out = Vec::with_capacity(expected + FRAME_SAMPLES);
+    resampler.process(samples, |frame| out.extend_from_slice(frame));
+    // Flush what the resampler still holds with 100 ms of silence, then trim to length.
+    let flush = vec![0.0; sample_rate as usize / 10];
+    resampler.process(&flush, |frame| out.extend_from_slice(frame));
+    out.truncate(expected);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processes_a_stereo_44k_file_to_48k_mono() {
+        crate::audio_denoise::register_builtin_denoisers();
+        let dir = std::env::temp_dir().join(format!("cordia-offline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.wav");
+        let output = dir.join("out.wav");
+
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+        for i in 0..44100 {
+            let s = (i as f32 * 440.0 * std::f32::consts::TAU / 44100.0).sin() * 0.5;
+            writer.write_sample((s * 32767.0) as i16).unwrap();
+            writer.write_sample((s * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let config = AudioDSP::new().config();
+        let summary = process_audio_file(&input, &output, &config).unwrap();
+        assert_eq!(summary.input_sample_rate, 44100);
+        assert!((summary.duration_secs - 1.0).abs() < 0.01);
+
+        let reader = hound::WavReader::open(&output).unwrap();
+        assert_eq!(reader.spec().sample_rate, PIPELINE_SAMPLE_RATE);
+        assert_eq!(reader.spec().channels, 1);
+        assert_eq!(reader.len(), 48000);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
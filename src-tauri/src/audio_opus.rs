@@ -0,0 +1,240 @@
+//! Opus encoding of processed mic frames.
+//!
+//! Runs on the emitter thread, after DSP: each processed frame (10/20/40 ms, all valid
+//! Opus frame sizes at 48 kHz) becomes one packet. A 20 ms voice packet is ~80 bytes at
+//! 32 kbps versus 3.8 KB of f32 PCM, which is what makes this worth doing before IPC.
+//!
+//! Settings can change mid-call: `set_opus_settings` bumps a generation counter and the
+//! encoder re-applies them before its next packet. The bandwidth cap from app settings
+//! bounds the configured bitrate the same way; since native peers send these packets as-is,
+//! it bounds their voice traffic too. The encoder also measures what it actually produces.
+
+use audiopus::coder::Encoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI32, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Largest packet libopus produces for one frame (its documented recommendation).
+const MAX_PACKET_BYTES: usize = 4000;
+/// Audio covered by each measured-bitrate update.
+const MEASURE_SAMPLES: usize = 48_000 * 2;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct OpusSettings {
+    /// Target bitrate in bits/s (6000–510000).
+    #[serde(default = "default_bitrate")]
+    pub bitrate: i32,
+    /// Encoder complexity 0–10 (higher = better quality, more CPU).
+    #[serde(default = "default_complexity")]
+    pub complexity: u8,
+    /// In-band forward error correction: each packet carries a low-bitrate copy of the
+    /// previous one so a single lost packet can be reconstructed.
+    #[serde(default = "default_fec")]
+    pub fec: bool,
+    /// Expected packet loss (%). Opus only spends bits on FEC when this is non-zero.
+    #[serde(default = "default_expected_loss")]
+    pub expected_loss_pct: u8,
+}
+
+fn default_bitrate() -> i32 {
+    32000
+}
+
+fn default_complexity() -> u8 {
+    9
+}
+
+fn default_fec() -> bool {
+    true
+}
+
+fn default_expected_loss() -> u8 {
+    10
+}
+
+impl Default for OpusSettings {
+    fn default() -> Self {
+        Self {
+            bitrate: default_bitrate(),
+            complexity: default_complexity(),
+            fec: default_fec(),
+            expected_loss_pct: default_expected_loss(),
+        }
+    }
+}
+
+static OPUS_SETTINGS: Mutex<Option<OpusSettings>> = Mutex::new(None);
+/// Bumped on every settings change so running encoders know to re-apply them.
+static OPUS_SETTINGS_GENERATION: AtomicU64 = AtomicU64::new(0);
+/// Bandwidth cap in bits/s; 0 = none.
+static BITRATE_CAP: AtomicI32 = AtomicI32::new(0);
+/// Encoded bits/s over the last `MEASURE_SAMPLES`; 0 while nothing is being encoded.
+static MEASURED_BITRATE: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BitrateReport {
+    /// From the Opus settings.
+    pub configured_bps: i32,
+    pub cap_bps: Option<i32>,
+    /// What the encoder is set to: the configured bitrate, lowered to the cap.
+    pub effective_bps: i32,
+    /// Measured from encoded packets; `None` when no encoder is running.
+    pub actual_bps: Option<u32>,
+}
+
+/// Current settings (defaults until changed).
+pub fn opus_settings() -> OpusSettings {
+    OPUS_SETTINGS
+        .lock()
+        .ok()
+        .and_then(|s| *s)
+        .unwrap_or_default()
+}
+
+/// Validate and store new settings; running encoders pick them up on their next frame.
+pub fn set_opus_settings(settings: OpusSettings) -> Result<(), String> {
+    if !(6000..=510_000).contains(&settings.bitrate) {
+        return Err(format!("Opus bitrate out of range: {} (6000-510000)", settings.bitrate));
+    }
+    if settings.complexity > 10 {
+        return Err(format!("Opus complexity out of range: {} (0-10)", settings.complexity));
+    }
+    if settings.expected_loss_pct > 100 {
+        return Err(format!("Expected packet loss out of range: {}%", settings.expected_loss_pct));
+    }
+    let mut current = OPUS_SETTINGS
+        .lock()
+        .map_err(|_| "Failed to lock Opus settings".to_string())?;
+    *current = Some(settings);
+    OPUS_SETTINGS_GENERATION.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Cap the encoder bitrate (from `settings::BandwidthSettings`); `None` removes the cap.
+pub fn set_bitrate_cap(max_kbps: Option<u32>) {
+    let cap = max_kbps.map_or(0, |kbps| (kbps.clamp(6, 510) * 1000) as i32);
+    if BITRATE_CAP.swap(cap, Ordering::SeqCst) != cap {
+        OPUS_SETTINGS_GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn bitrate_cap() -> Option<i32> {
+    Some(BITRATE_CAP.load(Ordering::SeqCst)).filter(|&cap| cap > 0)
+}
+
+fn effective_bitrate(configured: i32, cap: Option<i32>) -> i32 {
+    cap.map_or(configured, |cap| configured.min(cap))
+}
+
+pub fn bitrate_report() -> BitrateReport {
+    let configured_bps = opus_settings().bitrate;
+    let cap_bps = bitrate_cap();
+    BitrateReport {
+        configured_bps,
+        cap_bps,
+        effective_bps: effective_bitrate(configured_bps, cap_bps),
+        actual_bps: Some(MEASURED_BITRATE.load(Ordering::SeqCst)).filter(|&bps| bps > 0),
+    }
+}
+
+/// One encoded frame, as emitted to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpusPacket {
+    /// Sequence number of the processed frame (gaps = frames dropped before encoding).
+    pub seq: u64,
+    /// Capture time in µs on the pipeline clock.
+    pub capture_ts: u64,
+    /// Samples per channel in this packet (480/960/1920 at 48 kHz).
+    pub samples: usize,
+    /// Base64 Opus packet.
+    pub data: String,
+}
+
+/// Mono 48 kHz voice encoder that follows the global settings.
+pub struct OpusFrameEncoder {
+    encoder: Encoder,
+    generation: u64,
+    out: Vec<u8>,
+    measured_bytes: usize,
+    measured_samples: usize,
+}
+
+impl OpusFrameEncoder {
+    pub fn new() -> Result<Self, String> {
+        let encoder = Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Voip)
+            .map_err(|e| format!("Failed to create Opus encoder: {}", e))?;
+        let mut this = Self {
+            encoder,
+            generation: u64::MAX,
+            out: vec![0u8; MAX_PACKET_BYTES],
+            measured_bytes: 0,
+            measured_samples: 0,
+        };
+        this.apply_settings_if_changed()?;
+        Ok(this)
+    }
+
+    fn apply_settings_if_changed(&mut self) -> Result<(), String> {
+        let generation = OPUS_SETTINGS_GENERATION.load(Ordering::SeqCst);
+        if generation == self.generation {
+            return Ok(());
+        }
+        self.generation = generation;
+        let settings = opus_settings();
+        self.encoder
+            .set_bitrate(Bitrate::BitsPerSecond(effective_bitrate(settings.bitrate, bitrate_cap())))
+            .map_err(|e| format!("Failed to set Opus bitrate: {}", e))?;
+        self.encoder
+            .set_complexity(settings.complexity)
+            .map_err(|e| format!("Failed to set Opus complexity: {}", e))?;
+        self.encoder
+            .set_inband_fec(settings.fec)
+            .map_err(|e| format!("Failed to set Opus FEC: {}", e))?;
+        let loss = if settings.fec { settings.expected_loss_pct } else { 0 };
+        self.encoder
+            .set_packet_loss_perc(loss)
+            .map_err(|e| format!("Failed to set Opus packet loss: {}", e))?;
+        Ok(())
+    }
+
+    /// Encode one frame. `pcm` must be 2.5/5/10/20/40/60 ms of 48 kHz mono.
+    pub fn encode(&mut self, pcm: &[f32]) -> Result<&[u8], String> {
+        self.apply_settings_if_changed()?;
+        let len = self
+            .encoder
+            .encode_float(pcm, &mut self.out)
+            .map_err(|e| format!("Failed to encode Opus frame: {}", e))?;
+        self.measure(len, pcm.len());
+        Ok(&self.out[..len])
+    }
+
+    fn measure(&mut self, bytes: usize, samples: usize) {
+        self.measured_bytes += bytes;
+        self.measured_samples += samples;
+        if self.measured_samples >= MEASURE_SAMPLES {
+            let bps = self.measured_bytes as u64 * 8 * 48_000 / self.measured_samples as u64;
+            MEASURED_BITRATE.store(bps as u32, Ordering::SeqCst);
+            self.measured_bytes = 0;
+            self.measured_samples = 0;
+        }
+    }
+}
+
+impl Drop for OpusFrameEncoder {
+    fn drop(&mut self) {
+        MEASURED_BITRATE.store(0, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cap_only_lowers_the_bitrate() {
+        assert_eq!(effective_bitrate(32000, None), 32000);
+        assert_eq!(effective_bitrate(64000, Some(24000)), 24000);
+        assert_eq!(effective_bitrate(16000, Some(24000)), 16000);
+    }
+}
@@ -0,0 +1,953 @@
+//! Native audio playback with a per-peer mixer.
+//!
+//! Same philosophy as capture: **never block the audio callback.** Each remote peer
+//! gets its own lock-free ring of 10 ms mono frames; the output callback pulls from
+//! every ring, sums, and writes to the device. If a peer's ring is full the frame is
+//! dropped; if it's empty that peer contributes silence.
+//!
+//! Per-user volume, mute and pan live in a table of atomics shared with the callback, so
+//! local adjustments apply on the next buffer without touching the peer's stream.
+//! With spatial audio on, each user's position (azimuth/distance) replaces plain panning
+//! and is rendered binaurally by `audio_spatial`. Otherwise mono voices are upmixed into
+//! a stereo image of adjustable width; stereo sources (music bots, soundboard) get a second
+//! ring for the right channel and play as-is.
+//!
+//! Sidetone (hearing your own mic) is one more source in the same mixer, fed from the
+//! capture processing thread. It only ever flows capture → output, never back into
+//! the transmitted stream.
+
+use crate::audio_capture::{follows_system_default, resolve_device, AudioDeviceKind};
+use crate::audio_resample::{OutputResampler, FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+use crate::audio_tap::tee_slots;
+use crate::audio_spatial::{spread_azimuths, SpatialParams, Spatializer, Widener};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{Device, SampleFormat, Stream, StreamConfig};
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Frames dropped because a peer's playback ring was full (sender ahead of the device).
+static DROPPED_PLAYBACK: AtomicU64 = AtomicU64::new(0);
+/// Master output volume, stored as f32 bits so the callback can read it lock-free.
+static MASTER_VOLUME: AtomicU32 = AtomicU32::new(0x3f80_0000); // 1.0
+/// Silences all remote audio without touching the master volume.
+static DEAFENED: AtomicBool = AtomicBool::new(false);
+/// Sidetone volume (f32 bits), independent of the master volume.
+static SIDETONE_VOLUME: AtomicU32 = AtomicU32::new(0x3f00_0000); // 0.5
+/// Fast check for the capture thread so it skips the tap lock when sidetone is off.
+static SIDETONE_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Producer side of the sidetone ring; the consumer lives in whichever mixer plays it.
+static SIDETONE_TAP: Mutex<Option<Producer<[f32; FRAME_SAMPLES]>>> = Mutex::new(None);
+/// Render users at their virtual positions instead of plain pan.
+static SPATIAL_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Upmix width (f32 bits) for mono voices that don't set their own.
+static VOICE_WIDTH: AtomicU32 = AtomicU32::new(0); // 0.0
+/// Join order for new entries in the user mix table (first time a peer is seen).
+static NEXT_JOIN_ORDER: AtomicU64 = AtomicU64::new(0);
+
+/// Per-peer ring capacity: ~100 ms of buffered remote audio.
+const PEER_RING_CAP: usize = 10;
+/// Control ring capacity (peer add messages from command thread → callback).
+const CONTROL_RING_CAP: usize = 64;
+/// Upper bound on simultaneously mixed peers; sources vec is preallocated to this.
+const MAX_PEERS: usize = 64;
+/// Retired-source ring capacity (callback → stream thread), enough for every source at once.
+const RETIRE_RING_CAP: usize = MAX_PEERS + 1;
+/// How often the stream thread frees sources the callback retired.
+const RETIRE_POLL: Duration = Duration::from_millis(100);
+/// Sidetone ring capacity: small so monitoring latency stays low (overflow drops), but
+/// enough to take a whole 40 ms capture frame at once.
+const SIDETONE_RING_CAP: usize = 6;
+
+/// Local mix settings for one remote user, read lock-free by the output callback.
+/// Kept across playback restarts and reconnects (keyed by peer id).
+struct PeerControls {
+    volume: AtomicU32,
+    muted: AtomicBool,
+    /// -1.0 = hard left, 0.0 = centre, 1.0 = hard right (f32 bits).
+    pan: AtomicU32,
+    /// Spatial position: degrees (0 ahead, +90 right) and metres (f32 bits).
+    azimuth: AtomicU32,
+    distance: AtomicU32,
+    /// Upmix width for a mono voice (0-1, f32 bits); negative = follow the mixer default.
+    width: AtomicU32,
+    /// Source delivers stereo: played as-is, never upmixed or spatialized.
+    stereo: AtomicBool,
+    join_order: u64,
+}
+
+impl PeerControls {
+    fn new() -> Self {
+        Self {
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            muted: AtomicBool::new(false),
+            pan: AtomicU32::new(0.0f32.to_bits()),
+            azimuth: AtomicU32::new(0.0f32.to_bits()),
+            distance: AtomicU32::new(1.0f32.to_bits()),
+            width: AtomicU32::new((-1.0f32).to_bits()),
+            stereo: AtomicBool::new(false),
+            join_order: NEXT_JOIN_ORDER.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// (left, right) gains. Balance law: centre is unity on both sides, panning
+    /// attenuates the opposite side only. Pan is ignored when spatialized.
+    fn gains(&self, spatial: bool) -> (f32, f32) {
+        if self.muted.load(Ordering::Relaxed) {
+            return (0.0, 0.0);
+        }
+        let volume = f32::from_bits(self.volume.load(Ordering::Relaxed));
+        let pan = if spatial { 0.0 } else { f32::from_bits(self.pan.load(Ordering::Relaxed)) };
+        (volume * (1.0 - pan).min(1.0), volume * (1.0 + pan).min(1.0))
+    }
+
+    fn width(&self) -> Option<f32> {
+        let width = f32::from_bits(self.width.load(Ordering::Relaxed));
+        (width >= 0.0).then_some(width)
+    }
+
+    fn spatial_params(&self) -> SpatialParams {
+        SpatialParams::new(
+            f32::from_bits(self.azimuth.load(Ordering::Relaxed)),
+            f32::from_bits(self.distance.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+/// Current mix settings for a user, as reported to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserMixSettings {
+    pub volume: f32,
+    pub muted: bool,
+    pub pan: f32,
+    pub azimuth: f32,
+    pub distance: f32,
+    /// Own upmix width; None = the mixer's voice width.
+    pub width: Option<f32>,
+    pub stereo: bool,
+}
+
+/// Output mixer settings shared by all users.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixerConfig {
+    /// Stereo width mono voices are upmixed to (0 = centred mono, 1 = widest).
+    pub voice_width: f32,
+}
+
+impl Default for MixerConfig {
+    fn default() -> Self {
+        Self { voice_width: 0.0 }
+    }
+}
+
+static PEER_CONTROLS: Mutex<Option<HashMap<String, Arc<PeerControls>>>> = Mutex::new(None);
+
+/// Controls for a peer, created with defaults on first use.
+fn peer_controls(peer_id: &str) -> Result<Arc<PeerControls>, String> {
+    let mut table = PEER_CONTROLS
+        .lock()
+        .map_err(|_| "Failed to lock user mix settings".to_string())?;
+    Ok(table
+        .get_or_insert_with(HashMap::new)
+        .entry(peer_id.to_string())
+        .or_insert_with(|| Arc::new(PeerControls::new()))
+        .clone())
+}
+
+/// Messages from the command side to the output callback. Sources are built on the command
+/// side so the callback only moves pointers, never allocates.
+enum MixerCommand {
+    AddPeer(Box<PeerSource>),
+    /// Replace the sidetone source (the previous one is retired).
+    SetSidetone(Box<PeerSource>),
+}
+
+/// A source leaving the mixer, sent back to the stream thread to be freed off the callback.
+struct Retired {
+    source: Box<PeerSource>,
+    /// Arrived while `MAX_PEERS` sources were already mixing.
+    rejected: bool,
+}
+
+/// One remote peer (or the sidetone) as seen by the output callback.
+struct PeerSource {
+    consumer: Consumer<[f32; FRAME_SAMPLES]>,
+    frame: [f32; FRAME_SAMPLES],
+    /// Right channel of a stereo source, pushed before the matching left frame.
+    right: Option<Consumer<[f32; FRAME_SAMPLES]>>,
+    right_frame: [f32; FRAME_SAMPLES],
+    pos: usize,
+    /// None for the sidetone (always centred, own volume).
+    controls: Option<Arc<PeerControls>>,
+    /// (left, right) gains, refreshed from `controls` once per callback.
+    gains: (f32, f32),
+    /// Binaural position when spatial audio is on, refreshed with `gains`.
+    spatial: Option<SpatialParams>,
+    spatializer: Spatializer,
+    /// Upmix width for a mono source, refreshed with `gains`.
+    width: f32,
+    widener: Widener,
+}
+
+impl PeerSource {
+    fn new(
+        consumer: Consumer<[f32; FRAME_SAMPLES]>,
+        right: Option<Consumer<[f32; FRAME_SAMPLES]>>,
+        controls: Option<Arc<PeerControls>>,
+    ) -> Self {
+        Self {
+            consumer,
+            frame: [0.0; FRAME_SAMPLES],
+            right,
+            right_frame: [0.0; FRAME_SAMPLES],
+            pos: FRAME_SAMPLES,
+            controls,
+            gains: (1.0, 1.0),
+            spatial: None,
+            spatializer: Spatializer::new(),
+            width: 0.0,
+            widener: Widener::new(),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.consumer.is_abandoned() && self.consumer.is_empty()
+    }
+
+    /// Next sample for this peer (left/right are equal for mono); silence when the ring is
+    /// starved.
+    fn next_sample(&mut self) -> (f32, f32) {
+        if self.pos >= FRAME_SAMPLES {
+            match self.consumer.pop() {
+                Ok(frame) => {
+                    self.frame = frame;
+                    if let Some(right) = self.right.as_mut() {
+                        self.right_frame = right.pop().unwrap_or(frame);
+                    }
+                    self.pos = 0;
+                }
+                Err(_) => return (0.0, 0.0),
+            }
+        }
+        let left = self.frame[self.pos];
+        let right = if self.right.is_some() { self.right_frame[self.pos] } else { left };
+        self.pos += 1;
+        (left, right)
+    }
+}
+
+/// Mixer state owned by the output callback. Only touched on the audio thread.
+struct OutputMixer {
+    sources: Vec<Box<PeerSource>>,
+    sidetone: Option<Box<PeerSource>>,
+    control_rx: Consumer<MixerCommand>,
+    retired_tx: Producer<Retired>,
+    /// Echo canceller reference; only fed when the device runs at the mixer's 48 kHz.
+    feeds_reference: bool,
+    /// Last sample's peer mix after master volume, without sidetone.
+    far_end: f32,
+    reference: [f32; FRAME_SAMPLES],
+    reference_len: usize,
+    reference_start: Instant,
+}
+
+impl OutputMixer {
+    fn new(control_rx: Consumer<MixerCommand>, retired_tx: Producer<Retired>, sample_rate: u32) -> Self {
+        Self {
+            sources: Vec::with_capacity(MAX_PEERS),
+            sidetone: None,
+            control_rx,
+            retired_tx,
+            feeds_reference: sample_rate == PIPELINE_SAMPLE_RATE,
+            far_end: 0.0,
+            reference: [0.0; FRAME_SAMPLES],
+            reference_len: 0,
+            reference_start: Instant::now(),
+        }
+    }
+
+    /// Apply pending control messages and retire peers whose producer was dropped. Anything
+    /// leaving the mixer goes out on the retire ring; work that would overflow it waits for
+    /// the next callback.
+    fn drain_commands(&mut self) {
+        while !self.retired_tx.is_full() {
+            let Ok(cmd) = self.control_rx.pop() else {
+                break;
+            };
+            match cmd {
+                MixerCommand::AddPeer(source) => {
+                    if self.sources.len() < MAX_PEERS {
+                        self.sources.push(source);
+                    } else {
+                        let _ = self.retired_tx.push(Retired { source, rejected: true });
+                    }
+                }
+                MixerCommand::SetSidetone(source) => {
+                    if let Some(old) = self.sidetone.replace(source) {
+                        let _ = self.retired_tx.push(Retired { source: old, rejected: false });
+                    }
+                }
+            }
+        }
+        let mut i = 0;
+        while i < self.sources.len() && !self.retired_tx.is_full() {
+            if self.sources[i].is_finished() {
+                let source = self.sources.swap_remove(i);
+                let _ = self.retired_tx.push(Retired { source, rejected: false });
+            } else {
+                i += 1;
+            }
+        }
+        if !self.retired_tx.is_full() {
+            if let Some(source) = self.sidetone.take_if(|s| s.is_finished()) {
+                let _ = self.retired_tx.push(Retired { source, rejected: false });
+            }
+        }
+        let spatial = SPATIAL_ENABLED.load(Ordering::Relaxed);
+        let voice_width = f32::from_bits(VOICE_WIDTH.load(Ordering::Relaxed));
+        for source in &mut self.sources {
+            if let Some(controls) = &source.controls {
+                // Stereo sources keep their own image; pan acts as balance on them.
+                let spatial = spatial && source.right.is_none();
+                source.gains = controls.gains(spatial);
+                source.spatial = spatial.then(|| controls.spatial_params());
+                source.width = controls.width().unwrap_or(voice_width);
+            }
+        }
+    }
+
+    /// Mix one output sample (left, right) across all peers, plus sidetone at its own volume.
+    fn next_sample(&mut self) -> (f32, f32) {
+        let (mut left, mut right) = (0.0f32, 0.0f32);
+        for source in &mut self.sources {
+            let (s, s_right) = source.next_sample();
+            let (l, r) = if source.right.is_some() {
+                (s, s_right)
+            } else {
+                match &source.spatial {
+                    Some(params) => source.spatializer.process(s, params),
+                    None => source.widener.process(s, source.width),
+                }
+            };
+            left += l * source.gains.0;
+            right += r * source.gains.1;
+        }
+        let master = if DEAFENED.load(Ordering::Relaxed) {
+            0.0
+        } else {
+            f32::from_bits(MASTER_VOLUME.load(Ordering::Relaxed))
+        };
+        left *= master;
+        right *= master;
+        self.far_end = ((left + right) * 0.5).clamp(-1.0, 1.0);
+        if let Some(sidetone) = self.sidetone.as_mut() {
+            let s = sidetone.next_sample().0 * f32::from_bits(SIDETONE_VOLUME.load(Ordering::Relaxed));
+            left += s;
+            right += s;
+        }
+        (left.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0))
+    }
+
+    /// Collect the last mixed sample for the echo canceller. `index` is its position in the
+    /// callback buffer, whose first sample reaches the speaker at `played_at`.
+    fn record_reference(&mut self, index: usize, played_at: Instant) {
+        if self.reference_len == 0 {
+            self.reference_start = played_at + Duration::from_secs_f64(index as f64 / PIPELINE_SAMPLE_RATE as f64);
+        }
+        self.reference[self.reference_len] = self.far_end;
+        self.reference_len += 1;
+        if self.reference_len == FRAME_SAMPLES {
+            self.reference_len = 0;
+            crate::audio_aec::feed_reference(crate::audio_aec::ReferenceFrame {
+                samples: self.reference,
+                played_at: self.reference_start,
+            });
+        }
+    }
+}
+
+/// Command-side handle for one peer: producer plus leftover samples that didn't fill a frame.
+struct PeerInput {
+    producer: Producer<[f32; FRAME_SAMPLES]>,
+    /// Right-channel ring of a stereo source; `pending` is interleaved then.
+    right: Option<Producer<[f32; FRAME_SAMPLES]>>,
+    pending: Vec<f32>,
+}
+
+/// Global playback state. The cpal Stream is !Send, so it lives on its own thread;
+/// dropping `stop_tx` tells that thread to drop the stream and exit.
+struct AudioOutputState {
+    peers: HashMap<String, PeerInput>,
+    stream: OutputStreamHandle,
+    /// As passed to `start_playback`; None/"default"/"communications" follow the OS default.
+    device_id: Option<String>,
+}
+
+/// A running output stream: mixer control queue plus the thread that owns the cpal Stream.
+struct OutputStreamHandle {
+    control_tx: Producer<MixerCommand>,
+    stop_tx: mpsc::Sender<()>,
+    stream_thread: Option<thread::JoinHandle<()>>,
+    /// cpal name of the device actually opened.
+    device_name: String,
+}
+
+impl OutputStreamHandle {
+    fn stop(mut self) {
+        let _ = self.stop_tx.send(());
+        if let Some(thread) = self.stream_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Sidetone routing. `dedicated` is set when monitoring goes to a different device than
+/// playback; otherwise the sidetone rides in the playback mixer.
+struct SidetoneState {
+    dedicated: Option<OutputStreamHandle>,
+}
+
+static AUDIO_OUTPUT_STATE: Mutex<Option<AudioOutputState>> = Mutex::new(None);
+static SIDETONE_STATE: Mutex<Option<SidetoneState>> = Mutex::new(None);
+
+/// Pick an output config: 48 kHz in the device's default format if supported, else the default.
+/// The mixer runs at 48 kHz; other rates get the mix through an `OutputResampler`.
+fn choose_output_config(device: &Device) -> Result<(StreamConfig, SampleFormat), String> {
+    let default = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get device config: {}", e))?;
+    let target = cpal::SampleRate(PIPELINE_SAMPLE_RATE);
+    let supports_48k = device
+        .supported_output_configs()
+        .map(|mut configs| {
+            configs.any(|c| {
+                c.sample_format() == default.sample_format()
+                    && c.channels() == default.channels()
+                    && c.min_sample_rate() <= target
+                    && c.max_sample_rate() >= target
+            })
+        })
+        .unwrap_or(false);
+    let sample_rate = if supports_48k { target } else { default.sample_rate() };
+    Ok((
+        StreamConfig {
+            channels: default.channels(),
+            sample_rate,
+            buffer_size: cpal::BufferSize::Default,
+        },
+        default.sample_format(),
+    ))
+}
+
+/// Start playback on the specified output device. None/"default"/"communications" follow
+/// the OS default (see `follow_default_output`). Replaces any running playback.
+pub fn start_playback(device_id: Option<String>) -> Result<(), String> {
+    stop_playback();
+
+    DROPPED_PLAYBACK.store(0, Ordering::Relaxed);
+    let mut stream = open_output_stream(device_id.as_deref())?;
+
+    // Sidetone without a dedicated device follows the playback mixer across restarts.
+    if let Ok(sidetone) = SIDETONE_STATE.lock() {
+        if sidetone.as_ref().is_some_and(|s| s.dedicated.is_none()) {
+            attach_sidetone(&mut stream.control_tx)?;
+        }
+    }
+
+    let mut state = AUDIO_OUTPUT_STATE
+        .lock()
+        .map_err(|_| "Failed to lock audio output state".to_string())?;
+    *state = Some(AudioOutputState {
+        peers: HashMap::new(),
+        stream,
+        device_id,
+    });
+    Ok(())
+}
+
+/// If playback follows the OS default and the default has moved, restart on the new one.
+/// Returns true when playback switched devices. Called when the device watcher sees a
+/// default change; peers re-register on their next frame.
+pub fn follow_default_output() -> Result<bool, String> {
+    let device_id = {
+        let guard = AUDIO_OUTPUT_STATE
+            .lock()
+            .map_err(|_| "Failed to lock audio output state".to_string())?;
+        let Some(state) = guard.as_ref() else {
+            return Ok(false);
+        };
+        if !follows_system_default(state.device_id.as_deref()) {
+            return Ok(false);
+        }
+        let current = resolve_device(AudioDeviceKind::Output, state.device_id.as_deref())?
+            .name()
+            .map_err(|e| format!("Failed to get device name: {}", e))?;
+        if current == state.stream.device_name {
+            return Ok(false);
+        }
+        state.device_id.clone()
+    };
+    start_playback(device_id)?;
+    Ok(true)
+}
+
+/// Open an output device and start an (empty) mixer on it.
+fn open_output_stream(device_id: Option<&str>) -> Result<OutputStreamHandle, String> {
+    let device = resolve_device(AudioDeviceKind::Output, device_id)?;
+    let device_name = device
+        .name()
+        .map_err(|e| format!("Failed to get device name: {}", e))?;
+    let (config, sample_format) = choose_output_config(&device)?;
+
+    let (control_tx, control_rx) = RingBuffer::<MixerCommand>::new(CONTROL_RING_CAP);
+    let (retired_tx, mut retired_rx) = RingBuffer::<Retired>::new(RETIRE_RING_CAP);
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+
+    // Stream owner thread: build + play, then free retired sources until told to stop.
+    let stream_thread = thread::spawn(move || {
+        let mixer = OutputMixer::new(control_rx, retired_tx, config.sample_rate.0);
+        let stream = match sample_format {
+            SampleFormat::F32 => build_output_stream::<f32>(&device, &config, mixer),
+            SampleFormat::I16 => build_output_stream::<i16>(&device, &config, mixer),
+            SampleFormat::U16 => build_output_stream::<u16>(&device, &config, mixer),
+            _ => Err(format!("Unsupported sample format: {:?}", sample_format)),
+        };
+        let stream = match stream.and_then(|s| {
+            s.play().map_err(|e| format!("Failed to start stream: {}", e))?;
+            Ok(s)
+        }) {
+            Ok(s) => s,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+        // Runs until stop_tx is used or dropped.
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(RETIRE_POLL) {
+            free_retired(&mut retired_rx);
+        }
+        drop(stream);
+        free_retired(&mut retired_rx);
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|_| "Playback thread exited unexpectedly".to_string())??;
+
+    Ok(OutputStreamHandle {
+        control_tx,
+        stop_tx,
+        stream_thread: Some(stream_thread),
+        device_name,
+    })
+}
+
+/// Free sources the output callback retired. Runs on the stream thread, never the callback.
+fn free_retired(retired: &mut Consumer<Retired>) {
+    while let Ok(Retired { source, rejected }) = retired.pop() {
+        if rejected {
+            eprintln!("Output mixer full ({} sources); dropped a playback source", MAX_PEERS);
+        }
+        drop(source);
+    }
+}
+
+/// Build an output stream for the given sample type. Left/right go to the first two
+/// channels; mono devices and any extra channels get the average of the two.
+fn build_output_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    mut mixer: OutputMixer,
+) -> Result<Stream, String>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let err_fn = |err| eprintln!("Audio output stream error: {}", err);
+    let mut resampler = (config.sample_rate.0 != PIPELINE_SAMPLE_RATE)
+        .then(|| OutputResampler::new(config.sample_rate.0))
+        .transpose()?;
+
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                mixer.drain_commands();
+                // When this buffer's first sample will be heard, for the echo canceller.
+                let played_at = (mixer.feeds_reference && crate::audio_aec::reference_wanted()).then(|| {
+                    let ts = info.timestamp();
+                    Instant::now() + ts.playback.duration_since(&ts.callback).unwrap_or_default()
+                });
+                for (i, frame) in data.chunks_mut(channels).enumerate() {
+                    let (left, right) = match resampler.as_mut() {
+                        Some(resampler) => resampler.next_sample(|| mixer.next_sample()),
+                        None => mixer.next_sample(),
+                    };
+                    if let Some(played_at) = played_at {
+                        mixer.record_reference(i, played_at);
+                    }
+                    let centre = T::from_sample_((left + right) * 0.5);
+                    for out in frame.iter_mut() {
+                        *out = centre;
+                    }
+                    if frame.len() >= 2 {
+                        frame[0] = T::from_sample_(left);
+                        frame[1] = T::from_sample_(right);
+                    }
+                }
+            },
+            err_fn,
+            None,
+        )
+        .map_err(|e| format!("Failed to build output stream: {}", e))
+}
+
+/// Play a sine tone on its own stream (independent of playback) and return once it has
+/// been heard. Fades in/out over 10 ms to avoid clicks.
+pub fn play_test_tone(device_id: Option<&str>, freq_hz: f32, amplitude: f32, duration: Duration) -> Result<(), String> {
+    let mut stream = open_output_stream(device_id)?;
+    let (mut producer, consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(PEER_RING_CAP);
+    let source = PeerSource::new(consumer, None, Some(Arc::new(PeerControls::new())));
+    stream
+        .control_tx
+        .push(MixerCommand::AddPeer(Box::new(source)))
+        .map_err(|_| "Mixer control queue full".to_string())?;
+
+    let total = (duration.as_secs_f32() * PIPELINE_SAMPLE_RATE as f32) as usize;
+    let step = 2.0 * std::f32::consts::PI * freq_hz / PIPELINE_SAMPLE_RATE as f32;
+    let deadline = Instant::now() + duration + Duration::from_secs(1);
+    let mut n = 0;
+    while n < total && Instant::now() < deadline {
+        if producer.is_full() {
+            thread::sleep(Duration::from_millis(5));
+            continue;
+        }
+        let mut frame = [0.0f32; FRAME_SAMPLES];
+        for s in frame.iter_mut().take(total - n) {
+            let fade = (n.min(total - n) as f32 / FRAME_SAMPLES as f32).min(1.0);
+            *s = amplitude * fade * (n as f32 * step).sin();
+            n += 1;
+        }
+        let _ = producer.push(frame);
+    }
+    // Let the mixer drain the ring before closing the device.
+    while producer.slots() < PEER_RING_CAP && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+    thread::sleep(Duration::from_millis(50));
+    stream.stop();
+    Ok(())
+}
+
+/// Stop playback and release the output device.
+pub fn stop_playback() {
+    let state = match AUDIO_OUTPUT_STATE.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return,
+    };
+    if let Some(state) = state {
+        state.stream.stop();
+    }
+}
+
+/// Queue decoded mono 48 kHz PCM for a remote peer. Registers the peer on first use.
+/// Samples are packed into 10 ms frames; a trailing partial frame is held until the next push.
+pub fn push_peer_pcm(peer_id: &str, samples: &[f32]) -> Result<(), String> {
+    push_source_pcm(peer_id, samples, false)
+}
+
+/// Like `push_peer_pcm` for a stereo source (music bot, soundboard): `samples` is
+/// interleaved L/R. A source stays mono or stereo for as long as it's registered.
+pub fn push_peer_pcm_stereo(peer_id: &str, samples: &[f32]) -> Result<(), String> {
+    push_source_pcm(peer_id, samples, true)
+}
+
+fn push_source_pcm(peer_id: &str, samples: &[f32], stereo: bool) -> Result<(), String> {
+    crate::audio_ducking::note_remote_audio(samples);
+    let mut guard = AUDIO_OUTPUT_STATE
+        .lock()
+        .map_err(|_| "Failed to lock audio output state".to_string())?;
+    let state = guard
+        .as_mut()
+        .ok_or_else(|| "Audio playback not started".to_string())?;
+
+    if !state.peers.contains_key(peer_id) {
+        if state.peers.len() >= MAX_PEERS {
+            return Err(format!("Too many playback peers (max {})", MAX_PEERS));
+        }
+        let (producer, consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(PEER_RING_CAP);
+        let (right_producer, right_consumer) = if stereo {
+            let (p, c) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(PEER_RING_CAP);
+            (Some(p), Some(c))
+        } else {
+            (None, None)
+        };
+        let controls = peer_controls(peer_id)?;
+        controls.stereo.store(stereo, Ordering::Relaxed);
+        state
+            .stream
+            .control_tx
+            .push(MixerCommand::AddPeer(Box::new(PeerSource::new(consumer, right_consumer, Some(controls)))))
+            .map_err(|_| "Mixer control queue full".to_string())?;
+        state.peers.insert(
+            peer_id.to_string(),
+            PeerInput {
+                producer,
+                right: right_producer,
+                pending: Vec::with_capacity(FRAME_SAMPLES * if stereo { 2 } else { 1 }),
+            },
+        );
+    }
+
+    let input = state.peers.get_mut(peer_id).expect("peer inserted above");
+    if input.right.is_some() != stereo {
+        return Err(format!(
+            "Playback source {} is {}",
+            peer_id,
+            if stereo { "mono" } else { "stereo" }
+        ));
+    }
+    input.pending.extend_from_slice(samples);
+    match input.right.as_mut() {
+        None => {
+            let full_frames = input.pending.len() / FRAME_SAMPLES;
+            for chunk in input.pending.chunks_exact(FRAME_SAMPLES) {
+                let mut frame = [0.0f32; FRAME_SAMPLES];
+                frame.copy_from_slice(chunk);
+                if input.producer.push(frame).is_err() {
+                    DROPPED_PLAYBACK.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            input.pending.drain(..full_frames * FRAME_SAMPLES);
+        }
+        Some(right) => {
+            let full_frames = input.pending.len() / (FRAME_SAMPLES * 2);
+            for chunk in input.pending.chunks_exact(FRAME_SAMPLES * 2) {
+                // Only the callback frees slots, so both pushes succeed once both have room.
+                if input.producer.slots() == 0 || right.slots() == 0 {
+                    DROPPED_PLAYBACK.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                let mut left_frame = [0.0f32; FRAME_SAMPLES];
+                let mut right_frame = [0.0f32; FRAME_SAMPLES];
+                for (i, pair) in chunk.chunks_exact(2).enumerate() {
+                    left_frame[i] = pair[0];
+                    right_frame[i] = pair[1];
+                }
+                // Right first: the callback pops the right frame when the left one appears.
+                let _ = right.push(right_frame);
+                let _ = input.producer.push(left_frame);
+            }
+            input.pending.drain(..full_frames * FRAME_SAMPLES * 2);
+        }
+    }
+    Ok(())
+}
+
+/// Stop mixing a peer. The callback reaps the source once its ring drains.
+pub fn remove_peer(peer_id: &str) {
+    if let Ok(mut guard) = AUDIO_OUTPUT_STATE.lock() {
+        if let Some(state) = guard.as_mut() {
+            state.peers.remove(peer_id);
+        }
+    }
+}
+
+/// Set a remote user's local volume (0.0 = silent, 1.0 = unity, up to 2.0).
+pub fn set_user_volume(peer_id: &str, volume: f32) -> Result<(), String> {
+    peer_controls(peer_id)?
+        .volume
+        .store(volume.clamp(0.0, 2.0).to_bits(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Mute or unmute a remote user locally.
+pub fn set_user_muted(peer_id: &str, muted: bool) -> Result<(), String> {
+    peer_controls(peer_id)?.muted.store(muted, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Pan a remote user: -1.0 = left, 0.0 = centre, 1.0 = right. No effect on mono devices.
+pub fn set_user_pan(peer_id: &str, pan: f32) -> Result<(), String> {
+    peer_controls(peer_id)?
+        .pan
+        .store(pan.clamp(-1.0, 1.0).to_bits(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Upmix width for one mono user (0 = centred, 1 = widest); None follows the mixer's
+/// voice width. Stereo sources ignore it.
+pub fn set_user_width(peer_id: &str, width: Option<f32>) -> Result<(), String> {
+    let width = width.map_or(-1.0, |w| w.clamp(0.0, 1.0));
+    peer_controls(peer_id)?.width.store(width.to_bits(), Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn set_mixer_config(config: MixerConfig) {
+    VOICE_WIDTH.store(config.voice_width.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+}
+
+pub fn mixer_config() -> MixerConfig {
+    MixerConfig {
+        voice_width: f32::from_bits(VOICE_WIDTH.load(Ordering::Relaxed)),
+    }
+}
+
+/// Turn binaural positioning on or off for all users.
+pub fn set_spatial_enabled(enabled: bool) {
+    SPATIAL_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Place a remote user: `azimuth` in degrees (0 ahead, +90 right, ±180 behind),
+/// `distance` in metres (1.0 or less = full volume).
+pub fn set_user_position(peer_id: &str, azimuth: f32, distance: f32) -> Result<(), String> {
+    let controls = peer_controls(peer_id)?;
+    controls.azimuth.store(azimuth.to_bits(), Ordering::Relaxed);
+    controls.distance.store(distance.max(0.0).to_bits(), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Spread the users currently playing evenly across ±`spread_deg` in join order.
+pub fn spread_users_by_join_order(spread_deg: f32) -> Result<(), String> {
+    let peer_ids: Vec<String> = {
+        let guard = AUDIO_OUTPUT_STATE
+            .lock()
+            .map_err(|_| "Failed to lock audio output state".to_string())?;
+        guard.as_ref().map(|s| s.peers.keys().cloned().collect()).unwrap_or_default()
+    };
+    let mut controls = peer_ids
+        .iter()
+        .map(|id| peer_controls(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    controls.sort_by_key(|c| c.join_order);
+    let spread = spread_deg.clamp(0.0, 90.0);
+    for (c, azimuth) in controls.iter().zip(spread_azimuths(controls.len(), spread)) {
+        c.azimuth.store(azimuth.to_bits(), Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Mix settings for every user that has been adjusted or played this session.
+pub fn user_mix_settings() -> HashMap<String, UserMixSettings> {
+    let Ok(table) = PEER_CONTROLS.lock() else {
+        return HashMap::new();
+    };
+    table
+        .iter()
+        .flatten()
+        .map(|(id, c)| {
+            (
+                id.clone(),
+                UserMixSettings {
+                    volume: f32::from_bits(c.volume.load(Ordering::Relaxed)),
+                    muted: c.muted.load(Ordering::Relaxed),
+                    pan: f32::from_bits(c.pan.load(Ordering::Relaxed)),
+                    azimuth: f32::from_bits(c.azimuth.load(Ordering::Relaxed)),
+                    distance: f32::from_bits(c.distance.load(Ordering::Relaxed)),
+                    width: c.width(),
+                    stereo: c.stereo.load(Ordering::Relaxed),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Set master output volume (0.0 = silent, 1.0 = unity).
+pub fn set_output_volume(volume: f32) {
+    MASTER_VOLUME.store(volume.clamp(0.0, 2.0).to_bits(), Ordering::Relaxed);
+}
+
+pub fn set_deafened(deafened: bool) {
+    DEAFENED.store(deafened, Ordering::Relaxed);
+}
+
+pub fn is_deafened() -> bool {
+    DEAFENED.load(Ordering::Relaxed)
+}
+
+/// Frames dropped on the playback side since playback started.
+pub fn dropped_playback_frames() -> u64 {
+    DROPPED_PLAYBACK.load(Ordering::Relaxed)
+}
+
+/// Hand a fresh sidetone ring to a mixer and point the capture-side tap at it.
+fn attach_sidetone(control_tx: &mut Producer<MixerCommand>) -> Result<(), String> {
+    let (producer, consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(SIDETONE_RING_CAP);
+    control_tx
+        .push(MixerCommand::SetSidetone(Box::new(PeerSource::new(consumer, None, None))))
+        .map_err(|_| "Mixer control queue full".to_string())?;
+    let mut tap = SIDETONE_TAP
+        .lock()
+        .map_err(|_| "Failed to lock sidetone tap".to_string())?;
+    *tap = Some(producer);
+    Ok(())
+}
+
+/// Monitor the processed mic through an output device at `volume`.
+/// `device_id: None` mixes into the playback stream (attached whenever playback runs);
+/// `Some(id)` opens a dedicated stream on that device. Replaces any previous sidetone.
+pub fn start_sidetone(device_id: Option<String>, volume: f32) -> Result<(), String> {
+    stop_sidetone();
+    set_sidetone_volume(volume);
+
+    let dedicated = match device_id {
+        Some(id) => {
+            let mut stream = open_output_stream(Some(&id))?;
+            if let Err(e) = attach_sidetone(&mut stream.control_tx) {
+                stream.stop();
+                return Err(e);
+            }
+            Some(stream)
+        }
+        None => {
+            let mut output = AUDIO_OUTPUT_STATE
+                .lock()
+                .map_err(|_| "Failed to lock audio output state".to_string())?;
+            if let Some(state) = output.as_mut() {
+                attach_sidetone(&mut state.stream.control_tx)?;
+            }
+            None
+        }
+    };
+
+    let mut sidetone = SIDETONE_STATE
+        .lock()
+        .map_err(|_| "Failed to lock sidetone state".to_string())?;
+    *sidetone = Some(SidetoneState { dedicated });
+    SIDETONE_ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Stop mic monitoring. Dropping the tap lets the mixer reap the sidetone source.
+pub fn stop_sidetone() {
+    SIDETONE_ENABLED.store(false, Ordering::Relaxed);
+    if let Ok(mut tap) = SIDETONE_TAP.lock() {
+        tap.take();
+    }
+    let state = match SIDETONE_STATE.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return,
+    };
+    if let Some(stream) = state.and_then(|s| s.dedicated) {
+        stream.stop();
+    }
+}
+
+/// Set sidetone volume (0.0 = silent, 1.0 = unity).
+pub fn set_sidetone_volume(volume: f32) {
+    SIDETONE_VOLUME.store(volume.clamp(0.0, 2.0).to_bits(), Ordering::Relaxed);
+}
+
+/// Offer a processed mic frame to the sidetone. Called from the capture processing thread;
+/// never blocks (skips the frame if the tap is being swapped) and drops when the ring is full.
+pub fn push_sidetone(pcm: &[f32]) {
+    tee_slots(&SIDETONE_ENABLED, &SIDETONE_TAP, pcm, |_, _, frame| frame);
+}
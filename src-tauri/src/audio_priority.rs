@@ -0,0 +1,103 @@
+//! Realtime scheduling for audio threads that sit between the device callback and the
+//! network (the capture processing thread), so CPU load elsewhere doesn't cause drops.
+//!
+//! - Windows: MMCSS "Pro Audio" task, reverted when the guard drops.
+//! - Unix: `SCHED_FIFO`, falling back to the highest nice level we're allowed. Both need
+//!   rtprio/nice limits (or root) on Linux; failure just leaves the thread as it was.
+
+/// Keeps the current thread's raised priority; drop it on the same thread to restore it.
+pub struct PriorityGuard(imp::Handle);
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        imp::revert(&self.0);
+    }
+}
+
+/// Raise the calling thread to realtime/high priority.
+pub fn promote_current_thread() -> Result<PriorityGuard, String> {
+    imp::promote().map(PriorityGuard)
+}
+
+#[cfg(windows)]
+mod imp {
+    use windows::core::w;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Threading::{
+        AvRevertMmThreadCharacteristics, AvSetMmThreadCharacteristicsW, AvSetMmThreadPriority, AVRT_PRIORITY_HIGH,
+    };
+
+    pub struct Handle(HANDLE);
+
+    pub fn promote() -> Result<Handle, String> {
+        let mut task_index = 0u32;
+        let handle = unsafe { AvSetMmThreadCharacteristicsW(w!("Pro Audio"), &mut task_index) }
+            .map_err(|e| format!("Failed to join MMCSS task: {}", e))?;
+        unsafe {
+            let _ = AvSetMmThreadPriority(handle, AVRT_PRIORITY_HIGH);
+        }
+        Ok(Handle(handle))
+    }
+
+    pub fn revert(handle: &Handle) {
+        unsafe {
+            let _ = AvRevertMmThreadCharacteristics(handle.0);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    /// The scheduling policy and priority to put back.
+    pub struct Handle {
+        policy: libc::c_int,
+        param: libc::sched_param,
+        nice: Option<libc::c_int>,
+    }
+
+    /// Middle of the FIFO range: above other apps' realtime threads that don't ask for much,
+    /// below the audio server's own.
+    const FIFO_PRIORITY: libc::c_int = 10;
+    const NICE_LEVEL: libc::c_int = -10;
+
+    pub fn promote() -> Result<Handle, String> {
+        unsafe {
+            let thread = libc::pthread_self();
+            let mut policy = 0;
+            let mut param: libc::sched_param = std::mem::zeroed();
+            if libc::pthread_getschedparam(thread, &mut policy, &mut param) != 0 {
+                return Err("Failed to read thread scheduling".to_string());
+            }
+            let mut fifo: libc::sched_param = std::mem::zeroed();
+            fifo.sched_priority = FIFO_PRIORITY.min(libc::sched_get_priority_max(libc::SCHED_FIFO));
+            let err = libc::pthread_setschedparam(thread, libc::SCHED_FIFO, &fifo);
+            if err == 0 {
+                return Ok(Handle { policy, param, nice: None });
+            }
+
+            // No rtprio allowance: a negative nice still helps under load. On Linux this
+            // applies to the calling thread only.
+            let nice = libc::getpriority(libc::PRIO_PROCESS, 0);
+            if libc::setpriority(libc::PRIO_PROCESS, 0, NICE_LEVEL) == 0 {
+                return Ok(Handle { policy, param, nice: Some(nice) });
+            }
+            Err(format!(
+                "Failed to set realtime priority: {}",
+                std::io::Error::from_raw_os_error(err)
+            ))
+        }
+    }
+
+    pub fn revert(handle: &Handle) {
+        unsafe {
+            match handle.nice {
+                Some(nice) => {
+                    libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                }
+                None => {
+                    libc::pthread_setschedparam(libc::pthread_self(), handle.policy, &handle.param);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Local mic recording to WAV or FLAC.
+//!
+//! The capture processing thread tees frames into a small lock-free ring via [`tee_frame`];
+//! a writer thread drains it to disk so file I/O never stalls the audio path. If the writer
+//! falls behind, frames are dropped (counted) rather than queued.
+
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::flac_encoder::FlacWriter;
+use crate::audio_resample::{FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+use crate::audio_tap::tee_slots;
+
+/// ~2 s of frames between the processing thread and the writer.
+const RING_FRAMES: usize = 200;
+/// RIFF sizes are 32-bit; stop before hound would fail the header update.
+pub(crate) const WAV_MAX_BYTES: u64 = u32::MAX as u64 - 1024;
+const WAV_HEADER_BYTES: u64 = 44;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    Wav,
+    Flac,
+}
+
+/// Which point of the mic chain to record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingSource {
+    /// After DSP (gain, gate, PTT/mute) — what peers hear.
+    Processed,
+    /// Mixed device input before DSP, for debugging the chain.
+    Raw,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub path: String,
+    pub format: RecordingFormat,
+    pub duration_secs: f64,
+    pub bytes: u64,
+    pub dropped_frames: u64,
+    /// "stopped", "duration_limit", "size_limit" or an error message.
+    pub stop_reason: String,
+}
+
+struct RecordingState {
+    stop: Arc<AtomicBool>,
+    writer_thread: JoinHandle<RecordingSummary>,
+}
+
+static RECORDING_STATE: Mutex<Option<RecordingState>> = Mutex::new(None);
+static RECORDING_TAP: Mutex<Option<Producer<[f32; FRAME_SAMPLES]>>> = Mutex::new(None);
+static RECORDING_ACTIVE: AtomicBool = AtomicBool::new(false);
+static RECORDING_SOURCE: AtomicU8 = AtomicU8::new(0);
+static DROPPED_RECORDING_FRAMES: AtomicU64 = AtomicU64::new(0);
+
+fn source_code(source: RecordingSource) -> u8 {
+    match source {
+        RecordingSource::Processed => 0,
+        RecordingSource::Raw => 1,
+    }
+}
+
+/// Offer a frame to the active recording. Cheap no-op when nothing is recording;
+/// never blocks (skips the frame if the tap is being swapped).
+pub fn tee_frame(source: RecordingSource, pcm: &[f32]) {
+    if RECORDING_SOURCE.load(Ordering::Relaxed) != source_code(source) {
+        return;
+    }
+    let dropped = tee_slots(&RECORDING_ACTIVE, &RECORDING_TAP, pcm, |_, _, frame| frame);
+    if dropped > 0 {
+        DROPPED_RECORDING_FRAMES.fetch_add(dropped, Ordering::Relaxed);
+    }
+}
+
+/// Mono 48 kHz file writer, also used for the per-track files of call recordings.
+pub(crate) enum RecordingWriter {
+    Wav(hound::WavWriter<BufWriter<File>>, u64),
+    Flac(FlacWriter<BufWriter<File>>),
+}
+
+impl RecordingWriter {
+    pub(crate) fn create(path: &PathBuf, format: RecordingFormat) -> Result<Self, String> {
+        match format {
+            RecordingFormat::Wav => {
+                let spec = hound::WavSpec {
+                    channels: 1,
+                    sample_rate: PIPELINE_SAMPLE_RATE,
+                    bits_per_sample: 16,
+                    sample_format: hound::SampleFormat::Int,
+                };
+                hound::WavWriter::create(path, spec)
+                    .map(|w| RecordingWriter::Wav(w, 0))
+                    .map_err(|e| format!("Failed to create WAV file: {}", e))
+            }
+            RecordingFormat::Flac => {
+                let file = File::create(path)
+                    .map_err(|e| format!("Failed to create FLAC file: {}", e))?;
+                FlacWriter::new(BufWriter::new(file), PIPELINE_SAMPLE_RATE)
+                    .map(RecordingWriter::Flac)
+                    .map_err(|e| format!("Failed to write FLAC header: {}", e))
+            }
+        }
+    }
+
+    pub(crate) fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        match self {
+            RecordingWriter::Wav(w, count) => {
+                for &s in samples {
+                    w.write_sample((s.clamp(-1.0, 1.0) * 32767.0).round() as i16)
+                        .map_err(|e| format!("Failed to write WAV samples: {}", e))?;
+                }
+                *count += samples.len() as u64;
+                Ok(())
+            }
+            RecordingWriter::Flac(w) => w
+                .write_samples(samples)
+                .map_err(|e| format!("Failed to write FLAC samples: {}", e)),
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> u64 {
+        match self {
+            RecordingWriter::Wav(_, count) => WAV_HEADER_BYTES + count * 2,
+            RecordingWriter::Flac(w) => w.bytes_written(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<(), String> {
+        match self {
+            RecordingWriter::Wav(w, _) => w
+                .finalize()
+                .map_err(|e| format!("Failed to finalize WAV file: {}", e)),
+            RecordingWriter::Flac(w) => w
+                .finish()
+                .map(|_| ())
+                .map_err(|e| format!("Failed to finalize FLAC file: {}", e)),
+        }
+    }
+}
+
+/// Start recording the mic chain to `path`. Recording stops on [`stop_recording`] or when
+/// either limit is reached; `on_finished` is called from the writer thread in every case.
+pub fn start_recording<F>(
+    path: PathBuf,
+    format: RecordingFormat,
+    source: RecordingSource,
+    max_duration_secs: Option<f64>,
+    max_bytes: Option<u64>,
+    on_finished: F,
+) -> Result<(), String>
+where
+    F: FnOnce(RecordingSummary) + Send + 'static,
+{
+    let mut state = RECORDING_STATE
+        .lock()
+        .map_err(|_| "Failed to lock recording state".to_string())?;
+    if let Some(existing) = state.as_ref() {
+        if !existing.writer_thread.is_finished() {
+            return Err("A recording is already in progress".to_string());
+        }
+    }
+    // Reap a recording that already stopped on its own.
+    if let Some(old) = state.take() {
+        let _ = old.writer_thread.join();
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create recording directory: {}", e))?;
+    }
+    let writer = RecordingWriter::create(&path, format)?;
+
+    let max_samples = max_duration_secs
+        .filter(|s| *s > 0.0)
+        .map(|s| (s * PIPELINE_SAMPLE_RATE as f64) as u64);
+    let max_bytes = match format {
+        RecordingFormat::Wav => Some(max_bytes.unwrap_or(WAV_MAX_BYTES).min(WAV_MAX_BYTES)),
+        RecordingFormat::Flac => max_bytes,
+    };
+
+    let (producer, consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(RING_FRAMES);
+    {
+        let mut tap = RECORDING_TAP
+            .lock()
+            .map_err(|_| "Failed to lock recording tap".to_string())?;
+        *tap = Some(producer);
+    }
+    DROPPED_RECORDING_FRAMES.store(0, Ordering::Relaxed);
+    RECORDING_SOURCE.store(source_code(source), Ordering::Relaxed);
+    RECORDING_ACTIVE.store(true, Ordering::Relaxed);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    let writer_thread = std::thread::spawn(move || {
+        let summary = run_writer(writer, consumer, &stop_flag, path, format, max_samples, max_bytes);
+        RECORDING_ACTIVE.store(false, Ordering::Relaxed);
+        if let Ok(mut tap) = RECORDING_TAP.lock() {
+            tap.take();
+        }
+        on_finished(summary.clone());
+        summary
+    });
+
+    *state = Some(RecordingState { stop, writer_thread });
+    Ok(())
+}
+
+fn run_writer(
+    mut writer: RecordingWriter,
+    mut consumer: Consumer<[f32; FRAME_SAMPLES]>,
+    stop: &AtomicBool,
+    path: PathBuf,
+    format: RecordingFormat,
+    max_samples: Option<u64>,
+    max_bytes: Option<u64>,
+) -> RecordingSummary {
+    let mut samples_written: u64 = 0;
+    let mut stop_reason = "stopped".to_string();
+
+    'outer: loop {
+        let stopping = stop.load(Ordering::Relaxed);
+        while let Ok(frame) = consumer.pop() {
+            let mut take = frame.len();
+            if let Some(max) = max_samples {
+                take = take.min(max.saturating_sub(samples_written) as usize);
+            }
+            if let Err(e) = writer.write(&frame[..take]) {
+                stop_reason = e;
+                break 'outer;
+            }
+            samples_written += take as u64;
+            if max_samples.is_some_and(|max| samples_written >= max) {
+                stop_reason = "duration_limit".to_string();
+                break 'outer;
+            }
+            if max_bytes.is_some_and(|max| writer.bytes() >= max) {
+                stop_reason = "size_limit".to_string();
+                break 'outer;
+            }
+        }
+        if stopping {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    if let Err(e) = writer.finish() {
+        stop_reason = e;
+    }
+    let bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    RecordingSummary {
+        path: path.to_string_lossy().to_string(),
+        format,
+        duration_secs: samples_written as f64 / PIPELINE_SAMPLE_RATE as f64,
+        bytes,
+        dropped_frames: DROPPED_RECORDING_FRAMES.load(Ordering::Relaxed),
+        stop_reason,
+    }
+}
+
+/// Stop the current recording, flush it to disk and return its summary.
+/// Also returns the summary of a recording that already hit its limit.
+pub fn stop_recording() -> Result<RecordingSummary, String> {
+    let state = RECORDING_STATE
+        .lock()
+        .map_err(|_| "Failed to lock recording state".to_string())?
+        .take()
+        .ok_or_else(|| "No recording in progress".to_string())?;
+    state.stop.store(true, Ordering::Relaxed);
+    state
+        .writer_thread
+        .join()
+        .map_err(|_| "Recording writer thread panicked".to_string())
+}
+
+pub fn is_recording() -> bool {
+    RECORDING_ACTIVE.load(Ordering::Relaxed)
+}
@@ -0,0 +1,318 @@
+//! Sample-rate conversion into the pipeline's fixed 48 kHz / 10 ms framing.
+//!
+//! Devices that can't open at 48 kHz (typically 44.1 kHz hardware) deliver frames at
+//! their native rate. `FrameResampler` converts them and re-slices the output into
+//! exact 480-sample frames so everything downstream (DSP, emitter, encoder) can
+//! assume 48 kHz timing. At 48 kHz input it is a zero-cost passthrough.
+//!
+//! Playback goes the other way: `OutputResampler` turns the 48 kHz mix into the output
+//! device's rate, so 44.1 kHz speakers play at the right pitch and pace.
+//!
+//! Device clocks are never exactly their nominal rate. `DriftEstimator` measures the
+//! real rate over a long window and the resampler stretches/shrinks by that many ppm,
+//! so a slightly fast or slow device doesn't slowly build up drops downstream.
+
+use rubato::{FastFixedIn, PolynomialDegree, Resampler};
+use std::time::{Duration, Instant};
+
+/// Rate every processed frame is guaranteed to be at.
+pub const PIPELINE_SAMPLE_RATE: u32 = 48000;
+/// 10 ms at 48 kHz.
+pub const FRAME_SAMPLES: usize = 480;
+/// Headroom for later ratio adjustments (relative to the nominal ratio).
+const MAX_RATIO_RELATIVE: f64 = 1.1;
+/// Largest drift correction applied. Real crystal drift is well under this.
+const MAX_DRIFT_PPM: f64 = 1000.0;
+/// Measurement window for one drift estimate.
+const DRIFT_WINDOW: Duration = Duration::from_secs(10);
+/// A gap this long between samples (pause, device hiccup) restarts the window.
+const DRIFT_MAX_GAP: Duration = Duration::from_millis(250);
+/// Fraction of each new estimate applied, so one noisy window can't swing the ratio.
+const DRIFT_SMOOTHING: f64 = 0.3;
+
+pub struct FrameResampler {
+    /// None when input is already 48 kHz.
+    inner: Option<FastFixedIn<f32>>,
+    /// Input samples waiting for a full resampler chunk.
+    input: Vec<f32>,
+    /// Resampled samples waiting to fill a 480-sample frame.
+    output: Vec<f32>,
+    /// Scratch buffer for one resampler call (mono).
+    scratch: Vec<Vec<f32>>,
+    /// Nominal input→48 kHz ratio; drift corrections are relative to this.
+    nominal_ratio: f64,
+}
+
+impl FrameResampler {
+    /// Create a resampler from `input_rate` to 48 kHz.
+    pub fn new(input_rate: u32) -> Result<Self, String> {
+        if input_rate == PIPELINE_SAMPLE_RATE {
+            return Ok(Self {
+                inner: None,
+                input: Vec::new(),
+                output: Vec::new(),
+                scratch: Vec::new(),
+                nominal_ratio: 1.0,
+            });
+        }
+        Self::resampling(input_rate)
+    }
+
+    /// Like `new`, but always runs the resampler (even at 48 kHz) so `set_drift_ppm` can
+    /// correct for the device clock.
+    pub fn with_drift_compensation(input_rate: u32) -> Result<Self, String> {
+        Self::resampling(input_rate)
+    }
+
+    fn resampling(input_rate: u32) -> Result<Self, String> {
+        if input_rate == 0 {
+            return Err("Invalid input sample rate: 0".to_string());
+        }
+        // One 10 ms chunk of input per resampler call.
+        let chunk = (input_rate as usize / 100).max(1);
+        let ratio = PIPELINE_SAMPLE_RATE as f64 / input_rate as f64;
+        let inner = FastFixedIn::<f32>::new(
+            ratio,
+            MAX_RATIO_RELATIVE,
+            PolynomialDegree::Cubic,
+            chunk,
+            1,
+        )
+        .map_err(|e| format!("Failed to create resampler: {}", e))?;
+        let scratch = inner.output_buffer_allocate(true);
+        Ok(Self {
+            inner: Some(inner),
+            input: Vec::with_capacity(chunk * 4),
+            output: Vec::with_capacity(FRAME_SAMPLES * 4),
+            scratch,
+            nominal_ratio: ratio,
+        })
+    }
+
+    /// Compensate a device clock running `ppm` parts-per-million fast (negative = slow):
+    /// a fast device is shrunk so it still yields exactly 48k frames per second.
+    /// No-op for passthrough resamplers.
+    pub fn set_drift_ppm(&mut self, ppm: f64) {
+        let Some(inner) = self.inner.as_mut() else { return };
+        let ppm = ppm.clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM);
+        let relative = 1.0 / (1.0 + ppm * 1e-6);
+        if let Err(e) = inner.set_resample_ratio_relative(relative, true) {
+            eprintln!("Resampler drift correction failed (ratio {}): {}", self.nominal_ratio * relative, e);
+        }
+    }
+
+    /// Feed device-rate mono samples; `emit` is called once per complete 48 kHz frame.
+    pub fn process<F>(&mut self, samples: &[f32], mut emit: F)
+    where
+        F: FnMut(&[f32; FRAME_SAMPLES]),
+    {
+        let Some(inner) = self.inner.as_mut() else {
+            // Passthrough still re-slices, in case the device delivers odd-sized buffers.
+            self.output.extend_from_slice(samples);
+            Self::drain_frames(&mut self.output, &mut emit);
+            return;
+        };
+
+        self.input.extend_from_slice(samples);
+        loop {
+            let needed = inner.input_frames_next();
+            if self.input.len() < needed {
+                break;
+            }
+            match inner.process_into_buffer(&[&self.input[..needed]], &mut self.scratch, None) {
+                Ok((_, written)) => {
+                    self.output.extend_from_slice(&self.scratch[0][..written]);
+                }
+                Err(e) => {
+                    eprintln!("Resampler error: {}", e);
+                }
+            }
+            self.input.drain(..needed);
+        }
+        Self::drain_frames(&mut self.output, &mut emit);
+    }
+
+    fn drain_frames<F>(buffer: &mut Vec<f32>, emit: &mut F)
+    where
+        F: FnMut(&[f32; FRAME_SAMPLES]),
+    {
+        let full = buffer.len() / FRAME_SAMPLES;
+        let mut frame = [0.0f32; FRAME_SAMPLES];
+        for chunk in buffer.chunks_exact(FRAME_SAMPLES) {
+            frame.copy_from_slice(chunk);
+            emit(&frame);
+        }
+        buffer.drain(..full * FRAME_SAMPLES);
+    }
+}
+
+/// Stereo 48 kHz → device-rate conversion for playback. Runs inside the output callback:
+/// all buffers are allocated up front and each refill resamples one 10 ms chunk.
+pub struct OutputResampler {
+    inner: FastFixedIn<f32>,
+    /// One 10 ms chunk of 48 kHz (left, right) input.
+    input: Vec<Vec<f32>>,
+    /// Device-rate output of the last chunk.
+    output: Vec<Vec<f32>>,
+    output_len: usize,
+    output_pos: usize,
+}
+
+impl OutputResampler {
+    /// Create a resampler from 48 kHz to `output_rate`.
+    pub fn new(output_rate: u32) -> Result<Self, String> {
+        if output_rate == 0 {
+            return Err("Invalid output sample rate: 0".to_string());
+        }
+        let ratio = output_rate as f64 / PIPELINE_SAMPLE_RATE as f64;
+        let inner = FastFixedIn::<f32>::new(ratio, 1.0, PolynomialDegree::Cubic, FRAME_SAMPLES, 2)
+            .map_err(|e| format!("Failed to create resampler: {}", e))?;
+        let output = inner.output_buffer_allocate(true);
+        Ok(Self {
+            inner,
+            input: vec![vec![0.0; FRAME_SAMPLES]; 2],
+            output,
+            output_len: 0,
+            output_pos: 0,
+        })
+    }
+
+    /// Next device-rate (left, right) sample. Pulls another 10 ms from `mix` (48 kHz) when
+    /// the last chunk is used up.
+    pub fn next_sample(&mut self, mut mix: impl FnMut() -> (f32, f32)) -> (f32, f32) {
+        if self.output_pos == self.output_len {
+            for i in 0..FRAME_SAMPLES {
+                let (left, right) = mix();
+                self.input[0][i] = left;
+                self.input[1][i] = right;
+            }
+            self.output_pos = 0;
+            self.output_len = match self.inner.process_into_buffer(&self.input, &mut self.output, None) {
+                Ok((_, written)) => written,
+                Err(_) => 0,
+            };
+            if self.output_len == 0 {
+                return (0.0, 0.0);
+            }
+        }
+        let sample = (self.output[0][self.output_pos], self.output[1][self.output_pos]);
+        self.output_pos += 1;
+        sample
+    }
+}
+
+/// Long-term estimate of how far a device's real sample rate is from nominal, in ppm.
+/// Fed from the processing thread with the number of samples drained from the device.
+pub struct DriftEstimator {
+    nominal_rate: f64,
+    window_start: Option<Instant>,
+    last_record: Option<Instant>,
+    samples: u64,
+    ppm: f64,
+}
+
+impl DriftEstimator {
+    pub fn new(nominal_rate: u32) -> Self {
+        Self {
+            nominal_rate: nominal_rate as f64,
+            window_start: None,
+            last_record: None,
+            samples: 0,
+            ppm: 0.0,
+        }
+    }
+
+    /// Record `samples` device samples received at `now`. Returns the updated drift
+    /// estimate (ppm) whenever a measurement window completes.
+    pub fn record(&mut self, samples: usize, now: Instant) -> Option<f64> {
+        let gap = self.last_record.map(|t| now.duration_since(t) > DRIFT_MAX_GAP).unwrap_or(true);
+        self.last_record = Some(now);
+        if gap {
+            // Samples arriving after a gap were buffered during it; start measuring afresh.
+            self.window_start = Some(now);
+            self.samples = 0;
+            return None;
+        }
+        self.samples += samples as u64;
+
+        let start = self.window_start?;
+        let elapsed = now.duration_since(start);
+        if elapsed < DRIFT_WINDOW {
+            return None;
+        }
+        let measured_rate = self.samples as f64 / elapsed.as_secs_f64();
+        let window_ppm = (measured_rate / self.nominal_rate - 1.0) * 1e6;
+        self.ppm += (window_ppm.clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM) - self.ppm) * DRIFT_SMOOTHING;
+        self.window_start = Some(now);
+        self.samples = 0;
+        Some(self.ppm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passthrough_reslices_into_exact_frames() {
+        let mut resampler = FrameResampler::new(48000).unwrap();
+        let mut frames = 0;
+        resampler.process(&[0.5; 700], |_| frames += 1);
+        assert_eq!(frames, 1);
+        resampler.process(&[0.5; 260], |_| frames += 1);
+        assert_eq!(frames, 2);
+    }
+
+    #[test]
+    fn converts_44_1k_to_48k_frame_rate() {
+        let mut resampler = FrameResampler::new(44100).unwrap();
+        let mut frames = 0;
+        // One second of 44.1 kHz input in device-sized chunks.
+        for _ in 0..100 {
+            resampler.process(&[0.0; 441], |_| frames += 1);
+        }
+        // ~100 frames of 48 kHz output (a few may still be buffered in the filter).
+        assert!((98..=100).contains(&frames), "got {} frames", frames);
+    }
+
+    #[test]
+    fn plays_the_48k_mix_at_44_1k_pace() {
+        let mut resampler = OutputResampler::new(44100).unwrap();
+        let mut mixed = 0usize;
+        // One second of 44.1 kHz output.
+        for _ in 0..44100 {
+            resampler.next_sample(|| {
+                mixed += 1;
+                (0.0, 0.0)
+            });
+        }
+        // ~48000 mixer samples consumed, so the 48 kHz peer rings drain as fast as they fill.
+        assert!((48000..=48480).contains(&mixed), "mixed {} samples", mixed);
+    }
+
+    #[test]
+    fn estimates_fast_device_clock() {
+        let mut estimator = DriftEstimator::new(48000);
+        let start = Instant::now();
+        let mut estimate = None;
+        // 11 s of a device delivering 48048 samples/s (+1000 ppm), 10 ms at a time.
+        for i in 0..1100u64 {
+            let now = start + Duration::from_millis(i * 10);
+            if let Some(ppm) = estimator.record(480 + if i % 10 == 0 { 48 } else { 0 }, now) {
+                estimate = Some(ppm);
+            }
+        }
+        let ppm = estimate.expect("one window should have completed");
+        assert!(ppm > 200.0 && ppm <= 1000.0, "got {} ppm", ppm);
+    }
+
+    #[test]
+    fn gap_restarts_drift_window() {
+        let mut estimator = DriftEstimator::new(48000);
+        let start = Instant::now();
+        estimator.record(480, start);
+        // A long pause must not be read as a slow device.
+        assert!(estimator.record(480, start + Duration::from_secs(30)).is_none());
+        assert_eq!(estimator.ppm, 0.0);
+    }
+}
@@ -0,0 +1,211 @@
+//! Headless audio self-test for support ("my mic doesn't work").
+//!
+//! Opens the selected input through the normal capture pipeline (resampler, mixer, DSP),
+//! records a couple of seconds, and checks frame cadence and signal sanity. Optionally
+//! plays a short tone on the output device. Everything ends up in one serializable
+//! report, with human-readable `issues` for anything that looks wrong.
+
+use cpal::traits::DeviceTrait;
+use serde::Serialize;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use crate::audio_capture::{self, resolve_device, AudioDeviceKind, CaptureOptions};
+use crate::audio_output;
+
+const CAPTURE_DURATION: Duration = Duration::from_secs(2);
+const FRAME_MS: f64 = 10.0;
+/// Room for the whole test, so the collector never drops frames itself.
+const FRAME_QUEUE_CAP: usize = 256;
+const TONE_HZ: f32 = 440.0;
+/// -12 dBFS: clearly audible without being startling.
+const TONE_AMPLITUDE: f32 = 0.25;
+const TONE_DURATION: Duration = Duration::from_secs(1);
+/// Processed samples above this (≈ -60 dBFS) mean the gate let the frame through; a closed
+/// gate leaves at most -60 dB of the input.
+const GATE_OPEN_PEAK: f32 = 0.001;
+/// DSP level (0–1) below which speech wouldn't reach the default voice activity threshold.
+const LOW_LEVEL: f32 = 0.2;
+const CLIP_LEVEL: f32 = 0.999;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToneReport {
+    pub device: String,
+    pub played: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub input_device: String,
+    pub sample_rate: u32,
+    pub duration_ms: u64,
+    pub frames_received: u64,
+    pub frames_expected: u64,
+    /// Gaps between consecutive frame capture timestamps.
+    pub frame_interval_mean_ms: f64,
+    pub frame_interval_max_ms: f64,
+    pub seq_gaps: u64,
+    pub dropped_raw: u64,
+    pub dropped_processed: u64,
+    /// Processed (post-DSP) signal.
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+    pub clipped_samples: u64,
+    /// Highest UI level reported by the DSP (0–1, before the gate).
+    pub max_level: f32,
+    /// Share of frames the gate/PTT let through.
+    pub gate_open_pct: f32,
+    pub tone: Option<ToneReport>,
+    pub issues: Vec<String>,
+    pub passed: bool,
+}
+
+fn dbfs(x: f32) -> f32 {
+    20.0 * x.max(1e-6).log10()
+}
+
+/// Run the self-test on `device_id` (None = default input). With `play_tone`, a short
+/// tone is played on `output_device_id` (None = default output) after capturing.
+/// Refuses to run while capture is active, since it needs the pipeline to itself.
+pub fn run_selftest(
+    device_id: Option<String>,
+    play_tone: bool,
+    output_device_id: Option<String>,
+) -> Result<SelfTestReport, String> {
+    if audio_capture::is_capturing() {
+        return Err("Stop audio capture before running the self-test".to_string());
+    }
+    let input_device = resolve_device(AudioDeviceKind::Input, device_id.as_deref())?
+        .name()
+        .map_err(|e| format!("Failed to get device name: {}", e))?;
+
+    let (frame_tx, frame_rx) = mpsc::sync_channel(FRAME_QUEUE_CAP);
+    let (level_tx, level_rx) = mpsc::channel();
+    audio_capture::start_capture(
+        vec![device_id],
+        CaptureOptions::default(),
+        frame_tx,
+        level_tx,
+        Arc::new(|_| {}),
+        Arc::new(|_| {}),
+    )?;
+    let sample_rate = audio_capture::get_sample_rate().unwrap_or(0);
+
+    let started = Instant::now();
+    let deadline = started + CAPTURE_DURATION;
+    let mut frames_received = 0u64;
+    let mut seq_gaps = 0u64;
+    let mut next_seq = 0u64;
+    let mut last_ts: Option<u64> = None;
+    let (mut interval_sum, mut interval_max) = (0.0f64, 0.0f64);
+    let (mut peak, mut sum_sq, mut samples, mut clipped) = (0.0f32, 0.0f64, 0u64, 0u64);
+    let mut open_frames = 0u64;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(frame) = frame_rx.recv_timeout(remaining) else { break };
+        frames_received += 1;
+        seq_gaps += frame.seq.saturating_sub(next_seq);
+        next_seq = frame.seq + 1;
+        if let Some(last) = last_ts {
+            let interval = frame.capture_ts.saturating_sub(last) as f64 / 1000.0;
+            interval_sum += interval;
+            interval_max = interval_max.max(interval);
+        }
+        last_ts = Some(frame.capture_ts);
+        let mut frame_open = false;
+        for &s in &frame.pcm {
+            let a = s.abs();
+            peak = peak.max(a);
+            sum_sq += (s * s) as f64;
+            clipped += (a >= CLIP_LEVEL) as u64;
+            frame_open |= a > GATE_OPEN_PEAK;
+        }
+        samples += frame.pcm.len() as u64;
+        open_frames += frame_open as u64;
+        frame.recycle();
+    }
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let drops = audio_capture::get_audio_drop_stats();
+    audio_capture::stop_capture();
+    let max_level = level_rx.try_iter().fold(0.0f32, f32::max);
+
+    let tone = play_tone.then(|| {
+        let device = resolve_device(AudioDeviceKind::Output, output_device_id.as_deref())
+            .and_then(|d| d.name().map_err(|e| format!("Failed to get device name: {}", e)))
+            .unwrap_or_else(|_| "unknown".to_string());
+        let result = audio_output::play_test_tone(output_device_id.as_deref(), TONE_HZ, TONE_AMPLITUDE, TONE_DURATION);
+        ToneReport {
+            device,
+            played: result.is_ok(),
+            error: result.err(),
+        }
+    });
+
+    let frames_expected = (duration_ms as f64 / FRAME_MS) as u64;
+    let frame_interval_mean_ms = if frames_received > 1 {
+        interval_sum / (frames_received - 1) as f64
+    } else {
+        0.0
+    };
+    let rms = if samples > 0 { (sum_sq / samples as f64).sqrt() as f32 } else { 0.0 };
+    let gate_open_pct = if frames_received > 0 {
+        open_frames as f32 * 100.0 / frames_received as f32
+    } else {
+        0.0
+    };
+
+    let mut issues = Vec::new();
+    if frames_received == 0 {
+        issues.push("No audio frames arrived from the input device".to_string());
+    } else {
+        if frames_received * 10 < frames_expected * 9 {
+            issues.push(format!(
+                "Only {} of ~{} frames arrived; the device or driver is stalling",
+                frames_received, frames_expected
+            ));
+        }
+        if interval_max > FRAME_MS * 5.0 {
+            issues.push(format!("Irregular frame cadence: up to {:.0} ms between frames", interval_max));
+        }
+        if max_level == 0.0 {
+            issues.push("Input is silent: check the mic is unmuted and selected in the OS".to_string());
+        } else if max_level < LOW_LEVEL {
+            issues.push("Input level is very low: raise the input gain or move closer to the mic".to_string());
+        } else if open_frames == 0 {
+            issues.push("Mic picks up sound but the gate never opened (check threshold, push-to-talk or mute)".to_string());
+        }
+        if clipped > 0 {
+            issues.push(format!("{} clipped samples: lower the input gain", clipped));
+        }
+    }
+    if drops.dropped_raw > 0 || drops.dropped_processed > 0 {
+        issues.push(format!(
+            "Frames dropped in the pipeline (raw {}, processed {})",
+            drops.dropped_raw, drops.dropped_processed
+        ));
+    }
+    if let Some(error) = tone.as_ref().and_then(|t| t.error.as_ref()) {
+        issues.push(format!("Test tone failed: {}", error));
+    }
+
+    Ok(SelfTestReport {
+        input_device,
+        sample_rate,
+        duration_ms,
+        frames_received,
+        frames_expected,
+        frame_interval_mean_ms,
+        frame_interval_max_ms: interval_max,
+        seq_gaps,
+        dropped_raw: drops.dropped_raw,
+        dropped_processed: drops.dropped_processed,
+        peak_dbfs: dbfs(peak),
+        rms_dbfs: dbfs(rms),
+        clipped_samples: clipped,
+        max_level,
+        gate_open_pct,
+        tone,
+        passed: issues.is_empty(),
+        issues,
+    })
+}
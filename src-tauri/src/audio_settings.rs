@@ -1,4 +1,6 @@
+use crate::audio_dsp::{default_dsp_chain, CompressorSettings, DspConfig, DspStageConfig, EqBand, MeterSmoothing, MeterTuning};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::fs;
 use thiserror::Error;
@@ -20,15 +22,79 @@ pub struct AudioSettings {
     pub input_sensitivity: f32,  // 0.0 to 1.0 - voice activity threshold
     pub output_volume: f32, // 0.0 to 1.0
     #[serde(default = "default_input_mode")]
-    pub input_mode: String, // "voice_activity" or "push_to_talk"
+    pub input_mode: String, // "voice_activity", "level_gate", "push_to_talk", "hybrid" or "open_mic"
     #[serde(default)]
     pub push_to_talk_key: Option<String>, // Key binding for PTT
+    #[serde(default = "default_ptt_release_ms")]
+    pub ptt_release_ms: u32, // 0 to 500 - transmission tail after PTT release
+    #[serde(default)]
+    pub audio_host: Option<String>, // cpal host name ("ASIO", "JACK", ...); None = platform default
+    #[serde(default)]
+    pub noise_suppression: bool,
+    #[serde(default = "default_noise_suppression_strength")]
+    pub noise_suppression_strength: f32, // 0.0 to 1.0 - blend of denoised and original audio
+    #[serde(default = "default_denoiser")]
+    pub denoiser: String, // Noise suppression backend, e.g. "rnnoise"
+    #[serde(default)]
+    pub echo_cancellation: bool,
+    #[serde(default)]
+    pub auto_gain: bool,
+    #[serde(default = "default_auto_gain_target_db")]
+    pub auto_gain_target_db: f32, // dBFS RMS the AGC aims for (-40 to -6)
+    #[serde(default = "default_high_pass")]
+    pub high_pass: bool,
+    #[serde(default = "default_high_pass_hz")]
+    pub high_pass_hz: f32, // 20 to 300 - cutoff of the rumble filter
+    #[serde(default)]
+    pub compressor: bool,
+    #[serde(default)]
+    pub compressor_settings: CompressorSettings,
+    #[serde(default)]
+    pub clarity: bool,
+    #[serde(default = "default_clarity_strength")]
+    pub clarity_strength: f32, // 0.0 to 1.0 - presence boost + de-esser amount
+    #[serde(default)]
+    pub eq_bands: Vec<EqBand>, // Parametric EQ, up to 5 bands; empty = off
+    #[serde(default = "default_dsp_chain")]
+    pub dsp_chain: Vec<DspStageConfig>, // Stage order and bypass flags
+    #[serde(default)]
+    pub meter_tuning: MeterTuning, // Level meter/gate constants for unusual mics
+    #[serde(default)]
+    pub meter_smoothing: MeterSmoothing, // "peak", "ppm" or "vu"
 }
 
 fn default_input_mode() -> String {
     "voice_activity".to_string()
 }
 
+fn default_ptt_release_ms() -> u32 {
+    crate::audio_dsp::DEFAULT_PTT_RELEASE_MS
+}
+
+fn default_noise_suppression_strength() -> f32 {
+    1.0
+}
+
+fn default_denoiser() -> String {
+    crate::audio_denoise::DEFAULT_DENOISER.to_string()
+}
+
+fn default_auto_gain_target_db() -> f32 {
+    crate::audio_dsp::DEFAULT_AGC_TARGET_DB
+}
+
+fn default_clarity_strength() -> f32 {
+    crate::audio_dsp::DEFAULT_CLARITY_STRENGTH
+}
+
+fn default_high_pass() -> bool {
+    true
+}
+
+fn default_high_pass_hz() -> f32 {
+    crate::audio_dsp::DEFAULT_HIGH_PASS_HZ
+}
+
 impl Default for AudioSettings {
     fn default() -> Self {
         Self {
@@ -39,6 +105,24 @@ impl Default for AudioSettings {
             output_volume: 1.0,
             input_mode: "voice_activity".to_string(),
             push_to_talk_key: None,
+            ptt_release_ms: default_ptt_release_ms(),
+            audio_host: None,
+            noise_suppression: false,
+            noise_suppression_strength: default_noise_suppression_strength(),
+            denoiser: default_denoiser(),
+            echo_cancellation: false,
+            auto_gain: false,
+            auto_gain_target_db: default_auto_gain_target_db(),
+            high_pass: default_high_pass(),
+            high_pass_hz: default_high_pass_hz(),
+            compressor: false,
+            compressor_settings: CompressorSettings::default(),
+            clarity: false,
+            clarity_strength: default_clarity_strength(),
+            eq_bands: Vec::new(),
+            dsp_chain: default_dsp_chain(),
+            meter_tuning: MeterTuning::default(),
+            meter_smoothing: MeterSmoothing::default(),
         }
     }
 }
@@ -138,5 +222,42 @@ impl AudioSettingsManager {
         fs::write(settings_path, json)?;
         Ok(())
     }
+
+    fn get_presets_path(&self) -> PathBuf {
+        self.data_dir.join("dsp_presets.json")
+    }
+
+    /// Named DSP presets ("Headset", "Blue Yeti", ...), sorted by name.
+    pub fn load_presets(&self) -> Result<BTreeMap<String, DspConfig>, AudioSettingsError> {
+        let presets_path = self.get_presets_path();
+        if !presets_path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&presets_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save (or overwrite) a preset.
+    pub fn save_preset(&self, name: &str, config: &DspConfig) -> Result<(), AudioSettingsError> {
+        let mut presets = self.load_presets()?;
+        presets.insert(name.to_string(), config.clone());
+        self.write_presets(&presets)
+    }
+
+    /// Remove a preset. Returns whether it existed.
+    pub fn delete_preset(&self, name: &str) -> Result<bool, AudioSettingsError> {
+        let mut presets = self.load_presets()?;
+        let existed = presets.remove(name).is_some();
+        if existed {
+            self.write_presets(&presets)?;
+        }
+        Ok(existed)
+    }
+
+    fn write_presets(&self, presets: &BTreeMap<String, DspConfig>) -> Result<(), AudioSettingsError> {
+        let json = serde_json::to_string_pretty(presets)?;
+        fs::write(self.get_presets_path(), json)?;
+        Ok(())
+    }
 }
 
@@ -0,0 +1,201 @@
+//! Lightweight binaural spatialization for the output mixer.
+//!
+//! Not a measured HRTF: each voice gets the two cues that do most of the work for
+//! separating talkers on headphones — interaural time difference (Woodworth's spherical
+//! head model) and head shadow on the far ear (level drop plus a one-pole low-pass) —
+//! plus inverse-distance attenuation. Cheap enough to run per sample in the callback.
+//!
+//! Without spatial audio, mono voices can instead be widened into a plain stereo image
+//! (`Widener`).
+
+/// Head radius (m) and speed of sound (m/s) for the ITD model.
+const HEAD_RADIUS: f32 = 0.0875;
+const SPEED_OF_SOUND: f32 = 343.0;
+const SAMPLE_RATE: f32 = crate::audio_resample::PIPELINE_SAMPLE_RATE as f32;
+/// Delay line length; the largest ITD is ~0.66 ms ≈ 32 samples at 48 kHz.
+const MAX_DELAY: usize = 64;
+/// Far-ear level at 90° (about -6 dB).
+const MAX_SHADOW_GAIN_DROP: f32 = 0.5;
+/// Far-ear low-pass cutoff at 90°; straight ahead it's effectively open.
+const SHADOW_CUTOFF_HZ: f32 = 1500.0;
+const OPEN_CUTOFF_HZ: f32 = 20000.0;
+/// Sources behind the listener are mirrored to the front and slightly attenuated.
+const REAR_GAIN: f32 = 0.8;
+/// Widener delay (~12 ms): long enough to decorrelate, short enough not to read as echo.
+const WIDEN_DELAY: usize = 576;
+/// Side level at full width.
+const MAX_WIDEN_SIDE: f32 = 0.5;
+
+/// Per-position parameters, recomputed when a source moves (not per sample).
+#[derive(Debug, Clone, Copy)]
+pub struct SpatialParams {
+    /// Whole-sample delay applied to the far ear.
+    itd_samples: usize,
+    /// Far ear is the left one (source on the right).
+    far_is_left: bool,
+    far_gain: f32,
+    /// One-pole low-pass coefficient for the far ear.
+    far_lp: f32,
+    /// Distance/rear attenuation applied to both ears.
+    gain: f32,
+}
+
+impl SpatialParams {
+    /// `azimuth_deg`: 0 = ahead, +90 = right, -90 = left, ±180 = behind.
+    /// `distance`: metres from the listener; 1.0 or closer is unattenuated.
+    pub fn new(azimuth_deg: f32, distance: f32) -> Self {
+        let mut az = azimuth_deg.to_radians();
+        // Wrap to (-π, π], then mirror rear positions to the front (same ITD/ILD).
+        az = (az + std::f32::consts::PI).rem_euclid(2.0 * std::f32::consts::PI) - std::f32::consts::PI;
+        let rear = az.abs() > std::f32::consts::FRAC_PI_2;
+        if rear {
+            az = az.signum() * (std::f32::consts::PI - az.abs());
+        }
+        let lateral = az.abs();
+        let itd = HEAD_RADIUS / SPEED_OF_SOUND * (lateral + lateral.sin());
+        let shadow = lateral.sin();
+        let cutoff = OPEN_CUTOFF_HZ + (SHADOW_CUTOFF_HZ - OPEN_CUTOFF_HZ) * shadow;
+        Self {
+            itd_samples: ((itd * SAMPLE_RATE).round() as usize).min(MAX_DELAY - 1),
+            far_is_left: az > 0.0,
+            far_gain: 1.0 - MAX_SHADOW_GAIN_DROP * shadow,
+            far_lp: 1.0 - (-2.0 * std::f32::consts::PI * cutoff / SAMPLE_RATE).exp(),
+            gain: (if rear { REAR_GAIN } else { 1.0 }) / distance.max(1.0),
+        }
+    }
+}
+
+impl Default for SpatialParams {
+    fn default() -> Self {
+        Self::new(0.0, 1.0)
+    }
+}
+
+/// Per-source filter state. Lives in the mixer; never allocates after construction.
+pub struct Spatializer {
+    delay: [f32; MAX_DELAY],
+    write: usize,
+    lp_state: f32,
+}
+
+impl Spatializer {
+    pub fn new() -> Self {
+        Self {
+            delay: [0.0; MAX_DELAY],
+            write: 0,
+            lp_state: 0.0,
+        }
+    }
+
+    /// Render one mono sample to (left, right).
+    pub fn process(&mut self, sample: f32, params: &SpatialParams) -> (f32, f32) {
+        let sample = sample * params.gain;
+        self.delay[self.write] = sample;
+        let read = (self.write + MAX_DELAY - params.itd_samples) % MAX_DELAY;
+        self.write = (self.write + 1) % MAX_DELAY;
+
+        let delayed = self.delay[read];
+        self.lp_state += params.far_lp * (delayed - self.lp_state);
+        let far = self.lp_state * params.far_gain;
+        if params.far_is_left {
+            (far, sample)
+        } else {
+            (sample, far)
+        }
+    }
+}
+
+impl Default for Spatializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Mono → stereo upmix (Lauridsen): a delayed copy is added to one side and subtracted from
+/// the other. L + R is always twice the input, so the mix folds back to mono cleanly (and
+/// the echo canceller's mono reference is unaffected).
+pub struct Widener {
+    delay: [f32; WIDEN_DELAY],
+    pos: usize,
+}
+
+impl Widener {
+    pub fn new() -> Self {
+        Self { delay: [0.0; WIDEN_DELAY], pos: 0 }
+    }
+
+    /// Render one mono sample to (left, right). `width`: 0 = centred mono, 1 = widest.
+    pub fn process(&mut self, sample: f32, width: f32) -> (f32, f32) {
+        let side = self.delay[self.pos] * width * MAX_WIDEN_SIDE;
+        self.delay[self.pos] = sample;
+        self.pos = (self.pos + 1) % WIDEN_DELAY;
+        (sample + side, sample - side)
+    }
+}
+
+impl Default for Widener {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Evenly spread `count` talkers across the frontal arc (±`spread_deg`), in order.
+pub fn spread_azimuths(count: usize, spread_deg: f32) -> Vec<f32> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![0.0],
+        n => (0..n)
+            .map(|i| -spread_deg + 2.0 * spread_deg * i as f32 / (n - 1) as f32)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_on_the_right_is_louder_and_earlier_on_the_right() {
+        let params = SpatialParams::new(90.0, 1.0);
+        let mut spatializer = Spatializer::new();
+        let mut first_left = None;
+        let mut energy = (0.0f32, 0.0f32);
+        for i in 0..480 {
+            let (l, r) = spatializer.process(if i == 0 { 1.0 } else { 0.0 }, &params);
+            if first_left.is_none() && l.abs() > 1e-6 {
+                first_left = Some(i);
+            }
+            energy.0 += l * l;
+            energy.1 += r * r;
+        }
+        assert!(energy.1 > energy.0 * 2.0);
+        assert!(first_left.unwrap() >= 25, "ITD at 90° should be ~0.65 ms");
+    }
+
+    #[test]
+    fn straight_ahead_is_symmetric() {
+        let params = SpatialParams::new(0.0, 1.0);
+        let mut spatializer = Spatializer::new();
+        for i in 0..100 {
+            let s = (i as f32 * 0.3).sin();
+            let (l, r) = spatializer.process(s, &params);
+            assert!((l - r).abs() < 0.05);
+        }
+        assert_eq!(spread_azimuths(3, 60.0), vec![-60.0, 0.0, 60.0]);
+    }
+
+    #[test]
+    fn widener_is_mono_compatible() {
+        let mut centred = Widener::new();
+        let mut wide = Widener::new();
+        let mut differs = false;
+        for i in 0..2000 {
+            let s = (i as f32 * 0.07).sin() * 0.5;
+            assert_eq!(centred.process(s, 0.0), (s, s));
+            let (l, r) = wide.process(s, 1.0);
+            assert!((l + r - 2.0 * s).abs() < 1e-6);
+            differs |= (l - r).abs() > 0.1;
+        }
+        assert!(differs);
+    }
+}
@@ -0,0 +1,223 @@
+//! Low-rate spectrum analyzer on the processed mic stream, for the settings UI.
+//!
+//! The processing thread offers frames via [`tee_spectrum`] (same tap pattern as recording:
+//! an atomic fast path, `try_lock` on the producer, drop if full). An analyzer thread keeps
+//! a sliding 2048-sample window and, at the update rate, runs a Hann-windowed real FFT and
+//! sums the power into log-spaced bands.
+
+use crate::audio_resample::{FRAME_SAMPLES, PIPELINE_SAMPLE_RATE};
+use crate::audio_tap::tee_slots;
+use realfft::RealFftPlanner;
+use rtrb::{Producer, RingBuffer};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE: f32 = PIPELINE_SAMPLE_RATE as f32;
+/// 2048 points: ~23 Hz bins, enough to resolve the lowest bands.
+const FFT_SIZE: usize = 2048;
+/// ~200 ms of frames between the processing thread and the analyzer.
+const RING_FRAMES: usize = 20;
+const MIN_FREQ: f32 = 50.0;
+const MAX_FREQ: f32 = 16000.0;
+/// Band levels are clamped to this floor (dBFS).
+const FLOOR_DB: f32 = -100.0;
+
+pub const DEFAULT_BANDS: usize = 32;
+pub const DEFAULT_RATE_HZ: u32 = 15;
+
+/// One spectrum update, emitted as `cordia:audio-spectrum`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectrumFrame {
+    /// Band centre frequencies (Hz), low to high.
+    pub frequencies: Vec<f32>,
+    /// Band levels in dBFS (sine at full scale ≈ 0 dB).
+    pub levels_db: Vec<f32>,
+}
+
+struct SpectrumState {
+    stop: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+static SPECTRUM_STATE: Mutex<Option<SpectrumState>> = Mutex::new(None);
+static SPECTRUM_TAP: Mutex<Option<Producer<[f32; FRAME_SAMPLES]>>> = Mutex::new(None);
+static SPECTRUM_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Offer a processed frame to the analyzer. Cheap no-op when it isn't running.
+pub fn tee_spectrum(pcm: &[f32]) {
+    tee_slots(&SPECTRUM_ACTIVE, &SPECTRUM_TAP, pcm, |_, _, frame| frame);
+}
+
+/// Log-spaced band edges as FFT bin ranges; every band gets at least one bin.
+fn band_bins(bands: usize) -> Vec<(usize, usize, f32)> {
+    let bin_hz = SAMPLE_RATE / FFT_SIZE as f32;
+    let ratio = (MAX_FREQ / MIN_FREQ).powf(1.0 / bands as f32);
+    let mut out = Vec::with_capacity(bands);
+    let mut next_bin = (MIN_FREQ / bin_hz).floor() as usize;
+    for i in 0..bands {
+        let lo = MIN_FREQ * ratio.powi(i as i32);
+        let hi = lo * ratio;
+        let start = next_bin.max((lo / bin_hz).round() as usize);
+        let end = ((hi / bin_hz).round() as usize).max(start + 1).min(FFT_SIZE / 2);
+        out.push((start, end, (lo * hi).sqrt()));
+        next_bin = end;
+    }
+    out
+}
+
+/// Reusable FFT + window; `analyze` does no allocation.
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn realfft::RealToComplex<f32>>,
+    window: Vec<f32>,
+    input: Vec<f32>,
+    output: Vec<realfft::num_complex::Complex<f32>>,
+    bands: Vec<(usize, usize, f32)>,
+    /// Converts summed |X|² to power relative to a full-scale sine.
+    scale: f32,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(bands: usize) -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let window: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        // Parseval: a full-scale sine's one-sided spectrum sums to N·Σw²/4, so summing a
+        // band's bins (leakage included) reads 0 dB for it.
+        let window_power: f32 = window.iter().map(|w| w * w).sum();
+        Self {
+            input: fft.make_input_vec(),
+            output: fft.make_output_vec(),
+            fft,
+            window,
+            bands: band_bins(bands),
+            scale: 4.0 / (FFT_SIZE as f32 * window_power),
+        }
+    }
+
+    pub fn frequencies(&self) -> Vec<f32> {
+        self.bands.iter().map(|b| b.2).collect()
+    }
+
+    /// Band levels (dBFS) of the last `FFT_SIZE` samples, written into `levels_db`.
+    pub fn analyze(&mut self, samples: impl Iterator<Item = f32>, levels_db: &mut [f32]) {
+        for ((dst, s), w) in self.input.iter_mut().zip(samples).zip(&self.window) {
+            *dst = s * w;
+        }
+        if self.fft.process(&mut self.input, &mut self.output).is_err() {
+            return;
+        }
+        for (level, &(start, end, _)) in levels_db.iter_mut().zip(&self.bands) {
+            let power: f32 = self.output[start..end].iter().map(|c| c.norm_sqr()).sum();
+            *level = (10.0 * (power * self.scale).max(1e-12).log10()).max(FLOOR_DB);
+        }
+    }
+}
+
+/// Start the analyzer: `bands` log-spaced bands (8–128), `rate_hz` updates per second (1–60).
+/// `on_spectrum` runs on the analyzer thread.
+pub fn start_spectrum<F>(bands: usize, rate_hz: u32, on_spectrum: F) -> Result<(), String>
+where
+    F: Fn(SpectrumFrame) + Send + 'static,
+{
+    if !(8..=128).contains(&bands) {
+        return Err(format!("Spectrum band count out of range: {} (8-128)", bands));
+    }
+    if !(1..=60).contains(&rate_hz) {
+        return Err(format!("Spectrum update rate out of range: {} Hz (1-60)", rate_hz));
+    }
+    stop_spectrum();
+
+    let (producer, mut consumer) = RingBuffer::<[f32; FRAME_SAMPLES]>::new(RING_FRAMES);
+    {
+        let mut tap = SPECTRUM_TAP
+            .lock()
+            .map_err(|_| "Failed to lock spectrum tap".to_string())?;
+        *tap = Some(producer);
+    }
+    SPECTRUM_ACTIVE.store(true, Ordering::Relaxed);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    let interval = Duration::from_secs_f32(1.0 / rate_hz as f32);
+    let thread = thread::spawn(move || {
+        let mut analyzer = SpectrumAnalyzer::new(bands);
+        let frequencies = analyzer.frequencies();
+        let mut window: VecDeque<f32> = std::iter::repeat_n(0.0, FFT_SIZE).collect();
+        let mut next_update = Instant::now() + interval;
+        let mut fresh = false;
+        while !stop_flag.load(Ordering::Relaxed) {
+            while let Ok(frame) = consumer.pop() {
+                window.drain(..FRAME_SAMPLES);
+                window.extend(frame);
+                fresh = true;
+            }
+            let now = Instant::now();
+            if now >= next_update {
+                next_update = now + interval;
+                // Nothing arrived (capture stopped or muted upstream): skip rather than
+                // repeat a stale spectrum.
+                if fresh {
+                    fresh = false;
+                    let mut levels_db = vec![FLOOR_DB; bands];
+                    analyzer.analyze(window.iter().copied(), &mut levels_db);
+                    on_spectrum(SpectrumFrame {
+                        frequencies: frequencies.clone(),
+                        levels_db,
+                    });
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+
+    let mut state = SPECTRUM_STATE
+        .lock()
+        .map_err(|_| "Failed to lock spectrum state".to_string())?;
+    *state = Some(SpectrumState { stop, thread });
+    Ok(())
+}
+
+pub fn stop_spectrum() {
+    SPECTRUM_ACTIVE.store(false, Ordering::Relaxed);
+    let state = match SPECTRUM_STATE.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return,
+    };
+    if let Some(state) = state {
+        state.stop.store(true, Ordering::SeqCst);
+        let _ = state.thread.join();
+    }
+    if let Ok(mut tap) = SPECTRUM_TAP.lock() {
+        tap.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale_sine_peaks_near_zero_db_in_its_band() {
+        let mut analyzer = SpectrumAnalyzer::new(DEFAULT_BANDS);
+        let freq = 1000.0;
+        let samples = (0..FFT_SIZE).map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin());
+        let mut levels = vec![0.0; DEFAULT_BANDS];
+        analyzer.analyze(samples, &mut levels);
+
+        let (peak_band, peak_db) = levels
+            .iter()
+            .enumerate()
+            .fold((0, f32::MIN), |acc, (i, &l)| if l > acc.1 { (i, l) } else { acc });
+        let bands = band_bins(DEFAULT_BANDS);
+        let (start, end, _) = bands[peak_band];
+        let bin_hz = SAMPLE_RATE / FFT_SIZE as f32;
+        assert!((start as f32 * bin_hz..end as f32 * bin_hz + bin_hz).contains(&freq));
+        assert!(peak_db.abs() < 1.0, "peak {} dB", peak_db);
+        assert!(levels[0] < -60.0);
+    }
+}
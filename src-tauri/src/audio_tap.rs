@@ -0,0 +1,70 @@
+//! Non-blocking taps that copy pipeline frames into SPSC rings for side consumers
+//! (recording, sidetone, spectrum, calibration, multitrack).
+//!
+//! Capture frames may be 10, 20 or 40 ms; every tap ring carries 10 ms slots, so frames
+//! are split here (a short tail is zero-padded).
+
+use crate::audio_resample::FRAME_SAMPLES;
+use rtrb::Producer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Push `pcm` into the tap's ring as 10 ms slots. Cheap no-op when `active` is clear; never
+/// blocks (skips the frame while the tap is being swapped). `slot` builds the ring item from
+/// the slot index, slot count and samples. Returns how many slots were dropped on a full ring.
+pub fn tee_slots<T>(
+    active: &AtomicBool,
+    tap: &Mutex<Option<Producer<T>>>,
+    pcm: &[f32],
+    mut slot: impl FnMut(usize, usize, [f32; FRAME_SAMPLES]) -> T,
+) -> u64 {
+    if !active.load(Ordering::Relaxed) {
+        return 0;
+    }
+    let Ok(mut tap) = tap.try_lock() else {
+        return 0;
+    };
+    let Some(producer) = tap.as_mut() else {
+        return 0;
+    };
+    let count = pcm.len().div_ceil(FRAME_SAMPLES);
+    let mut dropped = 0;
+    for (i, chunk) in pcm.chunks(FRAME_SAMPLES).enumerate() {
+        let mut samples = [0.0f32; FRAME_SAMPLES];
+        samples[..chunk.len()].copy_from_slice(chunk);
+        if producer.push(slot(i, count, samples)).is_err() {
+            dropped += 1;
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtrb::RingBuffer;
+
+    #[test]
+    fn splits_frames_into_padded_slots_and_counts_drops() {
+        let (producer, mut consumer) = RingBuffer::<(usize, usize, [f32; FRAME_SAMPLES])>::new(2);
+        let tap = Mutex::new(Some(producer));
+        let active = AtomicBool::new(false);
+        let pcm = vec![1.0f32; FRAME_SAMPLES * 2 + 10];
+
+        assert_eq!(tee_slots(&active, &tap, &pcm, |i, n, s| (i, n, s)), 0);
+        assert!(consumer.pop().is_err());
+
+        active.store(true, Ordering::Relaxed);
+        assert_eq!(tee_slots(&active, &tap, &pcm, |i, n, s| (i, n, s)), 1);
+        let (i, n, first) = consumer.pop().unwrap();
+        assert_eq!((i, n), (0, 3));
+        assert!(first.iter().all(|&s| s == 1.0));
+        assert_eq!(consumer.pop().unwrap().0, 1);
+
+        assert_eq!(tee_slots(&active, &tap, &pcm[FRAME_SAMPLES * 2..], |i, n, s| (i, n, s)), 0);
+        let (_, n, tail) = consumer.pop().unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(tail[9], 1.0);
+        assert_eq!(tail[10], 0.0);
+    }
+}
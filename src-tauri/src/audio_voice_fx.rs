@@ -0,0 +1,213 @@
+//! Voice changer for the DSP's voice effect stage (`fun-effects` feature).
+//!
+//! A phase vocoder: each STFT frame is split into a smoothed spectral envelope (the
+//! formants) and the excitation under it (the harmonics). Pitch shift moves the excitation,
+//! formant shift stretches the envelope, so the two can be set independently — pitch alone
+//! keeps the speaker's timbre, formant alone makes a voice sound bigger or smaller.
+//! Adds `FFT_SIZE - HOP` samples (16 ms) of latency.
+
+use crate::audio_dsp::VoiceEffect;
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::f32::consts::{PI, TAU};
+use std::sync::Arc;
+
+const FFT_SIZE: usize = 1024;
+const HOP: usize = FFT_SIZE / 4;
+const BINS: usize = FFT_SIZE / 2 + 1;
+const LATENCY: usize = FFT_SIZE - HOP;
+/// Envelope smoothing half-width in bins (~190 Hz): wide enough to bridge the harmonics of
+/// a low voice, narrow enough to keep the formants.
+const ENVELOPE_HALF_WIDTH: usize = 4;
+/// Periodic Hann analysis and synthesis windows at 75% overlap sum to 1.5.
+const WINDOW_GAIN: f32 = 1.5;
+
+pub struct VoiceChanger {
+    effect: VoiceEffect,
+    pitch_ratio: f32,
+    formant_ratio: f32,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    window: Vec<f32>,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    overlap: Vec<f32>,
+    rover: usize,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    last_phase: Vec<f32>,
+    sum_phase: Vec<f32>,
+    magnitude: Vec<f32>,
+    frequency: Vec<f32>,
+    envelope: Vec<f32>,
+    synth_magnitude: Vec<f32>,
+    synth_frequency: Vec<f32>,
+}
+
+impl VoiceChanger {
+    pub fn new(effect: VoiceEffect) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let mut changer = Self {
+            effect,
+            pitch_ratio: 1.0,
+            formant_ratio: 1.0,
+            fft: planner.plan_fft_forward(FFT_SIZE),
+            ifft: planner.plan_fft_inverse(FFT_SIZE),
+            window: (0..FFT_SIZE)
+                .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+                .collect(),
+            input: vec![0.0; FFT_SIZE],
+            output: vec![0.0; FFT_SIZE],
+            overlap: vec![0.0; FFT_SIZE],
+            rover: LATENCY,
+            time: vec![0.0; FFT_SIZE],
+            spectrum: vec![Complex::new(0.0, 0.0); BINS],
+            last_phase: vec![0.0; BINS],
+            sum_phase: vec![0.0; BINS],
+            magnitude: vec![0.0; BINS],
+            frequency: vec![0.0; BINS],
+            envelope: vec![0.0; BINS],
+            synth_magnitude: vec![0.0; BINS],
+            synth_frequency: vec![0.0; BINS],
+        };
+        changer.set_effect(effect);
+        changer
+    }
+
+    pub fn effect(&self) -> VoiceEffect {
+        self.effect
+    }
+
+    pub fn set_effect(&mut self, effect: VoiceEffect) {
+        self.effect = effect;
+        self.pitch_ratio = 2f32.powf(effect.pitch_semitones / 12.0);
+        self.formant_ratio = 2f32.powf(effect.formant_semitones / 12.0);
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            self.input[self.rover] = *sample;
+            *sample = self.output[self.rover - LATENCY];
+            self.rover += 1;
+            if self.rover == FFT_SIZE {
+                self.rover = LATENCY;
+                self.process_frame();
+                self.output[..HOP].copy_from_slice(&self.overlap[..HOP]);
+                self.overlap.copy_within(HOP.., 0);
+                self.overlap[FFT_SIZE - HOP..].fill(0.0);
+                self.input.copy_within(HOP.., 0);
+            }
+        }
+    }
+
+    fn process_frame(&mut self) {
+        let oversampling = (FFT_SIZE / HOP) as f32;
+        let expected = TAU * HOP as f32 / FFT_SIZE as f32;
+
+        for ((t, &x), &w) in self.time.iter_mut().zip(&self.input).zip(&self.window) {
+            *t = x * w;
+        }
+        let _ = self.fft.process(&mut self.time, &mut self.spectrum);
+
+        // Analysis: magnitude and true frequency (in bins) of every bin.
+        for k in 0..BINS {
+            let bin = self.spectrum[k];
+            let phase = bin.arg();
+            let mut delta = phase - self.last_phase[k] - k as f32 * expected;
+            self.last_phase[k] = phase;
+            delta -= TAU * (delta / TAU).round();
+            self.magnitude[k] = bin.norm();
+            self.frequency[k] = k as f32 + delta * oversampling / TAU;
+        }
+        self.smooth_envelope();
+
+        // Move the excitation by the pitch ratio...
+        self.synth_magnitude.fill(0.0);
+        self.synth_frequency.fill(0.0);
+        for k in 0..BINS {
+            let target = (k as f32 * self.pitch_ratio).round() as usize;
+            if target < BINS {
+                self.synth_magnitude[target] += self.magnitude[k] / self.envelope[k];
+                self.synth_frequency[target] = self.frequency[k] * self.pitch_ratio;
+            }
+        }
+        // ...and put it under the envelope stretched by the formant ratio.
+        for (j, magnitude) in self.synth_magnitude.iter_mut().enumerate() {
+            let source = j as f32 / self.formant_ratio;
+            let i = source as usize;
+            *magnitude *= if i + 1 < BINS {
+                let frac = source - i as f32;
+                self.envelope[i] * (1.0 - frac) + self.envelope[i + 1] * frac
+            } else {
+                0.0
+            };
+        }
+
+        // Synthesis: accumulate each bin's phase at its new frequency.
+        for j in 0..BINS {
+            let deviation = self.synth_frequency[j] - j as f32;
+            self.sum_phase[j] += TAU * deviation / oversampling + j as f32 * expected;
+            self.sum_phase[j] = (self.sum_phase[j] + PI).rem_euclid(TAU) - PI;
+            self.spectrum[j] = Complex::from_polar(self.synth_magnitude[j], self.sum_phase[j]);
+        }
+        self.spectrum[0].im = 0.0;
+        self.spectrum[BINS - 1].im = 0.0;
+        let _ = self.ifft.process(&mut self.spectrum, &mut self.time);
+
+        let scale = 1.0 / (FFT_SIZE as f32 * WINDOW_GAIN);
+        for ((acc, &t), &w) in self.overlap.iter_mut().zip(&self.time).zip(&self.window) {
+            *acc += t * w * scale;
+        }
+    }
+
+    /// Moving average of the magnitudes across bins; never zero, since it divides.
+    fn smooth_envelope(&mut self) {
+        let mut sum: f32 = self.magnitude[..ENVELOPE_HALF_WIDTH].iter().sum();
+        for k in 0..BINS {
+            if k + ENVELOPE_HALF_WIDTH < BINS {
+                sum += self.magnitude[k + ENVELOPE_HALF_WIDTH];
+            }
+            if k > ENVELOPE_HALF_WIDTH {
+                sum -= self.magnitude[k - ENVELOPE_HALF_WIDTH - 1];
+            }
+            let lo = k.saturating_sub(ENVELOPE_HALF_WIDTH);
+            let hi = (k + ENVELOPE_HALF_WIDTH).min(BINS - 1);
+            self.envelope[k] = sum.max(0.0) / (hi - lo + 1) as f32 + 1e-9;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dominant frequency of a steady tone from its zero crossings.
+    fn frequency(samples: &[f32]) -> f32 {
+        let crossings = samples.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        crossings as f32 * 48000.0 / samples.len() as f32
+    }
+
+    fn run(effect: VoiceEffect, hz: f32) -> Vec<f32> {
+        let mut changer = VoiceChanger::new(effect);
+        let mut samples: Vec<f32> = (0..48000)
+            .map(|i| 0.3 * (TAU * hz * i as f32 / 48000.0).sin())
+            .collect();
+        for frame in samples.chunks_mut(480) {
+            changer.process(frame);
+        }
+        samples.split_off(9600)
+    }
+
+    #[test]
+    fn pitch_shift_moves_the_tone_and_formant_shift_does_not() {
+        let octave_up = run(VoiceEffect { pitch_semitones: 12.0, formant_semitones: 0.0 }, 220.0);
+        assert!((frequency(&octave_up) - 440.0).abs() < 15.0, "{}", frequency(&octave_up));
+
+        let formant_only = run(VoiceEffect { pitch_semitones: 0.0, formant_semitones: 5.0 }, 220.0);
+        assert!((frequency(&formant_only) - 220.0).abs() < 10.0, "{}", frequency(&formant_only));
+
+        let unchanged = run(VoiceEffect::default(), 220.0);
+        let level = unchanged.iter().fold(0.0f32, |a, s| a.max(s.abs()));
+        assert!((level - 0.3).abs() < 0.03, "level {}", level);
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -15,13 +15,60 @@ pub enum BeaconError {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum BeaconStatus {
     Connected,
+    /// Reachable but failing intermittently or slow.
+    Degraded,
     Disconnected,
     Checking,
 }
 
-/// Check if beacon is available at the given URL
-pub async fn check_beacon_health(url: &str) -> Result<bool, BeaconError> {
-    let timeout = Duration::from_secs(5);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BeaconLatency {
+    pub samples: usize,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub p95_ms: f64,
+}
+
+/// Newest signaling protocol this client speaks (see the beacon's `PROTOCOL_VERSION`).
+pub const CLIENT_PROTOCOL_VERSION: u32 = crate::protocol::SUPPORTED_VERSIONS[0];
+
+/// Limits the beacon enforces; 0 means unlimited. Absent on beacons without `/api/info`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BeaconLimits {
+    #[serde(default)]
+    pub max_body_bytes: u64,
+    #[serde(default)]
+    pub max_ws_connections: u32,
+    #[serde(default)]
+    pub max_ws_per_ip: u32,
+    #[serde(default)]
+    pub rate_limit_rest_per_min: u32,
+    #[serde(default)]
+    pub rate_limit_ws_per_min: u32,
+}
+
+/// What a beacon supports, from `/api/info`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BeaconInfo {
+    #[serde(default)]
+    pub version: Option<String>,
+    /// 0 for beacons that predate `/api/info`; they speak protocol 1.
+    #[serde(default)]
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub min_protocol_version: u32,
+    /// e.g. "voice", "friends", "swarm", "postgres", "redis".
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub limits: Option<BeaconLimits>,
+    /// Whether this client and the beacon share a protocol version (filled in locally).
+    #[serde(default)]
+    pub compatible: bool,
+}
+
+/// Base http(s):// URL for a ws:// or wss:// beacon URL
+fn http_base_url(url: &str) -> Result<String, BeaconError> {
     let url = url.trim();
 
     // Parse the URL to validate it
@@ -39,26 +86,184 @@ pub async fn check_beacon_health(url: &str) -> Result<bool, BeaconError> {
         url.replace("ws://", "http://")
     };
 
-    let health_url = format!("{}/health", http_url.trim_end_matches('/'));
-
-    let client = reqwest::Client::builder()
-        .timeout(timeout)
-        .build()
-        .map_err(|e| BeaconError::ConnectionFailed(e.to_string()))?;
-
-    match client.get(&health_url).send().await {
-        Ok(response) => {
-            if response.status().is_success() {
-                Ok(true)
-            } else {
-                Err(BeaconError::ConnectionFailed(
-                    format!("HTTP {} from health endpoint", response.status())
-                ))
-            }
+    Ok(http_url.trim_end_matches('/').to_string())
+}
+
+/// WebSocket endpoint (`.../ws`) for a ws:// or wss:// beacon URL
+pub fn websocket_url(url: &str) -> Result<String, BeaconError> {
+    let url = url.trim().trim_end_matches('/');
+    if !url.starts_with("ws://") && !url.starts_with("wss://") {
+        return Err(BeaconError::InvalidUrl(
+            "URL must start with ws:// or wss://".to_string()
+        ));
+    }
+    Ok(if url.ends_with("/ws") { url.to_string() } else { format!("{}/ws", url) })
+}
+
+/// Health endpoint for a ws:// or wss:// beacon URL
+fn health_url(url: &str) -> Result<String, BeaconError> {
+    Ok(format!("{}/health", http_base_url(url)?))
+}
+
+/// Limit on each HTTP request to the beacon's health and info endpoints.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Send `request` to the beacon at `url` (pin checked before sending), within `REQUEST_TIMEOUT`.
+async fn send(url: &str, request: reqwest::RequestBuilder) -> Result<reqwest::Response, BeaconError> {
+    match tokio::time::timeout(REQUEST_TIMEOUT, crate::beacon_tls::send(url, request)).await {
+        Ok(response) => response.map_err(BeaconError::ConnectionFailed),
+        Err(_) => Err(BeaconError::Timeout),
+    }
+}
+
+/// Check if beacon is available at the given URL
+pub async fn check_beacon_health(url: &str) -> Result<bool, BeaconError> {
+    let health_url = health_url(url)?;
+    let client = crate::beacon_tls::http_client(url).map_err(BeaconError::ConnectionFailed)?;
+
+    let response = send(url, client.get(&health_url)).await?;
+    if response.status().is_success() {
+        Ok(true)
+    } else {
+        Err(BeaconError::ConnectionFailed(
+            format!("HTTP {} from health endpoint", response.status())
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BeaconProbe {
+    /// HTTP `GET /health`.
+    pub http_ok: bool,
+    pub http_error: Option<String>,
+    pub http_ms: Option<f64>,
+    /// WebSocket upgrade on `/ws` plus a `Ping` answered by the beacon.
+    pub ws_ok: bool,
+    pub ws_error: Option<String>,
+    pub ws_ms: Option<f64>,
+}
+
+/// Probe both the HTTP health endpoint and the WebSocket path. The health endpoint can pass
+/// while upgrades fail (a proxy or load balancer that drops `Upgrade`), which only the
+/// second stage catches.
+pub async fn probe_beacon(url: &str) -> Result<BeaconProbe, BeaconError> {
+    let ws_url = websocket_url(url)?;
+
+    let start = Instant::now();
+    let http = check_beacon_health(url).await;
+    let http_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let start = Instant::now();
+    let ws = match tokio::time::timeout(Duration::from_secs(5), probe_websocket(&ws_url)).await {
+        Ok(result) => result,
+        Err(_) => Err("Timed out waiting for the beacon".to_string()),
+    };
+    let ws_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    Ok(BeaconProbe {
+        http_ok: http.is_ok(),
+        http_ms: http.is_ok().then_some(http_ms),
+        http_error: http.err().map(|e| e.to_string()),
+        ws_ok: ws.is_ok(),
+        ws_ms: ws.is_ok().then_some(ws_ms),
+        ws_error: ws.err(),
+    })
+}
+
+/// Open the signaling socket, send a `Ping`, wait for the beacon's first message, close.
+async fn probe_websocket(ws_url: &str) -> Result<(), String> {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let mut socket = crate::proxy::connect_websocket(ws_url).await?;
+    socket
+        .send(Message::Text(r#"{"type":"Ping"}"#.to_string()))
+        .await
+        .map_err(|e| format!("Failed to send: {}", e))?;
+    let result = loop {
+        match socket.next().await {
+            Some(Ok(Message::Text(_))) => break Ok(()),
+            Some(Ok(Message::Close(_))) | None => break Err("Beacon closed the connection".to_string()),
+            Some(Ok(_)) => {}
+            Some(Err(e)) => break Err(format!("Connection error: {}", e)),
+        }
+    };
+    let _ = socket.close(None).await;
+    result
+}
+
+/// Version, protocol range, features and limits of the beacon at `url`. Beacons without
+/// `/api/info` are reported as protocol 1 with no feature list once their health check passes.
+pub async fn get_beacon_info(url: &str) -> Result<BeaconInfo, BeaconError> {
+    let info_url = format!("{}/api/info", http_base_url(url)?);
+    let client = crate::beacon_tls::http_client(url).map_err(BeaconError::ConnectionFailed)?;
+    let response = send(url, client.get(&info_url)).await?;
+
+    let mut info = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        check_beacon_health(url).await?;
+        BeaconInfo {
+            version: None,
+            protocol_version: 0,
+            min_protocol_version: 0,
+            features: Vec::new(),
+            limits: None,
+            compatible: false,
         }
-        Err(e) => {
-            Err(BeaconError::ConnectionFailed(e.to_string()))
+    } else if response.status().is_success() {
+        response
+            .json::<BeaconInfo>()
+            .await
+            .map_err(|e| BeaconError::ConnectionFailed(format!("Invalid info response: {}", e)))?
+    } else {
+        return Err(BeaconError::ConnectionFailed(
+            format!("HTTP {} from info endpoint", response.status())
+        ));
+    };
+    info.compatible = is_compatible(&info);
+    Ok(info)
+}
+
+/// The beacon still accepts this client's protocol. Every beacon speaks at least protocol 1,
+/// which is all this client needs.
+fn is_compatible(info: &BeaconInfo) -> bool {
+    info.min_protocol_version <= CLIENT_PROTOCOL_VERSION
+}
+
+/// Time `samples` HEAD round trips to the health endpoint.
+/// One untimed request goes first so DNS, TCP and TLS setup don't count as latency.
+pub async fn measure_beacon_latency(url: &str, samples: usize) -> Result<BeaconLatency, BeaconError> {
+    let health_url = health_url(url)?;
+    let samples = samples.clamp(1, 20);
+
+    let client = crate::beacon_tls::http_client(url).map_err(BeaconError::ConnectionFailed)?;
+
+    let round_trip = || async {
+        let start = Instant::now();
+        let response = send(url, client.head(&health_url)).await?;
+        if !response.status().is_success() {
+            return Err(BeaconError::ConnectionFailed(
+                format!("HTTP {} from health endpoint", response.status())
+            ));
         }
+        Ok(start.elapsed().as_secs_f64() * 1000.0)
+    };
+
+    round_trip().await?;
+    let mut times = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        times.push(round_trip().await?);
+    }
+    Ok(latency_stats(times))
+}
+
+fn latency_stats(mut times: Vec<f64>) -> BeaconLatency {
+    times.sort_by(|a, b| a.total_cmp(b));
+    let p95_index = ((times.len() as f64 * 0.95).ceil() as usize).saturating_sub(1);
+    BeaconLatency {
+        samples: times.len(),
+        min_ms: times[0],
+        avg_ms: times.iter().sum::<f64>() / times.len() as f64,
+        p95_ms: times[p95_index],
     }
 }
 
@@ -67,3 +272,30 @@ pub fn get_default_beacon_url() -> String {
     // Default public beacon for end users (Discord-like out-of-box behavior)
     "wss://beacon.pkcollection.net".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beacon_info_parses_and_checks_protocol_range() {
+        let mut info: BeaconInfo = serde_json::from_str(
+            r#"{"name":"cordia-beacon","version":"0.1.0","protocol_version":1,"min_protocol_version":1,
+                "features":["voice","redis"],"limits":{"max_body_bytes":1000000}}"#,
+        )
+        .unwrap();
+        assert!(is_compatible(&info));
+        assert_eq!(info.limits.as_ref().unwrap().max_body_bytes, 1_000_000);
+        info.min_protocol_version = CLIENT_PROTOCOL_VERSION + 1;
+        assert!(!is_compatible(&info));
+    }
+
+    #[test]
+    fn latency_stats_take_min_mean_and_p95() {
+        let stats = latency_stats((1..=20).rev().map(f64::from).collect());
+        assert_eq!(stats.samples, 20);
+        assert_eq!(stats.min_ms, 1.0);
+        assert_eq!(stats.avg_ms, 10.5);
+        assert_eq!(stats.p95_ms, 19.0);
+    }
+}
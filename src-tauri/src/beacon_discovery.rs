@@ -0,0 +1,178 @@
+//! LAN beacon discovery over mDNS / DNS-SD (`_cordia-beacon._tcp`).
+//!
+//! Browsing lists beacons announced on the local network so a LAN party can pick one
+//! instead of typing an IP; results arrive as `cordia:beacon-discovered` and
+//! `cordia:beacon-lost`. Advertising announces a beacon hosted on this machine. Both share
+//! one mDNS daemon, created on first use.
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub const SERVICE_TYPE: &str = "_cordia-beacon._tcp.local.";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredBeacon {
+    /// Instance name, e.g. "Alex's beacon".
+    pub name: String,
+    /// DNS-SD full name; identifies the beacon in `cordia:beacon-lost`.
+    pub fullname: String,
+    /// ws:// URL to connect to, preferring IPv4.
+    pub url: String,
+    pub addresses: Vec<String>,
+    pub port: u16,
+    pub version: Option<String>,
+}
+
+static DAEMON: Mutex<Option<ServiceDaemon>> = Mutex::new(None);
+static BEACONS: Mutex<Vec<DiscoveredBeacon>> = Mutex::new(Vec::new());
+static BROWSING: AtomicBool = AtomicBool::new(false);
+/// Full name of the service this app is advertising.
+static ADVERTISED: Mutex<Option<String>> = Mutex::new(None);
+
+fn daemon() -> Result<ServiceDaemon, String> {
+    let mut daemon = DAEMON
+        .lock()
+        .map_err(|_| "Failed to lock mDNS daemon".to_string())?;
+    if let Some(daemon) = daemon.as_ref() {
+        // A handle to the daemon thread; clones talk to the same daemon.
+        return Ok(daemon.clone());
+    }
+    let started = ServiceDaemon::new().map_err(|e| format!("Failed to start mDNS: {}", e))?;
+    *daemon = Some(started.clone());
+    Ok(started)
+}
+
+/// Start browsing for beacons; a no-op if already browsing.
+pub fn start_discovery(app: AppHandle) -> Result<(), String> {
+    if BROWSING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let receiver = match daemon().and_then(|d| {
+        d.browse(SERVICE_TYPE)
+            .map_err(|e| format!("Failed to browse for beacons: {}", e))
+    }) {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            BROWSING.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
+
+    std::thread::spawn(move || {
+        while let Ok(event) = receiver.recv() {
+            match event {
+                ServiceEvent::ServiceResolved(info) => {
+                    let Some(beacon) = to_beacon(&info) else { continue };
+                    if let Ok(mut beacons) = BEACONS.lock() {
+                        beacons.retain(|b| b.fullname != beacon.fullname);
+                        beacons.push(beacon.clone());
+                    }
+                    let _ = app.emit_all("cordia:beacon-discovered", beacon);
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => {
+                    if let Ok(mut beacons) = BEACONS.lock() {
+                        beacons.retain(|b| b.fullname != fullname);
+                    }
+                    let _ = app.emit_all("cordia:beacon-lost", fullname);
+                }
+                ServiceEvent::SearchStopped(_) => break,
+                _ => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+pub fn stop_discovery() {
+    if !BROWSING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Ok(daemon) = daemon() {
+        let _ = daemon.stop_browse(SERVICE_TYPE);
+    }
+    if let Ok(mut beacons) = BEACONS.lock() {
+        beacons.clear();
+    }
+}
+
+/// Beacons found since discovery started and not announced as gone.
+pub fn discovered_beacons() -> Vec<DiscoveredBeacon> {
+    BEACONS.lock().map(|b| b.clone()).unwrap_or_default()
+}
+
+/// Announce a beacon listening on `port` of this machine, replacing any earlier
+/// announcement. Addresses follow the host's interfaces as they change.
+pub fn advertise(name: &str, port: u16) -> Result<(), String> {
+    stop_advertising();
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let host = format!("cordia-{}.local.", &id[..8]);
+    let properties = [("path", "/ws"), ("version", env!("CARGO_PKG_VERSION"))];
+    let info = ServiceInfo::new(SERVICE_TYPE, name, &host, (), port, &properties[..])
+        .map_err(|e| format!("Invalid beacon announcement: {}", e))?
+        .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon()?
+        .register(info)
+        .map_err(|e| format!("Failed to advertise beacon: {}", e))?;
+    let mut advertised = ADVERTISED
+        .lock()
+        .map_err(|_| "Failed to lock mDNS advertisement".to_string())?;
+    *advertised = Some(fullname);
+    Ok(())
+}
+
+pub fn stop_advertising() {
+    let fullname = ADVERTISED.lock().ok().and_then(|mut a| a.take());
+    if let (Some(fullname), Ok(daemon)) = (fullname, daemon()) {
+        let _ = daemon.unregister(&fullname);
+    }
+}
+
+fn to_beacon(info: &ServiceInfo) -> Option<DiscoveredBeacon> {
+    let addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+    let path = info.get_property_val_str("path").unwrap_or("/ws");
+    let url = beacon_url(&addresses, info.get_port(), path)?;
+    let fullname = info.get_fullname().to_string();
+    Some(DiscoveredBeacon {
+        name: fullname.strip_suffix(&format!(".{}", SERVICE_TYPE)).unwrap_or(&fullname).to_string(),
+        fullname,
+        url,
+        addresses: addresses.iter().map(|a| a.to_string()).collect(),
+        port: info.get_port(),
+        version: info.get_property_val_str("version").map(str::to_string),
+    })
+}
+
+/// ws:// URL for the first usable address, IPv4 first (link-local IPv6 needs a scope id
+/// that URLs can't carry reliably).
+fn beacon_url(addresses: &[IpAddr], port: u16, path: &str) -> Option<String> {
+    let path = if path.starts_with('/') { path.to_string() } else { format!("/{}", path) };
+    let v4 = addresses.iter().find(|a| a.is_ipv4());
+    let v6 = addresses.iter().find(|a| match a {
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) != 0xfe80,
+        IpAddr::V4(_) => false,
+    });
+    match v4.or(v6)? {
+        IpAddr::V4(ip) => Some(format!("ws://{}:{}{}", ip, port, path)),
+        IpAddr::V6(ip) => Some(format!("ws://[{}]:{}{}", ip, port, path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_urls_preferring_ipv4_and_skipping_link_local() {
+        let v4: IpAddr = "192.168.1.20".parse().unwrap();
+        let link_local: IpAddr = "fe80::1".parse().unwrap();
+        let global: IpAddr = "2001:db8::5".parse().unwrap();
+        assert_eq!(beacon_url(&[link_local, v4], 9001, "/ws").as_deref(), Some("ws://192.168.1.20:9001/ws"));
+        assert_eq!(beacon_url(&[link_local, global], 9001, "ws").as_deref(), Some("ws://[2001:db8::5]:9001/ws"));
+        assert_eq!(beacon_url(&[link_local], 9001, "/ws"), None);
+    }
+}
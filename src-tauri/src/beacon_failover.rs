@@ -0,0 +1,182 @@
+//! Multi-beacon failover for the native signaling connection.
+//!
+//! Given an ordered list of beacon URLs, the manager health-checks all of them every
+//! `CHECK_INTERVAL`, keeps the signaling client on the first healthy one, and moves it when
+//! the active beacon stops passing checks (or signaling can't reach it). Once a beacon
+//! earlier in the list has been healthy for `FAILBACK_CHECKS` rounds in a row it moves back.
+//! Each round is reported on `cordia:beacon-failover`.
+
+use futures_util::future::join_all;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::beacon::check_beacon_health;
+use crate::signaling::{self, SignalingState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Consecutive healthy rounds before switching back to a preferred beacon.
+const FAILBACK_CHECKS: u32 = 3;
+/// Failed signaling reconnects after which the active beacon counts as down even if its
+/// HTTP health endpoint still answers.
+const SIGNALING_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BeaconHealth {
+    pub url: String,
+    pub healthy: bool,
+    /// Duration of the last health check, when it passed.
+    pub rtt_ms: Option<f64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailoverStatus {
+    /// URL the signaling client is on; `None` until a beacon has passed a check.
+    pub active: Option<String>,
+    /// In configured order.
+    pub beacons: Vec<BeaconHealth>,
+    /// Why `active` last changed, e.g. "wss://a failed: Timeout".
+    pub last_switch: Option<String>,
+}
+
+static STOP: Mutex<Option<mpsc::UnboundedSender<()>>> = Mutex::new(None);
+static STATUS: Mutex<Option<FailoverStatus>> = Mutex::new(None);
+
+/// Start managing the signaling connection across `urls` (most preferred first), replacing
+/// any running manager.
+pub fn start(app: AppHandle, urls: Vec<String>) -> Result<(), String> {
+    let mut urls: Vec<String> = urls.iter().map(|u| u.trim().trim_end_matches('/').to_string()).collect();
+    urls.retain(|u| !u.is_empty());
+    let mut seen = std::collections::HashSet::new();
+    urls.retain(|u| seen.insert(u.clone()));
+    if urls.is_empty() {
+        return Err("No beacon URLs configured".to_string());
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    if let Some(old) = STOP
+        .lock()
+        .map_err(|_| "Failed to lock beacon failover".to_string())?
+        .replace(tx)
+    {
+        let _ = old.send(());
+    }
+    tauri::async_runtime::spawn(run(app, urls, rx));
+    Ok(())
+}
+
+/// Stop health-checking. The signaling connection stays on the current beacon.
+pub fn stop() {
+    if let Ok(mut stop) = STOP.lock() {
+        if let Some(tx) = stop.take() {
+            let _ = tx.send(());
+        }
+    }
+    if let Ok(mut status) = STATUS.lock() {
+        *status = None;
+    }
+}
+
+pub fn status() -> Option<FailoverStatus> {
+    STATUS.lock().ok().and_then(|status| status.clone())
+}
+
+async fn run(app: AppHandle, urls: Vec<String>, mut stop: mpsc::UnboundedReceiver<()>) {
+    let mut active: Option<usize> = None;
+    let mut streaks = vec![0u32; urls.len()];
+    let mut last_switch = None;
+
+    loop {
+        let beacons = check_all(&urls).await;
+        for (streak, beacon) in streaks.iter_mut().zip(&beacons) {
+            *streak = if beacon.healthy { *streak + 1 } else { 0 };
+        }
+        let mut healthy: Vec<bool> = beacons.iter().map(|b| b.healthy).collect();
+        if let Some(current) = active {
+            let signaling = signaling::status();
+            if signaling.state == SignalingState::Reconnecting && signaling.attempt >= SIGNALING_FAILURES {
+                healthy[current] = false;
+                streaks[current] = 0;
+            }
+        }
+
+        if let Some(next) = select(active, &healthy, &streaks) {
+            if stop.try_recv().is_ok() {
+                return;
+            }
+            last_switch = Some(match active {
+                None => format!("Connected to {}", urls[next]),
+                Some(current) if !healthy[current] => format!(
+                    "{} failed ({}), moved to {}",
+                    urls[current],
+                    beacons[current].last_error.as_deref().unwrap_or("signaling unreachable"),
+                    urls[next]
+                ),
+                Some(current) => format!("{} is back, moved from {}", urls[next], urls[current]),
+            });
+            active = Some(next);
+            if let Err(e) = signaling::connect(app.clone(), &urls[next]) {
+                eprintln!("[BeaconFailover] Failed to connect to {}: {}", urls[next], e);
+            }
+        }
+
+        let status = FailoverStatus {
+            active: active.map(|i| urls[i].clone()),
+            beacons,
+            last_switch: last_switch.clone(),
+        };
+        if let Ok(mut current) = STATUS.lock() {
+            *current = Some(status.clone());
+        }
+        let _ = app.emit_all("cordia:beacon-failover", status);
+
+        tokio::select! {
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+            _ = stop.recv() => return,
+        }
+    }
+}
+
+async fn check_all(urls: &[String]) -> Vec<BeaconHealth> {
+    join_all(urls.iter().map(|url| async move {
+        let start = Instant::now();
+        let result = check_beacon_health(url).await;
+        BeaconHealth {
+            url: url.clone(),
+            healthy: result.is_ok(),
+            rtt_ms: result.is_ok().then(|| start.elapsed().as_secs_f64() * 1000.0),
+            last_error: result.err().map(|e| e.to_string()),
+        }
+    }))
+    .await
+}
+
+/// The beacon to move to, if any: the first healthy one when the active beacon is down (or
+/// none is active), or a preferred one that has stayed healthy long enough to fail back to.
+/// With nothing healthy, stays put and lets the signaling client keep retrying.
+fn select(active: Option<usize>, healthy: &[bool], streaks: &[u32]) -> Option<usize> {
+    match active {
+        Some(current) if healthy[current] => {
+            (0..current).find(|&i| healthy[i] && streaks[i] >= FAILBACK_CHECKS)
+        }
+        _ => healthy.iter().position(|&h| h).filter(|&i| Some(i) != active),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_over_in_order_and_fails_back_after_a_streak() {
+        assert_eq!(select(None, &[false, true, true], &[0, 1, 1]), Some(1));
+        assert_eq!(select(Some(1), &[false, true, true], &[0, 5, 5]), None);
+        assert_eq!(select(Some(1), &[false, false, true], &[0, 0, 5]), Some(2));
+        assert_eq!(select(Some(2), &[true, false, true], &[1, 0, 6]), None);
+        assert_eq!(select(Some(2), &[true, false, true], &[3, 0, 8]), Some(0));
+        assert_eq!(select(Some(0), &[false, false, false], &[0, 0, 0]), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! Background health monitor for the configured beacon.
+//!
+//! Replaces frontend polling: a task checks the beacon with `check_beacon_health`, quickly
+//! while something is wrong and at the configured interval (`settings::BeaconMonitorSettings`)
+//! while it is fine, and emits
+//! `cordia:beacon-status` only when the status changes. One failed check (or a slow answer)
+//! makes the beacon `Degraded`; `Disconnected` takes `FAILURES_TO_DISCONNECT` in a row, so a
+//! single dropped request doesn't flash the UI red.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::beacon::{check_beacon_health, BeaconStatus};
+
+const RETRY_INTERVAL_MIN: Duration = Duration::from_secs(2);
+const RETRY_INTERVAL_MAX: Duration = Duration::from_secs(30);
+const FAILURES_TO_DISCONNECT: u32 = 2;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BeaconStatusEvent {
+    pub url: String,
+    pub status: BeaconStatus,
+    /// Why the beacon is degraded or disconnected.
+    pub reason: Option<String>,
+    /// Duration of the last passing check.
+    pub rtt_ms: Option<f64>,
+}
+
+static STOP: Mutex<Option<mpsc::UnboundedSender<()>>> = Mutex::new(None);
+static STATUS: Mutex<Option<BeaconStatusEvent>> = Mutex::new(None);
+
+/// Start monitoring `url`, replacing any running monitor.
+pub fn start(app: AppHandle, url: String) -> Result<(), String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    if let Some(old) = STOP
+        .lock()
+        .map_err(|_| "Failed to lock beacon monitor".to_string())?
+        .replace(tx)
+    {
+        let _ = old.send(());
+    }
+    set_status(None);
+    tauri::async_runtime::spawn(run(app, url, rx));
+    Ok(())
+}
+
+pub fn stop() {
+    if let Ok(mut stop) = STOP.lock() {
+        if let Some(tx) = stop.take() {
+            let _ = tx.send(());
+        }
+    }
+    set_status(None);
+}
+
+/// Last status emitted; `None` before the first check or when stopped.
+pub fn status() -> Option<BeaconStatusEvent> {
+    STATUS.lock().ok().and_then(|status| status.clone())
+}
+
+fn set_status(status: Option<BeaconStatusEvent>) {
+    if let Ok(mut current) = STATUS.lock() {
+        *current = status;
+    }
+}
+
+async fn run(app: AppHandle, url: String, mut stop: mpsc::UnboundedReceiver<()>) {
+    let mut failures = 0u32;
+    let mut last: Option<(BeaconStatus, Option<String>)> = None;
+
+    loop {
+        // Re-read each round so settings changes apply without restarting the monitor.
+        let config = crate::settings::current().beacon_monitor;
+        let healthy_interval = Duration::from_secs(config.healthy_interval_secs);
+        let slow_check = Duration::from_millis(config.slow_check_ms);
+        let start = Instant::now();
+        let result = check_beacon_health(&url).await;
+        let elapsed = start.elapsed();
+
+        let (status, reason, rtt_ms) = match result {
+            Ok(_) => {
+                failures = 0;
+                let rtt_ms = Some(elapsed.as_secs_f64() * 1000.0);
+                if elapsed > slow_check {
+                    let reason = format!("Slow response ({} ms)", elapsed.as_millis());
+                    (BeaconStatus::Degraded, Some(reason), rtt_ms)
+                } else {
+                    (BeaconStatus::Connected, None, rtt_ms)
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                let status = if failures >= FAILURES_TO_DISCONNECT {
+                    BeaconStatus::Disconnected
+                } else {
+                    BeaconStatus::Degraded
+                };
+                (status, Some(e.to_string()), None)
+            }
+        };
+
+        // Emit on status changes, and when the reason changes while still unhealthy.
+        let changed = last.as_ref().is_none_or(|(s, r)| *s != status || (status != BeaconStatus::Connected && *r != reason));
+        let event = BeaconStatusEvent { url: url.clone(), status: status.clone(), reason: reason.clone(), rtt_ms };
+        if stop.try_recv().is_ok() {
+            return;
+        }
+        set_status(Some(event.clone()));
+        if changed {
+            let _ = app.emit_all("cordia:beacon-status", event);
+            last = Some((status, reason));
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(next_interval(failures, healthy_interval)) => {}
+            _ = stop.recv() => return,
+        }
+    }
+}
+
+/// `healthy` while healthy; after failures, retry soon and back off: 2, 4, 8, 16, 30 s.
+fn next_interval(failures: u32, healthy: Duration) -> Duration {
+    if failures == 0 {
+        return healthy;
+    }
+    RETRY_INTERVAL_MIN
+        .saturating_mul(1 << (failures - 1).min(8))
+        .min(RETRY_INTERVAL_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_to_the_healthy_interval() {
+        let healthy = Duration::from_secs(30);
+        assert_eq!(next_interval(0, healthy), healthy);
+        assert_eq!(next_interval(1, healthy), Duration::from_secs(2));
+        assert_eq!(next_interval(3, healthy), Duration::from_secs(8));
+        assert_eq!(next_interval(20, healthy), RETRY_INTERVAL_MAX);
+    }
+}
@@ -0,0 +1,326 @@
+//! Per-beacon TLS options for self-hosted beacons.
+//!
+//! A beacon can have extra root certificates (a private CA) trusted on top of the system
+//! store, and an SPKI pin: the base64 SHA-256 of the server certificate's public key
+//! (`openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | openssl dgst -sha256 -binary | base64`).
+//! Options are keyed by `host:port` so the ws:// and http:// forms of a beacon share them.
+//! The pin is checked right after the TLS handshake, before anything is sent: the signaling
+//! WebSocket and pinned HTTP requests both go over a stream from [`connect`].
+
+use base64::Engine;
+use reqwest::{ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::MaybeTlsStream;
+
+use crate::account_manager::AccountManager;
+
+/// Timeout for a pinned HTTP request that doesn't set its own.
+const PINNED_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconTlsOptions {
+    /// PEM root certificate(s) to trust for this beacon, in addition to the system store.
+    #[serde(default)]
+    pub ca_cert_pem: Option<String>,
+    /// Base64 SHA-256 of the server's SubjectPublicKeyInfo; `sha256/` prefix allowed.
+    #[serde(default)]
+    pub spki_pin: Option<String>,
+}
+
+static BEACON_TLS: Mutex<Option<HashMap<String, BeaconTlsOptions>>> = Mutex::new(None);
+
+fn settings_path() -> Result<PathBuf, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    Ok(account_manager.get_base_data_dir().join("beacon_tls.json"))
+}
+
+/// Load saved options. Called once at startup.
+pub fn load_beacon_tls() {
+    let options = settings_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<HashMap<String, BeaconTlsOptions>>(&json).ok())
+        .unwrap_or_default();
+    if let Ok(mut current) = BEACON_TLS.lock() {
+        *current = Some(options);
+    }
+}
+
+/// `host:port` for a ws://, wss://, http:// or https:// beacon URL.
+fn beacon_key(url: &str) -> Result<String, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid beacon URL: {}", e))?;
+    let host = parsed.host_str().ok_or_else(|| "Beacon URL has no host".to_string())?;
+    let port = parsed.port_or_known_default().ok_or_else(|| "Beacon URL has no port".to_string())?;
+    Ok(format!("{}:{}", host.to_ascii_lowercase(), port))
+}
+
+pub fn beacon_tls_options(url: &str) -> BeaconTlsOptions {
+    let Ok(key) = beacon_key(url) else {
+        return BeaconTlsOptions::default();
+    };
+    BEACON_TLS
+        .lock()
+        .ok()
+        .and_then(|options| options.as_ref().and_then(|o| o.get(&key).cloned()))
+        .unwrap_or_default()
+}
+
+/// Validate and save the options for `url`'s beacon; empty options remove the entry.
+pub fn set_beacon_tls_options(url: &str, options: BeaconTlsOptions) -> Result<(), String> {
+    let key = beacon_key(url)?;
+    let options = BeaconTlsOptions {
+        ca_cert_pem: options.ca_cert_pem.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()),
+        spki_pin: options.spki_pin.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()),
+    };
+    if let Some(pem) = &options.ca_cert_pem {
+        root_certificates(pem)?;
+    }
+    if let Some(pin) = &options.spki_pin {
+        decode_pin(pin)?;
+    }
+
+    let mut all = BEACON_TLS
+        .lock()
+        .map_err(|_| "Failed to lock beacon TLS options".to_string())?;
+    let all = all.get_or_insert_with(HashMap::new);
+    if options == BeaconTlsOptions::default() {
+        all.remove(&key);
+    } else {
+        all.insert(key, options);
+    }
+    let json = serde_json::to_string_pretty(&*all)
+        .map_err(|e| format!("Failed to serialize beacon TLS options: {}", e))?;
+    std::fs::write(settings_path()?, json)
+        .map_err(|e| format!("Failed to save beacon TLS options: {}", e))
+}
+
+/// Each `CERTIFICATE` block of a PEM bundle.
+fn root_certificates(pem: &str) -> Result<Vec<native_tls::Certificate>, String> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+    let mut certs = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = rest.find(BEGIN) {
+        let end = rest[start..]
+            .find(END)
+            .ok_or_else(|| "Unterminated certificate in PEM".to_string())?
+            + start
+            + END.len();
+        let block = rest.get(start..end).ok_or_else(|| "Malformed certificate in PEM".to_string())?;
+        let cert = native_tls::Certificate::from_pem(block.as_bytes())
+            .map_err(|e| format!("Invalid CA certificate: {}", e))?;
+        certs.push(cert);
+        rest = &rest[end..];
+    }
+    if certs.is_empty() {
+        return Err("No certificate found in PEM".to_string());
+    }
+    Ok(certs)
+}
+
+fn decode_pin(pin: &str) -> Result<Vec<u8>, String> {
+    let encoded = pin.strip_prefix("sha256/").unwrap_or(pin);
+    let pin = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid SPKI pin: {}", e))?;
+    if pin.len() != 32 {
+        return Err("Invalid SPKI pin: expected a base64 SHA-256 hash".to_string());
+    }
+    Ok(pin)
+}
+
+/// Check the server's leaf certificate against the beacon's pin, if it has one.
+fn verify_pin(options: &BeaconTlsOptions, cert_der: Option<&[u8]>) -> Result<(), String> {
+    let Some(pin) = &options.spki_pin else {
+        return Ok(());
+    };
+    let expected = decode_pin(pin)?;
+    let cert = cert_der.ok_or_else(|| "Beacon presented no certificate to check the pin against".to_string())?;
+    let spki = subject_public_key_info(cert).ok_or_else(|| "Failed to parse beacon certificate".to_string())?;
+    if Sha256::digest(spki).as_slice() != expected.as_slice() {
+        return Err("Beacon certificate does not match the pinned key".to_string());
+    }
+    Ok(())
+}
+
+/// HTTP client for `beacon_url`: proxy settings plus the beacon's extra roots.
+pub fn http_client(beacon_url: &str) -> Result<reqwest::Client, String> {
+    http_client_builder(beacon_url)?
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+fn http_client_builder(beacon_url: &str) -> Result<reqwest::ClientBuilder, String> {
+    let options = beacon_tls_options(beacon_url);
+    let mut builder = crate::proxy::http_client_builder()?;
+    if let Some(pem) = &options.ca_cert_pem {
+        for cert in root_certificates(pem)? {
+            let der = cert.to_der().map_err(|e| format!("Invalid CA certificate: {}", e))?;
+            let cert = reqwest::Certificate::from_der(&der).map_err(|e| format!("Invalid CA certificate: {}", e))?;
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    Ok(builder)
+}
+
+/// Send `request` to `beacon_url`. When the beacon is pinned, an https request goes over a
+/// connection from [`connect`], so nothing reaches a server whose key doesn't match.
+pub async fn send(beacon_url: &str, request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+    let (client, request) = request.build_split();
+    let request = request.map_err(|e| format!("Invalid request: {}", e))?;
+    if request.url().scheme() != "https" || beacon_tls_options(beacon_url).spki_pin.is_none() {
+        return client.execute(request).await.map_err(|e| e.to_string());
+    }
+    let timeout = request.timeout().copied().unwrap_or(PINNED_REQUEST_TIMEOUT);
+    tokio::time::timeout(timeout, send_pinned(request))
+        .await
+        .map_err(|_| "Request timed out".to_string())?
+}
+
+async fn send_pinned(request: reqwest::Request) -> Result<reqwest::Response, String> {
+    let url = request.url().clone();
+    let stream = crate::proxy::connect_tcp(&url).await?;
+    let stream = connect(&url, stream).await?;
+    let (mut sender, connection) = hyper::client::conn::handshake(stream)
+        .await
+        .map_err(|e| format!("HTTP handshake failed: {}", e))?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let mut builder = hyper::Request::builder()
+        .method(request.method().clone())
+        .uri(path)
+        .header(hyper::header::HOST, host);
+    for (name, value) in request.headers() {
+        builder = builder.header(name, value);
+    }
+    let body = request.body().and_then(|b| b.as_bytes()).map(<[u8]>::to_vec).unwrap_or_default();
+    let http_request = builder
+        .body(hyper::Body::from(body))
+        .map_err(|e| format!("Invalid request: {}", e))?;
+
+    let response = sender
+        .send_request(http_request)
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let (parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    let mut builder = hyper::Response::builder().status(parts.status).version(parts.version).url(url);
+    if let Some(headers) = builder.headers_mut() {
+        *headers = parts.headers;
+    }
+    builder
+        .body(body)
+        .map(reqwest::Response::from)
+        .map_err(|e| format!("Invalid response: {}", e))
+}
+
+/// TLS handshake with the beacon over `stream` for a wss:// URL, trusting its extra roots
+/// and checking its pin. ws:// URLs pass through as plain TCP.
+pub async fn connect(url: &Url, stream: TcpStream) -> Result<MaybeTlsStream<TcpStream>, String> {
+    if url.scheme() != "wss" {
+        return Ok(MaybeTlsStream::Plain(stream));
+    }
+    let host = url.host_str().ok_or_else(|| "URL has no host".to_string())?;
+    let options = beacon_tls_options(url.as_str());
+
+    let mut connector = native_tls::TlsConnector::builder();
+    if let Some(pem) = &options.ca_cert_pem {
+        for cert in root_certificates(pem)? {
+            connector.add_root_certificate(cert);
+        }
+    }
+    let connector = connector
+        .build()
+        .map_err(|e| format!("Failed to set up TLS: {}", e))?;
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+        .connect(host, stream)
+        .await
+        .map_err(|e| format!("TLS handshake failed: {}", e))?;
+
+    let cert = tls
+        .get_ref()
+        .peer_certificate()
+        .map_err(|e| format!("Failed to read beacon certificate: {}", e))?
+        .map(|c| c.to_der())
+        .transpose()
+        .map_err(|e| format!("Failed to read beacon certificate: {}", e))?;
+    verify_pin(&options, cert.as_deref())?;
+    Ok(MaybeTlsStream::NativeTls(tls))
+}
+
+/// One DER element at the start of `der`: (tag, contents, remainder).
+fn der_element(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = der.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        (rest[..n].iter().fold(0usize, |acc, &b| acc << 8 | b as usize), &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+/// The encoded SubjectPublicKeyInfo of an X.509 certificate.
+fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    let (_, cert, _) = der_element(cert)?;
+    let (_, mut tbs, _) = der_element(cert)?;
+    // Explicit [0] version, when present.
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_element(tbs)?.2;
+    }
+    // serialNumber, signature, issuer, validity, subject.
+    for _ in 0..5 {
+        tbs = der_element(tbs)?.2;
+    }
+    let (_, _, rest) = der_element(tbs)?;
+    Some(&tbs[..tbs.len() - rest.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_spki_and_checks_the_pin() {
+        let spki = [0x30, 0x02, 0x05, 0x00];
+        let mut tbs = vec![0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01];
+        tbs.extend_from_slice(&[0x30, 0x00, 0x30, 0x00, 0x30, 0x00, 0x30, 0x00]);
+        tbs.extend_from_slice(&spki);
+        let mut cert = vec![0x30, tbs.len() as u8 + 2, 0x30, tbs.len() as u8];
+        cert.extend_from_slice(&tbs);
+        assert_eq!(subject_public_key_info(&cert), Some(&spki[..]));
+
+        let pin = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(spki));
+        let pinned = BeaconTlsOptions { ca_cert_pem: None, spki_pin: Some(format!("sha256/{}", pin)) };
+        assert!(verify_pin(&pinned, Some(&cert)).is_ok());
+        let other = BeaconTlsOptions { spki_pin: Some(base64::engine::general_purpose::STANDARD.encode([0u8; 32])), ..pinned };
+        assert!(verify_pin(&other, Some(&cert)).is_err());
+        assert!(verify_pin(&BeaconTlsOptions::default(), None).is_ok());
+    }
+}
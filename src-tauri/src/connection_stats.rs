@@ -0,0 +1,170 @@
+//! One report of how the connection is doing, for the connection info panel: beacon
+//! keepalive RTT and reconnects, per-peer voice loss and jitter, and local audio drops.
+//!
+//! Voice numbers come from the native jitter buffer for peers whose audio plays through it,
+//! and otherwise from the WebRTC stats the frontend reports back (`report`), which are
+//! dropped once they are `REPORT_TTL` old.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::audio_capture::{self, AudioDropStats};
+use crate::audio_jitter::{self, JitterStats};
+use crate::signaling::{self, SignalingState};
+
+const REPORT_TTL: Duration = Duration::from_secs(10);
+
+/// Inbound audio stats for one peer as seen by the webview's `RTCPeerConnection.getStats()`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportedPeerStats {
+    pub packets_received: u64,
+    pub packets_lost: u64,
+    pub jitter_ms: f64,
+    pub rtt_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BeaconConnectionStats {
+    pub state: SignalingState,
+    pub url: Option<String>,
+    /// Keepalive round trip over the signaling WebSocket.
+    pub rtt_ms: Option<f64>,
+    /// Times the connection dropped and came back since it was opened.
+    pub reconnects: u64,
+    /// Failed attempts since the last successful connect.
+    pub attempt: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerStatsSource {
+    /// The native jitter buffer.
+    Native,
+    /// Stats reported by the frontend.
+    Reported,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerConnectionStats {
+    pub peer_id: String,
+    pub source: PeerStatsSource,
+    pub packet_loss_pct: f64,
+    pub jitter_ms: f64,
+    pub rtt_ms: Option<f64>,
+    /// Audio waiting in the jitter buffer (native peers only).
+    pub buffered_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStats {
+    pub beacon: BeaconConnectionStats,
+    pub peers: Vec<PeerConnectionStats>,
+    pub audio: AudioDropStats,
+}
+
+static REPORTED: Mutex<BTreeMap<String, (Instant, ReportedPeerStats)>> = Mutex::new(BTreeMap::new());
+
+/// Record the frontend's latest stats for `peer_id`.
+pub fn report(peer_id: &str, stats: ReportedPeerStats) {
+    if let Ok(mut reported) = REPORTED.lock() {
+        reported.insert(peer_id.to_string(), (Instant::now(), stats));
+    }
+}
+
+/// Forget reported stats (e.g. on leaving voice).
+pub fn clear_reports() {
+    if let Ok(mut reported) = REPORTED.lock() {
+        reported.clear();
+    }
+}
+
+pub fn collect() -> ConnectionStats {
+    let status = signaling::status();
+    let reported: Vec<(String, ReportedPeerStats)> = REPORTED
+        .lock()
+        .map(|mut reported| {
+            reported.retain(|_, (at, _)| at.elapsed() < REPORT_TTL);
+            reported.iter().map(|(id, (_, stats))| (id.clone(), stats.clone())).collect()
+        })
+        .unwrap_or_default();
+    ConnectionStats {
+        beacon: BeaconConnectionStats {
+            state: status.state,
+            url: status.url,
+            rtt_ms: signaling::rtt_ms(),
+            reconnects: signaling::reconnects(),
+            attempt: status.attempt,
+        },
+        peers: merge_peers(audio_jitter::stats(), reported),
+        audio: audio_capture::get_audio_drop_stats(),
+    }
+}
+
+fn loss_pct(lost: u64, received: u64) -> f64 {
+    match lost + received {
+        0 => 0.0,
+        total => lost as f64 * 100.0 / total as f64,
+    }
+}
+
+/// Native stats win where both exist; reports still supply the RTT the buffer can't see.
+fn merge_peers(native: HashMap<String, JitterStats>, reported: Vec<(String, ReportedPeerStats)>) -> Vec<PeerConnectionStats> {
+    let mut peers: BTreeMap<String, PeerConnectionStats> = native
+        .into_iter()
+        .map(|(peer_id, stats)| {
+            let entry = PeerConnectionStats {
+                peer_id: peer_id.clone(),
+                source: PeerStatsSource::Native,
+                // Concealed frames are the ones whose packet never made it in time.
+                packet_loss_pct: loss_pct(stats.concealed, stats.received),
+                jitter_ms: stats.jitter_ms,
+                rtt_ms: None,
+                buffered_ms: Some(stats.buffered_ms),
+            };
+            (peer_id, entry)
+        })
+        .collect();
+    for (peer_id, stats) in reported {
+        peers
+            .entry(peer_id.clone())
+            .and_modify(|entry| entry.rtt_ms = stats.rtt_ms)
+            .or_insert(PeerConnectionStats {
+                peer_id,
+                source: PeerStatsSource::Reported,
+                packet_loss_pct: loss_pct(stats.packets_lost, stats.packets_received),
+                jitter_ms: stats.jitter_ms,
+                rtt_ms: stats.rtt_ms,
+                buffered_ms: None,
+            });
+    }
+    peers.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_stats_take_rtt_from_reports() {
+        let native = HashMap::from([(
+            "alice".to_string(),
+            JitterStats { received: 95, concealed: 5, jitter_ms: 4.0, ..Default::default() },
+        )]);
+        let reported = |lost, rtt| ReportedPeerStats { packets_received: 90, packets_lost: lost, jitter_ms: 12.0, rtt_ms: rtt };
+        let peers = merge_peers(native, vec![
+            ("alice".to_string(), reported(0, Some(40.0))),
+            ("bob".to_string(), reported(10, None)),
+        ]);
+
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].source, PeerStatsSource::Native);
+        assert_eq!(peers[0].packet_loss_pct, 5.0);
+        assert_eq!(peers[0].jitter_ms, 4.0);
+        assert_eq!(peers[0].rtt_ms, Some(40.0));
+        assert_eq!(peers[1].source, PeerStatsSource::Reported);
+        assert_eq!(peers[1].packet_loss_pct, 10.0);
+        assert_eq!(peers[1].buffered_ms, None);
+    }
+}
@@ -0,0 +1,100 @@
+//! Audio device hotplug detection.
+//!
+//! cpal has no cross-platform change notification, so a background thread polls the
+//! device list and reports differences. Polling is cheap at this interval and works
+//! the same on every host.
+
+use crate::audio_capture::{enumerate_devices, AudioDevice};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// How often the device list is re-enumerated.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+static WATCHER_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Bumped on every start/stop so a stopped thread can't be revived by a quick restart.
+static WATCHER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Payload for device change notifications.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceChangeEvent {
+    pub added: Vec<AudioDevice>,
+    pub removed: Vec<AudioDevice>,
+    /// Full device list after the change, so listeners don't need to re-enumerate.
+    pub devices: Vec<AudioDevice>,
+    /// The OS default (or default communications) device moved.
+    pub default_changed: bool,
+}
+
+fn same_device(a: &AudioDevice, b: &AudioDevice) -> bool {
+    a.device_id == b.device_id && a.label == b.label && a.kind == b.kind
+}
+
+/// Compare two snapshots; None when nothing changed.
+fn diff_devices(previous: &[AudioDevice], current: Vec<AudioDevice>) -> Option<DeviceChangeEvent> {
+    let added: Vec<AudioDevice> = current
+        .iter()
+        .filter(|d| !previous.iter().any(|p| same_device(p, d)))
+        .cloned()
+        .collect();
+    let removed: Vec<AudioDevice> = previous
+        .iter()
+        .filter(|p| !current.iter().any(|d| same_device(p, d)))
+        .cloned()
+        .collect();
+    let defaults = |devices: &[AudioDevice]| -> Vec<(String, bool, bool)> {
+        devices
+            .iter()
+            .filter(|d| d.is_default || d.is_default_communications)
+            .map(|d| (d.device_id.clone(), d.is_default, d.is_default_communications))
+            .collect()
+    };
+    let default_changed = defaults(previous) != defaults(&current);
+    if added.is_empty() && removed.is_empty() && !default_changed {
+        return None;
+    }
+    Some(DeviceChangeEvent {
+        added,
+        removed,
+        devices: current,
+        default_changed,
+    })
+}
+
+/// Start the watcher thread. `on_change` runs on the watcher thread for every change.
+/// Calling this while a watcher is already running is a no-op.
+pub fn start_device_watcher<F>(on_change: F)
+where
+    F: Fn(&DeviceChangeEvent) + Send + 'static,
+{
+    if WATCHER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let generation = WATCHER_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    thread::spawn(move || {
+        let mut known = enumerate_devices().unwrap_or_default();
+        while WATCHER_GENERATION.load(Ordering::SeqCst) == generation {
+            thread::sleep(POLL_INTERVAL);
+            // Enumeration can fail transiently while a device is mid-(un)plug; retry next tick.
+            let current = match enumerate_devices() {
+                Ok(devices) => devices,
+                Err(_) => continue,
+            };
+            if WATCHER_GENERATION.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            if let Some(event) = diff_devices(&known, current) {
+                known = event.devices.clone();
+                on_change(&event);
+            }
+        }
+    });
+}
+
+/// Ask the watcher thread to exit after its current poll.
+pub fn stop_device_watcher() {
+    WATCHER_GENERATION.fetch_add(1, Ordering::SeqCst);
+    WATCHER_RUNNING.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,333 @@
+//! "Having trouble connecting?": walks the path to the beacon one step at a time (DNS, TCP,
+//! TLS, HTTP, WebSocket upgrade), then checks UDP with a STUN binding and looks for a captive
+//! portal, so the report says where things break instead of just "can't connect".
+//!
+//! Steps that depend on an earlier one are skipped when it fails. Through a proxy, DNS, TCP
+//! and TLS happen on the proxy's side and are only covered by the HTTP and WebSocket steps.
+
+use reqwest::Url;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::timeout;
+
+const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+const STUN_ATTEMPT_TIMEOUT: Duration = Duration::from_millis(1500);
+/// Answers 204 with no body; anything else means something sits between us and the internet.
+const CAPTIVE_PORTAL_PROBE: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_RESPONSE: u16 = 0x0101;
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const STUN_MAPPED_ADDRESS: u16 = 0x0001;
+const STUN_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Skipped,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    /// Stable id: `dns`, `tcp`, `tls`, `http`, `websocket`, `udp`, `captive_portal`.
+    pub id: &'static str,
+    pub label: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub beacon_url: String,
+    /// Worst status among the checks.
+    pub status: CheckStatus,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+impl DiagnosticCheck {
+    fn new(id: &'static str, label: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self { id, label, status, detail: detail.into(), duration_ms: None }
+    }
+
+    fn timed(mut self, start: Instant) -> Self {
+        self.duration_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+        self
+    }
+}
+
+pub async fn run(beacon_url: &str) -> Result<DiagnosticsReport, String> {
+    let ws_url = crate::beacon::websocket_url(beacon_url).map_err(|e| e.to_string())?;
+    let target = Url::parse(&ws_url).map_err(|e| format!("Invalid beacon URL: {}", e))?;
+
+    let (mut checks, udp, captive) = tokio::join!(beacon_path(beacon_url, &target), udp_check(), captive_portal_check());
+    checks.push(udp);
+    checks.push(captive);
+
+    let status = checks.iter().map(|c| c.status).max().unwrap_or(CheckStatus::Pass);
+    Ok(DiagnosticsReport { beacon_url: beacon_url.to_string(), status, checks })
+}
+
+async fn beacon_path(beacon_url: &str, target: &Url) -> Vec<DiagnosticCheck> {
+    let mut checks = Vec::new();
+    let host = target.host_str().unwrap_or_default().to_string();
+    let port = target.port_or_known_default().unwrap_or(443);
+    let uses_tls = target.scheme() == "wss";
+
+    let skip = |id, label, why: &str| DiagnosticCheck::new(id, label, CheckStatus::Skipped, why);
+    match crate::proxy::proxy_for(target) {
+        Ok(Some(proxy)) => {
+            let why = format!("Connecting through proxy {}", proxy.host_str().unwrap_or_default());
+            checks.push(skip("dns", "DNS lookup", &why));
+            checks.push(skip("tcp", "TCP connection", &why));
+            checks.push(skip("tls", "TLS handshake", &why));
+        }
+        Ok(None) => {
+            if !direct_path(&mut checks, target, &host, port, uses_tls).await {
+                let why = "An earlier step failed";
+                checks.push(skip("http", "Beacon health", why));
+                checks.push(skip("websocket", "WebSocket upgrade", why));
+                return checks;
+            }
+        }
+        Err(e) => {
+            checks.push(DiagnosticCheck::new("dns", "DNS lookup", CheckStatus::Fail, format!("Proxy settings are invalid: {}", e)));
+            return checks;
+        }
+    }
+
+    match crate::beacon::probe_beacon(beacon_url).await {
+        Ok(probe) => {
+            let mut http = match probe.http_error {
+                None => DiagnosticCheck::new("http", "Beacon health", CheckStatus::Pass, "The beacon answered its health check"),
+                Some(e) => DiagnosticCheck::new("http", "Beacon health", CheckStatus::Fail, format!("Health check failed: {}", e)),
+            };
+            http.duration_ms = probe.http_ms;
+            checks.push(http);
+            let mut websocket = match probe.ws_error {
+                None => DiagnosticCheck::new("websocket", "WebSocket upgrade", CheckStatus::Pass, "Signaling connection works"),
+                Some(e) => DiagnosticCheck::new(
+                    "websocket",
+                    "WebSocket upgrade",
+                    CheckStatus::Fail,
+                    format!("{}. A firewall, proxy or load balancer may be dropping WebSocket upgrades", e),
+                ),
+            };
+            websocket.duration_ms = probe.ws_ms;
+            checks.push(websocket);
+        }
+        Err(e) => checks.push(DiagnosticCheck::new("http", "Beacon health", CheckStatus::Fail, e.to_string())),
+    }
+    checks
+}
+
+/// DNS, TCP and (for wss://) TLS without a proxy. Returns false if a step failed.
+async fn direct_path(checks: &mut Vec<DiagnosticCheck>, target: &Url, host: &str, port: u16, uses_tls: bool) -> bool {
+    let start = Instant::now();
+    let addrs: Vec<SocketAddr> = match timeout(STEP_TIMEOUT, tokio::net::lookup_host((host, port))).await {
+        Ok(Ok(addrs)) => addrs.collect(),
+        Ok(Err(e)) => {
+            checks.push(DiagnosticCheck::new("dns", "DNS lookup", CheckStatus::Fail, format!("Could not resolve {}: {}", host, e)).timed(start));
+            return false;
+        }
+        Err(_) => {
+            checks.push(DiagnosticCheck::new("dns", "DNS lookup", CheckStatus::Fail, format!("Resolving {} timed out", host)).timed(start));
+            return false;
+        }
+    };
+    let list = addrs.iter().map(|a| a.ip().to_string()).collect::<Vec<_>>().join(", ");
+    checks.push(DiagnosticCheck::new("dns", "DNS lookup", CheckStatus::Pass, format!("{} resolves to {}", host, list)).timed(start));
+
+    let start = Instant::now();
+    let stream = match timeout(STEP_TIMEOUT, TcpStream::connect(&addrs[..])).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            checks.push(DiagnosticCheck::new("tcp", "TCP connection", CheckStatus::Fail, format!("Could not connect to port {}: {}", port, e)).timed(start));
+            return false;
+        }
+        Err(_) => {
+            checks.push(
+                DiagnosticCheck::new("tcp", "TCP connection", CheckStatus::Fail, format!("Port {} did not answer; a firewall may be blocking it", port))
+                    .timed(start),
+            );
+            return false;
+        }
+    };
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+    checks.push(DiagnosticCheck::new("tcp", "TCP connection", CheckStatus::Pass, format!("Connected to {}", peer)).timed(start));
+
+    if !uses_tls {
+        checks.push(DiagnosticCheck::new("tls", "TLS handshake", CheckStatus::Warn, "The beacon URL is ws://, so traffic is not encrypted"));
+        return true;
+    }
+    let start = Instant::now();
+    let check = match timeout(STEP_TIMEOUT, crate::beacon_tls::connect(target, stream)).await {
+        Ok(Ok(_)) => DiagnosticCheck::new("tls", "TLS handshake", CheckStatus::Pass, "Certificate accepted"),
+        Ok(Err(e)) => DiagnosticCheck::new("tls", "TLS handshake", CheckStatus::Fail, e),
+        Err(_) => DiagnosticCheck::new("tls", "TLS handshake", CheckStatus::Fail, "The TLS handshake timed out"),
+    };
+    let ok = check.status == CheckStatus::Pass;
+    checks.push(check.timed(start));
+    ok
+}
+
+/// A STUN binding against the public servers peers use: proves UDP gets out and shows the
+/// address peers would see.
+async fn udp_check() -> DiagnosticCheck {
+    let start = Instant::now();
+    let mut last_error = String::new();
+    for url in crate::ice_servers::STUN_URLS {
+        let server = url.trim_start_matches("stun:");
+        match stun_binding(server).await {
+            Ok(mapped) => {
+                return DiagnosticCheck::new("udp", "UDP (STUN)", CheckStatus::Pass, format!("UDP works; peers see you as {}", mapped))
+                    .timed(start)
+            }
+            Err(e) => last_error = format!("{}: {}", server, e),
+        }
+    }
+    DiagnosticCheck::new(
+        "udp",
+        "UDP (STUN)",
+        CheckStatus::Warn,
+        format!("No STUN server answered ({}). UDP looks blocked; calls will need the beacon's TURN relay", last_error),
+    )
+    .timed(start)
+}
+
+async fn stun_binding(server: &str) -> Result<SocketAddr, String> {
+    let addr = tokio::net::lookup_host(server)
+        .await
+        .map_err(|e| format!("Failed to resolve: {}", e))?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| "No IPv4 address".to_string())?;
+    let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| format!("Failed to open UDP socket: {}", e))?;
+    let transaction: [u8; 12] = rand::random();
+    let request = stun_request(&transaction);
+    let mut buf = [0u8; 512];
+    // UDP may drop the first try; send twice before giving up on this server.
+    for _ in 0..2 {
+        socket.send_to(&request, addr).await.map_err(|e| format!("Failed to send: {}", e))?;
+        if let Ok(Ok((len, _))) = timeout(STUN_ATTEMPT_TIMEOUT, socket.recv_from(&mut buf)).await {
+            return parse_stun_response(&buf[..len], &transaction).ok_or_else(|| "Malformed STUN response".to_string());
+        }
+    }
+    Err("No answer".to_string())
+}
+
+fn stun_request(transaction: &[u8; 12]) -> Vec<u8> {
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction);
+    request
+}
+
+/// The mapped address from a binding response (IPv4; XOR-MAPPED-ADDRESS preferred).
+fn parse_stun_response(packet: &[u8], transaction: &[u8; 12]) -> Option<SocketAddr> {
+    if packet.len() < 20
+        || u16::from_be_bytes([packet[0], packet[1]]) != STUN_BINDING_RESPONSE
+        || packet[8..20] != transaction[..]
+    {
+        return None;
+    }
+    let mut attrs = &packet[20..];
+    let mut mapped = None;
+    while attrs.len() >= 4 {
+        let kind = u16::from_be_bytes([attrs[0], attrs[1]]);
+        let len = u16::from_be_bytes([attrs[2], attrs[3]]) as usize;
+        let value = attrs.get(4..4 + len)?;
+        // Family 0x01 is IPv4: [reserved, family, port(2), address(4)].
+        if value.len() >= 8 && value[1] == 0x01 {
+            let port = u16::from_be_bytes([value[2], value[3]]);
+            let ip = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+            match kind {
+                STUN_XOR_MAPPED_ADDRESS => {
+                    let port = port ^ (STUN_MAGIC_COOKIE >> 16) as u16;
+                    let ip = ip ^ STUN_MAGIC_COOKIE;
+                    return Some(SocketAddr::from((ip.to_be_bytes(), port)));
+                }
+                STUN_MAPPED_ADDRESS => mapped = Some(SocketAddr::from((ip.to_be_bytes(), port))),
+                _ => {}
+            }
+        }
+        // Attributes are padded to 4 bytes.
+        attrs = attrs.get(4 + len.div_ceil(4) * 4..).unwrap_or_default();
+    }
+    mapped
+}
+
+async fn captive_portal_check() -> DiagnosticCheck {
+    let start = Instant::now();
+    let client = match crate::proxy::http_client_builder().and_then(|builder| {
+        builder
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(STEP_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))
+    }) {
+        Ok(client) => client,
+        Err(e) => return DiagnosticCheck::new("captive_portal", "Captive portal", CheckStatus::Skipped, e),
+    };
+    let check = match client.get(CAPTIVE_PORTAL_PROBE).send().await {
+        Ok(response) if response.status() == reqwest::StatusCode::NO_CONTENT => {
+            DiagnosticCheck::new("captive_portal", "Captive portal", CheckStatus::Pass, "No captive portal in the way")
+        }
+        Ok(response) if response.status().is_redirection() => {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .unwrap_or("another page")
+                .to_string();
+            DiagnosticCheck::new(
+                "captive_portal",
+                "Captive portal",
+                CheckStatus::Fail,
+                format!("This network redirects to {}; sign in there first", location),
+            )
+        }
+        Ok(response) => DiagnosticCheck::new(
+            "captive_portal",
+            "Captive portal",
+            CheckStatus::Fail,
+            format!("The connectivity check got HTTP {} instead of 204; this network may need a sign-in", response.status()),
+        ),
+        Err(e) => DiagnosticCheck::new(
+            "captive_portal",
+            "Captive portal",
+            CheckStatus::Warn,
+            format!("Could not reach the internet connectivity check: {}", e),
+        ),
+    };
+    check.timed(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_xor_mapped_address() {
+        let transaction = [7u8; 12];
+        let mut response = stun_request(&transaction);
+        response[0..2].copy_from_slice(&STUN_BINDING_RESPONSE.to_be_bytes());
+        // 203.0.113.5:54321, XORed with the magic cookie.
+        let port = 54321u16 ^ (STUN_MAGIC_COOKIE >> 16) as u16;
+        let ip = u32::from_be_bytes([203, 0, 113, 5]) ^ STUN_MAGIC_COOKIE;
+        let mut attr = vec![0x00, 0x20, 0x00, 0x08, 0x00, 0x01];
+        attr.extend_from_slice(&port.to_be_bytes());
+        attr.extend_from_slice(&ip.to_be_bytes());
+        response[2..4].copy_from_slice(&(attr.len() as u16).to_be_bytes());
+        response.extend_from_slice(&attr);
+
+        assert_eq!(parse_stun_response(&response, &transaction), Some("203.0.113.5:54321".parse().unwrap()));
+        assert_eq!(parse_stun_response(&response, &[0u8; 12]), None);
+    }
+}
@@ -0,0 +1,869 @@
+//! Native peer-to-peer file transfer: chunked, hashed and resumable, without the file
+//! passing through the webview.
+//!
+//! The sender hashes the file in 1 MiB pieces and offers a manifest (name, size, SHA-256 of
+//! the whole file and of every piece). The receiver pulls a few pieces at a time
+//! (`Request`), the sender answers with 16 KiB blocks, and a piece only counts once it
+//! matches its hash; a bad piece is requested again, and so is one whose blocks stop
+//! arriving. Finished pieces are recorded under
+//! the account's `transfers/` directory, and transfer ids derive from the file hash and the
+//! recipient, so offering the same file again resumes an interrupted download.
+//!
+//! Frames go over the peer's native data channel when one is open (`native-webrtc` builds)
+//! and otherwise through the beacon as `AttachmentTransferSignal` relays, paced to stay under
+//! the beacon's rate limits. Progress is
+//! emitted as `cordia:file-transfer`.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::account_manager::AccountManager;
+
+const PIECE_SIZE: u64 = 1 << 20;
+const BLOCK_SIZE: usize = 16 * 1024;
+/// Pieces a receiver has requested but not finished; bounds what is in flight.
+const WINDOW: usize = 4;
+/// An in-flight piece that gets no block for this long is requested again.
+const PIECE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the worker looks for stale pieces.
+const STALE_CHECK: Duration = Duration::from_secs(5);
+/// Relayed frames count against the beacon's transfer quota (120/min) and its overall
+/// per-connection limit (250/min); this leaves room for chat and signaling.
+const RELAY_FRAMES_PER_MIN: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub transfer_id: String,
+    pub file_name: String,
+    pub size: u64,
+    /// Hex SHA-256 of the whole file.
+    pub sha256: String,
+    /// Hex SHA-256 of each `PIECE_SIZE` piece.
+    pub piece_hashes: Vec<String>,
+}
+
+impl Manifest {
+    fn piece_len(&self, piece: u32) -> u64 {
+        let start = piece as u64 * PIECE_SIZE;
+        (self.size - start).min(PIECE_SIZE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Send,
+    Receive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferState {
+    /// Waiting for the receiver to accept.
+    Offered,
+    Active,
+    Paused,
+    Complete,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub peer_user_id: String,
+    pub direction: Direction,
+    pub file_name: String,
+    pub size: u64,
+    /// Verified bytes (receiving) or bytes handed to the transport (sending).
+    pub bytes_done: u64,
+    pub state: TransferState,
+    /// Final location of a completed download.
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "ft", rename_all = "snake_case")]
+enum Frame {
+    Offer { manifest: Manifest },
+    Request { transfer_id: String, pieces: Vec<u32> },
+    Block { transfer_id: String, piece: u32, offset: u32, data: String },
+    Pause { transfer_id: String },
+    Resume { transfer_id: String },
+    Cancel { transfer_id: String },
+    Done { transfer_id: String },
+}
+
+impl Frame {
+    fn transfer_id(&self) -> &str {
+        match self {
+            Frame::Offer { manifest } => &manifest.transfer_id,
+            Frame::Request { transfer_id, .. }
+            | Frame::Block { transfer_id, .. }
+            | Frame::Pause { transfer_id }
+            | Frame::Resume { transfer_id }
+            | Frame::Cancel { transfer_id }
+            | Frame::Done { transfer_id } => transfer_id,
+        }
+    }
+}
+
+struct Outgoing {
+    manifest: Manifest,
+    to_user_id: String,
+    path: PathBuf,
+    state: TransferState,
+    bytes_sent: u64,
+}
+
+struct Incoming {
+    manifest: Manifest,
+    from_user_id: String,
+    state: TransferState,
+    target_path: Option<PathBuf>,
+    file: Option<File>,
+    done: Vec<bool>,
+    in_flight: BTreeMap<u32, PieceRequest>,
+    /// When any block of this transfer last arrived.
+    last_block: Option<Instant>,
+    error: Option<String>,
+}
+
+/// A requested piece that hasn't verified yet.
+struct PieceRequest {
+    requested_at: Instant,
+    last_block: Option<Instant>,
+    /// Received block ranges (offset -> length).
+    blocks: BTreeMap<u32, u32>,
+}
+
+impl PieceRequest {
+    fn new(now: Instant) -> Self {
+        Self { requested_at: now, last_block: None, blocks: BTreeMap::new() }
+    }
+}
+
+impl Incoming {
+    fn bytes_done(&self) -> u64 {
+        (0..self.done.len() as u32)
+            .filter(|&p| self.done[p as usize])
+            .map(|p| self.manifest.piece_len(p))
+            .sum()
+    }
+
+    fn part_path(&self) -> Option<PathBuf> {
+        self.target_path.as_ref().map(|p| part_path(p))
+    }
+
+    /// In-flight pieces that stopped arriving: a started piece with no block for
+    /// `PIECE_TIMEOUT`, or an unstarted one requested that long ago while the whole transfer
+    /// has been quiet (the sender works through a request one piece at a time).
+    fn stale_pieces(&self, now: Instant) -> Vec<u32> {
+        let quiet = |t: Instant| now.duration_since(t) >= PIECE_TIMEOUT;
+        let transfer_quiet = self.last_block.is_none_or(quiet);
+        self.in_flight
+            .iter()
+            .filter(|(_, r)| match r.last_block {
+                Some(t) => quiet(t),
+                None => transfer_quiet && quiet(r.requested_at),
+            })
+            .map(|(piece, _)| *piece)
+            .collect()
+    }
+}
+
+/// What survives a restart on the receiving side.
+#[derive(Serialize, Deserialize)]
+struct ResumeRecord {
+    manifest: Manifest,
+    from_user_id: String,
+    target_path: PathBuf,
+    done: Vec<bool>,
+}
+
+static OUTGOING: Mutex<BTreeMap<String, Outgoing>> = Mutex::new(BTreeMap::new());
+static INCOMING: Mutex<BTreeMap<String, Incoming>> = Mutex::new(BTreeMap::new());
+/// Incoming frames are handled in order on one worker thread, off the signaling task.
+static WORKER: Mutex<Option<mpsc::Sender<(AppHandle, String, Frame)>>> = Mutex::new(None);
+/// Earliest time the next relayed frame may go out.
+static NEXT_RELAY_SLOT: Mutex<Option<Instant>> = Mutex::new(None);
+
+fn part_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".cordia-part");
+    target.with_file_name(name)
+}
+
+fn records_dir() -> Result<PathBuf, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let account_id = account_manager
+        .get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))?
+        .ok_or_else(|| "No active session".to_string())?;
+    let dir = account_manager.get_account_dir(&account_id).join("transfers");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create transfers directory: {}", e))?;
+    Ok(dir)
+}
+
+/// Transfer ids are the first 32 hex digits of a SHA-256 (see `build_manifest`); anything
+/// else from a peer is rejected before it can reach a path.
+fn valid_transfer_id(transfer_id: &str) -> bool {
+    transfer_id.len() == 32 && transfer_id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn record_path(transfer_id: &str) -> Result<PathBuf, String> {
+    if !valid_transfer_id(transfer_id) {
+        return Err(format!("Invalid transfer id: {:?}", transfer_id));
+    }
+    Ok(records_dir()?.join(format!("{}.json", transfer_id)))
+}
+
+fn load_record(transfer_id: &str) -> Option<ResumeRecord> {
+    let json = std::fs::read_to_string(record_path(transfer_id).ok()?).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn save_record(incoming: &Incoming) -> Result<(), String> {
+    let target_path = match &incoming.target_path {
+        Some(path) => path.clone(),
+        None => return Ok(()),
+    };
+    let record = ResumeRecord {
+        manifest: incoming.manifest.clone(),
+        from_user_id: incoming.from_user_id.clone(),
+        target_path,
+        done: incoming.done.clone(),
+    };
+    let json = serde_json::to_string(&record).map_err(|e| format!("Failed to serialize transfer state: {}", e))?;
+    std::fs::write(record_path(&incoming.manifest.transfer_id)?, json)
+        .map_err(|e| format!("Failed to save transfer state: {}", e))
+}
+
+fn delete_record(transfer_id: &str) {
+    if let Ok(path) = record_path(transfer_id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Hash `path` in pieces and build the manifest offered to `to_user_id`.
+fn build_manifest(path: &Path, to_user_id: &str) -> Result<Manifest, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let size = file.metadata().map_err(|e| format!("Failed to read file metadata: {}", e))?.len();
+    let mut whole = Sha256::new();
+    let mut piece_hashes = Vec::new();
+    let mut buf = vec![0u8; PIECE_SIZE as usize];
+    loop {
+        let n = read_full(&mut file, &mut buf).map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        whole.update(&buf[..n]);
+        piece_hashes.push(hex_sha256(&buf[..n]));
+    }
+    let sha256 = hex::encode(whole.finalize());
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("file")
+        .to_string();
+    Ok(Manifest {
+        transfer_id: hex_sha256(format!("{}:{}", sha256, to_user_id).as_bytes())[..32].to_string(),
+        file_name,
+        size,
+        sha256,
+        piece_hashes,
+    })
+}
+
+/// Fill `buf` unless the file ends first; returns the bytes read.
+fn read_full(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn send_frame(to_user_id: &str, frame: &Frame) -> Result<(), String> {
+    let text = serde_json::to_string(frame).map_err(|e| format!("Failed to serialize transfer frame: {}", e))?;
+    #[cfg(feature = "native-webrtc")]
+    if crate::native_webrtc::send_data(to_user_id, text.clone()) {
+        return Ok(());
+    }
+    pace_relay();
+    crate::signaling::send(&serde_json::json!({
+        "type": "AttachmentTransferSignal",
+        "to_user_id": to_user_id,
+        "request_id": frame.transfer_id(),
+        "signal": text,
+    }))
+}
+
+/// Block until the next relay slot, spacing relayed frames evenly at `RELAY_FRAMES_PER_MIN`.
+fn pace_relay() {
+    let interval = Duration::from_secs(60) / RELAY_FRAMES_PER_MIN;
+    let slot = match NEXT_RELAY_SLOT.lock() {
+        Ok(mut next) => {
+            let now = Instant::now();
+            let slot = next.map_or(now, |n| n.max(now));
+            *next = Some(slot + interval);
+            slot
+        }
+        Err(_) => return,
+    };
+    std::thread::sleep(slot.saturating_duration_since(Instant::now()));
+}
+
+fn emit(app: &AppHandle, progress: TransferProgress) {
+    let _ = app.emit_all("cordia:file-transfer", progress);
+}
+
+fn outgoing_progress(outgoing: &Outgoing) -> TransferProgress {
+    TransferProgress {
+        transfer_id: outgoing.manifest.transfer_id.clone(),
+        peer_user_id: outgoing.to_user_id.clone(),
+        direction: Direction::Send,
+        file_name: outgoing.manifest.file_name.clone(),
+        size: outgoing.manifest.size,
+        bytes_done: outgoing.bytes_sent,
+        state: outgoing.state,
+        path: Some(outgoing.path.to_string_lossy().to_string()),
+        error: None,
+    }
+}
+
+fn incoming_progress(incoming: &Incoming) -> TransferProgress {
+    TransferProgress {
+        transfer_id: incoming.manifest.transfer_id.clone(),
+        peer_user_id: incoming.from_user_id.clone(),
+        direction: Direction::Receive,
+        file_name: incoming.manifest.file_name.clone(),
+        size: incoming.manifest.size,
+        bytes_done: incoming.bytes_done(),
+        state: incoming.state,
+        path: match incoming.state {
+            TransferState::Complete => incoming.target_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+            _ => None,
+        },
+        error: incoming.error.clone(),
+    }
+}
+
+/// Offer `path` to `to_user_id`. Blocks while the file is hashed.
+pub fn send_file(app: &AppHandle, to_user_id: &str, path: &Path) -> Result<TransferProgress, String> {
+    let manifest = build_manifest(path, to_user_id)?;
+    send_frame(to_user_id, &Frame::Offer { manifest: manifest.clone() })?;
+    let outgoing = Outgoing {
+        manifest,
+        to_user_id: to_user_id.to_string(),
+        path: path.to_path_buf(),
+        state: TransferState::Offered,
+        bytes_sent: 0,
+    };
+    let progress = outgoing_progress(&outgoing);
+    OUTGOING
+        .lock()
+        .map_err(|_| "Failed to lock transfers".to_string())?
+        .insert(progress.transfer_id.clone(), outgoing);
+    emit(app, progress.clone());
+    Ok(progress)
+}
+
+/// Route a relayed or data-channel frame from `from_user_id`. Returns false if `text` isn't
+/// a file-transfer frame, so the caller can pass it on.
+pub fn handle_frame(app: &AppHandle, from_user_id: &str, text: &str) -> bool {
+    let frame: Frame = match serde_json::from_str(text) {
+        Ok(frame) => frame,
+        Err(_) => return false,
+    };
+    let mut worker = match WORKER.lock() {
+        Ok(worker) => worker,
+        Err(_) => return true,
+    };
+    let tx = worker.get_or_insert_with(|| {
+        let (tx, rx) = mpsc::channel::<(AppHandle, String, Frame)>();
+        std::thread::spawn(move || {
+            let mut last_check = Instant::now();
+            loop {
+                match rx.recv_timeout(STALE_CHECK) {
+                    Ok((app, from, frame)) => {
+                        if let Err(e) = process_frame(&app, &from, frame) {
+                            eprintln!("[FileTransfer] {}", e);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+                if last_check.elapsed() >= STALE_CHECK {
+                    last_check = Instant::now();
+                    retry_stale();
+                }
+            }
+        });
+        tx
+    });
+    let _ = tx.send((app.clone(), from_user_id.to_string(), frame));
+    true
+}
+
+fn process_frame(app: &AppHandle, from: &str, frame: Frame) -> Result<(), String> {
+    if !valid_transfer_id(frame.transfer_id()) {
+        return Err(format!("Rejected frame from {}: invalid transfer id", from));
+    }
+    match frame {
+        Frame::Offer { manifest } => on_offer(app, from, manifest),
+        Frame::Request { transfer_id, pieces } => on_request(app, from, &transfer_id, pieces),
+        Frame::Block { transfer_id, piece, offset, data } => on_block(app, from, &transfer_id, piece, offset, &data),
+        Frame::Pause { transfer_id } => set_state(app, from, &transfer_id, TransferState::Paused),
+        Frame::Resume { transfer_id } => {
+            set_state(app, from, &transfer_id, TransferState::Active)?;
+            request_more(&transfer_id)
+        }
+        Frame::Cancel { transfer_id } => {
+            set_state(app, from, &transfer_id, TransferState::Cancelled)?;
+            discard_download(&transfer_id);
+            Ok(())
+        }
+        Frame::Done { transfer_id } => set_state(app, from, &transfer_id, TransferState::Complete),
+    }
+}
+
+fn on_offer(app: &AppHandle, from: &str, mut manifest: Manifest) -> Result<(), String> {
+    let pieces = manifest.size.div_ceil(PIECE_SIZE) as usize;
+    if manifest.piece_hashes.len() != pieces {
+        return Err(format!("Rejected transfer {}: piece count does not match size", manifest.transfer_id));
+    }
+    // Only ever a bare name: the sender doesn't get to pick a directory.
+    manifest.file_name = Path::new(manifest.file_name.trim())
+        .file_name()
+        .and_then(|n| n.to_str())
+        .filter(|n| !n.is_empty())
+        .unwrap_or("download.bin")
+        .to_string();
+
+    let mut incoming = INCOMING.lock().map_err(|_| "Failed to lock transfers".to_string())?;
+    if let Some(existing) = incoming.get(&manifest.transfer_id) {
+        if matches!(existing.state, TransferState::Active | TransferState::Paused) {
+            return Ok(());
+        }
+    }
+    let record = load_record(&manifest.transfer_id).filter(|r| r.manifest == manifest && r.from_user_id == from);
+    let entry = Incoming {
+        done: record.as_ref().map(|r| r.done.clone()).unwrap_or_else(|| vec![false; pieces]),
+        target_path: record.map(|r| r.target_path),
+        manifest,
+        from_user_id: from.to_string(),
+        state: TransferState::Offered,
+        file: None,
+        in_flight: BTreeMap::new(),
+        last_block: None,
+        error: None,
+    };
+    emit(app, incoming_progress(&entry));
+    incoming.insert(entry.manifest.transfer_id.clone(), entry);
+    Ok(())
+}
+
+/// Start (or continue) downloading an offered file. `target_for` picks the destination for
+/// a new download; a resumed one keeps its earlier location.
+pub fn accept(app: &AppHandle, transfer_id: &str, target_for: impl FnOnce(&str) -> PathBuf) -> Result<(), String> {
+    {
+        let mut transfers = INCOMING.lock().map_err(|_| "Failed to lock transfers".to_string())?;
+        let incoming = transfers
+            .get_mut(transfer_id)
+            .ok_or_else(|| "Unknown transfer".to_string())?;
+        if incoming.state != TransferState::Offered && incoming.state != TransferState::Paused {
+            return Ok(());
+        }
+        if incoming.target_path.is_none() {
+            incoming.target_path = Some(target_for(&incoming.manifest.file_name));
+        }
+        let part = incoming.part_path().ok_or_else(|| "Missing download path".to_string())?;
+        if !part.exists() {
+            incoming.done.iter_mut().for_each(|d| *d = false);
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&part)
+            .map_err(|e| format!("Failed to open download file: {}", e))?;
+        file.set_len(incoming.manifest.size)
+            .map_err(|e| format!("Failed to size download file: {}", e))?;
+        incoming.file = Some(file);
+        incoming.state = TransferState::Active;
+        save_record(incoming)?;
+        emit(app, incoming_progress(incoming));
+    }
+    if finished(transfer_id) {
+        return finalize(app, transfer_id);
+    }
+    request_more(transfer_id)
+}
+
+fn finished(transfer_id: &str) -> bool {
+    INCOMING
+        .lock()
+        .ok()
+        .and_then(|t| t.get(transfer_id).map(|i| i.done.iter().all(|d| *d)))
+        .unwrap_or(false)
+}
+
+/// Top the receiver's window back up with pieces it still needs.
+fn request_more(transfer_id: &str) -> Result<(), String> {
+    let (to, pieces) = {
+        let mut transfers = INCOMING.lock().map_err(|_| "Failed to lock transfers".to_string())?;
+        let incoming = match transfers.get_mut(transfer_id) {
+            Some(incoming) if incoming.state == TransferState::Active => incoming,
+            _ => return Ok(()),
+        };
+        let free = WINDOW.saturating_sub(incoming.in_flight.len());
+        let pieces: Vec<u32> = (0..incoming.done.len() as u32)
+            .filter(|p| !incoming.done[*p as usize] && !incoming.in_flight.contains_key(p))
+            .take(free)
+            .collect();
+        let now = Instant::now();
+        for piece in &pieces {
+            incoming.in_flight.insert(*piece, PieceRequest::new(now));
+        }
+        (incoming.from_user_id.clone(), pieces)
+    };
+    if pieces.is_empty() {
+        return Ok(());
+    }
+    send_frame(&to, &Frame::Request { transfer_id: transfer_id.to_string(), pieces })
+}
+
+/// Ask again for pieces whose request or blocks were lost.
+fn retry_stale() {
+    let now = Instant::now();
+    let retries: Vec<(String, String, Vec<u32>)> = match INCOMING.lock() {
+        Ok(mut transfers) => transfers
+            .iter_mut()
+            .filter(|(_, incoming)| incoming.state == TransferState::Active)
+            .filter_map(|(transfer_id, incoming)| {
+                let pieces = incoming.stale_pieces(now);
+                for piece in &pieces {
+                    if let Some(request) = incoming.in_flight.get_mut(piece) {
+                        // Each request is served on its own sender thread, so a retry starts
+                        // arriving right away.
+                        request.requested_at = now;
+                        request.last_block = Some(now);
+                    }
+                }
+                (!pieces.is_empty()).then(|| (transfer_id.clone(), incoming.from_user_id.clone(), pieces))
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    for (transfer_id, to, pieces) in retries {
+        eprintln!("[FileTransfer] {}: pieces {:?} stalled; requesting them again", transfer_id, pieces);
+        if let Err(e) = send_frame(&to, &Frame::Request { transfer_id, pieces }) {
+            eprintln!("[FileTransfer] {}", e);
+        }
+    }
+}
+
+fn on_request(app: &AppHandle, from: &str, transfer_id: &str, pieces: Vec<u32>) -> Result<(), String> {
+    let (path, manifest) = {
+        let mut transfers = OUTGOING.lock().map_err(|_| "Failed to lock transfers".to_string())?;
+        let outgoing = match transfers.get_mut(transfer_id) {
+            Some(outgoing) if outgoing.to_user_id == from => outgoing,
+            _ => return Ok(()),
+        };
+        if matches!(outgoing.state, TransferState::Cancelled | TransferState::Complete) {
+            return Ok(());
+        }
+        outgoing.state = TransferState::Active;
+        (outgoing.path.clone(), outgoing.manifest.clone())
+    };
+    let (app, to) = (app.clone(), from.to_string());
+    std::thread::spawn(move || {
+        if let Err(e) = send_pieces(&app, &to, &path, &manifest, &pieces) {
+            eprintln!("[FileTransfer] {}", e);
+        }
+    });
+    Ok(())
+}
+
+fn send_pieces(app: &AppHandle, to: &str, path: &Path, manifest: &Manifest, pieces: &[u32]) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut buf = vec![0u8; PIECE_SIZE as usize];
+    for &piece in pieces {
+        if piece as usize >= manifest.piece_hashes.len() {
+            continue;
+        }
+        let still_sending = OUTGOING
+            .lock()
+            .map(|t| t.get(&manifest.transfer_id).is_some_and(|o| o.state == TransferState::Active))
+            .unwrap_or(false);
+        if !still_sending {
+            return Ok(());
+        }
+        let len = manifest.piece_len(piece) as usize;
+        file.seek(SeekFrom::Start(piece as u64 * PIECE_SIZE))
+            .and_then(|_| file.read_exact(&mut buf[..len]))
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        for offset in (0..len).step_by(BLOCK_SIZE) {
+            let end = (offset + BLOCK_SIZE).min(len);
+            send_frame(to, &Frame::Block {
+                transfer_id: manifest.transfer_id.clone(),
+                piece,
+                offset: offset as u32,
+                data: base64::engine::general_purpose::STANDARD.encode(&buf[offset..end]),
+            })?;
+        }
+        if let Ok(mut transfers) = OUTGOING.lock() {
+            if let Some(outgoing) = transfers.get_mut(&manifest.transfer_id) {
+                outgoing.bytes_sent = (outgoing.bytes_sent + len as u64).min(manifest.size);
+                emit(app, outgoing_progress(outgoing));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn on_block(app: &AppHandle, from: &str, transfer_id: &str, piece: u32, offset: u32, data: &str) -> Result<(), String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid transfer block: {}", e))?;
+    let piece_done = {
+        let mut transfers = INCOMING.lock().map_err(|_| "Failed to lock transfers".to_string())?;
+        let incoming = match transfers.get_mut(transfer_id) {
+            Some(incoming) if incoming.from_user_id == from && incoming.state == TransferState::Active => incoming,
+            _ => return Ok(()),
+        };
+        if piece as usize >= incoming.done.len() || incoming.done[piece as usize] {
+            return Ok(());
+        }
+        let piece_len = incoming.manifest.piece_len(piece);
+        if offset as u64 + bytes.len() as u64 > piece_len {
+            return Err(format!("Transfer {}: block outside piece {}", transfer_id, piece));
+        }
+        let file = incoming.file.as_mut().ok_or_else(|| "Download file not open".to_string())?;
+        file.seek(SeekFrom::Start(piece as u64 * PIECE_SIZE + offset as u64))
+            .and_then(|_| file.write_all(&bytes))
+            .map_err(|e| format!("Failed to write download file: {}", e))?;
+        let now = Instant::now();
+        incoming.last_block = Some(now);
+        let request = incoming.in_flight.entry(piece).or_insert_with(|| PieceRequest::new(now));
+        request.last_block = Some(now);
+        request.blocks.insert(offset, bytes.len() as u32);
+        if request.blocks.values().map(|&l| l as u64).sum::<u64>() < piece_len {
+            return Ok(());
+        }
+
+        incoming.in_flight.remove(&piece);
+        let mut buf = vec![0u8; piece_len as usize];
+        file.seek(SeekFrom::Start(piece as u64 * PIECE_SIZE))
+            .and_then(|_| file.read_exact(&mut buf))
+            .map_err(|e| format!("Failed to read download file: {}", e))?;
+        if hex_sha256(&buf) == incoming.manifest.piece_hashes[piece as usize] {
+            incoming.done[piece as usize] = true;
+            save_record(incoming)?;
+            emit(app, incoming_progress(incoming));
+        } else {
+            eprintln!("[FileTransfer] {}: piece {} failed its hash check; requesting it again", transfer_id, piece);
+        }
+        incoming.done.iter().all(|d| *d)
+    };
+    if piece_done {
+        finalize(app, transfer_id)
+    } else {
+        request_more(transfer_id)
+    }
+}
+
+/// Check the whole file, move it into place and tell the sender.
+fn finalize(app: &AppHandle, transfer_id: &str) -> Result<(), String> {
+    let sender = {
+        let mut transfers = INCOMING.lock().map_err(|_| "Failed to lock transfers".to_string())?;
+        let incoming = transfers
+            .get_mut(transfer_id)
+            .ok_or_else(|| "Unknown transfer".to_string())?;
+        let (part, target) = match (incoming.part_path(), incoming.target_path.clone()) {
+            (Some(part), Some(target)) => (part, target),
+            _ => return Err("Missing download path".to_string()),
+        };
+        incoming.file = None;
+        let verified = crate::sha256_file_streaming(&part)
+            .map(|hash| hash == incoming.manifest.sha256)
+            .unwrap_or(false);
+        if verified {
+            std::fs::rename(&part, &target).map_err(|e| format!("Failed to finalize download: {}", e))?;
+            incoming.state = TransferState::Complete;
+        } else {
+            // Every piece matched, so the manifest itself was inconsistent; start over.
+            incoming.state = TransferState::Failed;
+            incoming.error = Some("Downloaded file does not match its checksum".to_string());
+            let _ = std::fs::remove_file(&part);
+        }
+        delete_record(transfer_id);
+        emit(app, incoming_progress(incoming));
+        verified.then(|| incoming.from_user_id.clone())
+    };
+    // Sent after the lock is released: a relayed frame may wait for its slot.
+    match sender {
+        Some(to) => send_frame(&to, &Frame::Done { transfer_id: transfer_id.to_string() }),
+        None => Ok(()),
+    }
+}
+
+fn set_state(app: &AppHandle, from: &str, transfer_id: &str, state: TransferState) -> Result<(), String> {
+    if let Some(outgoing) = OUTGOING
+        .lock()
+        .map_err(|_| "Failed to lock transfers".to_string())?
+        .get_mut(transfer_id)
+        .filter(|o| o.to_user_id == from)
+    {
+        outgoing.state = state;
+        emit(app, outgoing_progress(outgoing));
+        return Ok(());
+    }
+    if let Some(incoming) = INCOMING
+        .lock()
+        .map_err(|_| "Failed to lock transfers".to_string())?
+        .get_mut(transfer_id)
+        .filter(|i| i.from_user_id == from)
+    {
+        // The sender has nothing left to say about a download that already finished.
+        if incoming.state != TransferState::Complete {
+            incoming.state = state;
+            incoming.in_flight.clear();
+            emit(app, incoming_progress(incoming));
+        }
+    }
+    Ok(())
+}
+
+fn discard_download(transfer_id: &str) {
+    if let Ok(mut transfers) = INCOMING.lock() {
+        if let Some(incoming) = transfers.get_mut(transfer_id) {
+            incoming.file = None;
+            if let Some(part) = incoming.part_path() {
+                let _ = std::fs::remove_file(part);
+            }
+        }
+    }
+    delete_record(transfer_id);
+}
+
+/// The other side of `transfer_id`, whichever direction it runs.
+fn peer_of(transfer_id: &str) -> Option<String> {
+    let outgoing = OUTGOING.lock().ok()?.get(transfer_id).map(|o| o.to_user_id.clone());
+    outgoing.or_else(|| INCOMING.lock().ok()?.get(transfer_id).map(|i| i.from_user_id.clone()))
+}
+
+/// Pause locally and ask the peer to stop; in-flight pieces are requested again on resume.
+pub fn pause(app: &AppHandle, transfer_id: &str) -> Result<(), String> {
+    let peer = peer_of(transfer_id).ok_or_else(|| "Unknown transfer".to_string())?;
+    set_state(app, &peer, transfer_id, TransferState::Paused)?;
+    send_frame(&peer, &Frame::Pause { transfer_id: transfer_id.to_string() })
+}
+
+pub fn resume(app: &AppHandle, transfer_id: &str) -> Result<(), String> {
+    let peer = peer_of(transfer_id).ok_or_else(|| "Unknown transfer".to_string())?;
+    set_state(app, &peer, transfer_id, TransferState::Active)?;
+    send_frame(&peer, &Frame::Resume { transfer_id: transfer_id.to_string() })?;
+    request_more(transfer_id)
+}
+
+/// Stop for good; a partial download is deleted.
+pub fn cancel(app: &AppHandle, transfer_id: &str) -> Result<(), String> {
+    let peer = peer_of(transfer_id).ok_or_else(|| "Unknown transfer".to_string())?;
+    set_state(app, &peer, transfer_id, TransferState::Cancelled)?;
+    discard_download(transfer_id);
+    send_frame(&peer, &Frame::Cancel { transfer_id: transfer_id.to_string() })
+}
+
+pub fn list() -> Vec<TransferProgress> {
+    let mut all: Vec<TransferProgress> = OUTGOING
+        .lock()
+        .map(|t| t.values().map(outgoing_progress).collect())
+        .unwrap_or_default();
+    if let Ok(transfers) = INCOMING.lock() {
+        all.extend(transfers.values().map(incoming_progress));
+    }
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_pieces_cover_the_file() {
+        let dir = std::env::temp_dir().join(format!("cordia-transfer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clip.bin");
+        let data: Vec<u8> = (0..(PIECE_SIZE as usize * 2 + 123)).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let manifest = build_manifest(&path, "bob").unwrap();
+        assert_eq!(manifest.size, data.len() as u64);
+        assert_eq!(manifest.piece_hashes.len(), 3);
+        assert_eq!(manifest.piece_len(2), 123);
+        assert_eq!(manifest.piece_hashes[2], hex_sha256(&data[2 * PIECE_SIZE as usize..]));
+        assert_eq!(manifest.sha256, hex_sha256(&data));
+        // Same file to the same person resumes; to someone else it's a new transfer.
+        assert_eq!(build_manifest(&path, "bob").unwrap().transfer_id, manifest.transfer_id);
+        assert_ne!(build_manifest(&path, "carol").unwrap().transfer_id, manifest.transfer_id);
+        assert!(valid_transfer_id(&manifest.transfer_id));
+        assert!(!valid_transfer_id("../../settings"));
+        assert!(!valid_transfer_id(&manifest.transfer_id.to_uppercase()));
+
+        let frame: Frame = serde_json::from_str(r#"{"ft":"pause","transfer_id":"abc"}"#).unwrap();
+        assert_eq!(frame.transfer_id(), "abc");
+        assert!(serde_json::from_str::<Frame>(r#"{"type":"offer","sdp":"v=0"}"#).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stalled_pieces_are_found_without_hurrying_queued_ones() {
+        let start = Instant::now();
+        let later = start + PIECE_TIMEOUT;
+        let mut incoming = Incoming {
+            manifest: Manifest {
+                transfer_id: "0".repeat(32),
+                file_name: "clip.bin".to_string(),
+                size: PIECE_SIZE * 3,
+                sha256: String::new(),
+                piece_hashes: vec![String::new(); 3],
+            },
+            from_user_id: "alice".to_string(),
+            state: TransferState::Active,
+            target_path: None,
+            file: None,
+            done: vec![false; 3],
+            in_flight: (0..3).map(|p| (p, PieceRequest::new(start))).collect(),
+            last_block: None,
+            error: None,
+        };
+        // Nothing arrived at all: the request itself was lost.
+        assert_eq!(incoming.stale_pieces(later), vec![0, 1, 2]);
+
+        // Piece 0 lost a block and went quiet while piece 1 streams; piece 2 is just queued.
+        incoming.in_flight.get_mut(&0).unwrap().last_block = Some(start);
+        incoming.in_flight.get_mut(&1).unwrap().last_block = Some(later);
+        incoming.last_block = Some(later);
+        assert_eq!(incoming.stale_pieces(later), vec![0]);
+    }
+}
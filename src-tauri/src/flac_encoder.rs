@@ -0,0 +1,356 @@
+//! Minimal FLAC encoder for 16-bit mono recordings.
+//!
+//! No encoder crate fits our needs without pulling in C code, and voice audio doesn't
+//! need the full format: each block uses a constant subframe (silence), the best fixed
+//! predictor (order 0-4) with a single Rice partition, or verbatim samples, whichever is
+//! smallest. That already gets speech to roughly half the size of WAV.
+//!
+//! STREAMINFO is written up front and patched with the sample count on `finish`, so the
+//! output must be seekable. The MD5 signature is left zero ("not computed").
+
+use std::io::{Seek, SeekFrom, Write};
+
+/// Samples per FLAC block (fixed-blocksize stream; the last block may be shorter).
+const BLOCK_SIZE: usize = 4096;
+const BITS_PER_SAMPLE: u32 = 16;
+/// Largest Rice parameter for the 4-bit parameter encoding (15 is the escape code).
+const MAX_RICE_PARAM: u32 = 14;
+/// Byte offset of STREAMINFO's packed rate/channels/bps/total-samples field.
+const STREAMINFO_TOTAL_OFFSET: u64 = 4 + 4 + 10;
+
+pub struct FlacWriter<W: Write + Seek> {
+    out: W,
+    sample_rate: u32,
+    block: Vec<i32>,
+    frame_number: u64,
+    total_samples: u64,
+    bytes_written: u64,
+}
+
+impl<W: Write + Seek> FlacWriter<W> {
+    /// Start a mono 16-bit stream at `sample_rate`.
+    pub fn new(mut out: W, sample_rate: u32) -> std::io::Result<Self> {
+        let mut header = BitWriter::new();
+        header.write_bytes(b"fLaC");
+        // Metadata block header: last-block flag, type 0 (STREAMINFO), length 34.
+        header.write_bits(1, 1);
+        header.write_bits(0, 7);
+        header.write_bits(34, 24);
+        header.write_bits(BLOCK_SIZE as u64, 16); // min block size
+        header.write_bits(BLOCK_SIZE as u64, 16); // max block size
+        header.write_bits(0, 24); // min frame size (unknown)
+        header.write_bits(0, 24); // max frame size (unknown)
+        header.write_bits(sample_rate as u64, 20);
+        header.write_bits(0, 3); // channels - 1
+        header.write_bits((BITS_PER_SAMPLE - 1) as u64, 5);
+        header.write_bits(0, 36); // total samples, patched in finish()
+        header.write_bytes(&[0u8; 16]); // MD5 (not computed)
+        let bytes = header.into_bytes();
+        out.write_all(&bytes)?;
+        Ok(Self {
+            out,
+            sample_rate,
+            block: Vec::with_capacity(BLOCK_SIZE),
+            frame_number: 0,
+            total_samples: 0,
+            bytes_written: bytes.len() as u64,
+        })
+    }
+
+    /// Append samples in [-1.0, 1.0].
+    pub fn write_samples(&mut self, samples: &[f32]) -> std::io::Result<()> {
+        for &s in samples {
+            self.block.push((s.clamp(-1.0, 1.0) * 32767.0).round() as i32);
+            if self.block.len() == BLOCK_SIZE {
+                self.flush_block()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes written so far (excluding any partially filled block).
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flush the last block, patch STREAMINFO and return the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if !self.block.is_empty() {
+            self.flush_block()?;
+        }
+        // rate(20) | channels-1(3) | bps-1(5) | total samples(36) = 64 bits.
+        let packed = ((self.sample_rate as u64) << 44)
+            | ((BITS_PER_SAMPLE as u64 - 1) << 36)
+            | (self.total_samples & 0xF_FFFF_FFFF);
+        self.out.seek(SeekFrom::Start(STREAMINFO_TOTAL_OFFSET))?;
+        self.out.write_all(&packed.to_be_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn flush_block(&mut self) -> std::io::Result<()> {
+        let frame = encode_frame(&self.block, self.frame_number, self.sample_rate);
+        self.out.write_all(&frame)?;
+        self.bytes_written += frame.len() as u64;
+        self.total_samples += self.block.len() as u64;
+        self.frame_number += 1;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+/// Sample rate code for the frame header; 0 means "take it from STREAMINFO".
+fn sample_rate_code(rate: u32) -> u64 {
+    match rate {
+        88200 => 0b0001,
+        176400 => 0b0010,
+        192000 => 0b0011,
+        8000 => 0b0100,
+        16000 => 0b0101,
+        22050 => 0b0110,
+        24000 => 0b0111,
+        32000 => 0b1000,
+        44100 => 0b1001,
+        48000 => 0b1010,
+        96000 => 0b1011,
+        _ => 0b0000,
+    }
+}
+
+fn encode_frame(block: &[i32], frame_number: u64, sample_rate: u32) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    w.write_bits(0b11111111111110, 14); // sync
+    w.write_bits(0, 1); // reserved
+    w.write_bits(0, 1); // fixed blocksize
+    w.write_bits(0b0111, 4); // blocksize: 16-bit (n-1) follows
+    w.write_bits(sample_rate_code(sample_rate), 4);
+    w.write_bits(0b0000, 4); // mono
+    w.write_bits(0b100, 3); // 16 bits per sample
+    w.write_bits(0, 1); // reserved
+    w.write_utf8(frame_number);
+    w.write_bits(block.len() as u64 - 1, 16);
+    let crc = crc8(w.bytes());
+    w.write_bits(crc as u64, 8);
+
+    write_subframe(&mut w, block);
+
+    w.align();
+    let crc = crc16(w.bytes());
+    w.write_bits(crc as u64, 16);
+    w.into_bytes()
+}
+
+fn write_subframe(w: &mut BitWriter, block: &[i32]) {
+    let bps = BITS_PER_SAMPLE;
+    if block.iter().all(|&s| s == block[0]) {
+        // Constant (typically digital silence).
+        w.write_bits(0, 1);
+        w.write_bits(0b000000, 6);
+        w.write_bits(0, 1);
+        w.write_signed(block[0] as i64, bps);
+        return;
+    }
+
+    let verbatim_bits = block.len() as u64 * bps as u64;
+    let mut best: Option<(usize, u32, u64, Vec<i64>)> = None;
+    for order in 0..=4usize.min(block.len() - 1) {
+        let residual = fixed_residual(block, order);
+        let (param, bits) = best_rice_param(&residual);
+        let total = order as u64 * bps as u64 + 2 + 4 + 4 + bits;
+        if !matches!(&best, Some((_, _, b, _)) if *b <= total) {
+            best = Some((order, param, total, residual));
+        }
+    }
+
+    match best {
+        Some((order, param, bits, residual)) if bits < verbatim_bits => {
+            w.write_bits(0, 1);
+            w.write_bits(0b001000 | order as u64, 6);
+            w.write_bits(0, 1);
+            for &s in &block[..order] {
+                w.write_signed(s as i64, bps);
+            }
+            w.write_bits(0b00, 2); // Rice coding, 4-bit parameters
+            w.write_bits(0, 4); // partition order 0
+            w.write_bits(param as u64, 4);
+            for &r in &residual {
+                w.write_rice(r, param);
+            }
+        }
+        _ => {
+            w.write_bits(0, 1);
+            w.write_bits(0b000001, 6);
+            w.write_bits(0, 1);
+            for &s in block {
+                w.write_signed(s as i64, bps);
+            }
+        }
+    }
+}
+
+/// Residual of the fixed polynomial predictor of `order` (skips the warm-up samples).
+fn fixed_residual(block: &[i32], order: usize) -> Vec<i64> {
+    let s = |i: usize| block[i] as i64;
+    (order..block.len())
+        .map(|i| match order {
+            0 => s(i),
+            1 => s(i) - s(i - 1),
+            2 => s(i) - 2 * s(i - 1) + s(i - 2),
+            3 => s(i) - 3 * s(i - 1) + 3 * s(i - 2) - s(i - 3),
+            _ => s(i) - 4 * s(i - 1) + 6 * s(i - 2) - 4 * s(i - 3) + s(i - 4),
+        })
+        .collect()
+}
+
+fn zigzag(r: i64) -> u64 {
+    ((r << 1) ^ (r >> 63)) as u64
+}
+
+/// Rice parameter minimising the coded size, and that size in bits.
+fn best_rice_param(residual: &[i64]) -> (u32, u64) {
+    let mut best = (0, u64::MAX);
+    for k in 0..=MAX_RICE_PARAM {
+        let bits: u64 = residual
+            .iter()
+            .map(|&r| (zigzag(r) >> k) + 1 + k as u64)
+            .sum();
+        if bits < best.1 {
+            best = (k, bits);
+        }
+    }
+    best
+}
+
+/// CRC-8, polynomial x^8 + x^2 + x + 1 (frame header).
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-16, polynomial x^16 + x^15 + x^2 + 1 (whole frame).
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// MSB-first bit writer.
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::with_capacity(BLOCK_SIZE * 2),
+            acc: 0,
+            nbits: 0,
+        }
+    }
+
+    fn write_bits(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.acc = (self.acc << 1) | ((value >> i) & 1);
+            self.nbits += 1;
+            if self.nbits == 8 {
+                self.bytes.push(self.acc as u8);
+                self.acc = 0;
+                self.nbits = 0;
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write_bits(value as u64 & ((1u64 << bits) - 1), bits);
+    }
+
+    fn write_rice(&mut self, value: i64, k: u32) {
+        let u = zigzag(value);
+        let q = u >> k;
+        for _ in 0..q {
+            self.write_bits(0, 1);
+        }
+        self.write_bits(1, 1);
+        self.write_bits(u & ((1u64 << k) - 1), k);
+    }
+
+    /// FLAC's UTF-8-style variable-length integer (frame/sample numbers).
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write_bits(value, 8);
+            return;
+        }
+        let mut extra = 1;
+        while value >= 1u64 << (6 + 5 * extra) {
+            extra += 1;
+        }
+        let lead_bits = 6 - extra;
+        let lead_marker = (0xFFu64 << (7 - extra)) & 0xFF;
+        self.write_bits(lead_marker | (value >> (6 * extra)), 8);
+        debug_assert!(value >> (6 * extra) < 1u64 << lead_bits);
+        for i in (0..extra).rev() {
+            self.write_bits(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_bits(b as u64, 8);
+        }
+    }
+
+    /// Pad with zero bits to the next byte boundary.
+    fn align(&mut self) {
+        if self.nbits > 0 {
+            self.write_bits(0, 8 - self.nbits);
+        }
+    }
+
+    /// Completed bytes so far (callers align first when they need everything).
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trips_through_a_decoder() {
+        let samples: Vec<f32> = (0..48000 + 123)
+            .map(|i| (i as f32 * 0.05).sin() * 0.5 * if i % 10_000 < 2000 { 0.0 } else { 1.0 })
+            .collect();
+        let mut writer = FlacWriter::new(Cursor::new(Vec::new()), 48000).unwrap();
+        writer.write_samples(&samples).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+        assert!(bytes.len() < samples.len() * 2, "should compress below 16-bit PCM");
+
+        let mut reader = claxon::FlacReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.streaminfo().sample_rate, 48000);
+        assert_eq!(reader.streaminfo().samples, Some(samples.len() as u64));
+        let decoded: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        let expected: Vec<i32> = samples.iter().map(|s| (s * 32767.0).round() as i32).collect();
+        assert_eq!(decoded, expected);
+    }
+}
@@ -0,0 +1,346 @@
+//! System-wide keybinds, so push-to-talk and the other voice controls work while another
+//! window has focus.
+//!
+//! Push-to-talk comes from the audio settings' `push_to_talk_key` and is registered only
+//! while the input mode uses PTT; the other actions are the `keybinds` list in the app
+//! settings. Bindings use the frontend's key-string format ("Ctrl+Shift+T", "F13", " " for
+//! Space). PTT and mute/deafen are handled here (DSP gate, transmission mute, output mute);
+//! every action is also emitted as `cordia:keybind` (`cordia:global-ptt` for PTT) so the UI
+//! can follow, and actions with no native side are left to the frontend.
+//!
+//! The OS hotkey APIs are tied to the thread that created the manager, so registration is
+//! queued onto the main thread; its outcome, including why a binding couldn't be used, is
+//! emitted as `cordia:keybind-status`.
+
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::audio_settings::AudioSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeybindAction {
+    PushToTalk,
+    ToggleMute,
+    /// Silence remote audio and the mic together.
+    ToggleDeafen,
+    /// Join the voice channel of `Keybind::server_id`.
+    SwitchVoiceChannel,
+    /// Bring the main window to the front.
+    OpenOverlay,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keybind {
+    pub action: KeybindAction,
+    pub binding: String,
+    /// Target server for `switch_voice_channel`.
+    #[serde(default)]
+    pub server_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeybindStatus {
+    #[serde(flatten)]
+    pub keybind: Keybind,
+    pub registered: bool,
+    /// Why the binding couldn't be registered.
+    pub conflict: Option<String>,
+}
+
+/// Payload of `cordia:keybind`, with the state after any native handling.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeybindEvent {
+    pub action: KeybindAction,
+    pub server_id: Option<String>,
+    pub muted: Option<bool>,
+    pub deafened: Option<bool>,
+}
+
+thread_local! {
+    /// Main thread only.
+    static MANAGER: RefCell<Option<GlobalHotKeyManager>> = const { RefCell::new(None) };
+    static REGISTERED: RefCell<Vec<HotKey>> = const { RefCell::new(Vec::new()) };
+}
+/// Registered hotkey ids and what they trigger, for the event handler.
+static ACTIONS: Mutex<Option<HashMap<u32, Keybind>>> = Mutex::new(None);
+/// Current PTT binding (from the audio settings); `None` while PTT is off.
+static PTT_BINDING: Mutex<Option<String>> = Mutex::new(None);
+static STATUS: Mutex<Vec<KeybindStatus>> = Mutex::new(Vec::new());
+/// Set when the manager couldn't be created (e.g. a Wayland session without X11).
+static UNAVAILABLE: Mutex<Option<String>> = Mutex::new(None);
+/// Mute state from before deafening, restored on undeafen.
+static MUTED_BEFORE_DEAFEN: Mutex<Option<bool>> = Mutex::new(None);
+
+/// Create the manager and register the saved bindings. Call from `setup` (main thread).
+pub fn init(app: &AppHandle) {
+    match GlobalHotKeyManager::new() {
+        Ok(manager) => MANAGER.with(|m| *m.borrow_mut() = Some(manager)),
+        Err(e) => {
+            if let Ok(mut unavailable) = UNAVAILABLE.lock() {
+                *unavailable = Some(format!("Global hotkeys are unavailable: {}", e));
+            }
+        }
+    }
+    let event_app = app.clone();
+    GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+        let keybind = ACTIONS
+            .lock()
+            .ok()
+            .and_then(|actions| actions.as_ref()?.get(&event.id()).cloned());
+        if let Some(keybind) = keybind {
+            dispatch(&event_app, &keybind, event.state() == HotKeyState::Pressed);
+        }
+    }));
+
+    let settings = crate::audio_settings::AudioSettingsManager::new()
+        .ok()
+        .and_then(|manager| manager.load_settings().ok())
+        .unwrap_or_default();
+    set_push_to_talk(app, &settings);
+}
+
+/// Take the PTT binding from `settings` and re-register. Returns at once.
+pub fn set_push_to_talk(app: &AppHandle, settings: &AudioSettings) {
+    let uses_ptt = matches!(settings.input_mode.as_str(), "push_to_talk" | "hybrid");
+    if let Ok(mut ptt) = PTT_BINDING.lock() {
+        *ptt = settings.push_to_talk_key.clone().filter(|key| uses_ptt && !key.is_empty());
+    }
+    refresh(app);
+}
+
+/// Re-register everything from the current settings. Returns at once.
+pub fn refresh(app: &AppHandle) {
+    let mut keybinds: Vec<Keybind> = PTT_BINDING
+        .lock()
+        .ok()
+        .and_then(|ptt| ptt.clone())
+        .map(|binding| Keybind { action: KeybindAction::PushToTalk, binding, server_id: None })
+        .into_iter()
+        .collect();
+    keybinds.extend(crate::settings::current().keybinds);
+
+    let status_app = app.clone();
+    let queued = app.run_on_main_thread(move || {
+        let status = register_all(&status_app, keybinds);
+        if let Ok(mut current) = STATUS.lock() {
+            *current = status.clone();
+        }
+        let _ = status_app.emit_all("cordia:keybind-status", status);
+    });
+    if let Err(e) = queued {
+        eprintln!("[Hotkeys] Failed to schedule registration: {}", e);
+    }
+}
+
+pub fn status() -> Vec<KeybindStatus> {
+    STATUS.lock().map(|status| status.clone()).unwrap_or_default()
+}
+
+fn dispatch(app: &AppHandle, keybind: &Keybind, pressed: bool) {
+    if keybind.action == KeybindAction::PushToTalk {
+        set_ptt(app, pressed);
+        return;
+    }
+    // Everything else fires on press.
+    if !pressed {
+        return;
+    }
+    let mut event = KeybindEvent {
+        action: keybind.action,
+        server_id: keybind.server_id.clone(),
+        muted: None,
+        deafened: None,
+    };
+    match keybind.action {
+        KeybindAction::ToggleMute => {
+            if let Ok(mut dsp) = crate::audio_dsp::get_dsp().lock() {
+                let muted = !dsp.transmission_muted();
+                dsp.set_transmission_muted(muted);
+                event.muted = Some(muted);
+            }
+        }
+        KeybindAction::ToggleDeafen => {
+            let deafened = !crate::audio_output::is_deafened();
+            crate::audio_output::set_deafened(deafened);
+            if let (Ok(mut dsp), Ok(mut before)) = (crate::audio_dsp::get_dsp().lock(), MUTED_BEFORE_DEAFEN.lock()) {
+                let muted = if deafened {
+                    *before = Some(dsp.transmission_muted());
+                    true
+                } else {
+                    before.take().unwrap_or(false)
+                };
+                dsp.set_transmission_muted(muted);
+                event.muted = Some(muted);
+            }
+            event.deafened = Some(deafened);
+        }
+        KeybindAction::OpenOverlay => {
+            if let Some(window) = app.get_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }
+        KeybindAction::SwitchVoiceChannel | KeybindAction::PushToTalk => {}
+    }
+    let _ = app.emit_all("cordia:keybind", event);
+}
+
+fn set_ptt(app: &AppHandle, pressed: bool) {
+    if let Ok(mut dsp) = crate::audio_dsp::get_dsp().lock() {
+        dsp.set_ptt_pressed(pressed);
+    }
+    let _ = app.emit_all("cordia:global-ptt", pressed);
+}
+
+/// Main thread only.
+fn register_all(app: &AppHandle, keybinds: Vec<Keybind>) -> Vec<KeybindStatus> {
+    let mut statuses: Vec<KeybindStatus> = keybinds
+        .into_iter()
+        .map(|keybind| KeybindStatus { keybind, registered: false, conflict: None })
+        .collect();
+    if let Some(reason) = UNAVAILABLE.lock().ok().and_then(|u| u.clone()) {
+        statuses.iter_mut().for_each(|s| s.conflict = Some(reason.clone()));
+        return statuses;
+    }
+    MANAGER.with(|manager| {
+        let manager = manager.borrow();
+        let manager = match manager.as_ref() {
+            Some(manager) => manager,
+            None => {
+                statuses
+                    .iter_mut()
+                    .for_each(|s| s.conflict = Some("Global hotkeys are not initialized".to_string()));
+                return;
+            }
+        };
+
+        let had_ptt = ACTIONS
+            .lock()
+            .ok()
+            .and_then(|mut a| a.take())
+            .is_some_and(|a| a.values().any(|k| k.action == KeybindAction::PushToTalk));
+        for old in REGISTERED.with(|r| std::mem::take(&mut *r.borrow_mut())) {
+            let _ = manager.unregister(old);
+        }
+        // The release may never arrive once the key is gone.
+        if had_ptt {
+            set_ptt(app, false);
+        }
+
+        let mut actions: HashMap<u32, Keybind> = HashMap::new();
+        for status in statuses.iter_mut() {
+            let hotkey = match parse_binding(&status.keybind.binding) {
+                Ok(hotkey) => hotkey,
+                Err(reason) => {
+                    status.conflict = Some(reason);
+                    continue;
+                }
+            };
+            if let Some(other) = actions.get(&hotkey.id()) {
+                status.conflict = Some(format!("Same key as {}", action_label(other.action)));
+                continue;
+            }
+            match manager.register(hotkey) {
+                Ok(()) => {
+                    REGISTERED.with(|r| r.borrow_mut().push(hotkey));
+                    actions.insert(hotkey.id(), status.keybind.clone());
+                    status.registered = true;
+                }
+                Err(e) => {
+                    status.conflict = Some(format!("Already in use by another application or the system ({})", e));
+                }
+            }
+        }
+        if let Ok(mut current) = ACTIONS.lock() {
+            *current = Some(actions);
+        }
+    });
+    statuses
+}
+
+fn action_label(action: KeybindAction) -> &'static str {
+    match action {
+        KeybindAction::PushToTalk => "Push to Talk",
+        KeybindAction::ToggleMute => "Toggle Mute",
+        KeybindAction::ToggleDeafen => "Toggle Deafen",
+        KeybindAction::SwitchVoiceChannel => "Switch Voice Channel",
+        KeybindAction::OpenOverlay => "Open Overlay",
+    }
+}
+
+/// Parse a frontend key string. Bare typing keys are refused: grabbing one system-wide
+/// would stop it from typing anywhere else.
+fn parse_binding(binding: &str) -> Result<HotKey, String> {
+    let tokens: Vec<&str> = binding
+        .split('+')
+        .map(|token| match token {
+            " " => "Space",
+            "Meta" => "Super",
+            token => token,
+        })
+        .collect();
+    let is_modifier = |t: &&str| matches!(t.to_uppercase().as_str(), "CTRL" | "CONTROL" | "SHIFT" | "ALT" | "OPTION" | "SUPER");
+    if tokens.iter().all(is_modifier) {
+        return Err("Modifier keys alone can't be a global hotkey; add another key".to_string());
+    }
+    let hotkey = HotKey::from_str(&tokens.join("+"))
+        .map_err(|e| format!("Unsupported key for a global hotkey: {}", e))?;
+    if !hotkey.mods.intersects(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SUPER) && is_typing_key(hotkey.key) {
+        return Err(format!(
+            "\"{}\" would stop typing in other apps; add Ctrl/Alt or use a key like F13",
+            binding
+        ));
+    }
+    Ok(hotkey)
+}
+
+fn is_typing_key(code: Code) -> bool {
+    use Code::*;
+    let name = code.to_string();
+    name.starts_with("Key")
+        || name.starts_with("Digit")
+        || matches!(
+            code,
+            Space | Enter | Tab | Backspace | Delete | Minus | Equal | BracketLeft | BracketRight | Backslash
+                | Semicolon | Quote | Backquote | Comma | Period | Slash
+                | ArrowUp | ArrowDown | ArrowLeft | ArrowRight | Home | End | PageUp | PageDown
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frontend_bindings() {
+        let hotkey = parse_binding("Ctrl+Shift+T").unwrap();
+        assert_eq!(hotkey.mods, Modifiers::CONTROL | Modifiers::SHIFT);
+        assert_eq!(hotkey.key, Code::KeyT);
+        assert_eq!(parse_binding("Alt+ ").unwrap().key, Code::Space);
+        assert_eq!(parse_binding("Meta+F5").unwrap().mods, Modifiers::SUPER);
+        assert_eq!(parse_binding("F13").unwrap().key, Code::F13);
+
+        assert!(parse_binding("Ctrl+Shift").is_err());
+        assert!(parse_binding("T").is_err());
+        assert!(parse_binding("Shift+ ").is_err());
+    }
+
+    #[test]
+    fn keybinds_round_trip_through_settings_json() {
+        let keybind: Keybind =
+            serde_json::from_str(r#"{"action":"switch_voice_channel","binding":"Ctrl+Alt+1","server_id":"s1"}"#).unwrap();
+        assert_eq!(keybind.action, KeybindAction::SwitchVoiceChannel);
+        let mute: Keybind = serde_json::from_str(r#"{"action":"toggle_mute","binding":"F14"}"#).unwrap();
+        assert_eq!(mute.server_id, None);
+        let status = serde_json::to_value(KeybindStatus { keybind: mute, registered: true, conflict: None }).unwrap();
+        assert_eq!(status["action"], "toggle_mute");
+    }
+}
@@ -0,0 +1,150 @@
+//! ICE servers for WebRTC peer connections.
+//!
+//! Public STUN servers are always included. When the beacon hands out TURN credentials
+//! (`GET /api/turn/credentials`, in the TURN REST API shape: `username`, `password`, `ttl`,
+//! `uris`), they are added as a relay so peers behind symmetric NATs can still connect.
+//! Credentials are time-limited and cached per beacon until most of their `ttl` has passed.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Order matters: faster reflexive candidates first.
+pub const STUN_URLS: [&str; 3] = [
+    "stun:stun.cloudflare.com:3478",
+    "stun:stun.l.google.com:19302",
+    "stun:global.stun.twilio.com:3478",
+];
+/// Beacons without a TURN endpoint are asked again after this long.
+const NO_TURN_RECHECK: Duration = Duration::from_secs(10 * 60);
+
+/// One entry of `RTCConfiguration.iceServers`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TurnCredentials {
+    username: String,
+    password: String,
+    /// Seconds the credentials stay valid.
+    ttl: u64,
+    uris: Vec<String>,
+}
+
+struct Cached {
+    beacon_url: String,
+    turn: Option<IceServer>,
+    refresh_at: Instant,
+}
+
+static CACHE: Mutex<Option<Cached>> = Mutex::new(None);
+
+fn stun_servers() -> Vec<IceServer> {
+    vec![IceServer {
+        urls: STUN_URLS.iter().map(|u| u.to_string()).collect(),
+        username: None,
+        credential: None,
+    }]
+}
+
+/// ICE servers for peers meeting through `beacon_url`: STUN, plus the beacon's TURN relay
+/// when it offers one. A beacon that can't be reached yields STUN only.
+pub async fn get_ice_servers(beacon_url: &str) -> Vec<IceServer> {
+    let mut servers = stun_servers();
+    match turn_server(beacon_url).await {
+        Ok(Some(turn)) => servers.push(turn),
+        Ok(None) => {}
+        Err(e) => eprintln!("[ICE] {}", e),
+    }
+    servers
+}
+
+async fn turn_server(beacon_url: &str) -> Result<Option<IceServer>, String> {
+    let beacon_url = beacon_url.trim().trim_end_matches('/');
+    if let Ok(cache) = CACHE.lock() {
+        if let Some(cached) = cache.as_ref() {
+            if cached.beacon_url == beacon_url && Instant::now() < cached.refresh_at {
+                return Ok(cached.turn.clone());
+            }
+        }
+    }
+
+    let (turn, valid_for) = match fetch_credentials(beacon_url).await? {
+        Some(creds) => {
+            // Refresh well before expiry so a connection set up late in the window still works.
+            let valid_for = Duration::from_secs(creds.ttl.saturating_mul(4) / 5);
+            let turn = IceServer {
+                urls: creds.uris,
+                username: Some(creds.username),
+                credential: Some(creds.password),
+            };
+            (Some(turn).filter(|t| !t.urls.is_empty()), valid_for)
+        }
+        None => (None, NO_TURN_RECHECK),
+    };
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some(Cached {
+            beacon_url: beacon_url.to_string(),
+            turn: turn.clone(),
+            refresh_at: Instant::now() + valid_for,
+        });
+    }
+    Ok(turn)
+}
+
+/// `Ok(None)` if the beacon has no TURN endpoint.
+async fn fetch_credentials(beacon_url: &str) -> Result<Option<TurnCredentials>, String> {
+    let base = beacon_url
+        .replacen("wss://", "https://", 1)
+        .replacen("ws://", "http://", 1);
+    let client = crate::beacon_tls::http_client(beacon_url)?;
+    let request = client
+        .get(format!("{}/api/turn/credentials", base))
+        .timeout(Duration::from_secs(5));
+    let resp = crate::beacon_tls::send(beacon_url, request)
+        .await
+        .map_err(|e| format!("Failed to fetch TURN credentials: {}", e))?;
+
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(format!("Failed to fetch TURN credentials: HTTP {}", resp.status()));
+    }
+    resp.json::<TurnCredentials>()
+        .await
+        .map(Some)
+        .map_err(|e| format!("Failed to parse TURN credentials: {}", e))
+}
+
+/// Forget cached credentials (e.g. on sign-out or beacon change).
+pub fn clear_cache() {
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_like_rtc_ice_server() {
+        let json = serde_json::to_value(stun_servers()).unwrap();
+        assert_eq!(json[0]["urls"][0], STUN_URLS[0]);
+        assert!(json[0].get("username").is_none() && json[0].get("credential").is_none());
+
+        let creds: TurnCredentials = serde_json::from_str(
+            r#"{"username":"1700000000:alice","password":"secret","ttl":86400,"uris":["turn:turn.example:3478?transport=udp"]}"#,
+        )
+        .unwrap();
+        assert_eq!(creds.ttl, 86400);
+        assert_eq!(creds.uris.len(), 1);
+    }
+}
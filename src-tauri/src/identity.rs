@@ -1,9 +1,10 @@
-use ed25519_dalek::{SigningKey, VerifyingKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use sha2::{Sha256, Digest};
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
     Aes256Gcm, Nonce,
 };
+use base64::Engine;
 use chacha20poly1305::XChaCha20Poly1305;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -39,7 +40,8 @@ pub struct UserIdentity {
     pub private_key: Option<String>, // Hex-encoded private key (only in memory; not in exports)
 }
 
-/// Format stored in keys.dat (encrypted). Includes private_key so it survives save/load.
+/// Format stored in keys.dat (encrypted). `private_key` is only set when the OS keychain
+/// couldn't take it.
 #[derive(Serialize, Deserialize)]
 struct StoredIdentity {
     pub user_id: String,
@@ -261,12 +263,25 @@ impl IdentityManager {
         
         let stored: StoredIdentity = serde_json::from_slice(&plaintext)
             .map_err(|_| IdentityError::InvalidIdentity)?;
+        let in_file = stored.private_key.is_some();
+        let private_key = match (stored.private_key, &self.account_id) {
+            (Some(key), _) => Some(key),
+            (None, Some(account_id)) => crate::secrets::get(account_id, crate::secrets::IDENTITY_KEY)
+                .map_err(IdentityError::Decryption)?,
+            (None, None) => None,
+        };
         let identity = UserIdentity {
             user_id: stored.user_id,
             display_name: stored.display_name,
             public_key: stored.public_key,
-            private_key: stored.private_key,
+            private_key,
         };
+        // Older keys.dat files hold the private key; move it to the keychain when there is one.
+        if in_file && self.account_id.is_some() {
+            if let Err(e) = self.save_identity(&identity) {
+                eprintln!("[Identity] Failed to move private key to keychain: {}", e);
+            }
+        }
         Ok(identity)
     }
 
@@ -276,12 +291,25 @@ impl IdentityManager {
         let device_key = Self::get_device_key()?;
         let key = Self::derive_key_from_device(&device_key, &salt)?;
         
-        // Serialize identity (use StoredIdentity so private_key is persisted in keys.dat)
+        // The private key goes to the OS keychain when one is available, otherwise into
+        // keys.dat (StoredIdentity keeps it there).
+        let in_keychain = match (&identity.private_key, &self.account_id) {
+            (Some(key), Some(account_id)) => {
+                match crate::secrets::set(account_id, crate::secrets::IDENTITY_KEY, key) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        eprintln!("[Identity] Keychain unavailable, keeping private key in keys.dat: {}", e);
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
         let stored = StoredIdentity {
             user_id: identity.user_id.clone(),
             display_name: identity.display_name.clone(),
             public_key: identity.public_key.clone(),
-            private_key: identity.private_key.clone(),
+            private_key: if in_keychain { None } else { identity.private_key.clone() },
         };
         let plaintext = serde_json::to_vec(&stored)?;
         
@@ -305,6 +333,20 @@ impl IdentityManager {
         Ok(())
     }
 
+    /// The account's Ed25519 signing key. Stays in Rust; the webview only ever sees signatures.
+    pub fn signing_key(&self) -> Result<SigningKey, IdentityError> {
+        let identity = self.load_identity()?;
+        let private_key_hex = identity.private_key.ok_or(IdentityError::InvalidIdentity)?;
+        let bytes = hex::decode(private_key_hex)
+            .map_err(|e| IdentityError::HexDecode(e.to_string()))?;
+        let bytes: [u8; 32] = bytes.as_slice().try_into().map_err(|_| IdentityError::InvalidIdentity)?;
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<Signature, IdentityError> {
+        Ok(self.signing_key()?.sign(message))
+    }
+
     fn get_device_key() -> Result<String, IdentityError> {
         // Derive a device-specific key from machine identifier
         // This makes the encryption device-bound (passwordless)
@@ -621,3 +663,87 @@ impl IdentityManager {
 
 }
 
+/// Prefix on every message the webview has signed with the identity key, so those
+/// signatures can't stand in for anything the native side signs for itself.
+pub const USER_SIGN_CONTEXT: &str = "cordia-user-sign:";
+
+/// Contexts the native side signs under; webview messages may not start with any of them.
+const NATIVE_SIGN_CONTEXTS: [&str; 3] = [
+    crate::signaling::AUTH_CONTEXT,
+    crate::MEMBER_LEAVE_CONTEXT,
+    USER_SIGN_CONTEXT,
+];
+
+/// The bytes actually signed (and verified) for a webview `message`.
+pub fn user_sign_payload(message: &str) -> Result<String, String> {
+    match NATIVE_SIGN_CONTEXTS.iter().find(|context| message.starts_with(**context)) {
+        Some(context) => Err(format!("Refusing to sign a message in the {} context", context)),
+        None => Ok(format!("{}{}", USER_SIGN_CONTEXT, message)),
+    }
+}
+
+/// Check an Ed25519 signature (base64) over `message` by the hex-encoded `public_key`.
+pub fn verify_signature(public_key: &str, message: &[u8], signature_b64: &str) -> Result<bool, IdentityError> {
+    let verifying_key = parse_public_key(public_key)?;
+    let signature = base64::engine::general_purpose::STANDARD.decode(signature_b64.trim())
+        .map_err(|e| IdentityError::Decryption(format!("Invalid signature encoding: {}", e)))?;
+    let signature = Signature::from_slice(&signature)
+        .map_err(|e| IdentityError::Decryption(format!("Invalid signature: {}", e)))?;
+    Ok(verifying_key.verify(message, &signature).is_ok())
+}
+
+/// The user_id of a hex-encoded public key: the first 16 bytes of its SHA-256, as hex.
+pub fn user_id_for_key(public_key: &str) -> Result<String, IdentityError> {
+    let verifying_key = parse_public_key(public_key)?;
+    Ok(hex::encode(&Sha256::digest(verifying_key.as_bytes())[..16]))
+}
+
+/// Human-comparable fingerprint of a hex-encoded public key: the first 20 bytes of its
+/// SHA-256, as ten groups of four hex digits. Two people reading these to each other can
+/// confirm they hold each other's real keys.
+pub fn key_fingerprint(public_key: &str) -> Result<String, IdentityError> {
+    let verifying_key = parse_public_key(public_key)?;
+    let hash = Sha256::digest(verifying_key.as_bytes());
+    let hex = hex::encode_upper(&hash[..20]);
+    Ok(hex
+        .as_bytes()
+        .chunks(4)
+        .map(|group| std::str::from_utf8(group).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+fn parse_public_key(public_key: &str) -> Result<VerifyingKey, IdentityError> {
+    let bytes = hex::decode(public_key.trim()).map_err(|e| IdentityError::HexDecode(e.to_string()))?;
+    let bytes: [u8; 32] = bytes.as_slice().try_into().map_err(|_| IdentityError::InvalidIdentity)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| IdentityError::InvalidIdentity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_only_for_the_signed_message_and_key() {
+        let (signing_key, verifying_key) = IdentityManager::generate_keypair();
+        let public_key = hex::encode(verifying_key.to_bytes());
+        let signature = base64::engine::general_purpose::STANDARD.encode(signing_key.sign(b"hello").to_bytes());
+        assert!(verify_signature(&public_key, b"hello", &signature).unwrap());
+        assert!(!verify_signature(&public_key, b"hellO", &signature).unwrap());
+        let (_, other) = IdentityManager::generate_keypair();
+        assert!(!verify_signature(&hex::encode(other.to_bytes()), b"hello", &signature).unwrap());
+
+        let fingerprint = key_fingerprint(&public_key).unwrap();
+        assert_eq!(fingerprint.len(), 49);
+        assert_eq!(fingerprint.split(' ').count(), 10);
+        assert!(key_fingerprint("abcd").is_err());
+    }
+
+    #[test]
+    fn webview_messages_are_prefixed_and_kept_out_of_native_contexts() {
+        assert_eq!(user_sign_payload("hi").unwrap(), "cordia-user-sign:hi");
+        assert!(user_sign_payload("cordia-signaling-auth:abc").is_err());
+        assert!(user_sign_payload("cordia-member-left:abc\n1").is_err());
+        assert!(user_sign_payload("cordia-user-sign:hi").is_err());
+    }
+}
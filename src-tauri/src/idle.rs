@@ -0,0 +1,251 @@
+//! Away detection from the OS input idle time (no keyboard or mouse input anywhere).
+//!
+//! A background thread reads the idle time every `POLL_INTERVAL` and emits
+//! `cordia:idle-changed` when the user crosses `settings::IdleSettings::after_minutes` in
+//! either direction, so presence can drop to idle and come back. Sources: Windows
+//! `GetLastInputInfo`, macOS `CGEventSourceSecondsSinceLastEventType`, and on Linux the X11
+//! screensaver extension, or on Wayland (where X11 only sees XWayland clients) the GNOME
+//! Mutter / freedesktop ScreenSaver idle monitors over D-Bus. Without a source the user is
+//! never reported idle.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IdleState {
+    pub idle: bool,
+    /// Seconds since the last input; `None` when the platform can't tell.
+    pub idle_secs: Option<u64>,
+}
+
+static STATE: Mutex<Option<IdleState>> = Mutex::new(None);
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Start watching idle time. Call once from `setup`.
+pub fn start(app: AppHandle) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || {
+        let mut source = platform::IdleSource::new();
+        if source.is_none() {
+            eprintln!("[Idle] No input idle source on this system; idle detection is off");
+        }
+        loop {
+            let idle_time = source.as_mut().and_then(|source| source.idle_time());
+            let settings = crate::settings::current().idle;
+            let idle = settings.enabled
+                && idle_time.is_some_and(|t| t >= Duration::from_secs(u64::from(settings.after_minutes) * 60));
+            let state = IdleState { idle, idle_secs: idle_time.map(|t| t.as_secs()) };
+
+            let changed = STATE
+                .lock()
+                .map(|mut current| {
+                    let changed = current.as_ref().map(|c| c.idle) != Some(idle);
+                    *current = Some(state.clone());
+                    changed
+                })
+                .unwrap_or(false);
+            if changed {
+                let _ = app.emit_all("cordia:idle-changed", state);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+pub fn state() -> IdleState {
+    STATE.lock().ok().and_then(|state| state.clone()).unwrap_or_default()
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::time::Duration;
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub struct IdleSource;
+
+    impl IdleSource {
+        pub fn new() -> Option<Self> {
+            Some(Self)
+        }
+
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+            if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+                return None;
+            }
+            // Both are 32-bit tick counts; wrapping_sub survives the 49.7-day rollover.
+            let ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+            Some(Duration::from_millis(u64::from(ms)))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::time::Duration;
+
+    const HID_SYSTEM_STATE: i32 = 1;
+    const ANY_INPUT_EVENT: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    }
+
+    pub struct IdleSource;
+
+    impl IdleSource {
+        pub fn new() -> Option<Self> {
+            Some(Self)
+        }
+
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            let secs = unsafe { CGEventSourceSecondsSinceLastEventType(HID_SYSTEM_STATE, ANY_INPUT_EVENT) };
+            (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::time::Duration;
+    use x11_dl::xlib::{Display, Xlib};
+    use x11_dl::xss::{XScreenSaverInfo, Xss};
+
+    pub enum IdleSource {
+        X11(Box<X11Idle>),
+        DBus(DBusIdle),
+    }
+
+    impl IdleSource {
+        pub fn new() -> Option<Self> {
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                DBusIdle::connect().map(Self::DBus)
+            } else {
+                X11Idle::open().map(|x11| Self::X11(Box::new(x11))).or_else(|| DBusIdle::connect().map(Self::DBus))
+            }
+        }
+
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            match self {
+                Self::X11(x11) => x11.idle_time(),
+                Self::DBus(dbus) => dbus.idle_time(),
+            }
+        }
+    }
+
+    /// The MIT-SCREEN-SAVER extension, loaded at runtime so libXss isn't a link dependency.
+    pub struct X11Idle {
+        xlib: Xlib,
+        xss: Xss,
+        display: *mut Display,
+        info: *mut XScreenSaverInfo,
+    }
+
+    impl X11Idle {
+        fn open() -> Option<Self> {
+            let xlib = Xlib::open().ok()?;
+            let xss = Xss::open().ok()?;
+            let display = unsafe { (xlib.XOpenDisplay)(std::ptr::null()) };
+            if display.is_null() {
+                return None;
+            }
+            let info = unsafe { (xss.XScreenSaverAllocInfo)() };
+            if info.is_null() {
+                unsafe { (xlib.XCloseDisplay)(display) };
+                return None;
+            }
+            Some(Self { xlib, xss, display, info })
+        }
+
+        fn idle_time(&mut self) -> Option<Duration> {
+            unsafe {
+                let root = (self.xlib.XDefaultRootWindow)(self.display);
+                if (self.xss.XScreenSaverQueryInfo)(self.display, root, self.info) == 0 {
+                    return None;
+                }
+                Some(Duration::from_millis((*self.info).idle))
+            }
+        }
+    }
+
+    impl Drop for X11Idle {
+        fn drop(&mut self) {
+            unsafe {
+                (self.xlib.XFree)(self.info.cast());
+                (self.xlib.XCloseDisplay)(self.display);
+            }
+        }
+    }
+
+    pub struct DBusIdle {
+        connection: zbus::blocking::Connection,
+    }
+
+    impl DBusIdle {
+        fn connect() -> Option<Self> {
+            let idle = Self { connection: zbus::blocking::Connection::session().ok()? };
+            // Keep the connection only if some compositor answers.
+            idle.idle_time().map(|_| idle)
+        }
+
+        fn idle_time(&self) -> Option<Duration> {
+            self.mutter().or_else(|| self.screensaver())
+        }
+
+        /// GNOME.
+        fn mutter(&self) -> Option<Duration> {
+            let reply = self
+                .connection
+                .call_method(
+                    Some("org.gnome.Mutter.IdleMonitor"),
+                    "/org/gnome/Mutter/IdleMonitor/Core",
+                    Some("org.gnome.Mutter.IdleMonitor"),
+                    "GetIdletime",
+                    &(),
+                )
+                .ok()?;
+            reply.body::<u64>().ok().map(Duration::from_millis)
+        }
+
+        /// KDE and others implementing the freedesktop screensaver (KWin answers in ms).
+        fn screensaver(&self) -> Option<Duration> {
+            let reply = self
+                .connection
+                .call_method(
+                    Some("org.freedesktop.ScreenSaver"),
+                    "/org/freedesktop/ScreenSaver",
+                    Some("org.freedesktop.ScreenSaver"),
+                    "GetSessionIdleTime",
+                    &(),
+                )
+                .ok()?;
+            reply.body::<u32>().ok().map(|ms| Duration::from_millis(u64::from(ms)))
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    use std::time::Duration;
+
+    pub struct IdleSource;
+
+    impl IdleSource {
+        pub fn new() -> Option<Self> {
+            None
+        }
+
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            None
+        }
+    }
+}
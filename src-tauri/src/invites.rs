@@ -0,0 +1,300 @@
+//! `cordia://` invite links.
+//!
+//! A signed link carries the server's signing pubkey, the beacon to join through, an expiry
+//! and optionally a one-time invite code, signed with the server's Ed25519 key:
+//! `cordia://invite/<base64url(json)>.<base64url(signature)>`. The older `cordia://CODE@beacon`
+//! form still parses but is reported as unsigned.
+//!
+//! The scheme is registered with the OS at startup; a clicked link reaches the running
+//! instance (a second instance forwards it and exits) and is handed to the frontend as
+//! `cordia:invite-link`. A link that arrives before the webview listens is kept until
+//! [`take_pending`] is called.
+//!
+//! [`qr_data_url`] renders a link as a QR code so it can be scanned instead of pasted.
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::server::Server;
+
+pub const SCHEME: &str = "cordia";
+const LINK_VERSION: u32 = 1;
+/// Smallest edge of a rendered QR code, in pixels.
+const QR_MIN_SIZE: u32 = 256;
+/// Light border around the code, in modules; scanners need at least 4.
+const QR_QUIET_ZONE: usize = 4;
+
+/// The signed part of an invite link.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedInvite {
+    pub v: u32,
+    /// Base64 Ed25519 key the payload is signed with.
+    pub signing_pubkey: String,
+    pub beacon_url: String,
+    pub expires_at: DateTime<Utc>,
+    /// Invite code to redeem on the beacon; `None` for a link that only identifies the server.
+    pub token: Option<String>,
+}
+
+/// What the join flow needs from a link, signed or legacy.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParsedInvite {
+    pub signing_pubkey: Option<String>,
+    pub beacon_url: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub token: Option<String>,
+    /// The payload was signed by `signing_pubkey`. Legacy links are never signed.
+    pub signed: bool,
+}
+
+static PENDING: Mutex<Option<String>> = Mutex::new(None);
+
+/// Build a signed link for `server`. Needs the server's signing key (owners only).
+pub fn create_link(
+    server: &Server,
+    beacon_url: &str,
+    expires_at: DateTime<Utc>,
+    token: Option<String>,
+) -> Result<String, String> {
+    let invite = SignedInvite {
+        v: LINK_VERSION,
+        signing_pubkey: server.signing_pubkey.clone(),
+        beacon_url: beacon_url.trim().to_string(),
+        expires_at,
+        token,
+    };
+    let payload = serde_json::to_vec(&invite).map_err(|e| format!("Failed to serialize invite: {}", e))?;
+    let signature = server
+        .sign(&payload)
+        .map_err(|e| format!("Failed to sign invite: {}", e))?;
+    encode_link(&payload, &signature)
+}
+
+fn encode_link(payload: &[u8], signature_b64: &str) -> Result<String, String> {
+    let signature = STANDARD
+        .decode(signature_b64)
+        .map_err(|e| format!("Invalid invite signature: {}", e))?;
+    Ok(format!(
+        "{}://invite/{}.{}",
+        SCHEME,
+        URL_SAFE_NO_PAD.encode(payload),
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+/// Parse and validate an invite link. Signed links must verify and not be expired.
+pub fn parse_link(link: &str) -> Result<ParsedInvite, String> {
+    let rest = link
+        .trim()
+        .strip_prefix(SCHEME)
+        .and_then(|r| r.strip_prefix("://"))
+        .ok_or_else(|| format!("Not a {}:// link", SCHEME))?
+        .trim_end_matches('/');
+
+    match rest.strip_prefix("invite/") {
+        Some(signed) => parse_signed(signed, Utc::now()),
+        None => parse_legacy(rest),
+    }
+}
+
+fn parse_signed(encoded: &str, now: DateTime<Utc>) -> Result<ParsedInvite, String> {
+    let (payload_b64, signature_b64) = encoded
+        .split_once('.')
+        .ok_or_else(|| "Invite link is missing its signature".to_string())?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload_b64)
+        .map_err(|e| format!("Invalid invite link: {}", e))?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature_b64)
+        .map_err(|e| format!("Invalid invite signature: {}", e))?;
+    let invite: SignedInvite =
+        serde_json::from_slice(&payload).map_err(|e| format!("Invalid invite link: {}", e))?;
+    if invite.v > LINK_VERSION {
+        return Err("Invite link was made by a newer version of Cordia".to_string());
+    }
+
+    let pubkey: [u8; 32] = STANDARD
+        .decode(&invite.signing_pubkey)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Invalid server key in invite link".to_string())?;
+    let verifying_key =
+        VerifyingKey::from_bytes(&pubkey).map_err(|_| "Invalid server key in invite link".to_string())?;
+    let signature =
+        Signature::from_slice(&signature).map_err(|_| "Invalid invite signature".to_string())?;
+    if verifying_key.verify(&payload, &signature).is_err() {
+        return Err("Invite link signature does not match".to_string());
+    }
+    if invite.expires_at <= now {
+        return Err("Invite link has expired".to_string());
+    }
+
+    Ok(ParsedInvite {
+        signing_pubkey: Some(invite.signing_pubkey),
+        beacon_url: invite.beacon_url,
+        expires_at: Some(invite.expires_at),
+        token: invite.token,
+        signed: true,
+    })
+}
+
+/// `CODE@beacon`, as shared before links were signed.
+fn parse_legacy(rest: &str) -> Result<ParsedInvite, String> {
+    let (code, beacon) = rest
+        .split_once('@')
+        .ok_or_else(|| "Invite link is missing its beacon".to_string())?;
+    if code.trim().is_empty() || beacon.trim().is_empty() {
+        return Err("Invalid invite link".to_string());
+    }
+    Ok(ParsedInvite {
+        signing_pubkey: None,
+        beacon_url: beacon.trim().to_string(),
+        expires_at: None,
+        token: Some(code.trim().to_string()),
+        signed: false,
+    })
+}
+
+/// Render an invite link as a QR code data URL (`svg` or `png`) for an `<img>`.
+/// The link is validated first, so an expired or forged link is never shown.
+pub fn qr_data_url(link: &str, format: &str) -> Result<String, String> {
+    parse_link(link)?;
+    let code = qrcode::QrCode::new(link.trim().as_bytes())
+        .map_err(|e| format!("Failed to encode invite QR code: {}", e))?;
+    match format {
+        "svg" => {
+            let svg = code
+                .render::<qrcode::render::svg::Color>()
+                .min_dimensions(QR_MIN_SIZE, QR_MIN_SIZE)
+                .build();
+            Ok(format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg)))
+        }
+        "png" => Ok(format!("data:image/png;base64,{}", STANDARD.encode(qr_png(&code)?))),
+        other => Err(format!("Unsupported QR format: {}", other)),
+    }
+}
+
+/// Greyscale PNG of `code`, black modules on white, scaled to at least `QR_MIN_SIZE`.
+fn qr_png(code: &qrcode::QrCode) -> Result<Vec<u8>, String> {
+    let modules = code.width();
+    let span = modules + 2 * QR_QUIET_ZONE;
+    let scale = (QR_MIN_SIZE as usize).div_ceil(span).max(1);
+    let size = span * scale;
+    let colors = code.to_colors();
+
+    let mut pixels = vec![255u8; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let (x, y) = (i % modules + QR_QUIET_ZONE, i / modules + QR_QUIET_ZONE);
+        for row in y * scale..(y + 1) * scale {
+            pixels[row * size + x * scale..row * size + (x + 1) * scale].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| format!("Failed to write invite QR code: {}", e))?;
+    Ok(out)
+}
+
+/// The link that opened the app, if the frontend hasn't picked it up yet.
+pub fn take_pending() -> Option<String> {
+    PENDING.lock().ok().and_then(|mut p| p.take())
+}
+
+fn deliver(app: &AppHandle, link: String) {
+    if !link.trim().starts_with(&format!("{}://", SCHEME)) {
+        return;
+    }
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending = Some(link.clone());
+    }
+    if let Some(window) = app.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit_all("cordia:invite-link", link);
+}
+
+/// Register the `cordia://` scheme and route clicked links to the frontend. A cold start
+/// from a link gets it as a command-line argument on Windows and Linux.
+pub fn register_deep_links(app: AppHandle) {
+    if let Some(link) = std::env::args().skip(1).find(|a| a.starts_with(&format!("{}://", SCHEME))) {
+        deliver(&app, link);
+    }
+    let handle = app.clone();
+    if let Err(e) = tauri_plugin_deep_link::register(SCHEME, move |link| deliver(&handle, link)) {
+        eprintln!("[Invites] Failed to register {}:// links: {}", SCHEME, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signed_link(key: &SigningKey, invite: &SignedInvite) -> String {
+        let payload = serde_json::to_vec(invite).unwrap();
+        let signature = STANDARD.encode(key.sign(&payload).to_bytes());
+        encode_link(&payload, &signature).unwrap()
+    }
+
+    #[test]
+    fn signed_links_verify_and_expire() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let invite = SignedInvite {
+            v: LINK_VERSION,
+            signing_pubkey: STANDARD.encode(key.verifying_key().as_bytes()),
+            beacon_url: "wss://beacon.example".to_string(),
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            token: Some("ABCD2345".to_string()),
+        };
+        let link = signed_link(&key, &invite);
+        let parsed = parse_link(&link).unwrap();
+        assert!(parsed.signed);
+        assert_eq!(parsed.token.as_deref(), Some("ABCD2345"));
+        assert_eq!(parsed.beacon_url, "wss://beacon.example");
+
+        let encoded = link.strip_prefix("cordia://invite/").unwrap();
+        assert!(parse_signed(encoded, invite.expires_at).is_err());
+
+        let forged = SignedInvite { beacon_url: "wss://evil.example".to_string(), ..invite };
+        let (_, signature) = encoded.split_once('.').unwrap();
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        assert!(parse_link(&format!("cordia://invite/{}.{}", forged_payload, signature)).is_err());
+    }
+
+    #[test]
+    fn parses_legacy_links() {
+        let parsed = parse_link("cordia://ABCD2345@beacon.example:9001").unwrap();
+        assert!(!parsed.signed);
+        assert_eq!(parsed.token.as_deref(), Some("ABCD2345"));
+        assert_eq!(parsed.beacon_url, "beacon.example:9001");
+        assert!(parse_link("https://example.com").is_err());
+    }
+
+    #[test]
+    fn renders_qr_codes() {
+        let link = "cordia://ABCD2345@beacon.example:9001";
+        let png = qr_data_url(link, "png").unwrap();
+        let bytes = STANDARD.decode(png.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+        let info = png::Decoder::new(bytes.as_slice()).read_info().unwrap().info().clone();
+        assert!(info.width >= QR_MIN_SIZE && info.width == info.height);
+
+        assert!(qr_data_url(link, "svg").unwrap().starts_with("data:image/svg+xml;base64,"));
+        assert!(qr_data_url(link, "gif").is_err());
+        assert!(qr_data_url("not a link", "png").is_err());
+    }
+}
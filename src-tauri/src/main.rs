@@ -4,22 +4,71 @@
 mod identity;
 mod audio_settings;
 mod audio_capture;
+mod audio_output;
+mod audio_loopback;
+mod app_audio_capture;
+mod wasapi_exclusive;
+mod audio_resample;
+mod audio_tap;
+mod audio_recording;
+mod audio_multitrack;
+mod audio_opus;
+mod audio_jitter;
+mod audio_spatial;
+mod audio_ducking;
+mod audio_spectrum;
+mod audio_selftest;
+mod audio_calibration;
+mod audio_priority;
+mod flac_encoder;
+mod device_watcher;
+mod audio_aec;
+mod audio_denoise;
 mod audio_dsp;
+mod audio_offline;
 mod server;
 mod beacon;
+mod signaling;
+mod protocol;
+mod beacon_failover;
+mod proxy;
+mod ws_deflate;
+mod beacon_tls;
+mod beacon_monitor;
+mod beacon_discovery;
+mod outbound_queue;
+mod message_history;
+mod secrets;
+mod settings;
+mod invites;
+mod ice_servers;
+mod file_transfer;
+mod connection_stats;
+mod global_hotkeys;
+mod idle;
+mod power;
+mod network_monitor;
+mod diagnostics;
 mod account_manager;
 mod waveform;
 
+#[cfg(feature = "fun-effects")]
+mod audio_voice_fx;
+
+#[cfg(feature = "native-webrtc")]
+mod native_webrtc;
+
 #[cfg(windows)]
 mod file_association;
 
 use tauri::Manager;
 use identity::{IdentityManager, UserIdentity};
 use audio_settings::{AudioSettingsManager, AudioSettings};
-use audio_capture::{enumerate_devices, start_capture, stop_capture, AudioDevice, AudioDropStats};
-use audio_dsp::{get_dsp, InputMode};
-use server::{ServerManager, ServerInfo};
-use beacon::{check_beacon_health, get_default_beacon_url};
+use audio_capture::{enumerate_devices, start_capture, stop_capture, pause_capture, resume_capture, AudioDevice, AudioDeviceKind, AudioDropStats, DeviceCapabilities};
+use audio_dsp::{get_dsp, CompressorSettings, DspConfig, DspStageConfig, EqBand, InputMode, MeterSmoothing, MeterTuning, VoiceEffect};
+use audio_recording::{RecordingFormat, RecordingSource, RecordingSummary};
+use server::{Server, ServerManager, ServerInfo};
+use beacon::{check_beacon_health, get_default_beacon_url, measure_beacon_latency as measure_latency, BeaconInfo, BeaconLatency};
 use account_manager::{AccountManager, SessionState, AccountInfo, KnownProfile, KnownProfileForExport};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -45,6 +94,27 @@ struct EncryptedServerHint {
     last_updated: String,
 }
 
+/// A member's signed leave, for servers whose hint only the owner can sign.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MemberLeave {
+    public_key: String,
+    signature: String,
+    left_at: String,
+}
+
+/// A beacon event; for `MemberLeave` the payload is the member's public key and the
+/// timestamp is when they left.
+#[derive(Debug, Clone, Deserialize)]
+struct ServerEventRecord {
+    event_type: String,
+    encrypted_payload: String,
+    signature: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Prefix of the message a member signs to leave a server (matches the beacon).
+const MEMBER_LEAVE_CONTEXT: &str = "cordia-member-left:";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct InviteTokenCreateRequest {
     code: String,
@@ -409,6 +479,11 @@ fn resolve_downloads_dir(target_dir: Option<String>) -> Result<PathBuf, String>
         std::fs::create_dir_all(&p).map_err(|e| format!("Failed to create download directory: {}", e))?;
         return Ok(p);
     }
+    if let Some(dir) = require_session().ok().and_then(|id| settings::account(&id).downloads.preferred_dir) {
+        let p = PathBuf::from(dir);
+        std::fs::create_dir_all(&p).map_err(|e| format!("Failed to create download directory: {}", e))?;
+        return Ok(p);
+    }
     if let Some(p) = tauri::api::path::download_dir() {
         std::fs::create_dir_all(&p).map_err(|e| format!("Failed to create OS Downloads directory: {}", e))?;
         return Ok(p);
@@ -1712,6 +1787,73 @@ fn load_identity() -> Result<UserIdentity, String> {
         .map_err(|e| format!("Failed to load identity: {}", e))
 }
 
+/// Sign `message` (UTF-8) with the account's identity key under `identity::USER_SIGN_CONTEXT`;
+/// returns a base64 Ed25519 signature. Messages in a native protocol context are refused.
+#[tauri::command]
+fn sign_with_identity(message: String) -> Result<String, String> {
+    // GUARDED: Requires active session
+    require_session()?;
+
+    let payload = identity::user_sign_payload(&message)?;
+    let manager = IdentityManager::new()
+        .map_err(|e| format!("Failed to initialize identity manager: {}", e))?;
+    let signature = manager.sign(payload.as_bytes())
+        .map_err(|e| format!("Failed to sign message: {}", e))?;
+    Ok(base64::encode(signature.to_bytes()))
+}
+
+/// Check a signature made by `sign_with_identity`.
+#[tauri::command]
+fn verify_identity_signature(public_key: String, message: String, signature: String) -> Result<bool, String> {
+    let payload = identity::user_sign_payload(&message)?;
+    identity::verify_signature(&public_key, payload.as_bytes(), &signature)
+        .map_err(|e| format!("Failed to verify signature: {}", e))
+}
+
+#[derive(Serialize)]
+struct IdentityFingerprint {
+    public_key: String,
+    fingerprint: String,
+}
+
+/// Fingerprint of `public_key`, or of the current identity when omitted, for comparing out of band.
+#[tauri::command]
+fn get_identity_fingerprint(public_key: Option<String>) -> Result<IdentityFingerprint, String> {
+    let public_key = match public_key {
+        Some(public_key) => public_key,
+        None => {
+            require_session()?;
+            let manager = IdentityManager::new()
+                .map_err(|e| format!("Failed to initialize identity manager: {}", e))?;
+            manager.load_identity()
+                .map_err(|e| format!("Failed to load identity: {}", e))?
+                .public_key
+        }
+    };
+    let fingerprint = identity::key_fingerprint(&public_key)
+        .map_err(|e| format!("Invalid public key: {}", e))?;
+    Ok(IdentityFingerprint { public_key, fingerprint })
+}
+
+/// A secret the frontend stored in the OS keychain (e.g. a beacon auth token).
+#[tauri::command]
+fn get_secret(name: String) -> Result<Option<String>, String> {
+    let account_id = require_session()?;
+    secrets::get_app_secret(&account_id, &name)
+}
+
+#[tauri::command]
+fn set_secret(name: String, value: String) -> Result<(), String> {
+    let account_id = require_session()?;
+    secrets::set_app_secret(&account_id, &name, &value)
+}
+
+#[tauri::command]
+fn delete_secret(name: String) -> Result<(), String> {
+    let account_id = require_session()?;
+    secrets::delete_app_secret(&account_id, &name)
+}
+
 #[tauri::command]
 fn export_identity() -> Result<Vec<u8>, String> {
     // GUARDED: Requires active session
@@ -1784,6 +1926,7 @@ fn export_full_identity_for_account(
             display_name: String::new(),
             created_at: String::new(),
             signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
         });
     let signaling_server_url = account_info.signaling_server_url;
     let friends = account_manager.load_friends(&account_id).unwrap_or_default();
@@ -1857,6 +2000,7 @@ fn export_full_identity(profile_json: Option<serde_json::Value>) -> Result<Vec<u
             display_name: String::new(),
             created_at: String::new(),
             signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
         });
     let signaling_server_url = account_info.signaling_server_url;
     let friends = account_manager.load_friends(&current_account_id).unwrap_or_default();
@@ -1932,6 +2076,7 @@ fn export_full_identity_debug(profile_json: Option<serde_json::Value>) -> Result
             display_name: String::new(),
             created_at: String::new(),
             signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
         });
     let signaling_server_url = account_info.signaling_server_url;
 
@@ -1991,6 +2136,7 @@ fn import_identity(data: Vec<u8>) -> Result<ImportResult, String> {
                 display_name: display_name.clone(),
                 created_at: chrono::Utc::now().to_rfc3339(),
             signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
         });
         account_info.signaling_server_url = signaling_server_url.clone();
         account_manager.save_account_info(&account_info)
@@ -2144,11 +2290,63 @@ fn load_audio_settings() -> Result<AudioSettings, String> {
 }
 
 #[tauri::command]
-fn save_audio_settings(settings: AudioSettings) -> Result<(), String> {
+fn save_audio_settings(app: tauri::AppHandle, settings: AudioSettings) -> Result<(), String> {
     let manager = AudioSettingsManager::new()
         .map_err(|e| format!("Failed to initialize audio settings manager: {}", e))?;
     manager.save_settings(&settings)
-        .map_err(|e| format!("Failed to save audio settings: {}", e))
+        .map_err(|e| format!("Failed to save audio settings: {}", e))?;
+    global_hotkeys::set_push_to_talk(&app, &settings);
+    Ok(())
+}
+
+#[tauri::command]
+fn list_dsp_presets() -> Result<Vec<String>, String> {
+    let manager = AudioSettingsManager::new()
+        .map_err(|e| format!("Failed to initialize audio settings manager: {}", e))?;
+    let presets = manager.load_presets()
+        .map_err(|e| format!("Failed to load DSP presets: {}", e))?;
+    Ok(presets.into_keys().collect())
+}
+
+/// Save the current mic processing settings under `name` (overwrites an existing preset).
+#[tauri::command]
+fn save_dsp_preset(name: String) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name cannot be empty".to_string());
+    }
+    let config = {
+        let dsp = get_dsp();
+        let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+        dsp_guard.config()
+    };
+    let manager = AudioSettingsManager::new()
+        .map_err(|e| format!("Failed to initialize audio settings manager: {}", e))?;
+    manager.save_preset(name, &config)
+        .map_err(|e| format!("Failed to save DSP preset: {}", e))
+}
+
+/// Apply a saved preset to the DSP. Returns it so the settings UI can sync its controls.
+#[tauri::command]
+fn apply_dsp_preset(name: String) -> Result<DspConfig, String> {
+    let manager = AudioSettingsManager::new()
+        .map_err(|e| format!("Failed to initialize audio settings manager: {}", e))?;
+    let config = manager.load_presets()
+        .map_err(|e| format!("Failed to load DSP presets: {}", e))?
+        .remove(&name)
+        .ok_or_else(|| format!("DSP preset not found: {}", name))?;
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.apply_config(&config)?;
+    Ok(config)
+}
+
+#[tauri::command]
+fn delete_dsp_preset(name: String) -> Result<bool, String> {
+    let manager = AudioSettingsManager::new()
+        .map_err(|e| format!("Failed to initialize audio settings manager: {}", e))?;
+    manager.delete_preset(&name)
+        .map_err(|e| format!("Failed to delete DSP preset: {}", e))
 }
 
 #[tauri::command]
@@ -2286,6 +2484,39 @@ fn decrypt_ephemeral_chat_message_by_signing_pubkey(signing_pubkey: String, encr
     String::from_utf8(plaintext).map_err(|e| format!("Invalid UTF-8 message payload: {}", e))
 }
 
+/// Save received or sent chat messages to local history; returns how many were new.
+#[tauri::command]
+fn history_append(messages: Vec<message_history::HistoryMessage>) -> Result<usize, String> {
+    message_history::append(&messages)
+}
+
+/// A page of a channel's history before `before` (ms), oldest first.
+#[tauri::command]
+fn history_fetch(
+    server_id: String,
+    channel_id: String,
+    before: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<message_history::HistoryMessage>, String> {
+    message_history::fetch(&server_id, &channel_id, before, limit.unwrap_or(50))
+}
+
+/// Messages containing every word of `query`, newest first.
+#[tauri::command]
+fn history_search(
+    query: String,
+    server_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<message_history::HistoryMessage>, String> {
+    message_history::search(&query, server_id.as_deref(), limit.unwrap_or(50))
+}
+
+/// Delete history older than `before` (ms); returns how many messages were removed.
+#[tauri::command]
+fn history_prune(before: i64, server_id: Option<String>) -> Result<usize, String> {
+    message_history::prune(before, server_id.as_deref())
+}
+
 #[tauri::command]
 fn delete_server(server_id: String) -> Result<(), String> {
     // GUARDED: Requires active session
@@ -2346,11 +2577,8 @@ async fn register_server_hint(beacon_url: String, hint: EncryptedServerHint) ->
         urlencoding::encode(&hint.signing_pubkey)
     );
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .post(url)
-        .json(&hint)
-        .send()
+    let client = beacon_tls::http_client(&beacon_url)?;
+    let resp = beacon_tls::send(&beacon_url, client.post(url).json(&hint))
         .await
         .map_err(|e| format!("Failed to POST server hint: {}", e))?;
 
@@ -2373,10 +2601,8 @@ async fn get_server_hint(beacon_url: String, signing_pubkey: String) -> Result<O
         urlencoding::encode(&signing_pubkey)
     );
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(url)
-        .send()
+    let client = beacon_tls::http_client(&beacon_url)?;
+    let resp = beacon_tls::send(&beacon_url, client.get(url))
         .await
         .map_err(|e| format!("Failed to GET server hint: {}", e))?;
 
@@ -2396,6 +2622,21 @@ async fn get_server_hint(beacon_url: String, signing_pubkey: String) -> Result<O
     Ok(Some(hint))
 }
 
+/// The beacon only stores hints signed by the server's signing key over
+/// `signing_pubkey\nencrypted_state\nlast_updated_ms`, so only the owner can publish.
+fn sign_server_hint(server: &Server, encrypted_state: String) -> Result<EncryptedServerHint, String> {
+    let now = chrono::Utc::now();
+    let message = format!("{}\n{}\n{}", server.signing_pubkey, encrypted_state, now.timestamp_millis());
+    let signature = server.sign(message.as_bytes())
+        .map_err(|e| format!("Failed to sign server hint: {}", e))?;
+    Ok(EncryptedServerHint {
+        signing_pubkey: server.signing_pubkey.clone(),
+        encrypted_state,
+        signature,
+        last_updated: now.to_rfc3339(),
+    })
+}
+
 #[tauri::command]
 async fn publish_server_hint_opaque(beacon_url: String, server_id: String) -> Result<(), String> {
     require_session()?;
@@ -2411,17 +2652,14 @@ async fn publish_server_hint_opaque(beacon_url: String, server_id: String) -> Re
 
     let server_info = server.to_info();
     let encrypted_state = encrypt_server_hint(&symmetric_key, &server_info)?;
-
-    let hint = EncryptedServerHint {
-        signing_pubkey: server_info.signing_pubkey.clone(),
-        encrypted_state,
-        signature: "".to_string(),
-        last_updated: chrono::Utc::now().to_rfc3339(),
-    };
+    let hint = sign_server_hint(&server, encrypted_state)?;
 
     register_server_hint(beacon_url, hint).await
 }
 
+/// Tell the server that `user_id` left. The owner publishes a new hint without them; anyone
+/// else can only post their own signed leave, which the owner applies with
+/// `apply_server_member_leaves`.
 #[tauri::command]
 async fn publish_server_hint_member_left(beacon_url: String, server_id: String, user_id: String) -> Result<(), String> {
     require_session()?;
@@ -2432,6 +2670,10 @@ async fn publish_server_hint_member_left(beacon_url: String, server_id: String,
     let server = manager.load_server(&server_id)
         .map_err(|e| format!("Failed to load server: {}", e))?;
 
+    if !server.has_signing_key() {
+        return post_member_leave(&beacon_url, &server.signing_pubkey, &user_id).await;
+    }
+
     let symmetric_key = server.get_symmetric_key()
         .ok_or_else(|| "Server missing symmetric key".to_string())?;
 
@@ -2439,16 +2681,108 @@ async fn publish_server_hint_member_left(beacon_url: String, server_id: String,
     server_info.members.retain(|m| m.user_id != user_id);
 
     let encrypted_state = encrypt_server_hint(&symmetric_key, &server_info)?;
-    let hint = EncryptedServerHint {
-        signing_pubkey: server_info.signing_pubkey.clone(),
-        encrypted_state,
-        signature: "".to_string(),
-        last_updated: chrono::Utc::now().to_rfc3339(),
-    };
+    let hint = sign_server_hint(&server, encrypted_state)?;
 
     register_server_hint(beacon_url, hint).await
 }
 
+fn member_leave_message(signing_pubkey: &str, left_at: &chrono::DateTime<chrono::Utc>) -> String {
+    format!("{}{}\n{}", MEMBER_LEAVE_CONTEXT, signing_pubkey, left_at.timestamp_millis())
+}
+
+/// Post our own leave, signed with the identity key, to `/api/servers/:key/leave`.
+async fn post_member_leave(beacon_url: &str, signing_pubkey: &str, user_id: &str) -> Result<(), String> {
+    let identity_manager = IdentityManager::new()
+        .map_err(|e| format!("Failed to initialize identity manager: {}", e))?;
+    let identity = identity_manager.load_identity()
+        .map_err(|e| format!("Failed to load identity: {}", e))?;
+    if identity.user_id != user_id {
+        return Err("Only the server owner can remove other members".to_string());
+    }
+    let left_at = chrono::Utc::now();
+    let signature = identity_manager
+        .sign(member_leave_message(signing_pubkey, &left_at).as_bytes())
+        .map_err(|e| format!("Failed to sign leave: {}", e))?;
+    let leave = MemberLeave {
+        public_key: identity.public_key,
+        signature: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, signature.to_bytes()),
+        left_at: left_at.to_rfc3339(),
+    };
+
+    let base = normalize_beacon_to_http(beacon_url)?;
+    let url = format!("{}/api/servers/{}/leave", base, urlencoding::encode(signing_pubkey));
+    let client = beacon_tls::http_client(beacon_url)?;
+    let resp = beacon_tls::send(beacon_url, client.post(url).json(&leave))
+        .await
+        .map_err(|e| format!("Failed to POST leave: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(format!("Failed to post leave: HTTP {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// Owner only: drop members who posted a signed leave since they joined, and publish the
+/// new hint. Each leave is checked against the member's own key here, not just on the
+/// beacon. Returns true if any member was removed.
+#[tauri::command]
+async fn apply_server_member_leaves(beacon_url: String, signing_pubkey: String) -> Result<bool, String> {
+    require_session()?;
+
+    let manager = ServerManager::new()
+        .map_err(|e| format!("Failed to initialize server manager: {}", e))?;
+    let Some(server_id) = manager.find_server_id_by_signing_pubkey(&signing_pubkey)
+        .map_err(|e| format!("Failed to find local server: {}", e))? else {
+        return Ok(false);
+    };
+    let mut server = manager.load_server(&server_id)
+        .map_err(|e| format!("Failed to load server: {}", e))?;
+    if !server.has_signing_key() {
+        return Ok(false);
+    }
+
+    let base = normalize_beacon_to_http(&beacon_url)?;
+    let url = format!("{}/api/servers/{}/events", base, urlencoding::encode(&signing_pubkey));
+    let client = beacon_tls::http_client(&beacon_url)?;
+    let resp = beacon_tls::send(&beacon_url, client.get(url))
+        .await
+        .map_err(|e| format!("Failed to GET server events: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Failed to get server events: HTTP {}", resp.status()));
+    }
+    let events = resp
+        .json::<Vec<ServerEventRecord>>()
+        .await
+        .map_err(|e| format!("Failed to parse server events JSON: {}", e))?;
+
+    let before = server.members.len();
+    for event in events.iter().filter(|e| e.event_type == "MemberLeave") {
+        let message = member_leave_message(&signing_pubkey, &event.timestamp);
+        let signed = identity::verify_signature(&event.encrypted_payload, message.as_bytes(), &event.signature)
+            .unwrap_or(false);
+        let Ok(user_id) = identity::user_id_for_key(&event.encrypted_payload) else {
+            continue;
+        };
+        if !signed {
+            continue;
+        }
+        // A leave from before the member (re)joined doesn't count.
+        server.members.retain(|m| m.user_id != user_id || m.joined_at >= event.timestamp);
+    }
+    if server.members.len() == before {
+        return Ok(false);
+    }
+
+    manager.save_server(&server)
+        .map_err(|e| format!("Failed to save server: {}", e))?;
+    let symmetric_key = server.get_symmetric_key()
+        .ok_or_else(|| "Server missing symmetric key".to_string())?;
+    let encrypted_state = encrypt_server_hint(&symmetric_key, &server.to_info())?;
+    let hint = sign_server_hint(&server, encrypted_state)?;
+    register_server_hint(beacon_url, hint).await?;
+    Ok(true)
+}
+
 #[tauri::command]
 async fn fetch_and_import_server_hint_opaque(beacon_url: String, signing_pubkey: String) -> Result<bool, String> {
     require_session()?;
@@ -2498,10 +2832,8 @@ async fn resolve_invite_code(beacon_url: String, invite_code: String) -> Result<
     let code = invite_code.trim().to_ascii_uppercase();
     let url = format!("{}/api/invites/{}", base, urlencoding::encode(&code));
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(url)
-        .send()
+    let client = beacon_tls::http_client(&beacon_url)?;
+    let resp = beacon_tls::send(&beacon_url, client.get(url))
         .await
         .map_err(|e| format!("Failed to resolve invite code: {}", e))?;
 
@@ -2521,18 +2853,8 @@ async fn resolve_invite_code(beacon_url: String, invite_code: String) -> Result<
     Ok(Some(parsed.signing_pubkey))
 }
 
-#[tauri::command]
-async fn create_temporary_invite(beacon_url: String, server_id: String, max_uses: u32) -> Result<String, String> {
-    require_session()?;
-
-    let manager = ServerManager::new()
-        .map_err(|e| format!("Failed to initialize server manager: {}", e))?;
-
-    // Load server with secrets (need symmetric key)
-    let server = manager.load_server(&server_id)
-        .map_err(|e| format!("Failed to load server: {}", e))?;
-
-    // Generate a short human-shareable code (8 chars - easy to read over phone)
+/// Short human-shareable invite code (8 chars - easy to read over phone).
+fn generate_invite_code() -> String {
     const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
     let mut code = String::with_capacity(8);
     let mut bytes = [0u8; 8];
@@ -2540,43 +2862,39 @@ async fn create_temporary_invite(beacon_url: String, server_id: String, max_uses
     for b in bytes {
         code.push(CHARSET[(b as usize) % CHARSET.len()] as char);
     }
+    code
+}
 
-    let mut server_info = server.to_info();
-    let symmetric_key = server.get_symmetric_key()
-        .ok_or_else(|| "Server missing symmetric key".to_string())?;
-
-    // Include active invite fields in the payload so new joiners see the current invite state immediately.
-    // We treat the invite as "active until revoked"; this timestamp is just to allow UI hiding if it's very stale.
-    let invite_uri = format!("cordia://{}@{}", code, beacon_url.trim());
-    let expires_at = chrono::Utc::now() + chrono::Duration::days(30);
-    server_info.active_invite_uri = Some(invite_uri.clone());
-    server_info.active_invite_expires_at = Some(expires_at);
-
+/// Store an invite code on the beacon; redeeming it yields `server_info` and the symmetric key.
+async fn register_invite_code(
+    beacon_url: &str,
+    server_info: &ServerInfo,
+    symmetric_key: &[u8],
+    code: &str,
+    max_uses: u32,
+) -> Result<(), String> {
     let payload = InviteTokenPayload {
         server: server_info.clone(),
-        server_symmetric_key_b64: base64::encode(&symmetric_key),
+        server_symmetric_key_b64: base64::encode(symmetric_key),
     };
-    let encrypted_payload = encrypt_invite_payload(&code, &payload)?;
+    let encrypted_payload = encrypt_invite_payload(code, &payload)?;
 
     // POST to signaling server
-    let base = normalize_beacon_to_http(&beacon_url)?;
+    let base = normalize_beacon_to_http(beacon_url)?;
     let url = format!(
         "{}/api/servers/{}/invites",
         base,
         urlencoding::encode(&server_info.signing_pubkey)
     );
 
-    let client = reqwest::Client::new();
+    let client = beacon_tls::http_client(beacon_url)?;
     let req = InviteTokenCreateRequest {
-        code: code.clone(),
+        code: code.to_string(),
         max_uses,
         encrypted_payload,
         signature: "".to_string(),
     };
-    let resp = client
-        .post(url)
-        .json(&req)
-        .send()
+    let resp = beacon_tls::send(beacon_url, client.post(url).json(&req))
         .await
         .map_err(|e| format!("Failed to create invite on signaling server: {}", e))?;
 
@@ -2584,10 +2902,38 @@ async fn create_temporary_invite(beacon_url: String, server_id: String, max_uses
         return Err(format!("Failed to create invite: HTTP {}", resp.status()));
     }
 
-    // Store as active invite locally (clients hide when expired based on expires_at)
-    let record = resp.json::<InviteTokenRecord>().await
+    resp.json::<InviteTokenRecord>().await
         .map_err(|e| format!("Failed to parse invite response: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn create_temporary_invite(beacon_url: String, server_id: String, max_uses: u32) -> Result<String, String> {
+    require_session()?;
+
+    let manager = ServerManager::new()
+        .map_err(|e| format!("Failed to initialize server manager: {}", e))?;
+
+    // Load server with secrets (need symmetric key)
+    let server = manager.load_server(&server_id)
+        .map_err(|e| format!("Failed to load server: {}", e))?;
+
+    let code = generate_invite_code();
+
+    let mut server_info = server.to_info();
+    let symmetric_key = server.get_symmetric_key()
+        .ok_or_else(|| "Server missing symmetric key".to_string())?;
+
+    // Include active invite fields in the payload so new joiners see the current invite state immediately.
+    // We treat the invite as "active until revoked"; this timestamp is just to allow UI hiding if it's very stale.
+    let invite_uri = format!("cordia://{}@{}", code, beacon_url.trim());
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(30);
+    server_info.active_invite_uri = Some(invite_uri.clone());
+    server_info.active_invite_expires_at = Some(expires_at);
+
+    register_invite_code(&beacon_url, &server_info, &symmetric_key, &code, max_uses).await?;
 
+    // Store as active invite locally (clients hide when expired based on expires_at)
     manager.set_active_invite(&server_id, Some(invite_uri.clone()), Some(expires_at))
         .map_err(|e| format!("Failed to store active invite: {}", e))?;
 
@@ -2597,6 +2943,58 @@ async fn create_temporary_invite(beacon_url: String, server_id: String, max_uses
     Ok(invite_uri)
 }
 
+/// Signed `cordia://invite/...` link for a server. `one_time` registers a fresh single-use
+/// code for the link; otherwise it carries the server's active invite code.
+#[tauri::command]
+async fn create_invite_link(
+    beacon_url: String,
+    server_id: String,
+    expires_in_hours: Option<u32>,
+    one_time: bool,
+) -> Result<String, String> {
+    require_session()?;
+
+    let manager = ServerManager::new()
+        .map_err(|e| format!("Failed to initialize server manager: {}", e))?;
+    let server = manager.load_server(&server_id)
+        .map_err(|e| format!("Failed to load server: {}", e))?;
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(expires_in_hours.unwrap_or(24 * 7).max(1) as i64);
+    let token = if one_time {
+        let code = generate_invite_code();
+        let symmetric_key = server.get_symmetric_key()
+            .ok_or_else(|| "Server missing symmetric key".to_string())?;
+        register_invite_code(&beacon_url, &server.to_info(), &symmetric_key, &code, 1).await?;
+        code
+    } else {
+        server
+            .active_invite_uri
+            .as_deref()
+            .and_then(|uri| invites::parse_link(uri).ok())
+            .and_then(|invite| invite.token)
+            .ok_or_else(|| "Server has no active invite; create one first".to_string())?
+    };
+
+    invites::create_link(&server, &beacon_url, expires_at, Some(token))
+}
+
+#[tauri::command]
+fn parse_invite_link(link: String) -> Result<invites::ParsedInvite, String> {
+    invites::parse_link(&link)
+}
+
+/// QR code for an invite link as a data URL; `format` is `svg` or `png`.
+#[tauri::command]
+fn render_invite_qr(link: String, format: String) -> Result<String, String> {
+    invites::qr_data_url(&link, &format)
+}
+
+/// The invite link the app was opened with, if not yet handled.
+#[tauri::command]
+fn take_pending_invite_link() -> Option<String> {
+    invites::take_pending()
+}
+
 #[tauri::command]
 async fn revoke_active_invite(beacon_url: String, server_id: String) -> Result<(), String> {
     require_session()?;
@@ -2609,16 +3007,18 @@ async fn revoke_active_invite(beacon_url: String, server_id: String) -> Result<(
 
     let code = server
         .active_invite_uri
-        .as_ref()
-        .and_then(|uri| uri.trim().strip_prefix("cordia://"))
-        .and_then(|rest| rest.split('@').next())
-        .map(|s| s.to_string());
+        .as_deref()
+        .and_then(|uri| invites::parse_link(uri).ok())
+        .and_then(|invite| invite.token);
 
     if let Some(code) = code {
         let base = normalize_beacon_to_http(&beacon_url)?;
         let url = format!("{}/api/invites/{}/revoke", base, urlencoding::encode(code.trim()));
-        let client = reqwest::Client::new();
-        let _ = client.post(url).send().await;
+        let client = beacon_tls::http_client(&beacon_url)?;
+        // Best effort: the invite is cleared locally either way.
+        if let Err(e) = beacon_tls::send(&beacon_url, client.post(url)).await {
+            eprintln!("Failed to revoke invite on beacon: {}", e);
+        }
     }
 
     manager
@@ -2638,10 +3038,8 @@ async fn redeem_temporary_invite(beacon_url: String, code: String, user_id: Stri
     let base = normalize_beacon_to_http(&beacon_url)?;
     let url = format!("{}/api/invites/{}/redeem", base, urlencoding::encode(code.trim()));
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .post(url)
-        .send()
+    let client = beacon_tls::http_client(&beacon_url)?;
+    let resp = beacon_tls::send(&beacon_url, client.post(url))
         .await
         .map_err(|e| format!("Failed to fetch invite: {}", e))?;
 
@@ -2697,46 +3095,224 @@ async fn check_beacon(url: Option<String>) -> Result<bool, String> {
         .map_err(|e| format!("Beacon check failed: {}", e))
 }
 
+/// Two-stage check: HTTP health endpoint and a real WebSocket round trip, reported separately.
 #[tauri::command]
-fn get_default_beacon() -> String {
-    get_default_beacon_url()
+async fn probe_beacon(url: Option<String>) -> Result<beacon::BeaconProbe, String> {
+    let server_url = url.unwrap_or_else(get_default_beacon_url);
+    beacon::probe_beacon(&server_url)
+        .await
+        .map_err(|e| format!("Beacon probe failed: {}", e))
 }
 
+/// Step-by-step connectivity report for "Having trouble connecting?".
 #[tauri::command]
-async fn get_beacon_url() -> Result<String, String> {
-    // GUARDED: Requires active session
-    require_session()?;
-    
-    let account_manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    let current_account_id = account_manager.get_current_account_id()
-        .map_err(|e| format!("Failed to get current account: {}", e))?
-        .ok_or_else(|| "No active session".to_string())?;
-    
-    let account_info = account_manager.get_account_info(&current_account_id)
-        .map_err(|e| format!("Failed to get account info: {}", e))?
-        .unwrap_or_else(|| AccountInfo {
-            account_id: current_account_id,
-            display_name: String::new(),
-            created_at: String::new(),
-            signaling_server_url: None,
-        });
-    
-    Ok(account_info.signaling_server_url
-        .unwrap_or_else(|| get_default_beacon_url()))
+async fn run_network_diagnostics(url: Option<String>) -> Result<diagnostics::DiagnosticsReport, String> {
+    let server_url = url.unwrap_or_else(get_default_beacon_url);
+    diagnostics::run(&server_url).await
 }
 
 #[tauri::command]
-async fn set_beacon_url(url: String) -> Result<(), String> {
-    // GUARDED: Requires active session
-    require_session()?;
-    
-    let account_manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    let current_account_id = account_manager.get_current_account_id()
-        .map_err(|e| format!("Failed to get current account: {}", e))?
-        .ok_or_else(|| "No active session".to_string())?;
-    
+fn get_settings() -> settings::Settings {
+    settings::current()
+}
+
+/// Save app settings; every window and native module sees them via `cordia:settings-changed`.
+#[tauri::command]
+fn set_settings(app: tauri::AppHandle, settings: settings::Settings) -> Result<settings::Settings, String> {
+    let saved = settings::set_settings(&app, settings)?;
+    global_hotkeys::refresh(&app);
+    audio_opus::set_bitrate_cap(saved.bandwidth.max_voice_kbps);
+    Ok(saved)
+}
+
+/// Watch the beacon's health in the background; changes arrive as `cordia:beacon-status`.
+#[tauri::command]
+fn start_beacon_monitor(app: tauri::AppHandle, url: Option<String>) -> Result<(), String> {
+    let server_url = url.unwrap_or_else(get_default_beacon_url);
+    beacon_monitor::start(app, server_url)
+}
+
+#[tauri::command]
+fn stop_beacon_monitor() {
+    beacon_monitor::stop();
+}
+
+#[tauri::command]
+fn get_beacon_monitor_status() -> Option<beacon_monitor::BeaconStatusEvent> {
+    beacon_monitor::status()
+}
+
+/// Browse the LAN for beacons; results arrive as `cordia:beacon-discovered` / `cordia:beacon-lost`.
+#[tauri::command]
+fn start_beacon_discovery(app: tauri::AppHandle) -> Result<(), String> {
+    beacon_discovery::start_discovery(app)
+}
+
+#[tauri::command]
+fn stop_beacon_discovery() {
+    beacon_discovery::stop_discovery();
+}
+
+#[tauri::command]
+fn get_discovered_beacons() -> Vec<beacon_discovery::DiscoveredBeacon> {
+    beacon_discovery::discovered_beacons()
+}
+
+/// Announce a beacon running on this machine so others on the LAN can find it.
+#[tauri::command]
+fn advertise_local_beacon(port: u16, name: Option<String>) -> Result<(), String> {
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "Cordia beacon".to_string());
+    beacon_discovery::advertise(&name, port)
+}
+
+#[tauri::command]
+fn stop_advertising_beacon() {
+    beacon_discovery::stop_advertising();
+}
+
+/// What the beacon supports (version, protocol, features, limits), for per-beacon feature gating.
+#[tauri::command]
+async fn get_beacon_info(url: Option<String>) -> Result<BeaconInfo, String> {
+    let server_url = url.unwrap_or_else(get_default_beacon_url);
+    beacon::get_beacon_info(&server_url)
+        .await
+        .map_err(|e| format!("Beacon info check failed: {}", e))
+}
+
+/// Round-trip times to the beacon's health endpoint, for display next to its status.
+#[tauri::command]
+async fn measure_beacon_latency(url: Option<String>, samples: Option<usize>) -> Result<BeaconLatency, String> {
+    let server_url = url.unwrap_or_else(get_default_beacon_url);
+    measure_latency(&server_url, samples.unwrap_or(5))
+        .await
+        .map_err(|e| format!("Beacon latency check failed: {}", e))
+}
+
+#[tauri::command]
+fn signaling_connect(app: tauri::AppHandle, url: Option<String>) -> Result<(), String> {
+    let server_url = url.unwrap_or_else(get_default_beacon_url);
+    signaling::connect(app, &server_url)
+}
+
+#[tauri::command]
+fn signaling_disconnect() {
+    signaling::disconnect();
+}
+
+#[tauri::command]
+fn signaling_send(message: serde_json::Value) -> Result<(), String> {
+    signaling::send(&message)
+}
+
+/// Queue `message` on disk and deliver it once connected; returns its `dedup_id`.
+#[tauri::command]
+fn signaling_send_reliable(message: serde_json::Value) -> Result<String, String> {
+    signaling::send_reliable(message)
+}
+
+#[tauri::command]
+fn get_outbound_queue() -> Vec<outbound_queue::QueuedMessage> {
+    outbound_queue::pending()
+}
+
+#[tauri::command]
+fn clear_outbound_queue() -> Result<(), String> {
+    outbound_queue::clear()
+}
+
+/// Send `message` and resend it after every reconnect, until unregistered under `key`.
+#[tauri::command]
+fn signaling_register(key: String, message: serde_json::Value) -> Result<(), String> {
+    signaling::register(&key, &message)
+}
+
+/// Sign an `AuthChallenge` nonce for a beacon socket opened by the frontend; returns the
+/// `Authenticate` message to send back.
+#[tauri::command]
+fn signaling_answer_challenge(nonce: String) -> Result<serde_json::Value, String> {
+    signaling::authenticate_message(&nonce)
+}
+
+#[tauri::command]
+fn signaling_unregister(key: String) {
+    signaling::unregister(&key);
+}
+
+#[tauri::command]
+fn get_signaling_status() -> signaling::SignalingStatus {
+    signaling::status()
+}
+
+#[tauri::command]
+fn get_proxy_settings() -> proxy::ProxySettings {
+    proxy::proxy_settings()
+}
+
+/// Proxy for beacon HTTP requests and the signaling WebSocket; applies to new connections.
+#[tauri::command]
+fn set_proxy_settings(settings: proxy::ProxySettings) -> Result<(), String> {
+    proxy::set_proxy_settings(settings)
+}
+
+#[tauri::command]
+fn get_beacon_tls_options(url: String) -> beacon_tls::BeaconTlsOptions {
+    beacon_tls::beacon_tls_options(&url)
+}
+
+/// Extra CA / SPKI pin for a self-hosted beacon; empty options clear them.
+#[tauri::command]
+fn set_beacon_tls_options(url: String, options: beacon_tls::BeaconTlsOptions) -> Result<(), String> {
+    beacon_tls::set_beacon_tls_options(&url, options)
+}
+
+#[tauri::command]
+fn get_default_beacon() -> String {
+    get_default_beacon_url()
+}
+
+#[tauri::command]
+async fn get_beacon_url() -> Result<String, String> {
+    // GUARDED: Requires active session
+    require_session()?;
+    
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let current_account_id = account_manager.get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))?
+        .ok_or_else(|| "No active session".to_string())?;
+    
+    let account_info = account_manager.get_account_info(&current_account_id)
+        .map_err(|e| format!("Failed to get account info: {}", e))?
+        .unwrap_or_else(|| AccountInfo {
+            account_id: current_account_id,
+            display_name: String::new(),
+            created_at: String::new(),
+            signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
+        });
+    
+    Ok(account_info.signaling_server_url
+        .unwrap_or_else(|| get_default_beacon_url()))
+}
+
+#[tauri::command]
+async fn set_beacon_url(url: String) -> Result<(), String> {
+    // GUARDED: Requires active session
+    require_session()?;
+    save_beacon_url(&url)
+}
+
+/// Store `url` as the current account's primary beacon.
+fn save_beacon_url(url: &str) -> Result<(), String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let current_account_id = account_manager.get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))?
+        .ok_or_else(|| "No active session".to_string())?;
+    
     let mut account_info = account_manager.get_account_info(&current_account_id)
         .map_err(|e| format!("Failed to get account info: {}", e))?
         .unwrap_or_else(|| AccountInfo {
@@ -2744,6 +3320,7 @@ async fn set_beacon_url(url: String) -> Result<(), String> {
             display_name: String::new(),
             created_at: chrono::Utc::now().to_rfc3339(),
             signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
         });
     
     // Only save if different from default (to avoid cluttering account info)
@@ -2756,377 +3333,1485 @@ async fn set_beacon_url(url: String) -> Result<(), String> {
     
     account_manager.save_account_info(&account_info)
         .map_err(|e| format!("Failed to save account info: {}", e))?;
+    ice_servers::clear_cache();
     
     Ok(())
 }
 
-/// Read text from the system clipboard (avoids webview permission prompt).
+/// Primary beacon followed by the account's fallbacks, in failover order.
+fn account_beacon_urls() -> Result<Vec<String>, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let current_account_id = account_manager.get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))?
+        .ok_or_else(|| "No active session".to_string())?;
+    let account_info = account_manager.get_account_info(&current_account_id)
+        .map_err(|e| format!("Failed to get account info: {}", e))?;
+
+    let (primary, fallbacks) = match account_info {
+        Some(info) => (info.signaling_server_url, info.fallback_beacon_urls),
+        None => (None, Vec::new()),
+    };
+    let mut urls = vec![primary.unwrap_or_else(get_default_beacon_url)];
+    for url in fallbacks {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    Ok(urls)
+}
+
+/// ICE servers for new peer connections: STUN plus TURN credentials from the beacon the
+/// signaling connection is on (the primary beacon before failover has picked one).
 #[tauri::command]
-fn read_clipboard_text() -> Result<String, String> {
-    use arboard::Clipboard;
-    let mut clipboard = Clipboard::new().map_err(|e| format!("Clipboard unavailable: {}", e))?;
-    clipboard.get_text().map_err(|e| format!("Clipboard read failed: {}", e))
+async fn get_ice_servers() -> Result<Vec<ice_servers::IceServer>, String> {
+    require_session()?;
+    Ok(ice_servers::get_ice_servers(&active_beacon_url()?).await)
+}
+
+/// Beacon the signaling connection is on: failover's pick, else the account's primary.
+fn active_beacon_url() -> Result<String, String> {
+    match beacon_failover::status().and_then(|s| s.active) {
+        Some(url) => Ok(url),
+        None => Ok(account_beacon_urls()?.remove(0)),
+    }
 }
 
+// === Native WebRTC transport (`native-webrtc` builds only) ===
+
+#[cfg(not(feature = "native-webrtc"))]
+const NATIVE_RTC_UNAVAILABLE: &str = "Native WebRTC transport is not included in this build";
+
 #[tauri::command]
-fn open_path_in_file_explorer(path: String) -> Result<(), String> {
-    let target = path.trim();
-    if target.is_empty() {
-        return Err("Path is empty".to_string());
+fn native_rtc_available() -> bool {
+    cfg!(feature = "native-webrtc")
+}
+
+/// Open a native connection to `peer_id`; returns the offer SDP (candidates included).
+#[tauri::command]
+async fn native_rtc_create_offer(app: tauri::AppHandle, peer_id: String) -> Result<String, String> {
+    require_session()?;
+    #[cfg(feature = "native-webrtc")]
+    {
+        let ice = ice_servers::get_ice_servers(&active_beacon_url()?).await;
+        native_webrtc::create_offer(&app, &peer_id, ice).await
     }
-    let p = std::path::PathBuf::from(target);
-    if !p.exists() {
-        return Err("Path does not exist".to_string());
+    #[cfg(not(feature = "native-webrtc"))]
+    {
+        let _ = (app, peer_id);
+        Err(NATIVE_RTC_UNAVAILABLE.to_string())
     }
+}
 
-    #[cfg(target_os = "windows")]
+/// Answer a native offer from `peer_id`; returns the answer SDP.
+#[tauri::command]
+async fn native_rtc_accept_offer(app: tauri::AppHandle, peer_id: String, sdp: String) -> Result<String, String> {
+    require_session()?;
+    #[cfg(feature = "native-webrtc")]
     {
-        std::process::Command::new("explorer")
-            .arg(target)
-            .spawn()
-            .map_err(|e| format!("Failed to open in explorer: {}", e))?;
-        return Ok(());
+        let ice = ice_servers::get_ice_servers(&active_beacon_url()?).await;
+        native_webrtc::accept_offer(&app, &peer_id, sdp, ice).await
     }
-    #[cfg(target_os = "macos")]
+    #[cfg(not(feature = "native-webrtc"))]
     {
-        std::process::Command::new("open")
-            .arg(target)
-            .spawn()
-            .map_err(|e| format!("Failed to open in Finder: {}", e))?;
-        return Ok(());
+        let _ = (app, peer_id, sdp);
+        Err(NATIVE_RTC_UNAVAILABLE.to_string())
     }
-    #[cfg(target_os = "linux")]
+}
+
+#[tauri::command]
+async fn native_rtc_apply_answer(peer_id: String, sdp: String) -> Result<(), String> {
+    #[cfg(feature = "native-webrtc")]
     {
-        std::process::Command::new("xdg-open")
-            .arg(target)
-            .spawn()
-            .map_err(|e| format!("Failed to open in file manager: {}", e))?;
-        return Ok(());
+        native_webrtc::apply_answer(&peer_id, sdp).await
     }
-    #[allow(unreachable_code)]
-    Err("Unsupported OS".to_string())
+    #[cfg(not(feature = "native-webrtc"))]
+    {
+        let _ = (peer_id, sdp);
+        Err(NATIVE_RTC_UNAVAILABLE.to_string())
+    }
+}
+
+/// Close one native connection, or all of them without `peer_id`.
+#[tauri::command]
+async fn native_rtc_close(peer_id: Option<String>) -> Result<(), String> {
+    #[cfg(feature = "native-webrtc")]
+    {
+        match peer_id {
+            Some(peer_id) => native_webrtc::close(&peer_id).await,
+            None => native_webrtc::close_all().await,
+        }
+    }
+    #[cfg(not(feature = "native-webrtc"))]
+    {
+        let _ = peer_id;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "native-webrtc")]
+#[tauri::command]
+fn native_rtc_peers() -> Vec<native_webrtc::NativePeerState> {
+    native_webrtc::peers()
+}
+
+#[cfg(not(feature = "native-webrtc"))]
+#[tauri::command]
+fn native_rtc_peers() -> Vec<()> {
+    Vec::new()
+}
+
+// === Native file transfer ===
+
+/// Offer a file to `to_user_id`; it's read from disk as the peer pulls it.
+#[tauri::command]
+async fn send_file_transfer(app: tauri::AppHandle, to_user_id: String, path: String) -> Result<file_transfer::TransferProgress, String> {
+    require_session()?;
+    tauri::async_runtime::spawn_blocking(move || file_transfer::send_file(&app, &to_user_id, std::path::Path::new(&path)))
+        .await
+        .map_err(|e| format!("File transfer task failed: {}", e))?
+}
+
+/// Start downloading an offered file into the downloads folder (or resume into the earlier file).
+#[tauri::command]
+async fn accept_file_transfer(app: tauri::AppHandle, transfer_id: String) -> Result<(), String> {
+    require_session()?;
+    let downloads_dir = resolve_downloads_dir(None)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        file_transfer::accept(&app, &transfer_id, |name| resolve_download_target(&downloads_dir, name))
+    })
+    .await
+    .map_err(|e| format!("File transfer task failed: {}", e))?
+}
+
+#[tauri::command]
+fn pause_file_transfer(app: tauri::AppHandle, transfer_id: String) -> Result<(), String> {
+    require_session()?;
+    file_transfer::pause(&app, &transfer_id)
+}
+
+#[tauri::command]
+fn resume_file_transfer(app: tauri::AppHandle, transfer_id: String) -> Result<(), String> {
+    require_session()?;
+    file_transfer::resume(&app, &transfer_id)
+}
+
+#[tauri::command]
+fn cancel_file_transfer(app: tauri::AppHandle, transfer_id: String) -> Result<(), String> {
+    require_session()?;
+    file_transfer::cancel(&app, &transfer_id)
+}
+
+#[tauri::command]
+fn list_file_transfers() -> Result<Vec<file_transfer::TransferProgress>, String> {
+    require_session()?;
+    Ok(file_transfer::list())
+}
+
+#[tauri::command]
+async fn get_beacon_urls() -> Result<Vec<String>, String> {
+    // GUARDED: Requires active session
+    require_session()?;
+    account_beacon_urls()
+}
+
+/// Beacons tried after the primary one (`set_beacon_url`), most preferred first.
+#[tauri::command]
+async fn set_fallback_beacon_urls(urls: Vec<String>) -> Result<(), String> {
+    // GUARDED: Requires active session
+    require_session()?;
+
+    for url in &urls {
+        let url = url.trim();
+        if !url.starts_with("ws://") && !url.starts_with("wss://") {
+            return Err(format!("Invalid beacon URL (must start with ws:// or wss://): {}", url));
+        }
+    }
+
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let current_account_id = account_manager.get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))?
+        .ok_or_else(|| "No active session".to_string())?;
+
+    let mut account_info = account_manager.get_account_info(&current_account_id)
+        .map_err(|e| format!("Failed to get account info: {}", e))?
+        .unwrap_or_else(|| AccountInfo {
+            account_id: current_account_id.clone(),
+            display_name: String::new(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            signaling_server_url: None,
+            fallback_beacon_urls: Vec::new(),
+        });
+
+    account_info.fallback_beacon_urls = urls.iter()
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .collect();
+
+    account_manager.save_account_info(&account_info)
+        .map_err(|e| format!("Failed to save account info: {}", e))?;
+
+    Ok(())
+}
+
+/// Keep the native signaling connection on the best available beacon. Uses the account's
+/// primary + fallback beacons unless `urls` is given.
+#[tauri::command]
+async fn start_beacon_failover(app: tauri::AppHandle, urls: Option<Vec<String>>) -> Result<(), String> {
+    let urls = match urls {
+        Some(urls) => urls,
+        None => {
+            require_session()?;
+            account_beacon_urls()?
+        }
+    };
+    beacon_failover::start(app, urls)
+}
+
+#[tauri::command]
+fn list_beacon_profiles() -> Vec<settings::BeaconProfile> {
+    settings::current().beacon_profiles
+}
+
+/// Add or update a beacon profile (matched by id; a new one gets an id). Returns it as stored.
+#[tauri::command]
+fn save_beacon_profile(app: tauri::AppHandle, profile: settings::BeaconProfile) -> Result<settings::BeaconProfile, String> {
+    let url = profile.url.trim();
+    if !url.starts_with("ws://") && !url.starts_with("wss://") {
+        return Err(format!("Invalid beacon URL (must start with ws:// or wss://): {}", url));
+    }
+    let mut current = settings::current();
+    let index = match current.beacon_profiles.iter().position(|p| !profile.id.is_empty() && p.id == profile.id) {
+        Some(index) => {
+            current.beacon_profiles[index] = profile;
+            index
+        }
+        None => {
+            current.beacon_profiles.push(profile);
+            current.beacon_profiles.len() - 1
+        }
+    };
+    let saved = settings::set_settings(&app, current)?;
+    saved
+        .beacon_profiles
+        .get(index)
+        .cloned()
+        .ok_or_else(|| "Failed to save beacon profile".to_string())
+}
+
+#[tauri::command]
+fn delete_beacon_profile(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    let mut current = settings::current();
+    current.beacon_profiles.retain(|profile| profile.id != id);
+    settings::set_settings(&app, current).map(|_| ())
+}
+
+/// Move everything to another beacon profile: drop the connections and per-beacon state of
+/// the current one, apply the profile's account, proxy and TLS options, and make its URL the
+/// primary beacon. Emits `cordia:beacon-profile-switched` so the UI reconnects.
+#[tauri::command]
+fn switch_beacon_profile(app: tauri::AppHandle, id: String) -> Result<settings::BeaconProfile, String> {
+    let mut current = settings::current();
+    let profile = current
+        .beacon_profiles
+        .iter()
+        .find(|profile| profile.id == id)
+        .cloned()
+        .ok_or_else(|| format!("No beacon profile {}", id))?;
+
+    // Everything here is tied to the old beacon; nothing reconnects until the switch is done.
+    beacon_failover::stop();
+    beacon_monitor::stop();
+    signaling::disconnect();
+
+    match &profile.account_id {
+        Some(account_id) => set_session_account(account_id)?,
+        None => {
+            require_session()?;
+            // Queued messages and TURN credentials were meant for the old beacon.
+            ice_servers::clear_cache();
+            outbound_queue::clear()?;
+        }
+    }
+    proxy::set_proxy_settings(profile.proxy.clone())?;
+    beacon_tls::set_beacon_tls_options(&profile.url, profile.tls.clone())?;
+    save_beacon_url(&profile.url)?;
+
+    current.active_beacon_profile = Some(profile.id.clone());
+    settings::set_settings(&app, current)?;
+    let _ = app.emit_all("cordia:beacon-profile-switched", &profile);
+    Ok(profile)
+}
+
+#[tauri::command]
+fn stop_beacon_failover() {
+    beacon_failover::stop();
+}
+
+#[tauri::command]
+fn get_beacon_failover_status() -> Option<beacon_failover::FailoverStatus> {
+    beacon_failover::status()
+}
+
+/// Read text from the system clipboard (avoids webview permission prompt).
+#[tauri::command]
+fn read_clipboard_text() -> Result<String, String> {
+    use arboard::Clipboard;
+    let mut clipboard = Clipboard::new().map_err(|e| format!("Clipboard unavailable: {}", e))?;
+    clipboard.get_text().map_err(|e| format!("Clipboard read failed: {}", e))
+}
+
+#[tauri::command]
+fn open_path_in_file_explorer(path: String) -> Result<(), String> {
+    let target = path.trim();
+    if target.is_empty() {
+        return Err("Path is empty".to_string());
+    }
+    let p = std::path::PathBuf::from(target);
+    if !p.exists() {
+        return Err("Path does not exist".to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("explorer")
+            .arg(target)
+            .spawn()
+            .map_err(|e| format!("Failed to open in explorer: {}", e))?;
+        return Ok(());
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg(target)
+            .spawn()
+            .map_err(|e| format!("Failed to open in Finder: {}", e))?;
+        return Ok(());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("xdg-open")
+            .arg(target)
+            .spawn()
+            .map_err(|e| format!("Failed to open in file manager: {}", e))?;
+        return Ok(());
+    }
+    #[allow(unreachable_code)]
+    Err("Unsupported OS".to_string())
+}
+
+#[tauri::command]
+fn path_exists(path: String) -> bool {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return false;
+    }
+    PathBuf::from(trimmed).exists()
+}
+
+#[cfg(windows)]
+#[tauri::command]
+fn register_key_file_association_command() -> Result<(), String> {
+    #[cfg(all(windows, feature = "windows-registry"))]
+    {
+        use file_association::register_key_file_association;
+        register_key_file_association()
+            .map_err(|e| format!("Failed to register .key file association: {}", e))
+    }
+    #[cfg(not(all(windows, feature = "windows-registry")))]
+    {
+        Err("File association registration is only supported on Windows with windows-registry feature".to_string())
+    }
+}
+
+// === Account Management Commands ===
+
+#[tauri::command]
+fn list_accounts() -> Result<Vec<String>, String> {
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+    manager.list_accounts()
+        .map_err(|e| format!("Failed to list accounts: {}", e))
+}
+
+#[tauri::command]
+fn get_account_info(account_id: String) -> Result<Option<AccountInfo>, String> {
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+    manager.get_account_info(&account_id)
+        .map_err(|e| format!("Failed to get account info: {}", e))
+}
+
+#[tauri::command]
+fn get_current_session() -> Result<SessionState, String> {
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+    manager.get_session()
+        .map_err(|e| format!("Failed to get session: {}", e))
+}
+
+#[tauri::command]
+fn switch_account(account_id: String) -> Result<(), String> {
+    set_session_account(&account_id)
+}
+
+fn set_session_account(account_id: &str) -> Result<(), String> {
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+
+    // Verify account exists
+    if !manager.account_exists(account_id) {
+        return Err(format!("Account {} does not exist", account_id));
+    }
+
+    manager.set_session(account_id)
+        .map_err(|e| format!("Failed to switch account: {}", e))?;
+    // Queued messages and TURN credentials belong to the previous account's session.
+    ice_servers::clear_cache();
+    outbound_queue::clear()
+}
+
+#[tauri::command]
+fn logout_account() -> Result<(), String> {
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+    manager.clear_session()
+        .map_err(|e| format!("Failed to logout: {}", e))?;
+    ice_servers::clear_cache();
+    outbound_queue::clear()
+}
+
+#[tauri::command]
+fn get_current_account_id() -> Result<Option<String>, String> {
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+    manager.get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))
+}
+
+#[tauri::command]
+fn delete_account(account_id: String) -> Result<(), String> {
+    // NO GUARD: Can delete any account (user must be on account select screen)
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+    manager.delete_account(&account_id)
+        .map_err(|e| format!("Failed to delete account: {}", e))
+}
+
+#[tauri::command]
+fn list_friends() -> Result<Vec<String>, String> {
+    let account_id = require_session()?;
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    manager.load_friends(&account_id)
+        .map_err(|e| format!("Failed to load friends: {}", e))
+}
+
+#[tauri::command]
+fn add_friend(user_id: String) -> Result<(), String> {
+    let account_id = require_session()?;
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let mut friends = manager.load_friends(&account_id)
+        .map_err(|e| format!("Failed to load friends: {}", e))?;
+    if !friends.contains(&user_id) {
+        friends.push(user_id);
+        manager.save_friends(&account_id, &friends)
+            .map_err(|e| format!("Failed to save friends: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn load_known_profiles() -> Result<serde_json::Value, String> {
+    let account_id = require_session()?;
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let map = manager.load_known_profiles(&account_id)
+        .map_err(|e| format!("Failed to load known profiles: {}", e))?;
+    serde_json::to_value(map).map_err(|e| format!("Failed to serialize known profiles: {}", e))
+}
+
+#[tauri::command]
+fn save_known_profiles(profiles: std::collections::HashMap<String, KnownProfile>) -> Result<(), String> {
+    let account_id = require_session()?;
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    manager.save_known_profiles(&account_id, &profiles)
+        .map_err(|e| format!("Failed to save known profiles: {}", e))
+}
+
+#[tauri::command]
+fn remove_friend(user_id: String) -> Result<(), String> {
+    let account_id = require_session()?;
+    let manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let mut friends = manager.load_friends(&account_id)
+        .map_err(|e| format!("Failed to load friends: {}", e))?;
+    friends.retain(|id| id != &user_id);
+    manager.save_friends(&account_id, &friends)
+        .map_err(|e| format!("Failed to save friends: {}", e))
+}
+
+/// Returns headers for friend API auth: request signed with identity Ed25519 key.
+/// Envelope: method + "\n" + path + "\n" + timestamp + "\n" + sha256(body).hex().
+/// No shared secret; server verifies signature with public key (mailbox-style).
+#[tauri::command]
+fn get_friend_auth_headers(
+    method: String,
+    path: String,
+    body: Option<String>,
+) -> Result<std::collections::HashMap<String, String>, String> {
+    let _ = require_session()?;
+    let manager = IdentityManager::new()
+        .map_err(|e| format!("Identity manager: {}", e))?;
+    let identity = manager.load_identity()
+        .map_err(|e| format!("Load identity: {}", e))?;
+    if identity.private_key.is_none() {
+        return Err("Identity has no private key. If you created this account before a recent update, create a new account to use friend requests and friend codes.".to_string());
+    }
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let body_hash = match body.as_deref().unwrap_or("") {
+        "" => String::new(),
+        b => {
+            use sha2::Digest;
+            let mut hasher = sha2::Sha256::new();
+            hasher.update(b.as_bytes());
+            hex::encode(hasher.finalize())
+        }
+    };
+    let envelope = format!("{}\n{}\n{}\n{}",
+        method.to_uppercase(),
+        path.trim(),
+        timestamp,
+        body_hash,
+    );
+    let signature = manager.sign(envelope.as_bytes())
+        .map_err(|e| format!("Sign: {}", e))?;
+
+    let mut headers = std::collections::HashMap::new();
+    headers.insert("X-User-Id".to_string(), identity.user_id);
+    headers.insert("X-Timestamp".to_string(), timestamp.to_string());
+    headers.insert("X-Public-Key".to_string(), identity.public_key.clone());
+    headers.insert("X-Signature".to_string(), base64::encode(signature.to_bytes()));
+    Ok(headers)
+}
+
+// === Native Audio Commands ===
+
+#[tauri::command]
+fn enumerate_audio_devices_native() -> Result<Vec<AudioDevice>, String> {
+    enumerate_devices()
+}
+
+/// Audio hosts (backends) available in this build, e.g. ["WASAPI", "ASIO"].
+#[tauri::command]
+fn list_audio_hosts() -> Vec<String> {
+    audio_capture::available_hosts()
+}
+
+/// Devices on a specific host, without switching to it.
+#[tauri::command]
+fn enumerate_audio_devices_for_host(host: String) -> Result<Vec<AudioDevice>, String> {
+    audio_capture::enumerate_host_devices(&host)
+}
+
+/// Supported rates/channels/formats/buffer sizes for a device, so settings can be checked
+/// before capture starts. `kind` defaults to input; `device_id` omitted = default device.
+#[tauri::command]
+fn probe_device(device_id: Option<String>, kind: Option<AudioDeviceKind>) -> Result<DeviceCapabilities, String> {
+    audio_capture::probe_device(kind.unwrap_or(AudioDeviceKind::Input), device_id.as_deref())
+}
+
+/// Select the audio host for device enumeration and capture/playback (None = default).
+#[tauri::command]
+fn set_audio_host(host: Option<String>) -> Result<(), String> {
+    audio_capture::set_audio_host(host.as_deref())
+}
+
+/// Processed frames queued toward JS: ~30 ms of audio. If JS doesn't drain in time, we drop (never block Rust).
+const PROCESSED_FRAME_QUEUE_MS: u32 = 30;
+
+/// Queue capacity in frames for a frame duration; at least two so one late drain doesn't drop.
+fn processed_frame_queue_cap(frame_ms: u32) -> usize {
+    (PROCESSED_FRAME_QUEUE_MS.div_ceil(frame_ms.max(1)) as usize).max(2)
+}
+
+#[tauri::command]
+fn start_audio_capture(
+    app: tauri::AppHandle,
+    device_id: Option<String>,
+    device_ids: Option<Vec<String>>,
+    options: Option<audio_capture::CaptureOptions>,
+    opus: Option<audio_opus::OpusSettings>,
+) -> Result<(), String> {
+    // `device_ids` mixes several inputs (e.g. mic + line-in); otherwise capture `device_id` alone.
+    let devices = match device_ids {
+        Some(ids) if !ids.is_empty() => ids.into_iter().map(Some).collect(),
+        _ => vec![device_id],
+    };
+
+    // With `opus`, frames are encoded after DSP and emitted as `cordia:audio-packet` instead
+    // of raw PCM on `cordia:audio-frame`.
+    let encoder = match opus {
+        Some(settings) => {
+            audio_opus::set_opus_settings(settings)?;
+            Some(audio_opus::OpusFrameEncoder::new()?)
+        }
+        None => None,
+    };
+
+    // Bounded channel: processing thread uses try_send; when full, frames are dropped (audio loss > latency).
+    let frame_ms = options.as_ref().and_then(|o| o.frame_ms).unwrap_or(10);
+    let (processed_tx, processed_rx) = std::sync::mpsc::sync_channel(processed_frame_queue_cap(frame_ms));
+    let (level_tx, level_rx) = std::sync::mpsc::channel();
+
+    // Unplugged mid-call: capture moves to the default input; keep the stats going for it.
+    let app_lost = app.clone();
+    let on_device_lost: audio_capture::DeviceLostHandler = std::sync::Arc::new(move |event| {
+        let _ = app_lost.emit_all("cordia:audio-device-lost", event);
+        if event.failed_over {
+            spawn_capture_stats(app_lost.clone());
+        }
+    });
+    // Driver stopped delivering audio (e.g. after sleep/resume): the stream is rebuilt in place.
+    let app_stall = app.clone();
+    let on_stall: audio_capture::CaptureStallHandler = std::sync::Arc::new(move |event| {
+        let _ = app_stall.emit_all("cordia:audio-capture-stalled", event);
+        if event.restarted {
+            spawn_capture_stats(app_stall.clone());
+        }
+    });
+    // A new capture may be a different mic; relearn its noise floor.
+    get_dsp()
+        .lock()
+        .map_err(|_| "Failed to lock DSP".to_string())?
+        .reset_noise_floor();
+    start_capture(devices, options.unwrap_or_default(), processed_tx, level_tx, on_device_lost, on_stall)?;
+
+    let app_clone = app.clone();
+    std::thread::spawn(move || {
+        // Level updates: event-driven, no polling
+        let app_level = app_clone.clone();
+        std::thread::spawn(move || {
+            loop {
+                match level_rx.recv() {
+                    Ok(level) => {
+                        let _ = app_level.emit_all("cordia:audio-level", level);
+                        let dsp = get_dsp();
+                        let taken = dsp.lock().map(|mut dsp| (dsp.take_meter(), dsp.take_transmission_change()));
+                        if let Ok((meter, transmission)) = taken {
+                            let _ = app_level.emit_all("cordia:audio-meter", meter);
+                            // Gate opened/closed (debounced): drives the speaking indicator.
+                            if let Some(transmitting) = transmission {
+                                let _ = app_level.emit_all("cordia:transmission-state", transmitting);
+                            }
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        match encoder {
+            Some(encoder) => run_opus_emitter(&app_clone, processed_rx, encoder),
+            None => run_frame_emitter(&app_clone, processed_rx, "cordia:audio-frame", audio_capture::note_emitted_frame),
+        }
+    });
+
+    spawn_capture_stats(app);
+
+    Ok(())
+}
+
+/// Pipeline health for the dev overlay; ends with the current capture session.
+fn spawn_capture_stats(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut meter = audio_capture::CaptureStatsMeter::new();
+        loop {
+            std::thread::sleep(audio_capture::CaptureStatsMeter::INTERVAL);
+            match meter.sample() {
+                Some(stats) => {
+                    let _ = app.emit_all("cordia:audio-stats", stats);
+                }
+                None => break,
+            }
+        }
+    });
+}
+
+/// Emitter: drain opportunistically, batch ~20 ms of audio (2 frames at 10 ms) to reduce
+/// IPC jitter. Never block Rust.
+/// Frames are emitted as base64 f32 LE. Returns when the sender side disconnects.
+/// `on_frame` sees each frame as it is batched (timing/sequence bookkeeping).
+fn run_frame_emitter<F>(
+    app: &tauri::AppHandle,
+    processed_rx: std::sync::mpsc::Receiver<audio_capture::ProcessedFrame>,
+    event: &str,
+    mut on_frame: F,
+) where
+    F: FnMut(&audio_capture::ProcessedFrame),
+{
+    const BATCH_SAMPLES: usize = 960; // 20 ms; 20/40 ms frames go out one per event
+    const RECV_TIMEOUT_MS: u64 = 25; // ~2.5 frames at 10 ms/frame; if nothing, emit what we have
+    let timeout = std::time::Duration::from_millis(RECV_TIMEOUT_MS);
+    let mut batch: Vec<f32> = Vec::with_capacity(BATCH_SAMPLES * 2);
+    let mut frame_bytes: Vec<u8> = Vec::with_capacity(BATCH_SAMPLES * 2 * 4);
+    loop {
+        batch.clear();
+        let mut got_any = false;
+        while batch.len() < BATCH_SAMPLES {
+            match processed_rx.recv_timeout(timeout) {
+                Ok(frame) => {
+                    on_frame(&frame);
+                    batch.extend_from_slice(&frame.pcm);
+                    frame.recycle();
+                    got_any = true;
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => break,
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        if got_any && !batch.is_empty() {
+            frame_bytes.clear();
+            frame_bytes.extend(batch.iter().flat_map(|f| f.to_le_bytes()));
+            let frame_b64 = base64::encode(&frame_bytes);
+            let _ = app.emit_all(event, frame_b64);
+        }
+    }
+}
+
+/// Opus emitter: one `cordia:audio-packet` per processed frame (no batching; packets are
+/// small). A frame that fails to encode is skipped and shows up as a sequence gap.
+fn run_opus_emitter(
+    app: &tauri::AppHandle,
+    processed_rx: std::sync::mpsc::Receiver<audio_capture::ProcessedFrame>,
+    mut encoder: audio_opus::OpusFrameEncoder,
+) {
+    while let Ok(frame) = processed_rx.recv() {
+        audio_capture::note_emitted_frame(&frame);
+        match encoder.encode(&frame.pcm) {
+            Ok(packet) => {
+                #[cfg(feature = "native-webrtc")]
+                native_webrtc::write_opus(packet, frame.pcm.len());
+                let _ = app.emit_all("cordia:audio-packet", audio_opus::OpusPacket {
+                    seq: frame.seq,
+                    capture_ts: frame.capture_ts,
+                    samples: frame.pcm.len(),
+                    data: base64::encode(packet),
+                });
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        frame.recycle();
+    }
+}
+
+/// Spectrum meter for mic troubleshooting: emits `cordia:audio-spectrum` (band centre
+/// frequencies + dBFS levels of the processed mic) while capture is running.
+/// Defaults: 32 bands at 15 Hz.
+#[tauri::command]
+fn start_spectrum_analyzer(app: tauri::AppHandle, bands: Option<usize>, rate_hz: Option<u32>) -> Result<(), String> {
+    audio_spectrum::start_spectrum(
+        bands.unwrap_or(audio_spectrum::DEFAULT_BANDS),
+        rate_hz.unwrap_or(audio_spectrum::DEFAULT_RATE_HZ),
+        move |frame| {
+            let _ = app.emit_all("cordia:audio-spectrum", frame);
+        },
+    )
+}
+
+#[tauri::command]
+fn stop_spectrum_analyzer() -> Result<(), String> {
+    audio_spectrum::stop_spectrum();
+    Ok(())
+}
+
+/// Capture ~2 s from the input through the DSP, check cadence and levels, optionally play a
+/// test tone, and return a report for support. Fails if capture is already running.
+#[tauri::command]
+async fn run_audio_selftest(
+    device_id: Option<String>,
+    play_tone: Option<bool>,
+    output_device_id: Option<String>,
+) -> Result<audio_selftest::SelfTestReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        audio_selftest::run_selftest(device_id, play_tone.unwrap_or(false), output_device_id)
+    })
+    .await
+    .map_err(|e| format!("Self-test task failed: {}", e))?
+}
+
+/// Run a WAV file through the DSP chain into `output_path` (48 kHz mono WAV), with
+/// `config` or, if omitted, the live settings. For previewing denoise/gate settings.
+#[tauri::command]
+async fn process_audio_file(
+    input_path: String,
+    output_path: String,
+    config: Option<DspConfig>,
+) -> Result<audio_offline::ProcessedFile, String> {
+    let config = match config {
+        Some(config) => config,
+        None => {
+            let dsp = get_dsp();
+            let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+            dsp_guard.config()
+        }
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        audio_offline::process_audio_file(
+            std::path::Path::new(&input_path),
+            std::path::Path::new(&output_path),
+            &config,
+        )
+    })
+    .await
+    .map_err(|e| format!("Audio file processing task failed: {}", e))?
+}
+
+/// Listen to ~5 s of normal speech and propose input gain + voice activity threshold.
+/// With `apply`, the values are also set on the DSP (the frontend still persists them).
+#[tauri::command]
+async fn calibrate_input(
+    device_id: Option<String>,
+    duration_secs: Option<f32>,
+    apply: Option<bool>,
+) -> Result<audio_calibration::CalibrationResult, String> {
+    let duration = duration_secs
+        .map(|s| std::time::Duration::from_secs_f32(s.clamp(2.0, 15.0)))
+        .unwrap_or(audio_calibration::DEFAULT_DURATION);
+    tauri::async_runtime::spawn_blocking(move || {
+        audio_calibration::calibrate_input(device_id, duration, apply.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("Calibration task failed: {}", e))?
+}
+
+/// Start desktop-audio capture for screen sharing. Frames are emitted as
+/// `cordia:loopback-frame` in the same encoding as `cordia:audio-frame`.
+#[tauri::command]
+fn start_loopback_capture(
+    app: tauri::AppHandle,
+    device_id: Option<String>,
+) -> Result<(), String> {
+    let (processed_tx, processed_rx) = std::sync::mpsc::sync_channel(processed_frame_queue_cap(10));
+    audio_loopback::start_loopback_capture(device_id, processed_tx)?;
+    std::thread::spawn(move || {
+        run_frame_emitter(&app, processed_rx, "cordia:loopback-frame", |_| {});
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_loopback_capture() -> Result<(), String> {
+    audio_loopback::stop_loopback_capture();
+    Ok(())
+}
+
+/// List applications currently playing audio (Windows only).
+#[tauri::command]
+fn enumerate_audio_applications() -> Result<Vec<app_audio_capture::AudioApplication>, String> {
+    app_audio_capture::enumerate_audio_applications()
+}
+
+/// Capture a single application's audio (Windows only). Frames are emitted as
+/// `cordia:app-audio-frame` in the same encoding as `cordia:audio-frame`.
+#[tauri::command]
+fn start_app_capture(app: tauri::AppHandle, pid: u32) -> Result<(), String> {
+    let (processed_tx, processed_rx) = std::sync::mpsc::sync_channel(processed_frame_queue_cap(10));
+    app_audio_capture::start_app_capture(pid, processed_tx)?;
+    std::thread::spawn(move || {
+        run_frame_emitter(&app, processed_rx, "cordia:app-audio-frame", |_| {});
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_app_capture() -> Result<(), String> {
+    app_audio_capture::stop_app_capture();
+    Ok(())
+}
+
+/// Record the mic chain to a WAV or FLAC file. Emits `cordia:recording-stopped` with the
+/// summary when the recording ends (manual stop, duration/size limit, or write error).
+#[tauri::command]
+fn start_recording(
+    app: tauri::AppHandle,
+    path: String,
+    format: RecordingFormat,
+    source: Option<RecordingSource>,
+    max_duration_secs: Option<f64>,
+    max_bytes: Option<u64>,
+) -> Result<(), String> {
+    audio_recording::start_recording(
+        PathBuf::from(path),
+        format,
+        source.unwrap_or(RecordingSource::Processed),
+        max_duration_secs,
+        max_bytes,
+        move |summary| {
+            let _ = app.emit_all("cordia:recording-stopped", summary);
+        },
+    )
+}
+
+#[tauri::command]
+fn stop_recording() -> Result<RecordingSummary, String> {
+    audio_recording::stop_recording()
+}
+
+#[tauri::command]
+fn is_recording() -> bool {
+    audio_recording::is_recording()
+}
+
+/// Record the call with one track per participant into `directory`. Emits
+/// `cordia:multitrack-recording` when it starts, when a peer's track opens (the cue to ask
+/// that peer for consent) and with the summary when it stops.
+#[tauri::command]
+fn start_multitrack_recording(
+    app: tauri::AppHandle,
+    directory: String,
+    options: Option<audio_multitrack::MultitrackOptions>,
+) -> Result<(), String> {
+    audio_multitrack::start_multitrack_recording(PathBuf::from(directory), options.unwrap_or_default(), move |event| {
+        let _ = app.emit_all("cordia:multitrack-recording", event);
+    })
+}
+
+#[tauri::command]
+fn stop_multitrack_recording() -> Result<audio_multitrack::MultitrackSummary, String> {
+    audio_multitrack::stop_multitrack_recording()
+}
+
+#[tauri::command]
+fn is_multitrack_recording() -> bool {
+    audio_multitrack::is_multitrack_recording()
+}
+
+/// A peer's answer to the recording consent request.
+#[tauri::command]
+fn set_recording_consent(peer_id: String, granted: bool) -> Result<(), String> {
+    audio_multitrack::set_peer_consent(&peer_id, granted)
+}
+
+#[tauri::command]
+fn stop_audio_capture() -> Result<(), String> {
+    stop_capture();
+    Ok(())
+}
+
+/// Pause mic capture; the device stays open so resuming is instant.
+#[tauri::command]
+fn pause_audio_capture() -> Result<(), String> {
+    pause_capture()
+}
+
+#[tauri::command]
+fn resume_audio_capture() -> Result<(), String> {
+    resume_capture()
+}
+
+#[tauri::command]
+fn set_audio_gain(gain: f32) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_gain(gain);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_audio_threshold(threshold: f32) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_threshold(threshold);
+    Ok(())
+}
+
+/// Toggle noise suppression in front of the DSP. `strength` (0-1, default 1) blends the
+/// denoised signal with the original; `backend` picks one of `list_denoisers` (default:
+/// keep the current one, initially RNNoise).
+#[tauri::command]
+fn set_noise_suppression(enabled: bool, strength: Option<f32>, backend: Option<String>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    if let Some(backend) = backend {
+        dsp_guard.set_denoiser(&backend)?;
+    }
+    dsp_guard.set_noise_suppression(enabled, strength.unwrap_or(1.0));
+    Ok(())
+}
+
+#[tauri::command]
+fn list_denoisers() -> Vec<String> {
+    audio_denoise::denoiser_names().into_iter().map(String::from).collect()
+}
+
+/// Toggle the high-pass (rumble/plosive) filter. `cutoff_hz` is 20-300, default 90.
+#[tauri::command]
+fn set_high_pass_filter(enabled: bool, cutoff_hz: Option<f32>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_high_pass(enabled, cutoff_hz.unwrap_or(audio_dsp::DEFAULT_HIGH_PASS_HZ));
+    Ok(())
+}
+
+/// Toggle the compressor/limiter on the transmitted signal. Missing settings use defaults.
+#[tauri::command]
+fn set_compressor(enabled: bool, settings: Option<CompressorSettings>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_compressor(enabled, settings.unwrap_or_default());
+    Ok(())
+}
+
+/// Troubleshooting switch: send captured audio with only the gain applied, skipping all
+/// other processing, to tell device problems from DSP ones.
+#[tauri::command]
+fn set_dsp_bypass(bypass: bool) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_bypass(bypass);
+    Ok(())
+}
+
+/// Reorder or bypass mic processing stages. No stage may be listed twice; missing ones
+/// keep their default place.
+#[tauri::command]
+fn set_dsp_chain(chain: Vec<DspStageConfig>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_chain(&chain)
+}
+
+#[tauri::command]
+fn get_dsp_chain() -> Result<Vec<DspStageConfig>, String> {
+    let dsp = get_dsp();
+    let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    Ok(dsp_guard.chain())
+}
+
+/// Set the mic EQ (up to 5 bands; an empty list turns it off).
+#[tauri::command]
+fn set_eq(bands: Vec<EqBand>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_eq(&bands)
+}
+
+/// Toggle the voice changer (pitch/formant shift in semitones). Fails in builds without
+/// the `fun-effects` feature.
+#[tauri::command]
+fn set_voice_effect(enabled: bool, effect: Option<VoiceEffect>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_voice_effect(enabled, effect.unwrap_or_default())
+}
+
+/// Toggle the clarity stage (presence boost + de-esser) for muffled mics. `strength` is
+/// 0-1, default 0.5.
+#[tauri::command]
+fn set_clarity(enabled: bool, strength: Option<f32>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_clarity(enabled, strength.unwrap_or(audio_dsp::DEFAULT_CLARITY_STRENGTH));
+    Ok(())
+}
+
+/// Toggle automatic gain control. `target_db` (dBFS RMS, default -20) is the level it
+/// steers speech toward.
+#[tauri::command]
+fn set_auto_gain(enabled: bool, target_db: Option<f32>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_auto_gain(enabled, target_db.unwrap_or(audio_dsp::DEFAULT_AGC_TARGET_DB));
+    Ok(())
+}
+
+/// Toggle acoustic echo cancellation against native playback. `tail_ms` (50-500, default
+/// 200) is the longest echo it learns.
+#[tauri::command]
+fn set_echo_cancellation(enabled: bool, tail_ms: Option<u32>) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_echo_cancellation(enabled, tail_ms.unwrap_or(audio_aec::DEFAULT_TAIL_MS))
+}
+
+#[tauri::command]
+fn set_audio_input_mode(mode: String) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    let input_mode = match mode.as_str() {
+        "voice_activity" => InputMode::VoiceActivity,
+        "level_gate" => InputMode::LevelGate,
+        "push_to_talk" => InputMode::PushToTalk,
+        "hybrid" => InputMode::Hybrid,
+        "open_mic" => InputMode::OpenMic,
+        _ => return Err(format!("Invalid input mode: {}", mode)),
+    };
+    dsp_guard.set_input_mode(input_mode);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_ptt_key_pressed(pressed: bool) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_ptt_pressed(pressed);
+    Ok(())
+}
+
+/// Keep the system awake while in a voice call. Safe to call again while held.
+#[tauri::command]
+fn acquire_sleep_inhibitor() -> Result<(), String> {
+    power::acquire()
+}
+
+#[tauri::command]
+fn release_sleep_inhibitor() {
+    power::release();
+}
+
+/// Whether the user is idle (no input for `settings.idle.after_minutes`); changes arrive as `cordia:idle-changed`.
+#[tauri::command]
+fn get_idle_state() -> idle::IdleState {
+    idle::state()
+}
+
+/// Which keybinds (including PTT) are registered system-wide, and why not for the rest.
+#[tauri::command]
+fn get_keybind_status() -> Vec<global_hotkeys::KeybindStatus> {
+    global_hotkeys::status()
+}
+
+/// How long (0-500 ms) to keep transmitting after the PTT key is released.
+#[tauri::command]
+fn set_ptt_release_delay(ms: u32) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_ptt_release_ms(ms);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_transmission_muted(muted: bool) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_transmission_muted(muted);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_audio_level() -> Result<f32, String> {
+    let dsp = get_dsp();
+    let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    Ok(dsp_guard.get_level())
+}
+
+/// Forget the learned mic noise floor and relearn it (takes a few seconds).
+#[tauri::command]
+fn reset_noise_floor() -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.reset_noise_floor();
+    Ok(())
+}
+
+/// Whether peers currently hear the mic (debounced gate state). Changes are also emitted
+/// as `cordia:transmission-state` during capture.
+#[tauri::command]
+fn get_transmission_state() -> Result<bool, String> {
+    let dsp = get_dsp();
+    let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    Ok(dsp_guard.is_transmitting())
 }
 
+/// Override the level meter/gate constants for unusual mic hardware. Values are clamped;
+/// returns what was applied.
 #[tauri::command]
-fn path_exists(path: String) -> bool {
-    let trimmed = path.trim();
-    if trimmed.is_empty() {
-        return false;
-    }
-    PathBuf::from(trimmed).exists()
+fn set_meter_tuning(tuning: MeterTuning) -> Result<MeterTuning, String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    Ok(dsp_guard.set_meter_tuning(tuning))
 }
 
-#[cfg(windows)]
+/// Smoothing profile for the meter reading's `level`: "peak", "ppm" or "vu".
 #[tauri::command]
-fn register_key_file_association_command() -> Result<(), String> {
-    #[cfg(all(windows, feature = "windows-registry"))]
-    {
-        use file_association::register_key_file_association;
-        register_key_file_association()
-            .map_err(|e| format!("Failed to register .key file association: {}", e))
-    }
-    #[cfg(not(all(windows, feature = "windows-registry")))]
-    {
-        Err("File association registration is only supported on Windows with windows-registry feature".to_string())
-    }
+fn set_meter_smoothing(profile: MeterSmoothing) -> Result<(), String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    dsp_guard.set_meter_smoothing(profile);
+    Ok(())
 }
 
-// === Account Management Commands ===
+/// Latest dBFS peak/RMS of the mic (captured and transmitted) plus whether it clipped
+/// since the last read. Also emitted per frame as `cordia:audio-meter` during capture.
+#[tauri::command]
+fn get_audio_meter() -> Result<audio_dsp::MeterReading, String> {
+    let dsp = get_dsp();
+    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
+    Ok(dsp_guard.take_meter())
+}
 
+/// Start watching for audio device hotplug. Emits `cordia:audio-devices-changed` with
+/// added/removed devices and the full list. When the OS default output moves and playback
+/// follows it, playback switches over and `cordia:audio-output-followed` is emitted.
 #[tauri::command]
-fn list_accounts() -> Result<Vec<String>, String> {
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
-    manager.list_accounts()
-        .map_err(|e| format!("Failed to list accounts: {}", e))
+fn start_audio_device_watcher(app: tauri::AppHandle) -> Result<(), String> {
+    device_watcher::start_device_watcher(move |event| {
+        let _ = app.emit_all("cordia:audio-devices-changed", event);
+        if event.default_changed {
+            match audio_output::follow_default_output() {
+                Ok(true) => {
+                    let _ = app.emit_all("cordia:audio-output-followed", ());
+                }
+                Ok(false) => {}
+                Err(e) => eprintln!("Failed to follow default output: {}", e),
+            }
+        }
+    });
+    Ok(())
 }
 
 #[tauri::command]
-fn get_account_info(account_id: String) -> Result<Option<AccountInfo>, String> {
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
-    manager.get_account_info(&account_id)
-        .map_err(|e| format!("Failed to get account info: {}", e))
+fn stop_audio_device_watcher() -> Result<(), String> {
+    device_watcher::stop_device_watcher();
+    Ok(())
 }
 
 #[tauri::command]
-fn get_current_session() -> Result<SessionState, String> {
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
-    manager.get_session()
-        .map_err(|e| format!("Failed to get session: {}", e))
+fn start_audio_playback(device_id: Option<String>) -> Result<(), String> {
+    audio_output::start_playback(device_id)?;
+    audio_jitter::start_playout();
+    Ok(())
 }
 
 #[tauri::command]
-fn switch_account(account_id: String) -> Result<(), String> {
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
+fn stop_audio_playback() -> Result<(), String> {
+    audio_jitter::stop_playout();
+    connection_stats::clear_reports();
+    audio_output::stop_playback();
+    Ok(())
+}
 
-    // Verify account exists
-    if !manager.account_exists(&account_id) {
-        return Err(format!("Account {} does not exist", account_id));
-    }
+/// Queue a timestamped remote packet in that peer's jitter buffer. `codec` is "opus"
+/// (`data_b64` = Opus packet) or "pcm" (mono 48 kHz f32 LE, as in `cordia:audio-frame`).
+#[tauri::command]
+fn push_remote_audio_packet(
+    peer_id: String,
+    seq: u64,
+    capture_ts: u64,
+    codec: String,
+    data_b64: String,
+) -> Result<(), String> {
+    let bytes = base64::decode(&data_b64).map_err(|e| format!("Invalid packet encoding: {}", e))?;
+    let payload = match codec.as_str() {
+        "opus" => audio_jitter::PacketPayload::Opus(bytes),
+        "pcm" => {
+            if bytes.len() % 4 != 0 {
+                return Err("Frame length is not a multiple of 4 bytes".to_string());
+            }
+            audio_jitter::PacketPayload::Pcm(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            )
+        }
+        _ => return Err(format!("Unsupported codec: {}", codec)),
+    };
+    audio_jitter::push_packet(&peer_id, seq, capture_ts, payload)
+}
 
-    manager.set_session(&account_id)
-        .map_err(|e| format!("Failed to switch account: {}", e))
+#[tauri::command]
+fn set_jitter_buffer_config(config: audio_jitter::JitterConfig) -> Result<(), String> {
+    audio_jitter::set_config(config)
 }
 
 #[tauri::command]
-fn logout_account() -> Result<(), String> {
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
-    manager.clear_session()
-        .map_err(|e| format!("Failed to logout: {}", e))
+fn get_jitter_buffer_config() -> audio_jitter::JitterConfig {
+    audio_jitter::config()
 }
 
+/// Per-peer jitter buffer health (delay, jitter, loss/concealment counters).
 #[tauri::command]
-fn get_current_account_id() -> Result<Option<String>, String> {
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
-    manager.get_current_account_id()
-        .map_err(|e| format!("Failed to get current account: {}", e))
+fn get_jitter_buffer_stats() -> HashMap<String, audio_jitter::JitterStats> {
+    audio_jitter::stats()
 }
 
+/// WebRTC inbound-audio stats for a peer the webview plays, so `get_connection_stats` can
+/// include peers that bypass the jitter buffer.
 #[tauri::command]
-fn delete_account(account_id: String) -> Result<(), String> {
-    // NO GUARD: Can delete any account (user must be on account select screen)
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to create account manager: {}", e))?;
-    manager.delete_account(&account_id)
-        .map_err(|e| format!("Failed to delete account: {}", e))
+fn report_peer_connection_stats(peer_id: String, stats: connection_stats::ReportedPeerStats) {
+    connection_stats::report(&peer_id, stats);
 }
 
+/// Beacon RTT and reconnects, per-peer voice loss/jitter and audio drops in one report.
 #[tauri::command]
-fn list_friends() -> Result<Vec<String>, String> {
-    let account_id = require_session()?;
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    manager.load_friends(&account_id)
-        .map_err(|e| format!("Failed to load friends: {}", e))
+fn get_connection_stats() -> connection_stats::ConnectionStats {
+    connection_stats::collect()
 }
 
+/// Queue decoded remote audio for native playback. `frame_b64` is mono 48 kHz f32 LE,
+/// the same encoding as the `cordia:audio-frame` event, or interleaved L/R with `stereo`
+/// (music bots, soundboard).
 #[tauri::command]
-fn add_friend(user_id: String) -> Result<(), String> {
-    let account_id = require_session()?;
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    let mut friends = manager.load_friends(&account_id)
-        .map_err(|e| format!("Failed to load friends: {}", e))?;
-    if !friends.contains(&user_id) {
-        friends.push(user_id);
-        manager.save_friends(&account_id, &friends)
-            .map_err(|e| format!("Failed to save friends: {}", e))?;
+fn push_remote_audio_frame(peer_id: String, frame_b64: String, stereo: Option<bool>) -> Result<(), String> {
+    let bytes = base64::decode(&frame_b64).map_err(|e| format!("Invalid frame encoding: {}", e))?;
+    if bytes.len() % 4 != 0 {
+        return Err("Frame length is not a multiple of 4 bytes".to_string());
+    }
+    let samples: Vec<f32> = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    if stereo.unwrap_or(false) {
+        audio_output::push_peer_pcm_stereo(&peer_id, &samples)
+    } else {
+        audio_output::push_peer_pcm(&peer_id, &samples)
     }
-    Ok(())
 }
 
 #[tauri::command]
-fn load_known_profiles() -> Result<serde_json::Value, String> {
-    let account_id = require_session()?;
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    let map = manager.load_known_profiles(&account_id)
-        .map_err(|e| format!("Failed to load known profiles: {}", e))?;
-    serde_json::to_value(map).map_err(|e| format!("Failed to serialize known profiles: {}", e))
+fn remove_remote_audio_peer(peer_id: String) -> Result<(), String> {
+    audio_jitter::remove_peer(&peer_id);
+    audio_output::remove_peer(&peer_id);
+    Ok(())
 }
 
 #[tauri::command]
-fn save_known_profiles(profiles: std::collections::HashMap<String, KnownProfile>) -> Result<(), String> {
-    let account_id = require_session()?;
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    manager.save_known_profiles(&account_id, &profiles)
-        .map_err(|e| format!("Failed to save known profiles: {}", e))
+fn set_playback_volume(volume: f32) -> Result<(), String> {
+    audio_output::set_output_volume(volume);
+    Ok(())
 }
 
+/// Local volume for one remote user (0.0–2.0); doesn't affect what anyone else hears.
 #[tauri::command]
-fn remove_friend(user_id: String) -> Result<(), String> {
-    let account_id = require_session()?;
-    let manager = AccountManager::new()
-        .map_err(|e| format!("Failed to access account manager: {}", e))?;
-    let mut friends = manager.load_friends(&account_id)
-        .map_err(|e| format!("Failed to load friends: {}", e))?;
-    friends.retain(|id| id != &user_id);
-    manager.save_friends(&account_id, &friends)
-        .map_err(|e| format!("Failed to save friends: {}", e))
+fn set_user_volume(peer_id: String, gain: f32) -> Result<(), String> {
+    audio_output::set_user_volume(&peer_id, gain)
 }
 
-/// Returns headers for friend API auth: request signed with identity Ed25519 key.
-/// Envelope: method + "\n" + path + "\n" + timestamp + "\n" + sha256(body).hex().
-/// No shared secret; server verifies signature with public key (mailbox-style).
 #[tauri::command]
-fn get_friend_auth_headers(
-    method: String,
-    path: String,
-    body: Option<String>,
-) -> Result<std::collections::HashMap<String, String>, String> {
-    use ed25519_dalek::Signer;
-
-    let _ = require_session()?;
-    let manager = IdentityManager::new()
-        .map_err(|e| format!("Identity manager: {}", e))?;
-    let identity = manager.load_identity()
-        .map_err(|e| format!("Load identity: {}", e))?;
-    let private_key_hex = identity.private_key
-        .as_ref()
-        .ok_or("Identity has no private key. If you created this account before a recent update, create a new account to use friend requests and friend codes.")?;
-    let private_key_bytes = hex::decode(private_key_hex)
-        .map_err(|e| format!("Invalid private key hex: {}", e))?;
-    let signing_key = ed25519_dalek::SigningKey::from_bytes(
-        private_key_bytes.as_slice().try_into()
-            .map_err(|_| "Invalid private key length")?,
-    );
+fn set_user_muted(peer_id: String, muted: bool) -> Result<(), String> {
+    audio_output::set_user_muted(&peer_id, muted)
+}
 
-    let timestamp = chrono::Utc::now().timestamp();
-    let body_hash = match body.as_deref().unwrap_or("") {
-        "" => String::new(),
-        b => {
-            use sha2::Digest;
-            let mut hasher = sha2::Sha256::new();
-            hasher.update(b.as_bytes());
-            hex::encode(hasher.finalize())
-        }
-    };
-    let envelope = format!("{}\n{}\n{}\n{}",
-        method.to_uppercase(),
-        path.trim(),
-        timestamp,
-        body_hash,
-    );
-    let signature = signing_key.sign(envelope.as_bytes());
+/// Stereo position for one remote user (-1.0 left … 1.0 right).
+#[tauri::command]
+fn set_user_pan(peer_id: String, pan: f32) -> Result<(), String> {
+    audio_output::set_user_pan(&peer_id, pan)
+}
 
-    let mut headers = std::collections::HashMap::new();
-    headers.insert("X-User-Id".to_string(), identity.user_id);
-    headers.insert("X-Timestamp".to_string(), timestamp.to_string());
-    headers.insert("X-Public-Key".to_string(), identity.public_key.clone());
-    headers.insert("X-Signature".to_string(), base64::encode(signature.to_bytes()));
-    Ok(headers)
+/// Stereo width for one remote voice (0 centred … 1 widest); omit to follow the mixer's
+/// voice width.
+#[tauri::command]
+fn set_user_width(peer_id: String, width: Option<f32>) -> Result<(), String> {
+    audio_output::set_user_width(&peer_id, width)
 }
 
-// === Native Audio Commands ===
+/// Mixer-wide settings, e.g. how wide mono voices are upmixed.
+#[tauri::command]
+fn set_mixer_config(config: audio_output::MixerConfig) -> Result<(), String> {
+    audio_output::set_mixer_config(config);
+    Ok(())
+}
 
 #[tauri::command]
-fn enumerate_audio_devices_native() -> Result<Vec<AudioDevice>, String> {
-    enumerate_devices()
+fn get_mixer_config() -> audio_output::MixerConfig {
+    audio_output::mixer_config()
 }
 
-/// Bounded capacity for processed frames: ~30 ms. If JS doesn't drain in time, we drop (never block Rust).
-const PROCESSED_FRAME_QUEUE_CAP: usize = 3;
+/// Binaural positioning for remote users (headphones recommended). Replaces pan while on.
+#[tauri::command]
+fn set_spatial_audio_enabled(enabled: bool) -> Result<(), String> {
+    audio_output::set_spatial_enabled(enabled);
+    Ok(())
+}
 
+/// Virtual position for one remote user: azimuth in degrees (0 ahead, +90 right), distance in metres.
 #[tauri::command]
-fn start_audio_capture(
-    app: tauri::AppHandle,
-    device_id: Option<String>,
-) -> Result<(), String> {
-    // Bounded channel: processing thread uses try_send; when full, frames are dropped (audio loss > latency).
-    let (processed_tx, processed_rx) = std::sync::mpsc::sync_channel(PROCESSED_FRAME_QUEUE_CAP);
-    let (level_tx, level_rx) = std::sync::mpsc::channel();
+fn set_user_position(peer_id: String, azimuth: f32, distance: Option<f32>) -> Result<(), String> {
+    audio_output::set_user_position(&peer_id, azimuth, distance.unwrap_or(1.0))
+}
 
-    start_capture(device_id, processed_tx, level_tx)?;
+/// Spread everyone currently playing across the front arc in join order (default ±60°).
+#[tauri::command]
+fn spread_users_by_join_order(spread_deg: Option<f32>) -> Result<(), String> {
+    audio_output::spread_users_by_join_order(spread_deg.unwrap_or(60.0))
+}
 
-    let app_clone = app.clone();
-    std::thread::spawn(move || {
-        // Level updates: event-driven, no polling
-        let app_level = app_clone.clone();
-        std::thread::spawn(move || {
-            loop {
-                match level_rx.recv() {
-                    Ok(level) => {
-                        let _ = app_level.emit_all("cordia:audio-level", level);
-                    }
-                    Err(_) => break,
-                }
-            }
-        });
+#[tauri::command]
+fn get_user_mix_settings() -> HashMap<String, audio_output::UserMixSettings> {
+    audio_output::user_mix_settings()
+}
 
-        // Emitter: drain opportunistically, batch 2–3 frames to reduce IPC jitter. Never block Rust.
-        const BATCH_SIZE: usize = 2;
-        const RECV_TIMEOUT_MS: u64 = 25; // ~2.5 frames at 10 ms/frame; if nothing, emit what we have
-        let timeout = std::time::Duration::from_millis(RECV_TIMEOUT_MS);
-        let mut batch: Vec<f32> = Vec::with_capacity(480 * BATCH_SIZE);
-        loop {
-            batch.clear();
-            let mut got_any = false;
-            for _ in 0..BATCH_SIZE {
-                match processed_rx.recv_timeout(timeout) {
-                    Ok(frame) => {
-                        batch.extend_from_slice(&frame);
-                        got_any = true;
-                    }
-                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => break,
-                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
-                }
-            }
-            if got_any && !batch.is_empty() {
-                let frame_bytes: Vec<u8> = batch
-                    .iter()
-                    .flat_map(|f| f.to_le_bytes().to_vec())
-                    .collect();
-                let frame_b64 = base64::encode(&frame_bytes);
-                let _ = app_clone.emit_all("cordia:audio-frame", frame_b64);
-            }
-        }
-    });
+/// Hear your own processed mic. `device_id` omitted = mix into the playback device.
+#[tauri::command]
+fn start_sidetone(device_id: Option<String>, volume: f32) -> Result<(), String> {
+    audio_output::start_sidetone(device_id, volume)
+}
 
+#[tauri::command]
+fn stop_sidetone() -> Result<(), String> {
+    audio_output::stop_sidetone();
     Ok(())
 }
 
 #[tauri::command]
-fn stop_audio_capture() -> Result<(), String> {
-    stop_capture();
+fn set_sidetone_volume(volume: f32) -> Result<(), String> {
+    audio_output::set_sidetone_volume(volume);
     Ok(())
 }
 
+/// Change Opus bitrate/complexity/FEC; applies to the running encoder from its next frame.
 #[tauri::command]
-fn set_audio_gain(gain: f32) -> Result<(), String> {
-    let dsp = get_dsp();
-    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
-    dsp_guard.set_gain(gain);
-    Ok(())
+fn set_opus_settings(settings: audio_opus::OpusSettings) -> Result<(), String> {
+    audio_opus::set_opus_settings(settings)
 }
 
 #[tauri::command]
-fn set_audio_threshold(threshold: f32) -> Result<(), String> {
-    let dsp = get_dsp();
-    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
-    dsp_guard.set_threshold(threshold);
-    Ok(())
+fn get_opus_settings() -> audio_opus::OpusSettings {
+    audio_opus::opus_settings()
 }
 
+/// Configured, capped and measured voice bitrate.
 #[tauri::command]
-fn set_audio_input_mode(mode: String) -> Result<(), String> {
-    let dsp = get_dsp();
-    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
-    let input_mode = match mode.as_str() {
-        "voice_activity" => InputMode::VoiceActivity,
-        "push_to_talk" => InputMode::PushToTalk,
-        _ => return Err(format!("Invalid input mode: {}", mode)),
-    };
-    dsp_guard.set_input_mode(input_mode);
-    Ok(())
+fn get_voice_bitrate() -> audio_opus::BitrateReport {
+    audio_opus::bitrate_report()
 }
 
+/// Turn other applications down while remote speech is playing (Windows/Linux).
+/// `settings` replaces the duck amount / release time if given.
 #[tauri::command]
-fn set_ptt_key_pressed(pressed: bool) -> Result<(), String> {
-    let dsp = get_dsp();
-    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
-    dsp_guard.set_ptt_pressed(pressed);
-    Ok(())
+fn start_system_ducking(settings: Option<audio_ducking::DuckingSettings>) -> Result<(), String> {
+    if let Some(settings) = settings {
+        audio_ducking::set_ducking_settings(settings)?;
+    }
+    audio_ducking::start_ducking()
 }
 
+/// Stop ducking and restore other applications' volume.
 #[tauri::command]
-fn set_transmission_muted(muted: bool) -> Result<(), String> {
-    let dsp = get_dsp();
-    let mut dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
-    dsp_guard.set_transmission_muted(muted);
+fn stop_system_ducking() -> Result<(), String> {
+    audio_ducking::stop_ducking();
     Ok(())
 }
 
 #[tauri::command]
-fn get_audio_level() -> Result<f32, String> {
-    let dsp = get_dsp();
-    let dsp_guard = dsp.lock().map_err(|_| "Failed to lock DSP".to_string())?;
-    Ok(dsp_guard.get_level())
+fn set_ducking_settings(settings: audio_ducking::DuckingSettings) -> Result<(), String> {
+    audio_ducking::set_ducking_settings(settings)
+}
+
+#[tauri::command]
+fn get_ducking_settings() -> audio_ducking::DuckingSettings {
+    audio_ducking::ducking_settings()
+}
+
+#[tauri::command]
+fn is_system_ducking() -> bool {
+    audio_ducking::is_ducking()
 }
 
 /// Drop/underrun stats for dev overlay or debug. Resets on each start_audio_capture.
@@ -3136,13 +4821,35 @@ fn get_audio_drop_stats_command() -> AudioDropStats {
 }
 
 fn main() {
+    // Hands a clicked cordia:// link to the running instance and exits if there is one.
+    tauri_plugin_deep_link::prepare("com.cordia.app");
+    audio_denoise::register_builtin_denoisers();
+    proxy::load_proxy_settings();
+    beacon_tls::load_beacon_tls();
+    outbound_queue::load_outbound_queue();
+    settings::load_settings();
+    audio_opus::set_bitrate_cap(settings::current().bandwidth.max_voice_kbps);
+
     tauri::Builder::default()
+        .setup(|app| {
+            invites::register_deep_links(app.handle());
+            global_hotkeys::init(&app.handle());
+            idle::start(app.handle());
+            network_monitor::start(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Identity commands
             has_identity,
             check_account_has_identity,
             create_identity,
             load_identity,
+            sign_with_identity,
+            verify_identity_signature,
+            get_identity_fingerprint,
+            get_secret,
+            set_secret,
+            delete_secret,
             export_identity,
             export_identity_for_account,
             export_full_identity,
@@ -3167,17 +4874,100 @@ fn main() {
             // Audio settings commands
             load_audio_settings,
             save_audio_settings,
+            list_dsp_presets,
+            save_dsp_preset,
+            apply_dsp_preset,
+            delete_dsp_preset,
             // Native audio commands
             enumerate_audio_devices_native,
+            list_audio_hosts,
+            enumerate_audio_devices_for_host,
+            set_audio_host,
+            probe_device,
+            set_opus_settings,
+            get_opus_settings,
+            get_voice_bitrate,
+            start_system_ducking,
+            stop_system_ducking,
+            set_ducking_settings,
+            get_ducking_settings,
+            is_system_ducking,
+            start_spectrum_analyzer,
+            stop_spectrum_analyzer,
+            run_audio_selftest,
+            process_audio_file,
+            calibrate_input,
             start_audio_capture,
             stop_audio_capture,
+            pause_audio_capture,
+            resume_audio_capture,
             set_audio_gain,
             set_audio_threshold,
             set_audio_input_mode,
+            set_noise_suppression,
+            list_denoisers,
+            set_echo_cancellation,
+            set_clarity,
+            set_voice_effect,
+            set_auto_gain,
+            set_high_pass_filter,
+            set_compressor,
+            set_eq,
+            set_dsp_bypass,
+            set_dsp_chain,
+            get_dsp_chain,
             set_ptt_key_pressed,
+            get_keybind_status,
+            get_idle_state,
+            acquire_sleep_inhibitor,
+            release_sleep_inhibitor,
+            set_ptt_release_delay,
             set_transmission_muted,
             get_audio_level,
+            get_audio_meter,
+            reset_noise_floor,
+            set_meter_tuning,
+            get_transmission_state,
+            set_meter_smoothing,
             get_audio_drop_stats_command,
+            start_loopback_capture,
+            stop_loopback_capture,
+            enumerate_audio_applications,
+            start_app_capture,
+            stop_app_capture,
+            start_recording,
+            stop_recording,
+            is_recording,
+            start_multitrack_recording,
+            stop_multitrack_recording,
+            is_multitrack_recording,
+            set_recording_consent,
+            start_audio_device_watcher,
+            stop_audio_device_watcher,
+            start_audio_playback,
+            stop_audio_playback,
+            push_remote_audio_frame,
+            remove_remote_audio_peer,
+            push_remote_audio_packet,
+            set_jitter_buffer_config,
+            get_jitter_buffer_config,
+            get_jitter_buffer_stats,
+            report_peer_connection_stats,
+            get_connection_stats,
+            set_playback_volume,
+            set_user_volume,
+            set_user_muted,
+            set_user_pan,
+            set_user_width,
+            set_mixer_config,
+            get_mixer_config,
+            get_user_mix_settings,
+            set_spatial_audio_enabled,
+            set_user_position,
+            spread_users_by_join_order,
+            start_sidetone,
+            stop_sidetone,
+            set_sidetone_volume,
             // House commands
             create_server,
             list_servers,
@@ -3186,6 +4976,10 @@ fn main() {
             encrypt_ephemeral_chat_message_by_signing_pubkey,
             decrypt_ephemeral_chat_message,
             decrypt_ephemeral_chat_message_by_signing_pubkey,
+            history_append,
+            history_fetch,
+            history_search,
+            history_prune,
             get_file_metadata,
             get_audio_stream_info,
             ensure_music_cover_thumbnail,
@@ -3218,12 +5012,67 @@ fn main() {
             resolve_invite_code,
             publish_server_hint_opaque,
             publish_server_hint_member_left,
+            apply_server_member_leaves,
             fetch_and_import_server_hint_opaque,
             create_temporary_invite,
             revoke_active_invite,
+            create_invite_link,
+            parse_invite_link,
+            render_invite_qr,
+            take_pending_invite_link,
             redeem_temporary_invite,
             // Beacon commands
             check_beacon,
+            measure_beacon_latency,
+            get_beacon_info,
+            probe_beacon,
+            run_network_diagnostics,
+            get_settings,
+            set_settings,
+            start_beacon_monitor,
+            stop_beacon_monitor,
+            get_beacon_monitor_status,
+            start_beacon_discovery,
+            stop_beacon_discovery,
+            get_discovered_beacons,
+            advertise_local_beacon,
+            stop_advertising_beacon,
+            signaling_connect,
+            signaling_disconnect,
+            signaling_send,
+            signaling_send_reliable,
+            get_outbound_queue,
+            clear_outbound_queue,
+            signaling_register,
+            signaling_answer_challenge,
+            signaling_unregister,
+            get_signaling_status,
+            get_proxy_settings,
+            set_proxy_settings,
+            get_beacon_tls_options,
+            set_beacon_tls_options,
+            get_beacon_urls,
+            get_ice_servers,
+            native_rtc_available,
+            native_rtc_create_offer,
+            native_rtc_accept_offer,
+            native_rtc_apply_answer,
+            native_rtc_close,
+            native_rtc_peers,
+            send_file_transfer,
+            accept_file_transfer,
+            pause_file_transfer,
+            resume_file_transfer,
+            cancel_file_transfer,
+            list_file_transfers,
+            set_fallback_beacon_urls,
+            start_beacon_failover,
+            stop_beacon_failover,
+            list_beacon_profiles,
+            save_beacon_profile,
+            delete_beacon_profile,
+            switch_beacon_profile,
+            get_beacon_failover_status,
             get_default_beacon,
             get_beacon_url,
             set_beacon_url,
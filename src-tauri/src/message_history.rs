@@ -0,0 +1,351 @@
+//! Local chat history in SQLite (`history.sqlite` in the account directory).
+//!
+//! Message bodies are encrypted with XChaCha20-Poly1305 under a key kept in the OS keychain
+//! (`secrets::HISTORY_KEY`), or derived from the account's identity key where there is no
+//! keychain, so the file is useless on its own. Full-text search
+//! uses a blind index: each word of a body is stored as an HMAC under a second derived key,
+//! and a query matches messages containing all of its words. Ids, channels, senders and
+//! timestamps are stored in the clear for paging.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Mutex;
+
+use crate::account_manager::AccountManager;
+use crate::identity::IdentityManager;
+use crate::secrets;
+
+const MAX_PAGE: usize = 200;
+/// Index terms are truncated HMACs; 16 bytes keeps the index small without collisions mattering.
+const TERM_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryMessage {
+    pub id: String,
+    pub server_id: String,
+    pub channel_id: String,
+    pub sender_id: String,
+    pub body: String,
+    /// Unix time in milliseconds.
+    pub sent_at: i64,
+}
+
+struct History {
+    account_id: String,
+    conn: Connection,
+    cipher: XChaCha20Poly1305,
+    term_key: [u8; 32],
+}
+
+static HISTORY: Mutex<Option<History>> = Mutex::new(None);
+
+const SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS messages (
+        id TEXT PRIMARY KEY,
+        server_id TEXT NOT NULL,
+        channel_id TEXT NOT NULL,
+        sender_id TEXT NOT NULL,
+        sent_at INTEGER NOT NULL,
+        body BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS messages_channel ON messages (server_id, channel_id, sent_at);
+    CREATE TABLE IF NOT EXISTS terms (
+        term BLOB NOT NULL,
+        message_id TEXT NOT NULL REFERENCES messages (id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS terms_term ON terms (term);
+    CREATE INDEX IF NOT EXISTS terms_message ON terms (message_id);
+";
+
+impl History {
+    fn open(account_id: &str, conn: Connection, identity_key: &[u8]) -> Result<Self, String> {
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to initialize message history: {}", e))?;
+        Ok(Self {
+            account_id: account_id.to_string(),
+            conn,
+            cipher: XChaCha20Poly1305::new((&derive_key(identity_key, b"cordia-history-body-v1")).into()),
+            term_key: derive_key(identity_key, b"cordia-history-terms-v1"),
+        })
+    }
+
+    fn encrypt(&self, body: &str) -> Result<Vec<u8>, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.cipher
+                .encrypt(&nonce, body.as_bytes())
+                .map_err(|_| "Failed to encrypt message".to_string())?,
+        );
+        Ok(sealed)
+    }
+
+    fn decrypt(&self, sealed: &[u8]) -> Result<String, String> {
+        if sealed.len() < 24 {
+            return Err("Corrupt history entry".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(24);
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt history entry".to_string())?;
+        String::from_utf8(plaintext).map_err(|_| "Corrupt history entry".to_string())
+    }
+
+    fn term(&self, word: &str) -> Vec<u8> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.term_key).expect("HMAC takes any key length");
+        mac.update(word.as_bytes());
+        mac.finalize().into_bytes()[..TERM_LEN].to_vec()
+    }
+
+    /// Store messages, skipping ids already present. Returns how many were new.
+    fn append(&mut self, messages: &[HistoryMessage]) -> Result<usize, String> {
+        let mut sealed = Vec::with_capacity(messages.len());
+        for message in messages {
+            let terms: Vec<Vec<u8>> = words(&message.body).iter().map(|w| self.term(w)).collect();
+            sealed.push((message, self.encrypt(&message.body)?, terms));
+        }
+
+        let tx = self.conn.transaction().map_err(|e| format!("Failed to write history: {}", e))?;
+        let mut added = 0;
+        for (message, body, terms) in sealed {
+            let inserted = tx
+                .execute(
+                    "INSERT OR IGNORE INTO messages (id, server_id, channel_id, sender_id, sent_at, body)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![message.id, message.server_id, message.channel_id, message.sender_id, message.sent_at, body],
+                )
+                .map_err(|e| format!("Failed to write history: {}", e))?;
+            if inserted == 0 {
+                continue;
+            }
+            added += 1;
+            for term in terms {
+                tx.execute("INSERT INTO terms (term, message_id) VALUES (?1, ?2)", params![term, message.id])
+                    .map_err(|e| format!("Failed to index history: {}", e))?;
+            }
+        }
+        tx.commit().map_err(|e| format!("Failed to write history: {}", e))?;
+        Ok(added)
+    }
+
+    fn rows(&self, sql: &str, params: Vec<rusqlite::types::Value>) -> Result<Vec<HistoryMessage>, String> {
+        let mut stmt = self.conn.prepare(sql).map_err(|e| format!("Failed to read history: {}", e))?;
+        let rows = stmt
+            .query_map(params_from_iter(params), |row| {
+                Ok((
+                    HistoryMessage {
+                        id: row.get(0)?,
+                        server_id: row.get(1)?,
+                        channel_id: row.get(2)?,
+                        sender_id: row.get(3)?,
+                        sent_at: row.get(4)?,
+                        body: String::new(),
+                    },
+                    row.get::<_, Vec<u8>>(5)?,
+                ))
+            })
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let mut messages = Vec::new();
+        for row in rows {
+            let (message, body) = row.map_err(|e| format!("Failed to read history: {}", e))?;
+            messages.push(HistoryMessage { body: self.decrypt(&body)?, ..message });
+        }
+        Ok(messages)
+    }
+
+    /// Up to `limit` messages of a channel sent before `before` (latest first page when
+    /// `None`), oldest first.
+    fn fetch(&self, server_id: &str, channel_id: &str, before: Option<i64>, limit: usize) -> Result<Vec<HistoryMessage>, String> {
+        let mut messages = self.rows(
+            "SELECT id, server_id, channel_id, sender_id, sent_at, body FROM messages
+             WHERE server_id = ?1 AND channel_id = ?2 AND sent_at < ?3
+             ORDER BY sent_at DESC, id DESC LIMIT ?4",
+            vec![
+                server_id.to_string().into(),
+                channel_id.to_string().into(),
+                before.unwrap_or(i64::MAX).into(),
+                (limit.min(MAX_PAGE) as i64).into(),
+            ],
+        )?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Messages containing every word of `query`, newest first.
+    fn search(&self, query: &str, server_id: Option<&str>, limit: usize) -> Result<Vec<HistoryMessage>, String> {
+        let terms: Vec<Vec<u8>> = words(query).iter().map(|w| self.term(w)).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; terms.len()].join(", ");
+        let mut params: Vec<rusqlite::types::Value> = terms.iter().cloned().map(Into::into).collect();
+        let server_filter = match server_id {
+            Some(server_id) => {
+                params.push(server_id.to_string().into());
+                "AND m.server_id = ?"
+            }
+            None => "",
+        };
+        params.push((terms.len() as i64).into());
+        params.push((limit.min(MAX_PAGE) as i64).into());
+        self.rows(
+            &format!(
+                "SELECT m.id, m.server_id, m.channel_id, m.sender_id, m.sent_at, m.body
+                 FROM messages m JOIN terms t ON t.message_id = m.id
+                 WHERE t.term IN ({placeholders}) {server_filter}
+                 GROUP BY m.id HAVING COUNT(DISTINCT t.term) = ?
+                 ORDER BY m.sent_at DESC LIMIT ?"
+            ),
+            params,
+        )
+    }
+
+    /// Delete messages sent before `before`, optionally only for one server.
+    fn prune(&self, before: i64, server_id: Option<&str>) -> Result<usize, String> {
+        let deleted = match server_id {
+            Some(server_id) => self.conn.execute(
+                "DELETE FROM messages WHERE sent_at < ?1 AND server_id = ?2",
+                params![before, server_id],
+            ),
+            None => self.conn.execute("DELETE FROM messages WHERE sent_at < ?1", params![before]),
+        };
+        deleted.map_err(|e| format!("Failed to prune history: {}", e))
+    }
+}
+
+fn derive_key(identity_key: &[u8], label: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(identity_key).expect("HMAC takes any key length");
+    mac.update(label);
+    mac.finalize().into_bytes().into()
+}
+
+/// Distinct lowercase words of `text`, for indexing and queries.
+fn words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// Run `f` against the current account's history, opening it on first use or after an
+/// account switch.
+fn with_history<T>(f: impl FnOnce(&mut History) -> Result<T, String>) -> Result<T, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    let account_id = account_manager
+        .get_current_account_id()
+        .map_err(|e| format!("Failed to get current account: {}", e))?
+        .ok_or_else(|| "No active session".to_string())?;
+
+    let mut history = HISTORY.lock().map_err(|_| "Failed to lock message history".to_string())?;
+    if history.as_ref().is_none_or(|h| h.account_id != account_id) {
+        let path = account_manager.get_account_dir(&account_id).join("history.sqlite");
+        let key = history_key(&account_id, path.exists())?;
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open message history: {}", e))?;
+        *history = Some(History::open(&account_id, conn, &key)?);
+    }
+    f(history.as_mut().expect("history opened above"))
+}
+
+/// The database key from the keychain. A new database gets a random key; one created
+/// without a keychain was keyed from the identity, and that key is kept (and moved to the
+/// keychain once there is one).
+fn history_key(account_id: &str, db_exists: bool) -> Result<Vec<u8>, String> {
+    let identity_key = || -> Result<Vec<u8>, String> {
+        IdentityManager::for_account(account_id)
+            .and_then(|manager| manager.signing_key())
+            .map(|key| key.to_bytes().to_vec())
+            .map_err(|e| format!("Failed to load identity key: {}", e))
+    };
+    match secrets::get(account_id, secrets::HISTORY_KEY) {
+        Ok(Some(key)) => hex::decode(key).map_err(|e| format!("Invalid history key in keychain: {}", e)),
+        Ok(None) => {
+            let key = if db_exists { identity_key()? } else { rand::random::<[u8; 32]>().to_vec() };
+            match secrets::set(account_id, secrets::HISTORY_KEY, &hex::encode(&key)) {
+                Ok(()) => Ok(key),
+                // Never use a random key that couldn't be saved: the database would be
+                // unreadable after a restart.
+                Err(e) => {
+                    eprintln!("[History] {}; using the identity-derived key", e);
+                    identity_key()
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("[History] {}; using the identity-derived key", e);
+            identity_key()
+        }
+    }
+}
+
+pub fn append(messages: &[HistoryMessage]) -> Result<usize, String> {
+    with_history(|h| h.append(messages))
+}
+
+pub fn fetch(server_id: &str, channel_id: &str, before: Option<i64>, limit: usize) -> Result<Vec<HistoryMessage>, String> {
+    with_history(|h| h.fetch(server_id, channel_id, before, limit))
+}
+
+pub fn search(query: &str, server_id: Option<&str>, limit: usize) -> Result<Vec<HistoryMessage>, String> {
+    with_history(|h| h.search(query, server_id, limit))
+}
+
+pub fn prune(before: i64, server_id: Option<&str>) -> Result<usize, String> {
+    with_history(|h| h.prune(before, server_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, channel_id: &str, body: &str, sent_at: i64) -> HistoryMessage {
+        HistoryMessage {
+            id: id.to_string(),
+            server_id: "server".to_string(),
+            channel_id: channel_id.to_string(),
+            sender_id: "alice".to_string(),
+            body: body.to_string(),
+            sent_at,
+        }
+    }
+
+    #[test]
+    fn appends_pages_searches_and_prunes() {
+        let conn = Connection::open_in_memory().unwrap();
+        let mut history = History::open("account", conn, b"identity key").unwrap();
+        let messages = vec![
+            message("1", "general", "Anyone up for a match tonight?", 1_000),
+            message("2", "general", "Tonight works, match at nine", 2_000),
+            message("3", "general", "See you then", 3_000),
+            message("4", "random", "unrelated MATCH", 4_000),
+        ];
+        assert_eq!(history.append(&messages).unwrap(), 4);
+        assert_eq!(history.append(&messages[..1]).unwrap(), 0);
+
+        let body: Vec<u8> = history.conn.query_row("SELECT body FROM messages WHERE id = '1'", [], |r| r.get(0)).unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("match"));
+
+        assert_eq!(history.fetch("server", "general", None, 2).unwrap(), messages[1..3].to_vec());
+        assert_eq!(history.fetch("server", "general", Some(2_000), 10).unwrap(), messages[..1].to_vec());
+
+        let ids = |found: Vec<HistoryMessage>| found.into_iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids(history.search("match tonight", None, 10).unwrap()), ["2", "1"]);
+        assert_eq!(ids(history.search("Match", Some("server"), 10).unwrap()), ["4", "2", "1"]);
+        assert!(history.search("nothing here", None, 10).unwrap().is_empty());
+
+        assert_eq!(history.prune(2_500, None).unwrap(), 2);
+        assert_eq!(ids(history.search("match", None, 10).unwrap()), ["4"]);
+        let terms: i64 = history.conn.query_row("SELECT COUNT(*) FROM terms", [], |r| r.get(0)).unwrap();
+        assert_eq!(terms, 5);
+    }
+}
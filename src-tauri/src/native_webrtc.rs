@@ -0,0 +1,351 @@
+//! Native voice transport: peer connections, DTLS-SRTP and Opus packetization in Rust
+//! (webrtc-rs), built with the `native-webrtc` feature.
+//!
+//! Every peer shares one local Opus track, fed straight from the capture pipeline's encoder
+//! (`write_opus`), so mic audio never crosses into the webview. Remote RTP goes into the
+//! jitter buffer (`audio_jitter`) like any other remote packet, which gives native peers the
+//! same reordering, FEC/PLC concealment and adaptive delay as the rest of the output path.
+//!
+//! Signaling stays with the caller: offers and answers are returned with all ICE candidates
+//! gathered (no trickle), so they can travel over the existing beacon signaling as one
+//! message. Connection state changes are emitted as `cordia:native-rtc-state`.
+//!
+//! Each connection also carries a `files` data channel for `file_transfer` frames.
+
+use bytes::Bytes;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS};
+use webrtc::api::APIBuilder;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::data_channel_state::RTCDataChannelState;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::interceptor::registry::Registry;
+use webrtc::media::Sample;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+use webrtc::track::track_local::TrackLocal;
+use webrtc::track::track_remote::TrackRemote;
+
+use crate::audio_jitter::{self, PacketPayload};
+use crate::ice_servers::IceServer;
+
+const OPUS_CLOCK_RATE: u64 = 48_000;
+const FILES_CHANNEL: &str = "files";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NativePeerState {
+    pub peer_id: String,
+    /// `new`, `connecting`, `connected`, `disconnected`, `failed` or `closed`.
+    pub state: String,
+}
+
+static PEERS: Mutex<Option<HashMap<String, Arc<RTCPeerConnection>>>> = Mutex::new(None);
+static FILE_CHANNELS: Mutex<Option<HashMap<String, Arc<RTCDataChannel>>>> = Mutex::new(None);
+/// Shared by all peers; `None` while there are none, so the encoder thread skips the write.
+static LOCAL_TRACK: Mutex<Option<Arc<TrackLocalStaticSample>>> = Mutex::new(None);
+
+fn local_track() -> Arc<TrackLocalStaticSample> {
+    let mut track = LOCAL_TRACK.lock().unwrap_or_else(|e| e.into_inner());
+    track
+        .get_or_insert_with(|| {
+            Arc::new(TrackLocalStaticSample::new(
+                RTCRtpCodecCapability {
+                    mime_type: MIME_TYPE_OPUS.to_owned(),
+                    ..Default::default()
+                },
+                "voice".to_owned(),
+                "cordia".to_owned(),
+            ))
+        })
+        .clone()
+}
+
+/// Send one encoded mic packet to every native peer. Called on the encoder thread.
+pub fn write_opus(packet: &[u8], samples: usize) {
+    let track = match LOCAL_TRACK.lock().ok().and_then(|t| t.clone()) {
+        Some(track) => track,
+        None => return,
+    };
+    let sample = Sample {
+        data: Bytes::copy_from_slice(packet),
+        duration: Duration::from_micros(samples as u64 * 1_000_000 / OPUS_CLOCK_RATE),
+        ..Default::default()
+    };
+    if let Err(e) = tauri::async_runtime::block_on(track.write_sample(&sample)) {
+        eprintln!("[NativeRTC] Failed to send audio: {}", e);
+    }
+}
+
+async fn new_peer(app: &AppHandle, peer_id: &str, ice_servers: Vec<IceServer>) -> Result<Arc<RTCPeerConnection>, String> {
+    let mut media = MediaEngine::default();
+    media
+        .register_default_codecs()
+        .map_err(|e| format!("Failed to register codecs: {}", e))?;
+    // NACK and RTCP sender/receiver reports.
+    let registry = register_default_interceptors(Registry::new(), &mut media)
+        .map_err(|e| format!("Failed to register interceptors: {}", e))?;
+    let api = APIBuilder::new()
+        .with_media_engine(media)
+        .with_interceptor_registry(registry)
+        .build();
+
+    let config = RTCConfiguration {
+        ice_servers: ice_servers
+            .into_iter()
+            .map(|s| RTCIceServer {
+                urls: s.urls,
+                username: s.username.unwrap_or_default(),
+                credential: s.credential.unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let pc = Arc::new(
+        api.new_peer_connection(config)
+            .await
+            .map_err(|e| format!("Failed to create peer connection: {}", e))?,
+    );
+
+    let sender = pc
+        .add_track(local_track() as Arc<dyn TrackLocal + Send + Sync>)
+        .await
+        .map_err(|e| format!("Failed to add audio track: {}", e))?;
+    // RTCP has to be read for the interceptors (NACK, reports) to see it.
+    tauri::async_runtime::spawn(async move {
+        let mut buf = vec![0u8; 1500];
+        while sender.read(&mut buf).await.is_ok() {}
+    });
+
+    let remote_peer = peer_id.to_string();
+    pc.on_track(Box::new(move |track, _, _| {
+        tauri::async_runtime::spawn(receive_audio(remote_peer.clone(), track));
+        Box::pin(async {})
+    }));
+
+    // The answering side learns about the offerer's channel here.
+    let (dc_app, dc_peer) = (app.clone(), peer_id.to_string());
+    pc.on_data_channel(Box::new(move |dc| {
+        if dc.label() == FILES_CHANNEL {
+            attach_file_channel(&dc_app, &dc_peer, dc);
+        }
+        Box::pin(async {})
+    }));
+
+    let (state_app, state_peer) = (app.clone(), peer_id.to_string());
+    pc.on_peer_connection_state_change(Box::new(move |state| {
+        let _ = state_app.emit_all("cordia:native-rtc-state", NativePeerState {
+            peer_id: state_peer.clone(),
+            state: state.to_string(),
+        });
+        if matches!(state, RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed) {
+            forget(&state_peer);
+        }
+        Box::pin(async {})
+    }));
+
+    let replaced = PEERS
+        .lock()
+        .map_err(|_| "Failed to lock native peers".to_string())?
+        .get_or_insert_with(HashMap::new)
+        .insert(peer_id.to_string(), pc.clone());
+    if let Some(old) = replaced {
+        let _ = old.close().await;
+    }
+    Ok(pc)
+}
+
+fn attach_file_channel(app: &AppHandle, peer_id: &str, dc: Arc<RTCDataChannel>) {
+    let (app, from) = (app.clone(), peer_id.to_string());
+    dc.on_message(Box::new(move |msg: DataChannelMessage| {
+        if msg.is_string {
+            if let Ok(text) = std::str::from_utf8(&msg.data) {
+                crate::file_transfer::handle_frame(&app, &from, text);
+            }
+        }
+        Box::pin(async {})
+    }));
+    if let Ok(mut channels) = FILE_CHANNELS.lock() {
+        channels.get_or_insert_with(HashMap::new).insert(peer_id.to_string(), dc);
+    }
+}
+
+/// Queue `text` on the `files` channel to `peer_id`. False if there's no open channel, so
+/// the caller can fall back to the beacon relay.
+pub fn send_data(peer_id: &str, text: String) -> bool {
+    let dc = match FILE_CHANNELS.lock().ok().and_then(|c| c.as_ref()?.get(peer_id).cloned()) {
+        Some(dc) if dc.ready_state() == RTCDataChannelState::Open => dc,
+        _ => return false,
+    };
+    // Callers may be on a runtime thread, so don't block on the send.
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = dc.send_text(text).await {
+            eprintln!("[NativeRTC] Failed to send file data: {}", e);
+        }
+    });
+    true
+}
+
+/// Remote RTP into the jitter buffer until the track ends. RTP's 16-bit sequence numbers
+/// and 32-bit timestamps are extended so the buffer sees them as monotonic.
+async fn receive_audio(peer_id: String, track: Arc<TrackRemote>) {
+    let (mut last_seq, mut last_ts) = (None, None);
+    while let Ok((packet, _)) = track.read_rtp().await {
+        if packet.payload.is_empty() {
+            continue;
+        }
+        let seq = extend(&mut last_seq, packet.header.sequence_number as u64, 16);
+        let ts = extend(&mut last_ts, packet.header.timestamp as u64, 32);
+        let capture_ts = ts * 1_000_000 / OPUS_CLOCK_RATE;
+        if let Err(e) = audio_jitter::push_packet(&peer_id, seq, capture_ts, PacketPayload::Opus(packet.payload.to_vec())) {
+            eprintln!("[NativeRTC] {}", e);
+        }
+    }
+}
+
+/// Extend a wrapping `bits`-wide counter to 64 bits, relative to the last value seen.
+fn extend(last: &mut Option<u64>, value: u64, bits: u32) -> u64 {
+    let modulus = 1u64 << bits;
+    let extended = match *last {
+        None => modulus + value,
+        Some(prev) => {
+            let base = prev - prev % modulus;
+            // Pick the candidate closest to the previous value (handles wrap and reordering).
+            [base.wrapping_sub(modulus), base, base + modulus]
+                .into_iter()
+                .map(|b| b.wrapping_add(value))
+                .min_by_key(|c| c.abs_diff(prev))
+                .unwrap_or(base + value)
+        }
+    };
+    if last.is_none_or(|prev| extended > prev) {
+        *last = Some(extended);
+    }
+    extended
+}
+
+async fn gathered_sdp(pc: &RTCPeerConnection, description: RTCSessionDescription) -> Result<String, String> {
+    let mut gathered = pc.gathering_complete_promise().await;
+    pc.set_local_description(description)
+        .await
+        .map_err(|e| format!("Failed to set local description: {}", e))?;
+    let _ = gathered.recv().await;
+    pc.local_description()
+        .await
+        .map(|d| d.sdp)
+        .ok_or_else(|| "Missing local description".to_string())
+}
+
+/// Start a connection to `peer_id`; returns the offer SDP to send it.
+pub async fn create_offer(app: &AppHandle, peer_id: &str, ice_servers: Vec<IceServer>) -> Result<String, String> {
+    let pc = new_peer(app, peer_id, ice_servers).await?;
+    let dc = pc
+        .create_data_channel(FILES_CHANNEL, None)
+        .await
+        .map_err(|e| format!("Failed to create data channel: {}", e))?;
+    attach_file_channel(app, peer_id, dc);
+    let offer = pc
+        .create_offer(None)
+        .await
+        .map_err(|e| format!("Failed to create offer: {}", e))?;
+    gathered_sdp(&pc, offer).await
+}
+
+/// Accept an offer from `peer_id`; returns the answer SDP to send back.
+pub async fn accept_offer(app: &AppHandle, peer_id: &str, sdp: String, ice_servers: Vec<IceServer>) -> Result<String, String> {
+    let pc = new_peer(app, peer_id, ice_servers).await?;
+    let offer = RTCSessionDescription::offer(sdp).map_err(|e| format!("Invalid offer: {}", e))?;
+    pc.set_remote_description(offer)
+        .await
+        .map_err(|e| format!("Failed to apply offer: {}", e))?;
+    let answer = pc
+        .create_answer(None)
+        .await
+        .map_err(|e| format!("Failed to create answer: {}", e))?;
+    gathered_sdp(&pc, answer).await
+}
+
+/// Complete a connection started with [`create_offer`].
+pub async fn apply_answer(peer_id: &str, sdp: String) -> Result<(), String> {
+    let pc = peer(peer_id).ok_or_else(|| format!("No native connection to {}", peer_id))?;
+    let answer = RTCSessionDescription::answer(sdp).map_err(|e| format!("Invalid answer: {}", e))?;
+    pc.set_remote_description(answer)
+        .await
+        .map_err(|e| format!("Failed to apply answer: {}", e))
+}
+
+fn peer(peer_id: &str) -> Option<Arc<RTCPeerConnection>> {
+    PEERS.lock().ok()?.as_ref()?.get(peer_id).cloned()
+}
+
+/// Drop `peer_id` from the peer map and the audio path; the last one out releases the track.
+fn forget(peer_id: &str) -> Option<Arc<RTCPeerConnection>> {
+    let mut peers = PEERS.lock().ok()?;
+    let map = peers.get_or_insert_with(HashMap::new);
+    let pc = map.remove(peer_id);
+    if let Ok(mut channels) = FILE_CHANNELS.lock() {
+        channels.get_or_insert_with(HashMap::new).remove(peer_id);
+    }
+    if map.is_empty() {
+        if let Ok(mut track) = LOCAL_TRACK.lock() {
+            *track = None;
+        }
+    }
+    audio_jitter::remove_peer(peer_id);
+    pc
+}
+
+pub async fn close(peer_id: &str) {
+    if let Some(pc) = forget(peer_id) {
+        let _ = pc.close().await;
+    }
+}
+
+pub async fn close_all() {
+    let ids: Vec<String> = PEERS
+        .lock()
+        .ok()
+        .and_then(|p| p.as_ref().map(|m| m.keys().cloned().collect()))
+        .unwrap_or_default();
+    for id in ids {
+        close(&id).await;
+    }
+}
+
+pub fn peers() -> Vec<NativePeerState> {
+    let peers: Vec<(String, Arc<RTCPeerConnection>)> = PEERS
+        .lock()
+        .ok()
+        .and_then(|p| p.as_ref().map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect()))
+        .unwrap_or_default();
+    peers
+        .into_iter()
+        .map(|(peer_id, pc)| NativePeerState { peer_id, state: pc.connection_state().to_string() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extends_wrapping_counters() {
+        let mut last = None;
+        let first = extend(&mut last, 65_534, 16);
+        assert_eq!(extend(&mut last, 65_535, 16), first + 1);
+        assert_eq!(extend(&mut last, 0, 16), first + 2);
+        // A late packet from before the wrap stays behind.
+        assert_eq!(extend(&mut last, 65_535, 16), first + 1);
+        assert_eq!(extend(&mut last, 1, 16), first + 3);
+    }
+}
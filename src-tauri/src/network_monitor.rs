@@ -0,0 +1,136 @@
+//! Notices when the machine's network changes (Wi-Fi to Ethernet, VPN up or down, a new
+//! DHCP lease) so calls recover right away instead of waiting for keepalives and ICE
+//! timeouts to give up on the old path.
+//!
+//! A background thread samples the interface addresses and the default route's source
+//! address every `POLL_INTERVAL`. When they change and stay changed for `SETTLE`, the native
+//! beacon connection redials at once and `cordia:network-changed` is emitted so the call
+//! logic can reconnect its own signaling and restart ICE.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Interfaces come up in steps (link, then address, then route); wait for them to settle.
+const SETTLE: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkChange {
+    /// Local address of the default route (IPv4 if there is one), after the change.
+    pub default_route: Option<String>,
+    pub default_route_changed: bool,
+    /// Names of the interfaces with a usable address.
+    pub interfaces: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    route_v4: Option<IpAddr>,
+    route_v6: Option<IpAddr>,
+    addresses: BTreeSet<(String, IpAddr)>,
+}
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Start watching for network changes. Call once from `setup`.
+pub fn start(app: AppHandle) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || {
+        let mut last = Snapshot::take();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            if Snapshot::take() == last {
+                continue;
+            }
+            std::thread::sleep(SETTLE);
+            let current = Snapshot::take();
+            if current == last {
+                continue;
+            }
+            let change = current.change_from(&last);
+            last = current;
+            eprintln!("[Network] Changed (default route {:?}); reconnecting", change.default_route);
+            crate::signaling::reconnect_now();
+            let _ = app.emit_all("cordia:network-changed", change);
+        }
+    });
+}
+
+impl Snapshot {
+    fn take() -> Self {
+        let addresses = if_addrs::get_if_addrs()
+            .map(|interfaces| {
+                interfaces
+                    .into_iter()
+                    .filter(|iface| !iface.is_loopback() && !iface.is_link_local())
+                    .map(|iface| {
+                        let ip = iface.ip();
+                        (iface.name, ip)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            route_v4: default_route_source(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+            route_v6: default_route_source(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))),
+            addresses,
+        }
+    }
+
+    fn change_from(&self, previous: &Snapshot) -> NetworkChange {
+        let interfaces: BTreeSet<&String> = self.addresses.iter().map(|(name, _)| name).collect();
+        NetworkChange {
+            default_route: self.route_v4.or(self.route_v6).map(|ip| ip.to_string()),
+            default_route_changed: self.route_v4 != previous.route_v4 || self.route_v6 != previous.route_v6,
+            interfaces: interfaces.into_iter().cloned().collect(),
+        }
+    }
+}
+
+/// Source address the OS would use to reach `target` (a documentation address, so the
+/// answer is the default route). Connecting a UDP socket only consults the routing table;
+/// nothing is sent.
+fn default_route_source(target: IpAddr) -> Option<IpAddr> {
+    let bind: SocketAddr = match target {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect((target, 9)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_route_changes_separately_from_interface_changes() {
+        let wifi: IpAddr = "192.168.1.20".parse().unwrap();
+        let vpn: IpAddr = "10.8.0.2".parse().unwrap();
+        let before = Snapshot {
+            route_v4: Some(wifi),
+            route_v6: None,
+            addresses: BTreeSet::from([("wlan0".to_string(), wifi)]),
+        };
+        let vpn_up = Snapshot {
+            route_v4: Some(vpn),
+            route_v6: None,
+            addresses: BTreeSet::from([("wlan0".to_string(), wifi), ("tun0".to_string(), vpn)]),
+        };
+
+        let change = vpn_up.change_from(&before);
+        assert!(change.default_route_changed);
+        assert_eq!(change.default_route.as_deref(), Some("10.8.0.2"));
+        assert_eq!(change.interfaces, vec!["tun0".to_string(), "wlan0".to_string()]);
+
+        let same_route = Snapshot { route_v4: Some(wifi), ..vpn_up };
+        assert!(!same_route.change_from(&before).default_route_changed);
+    }
+}
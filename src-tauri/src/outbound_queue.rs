@@ -0,0 +1,108 @@
+//! Store-and-forward queue for outbound signaling payloads.
+//!
+//! Messages sent with `signaling::send_reliable` are written here first and removed once
+//! the signaling connection has written them to the socket, so anything typed while the
+//! beacon is unreachable survives reconnects and app restarts. Each message carries a
+//! `dedup_id` so a receiver can drop a replay that did get through the first time.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::account_manager::AccountManager;
+
+const MAX_QUEUED: usize = 1000;
+/// Messages older than this are dropped at startup rather than replayed out of context.
+const MAX_AGE_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedMessage {
+    pub id: String,
+    pub queued_at: String,
+    /// The message as it goes on the wire, `dedup_id` included.
+    pub payload: Value,
+}
+
+static QUEUE: Mutex<Vec<QueuedMessage>> = Mutex::new(Vec::new());
+
+fn queue_path() -> Result<PathBuf, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    Ok(account_manager.get_base_data_dir().join("outbound_queue.json"))
+}
+
+/// Load messages left over from the last run. Called once at startup.
+pub fn load_outbound_queue() {
+    let cutoff = chrono::Utc::now() - chrono::Duration::hours(MAX_AGE_HOURS);
+    let queued: Vec<QueuedMessage> = queue_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let queued = queued
+        .into_iter()
+        .filter(|m| {
+            chrono::DateTime::parse_from_rfc3339(&m.queued_at).is_ok_and(|t| t > cutoff)
+        })
+        .collect();
+    if let Ok(mut queue) = QUEUE.lock() {
+        *queue = queued;
+    }
+}
+
+fn save(queue: &[QueuedMessage]) -> Result<(), String> {
+    let json = serde_json::to_string(queue)
+        .map_err(|e| format!("Failed to serialize outbound queue: {}", e))?;
+    std::fs::write(queue_path()?, json).map_err(|e| format!("Failed to save outbound queue: {}", e))
+}
+
+/// Queue `message` and persist it. Uses the message's own `dedup_id` when it has one
+/// (so a retried send isn't queued twice) and assigns one otherwise. Returns the id.
+pub fn enqueue(mut message: Value) -> Result<String, String> {
+    let fields = message
+        .as_object_mut()
+        .ok_or_else(|| "Signaling message must be a JSON object".to_string())?;
+    let id = match fields.get("dedup_id").and_then(Value::as_str) {
+        Some(id) => id.to_string(),
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            fields.insert("dedup_id".to_string(), Value::String(id.clone()));
+            id
+        }
+    };
+
+    let mut queue = QUEUE.lock().map_err(|_| "Failed to lock outbound queue".to_string())?;
+    if queue.iter().any(|m| m.id == id) {
+        return Ok(id);
+    }
+    if queue.len() >= MAX_QUEUED {
+        return Err("Outbound queue is full".to_string());
+    }
+    queue.push(QueuedMessage {
+        id: id.clone(),
+        queued_at: chrono::Utc::now().to_rfc3339(),
+        payload: message,
+    });
+    save(&queue)?;
+    Ok(id)
+}
+
+/// Messages waiting to be sent, oldest first.
+pub fn pending() -> Vec<QueuedMessage> {
+    QUEUE.lock().map(|q| q.clone()).unwrap_or_default()
+}
+
+/// Drop a delivered message.
+pub fn remove(id: &str) -> Result<(), String> {
+    let mut queue = QUEUE.lock().map_err(|_| "Failed to lock outbound queue".to_string())?;
+    queue.retain(|m| m.id != id);
+    save(&queue)
+}
+
+/// Discard everything still queued (e.g. on sign-out).
+pub fn clear() -> Result<(), String> {
+    let mut queue = QUEUE.lock().map_err(|_| "Failed to lock outbound queue".to_string())?;
+    queue.clear();
+    save(&queue)
+}
@@ -0,0 +1,166 @@
+//! Keeps the machine from going to sleep while the user is in a voice call.
+//!
+//! The frontend acquires the inhibitor on voice join and releases it on leave; acquiring twice
+//! is a no-op. Windows holds `SetThreadExecutionState` on a parked thread (the state belongs to
+//! the thread that set it), macOS runs `caffeinate` tied to our pid, and Linux asks the desktop
+//! through `org.freedesktop.ScreenSaver.Inhibit`, falling back to a logind idle inhibitor.
+//! Only automatic idle sleep is blocked: the screen may still turn off, which doesn't affect
+//! the call, and sleeping on purpose (lid, menu) still works.
+
+use std::sync::Mutex;
+
+static INHIBITOR: Mutex<Option<platform::Inhibitor>> = Mutex::new(None);
+
+pub fn acquire() -> Result<(), String> {
+    let mut inhibitor = INHIBITOR.lock().map_err(|_| "Failed to lock sleep inhibitor".to_string())?;
+    if inhibitor.is_none() {
+        *inhibitor = Some(platform::Inhibitor::new()?);
+    }
+    Ok(())
+}
+
+pub fn release() {
+    if let Ok(mut inhibitor) = INHIBITOR.lock() {
+        // Dropping the inhibitor lets the system sleep again.
+        inhibitor.take();
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::sync::mpsc;
+    use windows::Win32::System::Power::{SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED};
+
+    pub struct Inhibitor {
+        /// Dropping it wakes the holder thread, which clears the state and exits.
+        _stop: mpsc::Sender<()>,
+    }
+
+    impl Inhibitor {
+        pub fn new() -> Result<Self, String> {
+            let (stop, stopped) = mpsc::channel::<()>();
+            let (ready_tx, ready) = mpsc::channel();
+            std::thread::spawn(move || {
+                let previous = unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) };
+                let _ = ready_tx.send(previous.0 != 0);
+                let _ = stopped.recv();
+                unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+            });
+            match ready.recv() {
+                Ok(true) => Ok(Self { _stop: stop }),
+                _ => Err("Failed to prevent sleep: SetThreadExecutionState was refused".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::{Child, Command, Stdio};
+
+    pub struct Inhibitor {
+        caffeinate: Child,
+    }
+
+    impl Inhibitor {
+        pub fn new() -> Result<Self, String> {
+            // -i: no idle sleep; -w: exit with us even if we never get to release.
+            let caffeinate = Command::new("/usr/bin/caffeinate")
+                .args(["-i", "-w", &std::process::id().to_string()])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|e| format!("Failed to prevent sleep: {}", e))?;
+            Ok(Self { caffeinate })
+        }
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            let _ = self.caffeinate.kill();
+            let _ = self.caffeinate.wait();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use zbus::blocking::Connection;
+    use zbus::zvariant::OwnedFd;
+
+    const REASON: &str = "Voice call in progress";
+
+    pub enum Inhibitor {
+        /// Desktop idle inhibition; lasts while the connection is open or until `UnInhibit`.
+        ScreenSaver { connection: Connection, cookie: u32 },
+        /// logind idle inhibitor; lasts while the fd is open.
+        Logind { _fd: OwnedFd },
+    }
+
+    impl Inhibitor {
+        pub fn new() -> Result<Self, String> {
+            match screensaver() {
+                Ok(inhibitor) => Ok(inhibitor),
+                Err(screensaver_error) => logind().map_err(|logind_error| {
+                    format!("Failed to prevent sleep: {}; {}", screensaver_error, logind_error)
+                }),
+            }
+        }
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            if let Self::ScreenSaver { connection, cookie } = self {
+                let _ = connection.call_method(
+                    Some("org.freedesktop.ScreenSaver"),
+                    "/org/freedesktop/ScreenSaver",
+                    Some("org.freedesktop.ScreenSaver"),
+                    "UnInhibit",
+                    &(*cookie,),
+                );
+            }
+        }
+    }
+
+    fn screensaver() -> Result<Inhibitor, String> {
+        let connection = Connection::session().map_err(|e| format!("session bus: {}", e))?;
+        let reply = connection
+            .call_method(
+                Some("org.freedesktop.ScreenSaver"),
+                "/org/freedesktop/ScreenSaver",
+                Some("org.freedesktop.ScreenSaver"),
+                "Inhibit",
+                &("Cordia", REASON),
+            )
+            .map_err(|e| format!("ScreenSaver.Inhibit: {}", e))?;
+        let cookie: u32 = reply.body().map_err(|e| format!("ScreenSaver.Inhibit: {}", e))?;
+        Ok(Inhibitor::ScreenSaver { connection, cookie })
+    }
+
+    fn logind() -> Result<Inhibitor, String> {
+        let connection = Connection::system().map_err(|e| format!("system bus: {}", e))?;
+        let reply = connection
+            .call_method(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1",
+                Some("org.freedesktop.login1.Manager"),
+                "Inhibit",
+                &("idle", "Cordia", REASON, "block"),
+            )
+            .map_err(|e| format!("login1 Inhibit: {}", e))?;
+        let fd: OwnedFd = reply.body().map_err(|e| format!("login1 Inhibit: {}", e))?;
+        Ok(Inhibitor::Logind { _fd: fd })
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod platform {
+    pub struct Inhibitor;
+
+    impl Inhibitor {
+        pub fn new() -> Result<Self, String> {
+            Err("Preventing sleep is not supported on this platform".to_string())
+        }
+    }
+}
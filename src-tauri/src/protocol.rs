@@ -0,0 +1,264 @@
+//! Signaling protocol version negotiation.
+//!
+//! Right after connecting, the client sends `{"type":"Hello","protocol_versions":[2,1],...}`.
+//! A beacon that negotiates answers `{"type":"Welcome","protocol_version":N,"accepts":[...]}`
+//! with the version it picked and, optionally, the client message types it handles, its
+//! enabled features and its limits (the same ones as on `/api/info`). Beacons
+//! from before negotiation reject `Hello` as an unknown variant; that error is swallowed and
+//! the beacon is treated as version 1, accepting the version 1 message set.
+//!
+//! With that known, messages the beacon can't handle are refused locally with a clear error
+//! instead of bouncing off as "Invalid message format", and beacon messages this client
+//! doesn't know are reported (`cordia:signaling-unsupported`) rather than silently ignored.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// Newest first. 1: the original message set, no handshake. 2: `Hello` / `Welcome`.
+pub const SUPPORTED_VERSIONS: [u32; 2] = [2, 1];
+
+/// Client -> beacon messages of protocol 1, which every beacon accepts.
+const V1_CLIENT_MESSAGES: &[&str] = &[
+    "Register",
+    "Offer",
+    "Answer",
+    "IceCandidate",
+    "EphemeralChatSend",
+    "EphemeralReceiptSend",
+    "AttachmentTransferRequest",
+    "AttachmentTransferResponse",
+    "AttachmentTransferSignal",
+    "SwarmAnnounce",
+    "SwarmUnannounce",
+    "SwarmPeerListRequest",
+    "SwarmHealthUpdate",
+    "PresenceHello",
+    "PresenceActive",
+    "ProfileAnnounce",
+    "ProfileHello",
+    "ProfilePush",
+    "VoiceRegister",
+    "VoiceUnregister",
+    "VoiceOffer",
+    "VoiceAnswer",
+    "VoiceIceCandidate",
+    "Ping",
+    "FriendMutualCheck",
+    "FriendMutualCheckReply",
+];
+
+/// Beacon -> client messages this client understands.
+const KNOWN_SERVER_MESSAGES: &[&str] = &[
+    "Welcome",
+    "AuthChallenge",
+    "Authenticated",
+    "Registered",
+    "Error",
+    "Offer",
+    "Answer",
+    "IceCandidate",
+    "ServerMemberJoined",
+    "ServerHintUpdated",
+    "ServerMemberLeft",
+    "EphemeralChatIncoming",
+    "EphemeralReceiptIncoming",
+    "AttachmentTransferRequestIncoming",
+    "AttachmentTransferResponseIncoming",
+    "AttachmentTransferSignalIncoming",
+    "SwarmPeerListResponse",
+    "PresenceSnapshot",
+    "PresenceUpdate",
+    "VoicePresenceUpdate",
+    "ProfileSnapshot",
+    "ProfileUpdate",
+    "VoiceRegistered",
+    "VoicePeerJoined",
+    "VoicePeerLeft",
+    "VoiceOffer",
+    "VoiceAnswer",
+    "VoiceIceCandidate",
+    "Pong",
+    "FriendPendingSnapshot",
+    "FriendRequestIncoming",
+    "FriendRequestAccepted",
+    "FriendRequestDeclined",
+    "FriendRequestCancelled",
+    "FriendCodeRedemptionIncoming",
+    "FriendCodeRedemptionAccepted",
+    "FriendCodeRedemptionDeclined",
+    "FriendCodeRedemptionCancelled",
+    "FriendRemoved",
+    "FriendMutualCheckIncoming",
+    "FriendMutualCheckReplyIncoming",
+    "ProfilePushIncoming",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Negotiated {
+    pub version: u32,
+    /// The beacon predates negotiation.
+    pub legacy: bool,
+    /// Client message types the beacon handles; `None` if it didn't say (anything goes).
+    pub accepts: Option<BTreeSet<String>>,
+    /// e.g. "voice", "connection_auth", "deflate"; empty if the beacon didn't say.
+    pub features: Vec<String>,
+    /// Rate limits and payload sizes, as on `/api/info`.
+    pub limits: Option<Value>,
+}
+
+impl Negotiated {
+    fn legacy() -> Self {
+        Self {
+            version: 1,
+            legacy: true,
+            accepts: Some(V1_CLIENT_MESSAGES.iter().map(|t| t.to_string()).collect()),
+            features: Vec::new(),
+            limits: None,
+        }
+    }
+}
+
+/// What to do with a message from the beacon.
+#[derive(Debug, PartialEq)]
+pub enum Inbound {
+    /// Handshake answer; not for the frontend.
+    Negotiated(Negotiated),
+    /// The beacon only speaks versions this client doesn't.
+    Incompatible(String),
+    Forward,
+    /// A message type this client doesn't know; forwarded, but worth reporting.
+    Unknown(String),
+}
+
+static NEGOTIATED: Mutex<Option<Negotiated>> = Mutex::new(None);
+/// Unknown beacon message types already reported this connection.
+static REPORTED_UNKNOWN: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// The handshake to send first on every connection. Forgets the previous negotiation.
+pub fn hello() -> String {
+    reset();
+    json!({
+        "type": "Hello",
+        "protocol_versions": SUPPORTED_VERSIONS,
+        "client_version": env!("CARGO_PKG_VERSION"),
+    })
+    .to_string()
+}
+
+pub fn reset() {
+    if let Ok(mut negotiated) = NEGOTIATED.lock() {
+        *negotiated = None;
+    }
+    if let Ok(mut reported) = REPORTED_UNKNOWN.lock() {
+        reported.clear();
+    }
+}
+
+pub fn negotiated() -> Option<Negotiated> {
+    NEGOTIATED.lock().ok().and_then(|n| n.clone())
+}
+
+/// Refuse a message the beacon won't understand. Before negotiation finishes, everything
+/// goes through.
+pub fn check_outbound(message: &Value) -> Result<(), String> {
+    let Some(kind) = message.get("type").and_then(Value::as_str) else {
+        return Ok(());
+    };
+    match negotiated().and_then(|n| n.accepts.map(|accepts| (n.version, accepts))) {
+        Some((version, accepts)) if !accepts.contains(kind) => Err(format!(
+            "The beacon (protocol {}) doesn't support {}; it needs to be updated",
+            version, kind
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Classify a beacon message, recording the negotiation result when it is one.
+pub fn inbound(message: &Value) -> Inbound {
+    let classified = classify(message, negotiated().is_none());
+    if let Inbound::Negotiated(negotiated) = &classified {
+        if let Ok(mut current) = NEGOTIATED.lock() {
+            *current = Some(negotiated.clone());
+        }
+    }
+    if let Inbound::Unknown(kind) = &classified {
+        let first = REPORTED_UNKNOWN.lock().map(|mut reported| reported.insert(kind.clone())).unwrap_or(false);
+        if !first {
+            return Inbound::Forward;
+        }
+    }
+    classified
+}
+
+fn classify(message: &Value, awaiting_handshake: bool) -> Inbound {
+    let kind = message.get("type").and_then(Value::as_str).unwrap_or_default();
+    match kind {
+        "Welcome" => {
+            let version = message.get("protocol_version").and_then(Value::as_u64).unwrap_or(0) as u32;
+            if !SUPPORTED_VERSIONS.contains(&version) {
+                return Inbound::Incompatible(format!(
+                    "The beacon speaks protocol {} but this version of Cordia supports {:?}; update Cordia",
+                    version, SUPPORTED_VERSIONS
+                ));
+            }
+            let accepts = message
+                .get("accepts")
+                .and_then(Value::as_array)
+                .map(|types| types.iter().filter_map(Value::as_str).map(str::to_string).collect());
+            let features = message
+                .get("features")
+                .and_then(Value::as_array)
+                .map(|names| names.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default();
+            let limits = message.get("limits").filter(|l| l.is_object()).cloned();
+            Inbound::Negotiated(Negotiated { version, legacy: false, accepts, features, limits })
+        }
+        // serde's "unknown variant `Hello`" from a beacon that predates the handshake.
+        "Error" if awaiting_handshake => {
+            let error = message.get("message").and_then(Value::as_str).unwrap_or_default();
+            if error.contains("`Hello`") {
+                Inbound::Negotiated(Negotiated::legacy())
+            } else {
+                Inbound::Forward
+            }
+        }
+        _ if KNOWN_SERVER_MESSAGES.contains(&kind) => Inbound::Forward,
+        _ => Inbound::Unknown(kind.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_with_new_and_legacy_beacons() {
+        let welcome = json!({
+            "type": "Welcome",
+            "protocol_version": 2,
+            "accepts": ["Ping"],
+            "features": ["voice"],
+            "limits": { "max_body_bytes": 1000000 },
+        });
+        let Inbound::Negotiated(negotiated) = classify(&welcome, true) else { panic!() };
+        assert_eq!(negotiated.version, 2);
+        assert_eq!(negotiated.features, vec!["voice".to_string()]);
+        assert!(negotiated.limits.is_some());
+        assert!(negotiated.accepts.unwrap().contains("Ping"));
+
+        let too_new = json!({ "type": "Welcome", "protocol_version": 9 });
+        assert!(matches!(classify(&too_new, true), Inbound::Incompatible(_)));
+
+        let legacy = json!({
+            "type": "Error",
+            "message": "Invalid message format: unknown variant `Hello`, expected one of `Register`, `Offer`",
+        });
+        assert_eq!(classify(&legacy, true), Inbound::Negotiated(Negotiated::legacy()));
+        assert_eq!(classify(&legacy, false), Inbound::Forward);
+
+        assert_eq!(classify(&json!({ "type": "VoicePeerJoined" }), false), Inbound::Forward);
+        assert_eq!(classify(&json!({ "type": "Reaction" }), false), Inbound::Unknown("Reaction".to_string()));
+    }
+}
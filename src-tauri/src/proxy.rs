@@ -0,0 +1,322 @@
+//! Proxy settings for connections to the beacon.
+//!
+//! Applies to the beacon's HTTP endpoints (health check, invites, hints) and to the native
+//! signaling WebSocket. `System` follows the platform: reqwest reads the environment and,
+//! on Windows, the registry; the WebSocket reads `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` and
+//! `NO_PROXY`. WebSockets are tunnelled with HTTP `CONNECT` or SOCKS5.
+
+use base64::Engine;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::account_manager::AccountManager;
+use crate::ws_deflate::{Deflate, DeflateStream};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    /// Whatever the OS / environment says.
+    #[default]
+    System,
+    /// Never use a proxy.
+    Direct,
+    /// Always use `url`.
+    Manual,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySettings {
+    #[serde(default)]
+    pub mode: ProxyMode,
+    /// `http://`, `socks5://` or `socks5h://`, with optional `user:pass@`. Used in `Manual` mode.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// A WebSocket to the beacon, compressed when the beacon accepted permessage-deflate.
+pub type BeaconSocket = WebSocketStream<DeflateStream<MaybeTlsStream<TcpStream>>>;
+
+static PROXY_SETTINGS: Mutex<Option<ProxySettings>> = Mutex::new(None);
+
+fn settings_path() -> Result<PathBuf, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    Ok(account_manager.get_base_data_dir().join("proxy_settings.json"))
+}
+
+/// Load the saved settings. Called once at startup; a missing or unreadable file means
+/// the default (system proxy).
+pub fn load_proxy_settings() {
+    let settings = settings_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<ProxySettings>(&json).ok())
+        .unwrap_or_default();
+    if let Ok(mut current) = PROXY_SETTINGS.lock() {
+        *current = Some(settings);
+    }
+}
+
+pub fn proxy_settings() -> ProxySettings {
+    PROXY_SETTINGS
+        .lock()
+        .ok()
+        .and_then(|s| s.clone())
+        .unwrap_or_default()
+}
+
+/// Validate, apply and save. New connections pick the settings up; an open signaling
+/// connection keeps its route until it reconnects.
+pub fn set_proxy_settings(settings: ProxySettings) -> Result<(), String> {
+    let settings = ProxySettings {
+        url: settings.url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty()),
+        ..settings
+    };
+    match (&settings.mode, &settings.url) {
+        (ProxyMode::Manual, None) => return Err("Manual proxy needs a URL".to_string()),
+        (_, Some(url)) => {
+            parse_proxy_url(url)?;
+        }
+        _ => {}
+    }
+
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize proxy settings: {}", e))?;
+    std::fs::write(settings_path()?, json)
+        .map_err(|e| format!("Failed to save proxy settings: {}", e))?;
+    let mut current = PROXY_SETTINGS
+        .lock()
+        .map_err(|_| "Failed to lock proxy settings".to_string())?;
+    *current = Some(settings);
+    Ok(())
+}
+
+fn parse_proxy_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+    match parsed.scheme() {
+        "http" | "socks5" | "socks5h" => {}
+        other => return Err(format!("Unsupported proxy scheme: {} (use http, socks5 or socks5h)", other)),
+    }
+    if parsed.host_str().is_none() || parsed.port_or_known_default().is_none() {
+        return Err("Proxy URL needs a host and port".to_string());
+    }
+    Ok(parsed)
+}
+
+/// HTTP client for talking to the beacon, routed per the proxy settings.
+pub fn http_client_builder() -> Result<reqwest::ClientBuilder, String> {
+    let settings = proxy_settings();
+    let builder = reqwest::Client::builder();
+    Ok(match settings.mode {
+        ProxyMode::System => builder,
+        ProxyMode::Direct => builder.no_proxy(),
+        ProxyMode::Manual => {
+            let url = settings.url.ok_or_else(|| "Manual proxy needs a URL".to_string())?;
+            builder.proxy(reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy URL: {}", e))?)
+        }
+    })
+}
+
+/// Proxy to use for a connection to `target`, if any.
+pub fn proxy_for(target: &Url) -> Result<Option<Url>, String> {
+    let settings = proxy_settings();
+    match settings.mode {
+        ProxyMode::Direct => Ok(None),
+        ProxyMode::Manual => {
+            let url = settings.url.ok_or_else(|| "Manual proxy needs a URL".to_string())?;
+            parse_proxy_url(&url).map(Some)
+        }
+        ProxyMode::System => {
+            let host = target.host_str().unwrap_or_default();
+            if env_var(&["NO_PROXY", "no_proxy"]).is_some_and(|list| bypasses(&list, host)) {
+                return Ok(None);
+            }
+            let names: &[&str] = if matches!(target.scheme(), "wss" | "https") {
+                &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+            } else {
+                &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+            };
+            // Like curl, a bare `host:port` means an HTTP proxy.
+            Ok(env_var(names).and_then(|url| {
+                let url = if url.contains("://") { url } else { format!("http://{}", url) };
+                parse_proxy_url(&url).ok()
+            }))
+        }
+    }
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// `NO_PROXY` matching: `*`, exact hosts, and domain suffixes (`example.com` and
+/// `.example.com` both cover `beacon.example.com`).
+fn bypasses(no_proxy: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    no_proxy.split(',').map(|entry| entry.trim().to_ascii_lowercase()).any(|entry| {
+        let entry = entry.split(':').next().unwrap_or_default().trim_start_matches('.');
+        entry == "*" || (!entry.is_empty() && (host == entry || host.ends_with(&format!(".{}", entry))))
+    })
+}
+
+/// Open a WebSocket to `url`, through the configured proxy when there is one. TLS is
+/// set up by `beacon_tls` so per-beacon roots and pins apply.
+pub async fn connect_websocket(url: &str) -> Result<BeaconSocket, String> {
+    connect_websocket_offering(url, &[]).await.map(|(socket, _)| socket)
+}
+
+/// [`connect_websocket`], offering `subprotocols` and permessage-deflate; also returns the
+/// subprotocol the beacon picked. The handshake is done here rather than by tungstenite,
+/// which can't negotiate extensions.
+pub async fn connect_websocket_offering(
+    url: &str,
+    subprotocols: &[&str],
+) -> Result<(BeaconSocket, Option<String>), String> {
+    let target = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let stream = connect_tcp(&target).await?;
+    let mut stream = crate::beacon_tls::connect(&target, stream).await?;
+
+    let host = target.host_str().ok_or_else(|| "URL has no host".to_string())?;
+    let host = match target.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let path = match target.query() {
+        Some(query) => format!("{}?{}", target.path(), query),
+        None => target.path().to_string(),
+    };
+    let key = generate_key();
+    let mut request = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Extensions: {}\r\n",
+        crate::ws_deflate::OFFER
+    );
+    if !subprotocols.is_empty() {
+        request.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", subprotocols.join(", ")));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
+
+    let head = read_head(&mut stream)
+        .await
+        .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
+    let mut lines = head.lines();
+    let status_line = lines.next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("101") {
+        return Err(format!("WebSocket handshake failed: {}", status_line));
+    }
+    let headers: Vec<(String, &str)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+    let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| *v);
+    if header("sec-websocket-accept") != Some(derive_accept_key(key.as_bytes()).as_str()) {
+        return Err("WebSocket handshake failed: bad Sec-WebSocket-Accept".to_string());
+    }
+    let deflate = header("sec-websocket-extensions").map(Deflate::from_response).transpose()?;
+    let picked = header("sec-websocket-protocol").map(str::to_string);
+
+    let socket = WebSocketStream::from_raw_socket(DeflateStream::new(stream, deflate), Role::Client, None).await;
+    Ok((socket, picked))
+}
+
+/// A TCP stream to `target`'s host and port, through the configured proxy when there is one.
+pub async fn connect_tcp(target: &Url) -> Result<TcpStream, String> {
+    let host = target.host_str().ok_or_else(|| "URL has no host".to_string())?;
+    let port = target.port_or_known_default().ok_or_else(|| "URL has no port".to_string())?;
+    match proxy_for(target)? {
+        Some(proxy) => tunnel(&proxy, host, port).await,
+        None => TcpStream::connect((host, port))
+            .await
+            .map_err(|e| format!("Failed to connect: {}", e)),
+    }
+}
+
+/// A TCP stream to `host:port` through `proxy`.
+async fn tunnel(proxy: &Url, host: &str, port: u16) -> Result<TcpStream, String> {
+    let proxy_host = proxy.host_str().unwrap_or_default();
+    let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+    let username = urlencoding::decode(proxy.username())
+        .map_err(|e| format!("Invalid proxy username: {}", e))?
+        .into_owned();
+    let password = urlencoding::decode(proxy.password().unwrap_or_default())
+        .map_err(|e| format!("Invalid proxy password: {}", e))?
+        .into_owned();
+
+    if proxy.scheme() != "http" {
+        let proxy_addr = (proxy_host, proxy_port);
+        let stream = if username.is_empty() {
+            tokio_socks::tcp::Socks5Stream::connect(proxy_addr, (host, port)).await
+        } else {
+            tokio_socks::tcp::Socks5Stream::connect_with_password(proxy_addr, (host, port), &username, &password).await
+        };
+        return stream
+            .map(|s| s.into_inner())
+            .map_err(|e| format!("SOCKS5 proxy failed: {}", e));
+    }
+
+    let mut stream = TcpStream::connect((proxy_host, proxy_port))
+        .await
+        .map_err(|e| format!("Failed to reach proxy: {}", e))?;
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if !username.is_empty() {
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to proxy: {}", e))?;
+
+    let head = read_head(&mut stream)
+        .await
+        .map_err(|e| format!("Failed to read from proxy: {}", e))?;
+    let status_line = head.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(stream),
+        _ => Err(format!("Proxy refused CONNECT: {}", status_line)),
+    }
+}
+
+/// Read an HTTP response head byte by byte, so nothing after it is consumed.
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S) -> std::io::Result<String> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Response head too long"));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_proxy_matches_hosts_and_domain_suffixes() {
+        let list = "localhost, .corp.example, 10.0.0.1:9001";
+        assert!(bypasses(list, "localhost"));
+        assert!(bypasses(list, "beacon.corp.example"));
+        assert!(bypasses(list, "10.0.0.1"));
+        assert!(!bypasses(list, "notcorp.example"));
+        assert!(!bypasses(list, "beacon.pkcollection.net"));
+        assert!(bypasses("*", "anything"));
+    }
+}
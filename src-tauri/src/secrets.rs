@@ -0,0 +1,116 @@
+//! Secrets in the OS keychain: Windows Credential Manager, macOS Keychain, or the Secret
+//! Service on Linux.
+//!
+//! Entries live under the `cordia` service as `<account_id>/<name>`. The identity signing
+//! key (`identity-key`) and the history database key (`history-key`) are stored here, moved
+//! out of the account files on first use; the settings file key (`settings-key`) sits under
+//! the `app` pseudo-account. Callers fall back to file storage when no keychain is reachable
+//! (e.g. a Linux session without a Secret Service), so `Err` means "unavailable", not "lost".
+//!
+//! Secrets set from the frontend are namespaced (`app:<name>`) so the webview can't read the
+//! internal keys, and their names are listed in `secret_names.json` so deleting the account
+//! can remove them.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::account_manager::AccountManager;
+
+const SERVICE: &str = "cordia";
+pub const IDENTITY_KEY: &str = "identity-key";
+pub const HISTORY_KEY: &str = "history-key";
+/// Entries not tied to an account, such as `SETTINGS_KEY`, live under this id.
+pub const APP_ACCOUNT: &str = "app";
+pub const SETTINGS_KEY: &str = "settings-key";
+const APP_PREFIX: &str = "app:";
+
+fn entry(account_id: &str, name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, &format!("{}/{}", account_id, name))
+        .map_err(|e| format!("Failed to open keychain entry: {}", e))
+}
+
+/// The secret `name` for `account_id`; `Ok(None)` if the keychain has no such entry.
+pub fn get(account_id: &str, name: &str) -> Result<Option<String>, String> {
+    match entry(account_id, name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read from keychain: {}", e)),
+    }
+}
+
+pub fn set(account_id: &str, name: &str, value: &str) -> Result<(), String> {
+    entry(account_id, name)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to write to keychain: {}", e))
+}
+
+/// Remove the secret; missing entries are fine.
+pub fn delete(account_id: &str, name: &str) -> Result<(), String> {
+    match entry(account_id, name)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete from keychain: {}", e)),
+    }
+}
+
+fn names_path(account_id: &str) -> Result<PathBuf, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    Ok(account_manager.get_account_dir(account_id).join("secret_names.json"))
+}
+
+fn app_names(account_id: &str) -> BTreeSet<String> {
+    names_path(account_id)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_app_names(account_id: &str, names: &BTreeSet<String>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(names)
+        .map_err(|e| format!("Failed to serialize secret names: {}", e))?;
+    std::fs::write(names_path(account_id)?, json)
+        .map_err(|e| format!("Failed to save secret names: {}", e))
+}
+
+fn app_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Secret name cannot be empty".to_string());
+    }
+    Ok(format!("{}{}", APP_PREFIX, name))
+}
+
+/// A secret stored by the frontend (e.g. a beacon auth token).
+pub fn get_app_secret(account_id: &str, name: &str) -> Result<Option<String>, String> {
+    get(account_id, &app_name(name)?)
+}
+
+pub fn set_app_secret(account_id: &str, name: &str, value: &str) -> Result<(), String> {
+    let name = app_name(name)?;
+    set(account_id, &name, value)?;
+    let mut names = app_names(account_id);
+    if names.insert(name) {
+        save_app_names(account_id, &names)?;
+    }
+    Ok(())
+}
+
+pub fn delete_app_secret(account_id: &str, name: &str) -> Result<(), String> {
+    let name = app_name(name)?;
+    delete(account_id, &name)?;
+    let mut names = app_names(account_id);
+    if names.remove(&name) {
+        save_app_names(account_id, &names)?;
+    }
+    Ok(())
+}
+
+/// Remove every secret of `account_id`. Called before its directory is deleted.
+pub fn delete_account_secrets(account_id: &str) {
+    for name in [IDENTITY_KEY, HISTORY_KEY].into_iter().map(str::to_string).chain(app_names(account_id)) {
+        if let Err(e) = delete(account_id, &name) {
+            eprintln!("[Secrets] {}", e);
+        }
+    }
+}
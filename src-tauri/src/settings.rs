@@ -0,0 +1,381 @@
+//! App settings shared by the frontend and native modules (`settings.json`).
+//!
+//! One typed struct, loaded at startup and read with [`current`], so native code (the beacon
+//! monitor, downloads) doesn't wait for the webview to pass config in. Every change is
+//! written atomically and broadcast as `cordia:settings-changed`. The file carries a
+//! `schema_version`; older files are migrated on load, and a file from a newer version is
+//! left untouched. With `encrypted` set, the file holds only ciphertext under a key kept in
+//! the OS keychain.
+
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::account_manager::AccountManager;
+use crate::beacon_tls::BeaconTlsOptions;
+use crate::global_hotkeys::{Keybind, KeybindAction};
+use crate::proxy::ProxySettings;
+use crate::secrets;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrade steps; entry `i` turns a version `i` file into version `i + 1`.
+const MIGRATIONS: [fn(&mut Value); SCHEMA_VERSION as usize] = [
+    // 0 -> 1: files from before versioning only need the stamp.
+    |_| {},
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    /// Keep settings.json encrypted with a key from the OS keychain.
+    pub encrypted: bool,
+    pub beacon_monitor: BeaconMonitorSettings,
+    /// Keyed by account id.
+    pub accounts: BTreeMap<String, AccountSettings>,
+    /// System-wide keybinds. Push-to-talk lives in the audio settings instead.
+    pub keybinds: Vec<Keybind>,
+    pub idle: IdleSettings,
+    pub bandwidth: BandwidthSettings,
+    /// Saved beacon setups to switch between (e.g. the public beacon and a self-hosted one).
+    pub beacon_profiles: Vec<BeaconProfile>,
+    /// Id of the profile last switched to.
+    pub active_beacon_profile: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            encrypted: false,
+            beacon_monitor: BeaconMonitorSettings::default(),
+            accounts: BTreeMap::new(),
+            keybinds: Vec::new(),
+            idle: IdleSettings::default(),
+            bandwidth: BandwidthSettings::default(),
+            beacon_profiles: Vec::new(),
+            active_beacon_profile: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeaconMonitorSettings {
+    /// Seconds between checks while the beacon is healthy (5 to 600).
+    pub healthy_interval_secs: u64,
+    /// A check slower than this marks the beacon degraded (100 to 10000 ms).
+    pub slow_check_ms: u64,
+}
+
+impl Default for BeaconMonitorSettings {
+    fn default() -> Self {
+        Self { healthy_interval_secs: 30, slow_check_ms: 1500 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleSettings {
+    /// Go idle automatically when there's no keyboard or mouse input.
+    pub enabled: bool,
+    /// Minutes without input before going idle (1 to 240).
+    pub after_minutes: u32,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self { enabled: true, after_minutes: 10 }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthSettings {
+    /// Upper bound on the voice encoder's bitrate (6 to 510 kbps); `None` means no cap.
+    pub max_voice_kbps: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeaconProfile {
+    /// Assigned on save when empty.
+    pub id: String,
+    pub name: String,
+    /// ws:// or wss:// beacon URL.
+    pub url: String,
+    pub proxy: ProxySettings,
+    pub tls: BeaconTlsOptions,
+    /// Account to sign in as on switch; `None` keeps the current one.
+    pub account_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountSettings {
+    pub downloads: DownloadSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadSettings {
+    /// Where attachments are saved; `None` means the OS Downloads folder.
+    pub preferred_dir: Option<String>,
+    pub group_by_server: bool,
+    pub flat_mode: bool,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self { preferred_dir: None, group_by_server: true, flat_mode: false }
+    }
+}
+
+/// On-disk form of an encrypted settings file.
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    schema_version: u32,
+    /// Base64 of nonce + ciphertext of the settings JSON.
+    encrypted_data: String,
+}
+
+static SETTINGS: Mutex<Option<Settings>> = Mutex::new(None);
+/// The file on disk is from a newer Cordia; saving would throw its settings away.
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+fn settings_path() -> Result<PathBuf, String> {
+    let account_manager = AccountManager::new()
+        .map_err(|e| format!("Failed to access account manager: {}", e))?;
+    Ok(account_manager.get_base_data_dir().join("settings.json"))
+}
+
+/// Load settings. Called once at startup; problems are logged and leave the defaults.
+pub fn load_settings() {
+    let settings = match settings_path().and_then(|path| read(&path)) {
+        Ok(settings) => settings.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("[Settings] {}; using defaults", e);
+            Settings::default()
+        }
+    };
+    if let Ok(mut current) = SETTINGS.lock() {
+        *current = Some(settings);
+    }
+}
+
+pub fn current() -> Settings {
+    SETTINGS.lock().ok().and_then(|s| s.clone()).unwrap_or_default()
+}
+
+/// Settings for one account (defaults if it has none yet).
+pub fn account(account_id: &str) -> AccountSettings {
+    current().accounts.get(account_id).cloned().unwrap_or_default()
+}
+
+/// Validate, save and broadcast new settings. Returns them as stored.
+pub fn set_settings(app: &AppHandle, settings: Settings) -> Result<Settings, String> {
+    if READ_ONLY.load(Ordering::SeqCst) {
+        return Err("Settings were saved by a newer version of Cordia and can't be changed here".to_string());
+    }
+    let settings = normalize(settings);
+    write(&settings_path()?, &settings)?;
+    {
+        let mut current = SETTINGS.lock().map_err(|_| "Failed to lock settings".to_string())?;
+        *current = Some(settings.clone());
+    }
+    let _ = app.emit_all("cordia:settings-changed", &settings);
+    Ok(settings)
+}
+
+fn normalize(mut settings: Settings) -> Settings {
+    settings.schema_version = SCHEMA_VERSION;
+    let monitor = &mut settings.beacon_monitor;
+    monitor.healthy_interval_secs = monitor.healthy_interval_secs.clamp(5, 600);
+    monitor.slow_check_ms = monitor.slow_check_ms.clamp(100, 10_000);
+    settings.idle.after_minutes = settings.idle.after_minutes.clamp(1, 240);
+    settings.bandwidth.max_voice_kbps = settings.bandwidth.max_voice_kbps.map(|kbps| kbps.clamp(6, 510));
+    for account in settings.accounts.values_mut() {
+        let downloads = &mut account.downloads;
+        downloads.preferred_dir = downloads.preferred_dir.take().map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    }
+    for profile in &mut settings.beacon_profiles {
+        profile.name = profile.name.trim().to_string();
+        profile.url = profile.url.trim().to_string();
+        if profile.id.is_empty() {
+            profile.id = hex::encode(rand::random::<[u8; 8]>());
+        }
+        if profile.name.is_empty() {
+            profile.name = profile.url.clone();
+        }
+    }
+    let mut ids = BTreeSet::new();
+    settings.beacon_profiles.retain(|profile| !profile.url.is_empty() && ids.insert(profile.id.clone()));
+    if let Some(active) = &settings.active_beacon_profile {
+        if !settings.beacon_profiles.iter().any(|profile| &profile.id == active) {
+            settings.active_beacon_profile = None;
+        }
+    }
+    // Bindings aren't trimmed: " " is the Space key.
+    settings.keybinds.retain(|keybind| match keybind.action {
+        KeybindAction::PushToTalk => false,
+        KeybindAction::SwitchVoiceChannel => keybind.server_id.is_some() && !keybind.binding.is_empty(),
+        _ => !keybind.binding.is_empty(),
+    });
+    settings
+}
+
+/// `Ok(None)` when there is no file yet.
+fn read(path: &Path) -> Result<Option<Settings>, String> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read settings: {}", e)),
+    };
+    let mut value: Value = serde_json::from_str(&json).map_err(|e| format!("Failed to parse settings: {}", e))?;
+    if value.get("encrypted_data").is_some() {
+        let file: EncryptedFile = serde_json::from_value(value).map_err(|e| format!("Failed to parse settings: {}", e))?;
+        value = decrypt(&file.encrypted_data)?;
+    }
+    let value = migrate(value).inspect_err(|_| READ_ONLY.store(true, Ordering::SeqCst))?;
+    serde_json::from_value(value)
+        .map(|settings| Some(normalize(settings)))
+        .map_err(|e| format!("Failed to parse settings: {}", e))
+}
+
+fn migrate(mut value: Value) -> Result<Value, String> {
+    if !value.is_object() {
+        return Err("Settings file is not a JSON object".to_string());
+    }
+    let version = value.get("schema_version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SCHEMA_VERSION as u64 {
+        return Err(format!(
+            "Settings schema {} is newer than this version supports ({})",
+            version, SCHEMA_VERSION
+        ));
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        step(&mut value);
+    }
+    value["schema_version"] = SCHEMA_VERSION.into();
+    Ok(value)
+}
+
+fn write(path: &Path, settings: &Settings) -> Result<(), String> {
+    let json = if settings.encrypted {
+        let file = EncryptedFile {
+            schema_version: settings.schema_version,
+            encrypted_data: encrypt(settings)?,
+        };
+        serde_json::to_string_pretty(&file)
+    } else {
+        serde_json::to_string_pretty(settings)
+    }
+    .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    write_atomic(path, json.as_bytes())
+}
+
+/// Write to a temporary file next to `path`, then rename over it, so a crash mid-write
+/// leaves the old file intact.
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    let tmp = path.with_extension("json.tmp");
+    let mut file = std::fs::File::create(&tmp).map_err(|e| format!("Failed to save settings: {}", e))?;
+    file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+fn cipher(create: bool) -> Result<XChaCha20Poly1305, String> {
+    let key = match secrets::get(secrets::APP_ACCOUNT, secrets::SETTINGS_KEY)? {
+        Some(key) => hex::decode(key).map_err(|e| format!("Invalid settings key in keychain: {}", e))?,
+        None if create => {
+            let key = rand::random::<[u8; 32]>().to_vec();
+            secrets::set(secrets::APP_ACCOUNT, secrets::SETTINGS_KEY, &hex::encode(&key))?;
+            key
+        }
+        None => return Err("Settings key missing from keychain".to_string()),
+    };
+    XChaCha20Poly1305::new_from_slice(&key).map_err(|_| "Invalid settings key".to_string())
+}
+
+fn encrypt(settings: &Settings) -> Result<String, String> {
+    let plaintext = serde_json::to_vec(settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut sealed = nonce.to_vec();
+    sealed.extend(
+        cipher(true)?
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|_| "Failed to encrypt settings".to_string())?,
+    );
+    Ok(base64::engine::general_purpose::STANDARD.encode(sealed))
+}
+
+fn decrypt(encoded: &str) -> Result<Value, String> {
+    let sealed = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid encrypted settings: {}", e))?;
+    if sealed.len() < 24 {
+        return Err("Invalid encrypted settings".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(24);
+    let plaintext = cipher(false)?
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt settings".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Failed to parse settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_old_files_and_refuses_newer_ones() {
+        let old = serde_json::json!({ "beacon_monitor": { "healthy_interval_secs": 60 } });
+        let settings: Settings = serde_json::from_value(migrate(old).unwrap()).unwrap();
+        assert_eq!(settings.schema_version, SCHEMA_VERSION);
+        assert_eq!(settings.beacon_monitor.healthy_interval_secs, 60);
+        assert_eq!(settings.beacon_monitor.slow_check_ms, 1500);
+
+        let newer = serde_json::json!({ "schema_version": SCHEMA_VERSION + 1 });
+        assert!(migrate(newer).is_err());
+    }
+
+    #[test]
+    fn normalizes_beacon_profiles() {
+        let settings = normalize(Settings {
+            beacon_profiles: vec![
+                BeaconProfile { url: " wss://beacon.example.com ".to_string(), ..Default::default() },
+                BeaconProfile { id: "empty".to_string(), ..Default::default() },
+            ],
+            active_beacon_profile: Some("empty".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(settings.beacon_profiles.len(), 1);
+        let profile = &settings.beacon_profiles[0];
+        assert!(!profile.id.is_empty());
+        assert_eq!(profile.url, "wss://beacon.example.com");
+        assert_eq!(profile.name, profile.url);
+        assert_eq!(settings.active_beacon_profile, None);
+    }
+
+    #[test]
+    fn writes_atomically_and_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let mut settings = Settings::default();
+        settings.accounts.entry("alice".to_string()).or_default().downloads.flat_mode = true;
+        write(&path, &settings).unwrap();
+        assert!(!dir.path().join("settings.json.tmp").exists());
+        assert_eq!(read(&path).unwrap(), Some(settings));
+        assert_eq!(read(&dir.path().join("missing.json")).unwrap(), None);
+    }
+}
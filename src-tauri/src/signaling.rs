@@ -0,0 +1,628 @@
+//! Native signaling client: owns the WebSocket to the beacon.
+//!
+//! The connection lives on the Tauri async runtime, so it survives webview reloads and is
+//! not throttled with a hidden window. Dropped connections are retried with exponential
+//! backoff, and every registration (`Register`, `VoiceRegister`, `PresenceHello`, ...) is
+//! resent after a reconnect, since the beacon forgets a peer as soon as its socket closes.
+//! Server messages go to the frontend as `cordia:signaling-message`, connection changes as
+//! `cordia:signaling-state`. Messages sent with [`send_reliable`] go through the persistent
+//! `outbound_queue` and are confirmed with `cordia:signaling-delivered`. Each connection
+//! opens with the `protocol` handshake. Beacons that authenticate connections send an
+//! `AuthChallenge` first; it is signed with the account's identity key before any
+//! registration goes out. Frames are JSON text unless the beacon accepts the `cordia.msgpack`
+//! subprotocol (MessagePack), and either way large messages are compressed when the beacon
+//! accepts permessage-deflate (see `ws_deflate`).
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::protocol::Inbound;
+
+const BACKOFF_INITIAL: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// The beacon answers `Ping` with `Pong`; anything inbound counts as a sign of life.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(20);
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long to hold registrations back waiting for the beacon's first message.
+const AUTH_CHALLENGE_WAIT: Duration = Duration::from_secs(5);
+/// WebSocket subprotocols offered, preferred first. Beacons that predate them pick none (JSON).
+const SUBPROTOCOLS: [&str; 2] = ["cordia.msgpack", "cordia.json"];
+/// Signed together with the challenge nonce; must match the beacon's `AUTH_CONTEXT`.
+pub(crate) const AUTH_CONTEXT: &str = "cordia-signaling-auth:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalingState {
+    Connecting,
+    Connected,
+    Reconnecting,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalingStatus {
+    pub state: SignalingState,
+    pub url: Option<String>,
+    /// Failed attempts since the last successful connect.
+    pub attempt: u32,
+    /// While reconnecting: how long until the next attempt.
+    pub retry_in_ms: Option<u64>,
+    pub last_error: Option<String>,
+    /// What the beacon agreed to speak; `None` until its answer to the handshake arrives.
+    pub protocol: Option<crate::protocol::Negotiated>,
+}
+
+impl SignalingStatus {
+    fn disconnected() -> Self {
+        Self {
+            state: SignalingState::Disconnected,
+            url: None,
+            attempt: 0,
+            retry_in_ms: None,
+            last_error: None,
+            protocol: None,
+        }
+    }
+}
+
+enum Command {
+    Send(String),
+    /// Send whatever is in the outbound queue.
+    Flush,
+    /// Drop the connection and dial again at once (the network changed under it).
+    Reconnect,
+    Disconnect,
+}
+
+struct SignalingClient {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+static CLIENT: Mutex<Option<SignalingClient>> = Mutex::new(None);
+static STATUS: Mutex<Option<SignalingStatus>> = Mutex::new(None);
+/// Registrations resent on every (re)connect, in the order they were first made.
+static REGISTRATIONS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+/// Bumped per connect so a superseded connection task can't overwrite the status.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+/// Round trip of the last answered keepalive, in µs; 0 until one is answered.
+static LAST_RTT_US: AtomicU64 = AtomicU64::new(0);
+/// Successful reconnects since `connect`.
+static RECONNECTS: AtomicU64 = AtomicU64::new(0);
+
+/// Connect to the beacon at `url`, replacing any existing connection. Returns immediately;
+/// progress is reported on `cordia:signaling-state`.
+pub fn connect(app: AppHandle, url: &str) -> Result<(), String> {
+    let url = crate::beacon::websocket_url(url).map_err(|e| e.to_string())?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    LAST_RTT_US.store(0, Ordering::Relaxed);
+    RECONNECTS.store(0, Ordering::Relaxed);
+    {
+        let mut client = CLIENT.lock().map_err(|_| "Failed to lock signaling client".to_string())?;
+        if let Some(old) = client.replace(SignalingClient { commands: tx }) {
+            let _ = old.commands.send(Command::Disconnect);
+        }
+    }
+    tauri::async_runtime::spawn(run(app, url, rx, generation));
+    Ok(())
+}
+
+/// Close the connection and forget all registrations.
+pub fn disconnect() {
+    if let Ok(mut client) = CLIENT.lock() {
+        if let Some(client) = client.take() {
+            let _ = client.commands.send(Command::Disconnect);
+        }
+    }
+    if let Ok(mut registrations) = REGISTRATIONS.lock() {
+        registrations.clear();
+    }
+}
+
+/// Send one message. Fails while not connected; see [`send_reliable`] for queued delivery.
+pub fn send(message: &Value) -> Result<(), String> {
+    if status().state != SignalingState::Connected {
+        return Err("Signaling not connected".to_string());
+    }
+    crate::protocol::check_outbound(message)?;
+    send_text(serialize(message)?)
+}
+
+/// Queue `message` on disk and send it as soon as the connection allows, replaying it after
+/// a reconnect or restart if the beacon is unreachable. Returns the message's `dedup_id`.
+pub fn send_reliable(message: Value) -> Result<String, String> {
+    crate::protocol::check_outbound(&message)?;
+    let id = crate::outbound_queue::enqueue(message)?;
+    if let Ok(client) = CLIENT.lock() {
+        if let Some(client) = client.as_ref() {
+            let _ = client.commands.send(Command::Flush);
+        }
+    }
+    Ok(id)
+}
+
+/// Send `message` now (if connected) and again after every reconnect, until
+/// [`unregister`] is called with the same `key`. A new message under an existing key
+/// replaces the old one.
+pub fn register(key: &str, message: &Value) -> Result<(), String> {
+    crate::protocol::check_outbound(message)?;
+    let text = serialize(message)?;
+    {
+        let mut registrations = REGISTRATIONS
+            .lock()
+            .map_err(|_| "Failed to lock signaling registrations".to_string())?;
+        match registrations.iter_mut().find(|(k, _)| k == key) {
+            Some(existing) => existing.1 = text.clone(),
+            None => registrations.push((key.to_string(), text.clone())),
+        }
+    }
+    if status().state == SignalingState::Connected {
+        send_text(text)?;
+    }
+    Ok(())
+}
+
+/// Stop resending the registration under `key`. Telling the beacon (e.g. `VoiceUnregister`)
+/// is up to the caller.
+pub fn unregister(key: &str) {
+    if let Ok(mut registrations) = REGISTRATIONS.lock() {
+        registrations.retain(|(k, _)| k != key);
+    }
+}
+
+/// Reconnect now instead of waiting for the keepalive to notice a dead socket, and skip any
+/// backoff in progress. Registrations are resent as usual.
+pub fn reconnect_now() {
+    if let Ok(client) = CLIENT.lock() {
+        if let Some(client) = client.as_ref() {
+            let _ = client.commands.send(Command::Reconnect);
+        }
+    }
+}
+
+pub fn status() -> SignalingStatus {
+    STATUS
+        .lock()
+        .ok()
+        .and_then(|status| status.clone())
+        .unwrap_or_else(SignalingStatus::disconnected)
+}
+
+/// Keepalive round trip to the beacon, once one has been measured.
+pub fn rtt_ms() -> Option<f64> {
+    match LAST_RTT_US.load(Ordering::Relaxed) {
+        0 => None,
+        us => Some(us as f64 / 1000.0),
+    }
+}
+
+pub fn reconnects() -> u64 {
+    RECONNECTS.load(Ordering::Relaxed)
+}
+
+fn serialize(message: &Value) -> Result<String, String> {
+    serde_json::to_string(message).map_err(|e| format!("Failed to serialize signaling message: {}", e))
+}
+
+fn send_text(text: String) -> Result<(), String> {
+    let client = CLIENT.lock().map_err(|_| "Failed to lock signaling client".to_string())?;
+    let client = client.as_ref().ok_or_else(|| "Signaling not connected".to_string())?;
+    client
+        .commands
+        .send(Command::Send(text))
+        .map_err(|_| "Signaling connection closed".to_string())
+}
+
+fn registrations() -> Vec<String> {
+    REGISTRATIONS
+        .lock()
+        .map(|registrations| registrations.iter().map(|(_, text)| text.clone()).collect())
+        .unwrap_or_default()
+}
+
+fn set_status(app: &AppHandle, generation: u64, status: SignalingStatus) {
+    if GENERATION.load(Ordering::SeqCst) != generation {
+        return;
+    }
+    let status = SignalingStatus { protocol: crate::protocol::negotiated(), ..status };
+    if let Ok(mut current) = STATUS.lock() {
+        *current = Some(status.clone());
+    }
+    let _ = app.emit_all("cordia:signaling-state", status);
+}
+
+/// Delay before reconnect attempt `attempt` (1-based): doubling from 500 ms up to 30 s,
+/// with ±20% jitter so clients dropped together don't all come back at once.
+fn backoff(attempt: u32) -> Duration {
+    let base = BACKOFF_INITIAL.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(BACKOFF_MAX);
+    base.mul_f64(rand::thread_rng().gen_range(0.8..1.2))
+}
+
+/// Why a connection ended.
+enum SessionEnd {
+    Stopped,
+    Lost(String),
+    /// Asked to reconnect; dial again without backoff.
+    Redial,
+}
+
+async fn run(app: AppHandle, url: String, mut commands: mpsc::UnboundedReceiver<Command>, generation: u64) {
+    let mut attempt = 0u32;
+    let mut last_error = None;
+    let mut connected_before = false;
+    loop {
+        set_status(&app, generation, SignalingStatus {
+            state: if attempt == 0 && !connected_before { SignalingState::Connecting } else { SignalingState::Reconnecting },
+            url: Some(url.clone()),
+            attempt,
+            retry_in_ms: None,
+            last_error: last_error.clone(),
+            protocol: None,
+        });
+
+        match crate::proxy::connect_websocket_offering(&url, &SUBPROTOCOLS).await {
+            Ok((socket, subprotocol)) => {
+                let wire = Wire::from_subprotocol(subprotocol.as_deref());
+                attempt = 0;
+                if connected_before && GENERATION.load(Ordering::SeqCst) == generation {
+                    RECONNECTS.fetch_add(1, Ordering::Relaxed);
+                }
+                connected_before = true;
+                set_status(&app, generation, SignalingStatus {
+                    state: SignalingState::Connected,
+                    url: Some(url.clone()),
+                    attempt,
+                    retry_in_ms: None,
+                    last_error: None,
+                    protocol: None,
+                });
+                match session(&app, socket, &mut commands, generation, wire).await {
+                    SessionEnd::Stopped => break,
+                    SessionEnd::Lost(reason) => last_error = Some(reason),
+                    SessionEnd::Redial => {
+                        last_error = Some("Network changed".to_string());
+                        continue;
+                    }
+                }
+            }
+            Err(e) => last_error = Some(e),
+        }
+
+        attempt += 1;
+        let delay = backoff(attempt);
+        set_status(&app, generation, SignalingStatus {
+            state: SignalingState::Reconnecting,
+            url: Some(url.clone()),
+            attempt,
+            retry_in_ms: Some(delay.as_millis() as u64),
+            last_error: last_error.clone(),
+            protocol: None,
+        });
+
+        // Wait out the backoff; sends made meanwhile are dropped (registrations are resent,
+        // queued messages stay queued).
+        let retry = sleep(delay);
+        tokio::pin!(retry);
+        let stopped = loop {
+            tokio::select! {
+                _ = &mut retry => break false,
+                command = commands.recv() => match command {
+                    Some(Command::Send(_)) | Some(Command::Flush) => {}
+                    Some(Command::Reconnect) => {
+                        attempt = 0;
+                        break false;
+                    }
+                    Some(Command::Disconnect) | None => break true,
+                },
+            }
+        };
+        if stopped {
+            break;
+        }
+    }
+
+    set_status(&app, generation, SignalingStatus { url: Some(url), ..SignalingStatus::disconnected() });
+}
+
+async fn session<S>(
+    app: &AppHandle,
+    socket: tokio_tungstenite::WebSocketStream<S>,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+    generation: u64,
+    wire: Wire,
+) -> SessionEnd
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (mut sink, mut stream) = socket.split();
+
+    if let Err(e) = sink.send(frame(crate::protocol::hello(), wire)).await {
+        return SessionEnd::Lost(format!("Failed to send handshake: {}", e));
+    }
+
+    // Registrations and frontend sends wait until the beacon has either challenged us (and
+    // got its answer) or shown it doesn't, since it refuses Register from unauthenticated
+    // connections.
+    let mut ready = false;
+    let auth_wait = sleep(AUTH_CHALLENGE_WAIT);
+    tokio::pin!(auth_wait);
+
+    let mut keepalive = tokio::time::interval_at(Instant::now() + KEEPALIVE_INTERVAL, KEEPALIVE_INTERVAL);
+    let mut last_seen = Instant::now();
+    let mut ping_sent = None;
+    loop {
+        tokio::select! {
+            _ = &mut auth_wait, if !ready => {
+                if let Err(e) = start(app, &mut sink, wire).await {
+                    return SessionEnd::Lost(e);
+                }
+                ready = true;
+            }
+            command = commands.recv(), if ready => match command {
+                Some(Command::Send(text)) => {
+                    if let Err(e) = sink.send(frame(text, wire)).await {
+                        return SessionEnd::Lost(format!("Failed to send: {}", e));
+                    }
+                }
+                Some(Command::Flush) => {
+                    if let Err(e) = flush_queue(app, &mut sink, wire).await {
+                        return SessionEnd::Lost(e);
+                    }
+                }
+                // The old path is likely gone, so don't wait on a close handshake.
+                Some(Command::Reconnect) => return SessionEnd::Redial,
+                Some(Command::Disconnect) | None => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    return SessionEnd::Stopped;
+                }
+            },
+            message = stream.next() => {
+                last_seen = Instant::now();
+                match message {
+                    Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
+                        let text = match message {
+                            Message::Text(text) => text,
+                            other => match decode_binary(&other.into_data()) {
+                                Ok(text) => text,
+                                Err(e) => {
+                                    eprintln!("[Signaling] Ignoring malformed MessagePack frame: {}", e);
+                                    continue;
+                                }
+                            },
+                        };
+                        let received = forward(app, &text);
+                        if let Received::Challenge(nonce) = &received {
+                            match answer_challenge(nonce) {
+                                Ok(answer) => {
+                                    if let Err(e) = sink.send(frame(answer, wire)).await {
+                                        return SessionEnd::Lost(format!("Failed to send authentication: {}", e));
+                                    }
+                                }
+                                Err(e) => eprintln!("[Signaling] {}", e),
+                            }
+                        }
+                        // The challenge always comes first, so anything else means there is none.
+                        if !ready {
+                            if let Err(e) = start(app, &mut sink, wire).await {
+                                return SessionEnd::Lost(e);
+                            }
+                            ready = true;
+                        }
+                        match received {
+                            Received::Challenge(_) => {}
+                            Received::Pong => {
+                                if let Some(sent) = ping_sent.take() {
+                                    let rtt = Instant::now().duration_since(sent);
+                                    LAST_RTT_US.store((rtt.as_micros() as u64).max(1), Ordering::Relaxed);
+                                }
+                            }
+                            Received::Handshake(last_error) => {
+                                // Republish the connected status, now with the protocol in it.
+                                if let Some(status) = STATUS.lock().ok().and_then(|s| s.clone()) {
+                                    set_status(app, generation, SignalingStatus { last_error, ..status });
+                                }
+                            }
+                            Received::Other => {}
+                        }
+                    }
+                    Some(Ok(Message::Ping(data))) => {
+                        let _ = sink.send(Message::Pong(data)).await;
+                    }
+                    Some(Ok(Message::Close(frame))) => {
+                        let reason = frame.map(|f| f.reason.to_string()).filter(|r| !r.is_empty());
+                        return SessionEnd::Lost(reason.unwrap_or_else(|| "Beacon closed the connection".to_string()));
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return SessionEnd::Lost(format!("Connection error: {}", e)),
+                    None => return SessionEnd::Lost("Beacon closed the connection".to_string()),
+                }
+            }
+            _ = keepalive.tick() => {
+                if last_seen.elapsed() > KEEPALIVE_TIMEOUT {
+                    return SessionEnd::Lost("Beacon stopped responding".to_string());
+                }
+                if let Err(e) = sink.send(frame(r#"{"type":"Ping"}"#.to_string(), wire)).await {
+                    return SessionEnd::Lost(format!("Failed to send keepalive: {}", e));
+                }
+                ping_sent = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// How frames are encoded on a connection, per the subprotocol the beacon picked.
+#[derive(Clone, Copy, PartialEq)]
+enum Wire {
+    Json,
+    MessagePack,
+}
+
+impl Wire {
+    fn from_subprotocol(subprotocol: Option<&str>) -> Self {
+        match subprotocol {
+            Some(p) if p == SUBPROTOCOLS[0] => Wire::MessagePack,
+            _ => Wire::Json,
+        }
+    }
+}
+
+/// A JSON message as a frame in the session's encoding.
+fn frame(text: String, wire: Wire) -> Message {
+    if wire == Wire::Json {
+        return Message::Text(text);
+    }
+    match encode_binary(&text) {
+        Ok(bytes) => Message::Binary(bytes),
+        Err(e) => {
+            eprintln!("[Signaling] Sending JSON, MessagePack encoding failed: {}", e);
+            Message::Text(text)
+        }
+    }
+}
+
+fn encode_binary(text: &str) -> Result<Vec<u8>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    rmp_serde::to_vec_named(&value).map_err(|e| e.to_string())
+}
+
+/// A binary frame from the beacon, as JSON text.
+fn decode_binary(bytes: &[u8]) -> Result<String, String> {
+    let value: Value = rmp_serde::from_slice(bytes).map_err(|e| e.to_string())?;
+    serde_json::to_string(&value).map_err(|e| e.to_string())
+}
+
+/// Resend registrations and flush the outbound queue once the beacon will accept them.
+async fn start<K>(app: &AppHandle, sink: &mut K, wire: Wire) -> Result<(), String>
+where
+    K: futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    for text in registrations() {
+        sink.send(frame(text, wire))
+            .await
+            .map_err(|e| format!("Failed to resend registration: {}", e))?;
+    }
+    flush_queue(app, sink, wire).await
+}
+
+/// Sign the beacon's challenge with the current account's identity key. Also answers the
+/// challenge on the frontend's own beacon sockets.
+pub fn authenticate_message(nonce: &str) -> Result<Value, String> {
+    let manager = crate::identity::IdentityManager::new()
+        .map_err(|e| format!("Failed to answer auth challenge: {}", e))?;
+    let identity = manager
+        .load_identity()
+        .map_err(|e| format!("Failed to answer auth challenge: {}", e))?;
+    let signature = manager
+        .sign(format!("{}{}", AUTH_CONTEXT, nonce).as_bytes())
+        .map_err(|e| format!("Failed to sign auth challenge: {}", e))?;
+    Ok(serde_json::json!({
+        "type": "Authenticate",
+        "public_key": identity.public_key,
+        "signature": base64::engine::general_purpose::STANDARD.encode(signature.to_bytes()),
+    }))
+}
+
+fn answer_challenge(nonce: &str) -> Result<String, String> {
+    serialize(&authenticate_message(nonce)?)
+}
+
+/// Send queued messages oldest first, dropping each from the queue once written.
+async fn flush_queue<K>(app: &AppHandle, sink: &mut K, wire: Wire) -> Result<(), String>
+where
+    K: futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    for queued in crate::outbound_queue::pending() {
+        sink.send(frame(serialize(&queued.payload)?, wire))
+            .await
+            .map_err(|e| format!("Failed to send queued message: {}", e))?;
+        if let Err(e) = crate::outbound_queue::remove(&queued.id) {
+            eprintln!("[Signaling] {}", e);
+        }
+        let _ = app.emit_all("cordia:signaling-delivered", queued.id);
+    }
+    Ok(())
+}
+
+enum Received {
+    /// The beacon's `AuthChallenge` nonce.
+    Challenge(String),
+    Pong,
+    /// The handshake finished; carries the error to show if the beacon is incompatible.
+    Handshake(Option<String>),
+    Other,
+}
+
+/// Pass a server message on to the frontend. Keepalive replies, the handshake answer, the
+/// auth challenge and native file-transfer frames stay here.
+fn forward(app: &AppHandle, text: &str) -> Received {
+    let message = match serde_json::from_str::<Value>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            eprintln!("[Signaling] Ignoring malformed message: {}", e);
+            return Received::Other;
+        }
+    };
+    match crate::protocol::inbound(&message) {
+        Inbound::Negotiated(_) => return Received::Handshake(None),
+        Inbound::Incompatible(reason) => {
+            eprintln!("[Signaling] {}", reason);
+            return Received::Handshake(Some(reason));
+        }
+        Inbound::Unknown(kind) => {
+            eprintln!("[Signaling] Beacon sent {}, which this version doesn't know", kind);
+            let _ = app.emit_all("cordia:signaling-unsupported", kind);
+        }
+        Inbound::Forward => {}
+    }
+    match message.get("type").and_then(Value::as_str) {
+        Some("Pong") => return Received::Pong,
+        Some("AuthChallenge") => {
+            let nonce = message.get("nonce").and_then(Value::as_str).unwrap_or_default();
+            return Received::Challenge(nonce.to_string());
+        }
+        Some("AttachmentTransferSignalIncoming") => {
+            let from = message.get("from_user_id").and_then(Value::as_str).unwrap_or_default();
+            let signal = message.get("signal").and_then(Value::as_str).unwrap_or_default();
+            if crate::file_transfer::handle_frame(app, from, signal) {
+                return Received::Other;
+            }
+        }
+        _ => {}
+    }
+    let _ = app.emit_all("cordia:signaling-message", message);
+    Received::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_frames_round_trip() {
+        let offer = format!(r#"{{"type":"Offer","sdp":"{}"}}"#, "a=candidate:1 1 udp 1 10.0.0.2 5 typ host ".repeat(60));
+        for text in [r#"{"type":"Ping"}"#, offer.as_str()] {
+            let bytes = encode_binary(text).unwrap();
+            let back: Value = serde_json::from_str(&decode_binary(&bytes).unwrap()).unwrap();
+            assert_eq!(back, serde_json::from_str::<Value>(text).unwrap());
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let ms = |attempt| backoff(attempt).as_millis();
+        assert!((400..=600).contains(&ms(1)), "{}", ms(1));
+        assert!((1600..=2400).contains(&ms(3)), "{}", ms(3));
+        assert!((24_000..=36_000).contains(&ms(40)), "{}", ms(40));
+    }
+}
@@ -0,0 +1,354 @@
+//! WASAPI exclusive-mode capture (Windows only).
+//!
+//! cpal only opens WASAPI in shared mode, where the OS mixer adds a period or two of
+//! latency. Exclusive mode talks to the driver directly, but the device must accept our
+//! exact format, so we try the common ones at the pipeline's rate and give up (caller
+//! falls back to shared mode) if none are accepted.
+//!
+//! Output feeds the same raw ring as the cpal path: 10 ms mono f32 frames, dropped if
+//! the ring is full.
+
+use crate::audio_capture::RawFrame;
+use rtrb::Producer;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(windows)]
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+/// A running exclusive-mode capture. Dropping it stops capture and releases the device.
+pub struct ExclusiveCapture {
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    capture_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ExclusiveCapture {
+    /// Stop the device clock; the device stays open.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for ExclusiveCapture {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.capture_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// What the capture thread needs besides the ring and the readiness channel.
+#[cfg(windows)]
+struct StreamParams {
+    device_name: String,
+    sample_rate: u32,
+    dropped: &'static AtomicU64,
+    packets: &'static AtomicU64,
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+/// Open `device_name` (the WASAPI friendly name cpal reports) in exclusive mode at
+/// `sample_rate`. On failure the producer is handed back so the caller can fall back
+/// to a shared-mode stream on the same ring.
+#[cfg(windows)]
+pub fn start_exclusive_capture(
+    device_name: &str,
+    sample_rate: u32,
+    producer: Producer<RawFrame>,
+    dropped: &'static AtomicU64,
+    packets: &'static AtomicU64,
+) -> Result<ExclusiveCapture, (String, Producer<RawFrame>)> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let paused = Arc::new(AtomicBool::new(false));
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<(), (String, Producer<RawFrame>)>>();
+
+    let params = StreamParams {
+        device_name: device_name.to_string(),
+        sample_rate,
+        dropped,
+        packets,
+        stop: stop.clone(),
+        paused: paused.clone(),
+    };
+    let capture_thread = thread::spawn(move || {
+        imp::run_exclusive_capture(params, producer, ready_tx);
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Ok(ExclusiveCapture {
+            stop,
+            paused,
+            capture_thread: Some(capture_thread),
+        }),
+        Ok(Err(e)) => {
+            let _ = capture_thread.join();
+            Err(e)
+        }
+        // The thread only exits without reporting by panicking (taking the producer with it).
+        Err(_) => match capture_thread.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(()) => unreachable!("exclusive capture thread always reports readiness"),
+        },
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::StreamParams;
+    use crate::audio_capture::RawFrame;
+    use crate::audio_resample::FRAME_SAMPLES;
+    use rtrb::Producer;
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc;
+    use std::time::Instant;
+    use windows::core::GUID;
+    use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Media::Audio::{
+        eCapture, IAudioCaptureClient, IAudioClient, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator,
+        AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_E_BUFFER_SIZE_NOT_ALIGNED, AUDCLNT_SHAREMODE_EXCLUSIVE,
+        AUDCLNT_STREAMFLAGS_EVENTCALLBACK, DEVICE_STATE_ACTIVE, WAVEFORMATEX, WAVEFORMATEXTENSIBLE,
+        WAVEFORMATEXTENSIBLE_0,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ};
+    use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+    const KSDATAFORMAT_SUBTYPE_PCM: GUID = GUID::from_u128(0x00000001_0000_0010_8000_00aa00389b71);
+    const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: GUID = GUID::from_u128(0x00000003_0000_0010_8000_00aa00389b71);
+    const SPEAKER_FRONT_CENTER: u32 = 0x4;
+    const SPEAKER_STEREO: u32 = 0x1 | 0x2;
+
+    type ReadyTx = mpsc::Sender<Result<(), (String, Producer<RawFrame>)>>;
+
+    /// Sample encodings we can decode, in order of preference.
+    #[derive(Clone, Copy)]
+    enum SampleKind {
+        F32,
+        /// 24-bit audio in 32-bit containers (most pro interfaces).
+        I32,
+        I24,
+        I16,
+    }
+
+    impl SampleKind {
+        /// (container bits, valid bits, subformat)
+        fn layout(self) -> (u16, u16, GUID) {
+            match self {
+                SampleKind::F32 => (32, 32, KSDATAFORMAT_SUBTYPE_IEEE_FLOAT),
+                SampleKind::I32 => (32, 24, KSDATAFORMAT_SUBTYPE_PCM),
+                SampleKind::I24 => (24, 24, KSDATAFORMAT_SUBTYPE_PCM),
+                SampleKind::I16 => (16, 16, KSDATAFORMAT_SUBTYPE_PCM),
+            }
+        }
+
+        fn bytes(self) -> usize {
+            self.layout().0 as usize / 8
+        }
+
+        /// Decode one sample from its little-endian bytes.
+        fn decode(self, b: &[u8]) -> f32 {
+            match self {
+                SampleKind::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                SampleKind::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+                SampleKind::I24 => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+                SampleKind::I16 => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+            }
+        }
+    }
+
+    fn wave_format(kind: SampleKind, channels: u16, sample_rate: u32) -> WAVEFORMATEXTENSIBLE {
+        let (bits, valid_bits, sub_format) = kind.layout();
+        let block_align = channels * bits / 8;
+        WAVEFORMATEXTENSIBLE {
+            Format: WAVEFORMATEX {
+                wFormatTag: WAVE_FORMAT_EXTENSIBLE,
+                nChannels: channels,
+                nSamplesPerSec: sample_rate,
+                nAvgBytesPerSec: sample_rate * block_align as u32,
+                nBlockAlign: block_align,
+                wBitsPerSample: bits,
+                cbSize: (std::mem::size_of::<WAVEFORMATEXTENSIBLE>() - std::mem::size_of::<WAVEFORMATEX>()) as u16,
+            },
+            Samples: WAVEFORMATEXTENSIBLE_0 { wValidBitsPerSample: valid_bits },
+            dwChannelMask: if channels == 1 { SPEAKER_FRONT_CENTER } else { SPEAKER_STEREO },
+            SubFormat: sub_format,
+        }
+    }
+
+    /// Find the active capture endpoint whose friendly name matches cpal's device name.
+    unsafe fn find_endpoint(device_name: &str) -> Result<IMMDevice, String> {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+            .map_err(|e| format!("Failed to create device enumerator: {}", e))?;
+        let endpoints = enumerator
+            .EnumAudioEndpoints(eCapture, DEVICE_STATE_ACTIVE)
+            .map_err(|e| format!("Failed to enumerate capture endpoints: {}", e))?;
+        let count = endpoints.GetCount().map_err(|e| e.to_string())?;
+        for i in 0..count {
+            let Ok(device) = endpoints.Item(i) else { continue };
+            let Ok(store) = device.OpenPropertyStore(STGM_READ) else { continue };
+            let Ok(name) = store.GetValue(&PKEY_Device_FriendlyName) else { continue };
+            if name.to_string() == device_name {
+                return Ok(device);
+            }
+        }
+        Err(format!("WASAPI endpoint not found: {}", device_name))
+    }
+
+    /// Activate and initialize an exclusive event-driven client in the first accepted format.
+    unsafe fn open_exclusive(
+        device: &IMMDevice,
+        sample_rate: u32,
+    ) -> Result<(IAudioClient, SampleKind, usize, HANDLE), String> {
+        let mut client: IAudioClient = device
+            .Activate(CLSCTX_ALL, None)
+            .map_err(|e| format!("Failed to activate audio client: {}", e))?;
+
+        let mut chosen = None;
+        'search: for kind in [SampleKind::F32, SampleKind::I32, SampleKind::I24, SampleKind::I16] {
+            for channels in [1u16, 2] {
+                let format = wave_format(kind, channels, sample_rate);
+                let hr = client.IsFormatSupported(
+                    AUDCLNT_SHAREMODE_EXCLUSIVE,
+                    &format as *const WAVEFORMATEXTENSIBLE as *const WAVEFORMATEX,
+                    None,
+                );
+                // S_FALSE means "not this, but something close"; only exact matches count.
+                if hr.0 == 0 {
+                    chosen = Some((kind, channels, format));
+                    break 'search;
+                }
+            }
+        }
+        let (kind, channels, format) =
+            chosen.ok_or_else(|| format!("Device rejects all exclusive-mode formats at {} Hz", sample_rate))?;
+        let format_ptr = &format as *const WAVEFORMATEXTENSIBLE as *const WAVEFORMATEX;
+
+        let mut period: i64 = 0;
+        client
+            .GetDevicePeriod(None, Some(&mut period))
+            .map_err(|e| format!("Failed to get device period: {}", e))?;
+
+        let flags = AUDCLNT_STREAMFLAGS_EVENTCALLBACK;
+        if let Err(e) = client.Initialize(AUDCLNT_SHAREMODE_EXCLUSIVE, flags, period, period, format_ptr, None) {
+            if e.code() != AUDCLNT_E_BUFFER_SIZE_NOT_ALIGNED {
+                return Err(format!("Failed to initialize exclusive mode: {}", e));
+            }
+            // Documented dance: align the period to the buffer the driver wants, re-activate, retry.
+            let frames = client.GetBufferSize().map_err(|e| e.to_string())?;
+            period = (10_000_000.0 * frames as f64 / sample_rate as f64 + 0.5) as i64;
+            client = device
+                .Activate(CLSCTX_ALL, None)
+                .map_err(|e| format!("Failed to activate audio client: {}", e))?;
+            client
+                .Initialize(AUDCLNT_SHAREMODE_EXCLUSIVE, flags, period, period, format_ptr, None)
+                .map_err(|e| format!("Failed to initialize exclusive mode: {}", e))?;
+        }
+
+        let event = CreateEventW(None, false, false, None).map_err(|e| e.to_string())?;
+        client.SetEventHandle(event).map_err(|e| e.to_string())?;
+        Ok((client, kind, channels as usize, event))
+    }
+
+    pub fn run_exclusive_capture(params: StreamParams, mut producer: Producer<RawFrame>, ready_tx: ReadyTx) {
+        let StreamParams { device_name, sample_rate, dropped, packets, stop, paused } = params;
+        let setup = unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            (|| -> Result<(IAudioClient, IAudioCaptureClient, SampleKind, usize, HANDLE), String> {
+                let device = find_endpoint(&device_name)?;
+                let (client, kind, channels, event) = open_exclusive(&device, sample_rate)?;
+                let capture: IAudioCaptureClient = client.GetService().map_err(|e| e.to_string())?;
+                client
+                    .Start()
+                    .map_err(|e| format!("Failed to start exclusive capture: {}", e))?;
+                Ok((client, capture, kind, channels, event))
+            })()
+        };
+        let (client, capture, kind, channels, event) = match setup {
+            Ok(parts) => {
+                let _ = ready_tx.send(Ok(()));
+                parts
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err((e, producer)));
+                return;
+            }
+        };
+
+        let sample_bytes = kind.bytes();
+        let frame_bytes = sample_bytes * channels;
+        let scale = 1.0 / channels as f32;
+        let mut frame = [0.0f32; FRAME_SAMPLES];
+        let mut pos = 0usize;
+        let mut running = true;
+
+        while !stop.load(Ordering::SeqCst) {
+            unsafe {
+                let want_running = !paused.load(Ordering::SeqCst);
+                if want_running != running {
+                    running = want_running;
+                    let _ = if running { client.Start() } else { client.Stop() };
+                }
+                // Wake at least every 100 ms to check the stop/pause flags.
+                WaitForSingleObject(event, 100);
+                if !running {
+                    continue;
+                }
+                while let Ok(packet) = capture.GetNextPacketSize() {
+                    if packet == 0 {
+                        break;
+                    }
+                    let mut data: *mut u8 = std::ptr::null_mut();
+                    let mut frames: u32 = 0;
+                    let mut flags: u32 = 0;
+                    if capture.GetBuffer(&mut data, &mut frames, &mut flags, None, None).is_err() {
+                        break;
+                    }
+                    packets.fetch_add(1, Ordering::Relaxed);
+                    let silent = flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0;
+                    let bytes = std::slice::from_raw_parts(data as *const u8, frames as usize * frame_bytes);
+                    for sample_frame in bytes.chunks_exact(frame_bytes) {
+                        frame[pos] = if silent {
+                            0.0
+                        } else {
+                            sample_frame.chunks_exact(sample_bytes).map(|b| kind.decode(b)).sum::<f32>() * scale
+                        };
+                        pos += 1;
+                        if pos == FRAME_SAMPLES {
+                            pos = 0;
+                            let raw = RawFrame { samples: frame, len: FRAME_SAMPLES, captured_at: Instant::now() };
+                            if producer.push(raw).is_err() {
+                                dropped.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                    let _ = capture.ReleaseBuffer(frames);
+                    crate::audio_capture::wake_processing();
+                }
+            }
+        }
+
+        unsafe {
+            let _ = client.Stop();
+            let _ = CloseHandle(event);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn start_exclusive_capture(
+    _device_name: &str,
+    _sample_rate: u32,
+    producer: Producer<RawFrame>,
+    _dropped: &'static AtomicU64,
+    _packets: &'static AtomicU64,
+) -> Result<ExclusiveCapture, (String, Producer<RawFrame>)> {
+    Err(("Exclusive-mode capture is only supported on Windows".to_string(), producer))
+}
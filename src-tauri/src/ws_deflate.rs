@@ -0,0 +1,403 @@
+//! permessage-deflate (RFC 7692) for the native signaling connection.
+//!
+//! tungstenite doesn't implement extensions, so [`DeflateStream`] sits between it and the
+//! (TLS) stream and rewrites frames: messages the beacon sent with RSV1 set are inflated
+//! before tungstenite reads them, and data frames at least `threshold` bytes are deflated
+//! on the way out. The offer asks for no context takeover from the beacon and this side
+//! never keeps a window between messages either, so no compressor state outlives a message.
+
+use flate2::{Compress, Decompress, FlushCompress, FlushDecompress, Status};
+use std::io::{self, Cursor};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::{Frame, FrameHeader};
+
+const EXTENSION: &str = "permessage-deflate";
+/// Sent as `Sec-WebSocket-Extensions`.
+pub const OFFER: &str = "permessage-deflate; server_no_context_takeover; client_max_window_bits";
+/// Empty stored block that ends every compressed message; stripped on the wire.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+/// Largest single frame buffered on its way through (tungstenite's default frame limit).
+const MAX_FRAME: usize = 16 << 20;
+/// Messages smaller than this go out uncompressed.
+const THRESHOLD: usize = 1024;
+/// Largest inflated message accepted from the beacon.
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+/// Compression settings for one connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Deflate {
+    /// Messages smaller than this go out uncompressed.
+    threshold: usize,
+    /// Deflate level, 0-9.
+    level: u32,
+    /// Window this side compresses with (`client_max_window_bits`), 9-15.
+    send_window_bits: u8,
+    /// Window the beacon compresses with (`server_max_window_bits`), 9-15.
+    receive_window_bits: u8,
+    /// Largest compressed message accepted from the beacon, before and after inflating.
+    max_message: usize,
+}
+
+impl Deflate {
+    /// The settings the beacon agreed to in its `Sec-WebSocket-Extensions` response to
+    /// [`OFFER`]. Anything this side can't honour fails the handshake, as RFC 7692 requires.
+    pub fn from_response(response: &str) -> Result<Deflate, String> {
+        let mut params = response.split(';').map(str::trim);
+        if params.next() != Some(EXTENSION) || response.contains(',') {
+            return Err(format!("Unsupported WebSocket extension: {}", response));
+        }
+        let mut deflate = Deflate {
+            threshold: THRESHOLD,
+            level: 6,
+            send_window_bits: 15,
+            receive_window_bits: 15,
+            max_message: MAX_MESSAGE,
+        };
+        let mut no_context_takeover = false;
+        let mut seen = Vec::new();
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            if seen.contains(&name) {
+                return Err(format!("Duplicate permessage-deflate parameter: {}", name));
+            }
+            seen.push(name);
+            let bits = value.and_then(|v| v.parse::<u8>().ok()).filter(|bits| (8..=15).contains(bits));
+            match (name, value, bits) {
+                ("server_no_context_takeover", None, _) => no_context_takeover = true,
+                ("client_no_context_takeover", None, _) => {}
+                // zlib can't inflate with an 8-bit raw window, but a 9-bit one covers it.
+                ("server_max_window_bits", Some(_), Some(bits)) => deflate.receive_window_bits = bits.max(9),
+                ("client_max_window_bits", Some(_), Some(bits)) if bits >= 9 => deflate.send_window_bits = bits,
+                _ => return Err(format!("Unsupported permessage-deflate parameter: {}", param)),
+            }
+        }
+        if !no_context_takeover {
+            return Err("Beacon ignored server_no_context_takeover".to_string());
+        }
+        Ok(deflate)
+    }
+}
+
+/// Deflate one message from a fresh window, without the trailing empty block.
+fn compress_message(data: &[u8], level: u32, window_bits: u8) -> io::Result<Vec<u8>> {
+    let mut compress = Compress::new_with_window_bits(flate2::Compression::new(level), false, window_bits);
+    let mut out = Vec::with_capacity(data.len() / 2 + 64);
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity());
+        }
+        let consumed = compress.total_in() as usize;
+        compress
+            .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+            .map_err(invalid)?;
+        if compress.total_in() as usize == data.len() && out.len() < out.capacity() {
+            break;
+        }
+    }
+    if out.ends_with(&TRAILER) {
+        out.truncate(out.len() - TRAILER.len());
+    }
+    Ok(out)
+}
+
+/// Inflate one message from a fresh window, refusing more than `max` bytes of output.
+fn inflate_message(data: &[u8], window_bits: u8, max: usize) -> io::Result<Vec<u8>> {
+    let mut decompress = Decompress::new_with_window_bits(false, window_bits);
+    let input = [data, &TRAILER].concat();
+    let mut out = Vec::with_capacity(data.len().saturating_mul(4).min(max) + 1);
+    loop {
+        if out.len() == out.capacity() {
+            let room = out.capacity().max(1024).min(max + 1 - out.len());
+            out.reserve_exact(room);
+        }
+        let (consumed, produced) = (decompress.total_in(), decompress.total_out());
+        let status = decompress
+            .decompress_vec(&input[consumed as usize..], &mut out, FlushDecompress::Sync)
+            .map_err(invalid)?;
+        if out.len() > max {
+            return Err(invalid("Inflated message too large"));
+        }
+        let input_done = decompress.total_in() as usize == input.len();
+        if status == Status::StreamEnd || (input_done && out.len() < out.capacity()) {
+            return Ok(out);
+        }
+        if decompress.total_in() == consumed && decompress.total_out() == produced && out.len() < out.capacity() {
+            return Err(invalid("Truncated compressed message"));
+        }
+    }
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// A whole frame as it appeared on the wire.
+struct WireFrame {
+    header: FrameHeader,
+    bytes: Vec<u8>,
+    payload_at: usize,
+}
+
+impl WireFrame {
+    /// Cut the first frame off `buf`, if all of it is there.
+    fn take(buf: &mut Vec<u8>) -> io::Result<Option<Self>> {
+        let mut cursor = Cursor::new(&buf[..]);
+        let (header, len) = match FrameHeader::parse(&mut cursor).map_err(invalid)? {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        };
+        if len > MAX_FRAME as u64 {
+            return Err(invalid("Frame too large"));
+        }
+        let payload_at = cursor.position() as usize;
+        let end = payload_at + len as usize;
+        if buf.len() < end {
+            return Ok(None);
+        }
+        let bytes = buf.drain(..end).collect();
+        Ok(Some(Self { header, bytes, payload_at }))
+    }
+
+    fn payload_len(&self) -> usize {
+        self.bytes.len() - self.payload_at
+    }
+
+    /// The payload with the mask removed.
+    fn payload(&self) -> Vec<u8> {
+        let mut payload = self.bytes[self.payload_at..].to_vec();
+        if let Some(mask) = self.header.mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        payload
+    }
+}
+
+/// The connection as tungstenite sees it. With no [`Deflate`] settings it passes bytes
+/// through untouched.
+pub struct DeflateStream<S> {
+    inner: S,
+    deflate: Option<Deflate>,
+    /// Bytes read from `inner` that don't make up a whole frame yet.
+    read_buf: Vec<u8>,
+    /// Header and payload so far of a fragmented compressed message.
+    fragmented: Option<(FrameHeader, Vec<u8>)>,
+    /// Frames ready for tungstenite, from `readable_pos` on.
+    readable: Vec<u8>,
+    readable_pos: usize,
+    /// Bytes from tungstenite that don't make up a whole frame yet.
+    write_buf: Vec<u8>,
+    /// Frames waiting to be written to `inner`, from `writable_pos` on.
+    writable: Vec<u8>,
+    writable_pos: usize,
+}
+
+impl<S> DeflateStream<S> {
+    pub fn new(inner: S, deflate: Option<Deflate>) -> Self {
+        Self {
+            inner,
+            deflate,
+            read_buf: Vec::new(),
+            fragmented: None,
+            readable: Vec::new(),
+            readable_pos: 0,
+            write_buf: Vec::new(),
+            writable: Vec::new(),
+            writable_pos: 0,
+        }
+    }
+
+    /// Move one frame from `read_buf` to `readable`, inflating compressed messages. Returns
+    /// false when `read_buf` doesn't hold a whole frame.
+    fn read_frame(&mut self, deflate: Deflate) -> io::Result<bool> {
+        let frame = match WireFrame::take(&mut self.read_buf)? {
+            Some(frame) => frame,
+            None => return Ok(false),
+        };
+        let header = &frame.header;
+        match (header.opcode, self.fragmented.take()) {
+            (OpCode::Data(Data::Continue), Some((first, mut payload))) => {
+                if header.rsv1 {
+                    return Err(invalid("RSV1 set on a continuation frame"));
+                }
+                if payload.len() + frame.payload_len() > deflate.max_message {
+                    return Err(invalid("Compressed message too large"));
+                }
+                payload.extend_from_slice(&frame.payload());
+                if header.is_final {
+                    self.push_inflated(first, &payload, deflate)?;
+                } else {
+                    self.fragmented = Some((first, payload));
+                }
+            }
+            (OpCode::Data(_), Some(_)) => return Err(invalid("Expected a continuation frame")),
+            (OpCode::Data(Data::Text | Data::Binary), None) if header.rsv1 => {
+                if frame.payload_len() > deflate.max_message {
+                    return Err(invalid("Compressed message too large"));
+                }
+                if header.is_final {
+                    self.push_inflated(header.clone(), &frame.payload(), deflate)?;
+                } else {
+                    self.fragmented = Some((header.clone(), frame.payload()));
+                }
+            }
+            // Control frames may arrive between fragments; stray RSV1 bits are left for
+            // tungstenite to reject.
+            (_, fragmented) => {
+                self.fragmented = fragmented;
+                self.readable.extend_from_slice(&frame.bytes);
+            }
+        }
+        Ok(true)
+    }
+
+    fn push_inflated(&mut self, first: FrameHeader, compressed: &[u8], deflate: Deflate) -> io::Result<()> {
+        let payload = inflate_message(compressed, deflate.receive_window_bits, deflate.max_message)?;
+        let header = FrameHeader { is_final: true, rsv1: false, ..first };
+        Frame::from_payload(header, payload).format(&mut self.readable).map_err(invalid)
+    }
+
+    /// Move one frame from `write_buf` to `writable`, deflating whole data messages at or
+    /// over the threshold. Returns false when `write_buf` doesn't hold a whole frame.
+    fn write_frame(&mut self, deflate: Deflate) -> io::Result<bool> {
+        let frame = match WireFrame::take(&mut self.write_buf)? {
+            Some(frame) => frame,
+            None => return Ok(false),
+        };
+        let header = &frame.header;
+        let whole_message = header.is_final && matches!(header.opcode, OpCode::Data(Data::Text | Data::Binary));
+        if whole_message && !header.rsv1 && frame.payload_len() >= deflate.threshold {
+            let payload = frame.payload();
+            let compressed = compress_message(&payload, deflate.level, deflate.send_window_bits)?;
+            if compressed.len() < payload.len() {
+                let header = FrameHeader { rsv1: true, ..header.clone() };
+                Frame::from_payload(header, compressed).format(&mut self.writable).map_err(invalid)?;
+                return Ok(true);
+            }
+        }
+        self.writable.extend_from_slice(&frame.bytes);
+        Ok(true)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// Write out `writable`; pending while `inner` can't take all of it.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.writable_pos < self.writable.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.writable[self.writable_pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.writable_pos += n;
+        }
+        self.writable.clear();
+        self.writable_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(deflate) = this.deflate else {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        };
+        loop {
+            if this.readable_pos < this.readable.len() {
+                let n = buf.remaining().min(this.readable.len() - this.readable_pos);
+                buf.put_slice(&this.readable[this.readable_pos..this.readable_pos + n]);
+                this.readable_pos += n;
+                if this.readable_pos == this.readable.len() {
+                    this.readable.clear();
+                    this.readable_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            if this.read_frame(deflate)? {
+                continue;
+            }
+            let mut chunk = [0u8; 8192];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            if chunk.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            this.read_buf.extend_from_slice(chunk.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(deflate) = this.deflate else {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        };
+        // Hold new frames back until the last batch is out, so a stalled connection can't
+        // make the app buffer without bound.
+        ready!(this.poll_drain(cx))?;
+        this.write_buf.extend_from_slice(buf);
+        while this.write_frame(deflate)? {}
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::WebSocketStream;
+
+    #[test]
+    fn accepts_only_responses_it_can_honour() {
+        let deflate = Deflate::from_response(
+            "permessage-deflate; server_no_context_takeover; client_no_context_takeover; \
+             server_max_window_bits=12; client_max_window_bits=10",
+        )
+        .unwrap();
+        assert_eq!((deflate.send_window_bits, deflate.receive_window_bits), (10, 12));
+
+        assert!(Deflate::from_response("permessage-deflate").is_err());
+        assert!(Deflate::from_response("permessage-deflate; server_no_context_takeover; client_max_window_bits=8").is_err());
+        assert!(Deflate::from_response("permessage-deflate; server_no_context_takeover; mystery").is_err());
+        assert!(Deflate::from_response("x-webkit-deflate-frame").is_err());
+    }
+
+    #[tokio::test]
+    async fn compresses_large_messages_both_ways() {
+        let (client_io, beacon_io) = tokio::io::duplex(1 << 16);
+        let deflate = Deflate::from_response("permessage-deflate; server_no_context_takeover; server_max_window_bits=12")
+            .unwrap();
+        let mut client =
+            WebSocketStream::from_raw_socket(DeflateStream::new(client_io, Some(deflate)), Role::Client, None).await;
+        let mut beacon =
+            WebSocketStream::from_raw_socket(DeflateStream::new(beacon_io, Some(deflate)), Role::Server, None).await;
+
+        let hint = "{\"encrypted_state\":\"AAAA\"}".repeat(100);
+        client.send(Message::Text(hint.clone())).await.unwrap();
+        assert_eq!(beacon.next().await.unwrap().unwrap(), Message::Text(hint.clone()));
+        beacon.send(Message::Binary(hint.clone().into_bytes())).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), Message::Binary(hint.into_bytes()));
+    }
+}
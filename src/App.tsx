@@ -1,4 +1,4 @@
-import { BrowserRouter as Router, Routes, Route, Navigate, useLocation } from 'react-router-dom'
+import { BrowserRouter as Router, Routes, Route, Navigate, useLocation, useNavigate } from 'react-router-dom'
 import { useEffect, useRef } from 'react'
 import { IdentityProvider, useIdentity } from './contexts/IdentityContext'
 import { AccountProvider, useAccount } from './contexts/AccountContext'
@@ -38,6 +38,7 @@ import ServerListPage from './pages/ServerListPage'
 import ServerViewPage from './pages/ServerViewPage'
 import SettingsPage from './pages/SettingsPage'
 import TransfersPage from './pages/TransfersPage'
+import { listenInviteLinks, listenKeybinds } from './lib/tauri'
 
 function ProtectedRoute({ children }: { children: React.ReactNode }) {
   const { identity } = useIdentity()
@@ -106,6 +107,39 @@ function MediaPreviewRoot() {
 
 function AppLayout() {
   const { isNativeVideoFullscreen } = useVideoFullscreen()
+  const navigate = useNavigate()
+
+  // A clicked cordia:// link: the server list picks it up and opens the join flow
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listenInviteLinks(() => navigate('/home')).then((fn) => {
+      if (cancelled) fn()
+      else unlisten = fn
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [navigate])
+
+  // Switch-voice-channel keybind: open the server and let its page join voice
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listenKeybinds((event) => {
+      if (event.action === 'switch_voice_channel' && event.server_id) {
+        navigate(`/home/${event.server_id}`, { state: { joinVoice: true } })
+      }
+    }).then((fn) => {
+      if (cancelled) fn()
+      else unlisten = fn
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [navigate])
   return (
     <div className="flex flex-col h-screen overflow-hidden border-2 border-foreground/20 relative">
       {!isNativeVideoFullscreen && <WindowResizeHandles />}
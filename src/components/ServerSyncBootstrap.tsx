@@ -6,7 +6,8 @@ import { useVoicePresence } from '../contexts/VoicePresenceContext'
 import { useBeacon } from '../contexts/BeaconContext'
 import { useProfile } from '../contexts/ProfileContext'
 import { useRemoteProfiles } from '../contexts/RemoteProfilesContext'
-import { fetchAndImportServerHintOpaque, listServers, listFriends } from '../lib/tauri'
+import { applyServerMemberLeaves, fetchAndImportServerHintOpaque, listServers, listFriends, listenIdle } from '../lib/tauri'
+import { authenticateBeaconSocket, type SignalingMessage } from '../lib/signaling'
 
 /**
  * Pull latest server metadata (members/chats) from the beacon after login.
@@ -34,10 +35,29 @@ export function ServerSyncBootstrap() {
   const lastConnectStartAtRef = useRef<number>(0)
   const subscribedSigningPubkeysRef = useRef<Set<string>>(new Set())
   const activeSigningPubkeyRef = useRef<string | null>(null)
+  // While idle we advertise no active server, so others see "online" instead of "active"
+  const isIdleRef = useRef(false)
   const pendingOutboundRef = useRef<string[]>([])
   const profilePushRef = useRef({ profile, identity, accountInfoMap, currentAccountId })
   profilePushRef.current = { profile, identity, accountInfoMap, currentAccountId }
 
+  // OS idle detection (native): re-announce presence when we go idle or come back
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listenIdle(({ idle }) => {
+      isIdleRef.current = idle
+      window.dispatchEvent(new CustomEvent('cordia:idle-changed', { detail: { idle } }))
+    }).then((u) => {
+      if (cancelled) u()
+      else unlisten = u
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [])
+
   useEffect(() => {
     // Only run when logged in + beacon URL exists
     if (!sessionLoaded || !currentAccountId) return
@@ -58,6 +78,8 @@ export function ServerSyncBootstrap() {
           if (cancelled) return
           try {
             await fetchAndImportServerHintOpaque(beaconUrl, s.signing_pubkey)
+            // Owner: apply leaves members posted while we were away (no-op for everyone else).
+            if (s.has_signing_key) await applyServerMemberLeaves(beaconUrl, s.signing_pubkey)
           } catch (e) {
             console.warn('[ServerSyncBootstrap] Failed to sync server hint:', e)
           }
@@ -248,7 +270,7 @@ export function ServerSyncBootstrap() {
               type: 'PresenceHello',
               user_id: identity.user_id,
               signing_pubkeys: signingPubkeys,
-              active_signing_pubkey: activeSigningPubkeyRef.current,
+              active_signing_pubkey: isIdleRef.current ? null : activeSigningPubkeyRef.current,
               friend_user_ids,
             })
           )
@@ -286,42 +308,47 @@ export function ServerSyncBootstrap() {
         }
       }
 
-      ws.onopen = async () => {
+      let handleAuth: ((message: SignalingMessage) => boolean) | null = null
+
+      ws.onopen = () => {
         reconnectAttemptRef.current = 0
-        try {
-          const servers = await listServers()
-          const nextSet = new Set<string>()
-          for (const s of servers) {
-            nextSet.add(s.signing_pubkey)
-            // Register subscription for this signing_pubkey (beacon uses it for ServerHintUpdated broadcasts)
-            ws.send(
-              JSON.stringify({
-                type: 'Register',
-                server_id: s.id,
-                peer_id: `server-sync:${currentAccountId}:${s.id}`,
-                signing_pubkey: s.signing_pubkey,
-              })
-            )
-          }
-          subscribedSigningPubkeysRef.current = nextSet
-          // Announce presence after subscriptions are set up
-          await sendPresenceHello('ws.onopen')
-          await sendProfileAnnounce()
-          await sendProfileHello()
-          await sendProfilePush()
-
-          // Flush any messages queued while reconnecting.
-          if (pendingOutboundRef.current.length > 0) {
-            const queued = pendingOutboundRef.current
-            pendingOutboundRef.current = []
-            for (const payload of queued) {
-              if (ws.readyState !== WebSocket.OPEN) break
-              ws.send(payload)
+        // Subscriptions go out once the beacon's AuthChallenge is answered.
+        handleAuth = authenticateBeaconSocket(ws, async () => {
+          try {
+            const servers = await listServers()
+            const nextSet = new Set<string>()
+            for (const s of servers) {
+              nextSet.add(s.signing_pubkey)
+              // Register subscription for this signing_pubkey (beacon uses it for ServerHintUpdated broadcasts)
+              ws.send(
+                JSON.stringify({
+                  type: 'Register',
+                  server_id: s.id,
+                  peer_id: `server-sync:${currentAccountId}:${s.id}`,
+                  signing_pubkey: s.signing_pubkey,
+                })
+              )
             }
+            subscribedSigningPubkeysRef.current = nextSet
+            // Announce presence after subscriptions are set up
+            await sendPresenceHello('ws.onopen')
+            await sendProfileAnnounce()
+            await sendProfileHello()
+            await sendProfilePush()
+
+            // Flush any messages queued while reconnecting.
+            if (pendingOutboundRef.current.length > 0) {
+              const queued = pendingOutboundRef.current
+              pendingOutboundRef.current = []
+              for (const payload of queued) {
+                if (ws.readyState !== WebSocket.OPEN) break
+                ws.send(payload)
+              }
+            }
+          } catch (e) {
+            console.warn('[ServerSyncBootstrap] Failed to subscribe servers over WS:', e)
           }
-        } catch (e) {
-          console.warn('[ServerSyncBootstrap] Failed to subscribe servers over WS:', e)
-        }
+        })
 
         // Heartbeat: keep idle WS alive and detect dead peers.
         if (heartbeatTimerRef.current != null) window.clearInterval(heartbeatTimerRef.current)
@@ -355,10 +382,23 @@ export function ServerSyncBootstrap() {
         try {
           const msg = JSON.parse(event.data)
           lastMessageAtRef.current = Date.now()
+          if (handleAuth?.(msg)) return
           if (msg.type === 'Pong') {
             lastPongAtRef.current = Date.now()
             return
           }
+          if (msg.type === 'ServerMemberLeft') {
+            // Only the owner can act on it: drop the member and republish the hint, which
+            // reaches everyone else as ServerHintUpdated.
+            try {
+              if (await applyServerMemberLeaves(beaconUrl, msg.signing_pubkey)) {
+                window.dispatchEvent(new Event('cordia:servers-updated'))
+              }
+            } catch (e) {
+              console.warn('[ServerSyncBootstrap] Failed to apply member leave:', e)
+            }
+            return
+          }
           if (msg.type === 'ServerHintUpdated') {
             const signingPubkey: string = msg.signing_pubkey
 
@@ -730,7 +770,7 @@ export function ServerSyncBootstrap() {
         const detail = (ev as CustomEvent<{ signing_pubkey?: string | null }>).detail
         const next = detail?.signing_pubkey ?? null
         activeSigningPubkeyRef.current = next
-        if (!identity?.user_id) return
+        if (!identity?.user_id || isIdleRef.current) return
         sendOrQueue({
           type: 'PresenceActive',
           user_id: identity.user_id,
@@ -738,6 +778,16 @@ export function ServerSyncBootstrap() {
         })
       }
 
+      const onIdleChanged = (ev: Event) => {
+        const idle = (ev as CustomEvent<{ idle?: boolean }>).detail?.idle === true
+        if (!identity?.user_id) return
+        sendOrQueue({
+          type: 'PresenceActive',
+          user_id: identity.user_id,
+          active_signing_pubkey: idle ? null : activeSigningPubkeyRef.current,
+        })
+      }
+
       const onSendEphemeralChat = (ev: Event) => {
         const detail = (ev as CustomEvent<{
           signing_pubkey?: string
@@ -948,6 +998,7 @@ export function ServerSyncBootstrap() {
       }
       window.addEventListener('cordia:profile-updated', onProfileUpdated as any)
       window.addEventListener('cordia:active-server-changed', onActiveServerChanged as any)
+      window.addEventListener('cordia:idle-changed', onIdleChanged)
       window.addEventListener('cordia:send-ephemeral-chat', onSendEphemeralChat as EventListener)
       window.addEventListener('cordia:send-ephemeral-receipt', onSendEphemeralReceipt as EventListener)
       window.addEventListener('cordia:send-friend-mutual-check', onSendFriendMutualCheck as EventListener)
@@ -967,6 +1018,7 @@ export function ServerSyncBootstrap() {
         window.removeEventListener('cordia:friends-updated', onFriendsUpdated)
         window.removeEventListener('cordia:profile-updated', onProfileUpdated as any)
         window.removeEventListener('cordia:active-server-changed', onActiveServerChanged as any)
+        window.removeEventListener('cordia:idle-changed', onIdleChanged)
         window.removeEventListener('cordia:send-ephemeral-chat', onSendEphemeralChat as EventListener)
         window.removeEventListener('cordia:send-ephemeral-receipt', onSendEphemeralReceipt as EventListener)
         window.removeEventListener('cordia:send-friend-mutual-check', onSendFriendMutualCheck as EventListener)
@@ -1,7 +1,8 @@
 import { useEffect, useState } from 'react'
-import { User, Volume2, Info, Wifi, Database, FolderDown, Palette } from 'lucide-react'
+import { User, Volume2, Keyboard, Info, Wifi, Database, FolderDown, Palette } from 'lucide-react'
 import { AccountSettings } from '../pages/settings/AccountSettings'
 import { AudioSettingsPage } from '../pages/settings/AudioSettings'
+import { KeybindSettings } from '../pages/settings/KeybindSettings'
 import { InfoExportSettings } from '../pages/settings/InfoExportSettings'
 import { ConnectionSettings } from '../pages/settings/ConnectionSettings'
 import { MessagesSettings } from '../pages/settings/MessagesSettings'
@@ -23,6 +24,7 @@ export function SettingsPanel({ initialTab = 'account' }: Props) {
   const pages: { id: SettingsTab; label: string; icon: typeof User }[] = [
     { id: 'account', label: 'Account', icon: User },
     { id: 'audio', label: 'Audio', icon: Volume2 },
+    { id: 'keybinds', label: 'Keybinds', icon: Keyboard },
     { id: 'connections', label: 'Connections', icon: Wifi },
     { id: 'messages', label: 'Messages', icon: Database },
     { id: 'downloads', label: 'Downloads', icon: FolderDown },
@@ -68,6 +70,7 @@ export function SettingsPanel({ initialTab = 'account' }: Props) {
             <div key={activeTab} className="animate-fade-in">
               {activeTab === 'account' && <AccountSettings />}
               {activeTab === 'audio' && <AudioSettingsPage />}
+              {activeTab === 'keybinds' && <KeybindSettings />}
               {activeTab === 'connections' && <ConnectionSettings />}
               {activeTab === 'messages' && <MessagesSettings />}
               {activeTab === 'downloads' && <DownloadsSettings />}
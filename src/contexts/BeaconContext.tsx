@@ -1,12 +1,30 @@
 import { createContext, useContext, useState, useEffect, useCallback, ReactNode, useRef, useMemo } from 'react'
-import { checkBeacon, getBeaconUrl } from '../lib/tauri'
+import { listen } from '@tauri-apps/api/event'
+import {
+  checkBeacon,
+  getBeaconInfo,
+  getBeaconUrl,
+  listenBeaconProfileSwitched,
+  startBeaconMonitor,
+  stopBeaconMonitor,
+  type BeaconInfo,
+  type BeaconStatusEvent,
+} from '../lib/tauri'
 import { useAccount } from './AccountContext'
 
 export type BeaconStatus = 'connected' | 'disconnected' | 'checking'
 
 interface BeaconContextType {
   status: BeaconStatus
+  /** Reachable, but the last check failed or was slow (status stays 'connected'). */
+  degraded: boolean
+  /** Why the beacon is degraded or disconnected, from the background monitor. */
+  statusReason: string | null
   beaconUrl: string
+  /** Capabilities of the current beacon; null until fetched or if unreachable. */
+  info: BeaconInfo | null
+  /** Whether the beacon advertises `feature`. Beacons without a feature list are assumed to have everything they had before /api/info. */
+  hasFeature: (feature: string) => boolean
   checkHealth: () => Promise<void>
   reloadUrl: () => Promise<void>
 }
@@ -15,7 +33,10 @@ const BeaconContext = createContext<BeaconContextType | null>(null)
 
 export function BeaconProvider({ children }: { children: ReactNode }) {
   const [status, setStatus] = useState<BeaconStatus>('checking')
+  const [degraded, setDegraded] = useState(false)
+  const [statusReason, setStatusReason] = useState<string | null>(null)
   const [beaconUrl, setBeaconUrl] = useState<string>('')
+  const [info, setInfo] = useState<BeaconInfo | null>(null)
   const healthCheckInFlightRef = useRef(false)
   const { currentAccountId } = useAccount()
 
@@ -35,25 +56,6 @@ export function BeaconProvider({ children }: { children: ReactNode }) {
     }
   }, [beaconUrl])
 
-  const checkHealthSilent = useCallback(async () => {
-    if (healthCheckInFlightRef.current) return
-    healthCheckInFlightRef.current = true
-
-    try {
-      const isHealthy = await checkBeacon(beaconUrl || undefined)
-      setStatus(prev => {
-        const next: BeaconStatus = isHealthy ? 'connected' : 'disconnected'
-        if (prev === 'checking') return prev
-        return next
-      })
-    } catch (error) {
-      console.error('Beacon health check failed:', error)
-      setStatus(prev => (prev === 'checking' ? prev : 'disconnected'))
-    } finally {
-      healthCheckInFlightRef.current = false
-    }
-  }, [beaconUrl])
-
   const reloadUrl = useCallback(async () => {
     try {
       const url = await getBeaconUrl()
@@ -73,24 +75,82 @@ export function BeaconProvider({ children }: { children: ReactNode }) {
     }
   }, [currentAccountId, reloadUrl])
 
+  // Switching profiles tore the old connections down natively; pick up the new URL
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listenBeaconProfileSwitched(() => reloadUrl()).then((u) => {
+      if (cancelled) u()
+      else unlisten = u
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [reloadUrl])
+
+  // Background health monitor in Rust: only status changes come through
   useEffect(() => {
     if (!beaconUrl) return
 
-    checkHealth()
+    setStatus('checking')
+    setDegraded(false)
+    setStatusReason(null)
+
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listen<BeaconStatusEvent>('cordia:beacon-status', (event) => {
+      const e = event.payload
+      if (e.url !== beaconUrl) return
+      setStatus(e.status === 'Disconnected' ? 'disconnected' : 'connected')
+      setDegraded(e.status === 'Degraded')
+      setStatusReason(e.reason)
+    }).then((fn) => {
+      if (cancelled) fn()
+      else unlisten = fn
+    })
+    startBeaconMonitor(beaconUrl).catch((error) => {
+      console.error('Failed to start beacon monitor:', error)
+      checkHealth()
+    })
+
+    return () => {
+      cancelled = true
+      unlisten?.()
+      stopBeaconMonitor().catch(() => {})
+    }
+  }, [beaconUrl, checkHealth])
 
-    const interval = setInterval(checkHealthSilent, 30000)
+  // Refresh capabilities whenever the beacon (re)appears
+  useEffect(() => {
+    if (status !== 'connected' || !beaconUrl) return
+    let cancelled = false
+    getBeaconInfo(beaconUrl)
+      .then((i) => { if (!cancelled) setInfo(i) })
+      .catch((error) => {
+        console.warn('Failed to fetch beacon info:', error)
+        if (!cancelled) setInfo(null)
+      })
+    return () => { cancelled = true }
+  }, [status, beaconUrl])
 
-    return () => clearInterval(interval)
-  }, [beaconUrl, checkHealth, checkHealthSilent])
+  const hasFeature = useCallback(
+    (feature: string) => !info || info.protocol_version === 0 || info.features.includes(feature),
+    [info]
+  )
 
   const value = useMemo<BeaconContextType>(
     () => ({
       status,
+      degraded,
+      statusReason,
       beaconUrl,
+      info,
+      hasFeature,
       checkHealth,
       reloadUrl,
     }),
-    [status, beaconUrl, checkHealth, reloadUrl]
+    [status, degraded, statusReason, beaconUrl, info, hasFeature, checkHealth, reloadUrl]
   );
 
   return (
@@ -1298,7 +1298,7 @@ export function EphemeralMessagesProvider({ children }: { children: ReactNode })
     uploadSessionWakeRef.current.clear()
     loadedBucketsRef.current.clear()
     setSettingsBySigningPubkey({})
-    setDownloadSettingsState(getDownloadSettings(currentAccountId))
+    getDownloadSettings(currentAccountId).then(setDownloadSettingsState).catch(() => {})
 
     if (!currentAccountId) {
       setMessagesByBucket({})
@@ -1961,7 +1961,7 @@ export function EphemeralMessagesProvider({ children }: { children: ReactNode })
         detail: { to_user_id: fromUserId, request_id: requestId, accepted: true },
       }))
 
-      const pc = createPeerConnection()
+      const pc = await createPeerConnection()
       transferPeersRef.current.set(requestId, pc)
       pc.onicecandidate = (ev) => {
         if (!ev.candidate) return
@@ -2065,7 +2065,7 @@ export function EphemeralMessagesProvider({ children }: { children: ReactNode })
         return
       }
       upsertTransfer(requestId, (prev) => ({ ...(prev as AttachmentTransferState), status: 'connecting' }))
-      const pc = createPeerConnection()
+      const pc = await createPeerConnection()
       transferPeersRef.current.set(requestId, pc)
       pc.onicecandidate = (ev) => {
         if (!ev.candidate) return
@@ -9,7 +9,8 @@ import {
   attachAudioTrack,
   createRemoteAudioElement,
   closePeerConnection,
-  isLastPathP2P
+  isLastPathP2P,
+  getInboundAudioStats
 } from '../lib/webrtc'
 import {
   initReceiverAudio,
@@ -26,7 +27,16 @@ import { useProfile } from './ProfileContext'
 import { useAccount } from './AccountContext'
 import { useRemoteProfiles } from './RemoteProfilesContext'
 import { RemoteAudioAnalyzer } from '../lib/remoteAudioAnalyzer'
-import { loadAudioSettings } from '../lib/tauri'
+import { listenTransmissionState } from '../lib/nativeAudio'
+import {
+  acquireSleepInhibitor,
+  listenKeybinds,
+  listenNetworkChanged,
+  loadAudioSettings,
+  releaseSleepInhibitor,
+  reportPeerConnectionStats,
+} from '../lib/tauri'
+import { authenticateBeaconSocket, type SignalingMessage } from '../lib/signaling'
 
 /**
  * WebRTC Context for peer-to-peer voice communication.
@@ -45,6 +55,8 @@ import { loadAudioSettings } from '../lib/tauri'
 const SIGNALING_KEEPALIVE_INTERVAL_MS = 25000
 // Let ICE pool warm before first offer (improves candidate selection; 100–200ms typical)
 const ICE_OFFER_WARMUP_MS = 100
+// How often peer stats are sent to the native connection report (it drops them after 10s)
+const STATS_REPORT_INTERVAL_MS = 5000
 
 export type PeerConnectionState = RTCPeerConnectionState
 
@@ -124,12 +136,15 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
   const currentUserIdRef = useRef<string | null>(null)   // Stable identity
   const currentSigningPubkeyRef = useRef<string | null>(null)  // House signing pubkey
   const outputDeviceRef = useRef<string | null>(null)
+  const isDeafenedRef = useRef(false)  // Set by the global deafen keybind
+  const iceRestartOnOpenRef = useRef(false)  // Network changed: restart ICE once signaling is back
   const isInVoiceRef = useRef<boolean>(false)            // For reconnect logic
   const peersRef = useRef<Map<string, PeerConnectionInfo>>(new Map())  // For message handlers
   const isRebuildingAudioRef = useRef<boolean>(false)    // Guard against concurrent rebuilds
   const keepaliveIntervalRef = useRef<ReturnType<typeof setInterval> | null>(null)  // Signaling keepalive
   const signalingConnectedRef = useRef<boolean>(false)   // Track signaling state separately from media
   const localAudioAnalyzerRef = useRef<RemoteAudioAnalyzer | null>(null)  // For self-speaking detection
+  const localTransmissionUnlistenRef = useRef<(() => void) | null>(null)  // Native gate state (self-speaking)
   const cleanedPeersRef = useRef<Set<string>>(new Set())  // Track cleaned peers to prevent double cleanup
   const profileP2PRef = useRef<{ user_id: string; display_name: string; real_name: string | null; show_real_name: boolean; rev: number; account_created_at: string | null } | null>(null)
   // Per-peer recovery: ICE disconnected → try restart after delay; failed → delayed cleanup so restart can recover
@@ -163,6 +178,44 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
     peersRef.current = peers
   }, [peers])
 
+  // Feed per-peer loss/jitter/RTT to the native connection stats while in voice
+  useEffect(() => {
+    if (!isInVoice) return
+    const interval = setInterval(() => {
+      peersRef.current.forEach((info, peerId) => {
+        getInboundAudioStats(info.connection).then((stats) => {
+          if (stats) reportPeerConnectionStats(peerId, stats).catch(() => {})
+        })
+      })
+    }, STATS_REPORT_INTERVAL_MS)
+    return () => clearInterval(interval)
+  }, [isInVoice])
+
+  // Global mute/deafen keybinds: the native side has already switched the mic, so follow its state
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listenKeybinds((event) => {
+      if (event.muted !== null) {
+        setIsLocalMuted(event.muted)
+        inputLevelMeterRef.current?.setTransmissionMuted(event.muted)
+      }
+      if (event.deafened !== null) {
+        isDeafenedRef.current = event.deafened
+        peersRef.current.forEach((info) => {
+          if (info.audioElement) info.audioElement.muted = event.deafened ?? false
+        })
+      }
+    }).then((u) => {
+      if (cancelled) u()
+      else unlisten = u
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [])
+
   // Init receiver-side per-user audio prefs from storage
   useEffect(() => {
     initReceiverAudio()
@@ -555,7 +608,7 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
   }, [cleanupPeerConnection])
 
   const createPeerConnectionForPeer = useCallback(async (remotePeerId: string, remoteUserId: string): Promise<RTCPeerConnection> => {
-    const pc = createPeerConnection()
+    const pc = await createPeerConnection()
     const roomId = currentRoomRef.current
 
     // P2P profile channel: receive peer profile (account_created_at, etc.) without using signaling server
@@ -715,6 +768,7 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
         const prefs = getReceiverPrefs(remoteUserId)
         const audioElement = createRemoteAudioElement(remoteStream, outputDeviceRef.current || undefined)
         audioElement.volume = prefs.muted ? 0 : Math.max(0, Math.min(2, prefs.volume))
+        audioElement.muted = isDeafenedRef.current
         console.log(`[Media] Created audio element for peer=${remotePeerId}`)
 
         let audioAnalyzer: RemoteAudioAnalyzer | null = null
@@ -1119,6 +1173,7 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
     const wsUrl = base.endsWith('/ws') ? base : base + '/ws'
     const ws = new WebSocket(wsUrl)
     wsRef.current = ws
+    let handleAuth: ((message: SignalingMessage) => boolean) | null = null
 
     ws.onopen = () => {
       console.log('[Signal] Connected to signaling server')
@@ -1127,20 +1182,33 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
       // Start keepalive to prevent idle disconnect
       startKeepalive()
 
-      // Register for voice in the chat (beacon expects server_id and chat_id)
-      const registerMessage = {
-        type: 'VoiceRegister',
-        server_id: currentHouseRef.current,
-        chat_id: currentRoomRef.current,
-        peer_id: currentPeerIdRef.current,
-        user_id: currentUserIdRef.current,
-        signing_pubkey: currentSigningPubkeyRef.current
-      }
-      ws.send(JSON.stringify(registerMessage))
-      console.log(`[Signal] Sent VoiceRegister: peer=${currentPeerIdRef.current}`)
+      // The beacon refuses VoiceRegister until its AuthChallenge is answered.
+      handleAuth = authenticateBeaconSocket(ws, () => {
+        // Register for voice in the chat (beacon expects server_id and chat_id)
+        const registerMessage = {
+          type: 'VoiceRegister',
+          server_id: currentHouseRef.current,
+          chat_id: currentRoomRef.current,
+          peer_id: currentPeerIdRef.current,
+          user_id: currentUserIdRef.current,
+          signing_pubkey: currentSigningPubkeyRef.current
+        }
+        ws.send(JSON.stringify(registerMessage))
+        console.log(`[Signal] Sent VoiceRegister: peer=${currentPeerIdRef.current}`)
+
+        if (iceRestartOnOpenRef.current) {
+          iceRestartOnOpenRef.current = false
+          peersRef.current.forEach((_, peerId) => tryIceRestart(peerId))
+        }
+      })
     }
 
     ws.onmessage = (event) => {
+      try {
+        if (handleAuth?.(JSON.parse(event.data))) return
+      } catch {
+        // Not JSON; left to handleSignalingMessage
+      }
       handleSignalingMessage(event.data)
     }
 
@@ -1171,7 +1239,35 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
         }, 2000)
       }
     }
-  }, [beaconUrl, handleSignalingMessage, startKeepalive, stopKeepalive])
+  }, [beaconUrl, handleSignalingMessage, startKeepalive, stopKeepalive, tryIceRestart])
+
+  // Network changed (Wi-Fi <-> Ethernet, VPN): the old socket and ICE paths are likely dead, so
+  // redial signaling now and restart ICE with every peer instead of waiting for timeouts
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    listenNetworkChanged((change) => {
+      if (!isInVoiceRef.current || !currentRoomRef.current) return
+      console.log(`[Network] Changed (default route ${change.default_route}) - reconnecting signaling, restarting ICE`)
+      iceRestartOnOpenRef.current = true
+      const old = wsRef.current
+      if (old) {
+        old.onclose = null  // Reconnecting here, not after the usual delay
+        old.close()
+        wsRef.current = null
+      }
+      signalingConnectedRef.current = false
+      stopKeepalive()
+      connectToSignaling()
+    }).then((u) => {
+      if (cancelled) u()
+      else unlisten = u
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [connectToSignaling, stopKeepalive])
 
   const joinVoice = useCallback(async (roomId: string, houseId: string, userId: string, signingPubkey: string) => {
     if (isInVoice) {
@@ -1214,18 +1310,29 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
     console.log('[Voice] ✓ Stream track ID:', audioTracks[0].id)
     console.log(`[Media] Transmission track ready: readyState=${audioTracks[0].readyState}`)
 
-    // Create analyzer for local voice activity detection (self-speaking indicator)
-    try {
-      localAudioAnalyzerRef.current = new RemoteAudioAnalyzer(
-        transmissionStream,
-        (isSpeaking: boolean) => {
-          // Update speaking state for self
-          setUserSpeaking(userId, isSpeaking)
-        }
-      )
-      console.log('[Media] Created local audio analyzer for self-speaking detection')
-    } catch (error) {
-      console.warn('[Media] Failed to create local audio analyzer:', error)
+    // Self-speaking indicator. With native capture the DSP reports its gate directly, so the
+    // indicator matches what peers hear; otherwise analyze the transmission stream.
+    if (meter.isNativeCapture()) {
+      try {
+        localTransmissionUnlistenRef.current = await listenTransmissionState((transmitting) => {
+          setUserSpeaking(userId, transmitting)
+        })
+      } catch (error) {
+        console.warn('[Media] Failed to listen for transmission state:', error)
+      }
+    } else {
+      try {
+        localAudioAnalyzerRef.current = new RemoteAudioAnalyzer(
+          transmissionStream,
+          (isSpeaking: boolean) => {
+            // Update speaking state for self
+            setUserSpeaking(userId, isSpeaking)
+          }
+        )
+        console.log('[Media] Created local audio analyzer for self-speaking detection')
+      } catch (error) {
+        console.warn('[Media] Failed to create local audio analyzer:', error)
+      }
     }
 
     // Generate EPHEMERAL peer_id for this session
@@ -1246,6 +1353,9 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
     setCurrentRoomId(roomId)
     isInVoiceRef.current = true
 
+    // Don't let the laptop suspend mid-call
+    acquireSleepInhibitor().catch((e) => console.warn('[Voice] Could not prevent sleep:', e))
+
     // Clean up any stale presence data for this user in this house before joining
     // This ensures we don't have duplicate entries if the user was in a different room
     voicePresence.removeUserFromAllRooms(signingPubkey, userId)
@@ -1259,10 +1369,12 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
   const leaveVoiceInternal = useCallback(() => {
     console.log('[Voice] Leaving voice - tearing down signaling and media')
 
-    // Stop local audio analyzer
-    if (localAudioAnalyzerRef.current) {
-      localAudioAnalyzerRef.current.stop()
+    // Stop local audio analyzer / transmission listener
+    if (localAudioAnalyzerRef.current || localTransmissionUnlistenRef.current) {
+      localAudioAnalyzerRef.current?.stop()
       localAudioAnalyzerRef.current = null
+      localTransmissionUnlistenRef.current?.()
+      localTransmissionUnlistenRef.current = null
       // Clear self-speaking state
       if (currentUserIdRef.current) {
         setUserSpeaking(currentUserIdRef.current, false)
@@ -1318,6 +1430,7 @@ export function WebRTCProvider({ children }: { children: ReactNode }) {
     setCurrentRoomId(null)
     setIsLocalMuted(false)
     isInVoiceRef.current = false
+    releaseSleepInhibitor().catch(() => {})
 
     console.log('[Voice] Leave complete')
   }, [cleanupPeerConnection, stopKeepalive, setUserSpeaking])
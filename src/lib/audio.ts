@@ -95,6 +95,8 @@ export class InputLevelMeter {
   private currentGain: number = 0 // Current smoothed gain for threshold gating
   private useVoiceActivity: boolean = true // false for push-to-talk (raw audio always on)
   private isPttKeyPressed: boolean = false // PTT key state
+  private pttOverridesVoice: boolean = false // hybrid mode: PTT forces the voice gate open
+  private openMic: boolean = false // open mic mode: always transmit, no gate
   private isTransmissionMuted: boolean = false // Manual mute (overrides VAD/PTT)
   
   // Use native capture by default
@@ -219,9 +221,12 @@ export class InputLevelMeter {
     }
   }
 
-  async setInputMode(mode: 'voice_activity' | 'push_to_talk') {
+  async setInputMode(mode: 'voice_activity' | 'level_gate' | 'push_to_talk' | 'hybrid' | 'open_mic') {
     const wasVAD = this.useVoiceActivity
-    this.useVoiceActivity = mode === 'voice_activity'
+    // The browser path has no speech detector; every gated mode uses its level threshold
+    this.useVoiceActivity = mode !== 'push_to_talk'
+    this.pttOverridesVoice = mode === 'hybrid'
+    this.openMic = mode === 'open_mic'
     // Reset gain when switching modes
     if (!this.useVoiceActivity) {
       this.currentGain = 0 // PTT starts muted until key pressed
@@ -284,6 +289,11 @@ export class InputLevelMeter {
     }
   }
 
+  /** True when the mic runs through native capture, so gating happens in the Rust DSP. */
+  isNativeCapture(): boolean {
+    return this.useNativeCapture && this.nativeCapture !== null
+  }
+
   /**
    * Get the audio stream for WebRTC transmission.
    * With native capture, this stream already has VAD/PTT gating applied in Rust DSP.
@@ -369,9 +379,15 @@ export class InputLevelMeter {
       if (this.isTransmissionMuted) {
         this.monitoringGain.gain.setValueAtTime(0, this.audioContext!.currentTime)
         this.currentGain = 0 // Reset envelope when muted
+      } else if (this.openMic) {
+        // Open mic: no gating at all
+        this.monitoringGain.gain.setValueAtTime(1, this.audioContext!.currentTime)
       } else if (this.useVoiceActivity) {
-        // Voice Activity mode: gate based on threshold
+        // Voice Activity mode: gate based on threshold (hybrid: PTT held opens it at once)
         const targetGain = level >= this.threshold ? 1.0 : 0.0
+        if (this.pttOverridesVoice && this.isPttKeyPressed) {
+          this.currentGain = 1.0
+        }
 
         // Smooth envelope with exponential attack/release
         const attackCoeff = 0.3   // Fast attack (30% blend per frame)
@@ -1,3 +1,5 @@
+import { getSettings, setSettings } from './settings'
+
 export interface DownloadSettings {
   preferred_dir: string | null
   group_by_server: boolean
@@ -30,27 +32,40 @@ export function normalizeDownloadSettings(
   }
 }
 
-export function getDownloadSettings(accountId: string | null): DownloadSettings {
+/** Settings saved in localStorage before they moved to the native settings store. */
+function takeLegacyDownloadSettings(accountId: string): DownloadSettings | null {
   try {
     const raw = window.localStorage.getItem(keyFor(accountId))
-    if (!raw) return { ...DEFAULT_DOWNLOAD_SETTINGS }
+    if (!raw) return null
+    window.localStorage.removeItem(keyFor(accountId))
     return normalizeDownloadSettings(JSON.parse(raw))
   } catch {
-    return { ...DEFAULT_DOWNLOAD_SETTINGS }
+    return null
   }
 }
 
-export function setDownloadSettings(
+export async function getDownloadSettings(accountId: string | null): Promise<DownloadSettings> {
+  if (!accountId) return { ...DEFAULT_DOWNLOAD_SETTINGS }
+  const settings = await getSettings()
+  const stored = settings.accounts[accountId]?.downloads
+  if (stored) return normalizeDownloadSettings(stored)
+  const legacy = takeLegacyDownloadSettings(accountId)
+  if (legacy) return await setDownloadSettings(accountId, legacy)
+  return { ...DEFAULT_DOWNLOAD_SETTINGS }
+}
+
+export async function setDownloadSettings(
   accountId: string | null,
   value: Partial<DownloadSettings>
-): DownloadSettings {
+): Promise<DownloadSettings> {
   const next = normalizeDownloadSettings(value)
-  try {
-    window.localStorage.setItem(keyFor(accountId), JSON.stringify(next))
-  } catch {
-    // ignore storage failures
+  if (accountId) {
+    const settings = await getSettings()
+    await setSettings({
+      ...settings,
+      accounts: { ...settings.accounts, [accountId]: { ...settings.accounts[accountId], downloads: next } },
+    })
   }
   window.dispatchEvent(new CustomEvent('cordia:download-settings-changed', { detail: next }))
   return next
 }
-
@@ -20,7 +20,34 @@ declare global {
 export interface NativeAudioDevice {
   device_id: string;
   label: string;
-  kind: 'audioinput' | 'audiooutput';
+  kind: 'audioinput' | 'audiooutput' | 'audiomonitor';
+}
+
+/** Per-frame dBFS levels from `cordia:audio-meter` (floor -120). */
+export interface AudioMeterReading {
+  input_peak_dbfs: number;
+  input_rms_dbfs: number;
+  output_peak_dbfs: number;
+  output_rms_dbfs: number;
+  clipped: boolean;
+  noise_floor_dbfs: number;
+  /** 0-1 meter level under the selected smoothing profile. */
+  level: number;
+  /** Recent maximum of `level`, held ~1.5 s before falling. */
+  peak_hold: number;
+}
+
+/** Subscribe to dBFS meter readings while native capture runs. Returns the unlisten function. */
+export async function listenAudioMeter(onReading: (reading: AudioMeterReading) => void): Promise<() => void> {
+  return await listen<AudioMeterReading>('cordia:audio-meter', (event) => onReading(event.payload));
+}
+
+/**
+ * Subscribe to the native gate opening/closing (debounced), i.e. whether peers hear the mic.
+ * Returns the unlisten function.
+ */
+export async function listenTransmissionState(onChange: (transmitting: boolean) => void): Promise<() => void> {
+  return await listen<boolean>('cordia:transmission-state', (event) => onChange(event.payload));
 }
 
 /**
@@ -278,11 +305,14 @@ export class NativeAudioCapture {
 export async function enumerateAudioDevicesNative(): Promise<{
   inputDevices: NativeAudioDevice[];
   outputDevices: NativeAudioDevice[];
+  /** Output monitors (Linux/macOS), capturable like inputs for "share what I hear". */
+  monitorDevices: NativeAudioDevice[];
 }> {
   const devices = await invoke<NativeAudioDevice[]>('enumerate_audio_devices_native');
   
   return {
     inputDevices: devices.filter((d) => d.kind === 'audioinput'),
     outputDevices: devices.filter((d) => d.kind === 'audiooutput'),
+    monitorDevices: devices.filter((d) => d.kind === 'audiomonitor'),
   };
 }
@@ -0,0 +1,82 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import type { DownloadSettings } from './downloadSettings'
+import type { BeaconTlsOptions, ProxySettings } from './tauri'
+
+// App settings stored natively (settings.json), so Rust modules read them directly and every
+// window sees the same values. Saving broadcasts `cordia:settings-changed`.
+
+export interface BeaconMonitorSettings {
+  /** Seconds between checks while the beacon is healthy (5 to 600). */
+  healthy_interval_secs: number
+  /** A check slower than this marks the beacon degraded (100 to 10000 ms). */
+  slow_check_ms: number
+}
+
+export interface IdleSettings {
+  /** Go idle automatically when there's no keyboard or mouse input. */
+  enabled: boolean
+  /** Minutes without input before going idle (1 to 240). */
+  after_minutes: number
+}
+
+export interface BandwidthSettings {
+  /** Upper bound on the voice encoder's bitrate (6 to 510 kbps); null means no cap. */
+  max_voice_kbps: number | null
+}
+
+/** A saved beacon setup to switch to, e.g. the public beacon or a self-hosted one. */
+export interface BeaconProfile {
+  /** Assigned on save when empty. */
+  id: string
+  name: string
+  /** ws:// or wss:// beacon URL. */
+  url: string
+  proxy: ProxySettings
+  tls: BeaconTlsOptions
+  /** Account to sign in as on switch; null keeps the current one. */
+  account_id: string | null
+}
+
+export interface AccountSettings {
+  downloads: DownloadSettings
+}
+
+export type KeybindAction = 'push_to_talk' | 'toggle_mute' | 'toggle_deafen' | 'switch_voice_channel' | 'open_overlay'
+
+/** A system-wide keybind; `binding` uses the same key-string format as the PTT key. */
+export interface Keybind {
+  action: KeybindAction
+  binding: string
+  /** Target server for `switch_voice_channel`. */
+  server_id?: string | null
+}
+
+export interface AppSettings {
+  schema_version: number
+  /** Keep settings.json encrypted with a key from the OS keychain. */
+  encrypted: boolean
+  beacon_monitor: BeaconMonitorSettings
+  /** Keyed by account id. */
+  accounts: Record<string, AccountSettings>
+  /** Global keybinds other than push-to-talk (that one lives in the audio settings). */
+  keybinds: Keybind[]
+  idle: IdleSettings
+  bandwidth: BandwidthSettings
+  beacon_profiles: BeaconProfile[]
+  /** Id of the profile last switched to. */
+  active_beacon_profile: string | null
+}
+
+export async function getSettings(): Promise<AppSettings> {
+  return await invoke('get_settings')
+}
+
+/** Save the whole settings object; resolves to what was stored (after clamping). */
+export async function setSettings(settings: AppSettings): Promise<AppSettings> {
+  return await invoke('set_settings', { settings })
+}
+
+export async function listenSettings(onChange: (settings: AppSettings) => void): Promise<() => void> {
+  return await listen<AppSettings>('cordia:settings-changed', (event) => onChange(event.payload))
+}
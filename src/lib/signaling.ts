@@ -0,0 +1,222 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+
+// Native signaling client: the WebSocket to the beacon lives in Rust, which reconnects with
+// backoff and resends registrations on its own.
+
+export type SignalingState = 'connecting' | 'connected' | 'reconnecting' | 'disconnected'
+
+/** Result of the protocol handshake with the beacon. */
+export interface SignalingProtocol {
+  version: number
+  /** The beacon predates negotiation. */
+  legacy: boolean
+  /** Client message types the beacon handles; null if it didn't say. */
+  accepts: string[] | null
+  /** e.g. "voice", "connection_auth", "deflate"; empty if the beacon didn't say. */
+  features: string[]
+  /** Rate limits and payload sizes, as on `/api/info`; null if the beacon didn't say. */
+  limits: Record<string, unknown> | null
+}
+
+export interface SignalingStatus {
+  state: SignalingState
+  url: string | null
+  /** Failed attempts since the last successful connect. */
+  attempt: number
+  /** While reconnecting: how long until the next attempt. */
+  retry_in_ms: number | null
+  last_error: string | null
+  /** Null until the beacon answers the handshake. */
+  protocol: SignalingProtocol | null
+}
+
+/** A beacon message as sent on the wire, tagged by `type` (e.g. `VoicePeerJoined`). */
+export interface SignalingMessage {
+  type: string
+  [field: string]: unknown
+}
+
+/** Connect to `url` (default beacon if omitted), replacing any existing connection. */
+export async function signalingConnect(url?: string): Promise<void> {
+  return await invoke('signaling_connect', { url })
+}
+
+/** Close the connection and drop all registrations. */
+export async function signalingDisconnect(): Promise<void> {
+  return await invoke('signaling_disconnect')
+}
+
+/** Send one message; rejects while not connected. */
+export async function signalingSend(message: SignalingMessage): Promise<void> {
+  return await invoke('signaling_send', { message })
+}
+
+export interface QueuedMessage {
+  /** The message's `dedup_id`. */
+  id: string
+  queued_at: string
+  payload: SignalingMessage
+}
+
+/**
+ * Send `message` without losing it to a network blip: it is queued on disk, sent once
+ * connected (replayed after reconnects and restarts), and confirmed on
+ * `cordia:signaling-delivered`. Resolves to the message's `dedup_id`.
+ */
+export async function signalingSendReliable(message: SignalingMessage): Promise<string> {
+  return await invoke('signaling_send_reliable', { message })
+}
+
+export async function getOutboundQueue(): Promise<QueuedMessage[]> {
+  return await invoke('get_outbound_queue')
+}
+
+export async function clearOutboundQueue(): Promise<void> {
+  return await invoke('clear_outbound_queue')
+}
+
+export async function listenSignalingDelivered(onDelivered: (id: string) => void): Promise<() => void> {
+  return await listen<string>('cordia:signaling-delivered', (event) => onDelivered(event.payload))
+}
+
+/**
+ * Send `message` now and again after every reconnect (e.g. `VoiceRegister`, `PresenceHello`).
+ * Registering under an existing `key` replaces its message.
+ */
+export async function signalingRegister(key: string, message: SignalingMessage): Promise<void> {
+  return await invoke('signaling_register', { key, message })
+}
+
+export async function signalingUnregister(key: string): Promise<void> {
+  return await invoke('signaling_unregister', { key })
+}
+
+/** How long a frontend-owned beacon socket may take to authenticate before it is closed. */
+const AUTH_WAIT_MS = 10000
+/** Signaling protocols offered in `Hello`, newest first (matches the native client). */
+const PROTOCOL_VERSIONS = [2, 1]
+
+/** Sign an `AuthChallenge` nonce with the identity key; resolves to the `Authenticate` reply. */
+export async function answerAuthChallenge(nonce: string): Promise<SignalingMessage> {
+  return await invoke('signaling_answer_challenge', { nonce })
+}
+
+/**
+ * Authenticate a beacon WebSocket opened outside the native client. Call once the socket is
+ * open and pass every parsed incoming message to the returned handler (it returns true for
+ * messages it consumed). It sends `Hello`, answers the `AuthChallenge` and calls `onReady`
+ * once the beacon confirms with `Authenticated`. Registrations belong in `onReady`: the
+ * beacon refuses them from unauthenticated connections.
+ *
+ * Only a beacon whose handshake shows it predates authentication (a `Welcome` that doesn't
+ * accept `Authenticate`, or a protocol 1 beacon rejecting `Hello`) is used without it. Any
+ * other socket that isn't authenticated within `AUTH_WAIT_MS` is closed.
+ */
+export function authenticateBeaconSocket(ws: WebSocket, onReady: () => void): (message: SignalingMessage) => boolean {
+  let settled = false
+  let answered = false
+  const finish = () => {
+    if (settled) return
+    settled = true
+    window.clearTimeout(deadline)
+    if (ws.readyState === WebSocket.OPEN) onReady()
+  }
+  const fail = (reason: string) => {
+    if (settled) return
+    settled = true
+    window.clearTimeout(deadline)
+    console.warn(`[Signaling] ${reason}; closing the connection`)
+    if (ws.readyState === WebSocket.OPEN || ws.readyState === WebSocket.CONNECTING) ws.close(4001, 'Not authenticated')
+  }
+  const deadline = window.setTimeout(() => fail('Beacon did not authenticate this connection'), AUTH_WAIT_MS)
+  ws.send(JSON.stringify({ type: 'Hello', protocol_versions: PROTOCOL_VERSIONS }))
+
+  return (message) => {
+    switch (message.type) {
+      case 'AuthChallenge':
+        answerAuthChallenge(String(message.nonce ?? ''))
+          .then((answer) => {
+            answered = true
+            if (ws.readyState === WebSocket.OPEN) ws.send(JSON.stringify(answer))
+          })
+          .catch((e) => fail(`Failed to answer auth challenge: ${e}`))
+        return true
+      case 'Authenticated':
+        finish()
+        return true
+      case 'Welcome': {
+        const accepts = message.accepts
+        if (Array.isArray(accepts) && !accepts.includes('Authenticate')) finish()
+        return true
+      }
+      case 'Error': {
+        if (settled) return false
+        const error = String(message.message ?? '')
+        // serde's "unknown variant `Hello`" from a beacon that predates the handshake (and auth).
+        if (!answered && error.includes('`Hello`')) {
+          finish()
+          return true
+        }
+        if (answered) {
+          fail(`Beacon rejected authentication: ${error}`)
+          return true
+        }
+        return false
+      }
+      default:
+        return false
+    }
+  }
+}
+
+export async function getSignalingStatus(): Promise<SignalingStatus> {
+  return await invoke('get_signaling_status')
+}
+
+export async function listenSignalingState(onChange: (status: SignalingStatus) => void): Promise<() => void> {
+  return await listen<SignalingStatus>('cordia:signaling-state', (event) => onChange(event.payload))
+}
+
+export async function listenSignalingMessages(onMessage: (message: SignalingMessage) => void): Promise<() => void> {
+  return await listen<SignalingMessage>('cordia:signaling-message', (event) => onMessage(event.payload))
+}
+
+/** The beacon sent a message type this version doesn't know (reported once per type per connection). */
+export async function listenSignalingUnsupported(onType: (type: string) => void): Promise<() => void> {
+  return await listen<string>('cordia:signaling-unsupported', (event) => onType(event.payload))
+}
+
+export interface BeaconHealth {
+  url: string
+  healthy: boolean
+  rtt_ms: number | null
+  last_error: string | null
+}
+
+export interface FailoverStatus {
+  active: string | null
+  /** In configured order. */
+  beacons: BeaconHealth[]
+  last_switch: string | null
+}
+
+/**
+ * Keep the connection on the first healthy beacon, failing over and back automatically.
+ * Uses the account's primary + fallback beacons unless `urls` is given.
+ */
+export async function startBeaconFailover(urls?: string[]): Promise<void> {
+  return await invoke('start_beacon_failover', { urls })
+}
+
+export async function stopBeaconFailover(): Promise<void> {
+  return await invoke('stop_beacon_failover')
+}
+
+export async function getBeaconFailoverStatus(): Promise<FailoverStatus | null> {
+  return await invoke('get_beacon_failover_status')
+}
+
+export async function listenBeaconFailover(onChange: (status: FailoverStatus) => void): Promise<() => void> {
+  return await listen<FailoverStatus>('cordia:beacon-failover', (event) => onChange(event.payload))
+}
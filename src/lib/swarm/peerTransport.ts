@@ -15,7 +15,7 @@ export class PeerTransport {
     label: string,
     onIce: (candidate: string) => void
   ): Promise<SignalPayload> {
-    const pc = createPeerConnection()
+    const pc = await createPeerConnection()
     pc.onicecandidate = (ev) => {
       if (ev.candidate) {
         onIce(JSON.stringify(ev.candidate))
@@ -34,7 +34,7 @@ export class PeerTransport {
     onIce: (candidate: string) => void,
     onChannel: (dc: RTCDataChannel) => void
   ): Promise<SignalPayload> {
-    const pc = createPeerConnection()
+    const pc = await createPeerConnection()
     pc.onicecandidate = (ev) => {
       if (ev.candidate) {
         onIce(JSON.stringify(ev.candidate))
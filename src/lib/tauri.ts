@@ -1,10 +1,11 @@
 import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import type { BeaconProfile, Keybind, KeybindAction } from './settings'
 
 export interface UserIdentity {
   user_id: string
   display_name: string
   public_key: string
-  private_key?: string
 }
 
 export async function hasIdentity(): Promise<boolean> {
@@ -23,6 +24,47 @@ export async function loadIdentity(): Promise<UserIdentity> {
   return await invoke('load_identity')
 }
 
+/**
+ * Sign `message` with the identity key, which never leaves the native side. The native side
+ * signs `cordia-user-sign:` + `message` and refuses messages in its own protocol contexts.
+ * Resolves to a base64 signature.
+ */
+export async function signWithIdentity(message: string): Promise<string> {
+  return await invoke('sign_with_identity', { message })
+}
+
+/** Check a signature made by `signWithIdentity`. */
+export async function verifyIdentitySignature(publicKey: string, message: string, signature: string): Promise<boolean> {
+  return await invoke('verify_identity_signature', { publicKey, message, signature })
+}
+
+export interface IdentityFingerprint {
+  public_key: string
+  /** Ten groups of four hex digits, for reading aloud to compare keys. */
+  fingerprint: string
+}
+
+/** Fingerprint of `publicKey`, or of the current identity when omitted. */
+export async function getIdentityFingerprint(publicKey?: string): Promise<IdentityFingerprint> {
+  return await invoke('get_identity_fingerprint', { publicKey })
+}
+
+/**
+ * Secrets for the current account in the OS keychain (Credential Manager, Keychain, Secret
+ * Service), e.g. beacon auth tokens. Prefer these over localStorage for anything sensitive.
+ */
+export async function getSecret(name: string): Promise<string | null> {
+  return await invoke('get_secret', { name })
+}
+
+export async function setSecret(name: string, value: string): Promise<void> {
+  return await invoke('set_secret', { name, value })
+}
+
+export async function deleteSecret(name: string): Promise<void> {
+  return await invoke('delete_secret', { name })
+}
+
 export async function exportIdentity(): Promise<Uint8Array> {
   const data = await invoke<number[]>('export_identity')
   return new Uint8Array(data)
@@ -38,8 +80,84 @@ export interface AudioSettings {
   input_volume: number
   input_sensitivity: number
   output_volume: number
-  input_mode: 'voice_activity' | 'push_to_talk'
+  input_mode: 'voice_activity' | 'level_gate' | 'push_to_talk' | 'hybrid' | 'open_mic'
   push_to_talk_key: string | null
+  ptt_release_ms?: number
+  audio_host?: string | null
+  noise_suppression?: boolean
+  noise_suppression_strength?: number
+  denoiser?: string
+  echo_cancellation?: boolean
+  auto_gain?: boolean
+  auto_gain_target_db?: number
+  high_pass?: boolean
+  high_pass_hz?: number
+  compressor?: boolean
+  compressor_settings?: CompressorSettings
+  clarity?: boolean
+  clarity_strength?: number
+  eq_bands?: EqBand[]
+  dsp_chain?: DspStageConfig[]
+  meter_tuning?: MeterTuning
+  meter_smoothing?: MeterSmoothing
+}
+
+export type DspStage =
+  | 'high_pass'
+  | 'echo_cancellation'
+  | 'noise_suppression'
+  | 'eq'
+  | 'clarity'
+  | 'gain'
+  | 'auto_gain'
+  | 'gate'
+  | 'voice_effect'
+  | 'compressor'
+
+export interface DspStageConfig {
+  stage: DspStage
+  bypass?: boolean
+}
+
+export interface EqBand {
+  kind: 'peaking' | 'low_shelf' | 'high_shelf'
+  freq_hz: number
+  gain_db: number
+  q?: number
+}
+
+/** Level meter/gate constants; coefficients are per 10 ms frame. */
+export interface MeterTuning {
+  noise_floor: number
+  max_level: number
+  decay_factor: number
+  attack_coeff: number
+  release_coeff: number
+  adaptive_noise_floor: boolean
+}
+
+export async function setMeterTuning(tuning: MeterTuning): Promise<MeterTuning> {
+  return await invoke('set_meter_tuning', { tuning })
+}
+
+export type MeterSmoothing = 'peak' | 'ppm' | 'vu'
+
+export async function setMeterSmoothing(profile: MeterSmoothing): Promise<void> {
+  return await invoke('set_meter_smoothing', { profile })
+}
+
+/** Voice changer shifts in semitones (±12). Needs a build with `fun-effects`. */
+export interface VoiceEffect {
+  pitch_semitones: number
+  formant_semitones: number
+}
+
+export interface CompressorSettings {
+  threshold_db: number
+  ratio: number
+  attack_ms: number
+  release_ms: number
+  makeup_db: number
 }
 
 export async function loadAudioSettings(): Promise<AudioSettings> {
@@ -50,6 +168,135 @@ export async function saveAudioSettings(settings: AudioSettings): Promise<void>
   return await invoke('save_audio_settings', { settings })
 }
 
+export interface VoiceBitrate {
+  /** From the Opus settings */
+  configured_bps: number
+  cap_bps: number | null
+  /** Configured bitrate lowered to the cap */
+  effective_bps: number
+  /** Measured from encoded packets; null when the native encoder isn't running */
+  actual_bps: number | null
+}
+
+export async function getVoiceBitrate(): Promise<VoiceBitrate> {
+  return await invoke('get_voice_bitrate')
+}
+
+/** The network changed under us; the native beacon connection is already redialing. */
+export interface NetworkChange {
+  /** Local address of the default route after the change. */
+  default_route: string | null
+  default_route_changed: boolean
+  interfaces: string[]
+}
+
+export async function listenNetworkChanged(onChange: (change: NetworkChange) => void): Promise<() => void> {
+  return await listen<NetworkChange>('cordia:network-changed', (event) => onChange(event.payload))
+}
+
+/** Keep the system from sleeping while in voice; holding it twice is fine. */
+export async function acquireSleepInhibitor(): Promise<void> {
+  return await invoke('acquire_sleep_inhibitor')
+}
+
+export async function releaseSleepInhibitor(): Promise<void> {
+  return await invoke('release_sleep_inhibitor')
+}
+
+/** OS input idle state; `idle` flips after `settings.idle.after_minutes` without input. */
+export interface IdleState {
+  idle: boolean
+  /** Seconds since the last input; null when the platform can't tell. */
+  idle_secs: number | null
+}
+
+export async function getIdleState(): Promise<IdleState> {
+  return await invoke('get_idle_state')
+}
+
+export async function listenIdle(onChange: (state: IdleState) => void): Promise<() => void> {
+  return await listen<IdleState>('cordia:idle-changed', (event) => onChange(event.payload))
+}
+
+/** A keybind (PTT included) as registered system-wide. */
+export interface KeybindStatus extends Keybind {
+  registered: boolean
+  /** Why the binding couldn't be used: a bare typing key, taken by another app, etc. */
+  conflict: string | null
+}
+
+export async function getKeybindStatus(): Promise<KeybindStatus[]> {
+  return await invoke('get_keybind_status')
+}
+
+export async function listenKeybindStatus(onStatus: (status: KeybindStatus[]) => void): Promise<() => void> {
+  return await listen<KeybindStatus[]>('cordia:keybind-status', (event) => onStatus(event.payload))
+}
+
+/** A global keybind fired. Mute/deafen have already been applied natively; `muted`/`deafened` are the new state. */
+export interface KeybindEvent {
+  action: KeybindAction
+  server_id: string | null
+  muted: boolean | null
+  deafened: boolean | null
+}
+
+export async function listenKeybinds(onKeybind: (event: KeybindEvent) => void): Promise<() => void> {
+  return await listen<KeybindEvent>('cordia:keybind', (event) => onKeybind(event.payload))
+}
+
+/** Press/release of the global PTT key; the native DSP has already been updated. */
+export async function listenGlobalPtt(onPtt: (pressed: boolean) => void): Promise<() => void> {
+  return await listen<boolean>('cordia:global-ptt', (event) => onPtt(event.payload))
+}
+
+/** Mic processing settings stored in a named preset; null = stage off. */
+export interface DspConfig {
+  gain: number
+  threshold: number
+  high_pass_hz: number | null
+  echo_cancellation_tail_ms: number | null
+  denoiser: string
+  noise_suppression_strength: number | null
+  eq_bands: EqBand[]
+  clarity_strength: number | null
+  auto_gain_target_db: number | null
+  compressor: CompressorSettings | null
+  voice_effect: VoiceEffect | null
+  chain: DspStageConfig[]
+}
+
+export async function listDspPresets(): Promise<string[]> {
+  return await invoke('list_dsp_presets')
+}
+
+export async function saveDspPreset(name: string): Promise<void> {
+  return await invoke('save_dsp_preset', { name })
+}
+
+export async function applyDspPreset(name: string): Promise<DspConfig> {
+  return await invoke('apply_dsp_preset', { name })
+}
+
+export async function deleteDspPreset(name: string): Promise<boolean> {
+  return await invoke('delete_dsp_preset', { name })
+}
+
+export interface ProcessedFile {
+  path: string
+  duration_secs: number
+  input_sample_rate: number
+}
+
+/** Run a WAV file through the DSP chain (live settings unless `config` is given). */
+export async function processAudioFile(
+  inputPath: string,
+  outputPath: string,
+  config?: DspConfig
+): Promise<ProcessedFile> {
+  return await invoke('process_audio_file', { inputPath, outputPath, config: config ?? null })
+}
+
 export interface Chat {
   id: string
   name: string
@@ -130,6 +377,42 @@ export async function decryptEphemeralChatMessageBySigningPubkey(
   return await invoke('decrypt_ephemeral_chat_message_by_signing_pubkey', { signingPubkey, encryptedPayloadB64 })
 }
 
+/** A chat message in local history (stored encrypted on disk). */
+export interface HistoryMessage {
+  id: string
+  server_id: string
+  channel_id: string
+  sender_id: string
+  body: string
+  /** Unix time in milliseconds. */
+  sent_at: number
+}
+
+/** Save messages to local history; ones already stored (same id) are skipped. Resolves to how many were new. */
+export async function historyAppend(messages: HistoryMessage[]): Promise<number> {
+  return await invoke('history_append', { messages })
+}
+
+/** Up to `limit` messages sent before `before` (latest page when omitted), oldest first. */
+export async function historyFetch(
+  serverId: string,
+  channelId: string,
+  before?: number,
+  limit?: number
+): Promise<HistoryMessage[]> {
+  return await invoke('history_fetch', { serverId, channelId, before, limit })
+}
+
+/** Messages containing every word of `query`, newest first. */
+export async function historySearch(query: string, serverId?: string, limit?: number): Promise<HistoryMessage[]> {
+  return await invoke('history_search', { query, serverId, limit })
+}
+
+/** Delete history older than `before` (ms); resolves to how many messages were removed. */
+export async function historyPrune(before: number, serverId?: string): Promise<number> {
+  return await invoke('history_prune', { before, serverId })
+}
+
 export interface AttachmentWaveformPeaks {
   top: number[]
   bottom: number[]
@@ -418,10 +701,19 @@ export async function publishServerHintOpaque(beaconUrl: string, serverId: strin
   return await invoke('publish_server_hint_opaque', { beaconUrl, serverId })
 }
 
+/**
+ * Tell the server that `userId` left. The owner publishes a new hint; a member can only post
+ * their own signed leave, which the owner applies (see `applyServerMemberLeaves`).
+ */
 export async function publishServerHintMemberLeft(beaconUrl: string, serverId: string, userId: string): Promise<void> {
   return await invoke('publish_server_hint_member_left', { beaconUrl, serverId, userId })
 }
 
+/** Owner only: drop members who posted a signed leave and republish the hint. True if any were removed. */
+export async function applyServerMemberLeaves(beaconUrl: string, signingPubkey: string): Promise<boolean> {
+  return await invoke('apply_server_member_leaves', { beaconUrl, signingPubkey })
+}
+
 export async function fetchAndImportServerHintOpaque(beaconUrl: string, signingPubkey: string): Promise<boolean> {
   return await invoke('fetch_and_import_server_hint_opaque', { beaconUrl, signingPubkey })
 }
@@ -447,10 +739,190 @@ export async function revokeActiveInvite(beaconUrl: string, serverId: string): P
   return await invoke('revoke_active_invite', { beaconUrl, serverId })
 }
 
+/** A `cordia://` invite link after native validation. */
+export interface ParsedInvite {
+  signing_pubkey: string | null
+  beacon_url: string
+  expires_at: string | null
+  /** Invite code to redeem on the beacon. */
+  token: string | null
+  /** False for legacy `cordia://CODE@beacon` links. */
+  signed: boolean
+}
+
+/** Signed invite link for a server. `oneTime` mints a single-use code; otherwise the active invite is used. */
+export async function createInviteLink(
+  beaconUrl: string,
+  serverId: string,
+  oneTime: boolean,
+  expiresInHours?: number
+): Promise<string> {
+  return await invoke('create_invite_link', { beaconUrl, serverId, expiresInHours, oneTime })
+}
+
+/** Rejects links with a bad signature or past their expiry. */
+export async function parseInviteLink(link: string): Promise<ParsedInvite> {
+  return await invoke('parse_invite_link', { link })
+}
+
+/** QR code for an invite link, as a data URL usable as an `<img>` src. */
+export async function renderInviteQr(link: string, format: 'svg' | 'png' = 'svg'): Promise<string> {
+  return await invoke('render_invite_qr', { link, format })
+}
+
+/** The invite link the app was opened with, if nothing has handled it yet. */
+export async function takePendingInviteLink(): Promise<string | null> {
+  return await invoke('take_pending_invite_link')
+}
+
+/** Fires when a cordia:// link is opened while the app is running. */
+export async function listenInviteLinks(onLink: (link: string) => void): Promise<() => void> {
+  return await listen<string>('cordia:invite-link', (event) => onLink(event.payload))
+}
+
 export async function checkBeacon(url?: string): Promise<boolean> {
   return await invoke('check_beacon', { url })
 }
 
+export interface BeaconLimits {
+  max_body_bytes: number
+  max_ws_connections: number
+  max_ws_per_ip: number
+  rate_limit_rest_per_min: number
+  rate_limit_ws_per_min: number
+}
+
+export interface BeaconInfo {
+  version: string | null
+  /** 0 for beacons that predate /api/info (they speak protocol 1). */
+  protocol_version: number
+  min_protocol_version: number
+  /** e.g. 'voice', 'friends', 'swarm', 'postgres', 'redis'. Empty for old beacons. */
+  features: string[]
+  limits: BeaconLimits | null
+  compatible: boolean
+}
+
+export interface BeaconStatusEvent {
+  url: string
+  status: 'Connected' | 'Degraded' | 'Disconnected' | 'Checking'
+  /** Why the beacon is degraded or disconnected. */
+  reason: string | null
+  rtt_ms: number | null
+}
+
+/** Health-check the beacon in the background; changes arrive as `cordia:beacon-status`. */
+export async function startBeaconMonitor(url?: string): Promise<void> {
+  return await invoke('start_beacon_monitor', { url })
+}
+
+export async function stopBeaconMonitor(): Promise<void> {
+  return await invoke('stop_beacon_monitor')
+}
+
+export async function getBeaconMonitorStatus(): Promise<BeaconStatusEvent | null> {
+  return await invoke('get_beacon_monitor_status')
+}
+
+/** A beacon announced on the LAN over mDNS (`_cordia-beacon._tcp`). */
+export interface DiscoveredBeacon {
+  name: string
+  /** DNS-SD full name; what `cordia:beacon-lost` reports. */
+  fullname: string
+  /** ws:// URL to connect to. */
+  url: string
+  addresses: string[]
+  port: number
+  version: string | null
+}
+
+export async function startBeaconDiscovery(): Promise<void> {
+  return await invoke('start_beacon_discovery')
+}
+
+export async function stopBeaconDiscovery(): Promise<void> {
+  return await invoke('stop_beacon_discovery')
+}
+
+export async function getDiscoveredBeacons(): Promise<DiscoveredBeacon[]> {
+  return await invoke('get_discovered_beacons')
+}
+
+/** Announce a beacon hosted on this machine (listening on `port`) to the LAN. */
+export async function advertiseLocalBeacon(port: number, name?: string): Promise<void> {
+  return await invoke('advertise_local_beacon', { port, name })
+}
+
+export async function stopAdvertisingBeacon(): Promise<void> {
+  return await invoke('stop_advertising_beacon')
+}
+
+export async function listenBeaconDiscovery(
+  onFound: (beacon: DiscoveredBeacon) => void,
+  onLost: (fullname: string) => void
+): Promise<() => void> {
+  const unlistenFound = await listen<DiscoveredBeacon>('cordia:beacon-discovered', (event) => onFound(event.payload))
+  const unlistenLost = await listen<string>('cordia:beacon-lost', (event) => onLost(event.payload))
+  return () => {
+    unlistenFound()
+    unlistenLost()
+  }
+}
+
+export interface BeaconProbe {
+  /** HTTP GET /health */
+  http_ok: boolean
+  http_error: string | null
+  http_ms: number | null
+  /** WebSocket upgrade on /ws plus a Ping round trip */
+  ws_ok: boolean
+  ws_error: string | null
+  ws_ms: number | null
+}
+
+/** Check both the health endpoint and the WebSocket path; either can fail on its own. */
+export async function probeBeacon(url?: string): Promise<BeaconProbe> {
+  return await invoke('probe_beacon', { url })
+}
+
+export type DiagnosticStatus = 'pass' | 'skipped' | 'warn' | 'fail'
+
+export interface DiagnosticCheck {
+  /** dns, tcp, tls, http, websocket, udp, captive_portal */
+  id: string
+  label: string
+  status: DiagnosticStatus
+  detail: string
+  duration_ms: number | null
+}
+
+export interface DiagnosticsReport {
+  beacon_url: string
+  /** Worst status among the checks */
+  status: DiagnosticStatus
+  checks: DiagnosticCheck[]
+}
+
+/** DNS, TCP, TLS, health, WebSocket, UDP/STUN and captive-portal checks, step by step. */
+export async function runNetworkDiagnostics(url?: string): Promise<DiagnosticsReport> {
+  return await invoke('run_network_diagnostics', { url })
+}
+
+export async function getBeaconInfo(url?: string): Promise<BeaconInfo> {
+  return await invoke('get_beacon_info', { url })
+}
+
+export interface BeaconLatency {
+  samples: number
+  min_ms: number
+  avg_ms: number
+  p95_ms: number
+}
+
+export async function measureBeaconLatency(url?: string, samples?: number): Promise<BeaconLatency> {
+  return await invoke('measure_beacon_latency', { url, samples })
+}
+
 export async function getDefaultBeacon(): Promise<string> {
   return await invoke('get_default_beacon')
 }
@@ -463,6 +935,212 @@ export async function setBeaconUrl(url: string): Promise<void> {
   return await invoke('set_beacon_url', { url })
 }
 
+export type ProxyMode = 'system' | 'direct' | 'manual'
+
+export interface ProxySettings {
+  mode: ProxyMode
+  /** http://, socks5:// or socks5h://, with optional user:pass@. Used in manual mode. */
+  url: string | null
+}
+
+export async function getProxySettings(): Promise<ProxySettings> {
+  return await invoke('get_proxy_settings')
+}
+
+/** Applies to beacon requests and the signaling connection from the next connect on. */
+export async function setProxySettings(settings: ProxySettings): Promise<void> {
+  return await invoke('set_proxy_settings', { settings })
+}
+
+export interface BeaconTlsOptions {
+  /** PEM root certificate(s) trusted for this beacon on top of the system store. */
+  ca_cert_pem: string | null
+  /** Base64 SHA-256 of the server's public key (SPKI); `sha256/` prefix allowed. */
+  spki_pin: string | null
+}
+
+export async function getBeaconTlsOptions(url: string): Promise<BeaconTlsOptions> {
+  return await invoke('get_beacon_tls_options', { url })
+}
+
+/** Options are per beacon host:port; empty options clear them. */
+export async function setBeaconTlsOptions(url: string, options: BeaconTlsOptions): Promise<void> {
+  return await invoke('set_beacon_tls_options', { url, options })
+}
+
+export async function listBeaconProfiles(): Promise<BeaconProfile[]> {
+  return await invoke('list_beacon_profiles')
+}
+
+/** Add or update a profile (matched by id); resolves to it as stored. */
+export async function saveBeaconProfile(profile: BeaconProfile): Promise<BeaconProfile> {
+  return await invoke('save_beacon_profile', { profile })
+}
+
+export async function deleteBeaconProfile(id: string): Promise<void> {
+  return await invoke('delete_beacon_profile', { id })
+}
+
+/**
+ * Drop the current beacon's connections and state and apply the profile's account, proxy,
+ * TLS options and URL. Emits `cordia:beacon-profile-switched`.
+ */
+export async function switchBeaconProfile(id: string): Promise<BeaconProfile> {
+  return await invoke('switch_beacon_profile', { id })
+}
+
+export async function listenBeaconProfileSwitched(onSwitch: (profile: BeaconProfile) => void): Promise<() => void> {
+  return await listen<BeaconProfile>('cordia:beacon-profile-switched', (event) => onSwitch(event.payload))
+}
+
+/** Primary beacon followed by the fallbacks, in failover order. */
+export async function getBeaconUrls(): Promise<string[]> {
+  return await invoke('get_beacon_urls')
+}
+
+/** STUN servers plus TURN credentials from the active beacon (cached natively until they near expiry). */
+export async function getIceServers(): Promise<RTCIceServer[]> {
+  return await invoke('get_ice_servers')
+}
+
+// === Connection stats ===
+
+/** Inbound audio stats from `RTCPeerConnection.getStats()`, reported for peers the webview plays. */
+export interface ReportedPeerStats {
+  packets_received: number
+  packets_lost: number
+  jitter_ms: number
+  rtt_ms: number | null
+}
+
+export interface PeerConnectionStats {
+  peer_id: string
+  /** 'native' = the native jitter buffer; 'reported' = stats sent with `reportPeerConnectionStats`. */
+  source: 'native' | 'reported'
+  packet_loss_pct: number
+  jitter_ms: number
+  rtt_ms: number | null
+  buffered_ms: number | null
+}
+
+export interface ConnectionStats {
+  beacon: {
+    state: 'connecting' | 'connected' | 'reconnecting' | 'disconnected'
+    url: string | null
+    rtt_ms: number | null
+    reconnects: number
+    attempt: number
+  }
+  peers: PeerConnectionStats[]
+  audio: {
+    dropped_raw: number
+    dropped_processed: number
+    dropped_playback: number
+    dropped_loopback: number
+    dropped_app_capture: number
+    capture_drift_ppm: number
+    dsp_frame_ms: number
+    dsp_frame_max_ms: number
+    dsp_overruns: number
+  }
+}
+
+export async function getConnectionStats(): Promise<ConnectionStats> {
+  return await invoke('get_connection_stats')
+}
+
+export async function reportPeerConnectionStats(peerId: string, stats: ReportedPeerStats): Promise<void> {
+  return await invoke('report_peer_connection_stats', { peerId, stats })
+}
+
+// === Native WebRTC transport (builds with the `native-webrtc` feature) ===
+// Peer connections run in Rust and take mic audio straight from the Opus encoder, so voice
+// skips the webview's WebRTC stack. SDPs carry all ICE candidates; send them as one message.
+
+export interface NativePeerState {
+  peer_id: string
+  /** 'new' | 'connecting' | 'connected' | 'disconnected' | 'failed' | 'closed' */
+  state: string
+}
+
+export async function nativeRtcAvailable(): Promise<boolean> {
+  return await invoke('native_rtc_available')
+}
+
+export async function nativeRtcCreateOffer(peerId: string): Promise<string> {
+  return await invoke('native_rtc_create_offer', { peerId })
+}
+
+export async function nativeRtcAcceptOffer(peerId: string, sdp: string): Promise<string> {
+  return await invoke('native_rtc_accept_offer', { peerId, sdp })
+}
+
+export async function nativeRtcApplyAnswer(peerId: string, sdp: string): Promise<void> {
+  return await invoke('native_rtc_apply_answer', { peerId, sdp })
+}
+
+/** Close one peer, or every native connection when `peerId` is omitted. */
+export async function nativeRtcClose(peerId?: string): Promise<void> {
+  return await invoke('native_rtc_close', { peerId })
+}
+
+export async function getNativeRtcPeers(): Promise<NativePeerState[]> {
+  return await invoke('native_rtc_peers')
+}
+
+export async function listenNativeRtcState(onState: (state: NativePeerState) => void): Promise<() => void> {
+  return await listen<NativePeerState>('cordia:native-rtc-state', (event) => onState(event.payload))
+}
+
+// === Native file transfer ===
+// Files are hashed, chunked and verified in Rust and go over the native data channel when
+// one is open, else the beacon relay. Progress arrives as `cordia:file-transfer`.
+
+export interface FileTransferProgress {
+  transfer_id: string
+  peer_user_id: string
+  direction: 'send' | 'receive'
+  file_name: string
+  size: number
+  bytes_done: number
+  state: 'offered' | 'active' | 'paused' | 'complete' | 'failed' | 'cancelled'
+  /** Final location once a download completes. */
+  path: string | null
+  error: string | null
+}
+
+export async function sendFileTransfer(toUserId: string, path: string): Promise<FileTransferProgress> {
+  return await invoke('send_file_transfer', { toUserId, path })
+}
+
+export async function acceptFileTransfer(transferId: string): Promise<void> {
+  return await invoke('accept_file_transfer', { transferId })
+}
+
+export async function pauseFileTransfer(transferId: string): Promise<void> {
+  return await invoke('pause_file_transfer', { transferId })
+}
+
+export async function resumeFileTransfer(transferId: string): Promise<void> {
+  return await invoke('resume_file_transfer', { transferId })
+}
+
+export async function cancelFileTransfer(transferId: string): Promise<void> {
+  return await invoke('cancel_file_transfer', { transferId })
+}
+
+export async function listFileTransfers(): Promise<FileTransferProgress[]> {
+  return await invoke('list_file_transfers')
+}
+
+export async function listenFileTransfers(onProgress: (progress: FileTransferProgress) => void): Promise<() => void> {
+  return await listen<FileTransferProgress>('cordia:file-transfer', (event) => onProgress(event.payload))
+}
+
+export async function setFallbackBeaconUrls(urls: string[]): Promise<void> {
+  return await invoke('set_fallback_beacon_urls', { urls })
+}
+
 // === Account Management ===
 
 export interface SessionState {
@@ -591,20 +1269,6 @@ export async function registerKeyFileAssociation(): Promise<void> {
 
 // === Invite URI Helpers ===
 
-/**
- * Parse an invite URI into its components
- * Format: cordia://{signing_pubkey}@{server}
- */
-export function parseInviteUri(uri: string): { signingPubkey: string; server: string } | null {
-  // Be tolerant of users pasting uppercased scheme (e.g. CORDIA://...), and trim whitespace.
-  const match = uri.trim().match(/^cordia:\/\/([^@]+)@(.+)$/i)
-  if (!match) return null
-  return {
-    signingPubkey: match[1],
-    server: match[2],
-  }
-}
-
 /**
  * Get the HTTP base URL from a beacon URL
  */
@@ -2,16 +2,13 @@
  * WebRTC utilities for peer-to-peer voice connections
  */
 
-// RTCConfiguration for P2P-only voice (no TURN). Order matters: faster reflexive candidates first.
+import { getIceServers, type ReportedPeerStats } from './tauri'
+
+// Base RTCConfiguration; ICE servers (STUN + beacon TURN relay) come from the native side.
 // Small pool (2) keeps gathering lean; TCP candidate gathering is implementation-defined.
 // iceTransportPolicy: 'all' — do not restrict to relay; stack gathers from all interfaces
 // (Wi‑Fi, Ethernet, VPN, etc.) and includes IPv6 when available (helps bypass NAT on modern networks).
 export const PEER_CONNECTION_CONFIG: RTCConfiguration = {
-  iceServers: [
-    { urls: 'stun:stun.cloudflare.com:3478' },
-    { urls: 'stun:stun.l.google.com:19302' },
-    { urls: 'stun:global.stun.twilio.com:3478' }
-  ],
   iceCandidatePoolSize: 2,
   bundlePolicy: 'max-bundle',
   rtcpMuxPolicy: 'require',
@@ -31,10 +28,15 @@ export const AUDIO_CONSTRAINTS: MediaTrackConstraints = {
 }
 
 /**
- * Create a new RTCPeerConnection with default configuration.
+ * Create a new RTCPeerConnection with default configuration and the current ICE servers.
+ * Falls back to no ICE servers (host candidates only, fine on a LAN) if they can't be fetched.
  */
-export function createPeerConnection(): RTCPeerConnection {
-  const pc = new RTCPeerConnection(PEER_CONNECTION_CONFIG)
+export async function createPeerConnection(): Promise<RTCPeerConnection> {
+  const iceServers = await getIceServers().catch((e) => {
+    console.warn('[WebRTC] Failed to get ICE servers:', e)
+    return []
+  })
+  const pc = new RTCPeerConnection({ ...PEER_CONNECTION_CONFIG, iceServers })
 
   // Log connection state changes for debugging
   pc.onconnectionstatechange = () => {
@@ -81,6 +83,45 @@ export async function isLastPathP2P(pc: RTCPeerConnection): Promise<boolean> {
   }
 }
 
+/**
+ * Inbound audio loss/jitter and the selected pair's RTT, in the shape `reportPeerConnectionStats` takes.
+ * Null until the connection has received audio.
+ */
+export async function getInboundAudioStats(pc: RTCPeerConnection): Promise<ReportedPeerStats | null> {
+  try {
+    const report = await pc.getStats()
+    let inbound: ReportedPeerStats | null = null
+    let rttMs: number | null = null
+    report.forEach((stats) => {
+      const s = stats as RTCStats & {
+        type: string
+        kind?: string
+        packetsReceived?: number
+        packetsLost?: number
+        jitter?: number
+        state?: string
+        nominated?: boolean
+        currentRoundTripTime?: number
+      }
+      if (s.type === 'inbound-rtp' && s.kind === 'audio') {
+        inbound = {
+          packets_received: s.packetsReceived ?? 0,
+          packets_lost: Math.max(0, s.packetsLost ?? 0),
+          jitter_ms: (s.jitter ?? 0) * 1000,
+          rtt_ms: null,
+        }
+      }
+      if (s.type === 'candidate-pair' && s.state === 'succeeded' && s.nominated && s.currentRoundTripTime != null) {
+        rttMs = s.currentRoundTripTime * 1000
+      }
+    })
+    if (!inbound) return null
+    return { ...(inbound as ReportedPeerStats), rtt_ms: rttMs }
+  } catch {
+    return null
+  }
+}
+
 /**
  * Create an SDP offer
  *
@@ -13,7 +13,7 @@ import { TransferCenterButton } from '../components/TransferCenterButton'
 import { NotificationCenterButton } from '../components/NotificationCenterButton'
 import { useNotificationsModal } from '../contexts/NotificationsModalContext'
 import { FriendsOverlay } from '../components/FriendsOverlay'
-import { createServer, deleteServer, type Server, parseInviteUri, publishServerHintOpaque, publishServerHintMemberLeft, redeemTemporaryInvite, readClipboardText } from '../lib/tauri'
+import { createServer, deleteServer, type Server, type ParsedInvite, parseInviteLink, takePendingInviteLink, listenInviteLinks, publishServerHintOpaque, publishServerHintMemberLeft, redeemTemporaryInvite, readClipboardText } from '../lib/tauri'
 import { useIdentity } from '../contexts/IdentityContext'
 import { usePresence, type PresenceLevel } from '../contexts/PresenceContext'
 import { useVoicePresence } from '../contexts/VoicePresenceContext'
@@ -63,6 +63,8 @@ function ServerListPage() {
   const [serverName, setServerName] = useState('')
   const [inviteCode, setInviteCode] = useState('')
   const [showJoinInline, setShowJoinInline] = useState(false)
+  /** Invite from a clicked cordia:// link; its code is prefilled and it may point at another beacon. */
+  const [linkInvite, setLinkInvite] = useState<ParsedInvite | null>(null)
   const [showCreateInline, setShowCreateInline] = useState(false)
   const joinFirstInputRef = useRef<HTMLInputElement | null>(null)
   const joinSecondInputRef = useRef<HTMLInputElement | null>(null)
@@ -137,9 +139,47 @@ function ServerListPage() {
     } else {
       setInviteCode('')
       setPastedJoinCode(false)
+      setLinkInvite(null)
     }
   }, [showJoinInline])
 
+  const inviteCodeForInput = (token: string) => token.replace(/\W/g, '').toUpperCase().slice(0, 8)
+
+  /** Validate a cordia:// link and open the join popover with its code filled in. */
+  const applyInviteLink = async (link: string) => {
+    try {
+      const parsed = await parseInviteLink(link)
+      if (!parsed.token) {
+        toast('This invite link has no invite code.')
+        return
+      }
+      setLinkInvite(parsed)
+      setInviteCode(inviteCodeForInput(parsed.token))
+      setShowJoinInline(true)
+    } catch (error) {
+      toast(typeof error === 'string' ? error : 'Invalid invite link.')
+    }
+  }
+
+  // Links clicked outside the app (App routes here first when one arrives)
+  useEffect(() => {
+    let cancelled = false
+    const openInviteLink = async () => {
+      const link = await takePendingInviteLink().catch(() => null)
+      if (link && !cancelled) await applyInviteLink(link)
+    }
+    openInviteLink()
+    let unlisten: (() => void) | null = null
+    listenInviteLinks(() => openInviteLink()).then((fn) => {
+      if (cancelled) fn()
+      else unlisten = fn
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [])
+
   useEffect(() => {
     if (showCreateInline) {
       setTimeout(() => createInputRef.current?.focus(), 0)
@@ -559,19 +599,10 @@ function ServerListPage() {
       let effectiveBeaconUrl = beaconUrl || ''
       let inviteCode: string | null = null
 
-      if (/^rmmt:\/\//i.test(input)) {
-        const parsed = parseInviteUri(input)
-        if (!parsed) {
-          toast('Invalid invite. Paste the full invite link (cordia://...).')
-          return
-        }
-
-        effectiveBeaconUrl =
-          parsed.server.startsWith('ws://') || parsed.server.startsWith('wss://')
-            ? parsed.server
-            : `wss://${parsed.server}`
-        // Temporary invites use cordia://{code}@{server}
-        inviteCode = parsed.signingPubkey
+      if (linkInvite?.token && input === inviteCodeForInput(linkInvite.token)) {
+        const server = linkInvite.beacon_url
+        effectiveBeaconUrl = server.startsWith('ws://') || server.startsWith('wss://') ? server : `wss://${server}`
+        inviteCode = linkInvite.token
       } else {
         if (!effectiveBeaconUrl) {
           toast('No beacon configured.')
@@ -618,13 +649,15 @@ function ServerListPage() {
       )
       if (currentAccountId) clearDraft(currentAccountId, deleteTarget.signing_pubkey)
 
-      // Best-effort: advertise leave to other members (requires symmetric key to encrypt hint).
-      // If this fails (e.g. server missing key), we still delete locally so the user can always leave.
+      // Best-effort: advertise leave to other members (the owner republishes the hint; anyone
+      // else posts a signed leave for the owner to apply). If this fails we still delete locally
+      // so the user can always leave, but say so: others keep seeing them until the owner syncs.
       if (identity && beaconStatus === 'connected' && beaconUrl) {
         try {
           await publishServerHintMemberLeft(beaconUrl, serverId, identity.user_id)
-        } catch (_) {
-          // Ignore: allow delete even when we can't notify the beacon (e.g. missing symmetric key).
+        } catch (error) {
+          console.warn('Failed to announce leaving the server:', error)
+          toast("Left the server, but couldn't tell the other members.")
         }
       }
 
@@ -786,6 +819,11 @@ function ServerListPage() {
                                   const text = (window as { __TAURI__?: unknown }).__TAURI__
                                     ? await readClipboardText()
                                     : await navigator.clipboard.readText()
+                                  const trimmed = (text ?? '').trim()
+                                  if (/^cordia:\/\//i.test(trimmed)) {
+                                    await applyInviteLink(trimmed)
+                                    return
+                                  }
                                   const raw = (text ?? '').replace(/\W/g, '').toUpperCase().slice(0, 8)
                                   setInviteCode(raw)
                                   setPastedJoinCode(true)
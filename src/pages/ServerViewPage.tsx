@@ -2,12 +2,12 @@ import { forwardRef, useCallback, useEffect, useMemo, useRef, useState, type Com
 import { createPortal } from 'react-dom'
 import { useParams, useNavigate, useLocation } from 'react-router-dom'
 import type { VirtuosoHandle } from 'react-virtuoso'
-import { ArrowLeft, Copy, Check, EyeOff, Plus, Minus, X, Volume2, VolumeX } from 'lucide-react'
+import { ArrowLeft, Copy, Check, EyeOff, Plus, Minus, X, Volume2, VolumeX, QrCode } from 'lucide-react'
 import { open, confirm } from '@tauri-apps/api/dialog'
 import { listen } from '@tauri-apps/api/event'
 import { convertFileSrc } from '@tauri-apps/api/tauri'
 import { Button } from '../components/ui/button'
-import { loadServer, type Server, fetchAndImportServerHintOpaque, createTemporaryInvite, revokeActiveInvite, getFileMetadata, computeFileSha256, registerAttachmentFromPath, getAttachmentRecord, shareAttachmentAgain } from '../lib/tauri'
+import { loadServer, type Server, fetchAndImportServerHintOpaque, createTemporaryInvite, revokeActiveInvite, createInviteLink, renderInviteQr, getFileMetadata, computeFileSha256, registerAttachmentFromPath, getAttachmentRecord, shareAttachmentAgain } from '../lib/tauri'
 import { UserProfileCard } from '../components/UserProfileCard'
 import { UserCard } from '../components/UserCard'
 import { useIdentity } from '../contexts/IdentityContext'
@@ -159,6 +159,8 @@ function ServerViewPage() {
   const [isRevokingInvite, setIsRevokingInvite] = useState(false)
   const [showInviteCodePopover, setShowInviteCodePopover] = useState(false)
   const [revealInviteCode, setRevealInviteCode] = useState(false)
+  const [inviteQr, setInviteQr] = useState<string | null>(null)
+  const [isLoadingInviteQr, setIsLoadingInviteQr] = useState(false)
   const [inviteCodeButtonRect, setInviteCodeButtonRect] = useState<DOMRect | null>(null)
   const inviteCodeButtonRef = useRef<HTMLButtonElement>(null)
   const [profileCardUserId, setProfileCardUserId] = useState<string | null>(null)
@@ -354,8 +356,8 @@ function ServerViewPage() {
       return
     }
 
-    // If we don't have server data from navigation state, load it from disk
-    if (!server) {
+    // If we don't have server data from navigation state (or it's the previous server's), load it from disk
+    if (!server || server.id !== serverId) {
       loadServerData()
     } else {
       // We have server data, but still sync with beacon in background
@@ -442,6 +444,25 @@ function ServerViewPage() {
     }
   }
 
+  const handleShowInviteQr = async () => {
+    const uri = getActiveInviteUri()
+    if (!serverId || !uri || !beaconUrl) return
+    setIsLoadingInviteQr(true)
+    try {
+      // Owners get a signed link; other members can only share the plain invite URI.
+      const link = await createInviteLink(beaconUrl, serverId, false).catch(() => uri)
+      setInviteQr(await renderInviteQr(link))
+    } catch (e) {
+      console.warn('Failed to render invite QR code:', e)
+    } finally {
+      setIsLoadingInviteQr(false)
+    }
+  }
+
+  useEffect(() => {
+    if (!showInviteCodePopover) setInviteQr(null)
+  }, [showInviteCodePopover])
+
   const handleRevokeInvite = async () => {
     if (!serverId) return
     if (beaconStatus !== 'connected' || !beaconUrl) return
@@ -481,6 +502,18 @@ function ServerViewPage() {
     leaveVoice()
   }
 
+  // Opened by the switch-voice-channel keybind: join once the server is loaded
+  const joinVoiceRequested = (location.state as { joinVoice?: boolean } | null)?.joinVoice === true
+  useEffect(() => {
+    // `server` may still be the previous one right after navigating between servers
+    if (!joinVoiceRequested || !server || server.id !== serverId || !identity || !groupChat) return
+    navigate(location.pathname, { replace: true, state: { server } })
+    if (webrtcIsInVoice && currentRoomId === groupChat.id) return
+    joinVoice(groupChat.id, server.id, identity.user_id, server.signing_pubkey).catch((error) => {
+      console.error('Failed to join voice:', error)
+    })
+  }, [joinVoiceRequested, server, identity, groupChat, webrtcIsInVoice, currentRoomId, joinVoice, navigate, location.pathname, serverId])
+
   useEffect(() => {
     const unlistenPromise = listen<{ attachment_id: string; ok: boolean; error?: string }>(
       'cordia:attachment-ready',
@@ -1336,6 +1369,20 @@ function ServerViewPage() {
                         </Tooltip>
                       </div>
                     </button>
+                    {inviteQr ? (
+                      <img src={inviteQr} alt="Invite QR code" className="w-full rounded-sm bg-white p-1" />
+                    ) : (
+                      <Button
+                        variant="outline"
+                        size="sm"
+                        className="w-full justify-start gap-2 font-light"
+                        disabled={isLoadingInviteQr || !beaconUrl}
+                        onClick={handleShowInviteQr}
+                      >
+                        <QrCode className="h-3.5 w-3.5" />
+                        {isLoadingInviteQr ? 'Rendering…' : 'Show QR code'}
+                      </Button>
+                    )}
                   </>
                 )}
               </div>
@@ -11,6 +11,7 @@ function SettingsPage() {
     const tabParam = searchParams.get('tab')
     const tab: SettingsTab =
       tabParam === 'audio' ||
+      tabParam === 'keybinds' ||
       tabParam === 'connections' ||
       tabParam === 'messages' ||
       tabParam === 'downloads' ||
@@ -7,6 +7,7 @@ import { Label } from '../../components/ui/label'
 import { useEffect, useMemo, useRef, useState } from 'react'
 import { AvatarCropModal } from '../../components/AvatarCropModal'
 import { useToast } from '../../contexts/ToastContext'
+import { getSettings, listenSettings, setSettings, type IdleSettings } from '../../lib/settings'
 
 export function AccountSettings() {
   const { identity } = useIdentity()
@@ -30,6 +31,32 @@ export function AccountSettings() {
   const [draftRealName, setDraftRealName] = useState(currentRealName)
   const [draftShowRealName, setDraftShowRealName] = useState(currentShowRealName)
   const [saveMessage, setSaveMessage] = useState('')
+  const [idleSettings, setIdleSettings] = useState<IdleSettings | null>(null)
+
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    getSettings().then((s) => { if (!cancelled) setIdleSettings(s.idle) }).catch(() => {})
+    listenSettings((s) => setIdleSettings(s.idle)).then((u) => {
+      if (cancelled) u()
+      else unlisten = u
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [])
+
+  const applyIdleSettings = async (next: IdleSettings) => {
+    setIdleSettings(next)
+    try {
+      const settings = await getSettings()
+      const saved = await setSettings({ ...settings, idle: next })
+      setIdleSettings(saved.idle)
+    } catch (error) {
+      console.error('Failed to save idle settings:', error)
+    }
+  }
 
   // Keep drafts in sync when switching accounts / loading, but don't stomp user edits.
   useEffect(() => {
@@ -282,6 +309,34 @@ export function AccountSettings() {
             </div>
           </button>
         </div>
+        {idleSettings && (
+          <div className="space-y-2">
+            <p className="text-xs font-medium uppercase tracking-wider text-muted-foreground">Away</p>
+            <label className="flex items-center justify-between gap-3 text-xs">
+              <span className="text-muted-foreground">Show me as idle when I'm not using my computer</span>
+              <input
+                type="checkbox"
+                checked={idleSettings.enabled}
+                onChange={(e) => applyIdleSettings({ ...idleSettings, enabled: e.target.checked })}
+              />
+            </label>
+            <label className="flex items-center justify-between gap-3 text-xs">
+              <span className="text-muted-foreground">Minutes without input</span>
+              <Input
+                type="number"
+                min={1}
+                max={240}
+                className="w-20 h-8 text-xs"
+                disabled={!idleSettings.enabled}
+                value={idleSettings.after_minutes}
+                onChange={(e) => {
+                  const minutes = Number(e.target.value)
+                  if (Number.isFinite(minutes) && minutes > 0) applyIdleSettings({ ...idleSettings, after_minutes: minutes })
+                }}
+              />
+            </label>
+          </div>
+        )}
       </div>
 
       {pendingCropUrl && (
@@ -4,7 +4,15 @@ import { Button } from '../../components/ui/button'
 import { Select } from '../../components/ui/select'
 import { Slider } from '../../components/ui/slider'
 import { VoiceLevelMeter } from '../../components/VoiceLevelMeter'
-import { loadAudioSettings, saveAudioSettings, AudioSettings } from '../../lib/tauri'
+import {
+  loadAudioSettings,
+  saveAudioSettings,
+  AudioSettings,
+  getKeybindStatus,
+  listenKeybindStatus,
+  listenGlobalPtt,
+  type KeybindStatus,
+} from '../../lib/tauri'
 import { enumerateAudioDevices, AudioDevice, setupDeviceChangeListener } from '../../lib/audio'
 import { useWebRTC } from '../../contexts/WebRTCContext'
 
@@ -30,6 +38,7 @@ export function AudioSettingsPage() {
   const [isDragging, setIsDragging] = useState(false)
   const [isCapturingKey, setIsCapturingKey] = useState(false)
   const [isPttKeyPressed, setIsPttKeyPressed] = useState(false)
+  const [hotkeyStatus, setHotkeyStatus] = useState<KeybindStatus | null>(null)
   const [deviceChangeBlocked, setDeviceChangeBlocked] = useState(false)
   const [deviceChangeInProgress, setDeviceChangeInProgress] = useState(false)
   const deviceSwapInProgressRef = useRef(false) // Prevent concurrent swaps
@@ -283,9 +292,24 @@ export function AudioSettingsPage() {
     }
   }, [isCapturingKey])
 
-  // PTT key press/release listener (only active when monitoring and in PTT mode)
+  // System-wide PTT: registration outcome, and presses made while another app (or this one) has focus
   useEffect(() => {
-    if (!isMonitoring || audioSettings.input_mode !== 'push_to_talk' || !audioSettings.push_to_talk_key) {
+    let cancelled = false
+    const unlisteners: Array<() => void> = []
+    const pttStatus = (status: KeybindStatus[]) => status.find((s) => s.action === 'push_to_talk') ?? null
+    getKeybindStatus().then((status) => { if (!cancelled) setHotkeyStatus(pttStatus(status)) }).catch(() => {})
+    listenKeybindStatus((status) => setHotkeyStatus(pttStatus(status))).then((u) => unlisteners.push(u))
+    listenGlobalPtt((pressed) => setIsPttKeyPressed(pressed)).then((u) => unlisteners.push(u))
+    return () => {
+      cancelled = true
+      unlisteners.forEach((u) => u())
+    }
+  }, [])
+
+  // PTT key press/release listener (only active when monitoring and in PTT or hybrid mode)
+  useEffect(() => {
+    const usesPtt = audioSettings.input_mode === 'push_to_talk' || audioSettings.input_mode === 'hybrid'
+    if (!isMonitoring || !usesPtt || !audioSettings.push_to_talk_key) {
       return
     }
 
@@ -474,6 +498,16 @@ export function AudioSettingsPage() {
             >
               Voice Activity
             </button>
+            <button
+              onClick={() => handleAudioSettingsChange({ input_mode: 'level_gate' })}
+              className={`flex-1 px-4 py-2 text-sm font-light rounded-md transition-colors ${
+                audioSettings.input_mode === 'level_gate'
+                  ? 'bg-primary text-primary-foreground'
+                  : 'bg-muted hover:bg-muted/80 text-muted-foreground'
+              }`}
+            >
+              Level Gate
+            </button>
             <button
               onClick={() => handleAudioSettingsChange({ input_mode: 'push_to_talk' })}
               className={`flex-1 px-4 py-2 text-sm font-light rounded-md transition-colors ${
@@ -484,11 +518,31 @@ export function AudioSettingsPage() {
             >
               Push to Talk
             </button>
+            <button
+              onClick={() => handleAudioSettingsChange({ input_mode: 'hybrid' })}
+              className={`flex-1 px-4 py-2 text-sm font-light rounded-md transition-colors ${
+                audioSettings.input_mode === 'hybrid'
+                  ? 'bg-primary text-primary-foreground'
+                  : 'bg-muted hover:bg-muted/80 text-muted-foreground'
+              }`}
+            >
+              Hybrid
+            </button>
+            <button
+              onClick={() => handleAudioSettingsChange({ input_mode: 'open_mic' })}
+              className={`flex-1 px-4 py-2 text-sm font-light rounded-md transition-colors ${
+                audioSettings.input_mode === 'open_mic'
+                  ? 'bg-primary text-primary-foreground'
+                  : 'bg-muted hover:bg-muted/80 text-muted-foreground'
+              }`}
+            >
+              Open Mic
+            </button>
           </div>
         </div>
 
-        {/* Voice Activity Mode - Threshold Slider */}
-        {audioSettings.input_mode === 'voice_activity' && (
+        {/* Gated Modes - Threshold Slider */}
+        {audioSettings.input_mode !== 'push_to_talk' && audioSettings.input_mode !== 'open_mic' && (
           <div className="space-y-3">
             <div className="flex items-center justify-between">
               <label className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
@@ -513,8 +567,8 @@ export function AudioSettingsPage() {
           </div>
         )}
 
-        {/* Push to Talk Mode - Key Binding */}
-        {audioSettings.input_mode === 'push_to_talk' && (
+        {/* Push to Talk / Hybrid Mode - Key Binding */}
+        {(audioSettings.input_mode === 'push_to_talk' || audioSettings.input_mode === 'hybrid') && (
           <div className="space-y-3">
             <label className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
               Push to Talk Key
@@ -534,6 +588,15 @@ export function AudioSettingsPage() {
                 ? 'Listening for key press...'
                 : 'Click and press any key to set your Push to Talk binding.'}
             </p>
+            {!isCapturingKey && hotkeyStatus?.binding === audioSettings.push_to_talk_key && (
+              hotkeyStatus?.conflict ? (
+                <p className="text-xs text-red-500 font-light">
+                  Only works while Cordia is focused: {hotkeyStatus.conflict}
+                </p>
+              ) : hotkeyStatus?.registered ? (
+                <p className="text-xs text-muted-foreground font-light">Works in other apps too.</p>
+              ) : null
+            )}
 
             {/* PTT Active Indicator */}
             {isMonitoring && audioSettings.push_to_talk_key && (
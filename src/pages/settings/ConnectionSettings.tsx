@@ -1,14 +1,51 @@
 import { useState, useEffect, useMemo } from 'react'
-import { Save, RefreshCw } from 'lucide-react'
+import { Save, RefreshCw, Stethoscope, Trash2 } from 'lucide-react'
 import { Button } from '../../components/ui/button'
 import { Input } from '../../components/ui/input'
 import { Label } from '../../components/ui/label'
 import { Select } from '../../components/ui/select'
+import { useAccount } from '../../contexts/AccountContext'
 import { useBeacon } from '../../contexts/BeaconContext'
 import { useToast } from '../../contexts/ToastContext'
-import { getBeaconUrl, setBeaconUrl } from '../../lib/tauri'
+import {
+  getBeaconUrl,
+  setBeaconUrl,
+  measureBeaconLatency,
+  probeBeacon,
+  runNetworkDiagnostics,
+  getVoiceBitrate,
+  saveBeaconProfile,
+  deleteBeaconProfile,
+  switchBeaconProfile,
+  getProxySettings,
+  setProxySettings,
+  getBeaconTlsOptions,
+  setBeaconTlsOptions,
+  type BeaconLatency,
+  type DiagnosticsReport,
+  type DiagnosticStatus,
+  type VoiceBitrate,
+  type ProxyMode,
+} from '../../lib/tauri'
+import { getSettings, listenSettings, setSettings, type AppSettings, type BeaconProfile } from '../../lib/settings'
 import { getNatOverride, setNatOverride, type NatOverride } from '../../lib/natOverride'
-import { PEER_CONNECTION_CONFIG } from '../../lib/webrtc'
+import { createPeerConnection } from '../../lib/webrtc'
+
+const DIAGNOSTIC_COLORS: Record<DiagnosticStatus, string> = {
+  pass: 'text-green-500',
+  skipped: 'text-muted-foreground',
+  warn: 'text-amber-500',
+  fail: 'text-red-500',
+}
+
+const DIAGNOSTIC_LABELS: Record<DiagnosticStatus, string> = {
+  pass: 'OK',
+  skipped: 'Skipped',
+  warn: 'Warning',
+  fail: 'Failed',
+}
+
+const VOICE_BITRATE_CAPS = [64, 48, 32, 24, 16, 12]
 
 type NatIndicator = 'checking' | 'local_only' | 'nat' | 'relay' | 'unknown'
 
@@ -16,7 +53,7 @@ let natProbePromise: Promise<NatIndicator> | null = null
 
 async function probeNatIndicator(): Promise<NatIndicator> {
   try {
-    const pc = new RTCPeerConnection(PEER_CONNECTION_CONFIG)
+    const pc = await createPeerConnection()
     pc.createDataChannel('rmmt-nat-probe')
 
     const candidates: string[] = []
@@ -62,13 +99,30 @@ async function probeNatIndicator(): Promise<NatIndicator> {
 
 export function ConnectionSettings() {
   const { toast } = useToast()
-  const { status, checkHealth, reloadUrl } = useBeacon()
+  const { status, degraded, statusReason, beaconUrl, checkHealth, reloadUrl } = useBeacon()
+  const { currentAccountId, switchToAccount } = useAccount()
   const [url, setUrl] = useState('')
   const [isSaving, setIsSaving] = useState(false)
   const [isChecking, setIsChecking] = useState(false)
   const [saveMessage, setSaveMessage] = useState('')
   const [natOverride, setNatOverrideState] = useState<NatOverride>('auto')
   const [nat, setNat] = useState<NatIndicator>('checking')
+  const [latency, setLatency] = useState<BeaconLatency | null>(null)
+  const [proxyMode, setProxyMode] = useState<ProxyMode>('system')
+  const [proxyUrl, setProxyUrl] = useState('')
+  const [isSavingProxy, setIsSavingProxy] = useState(false)
+  const [caCertPem, setCaCertPem] = useState('')
+  const [spkiPin, setSpkiPin] = useState('')
+  const [isSavingTls, setIsSavingTls] = useState(false)
+  const [diagnostics, setDiagnostics] = useState<DiagnosticsReport | null>(null)
+  const [isDiagnosing, setIsDiagnosing] = useState(false)
+  const [maxVoiceKbps, setMaxVoiceKbps] = useState<number | null>(null)
+  const [voiceBitrate, setVoiceBitrate] = useState<VoiceBitrate | null>(null)
+  const [profiles, setProfiles] = useState<BeaconProfile[]>([])
+  const [activeProfile, setActiveProfile] = useState<string | null>(null)
+  const [selectedProfile, setSelectedProfile] = useState('')
+  const [profileName, setProfileName] = useState('')
+  const [isSwitching, setIsSwitching] = useState(false)
 
   // Load current signaling server URL
   useEffect(() => {
@@ -79,6 +133,75 @@ export function ConnectionSettings() {
     setNatOverrideState(getNatOverride())
   }, [])
 
+  useEffect(() => {
+    getProxySettings()
+      .then((p) => {
+        setProxyMode(p.mode)
+        setProxyUrl(p.url ?? '')
+      })
+      .catch(console.error)
+  }, [])
+
+  // Measure round-trip time whenever the beacon is (re)confirmed reachable
+  useEffect(() => {
+    if (status !== 'connected' || !beaconUrl) {
+      setLatency(null)
+      return
+    }
+    let cancelled = false
+    measureBeaconLatency(beaconUrl)
+      .then((l) => { if (!cancelled) setLatency(l) })
+      .catch(() => { if (!cancelled) setLatency(null) })
+    return () => { cancelled = true }
+  }, [status, beaconUrl])
+
+  useEffect(() => {
+    let unlisten: (() => void) | null = null
+    let cancelled = false
+    const apply = (s: AppSettings) => {
+      setMaxVoiceKbps(s.bandwidth.max_voice_kbps)
+      setProfiles(s.beacon_profiles)
+      setActiveProfile(s.active_beacon_profile)
+    }
+    getSettings().then((s) => { if (!cancelled) apply(s) }).catch(() => {})
+    listenSettings(apply).then((u) => {
+      if (cancelled) u()
+      else unlisten = u
+    })
+    return () => {
+      cancelled = true
+      unlisten?.()
+    }
+  }, [])
+
+  // The measured rate updates every couple of seconds while the encoder runs
+  useEffect(() => {
+    let cancelled = false
+    const poll = () => {
+      getVoiceBitrate()
+        .then((b) => { if (!cancelled) setVoiceBitrate(b) })
+        .catch(() => {})
+    }
+    poll()
+    const timer = window.setInterval(poll, 2000)
+    return () => {
+      cancelled = true
+      window.clearInterval(timer)
+    }
+  }, [])
+
+  const handleMaxVoiceKbps = async (next: number | null) => {
+    setMaxVoiceKbps(next)
+    try {
+      const settings = await getSettings()
+      const saved = await setSettings({ ...settings, bandwidth: { ...settings.bandwidth, max_voice_kbps: next } })
+      setMaxVoiceKbps(saved.bandwidth.max_voice_kbps)
+    } catch (error) {
+      console.error('Failed to save bandwidth cap:', error)
+      toast('Failed to save bandwidth cap')
+    }
+  }
+
   useEffect(() => {
     if (!natProbePromise) natProbePromise = probeNatIndicator()
     natProbePromise.then(setNat).catch(() => setNat('unknown'))
@@ -152,20 +275,64 @@ export function ConnectionSettings() {
     }
   }
 
+  useEffect(() => {
+    if (!beaconUrl) return
+    getBeaconTlsOptions(beaconUrl)
+      .then((o) => {
+        setCaCertPem(o.ca_cert_pem ?? '')
+        setSpkiPin(o.spki_pin ?? '')
+      })
+      .catch(console.error)
+  }, [beaconUrl])
+
+  const handleSaveTls = async () => {
+    if (!beaconUrl) return
+    setIsSavingTls(true)
+    try {
+      await setBeaconTlsOptions(beaconUrl, {
+        ca_cert_pem: caCertPem.trim() || null,
+        spki_pin: spkiPin.trim() || null,
+      })
+      await checkHealth()
+      toast('TLS settings saved')
+    } catch (error) {
+      console.error('Failed to save TLS settings:', error)
+      toast(typeof error === 'string' ? error : 'Failed to save TLS settings')
+    } finally {
+      setIsSavingTls(false)
+    }
+  }
+
+  const handleSaveProxy = async () => {
+    setIsSavingProxy(true)
+    try {
+      await setProxySettings({ mode: proxyMode, url: proxyUrl.trim() || null })
+      // Re-check through the new route
+      await checkHealth()
+      toast('Proxy settings saved')
+    } catch (error) {
+      console.error('Failed to save proxy settings:', error)
+      toast(typeof error === 'string' ? error : 'Failed to save proxy settings')
+    } finally {
+      setIsSavingProxy(false)
+    }
+  }
+
   const handleCheck = async () => {
     setIsChecking(true)
     setSaveMessage('')
     try {
       await checkHealth()
-      // Show result after check completes
-      setTimeout(() => {
-        if (status === 'connected') {
-          setSaveMessage('Connection successful!')
-        } else {
-          toast('Connection failed - check URL and server')
-        }
+      // The health endpoint can pass while WebSocket upgrades fail (e.g. a proxy dropping them)
+      const probe = await probeBeacon(beaconUrl || undefined)
+      if (probe.http_ok && probe.ws_ok) {
+        setSaveMessage('Connection successful!')
         setTimeout(() => setSaveMessage(''), 3000)
-      }, 500)
+      } else if (probe.http_ok) {
+        toast(`Beacon reachable, but WebSocket failed: ${probe.ws_error ?? 'unknown error'}`)
+      } else {
+        toast('Connection failed - check URL and server')
+      }
     } catch (error) {
       console.error('Check failed:', error)
       toast('Connection failed')
@@ -174,10 +341,76 @@ export function ConnectionSettings() {
     }
   }
 
+  const handleSaveProfile = async () => {
+    try {
+      const saved = await saveBeaconProfile({
+        id: '',
+        name: profileName.trim(),
+        url: url.trim(),
+        proxy: { mode: proxyMode, url: proxyUrl.trim() || null },
+        tls: { ca_cert_pem: caCertPem.trim() || null, spki_pin: spkiPin.trim() || null },
+        account_id: currentAccountId,
+      })
+      setProfileName('')
+      setSelectedProfile(saved.id)
+      toast(`Saved profile ${saved.name}`)
+    } catch (error) {
+      console.error('Failed to save beacon profile:', error)
+      toast(typeof error === 'string' ? error : 'Failed to save profile')
+    }
+  }
+
+  const handleSwitchProfile = async () => {
+    if (!selectedProfile) return
+    setIsSwitching(true)
+    try {
+      const profile = await switchBeaconProfile(selectedProfile)
+      // The session already points at the profile's account; load its identity and servers
+      if (profile.account_id && profile.account_id !== currentAccountId) {
+        await switchToAccount(profile.account_id)
+      }
+      setUrl(profile.url)
+      setProxyMode(profile.proxy.mode)
+      setProxyUrl(profile.proxy.url ?? '')
+      setCaCertPem(profile.tls.ca_cert_pem ?? '')
+      setSpkiPin(profile.tls.spki_pin ?? '')
+      toast(`Switched to ${profile.name}`)
+    } catch (error) {
+      console.error('Failed to switch beacon profile:', error)
+      toast(typeof error === 'string' ? error : 'Failed to switch profile')
+    } finally {
+      setIsSwitching(false)
+    }
+  }
+
+  const handleDeleteProfile = async () => {
+    if (!selectedProfile) return
+    try {
+      await deleteBeaconProfile(selectedProfile)
+      setSelectedProfile('')
+    } catch (error) {
+      console.error('Failed to delete beacon profile:', error)
+      toast('Failed to delete profile')
+    }
+  }
+
+  const handleDiagnose = async () => {
+    setIsDiagnosing(true)
+    setDiagnostics(null)
+    try {
+      setDiagnostics(await runNetworkDiagnostics(beaconUrl || undefined))
+    } catch (error) {
+      console.error('Diagnostics failed:', error)
+      toast(typeof error === 'string' ? error : 'Diagnostics failed')
+    } finally {
+      setIsDiagnosing(false)
+    }
+  }
+
   const getStatusText = () => {
     switch (status) {
       case 'connected':
-        return 'Connected'
+        return degraded ? 'Degraded' : 'Connected'
       case 'disconnected':
         return 'Disconnected'
       case 'checking':
@@ -200,6 +433,68 @@ export function ConnectionSettings() {
       </div>
 
       <div className="space-y-8">
+        {/* Profiles */}
+        <div className="space-y-3">
+          <Label htmlFor="beacon-profile" className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+            Profiles
+          </Label>
+          {profiles.length > 0 && (
+            <div className="flex gap-2">
+              <Select
+                id="beacon-profile"
+                value={selectedProfile}
+                onChange={(e) => setSelectedProfile(e.target.value)}
+                className="flex-1"
+              >
+                <option value="">Choose a profile…</option>
+                {profiles.map((profile) => (
+                  <option key={profile.id} value={profile.id}>
+                    {profile.name}{profile.id === activeProfile ? ' (active)' : ''}
+                  </option>
+                ))}
+              </Select>
+              <Button
+                onClick={handleSwitchProfile}
+                disabled={isSwitching || !selectedProfile}
+                variant="outline"
+                className="h-11 font-light"
+              >
+                {isSwitching ? 'Switching...' : 'Switch'}
+              </Button>
+              <Button
+                onClick={handleDeleteProfile}
+                disabled={!selectedProfile}
+                variant="ghost"
+                className="h-11"
+                title="Delete profile"
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          )}
+          <div className="flex gap-2">
+            <Input
+              type="text"
+              value={profileName}
+              onChange={(e) => setProfileName(e.target.value)}
+              placeholder="Profile name (e.g. Self-hosted)"
+              className="flex-1 text-sm h-11"
+            />
+            <Button
+              onClick={handleSaveProfile}
+              disabled={!url.trim()}
+              variant="outline"
+              className="h-11 font-light gap-2"
+            >
+              <Save className="h-4 w-4" />
+              Save current
+            </Button>
+          </div>
+          <p className="text-xs text-muted-foreground font-light">
+            A profile keeps the beacon URL, proxy, TLS options and account below. Switching reconnects everything to it.
+          </p>
+        </div>
+
         {/* Beacon URL */}
         <div className="space-y-3">
           <Label htmlFor="signaling-url" className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
@@ -239,7 +534,9 @@ export function ConnectionSettings() {
               <div
                 className={`w-2 h-2 rounded-none ${
                   status === 'connected'
-                    ? 'bg-success'
+                    ? degraded
+                      ? 'bg-warning'
+                      : 'bg-success'
                     : status === 'checking'
                       ? 'bg-warning animate-pulse'
                       : status === 'disconnected'
@@ -248,10 +545,25 @@ export function ConnectionSettings() {
                 }`}
               />
               <div className="min-w-0">
-                <p className="text-sm font-light">{getStatusText()}</p>
+                <p className="text-sm font-light">
+                  {getStatusText()}
+                  {latency && (
+                    <span
+                      className="ml-2 text-xs text-muted-foreground"
+                      title={`min ${latency.min_ms.toFixed(0)} ms · p95 ${latency.p95_ms.toFixed(0)} ms`}
+                    >
+                      {latency.avg_ms.toFixed(0)} ms
+                    </span>
+                  )}
+                </p>
                 <p className="text-xs text-muted-foreground font-light truncate">
                   {url || 'No beacon configured'}
                 </p>
+                {statusReason && (degraded || status === 'disconnected') && (
+                  <p className="text-xs text-muted-foreground font-light truncate" title={statusReason}>
+                    {statusReason}
+                  </p>
+                )}
               </div>
             </div>
             <Button
@@ -267,6 +579,149 @@ export function ConnectionSettings() {
           </div>
         </div>
 
+        {/* Diagnostics */}
+        <div className="space-y-3">
+          <div className="flex items-center justify-between gap-3">
+            <Label className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+              Having trouble connecting?
+            </Label>
+            <Button
+              variant="outline"
+              size="sm"
+              onClick={handleDiagnose}
+              disabled={isDiagnosing || !url}
+              className="h-9 font-light gap-2"
+            >
+              <Stethoscope className={`h-3 w-3 ${isDiagnosing ? 'animate-pulse' : ''}`} />
+              {isDiagnosing ? 'Running...' : 'Run diagnostics'}
+            </Button>
+          </div>
+          {diagnostics && (
+            <div className="border border-border/50 bg-muted/20 rounded-md p-4 space-y-2">
+              {diagnostics.checks.map((check) => (
+                <div key={check.id} className="flex items-start justify-between gap-3">
+                  <div className="min-w-0">
+                    <p className="text-sm font-light">{check.label}</p>
+                    <p className="text-xs text-muted-foreground font-light break-words">{check.detail}</p>
+                  </div>
+                  <div className="text-right shrink-0">
+                    <p className={`text-xs font-light ${DIAGNOSTIC_COLORS[check.status]}`}>
+                      {DIAGNOSTIC_LABELS[check.status]}
+                    </p>
+                    {check.duration_ms !== null && (
+                      <p className="text-xs text-muted-foreground font-light">{check.duration_ms.toFixed(0)} ms</p>
+                    )}
+                  </div>
+                </div>
+              ))}
+            </div>
+          )}
+          <p className="text-xs text-muted-foreground font-light">
+            Checks each step to the beacon (DNS, TCP, TLS, WebSocket), whether UDP gets out for voice, and whether this network needs a sign-in page.
+          </p>
+        </div>
+
+        {/* Proxy */}
+        <div className="space-y-3">
+          <Label htmlFor="proxy-mode" className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+            Proxy
+          </Label>
+          <div className="flex gap-2">
+            <Select
+              id="proxy-mode"
+              value={proxyMode}
+              onChange={(e) => setProxyMode(e.target.value as ProxyMode)}
+              className="w-40"
+            >
+              <option value="system">System</option>
+              <option value="direct">No proxy</option>
+              <option value="manual">Manual</option>
+            </Select>
+            <Input
+              type="text"
+              value={proxyUrl}
+              onChange={(e) => setProxyUrl(e.target.value)}
+              placeholder="socks5://127.0.0.1:1080"
+              disabled={proxyMode !== 'manual'}
+              className="flex-1 font-mono text-sm h-11"
+            />
+            <Button
+              onClick={handleSaveProxy}
+              disabled={isSavingProxy || (proxyMode === 'manual' && !proxyUrl.trim())}
+              variant="outline"
+              className="h-11 font-light gap-2"
+            >
+              <Save className="h-4 w-4" />
+              {isSavingProxy ? 'Saving...' : 'Save'}
+            </Button>
+          </div>
+          <p className="text-xs text-muted-foreground font-light">
+            Used for the beacon and signaling connection (http://, socks5:// or socks5h://). Voice itself is peer-to-peer.
+          </p>
+        </div>
+
+        {/* Voice bandwidth */}
+        <div className="space-y-3">
+          <Label htmlFor="voice-bitrate-cap" className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+            Voice bandwidth
+          </Label>
+          <Select
+            id="voice-bitrate-cap"
+            value={maxVoiceKbps === null ? '' : String(maxVoiceKbps)}
+            onChange={(e) => handleMaxVoiceKbps(e.target.value ? Number(e.target.value) : null)}
+          >
+            <option value="">No limit</option>
+            {VOICE_BITRATE_CAPS.map((kbps) => (
+              <option key={kbps} value={kbps}>Up to {kbps} kbps</option>
+            ))}
+          </Select>
+          {voiceBitrate && (
+            <p className="text-xs text-muted-foreground font-light">
+              Encoder set to {(voiceBitrate.effective_bps / 1000).toFixed(0)} kbps
+              {voiceBitrate.actual_bps !== null && ` · sending ${(voiceBitrate.actual_bps / 1000).toFixed(1)} kbps`}
+            </p>
+          )}
+          <p className="text-xs text-muted-foreground font-light">
+            Caps how much your voice uses on metered connections, per person in the call.
+          </p>
+        </div>
+
+        {/* TLS for self-hosted beacons */}
+        <div className="space-y-3">
+          <Label htmlFor="beacon-ca" className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+            Beacon TLS (self-hosted)
+          </Label>
+          <textarea
+            id="beacon-ca"
+            value={caCertPem}
+            onChange={(e) => setCaCertPem(e.target.value)}
+            placeholder="-----BEGIN CERTIFICATE----- (private CA, optional)"
+            rows={4}
+            className="w-full rounded-md border border-border/50 bg-background px-3 py-2 font-mono text-xs"
+          />
+          <div className="flex gap-2">
+            <Input
+              type="text"
+              value={spkiPin}
+              onChange={(e) => setSpkiPin(e.target.value)}
+              placeholder="SPKI pin: sha256/BASE64 (optional)"
+              className="flex-1 font-mono text-sm h-11"
+            />
+            <Button
+              onClick={handleSaveTls}
+              disabled={isSavingTls || !beaconUrl}
+              variant="outline"
+              className="h-11 font-light gap-2"
+            >
+              <Save className="h-4 w-4" />
+              {isSavingTls ? 'Saving...' : 'Save'}
+            </Button>
+          </div>
+          <p className="text-xs text-muted-foreground font-light">
+            Applies to the saved beacon only. Leave both empty to use the system certificate store.
+          </p>
+        </div>
+
         {/* NAT Type Display */}
         <div className="space-y-3">
           <Label className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
@@ -14,12 +14,12 @@ export function DownloadsSettings() {
   const [settings, setSettings] = useState<DownloadSettings>(DEFAULT_DOWNLOAD_SETTINGS)
 
   useEffect(() => {
-    setSettings(getDownloadSettings(currentAccountId))
+    getDownloadSettings(currentAccountId).then(setSettings).catch(() => {})
   }, [currentAccountId])
 
   const apply = (next: DownloadSettings) => {
     setSettings(next)
-    setDownloadSettings(currentAccountId, next)
+    setDownloadSettings(currentAccountId, next).catch(() => {})
   }
 
   const chooseFolder = async () => {
@@ -0,0 +1,202 @@
+import { useEffect, useState } from 'react'
+import { Plus, Trash2 } from 'lucide-react'
+import { Button } from '../../components/ui/button'
+import { Select } from '../../components/ui/select'
+import { getSettings, listenSettings, setSettings, type Keybind, type KeybindAction } from '../../lib/settings'
+import { getKeybindStatus, listenKeybindStatus, listServers, type KeybindStatus, type Server } from '../../lib/tauri'
+
+const SINGLE_ACTIONS: { action: KeybindAction; label: string; hint: string }[] = [
+  { action: 'toggle_mute', label: 'Toggle Mute', hint: 'Stop or resume sending your mic.' },
+  { action: 'toggle_deafen', label: 'Toggle Deafen', hint: 'Silence everyone else and mute your mic.' },
+  { action: 'open_overlay', label: 'Open Overlay', hint: 'Bring the Cordia window to the front.' },
+]
+
+/** Same key-string format as the PTT key ("Ctrl+Shift+T"); null while only modifiers are held. */
+function keyStringFromEvent(e: KeyboardEvent): string | null {
+  if (['Control', 'Shift', 'Alt', 'Meta'].includes(e.key)) return null
+  const parts: string[] = []
+  if (e.ctrlKey) parts.push('Ctrl')
+  if (e.shiftKey) parts.push('Shift')
+  if (e.altKey) parts.push('Alt')
+  if (e.metaKey) parts.push('Meta')
+  parts.push(e.key.length === 1 ? e.key.toUpperCase() : e.key)
+  return parts.join('+')
+}
+
+function displayKey(binding: string): string {
+  return binding.split('+').map((part) => (part === ' ' ? 'Space' : part)).join('+')
+}
+
+function sameKeybind(a: Keybind, b: Keybind): boolean {
+  return a.action === b.action && a.binding === b.binding && (a.server_id ?? null) === (b.server_id ?? null)
+}
+
+export function KeybindSettings() {
+  const [keybinds, setKeybinds] = useState<Keybind[]>([])
+  const [status, setStatus] = useState<KeybindStatus[]>([])
+  const [servers, setServers] = useState<Server[]>([])
+  // Index into `keybinds` being captured, or a new single action not bound yet
+  const [capturing, setCapturing] = useState<number | KeybindAction | null>(null)
+
+  useEffect(() => {
+    let cancelled = false
+    const unlisteners: Array<() => void> = []
+    getSettings().then((s) => { if (!cancelled) setKeybinds(s.keybinds) }).catch(() => {})
+    getKeybindStatus().then((s) => { if (!cancelled) setStatus(s) }).catch(() => {})
+    listServers().then((s) => { if (!cancelled) setServers(s) }).catch(() => {})
+    listenSettings((s) => setKeybinds(s.keybinds)).then((u) => unlisteners.push(u))
+    listenKeybindStatus(setStatus).then((u) => unlisteners.push(u))
+    return () => {
+      cancelled = true
+      unlisteners.forEach((u) => u())
+    }
+  }, [])
+
+  const save = async (next: Keybind[]) => {
+    setKeybinds(next)
+    try {
+      const settings = await getSettings()
+      await setSettings({ ...settings, keybinds: next })
+    } catch (error) {
+      console.error('Failed to save keybinds:', error)
+    }
+  }
+
+  useEffect(() => {
+    if (capturing === null) return
+
+    const handleKeyDown = (e: KeyboardEvent) => {
+      e.preventDefault()
+      if (e.key === 'Escape') {
+        setCapturing(null)
+        return
+      }
+      const binding = keyStringFromEvent(e)
+      if (!binding) return
+      if (typeof capturing === 'number') {
+        save(keybinds.map((k, i) => (i === capturing ? { ...k, binding } : k)))
+      } else if (capturing === 'switch_voice_channel') {
+        // Starts on the first server; the row's picker changes it
+        save([...keybinds, { action: capturing, binding, server_id: servers[0]?.id ?? null }])
+      } else {
+        save([...keybinds, { action: capturing, binding }])
+      }
+      setCapturing(null)
+    }
+    const handleBlur = () => setCapturing(null)
+
+    window.addEventListener('keydown', handleKeyDown)
+    window.addEventListener('blur', handleBlur)
+    return () => {
+      window.removeEventListener('keydown', handleKeyDown)
+      window.removeEventListener('blur', handleBlur)
+    }
+  }, [capturing, keybinds, servers])
+
+  const statusFor = (keybind: Keybind) => status.find((s) => sameKeybind(s, keybind))
+
+  const renderKeyButton = (target: number | KeybindAction, binding: string | undefined) => (
+    <Button type="button" size="sm" variant="outline" className="min-w-[9rem]" onClick={() => setCapturing(target)}>
+      {capturing === target ? 'Press a key…' : binding ? displayKey(binding) : 'Not set'}
+    </Button>
+  )
+
+  const renderStatus = (keybind: Keybind | undefined) => {
+    const s = keybind ? statusFor(keybind) : undefined
+    if (!s) return null
+    if (s.conflict) return <p className="text-xs text-red-500 font-light">{s.conflict}</p>
+    return null
+  }
+
+  const switchBinds = keybinds
+    .map((keybind, index) => ({ keybind, index }))
+    .filter(({ keybind }) => keybind.action === 'switch_voice_channel')
+
+  return (
+    <div className="bg-card/50 backdrop-blur-sm border border-border/50 space-y-4 p-4">
+      <div className="space-y-1">
+        <h2 className="text-lg font-light tracking-tight">Keybinds</h2>
+        <p className="text-xs text-muted-foreground">
+          These work system-wide, even while another app is focused. Push to Talk is set under Audio. Bare
+          letter keys need Ctrl or Alt so they keep typing elsewhere.
+        </p>
+      </div>
+
+      <div className="border border-border/50 rounded-md p-3 space-y-3">
+        {SINGLE_ACTIONS.map(({ action, label, hint }) => {
+          const index = keybinds.findIndex((k) => k.action === action)
+          const keybind = index >= 0 ? keybinds[index] : undefined
+          return (
+            <div key={action} className="space-y-1">
+              <div className="flex items-center justify-between gap-3">
+                <div>
+                  <p className="text-sm font-light">{label}</p>
+                  <p className="text-xs text-muted-foreground">{hint}</p>
+                </div>
+                <div className="flex items-center gap-2">
+                  {renderKeyButton(index >= 0 ? index : action, keybind?.binding)}
+                  {keybind && (
+                    <Button
+                      type="button"
+                      size="sm"
+                      variant="ghost"
+                      title="Clear"
+                      onClick={() => save(keybinds.filter((_, i) => i !== index))}
+                    >
+                      <Trash2 className="h-4 w-4" />
+                    </Button>
+                  )}
+                </div>
+              </div>
+              {renderStatus(keybind)}
+            </div>
+          )
+        })}
+      </div>
+
+      <div className="border border-border/50 rounded-md p-3 space-y-3">
+        <div className="space-y-1">
+          <p className="text-xs font-medium uppercase tracking-wider text-muted-foreground">Switch voice channel</p>
+          <p className="text-xs text-muted-foreground">Jump straight into a server's voice channel.</p>
+        </div>
+        {switchBinds.map(({ keybind, index }) => (
+          <div key={index} className="space-y-1">
+            <div className="flex items-center gap-2">
+              <div className="flex-1 min-w-0">
+                <Select
+                  value={keybind.server_id ?? ''}
+                  onChange={(e) => save(keybinds.map((k, i) => (i === index ? { ...k, server_id: e.target.value } : k)))}
+                >
+                  {servers.map((server) => (
+                    <option key={server.id} value={server.id}>{server.name}</option>
+                  ))}
+                </Select>
+              </div>
+              {renderKeyButton(index, keybind.binding)}
+              <Button
+                type="button"
+                size="sm"
+                variant="ghost"
+                title="Remove"
+                onClick={() => save(keybinds.filter((_, i) => i !== index))}
+              >
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+            {renderStatus(keybind)}
+          </div>
+        ))}
+        <Button
+          type="button"
+          size="sm"
+          variant="outline"
+          disabled={servers.length === 0}
+          onClick={() => setCapturing('switch_voice_channel')}
+        >
+          <Plus className="h-4 w-4 mr-1" />
+          {capturing === 'switch_voice_channel' ? 'Press a key…' : 'Add channel keybind'}
+        </Button>
+      </div>
+    </div>
+  )
+}